@@ -26,7 +26,7 @@
 // IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use crate::OperationCost;
+use crate::{error::Error, OperationCost};
 
 /// Wrapped operation result with associated cost.
 #[must_use]
@@ -46,6 +46,14 @@ impl<T> CostContext<T> {
         self.value
     }
 
+    /// Take wrapped value out adding its cost to provided accumulator with
+    /// `OperationCost::accumulate`, so failing on overflow with the
+    /// `checked_arithmetic` feature.
+    pub fn unwrap_accumulate_cost(self, acc_cost: &mut OperationCost) -> Result<T, Error> {
+        acc_cost.accumulate(self.cost)?;
+        Ok(self.value)
+    }
+
     /// Take wrapped value out dropping cost data.
     pub fn unwrap(self) -> T {
         self.value
@@ -170,8 +178,10 @@ impl<T> CostsExt for T {}
 /// 1. Early termination on error;
 /// 2. Because of 1, `Result` is removed from the equation;
 /// 3. `CostContext` is removed too because it is added to external cost
-/// accumulator; 4. Early termination uses external cost accumulator so previous
-///    costs won't be lost.
+///    accumulator;
+/// 4. Early termination uses external cost accumulator so previous costs won't
+///    be lost.
+#[cfg(not(feature = "checked_arithmetic"))]
 #[macro_export]
 macro_rules! cost_return_on_error {
     ( &mut $cost:ident, $($body:tt)+ ) => {
@@ -187,6 +197,37 @@ macro_rules! cost_return_on_error {
     };
 }
 
+/// Macro to achieve kind of what the `?` operator does, but with `CostContext`
+/// on top.
+///
+/// Main properties are:
+/// 1. Early termination on error;
+/// 2. Because of 1, `Result` is removed from the equation;
+/// 3. `CostContext` is removed too because it is added to external cost
+///    accumulator;
+/// 4. Early termination uses external cost accumulator so previous costs won't
+///    be lost;
+/// 5. An overflow of the external cost accumulator terminates early too, with
+///    the overflow error converted into the caller's error type.
+#[cfg(feature = "checked_arithmetic")]
+#[macro_export]
+macro_rules! cost_return_on_error {
+    ( &mut $cost:ident, $($body:tt)+ ) => {
+        {
+            use $crate::CostsExt;
+            let result_with_cost = { $($body)+ };
+            let result = match result_with_cost.unwrap_accumulate_cost(&mut $cost) {
+                Ok(result) => result,
+                Err(overflow) => return Err(overflow.into()).wrap_with_cost($cost),
+            };
+            match result {
+                Ok(x) => x,
+                Err(e) => return Err(e).wrap_with_cost($cost),
+            }
+        }
+    };
+}
+
 /// Macro to achieve kind of what the `?` operator does, but with `CostContext`
 /// on top. The difference between this macro and `cost_return_on_error` is that
 /// it is intended to be used on `Result` rather than `CostContext<Result<..>>`,
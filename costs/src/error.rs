@@ -40,4 +40,7 @@ pub enum Error {
         /// The actual storage cost in summed bytes
         actual_total_bytes: u32,
     },
+    /// A cost counter overflowed
+    #[error("cost overflow of {0}")]
+    CostOverflow(&'static str),
 }
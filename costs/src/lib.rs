@@ -53,8 +53,8 @@ use crate::{
 /// Child key length
 pub type ChildKeyLength = u32;
 
-/// Feature sum length
-pub type FeatureSumLength = u32;
+/// Feature sum length, as encoded and as paid for
+pub type FeatureSumLength = (u32, u32);
 
 /// Child sum length
 pub type ChildSumLength = u32;
@@ -194,9 +194,9 @@ impl OperationCost {
                     paid_value_len -= right_child_sum_len;
                 }
 
-                if let Some(sum_tree_len) = in_sum_tree {
+                if let Some((sum_tree_len, paid_sum_tree_len)) = in_sum_tree {
                     paid_value_len -= sum_tree_len;
-                    paid_value_len += 8;
+                    paid_value_len += paid_sum_tree_len;
                 }
 
                 // This is the moment we need to add the required space (after removing
@@ -256,25 +256,91 @@ impl OperationCost {
     }
 }
 
+/// Additions of costs saturate at the maximum of each counter, so that
+/// malicious inputs can't wrap a counter around and slip under a limit.
+/// `checked_add` and `accumulate` report overflows instead.
 impl Add for OperationCost {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
         OperationCost {
-            seek_count: self.seek_count + rhs.seek_count,
+            seek_count: self.seek_count.saturating_add(rhs.seek_count),
             storage_cost: self.storage_cost + rhs.storage_cost,
-            storage_loaded_bytes: self.storage_loaded_bytes + rhs.storage_loaded_bytes,
-            hash_node_calls: self.hash_node_calls + rhs.hash_node_calls,
+            storage_loaded_bytes: self
+                .storage_loaded_bytes
+                .saturating_add(rhs.storage_loaded_bytes),
+            hash_node_calls: self.hash_node_calls.saturating_add(rhs.hash_node_calls),
         }
     }
 }
 
 impl AddAssign for OperationCost {
     fn add_assign(&mut self, rhs: Self) {
-        self.seek_count += rhs.seek_count;
+        self.seek_count = self.seek_count.saturating_add(rhs.seek_count);
         self.storage_cost += rhs.storage_cost;
-        self.storage_loaded_bytes += rhs.storage_loaded_bytes;
-        self.hash_node_calls += rhs.hash_node_calls;
+        self.storage_loaded_bytes = self
+            .storage_loaded_bytes
+            .saturating_add(rhs.storage_loaded_bytes);
+        self.hash_node_calls = self.hash_node_calls.saturating_add(rhs.hash_node_calls);
+    }
+}
+
+impl OperationCost {
+    /// Adds two costs, failing if any counter overflows
+    pub fn checked_add(&self, rhs: &Self) -> Result<Self, Error> {
+        let overflow = |counter| move || Error::CostOverflow(counter);
+        let removed_bytes = self
+            .storage_cost
+            .removed_bytes
+            .total_removed_bytes()
+            .checked_add(rhs.storage_cost.removed_bytes.total_removed_bytes());
+        Ok(OperationCost {
+            seek_count: self
+                .seek_count
+                .checked_add(rhs.seek_count)
+                .ok_or_else(overflow("seek_count"))?,
+            storage_cost: StorageCost {
+                added_bytes: self
+                    .storage_cost
+                    .added_bytes
+                    .checked_add(rhs.storage_cost.added_bytes)
+                    .ok_or_else(overflow("added_bytes"))?,
+                replaced_bytes: self
+                    .storage_cost
+                    .replaced_bytes
+                    .checked_add(rhs.storage_cost.replaced_bytes)
+                    .ok_or_else(overflow("replaced_bytes"))?,
+                removed_bytes: removed_bytes
+                    .map(|_| {
+                        self.storage_cost.removed_bytes.clone()
+                            + rhs.storage_cost.removed_bytes.clone()
+                    })
+                    .ok_or_else(overflow("removed_bytes"))?,
+            },
+            storage_loaded_bytes: self
+                .storage_loaded_bytes
+                .checked_add(rhs.storage_loaded_bytes)
+                .ok_or_else(overflow("storage_loaded_bytes"))?,
+            hash_node_calls: self
+                .hash_node_calls
+                .checked_add(rhs.hash_node_calls)
+                .ok_or_else(overflow("hash_node_calls"))?,
+        })
+    }
+
+    /// Adds `rhs` to the cost. With the `checked_arithmetic` feature, as
+    /// required in consensus code, an overflow is an error and leaves the
+    /// cost unchanged; otherwise counters saturate and this never fails.
+    pub fn accumulate(&mut self, rhs: Self) -> Result<(), Error> {
+        #[cfg(feature = "checked_arithmetic")]
+        {
+            *self = self.checked_add(&rhs)?;
+        }
+        #[cfg(not(feature = "checked_arithmetic"))]
+        {
+            *self += rhs;
+        }
+        Ok(())
     }
 }
 
@@ -283,6 +349,74 @@ mod tests {
     use super::*;
     use crate::context::{CostContext, CostResult, CostsExt};
 
+    #[test]
+    fn test_add_saturates() {
+        let cost = OperationCost {
+            seek_count: u16::MAX - 1,
+            storage_loaded_bytes: u32::MAX,
+            ..Default::default()
+        } + OperationCost {
+            seek_count: 2,
+            storage_loaded_bytes: 1,
+            ..Default::default()
+        };
+        assert_eq!(cost.seek_count, u16::MAX);
+        assert_eq!(cost.storage_loaded_bytes, u32::MAX);
+
+        let mut cost = OperationCost::with_storage_written_bytes(u32::MAX);
+        cost += OperationCost::with_storage_written_bytes(1);
+        assert_eq!(cost.storage_cost.added_bytes, u32::MAX);
+    }
+
+    #[test]
+    fn test_checked_add() {
+        assert_eq!(
+            OperationCost::with_seek_count(1)
+                .checked_add(&OperationCost::with_hash_node_calls(2))
+                .expect("expected no overflow"),
+            OperationCost {
+                seek_count: 1,
+                hash_node_calls: 2,
+                ..Default::default()
+            }
+        );
+        assert!(matches!(
+            OperationCost::with_hash_node_calls(u32::MAX)
+                .checked_add(&OperationCost::with_hash_node_calls(1)),
+            Err(Error::CostOverflow("hash_node_calls"))
+        ));
+        assert!(matches!(
+            OperationCost::with_storage_freed_bytes(u32::MAX)
+                .checked_add(&OperationCost::with_storage_freed_bytes(1)),
+            Err(Error::CostOverflow("removed_bytes"))
+        ));
+    }
+
+    #[test]
+    fn test_cost_return_on_error_accumulates() {
+        fn add_hash_node_calls(
+            mut cost: OperationCost,
+            hash_node_calls: u32,
+        ) -> CostResult<(), Error> {
+            cost_return_on_error!(
+                &mut cost,
+                Ok::<_, Error>(())
+                    .wrap_with_cost(OperationCost::with_hash_node_calls(hash_node_calls))
+            );
+            Ok(()).wrap_with_cost(cost)
+        }
+
+        let accumulated = add_hash_node_calls(OperationCost::with_hash_node_calls(u32::MAX), 1);
+        assert_eq!(accumulated.cost.hash_node_calls, u32::MAX);
+        #[cfg(feature = "checked_arithmetic")]
+        assert!(matches!(
+            accumulated.value,
+            Err(Error::CostOverflow("hash_node_calls"))
+        ));
+        #[cfg(not(feature = "checked_arithmetic"))]
+        assert!(accumulated.value.is_ok());
+    }
+
     #[test]
     fn test_map() {
         let initial = CostContext {
@@ -49,9 +49,10 @@ pub const UNKNOWN_EPOCH: u64 = u64::MAX;
 pub type StorageRemovalPerEpochByIdentifier = BTreeMap<Identifier, IntMap<u32>>;
 
 /// Removal bytes
-#[derive(Debug, PartialEq, Clone, Eq)]
+#[derive(Debug, PartialEq, Clone, Eq, Default)]
 pub enum StorageRemovedBytes {
     /// No storage removal
+    #[default]
     NoStorageRemoval,
     /// Basic storage removal
     BasicStorageRemoval(u32),
@@ -59,12 +60,6 @@ pub enum StorageRemovedBytes {
     SectionedStorageRemoval(StorageRemovalPerEpochByIdentifier),
 }
 
-impl Default for StorageRemovedBytes {
-    fn default() -> Self {
-        NoStorageRemoval
-    }
-}
-
 impl Add for StorageRemovedBytes {
     type Output = Self;
 
@@ -77,7 +72,7 @@ impl Add for StorageRemovedBytes {
             },
             BasicStorageRemoval(s) => match rhs {
                 NoStorageRemoval => BasicStorageRemoval(s),
-                BasicStorageRemoval(r) => BasicStorageRemoval(s + r),
+                BasicStorageRemoval(r) => BasicStorageRemoval(s.saturating_add(r)),
                 SectionedStorageRemoval(mut map) => {
                     let default = Identifier::default();
                     if let std::collections::btree_map::Entry::Vacant(e) = map.entry(default) {
@@ -87,7 +82,7 @@ impl Add for StorageRemovedBytes {
                     } else {
                         let mut old_section_map = map.remove(&default).unwrap_or_default();
                         if let Some(old_value) = old_section_map.remove(UNKNOWN_EPOCH) {
-                            old_section_map.insert(UNKNOWN_EPOCH, old_value + s);
+                            old_section_map.insert(UNKNOWN_EPOCH, old_value.saturating_add(s));
                         } else {
                             old_section_map.insert(UNKNOWN_EPOCH, s);
                         }
@@ -106,7 +101,7 @@ impl Add for StorageRemovedBytes {
                     } else {
                         let mut old_section_map = smap.remove(&default).unwrap_or_default();
                         if let Some(old_value) = old_section_map.remove(UNKNOWN_EPOCH) {
-                            old_section_map.insert(UNKNOWN_EPOCH, old_value + r);
+                            old_section_map.insert(UNKNOWN_EPOCH, old_value.saturating_add(r));
                         } else {
                             old_section_map.insert(UNKNOWN_EPOCH, r);
                         }
@@ -121,7 +116,7 @@ impl Add for StorageRemovedBytes {
                                 .into_iter()
                                 .map(|(k, v)| {
                                     let combined = if let Some(value_b) = int_map_b.remove(k) {
-                                        v + value_b
+                                        v.saturating_add(value_b)
                                     } else {
                                         v
                                     };
@@ -147,13 +142,13 @@ impl AddAssign for StorageRemovedBytes {
             NoStorageRemoval => *self = rhs,
             BasicStorageRemoval(s) => match rhs {
                 NoStorageRemoval => {}
-                BasicStorageRemoval(r) => *s += r,
+                BasicStorageRemoval(r) => *s = s.saturating_add(r),
                 SectionedStorageRemoval(mut map) => {
                     let default = Identifier::default();
                     if let Some(mut old_int_map) = map.remove(&default) {
                         if old_int_map.contains_key(UNKNOWN_EPOCH) {
                             let old_value = old_int_map.remove(UNKNOWN_EPOCH).unwrap_or_default();
-                            old_int_map.insert(UNKNOWN_EPOCH, old_value + *s);
+                            old_int_map.insert(UNKNOWN_EPOCH, old_value.saturating_add(*s));
                         } else {
                             old_int_map.insert(UNKNOWN_EPOCH, *s);
                         }
@@ -172,7 +167,7 @@ impl AddAssign for StorageRemovedBytes {
                     let map_to_insert = if let Some(mut old_int_map) = smap.remove(&default) {
                         if old_int_map.contains_key(UNKNOWN_EPOCH) {
                             let old_value = old_int_map.remove(UNKNOWN_EPOCH).unwrap_or_default();
-                            old_int_map.insert(UNKNOWN_EPOCH, old_value + r);
+                            old_int_map.insert(UNKNOWN_EPOCH, old_value.saturating_add(r));
                         } else {
                             old_int_map.insert(UNKNOWN_EPOCH, r);
                         }
@@ -192,7 +187,7 @@ impl AddAssign for StorageRemovedBytes {
                                 .into_iter()
                                 .map(|(k, v)| {
                                     let combined = if let Some(value_b) = int_map_b.remove(k) {
-                                        v + value_b
+                                        v.saturating_add(value_b)
                                     } else {
                                         v
                                     };
@@ -230,15 +225,15 @@ impl StorageRemovedBytes {
         }
     }
 
-    /// The total number of removed bytes
+    /// The total number of removed bytes, saturating at `u32::MAX`
     pub fn total_removed_bytes(&self) -> u32 {
         match self {
             NoStorageRemoval => 0,
             BasicStorageRemoval(r) => *r,
             SectionedStorageRemoval(m) => m
-                .iter()
-                .map(|(_, int_map)| int_map.iter().map(|(_, r)| *r).sum::<u32>())
-                .sum(),
+                .values()
+                .flat_map(|int_map| int_map.values().copied())
+                .fold(0, u32::saturating_add),
         }
     }
 }
@@ -86,7 +86,7 @@ pub fn insertion_benchmark_with_transaction(c: &mut Criterion) {
 
     c.bench_function("scalars insertion with transaction", |b| {
         b.iter(|| {
-            let tx = db.start_transaction();
+            let tx = db.start_transaction().unwrap();
             for k in keys.clone() {
                 db.insert(
                     [test_leaf].as_ref(),
@@ -130,7 +130,7 @@ pub fn root_leaf_insertion_benchmark_with_transaction(c: &mut Criterion) {
 
     c.bench_function("root leaves insertion with transaction", |b| {
         b.iter(|| {
-            let tx = db.start_transaction();
+            let tx = db.start_transaction().unwrap();
             for k in keys.clone() {
                 db.insert(EMPTY_PATH, &k, Element::empty_tree(), None, Some(&tx))
                     .unwrap()
@@ -204,7 +204,7 @@ pub fn deeply_nested_insertion_benchmark_with_transaction(c: &mut Criterion) {
 
     c.bench_function("deeply nested scalars insertion with transaction", |b| {
         b.iter(|| {
-            let tx = db.start_transaction();
+            let tx = db.start_transaction().unwrap();
             for k in keys.clone() {
                 db.insert(
                     nested_subtrees.as_slice(),
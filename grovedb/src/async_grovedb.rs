@@ -0,0 +1,283 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Async GroveDb
+//!
+//! GroveDb operations block on RocksDB, so calling them from an async
+//! executor stalls its threads. [`AsyncGroveDb`] runs them on a pool of
+//! worker threads and exposes them as futures, which don't depend on any
+//! particular executor and so can be awaited from tokio as well as others.
+//! Query results are streamed from a thread of their own.
+
+use std::{
+    any::Any,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::{
+        mpsc::{self, TryRecvError},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+    thread,
+};
+
+use futures_core::Stream;
+use grovedb_costs::{CostResult, OperationCost};
+
+use crate::{
+    batch::{BatchApplyOptions, GroveDbOp},
+    operations::insert::InsertOptions,
+    query_result_type::{QueryResultElement, QueryResultType},
+    Element, Error, GroveDb, PathQuery,
+};
+
+type Job = Box<dyn FnOnce(&GroveDb) + Send>;
+
+/// GroveDb handle whose operations are run on worker threads and awaited.
+///
+/// Operations run without a transaction; transactional work can be run as a
+/// whole on a worker thread with [`AsyncGroveDb::run`].
+pub struct AsyncGroveDb {
+    db: Arc<GroveDb>,
+    jobs: Mutex<mpsc::Sender<Job>>,
+}
+
+impl AsyncGroveDb {
+    /// Wraps `db`, running its operations on `worker_threads` threads, which
+    /// stop once the handle is dropped
+    pub fn new(db: Arc<GroveDb>, worker_threads: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..worker_threads.max(1) {
+            let db = db.clone();
+            let receiver = receiver.clone();
+            thread::spawn(move || loop {
+                // The lock is released before running the job so that other
+                // workers can take the next ones
+                let job = receiver.lock().expect("jobs lock").recv();
+                match job {
+                    Ok(job) => job(&db),
+                    Err(_) => break,
+                }
+            });
+        }
+        AsyncGroveDb {
+            db,
+            jobs: Mutex::new(sender),
+        }
+    }
+
+    /// Wrapped GroveDb, to be used for blocking calls
+    pub fn db(&self) -> &Arc<GroveDb> {
+        &self.db
+    }
+
+    /// Runs `f` on a worker thread, resolving to its result. A panic of `f`
+    /// is resumed when the result is awaited.
+    pub fn run<T, F>(&self, f: F) -> BlockingTask<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&GroveDb) -> T + Send + 'static,
+    {
+        let state = Arc::new(Mutex::new(TaskState {
+            result: None,
+            waker: None,
+        }));
+        let task_state = state.clone();
+        let job: Job = Box::new(move |db| {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| f(db)));
+            let mut state = task_state.lock().expect("task lock");
+            state.result = Some(result);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+        self.jobs
+            .lock()
+            .expect("jobs lock")
+            .send(job)
+            .expect("worker threads outlive the handle");
+        BlockingTask { state }
+    }
+
+    /// Get an element, see [`GroveDb::get`]
+    pub async fn get(&self, path: Vec<Vec<u8>>, key: Vec<u8>) -> CostResult<Element, Error> {
+        self.run(move |db| db.get(path.as_slice(), &key, None))
+            .await
+    }
+
+    /// Insert an element, see [`GroveDb::insert`]
+    pub async fn insert(
+        &self,
+        path: Vec<Vec<u8>>,
+        key: Vec<u8>,
+        element: Element,
+        options: Option<InsertOptions>,
+    ) -> CostResult<(), Error> {
+        self.run(move |db| db.insert(path.as_slice(), &key, element, options, None))
+            .await
+    }
+
+    /// Apply a batch of operations, see [`GroveDb::apply_batch`]
+    pub async fn apply_batch(
+        &self,
+        ops: Vec<GroveDbOp>,
+        batch_apply_options: Option<BatchApplyOptions>,
+    ) -> CostResult<(), Error> {
+        self.run(move |db| db.apply_batch(ops, batch_apply_options, None))
+            .await
+    }
+
+    /// Prove a path query, see [`GroveDb::prove_query`]
+    pub async fn prove_query(&self, path_query: PathQuery) -> CostResult<Vec<u8>, Error> {
+        self.run(move |db| db.prove_query(&path_query)).await
+    }
+
+    /// Stream the results of a path query, see [`GroveDb::query_stream`].
+    /// The results are read on a thread of their own, which stays at most a
+    /// couple of results ahead of the ones polled and stops once the stream
+    /// is dropped.
+    pub fn query_stream(
+        &self,
+        path_query: PathQuery,
+        result_type: QueryResultType,
+    ) -> AsyncQueryStream {
+        let (sender, results) = mpsc::sync_channel(1);
+        let state = Arc::new(Mutex::new(StreamState {
+            cost: OperationCost::default(),
+            panic: None,
+            waker: None,
+        }));
+        let db = self.db.clone();
+        let stream_state = state.clone();
+        thread::spawn(move || {
+            let wake = || {
+                let waker = stream_state.lock().expect("stream lock").waker.take();
+                if let Some(waker) = waker {
+                    waker.wake();
+                }
+            };
+            let read = panic::catch_unwind(AssertUnwindSafe(|| {
+                let mut stream = db.query_stream(&path_query, result_type, None);
+                while let Some(result) = stream.next() {
+                    stream_state.lock().expect("stream lock").cost = stream.cost().clone();
+                    if sender.send(result).is_err() {
+                        return;
+                    }
+                    wake();
+                }
+                stream_state.lock().expect("stream lock").cost = stream.cost().clone();
+            }));
+            if let Err(panic) = read {
+                stream_state.lock().expect("stream lock").panic = Some(panic);
+            }
+            // The stream ends once the sender is dropped
+            drop(sender);
+            wake();
+        });
+        AsyncQueryStream { results, state }
+    }
+}
+
+struct TaskState<T> {
+    result: Option<thread::Result<T>>,
+    waker: Option<Waker>,
+}
+
+/// Future of the result of a closure run on a worker thread of an
+/// [`AsyncGroveDb`]
+pub struct BlockingTask<T> {
+    state: Arc<Mutex<TaskState<T>>>,
+}
+
+impl<T> Future for BlockingTask<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.state.lock().expect("task lock");
+        match state.result.take() {
+            Some(Ok(value)) => Poll::Ready(value),
+            Some(Err(panic)) => {
+                drop(state);
+                panic::resume_unwind(panic)
+            }
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+struct StreamState {
+    cost: OperationCost,
+    panic: Option<Box<dyn Any + Send>>,
+    waker: Option<Waker>,
+}
+
+/// Stream of the results of a path query read on a thread of an
+/// [`AsyncGroveDb`]. A panic while reading is resumed when the stream is
+/// polled.
+pub struct AsyncQueryStream {
+    results: mpsc::Receiver<Result<QueryResultElement, Error>>,
+    state: Arc<Mutex<StreamState>>,
+}
+
+impl AsyncQueryStream {
+    /// Accumulated cost of the storage operations done so far
+    pub fn cost(&self) -> OperationCost {
+        self.state.lock().expect("stream lock").cost.clone()
+    }
+
+    fn try_next(&self) -> Poll<Option<Result<QueryResultElement, Error>>> {
+        match self.results.try_recv() {
+            Ok(result) => Poll::Ready(Some(result)),
+            Err(TryRecvError::Empty) => Poll::Pending,
+            Err(TryRecvError::Disconnected) => {
+                if let Some(panic) = self.state.lock().expect("stream lock").panic.take() {
+                    panic::resume_unwind(panic)
+                }
+                Poll::Ready(None)
+            }
+        }
+    }
+}
+
+impl Stream for AsyncQueryStream {
+    type Item = Result<QueryResultElement, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Poll::Ready(next) = self.try_next() {
+            return Poll::Ready(next);
+        }
+        self.state.lock().expect("stream lock").waker = Some(cx.waker().clone());
+        // A result sent before the waker was registered doesn't wake it
+        self.try_next()
+    }
+}
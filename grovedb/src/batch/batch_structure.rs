@@ -148,17 +148,28 @@ where
             ops_by_qualified_paths.insert(path.to_path_consume(), op.op.clone());
             let op_cost = OperationCost::default();
             let op_result = match &op.op {
-                Op::Insert { element } | Op::Replace { element } | Op::Patch { element, .. } => {
+                Op::Insert { element }
+                | Op::Replace { element }
+                | Op::Patch { element, .. }
+                | Op::InsertIfNotExists { element }
+                | Op::ReplaceExpecting { element, .. } => {
                     if let Element::Tree(..) = element {
                         cost_return_on_error!(&mut cost, merk_tree_cache.insert(&op, false));
-                    } else if let Element::SumTree(..) = element {
+                    } else if let Element::SumTree(..)
+                    | Element::CountTree(..)
+                    | Element::BigSumTree(..)
+                    | Element::AggregateTree(..) = element
+                    {
                         cost_return_on_error!(&mut cost, merk_tree_cache.insert(&op, true));
                     }
                     Ok(())
                 }
-                Op::RefreshReference { .. } | Op::Delete | Op::DeleteTree | Op::DeleteSumTree => {
-                    Ok(())
-                }
+                Op::RefreshReference { .. }
+                | Op::UpdateFlags { .. }
+                | Op::Delete
+                | Op::DeleteTree
+                | Op::DeleteSumTree
+                | Op::DeleteIfValueMatches { .. } => Ok(()),
                 Op::ReplaceTreeRootKey { .. } | Op::InsertTreeWithRootHash { .. } => {
                     Err(Error::InvalidBatchOperation(
                         "replace and insert tree hash are internal operations only",
@@ -0,0 +1,218 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Fluent builder for batches of operations
+
+use crate::{batch::GroveDbOp, reference_path::ReferencePathType, Element, Error};
+
+/// Merk stores key lengths in a single byte
+const MAX_KEY_LENGTH: usize = u8::MAX as usize;
+
+/// Builds a list of batch operations, validating each one as it is added.
+///
+/// The first validation error is kept and returned by `build`, so a chain of
+/// calls never has to be interrupted to check for errors.
+#[derive(Debug, Default)]
+pub struct BatchBuilder {
+    ops: Vec<GroveDbOp>,
+    error: Option<Error>,
+}
+
+/// Kind of an operation carrying an element
+#[derive(Debug, Clone, Copy)]
+enum ElementOpKind {
+    Insert,
+    Replace,
+}
+
+/// Operation carrying an element, waiting for its key
+#[derive(Debug)]
+pub struct ElementOpBuilder {
+    builder: BatchBuilder,
+    kind: ElementOpKind,
+    path: Vec<Vec<u8>>,
+}
+
+/// Operation carrying an element, waiting for its element
+#[derive(Debug)]
+pub struct ElementOpKeyBuilder {
+    builder: BatchBuilder,
+    kind: ElementOpKind,
+    path: Vec<Vec<u8>>,
+    key: Vec<u8>,
+}
+
+/// Delete operation, waiting for its key
+#[derive(Debug)]
+pub struct DeleteOpBuilder {
+    builder: BatchBuilder,
+    path: Vec<Vec<u8>>,
+}
+
+impl BatchBuilder {
+    /// Creates an empty builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts an insert operation into the subtree at `path`
+    pub fn insert(self, path: Vec<Vec<u8>>) -> ElementOpBuilder {
+        ElementOpBuilder {
+            builder: self,
+            kind: ElementOpKind::Insert,
+            path,
+        }
+    }
+
+    /// Starts a replace operation in the subtree at `path`
+    pub fn replace(self, path: Vec<Vec<u8>>) -> ElementOpBuilder {
+        ElementOpBuilder {
+            builder: self,
+            kind: ElementOpKind::Replace,
+            path,
+        }
+    }
+
+    /// Starts a delete operation in the subtree at `path`
+    pub fn delete(self, path: Vec<Vec<u8>>) -> DeleteOpBuilder {
+        DeleteOpBuilder {
+            builder: self,
+            path,
+        }
+    }
+
+    /// Number of operations added so far
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Whether no operations were added yet
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Returns the operations, or the first error met while adding them.
+    /// The operations as a whole must also pass the batch consistency check.
+    pub fn build(self) -> Result<Vec<GroveDbOp>, Error> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        if !GroveDbOp::verify_consistency_of_operations(&self.ops).is_empty() {
+            return Err(Error::InvalidBatchOperation(
+                "batch operations are inconsistent",
+            ));
+        }
+        Ok(self.ops)
+    }
+
+    /// Adds an operation unless it fails validation or an earlier one did
+    fn push(
+        mut self,
+        path: Vec<Vec<u8>>,
+        key: Vec<u8>,
+        op: impl FnOnce(Vec<Vec<u8>>, Vec<u8>) -> GroveDbOp,
+    ) -> Self {
+        if self.error.is_none() {
+            match Self::validate(&path, &key) {
+                Ok(()) => self.ops.push(op(path, key)),
+                Err(e) => self.error = Some(e),
+            }
+        }
+        self
+    }
+
+    fn validate(path: &[Vec<u8>], key: &[u8]) -> Result<(), Error> {
+        if path.iter().any(|segment| segment.is_empty()) {
+            return Err(Error::InvalidInput("path contains an empty segment"));
+        }
+        if key.len() > MAX_KEY_LENGTH {
+            return Err(Error::InvalidInput("key is longer than 255 bytes"));
+        }
+        Ok(())
+    }
+
+    fn fail(mut self, error: Error) -> Self {
+        if self.error.is_none() {
+            self.error = Some(error);
+        }
+        self
+    }
+}
+
+impl ElementOpBuilder {
+    /// Sets the key of the operation
+    pub fn key(self, key: Vec<u8>) -> ElementOpKeyBuilder {
+        ElementOpKeyBuilder {
+            builder: self.builder,
+            kind: self.kind,
+            path: self.path,
+            key,
+        }
+    }
+}
+
+impl ElementOpKeyBuilder {
+    /// Finishes the operation with `element`
+    pub fn element(self, element: Element) -> BatchBuilder {
+        let kind = self.kind;
+        self.builder
+            .push(self.path, self.key, move |path, key| match kind {
+                ElementOpKind::Insert => GroveDbOp::insert_op(path, key, element),
+                ElementOpKind::Replace => GroveDbOp::replace_op(path, key, element),
+            })
+    }
+
+    /// Finishes the operation with a reference to the element at the absolute
+    /// `reference_path`, whose last segment is the referenced key
+    pub fn ref_to(self, reference_path: Vec<Vec<u8>>) -> BatchBuilder {
+        let validation = match reference_path.split_last() {
+            None => Err(Error::InvalidInput("reference path is empty")),
+            Some((key, path)) => BatchBuilder::validate(path, key),
+        };
+        if let Err(e) = validation {
+            return self.builder.fail(e);
+        }
+        self.element(Element::new_reference(
+            ReferencePathType::AbsolutePathReference(reference_path),
+        ))
+    }
+}
+
+impl DeleteOpBuilder {
+    /// Finishes the operation by deleting the item at `key`
+    pub fn key(self, key: Vec<u8>) -> BatchBuilder {
+        self.builder.push(self.path, key, GroveDbOp::delete_op)
+    }
+
+    /// Finishes the operation by deleting the tree at `key`
+    pub fn tree_key(self, key: Vec<u8>, is_sum_tree: bool) -> BatchBuilder {
+        self.builder.push(self.path, key, |path, key| {
+            GroveDbOp::delete_tree_op(path, key, is_sum_tree)
+        })
+    }
+}
@@ -0,0 +1,227 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Resolution of conditional batch operations
+//!
+//! Conditions are first checked against the current state to turn the
+//! conditional operations of a batch into the operations they stand for, or
+//! to drop them. The conditions of the operations kept are checked again
+//! against the merk they are applied to while the batch is executed, so a
+//! change made between the two checks fails the batch instead of being
+//! overwritten.
+
+use std::collections::BTreeMap;
+
+use grovedb_costs::{cost_return_on_error, CostResult, CostsExt, OperationCost};
+use grovedb_merk::Merk;
+use grovedb_path::SubtreePath;
+use grovedb_storage::StorageContext;
+
+use crate::{
+    batch::{GroveDbOp, Op},
+    element::ElementGetExt,
+    util::merk_optional_tx,
+    Element, Error, GroveDb, Hash, TransactionArg,
+};
+
+/// Condition an element must meet for a resolved conditional operation to be
+/// applied
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Condition {
+    /// No element is stored under the key
+    Absent,
+    /// The element stored under the key has this value hash
+    ValueHash(Hash),
+    /// This element is stored under the key
+    Element(Element),
+}
+
+/// Conditions of the resolved conditional operations of a batch, by
+/// qualified path of the element they apply to
+pub(crate) type ConditionsByQualifiedPath = BTreeMap<Vec<Vec<u8>>, Condition>;
+
+impl Condition {
+    /// Checks the condition against the element stored under `key` in
+    /// `merk`, failing if it doesn't hold anymore
+    pub(crate) fn check<'db, S: StorageContext<'db>>(
+        &self,
+        merk: &Merk<S>,
+        key: &[u8],
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        let holds = match self {
+            Condition::Absent => !cost_return_on_error!(
+                &mut cost,
+                merk.exists_by_traversing_tree(key)
+                    .map_err(Error::MerkError)
+            ),
+            Condition::ValueHash(expected_value_hash) => {
+                cost_return_on_error!(&mut cost, Element::get_value_hash(merk, key, true))
+                    == Some(*expected_value_hash)
+            }
+            Condition::Element(expected) => {
+                cost_return_on_error!(&mut cost, Element::get_optional(merk, key, true)).as_ref()
+                    == Some(expected)
+            }
+        };
+        if holds {
+            Ok(()).wrap_with_cost(cost)
+        } else {
+            Err(Error::BatchOperationConditionFailed(format!(
+                "condition of the operation on {} changed while applying the batch",
+                hex::encode(key)
+            )))
+            .wrap_with_cost(cost)
+        }
+    }
+}
+
+impl GroveDb {
+    /// Turns the conditional operations of a batch into the operations they
+    /// stand for, checking their conditions against the current state. Inserts
+    /// under existing keys and deletions of elements other than the expected
+    /// ones are dropped, while a replace of an element without the expected
+    /// value hash fails the whole batch. Also returns the conditions of the
+    /// operations kept, to be checked again when the batch is executed.
+    pub(crate) fn resolve_conditional_ops(
+        &self,
+        ops: Vec<GroveDbOp>,
+        transaction: TransactionArg,
+    ) -> CostResult<(Vec<GroveDbOp>, ConditionsByQualifiedPath), Error> {
+        let mut cost = OperationCost::default();
+
+        let mut conditions = ConditionsByQualifiedPath::new();
+        if !ops.iter().any(|op| {
+            matches!(
+                op.op,
+                Op::InsertIfNotExists { .. }
+                    | Op::ReplaceExpecting { .. }
+                    | Op::DeleteIfValueMatches { .. }
+            )
+        }) {
+            return Ok((ops, conditions)).wrap_with_cost(cost);
+        }
+
+        let mut resolved = Vec::with_capacity(ops.len());
+        for GroveDbOp { path, key, op } in ops {
+            let subtree_path = path.to_path();
+            let subtree_path_ref: SubtreePath<_> = subtree_path.as_slice().into();
+            let (op, condition) = match op {
+                Op::InsertIfNotExists { element } => {
+                    if cost_return_on_error!(
+                        &mut cost,
+                        self.has_raw(subtree_path_ref, key.as_slice(), transaction)
+                    ) {
+                        continue;
+                    }
+                    (Op::Insert { element }, Some(Condition::Absent))
+                }
+                Op::ReplaceExpecting {
+                    element,
+                    expected_value_hash,
+                } => {
+                    let value_hash = cost_return_on_error!(
+                        &mut cost,
+                        self.stored_value_hash(subtree_path_ref, key.as_slice(), transaction)
+                    );
+                    if value_hash != Some(expected_value_hash) {
+                        return Err(Error::BatchOperationConditionFailed(format!(
+                            "element {} to replace doesn't have the expected value hash",
+                            hex::encode(key.as_slice())
+                        )))
+                        .wrap_with_cost(cost);
+                    }
+                    (
+                        Op::Replace { element },
+                        Some(Condition::ValueHash(expected_value_hash)),
+                    )
+                }
+                Op::DeleteIfValueMatches { expected } => {
+                    let stored = cost_return_on_error!(
+                        &mut cost,
+                        self.get_raw_optional(
+                            subtree_path_ref.clone(),
+                            key.as_slice(),
+                            transaction
+                        )
+                    );
+                    if stored.as_ref() != Some(&expected) {
+                        continue;
+                    }
+                    let op = if expected.is_tree() {
+                        let tree_path = subtree_path_ref.derive_owned_with_child(key.as_slice());
+                        if !cost_return_on_error!(
+                            &mut cost,
+                            self.is_empty_tree(&tree_path, transaction)
+                        ) {
+                            return Err(Error::DeletingNonEmptyTree(
+                                "conditional deletion of a non empty tree",
+                            ))
+                            .wrap_with_cost(cost);
+                        }
+                        if expected.uses_sum_nodes() {
+                            Op::DeleteSumTree
+                        } else {
+                            Op::DeleteTree
+                        }
+                    } else {
+                        Op::Delete
+                    };
+                    (op, Some(Condition::Element(expected)))
+                }
+                op => (op, None),
+            };
+            if let Some(condition) = condition {
+                let mut qualified_path = subtree_path;
+                qualified_path.push(key.get_key_clone());
+                conditions.insert(qualified_path, condition);
+            }
+            resolved.push(GroveDbOp { path, key, op });
+        }
+        Ok((resolved, conditions)).wrap_with_cost(cost)
+    }
+
+    /// Value hash of the element stored under `key` in the subtree at `path`
+    fn stored_value_hash<B: AsRef<[u8]>>(
+        &self,
+        path: SubtreePath<B>,
+        key: &[u8],
+        transaction: TransactionArg,
+    ) -> CostResult<Option<Hash>, Error> {
+        let mut cost = OperationCost::default();
+
+        cost_return_on_error!(
+            &mut cost,
+            self.check_subtree_exists_path_not_found(path.clone(), transaction)
+        );
+        merk_optional_tx!(&mut cost, self.db, path, None, transaction, subtree, {
+            Element::get_value_hash(&subtree, key, true).add_cost(cost)
+        })
+    }
+}
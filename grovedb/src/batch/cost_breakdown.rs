@@ -0,0 +1,305 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Attribution of the cost of a batch to its operations
+
+use grovedb_costs::{
+    cost_return_on_error_default,
+    storage_cost::{
+        removal::{
+            StorageRemovalPerEpochByIdentifier,
+            StorageRemovedBytes::{
+                self, BasicStorageRemoval, NoStorageRemoval, SectionedStorageRemoval,
+            },
+        },
+        StorageCost,
+    },
+    CostContext, CostResult, CostsExt, OperationCost,
+};
+use intmap::IntMap;
+
+use crate::{
+    batch::{BatchApplyOptions, GroveDbOp},
+    ElementFlags, Error, GroveDb, TransactionArg,
+};
+
+impl GroveDb {
+    /// Applies batch of operations on GroveDB like `apply_batch`, also
+    /// returning the cost attributed to each operation, in the order they
+    /// were given. Element flags go through the flags codec as in
+    /// `apply_batch`.
+    pub fn apply_batch_with_cost_breakdown(
+        &self,
+        ops: Vec<GroveDbOp>,
+        batch_apply_options: Option<BatchApplyOptions>,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<OperationCost>, Error> {
+        let ops = cost_return_on_error_default!(self.encode_ops_flags(ops));
+        let epoch = batch_apply_options
+            .as_ref()
+            .map_or(0, |batch_options| batch_options.epoch);
+        self.apply_batch_with_element_flags_update_and_cost_breakdown(
+            ops,
+            batch_apply_options,
+            |cost, old_flags, new_flags| self.flags_codec.merge(epoch, cost, old_flags, new_flags),
+            |flags, removed_key_bytes, removed_value_bytes| {
+                self.flags_codec
+                    .decode_removed_bytes(flags, removed_key_bytes, removed_value_bytes)
+            },
+            transaction,
+        )
+    }
+
+    /// Applies batch of operations on GroveDB like
+    /// `apply_batch_with_element_flags_update`, also returning the cost
+    /// attributed to each operation, in the order they were given.
+    ///
+    /// Each operation pays for the storage of its own key. The cost of
+    /// executing a subtree, including the propagation of the root hashes of
+    /// its changed subtrees, and the storage of the other keys of the
+    /// subtree, is shared evenly by the operations on it and below it. The
+    /// rest of the cost of the batch is shared evenly by all operations, so
+    /// the attributed costs add up to the cost of the batch.
+    pub fn apply_batch_with_element_flags_update_and_cost_breakdown(
+        &self,
+        ops: Vec<GroveDbOp>,
+        batch_apply_options: Option<BatchApplyOptions>,
+        update_element_flags_function: impl FnMut(
+            &StorageCost,
+            Option<ElementFlags>,
+            &mut ElementFlags,
+        ) -> Result<bool, Error>,
+        split_removal_bytes_function: impl FnMut(
+            &mut ElementFlags,
+            u32, // key removed bytes
+            u32, // value removed bytes
+        ) -> Result<
+            (StorageRemovedBytes, StorageRemovedBytes),
+            Error,
+        >,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<OperationCost>, Error> {
+        let op_paths: Vec<Vec<Vec<u8>>> = ops.iter().map(|op| op.path.to_path()).collect();
+        let op_keys: Vec<Vec<u8>> = ops
+            .iter()
+            .zip(op_paths.iter())
+            .map(|(op, path)| {
+                let mut prefixed_key = self.db.subtree_prefix(path.as_slice().into()).unwrap();
+                prefixed_key.extend_from_slice(op.key.as_slice());
+                prefixed_key
+            })
+            .collect();
+        let mut path_costs = Vec::new();
+        let mut key_costs = Vec::new();
+
+        let CostContext { value, cost } = self.apply_batch_recording_path_costs(
+            ops,
+            batch_apply_options,
+            update_element_flags_function,
+            split_removal_bytes_function,
+            Some(&mut path_costs),
+            Some(&mut key_costs),
+            transaction,
+        );
+        let breakdown = value.map(|_| {
+            let path_prefixes: Vec<Vec<u8>> = path_costs
+                .iter()
+                .map(|(path, _)| self.db.subtree_prefix(path.as_slice().into()).unwrap())
+                .collect();
+            let ops = OpTargets {
+                paths: &op_paths,
+                keys: &op_keys,
+            };
+            attribute_costs(&ops, &path_costs, &path_prefixes, &key_costs, &cost)
+        });
+        breakdown.wrap_with_cost(cost)
+    }
+}
+
+/// Paths and prefixed keys of the operations of a batch
+struct OpTargets<'a> {
+    paths: &'a [Vec<Vec<u8>>],
+    keys: &'a [Vec<u8>],
+}
+
+impl OpTargets<'_> {
+    /// Indices of the operations on the subtree at `path` or below it, or of
+    /// all operations if there are none
+    fn triggered_by(&self, path: &[Vec<u8>]) -> Vec<usize> {
+        let triggering_ops: Vec<usize> = (0..self.paths.len())
+            .filter(|i| self.paths[*i].starts_with(path))
+            .collect();
+        if triggering_ops.is_empty() {
+            (0..self.paths.len()).collect()
+        } else {
+            triggering_ops
+        }
+    }
+}
+
+/// Attributes the storage costs of keys to the operations on them, the costs
+/// of executing subtrees and of storing their other keys to the operations on
+/// the subtrees, and the remainder of `total` to all of them
+fn attribute_costs(
+    ops: &OpTargets,
+    path_costs: &[(Vec<Vec<u8>>, OperationCost)],
+    path_prefixes: &[Vec<u8>],
+    key_costs: &[(Vec<u8>, OperationCost)],
+    total: &OperationCost,
+) -> Vec<OperationCost> {
+    let mut breakdown = vec![OperationCost::default(); ops.paths.len()];
+    let mut attributed = OperationCost::default();
+
+    for (path, cost) in path_costs {
+        share_cost(cost, &ops.triggered_by(path), &mut breakdown);
+        attributed += cost.clone();
+    }
+
+    for (key, cost) in key_costs {
+        if let Some(op) = ops.keys.iter().position(|op_key| op_key == key) {
+            breakdown[op] += cost.clone();
+        } else {
+            // The key belongs to the subtree with the longest prefix it starts with
+            let subtree = path_costs
+                .iter()
+                .zip(path_prefixes)
+                .filter(|(_, prefix)| key.starts_with(prefix))
+                .max_by_key(|(_, prefix)| prefix.len())
+                .map(|((path, _), _)| path.as_slice());
+            share_cost(
+                cost,
+                &ops.triggered_by(subtree.unwrap_or_default()),
+                &mut breakdown,
+            );
+        }
+        attributed += cost.clone();
+    }
+
+    let remainder = OperationCost {
+        seek_count: total.seek_count.saturating_sub(attributed.seek_count),
+        storage_cost: StorageCost {
+            added_bytes: total
+                .storage_cost
+                .added_bytes
+                .saturating_sub(attributed.storage_cost.added_bytes),
+            replaced_bytes: total
+                .storage_cost
+                .replaced_bytes
+                .saturating_sub(attributed.storage_cost.replaced_bytes),
+            removed_bytes: subtract_removed_bytes(
+                &total.storage_cost.removed_bytes,
+                &attributed.storage_cost.removed_bytes,
+            ),
+        },
+        storage_loaded_bytes: total
+            .storage_loaded_bytes
+            .saturating_sub(attributed.storage_loaded_bytes),
+        hash_node_calls: total
+            .hash_node_calls
+            .saturating_sub(attributed.hash_node_calls),
+    };
+    let all_ops: Vec<usize> = (0..ops.paths.len()).collect();
+    share_cost(&remainder, &all_ops, &mut breakdown);
+
+    breakdown
+}
+
+/// Removed bytes of `total` that are not in `attributed`
+fn subtract_removed_bytes(
+    total: &StorageRemovedBytes,
+    attributed: &StorageRemovedBytes,
+) -> StorageRemovedBytes {
+    match (total, attributed) {
+        (_, NoStorageRemoval) => total.clone(),
+        (SectionedStorageRemoval(total), SectionedStorageRemoval(attributed)) => {
+            map_sections(total, |identifier, epoch, removed| {
+                let attributed = attributed
+                    .get(identifier)
+                    .and_then(|epochs| epochs.get(epoch))
+                    .copied()
+                    .unwrap_or_default();
+                removed.saturating_sub(attributed)
+            })
+        }
+        _ => BasicStorageRemoval(
+            total
+                .total_removed_bytes()
+                .saturating_sub(attributed.total_removed_bytes()),
+        ),
+    }
+}
+
+/// Sectioned removal of the bytes `f` takes from each identifier and epoch of
+/// `removed`, leaving out the empty sections
+fn map_sections(
+    removed: &StorageRemovalPerEpochByIdentifier,
+    mut f: impl FnMut(&[u8; 32], u64, u32) -> u32,
+) -> StorageRemovedBytes {
+    let sections: StorageRemovalPerEpochByIdentifier = removed
+        .iter()
+        .filter_map(|(identifier, epochs)| {
+            let epochs: IntMap<u32> = epochs
+                .iter()
+                .map(|(epoch, removed)| (*epoch, f(identifier, *epoch, *removed)))
+                .filter(|(_, removed)| *removed > 0)
+                .collect();
+            (!epochs.is_empty()).then_some((*identifier, epochs))
+        })
+        .collect();
+    if sections.is_empty() {
+        NoStorageRemoval
+    } else {
+        SectionedStorageRemoval(sections)
+    }
+}
+
+/// Shares `cost` evenly between the operations at the given indices, the
+/// first ones getting the remainder of the divisions
+fn share_cost(cost: &OperationCost, ops: &[usize], breakdown: &mut [OperationCost]) {
+    let count = ops.len() as u32;
+    for (position, op) in ops.iter().enumerate() {
+        let share = |total: u32| total / count + u32::from((position as u32) < total % count);
+        let removed_bytes = match &cost.storage_cost.removed_bytes {
+            NoStorageRemoval => NoStorageRemoval,
+            BasicStorageRemoval(removed) => BasicStorageRemoval(share(*removed)),
+            SectionedStorageRemoval(removed) => {
+                map_sections(removed, |_, _, removed| share(removed))
+            }
+        };
+        breakdown[*op] += OperationCost {
+            seek_count: share(cost.seek_count as u32) as u16,
+            storage_cost: StorageCost {
+                added_bytes: share(cost.storage_cost.added_bytes),
+                replaced_bytes: share(cost.storage_cost.replaced_bytes),
+                removed_bytes,
+            },
+            storage_loaded_bytes: share(cost.storage_loaded_bytes),
+            hash_node_calls: share(cost.hash_node_calls),
+        };
+    }
+}
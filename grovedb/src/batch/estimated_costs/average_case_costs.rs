@@ -85,15 +85,20 @@ impl Op {
                 sum.is_some(),
                 propagate,
             ),
-            Op::InsertTreeWithRootHash { flags, sum, .. } => {
-                GroveDb::average_case_merk_insert_tree(
-                    key,
-                    flags,
-                    sum.is_some(),
-                    in_tree_using_sums,
-                    propagate_if_input(),
-                )
-            }
+            Op::InsertTreeWithRootHash {
+                flags,
+                sum,
+                count,
+                big_sum,
+                aggregate,
+                ..
+            } => GroveDb::average_case_merk_insert_tree(
+                key,
+                flags,
+                sum.is_some() || count.is_some() || big_sum.is_some() || aggregate.is_some(),
+                in_tree_using_sums,
+                propagate_if_input(),
+            ),
             Op::Insert { element } => GroveDb::average_case_merk_insert_element(
                 key,
                 element,
@@ -115,11 +120,11 @@ impl Op {
                 in_tree_using_sums,
                 propagate_if_input(),
             ),
-            Op::Replace { element } => GroveDb::average_case_merk_replace_element(
+            Op::Replace { element } => GroveDb::average_case_merk_replace_existing_element(
                 key,
                 element,
-                in_tree_using_sums,
-                propagate_if_input(),
+                layer_element_estimates,
+                propagate,
             ),
             Op::Patch {
                 element,
@@ -131,6 +136,11 @@ impl Op {
                 in_tree_using_sums,
                 propagate_if_input(),
             ),
+            Op::UpdateFlags { .. } => GroveDb::average_case_merk_update_element_flags(
+                key,
+                layer_element_estimates,
+                propagate,
+            ),
             Op::Delete => {
                 GroveDb::average_case_merk_delete_element(key, layer_element_estimates, propagate)
             }
@@ -146,6 +156,30 @@ impl Op {
                 layer_element_estimates,
                 propagate,
             ),
+            // Conditional operations are estimated as the operation they turn into
+            Op::InsertIfNotExists { element } => GroveDb::average_case_merk_insert_element(
+                key,
+                element,
+                in_tree_using_sums,
+                propagate_if_input(),
+            ),
+            Op::ReplaceExpecting { element, .. } => GroveDb::average_case_merk_replace_element(
+                key,
+                element,
+                in_tree_using_sums,
+                propagate_if_input(),
+            ),
+            Op::DeleteIfValueMatches { expected } if expected.is_tree() => {
+                GroveDb::average_case_merk_delete_tree(
+                    key,
+                    expected.uses_sum_nodes(),
+                    layer_element_estimates,
+                    propagate,
+                )
+            }
+            Op::DeleteIfValueMatches { .. } => {
+                GroveDb::average_case_merk_delete_element(key, layer_element_estimates, propagate)
+            }
         }
     }
 }
@@ -319,7 +353,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_tree_insert_op_average_case_costs() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let ops = vec![GroveDbOp::insert_op(
             vec![],
@@ -386,7 +420,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_tree_with_flags_insert_op_average_case_costs() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let ops = vec![GroveDbOp::insert_op(
             vec![],
@@ -451,7 +485,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_item_insert_op_average_case_costs() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let ops = vec![GroveDbOp::insert_op(
             vec![],
@@ -511,7 +545,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_tree_insert_op_under_element_average_case_costs() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"0", Element::empty_tree(), None, Some(&tx))
             .unwrap()
@@ -588,7 +622,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_tree_insert_op_in_sub_tree_average_case_costs() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"0", Element::empty_tree(), None, Some(&tx))
             .unwrap()
@@ -717,16 +751,18 @@ mod tests {
         // case cost if it doesn't already exist
         assert_eq!(average_case_cost.storage_cost.added_bytes, 0);
 
+        // the replaced key is first looked up to check that it exists, which
+        // costs a seek and loading the 238 bytes of its node
         assert_eq!(
             average_case_cost,
             OperationCost {
-                seek_count: 41,
+                seek_count: 42,
                 storage_cost: StorageCost {
                     added_bytes: 0,
                     replaced_bytes: 5625,
                     removed_bytes: NoStorageRemoval,
                 },
-                storage_loaded_bytes: 7669,
+                storage_loaded_bytes: 7907,
                 hash_node_calls: 79,
             }
         );
@@ -735,7 +771,7 @@ mod tests {
     #[test]
     fn test_batch_average_case_costs() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"keyb", Element::empty_tree(), None, Some(&tx))
             .unwrap()
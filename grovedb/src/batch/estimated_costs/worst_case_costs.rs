@@ -55,6 +55,9 @@ use crate::{
         key_info::KeyInfo, mode::BatchRunMode, BatchApplyOptions, GroveDbOp, KeyInfoPath, Op,
         TreeCache,
     },
+    element::MaxReferenceHop,
+    operations::get::MAX_REFERENCE_HOPS,
+    reference_path::{path_from_reference_qualified_path_type, ReferencePathType},
     Error, GroveDb,
 };
 
@@ -82,10 +85,17 @@ impl Op {
                 worst_case_layer_element_estimates,
                 propagate,
             ),
-            Op::InsertTreeWithRootHash { flags, sum, .. } => GroveDb::worst_case_merk_insert_tree(
+            Op::InsertTreeWithRootHash {
+                flags,
+                sum,
+                count,
+                big_sum,
+                aggregate,
+                ..
+            } => GroveDb::worst_case_merk_insert_tree(
                 key,
                 flags,
-                sum.is_some(),
+                sum.is_some() || count.is_some() || big_sum.is_some() || aggregate.is_some(),
                 is_in_parent_sum_tree,
                 propagate_if_input(),
             ),
@@ -110,7 +120,7 @@ impl Op {
                 is_in_parent_sum_tree,
                 propagate_if_input(),
             ),
-            Op::Replace { element } => GroveDb::worst_case_merk_replace_element(
+            Op::Replace { element } => GroveDb::worst_case_merk_replace_existing_element(
                 key,
                 element,
                 is_in_parent_sum_tree,
@@ -125,6 +135,12 @@ impl Op {
                 is_in_parent_sum_tree,
                 propagate_if_input(),
             ),
+            Op::UpdateFlags { flags } => GroveDb::worst_case_merk_update_element_flags(
+                key,
+                flags,
+                is_in_parent_sum_tree,
+                propagate_if_input(),
+            ),
             Op::Delete => GroveDb::worst_case_merk_delete_element(
                 key,
                 worst_case_layer_element_estimates,
@@ -142,10 +158,104 @@ impl Op {
                 worst_case_layer_element_estimates,
                 propagate,
             ),
+            // Conditional operations are estimated as the operation they turn into
+            Op::InsertIfNotExists { element } => GroveDb::worst_case_merk_insert_element(
+                key,
+                element,
+                is_in_parent_sum_tree,
+                propagate_if_input(),
+            ),
+            Op::ReplaceExpecting { element, .. } => GroveDb::worst_case_merk_replace_element(
+                key,
+                element,
+                is_in_parent_sum_tree,
+                propagate_if_input(),
+            ),
+            Op::DeleteIfValueMatches { expected } if expected.is_tree() => {
+                GroveDb::worst_case_merk_delete_tree(
+                    key,
+                    expected.uses_sum_nodes(),
+                    worst_case_layer_element_estimates,
+                    propagate,
+                )
+            }
+            Op::DeleteIfValueMatches { .. } => GroveDb::worst_case_merk_delete_element(
+                key,
+                worst_case_layer_element_estimates,
+                propagate,
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "full")]
+impl Op {
+    /// Path type and max hops of the reference the operation writes, which is
+    /// resolved to hash its value
+    fn written_reference(&self) -> Option<(&ReferencePathType, MaxReferenceHop)> {
+        match self {
+            Op::Insert { element }
+            | Op::Replace { element }
+            | Op::Patch { element, .. }
+            | Op::InsertIfNotExists { element }
+            | Op::ReplaceExpecting { element, .. } => match element {
+                Element::Reference(reference_path_type, max_reference_hop, _)
+                | Element::BidirectionalReference(reference_path_type, max_reference_hop, _) => {
+                    Some((reference_path_type, *max_reference_hop))
+                }
+                _ => None,
+            },
+            Op::RefreshReference {
+                reference_path_type,
+                max_reference_hop,
+                ..
+            } => Some((reference_path_type, *max_reference_hop)),
+            _ => None,
         }
     }
 }
 
+#[cfg(feature = "full")]
+/// Adds the worst case cost of resolving a reference written at `key` of the
+/// subtree at `path`: every hop allowed opens the subtree of the element it
+/// points to and gets it. The paths of the hops after the first one are
+/// unknown and are estimated like the first one, and the sizes of the
+/// referenced elements are unknown and are not charged.
+fn add_worst_case_reference_resolution(
+    cost: &mut OperationCost,
+    path: &KeyInfoPath,
+    key: &KeyInfo,
+    reference_path_type: &ReferencePathType,
+    max_reference_hop: MaxReferenceHop,
+) -> Result<(), Error> {
+    let mut qualified_path = path.to_path();
+    qualified_path.push(key.get_key_clone());
+    let mut referenced_path =
+        path_from_reference_qualified_path_type(reference_path_type.clone(), &qualified_path)?;
+    let referenced_key = referenced_path
+        .pop()
+        .ok_or(Error::CorruptedPath("reference path can not be empty"))?;
+    let referenced_merk_path = KeyInfoPath::from_known_owned_path(referenced_path);
+    let referenced_key = KeyInfo::KnownKey(referenced_key);
+
+    let hops = max_reference_hop.unwrap_or(MAX_REFERENCE_HOPS as u8);
+    for _ in 0..hops {
+        GroveDb::add_worst_case_get_merk_at_path::<RocksDbStorage>(
+            cost,
+            &referenced_merk_path,
+            false,
+        );
+        GroveDb::add_worst_case_get_raw_cost::<RocksDbStorage>(
+            cost,
+            &referenced_merk_path,
+            &referenced_key,
+            0,
+            false,
+        );
+    }
+    Ok(())
+}
+
 #[cfg(feature = "full")]
 /// Cache for subtree paths for worst case scenario costs.
 #[derive(Default)]
@@ -224,6 +334,18 @@ impl<G, SR> TreeCache<G, SR> for WorstCaseTreeCacheKnownPaths {
                 &mut cost,
                 op.worst_case_cost(&key, false, worst_case_layer_element_estimates, false)
             );
+            if let Some((reference_path_type, max_reference_hop)) = op.written_reference() {
+                cost_return_on_error_no_add!(
+                    &cost,
+                    add_worst_case_reference_resolution(
+                        &mut cost,
+                        path,
+                        &key,
+                        reference_path_type,
+                        max_reference_hop,
+                    )
+                );
+            }
         }
 
         cost_return_on_error!(
@@ -267,6 +389,7 @@ mod tests {
             estimated_costs::EstimatedCostsType::WorstCaseCostsType, key_info::KeyInfo, GroveDbOp,
             KeyInfoPath,
         },
+        reference_path::ReferencePathType,
         tests::{common::EMPTY_PATH, make_empty_grovedb},
         Element, GroveDb,
     };
@@ -274,7 +397,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_tree_insert_op_worst_case_costs() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let ops = vec![GroveDbOp::insert_op(
             vec![],
@@ -327,7 +450,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_tree_with_flags_insert_op_worst_case_costs() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let ops = vec![GroveDbOp::insert_op(
             vec![],
@@ -380,7 +503,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_item_insert_op_worst_case_costs() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let ops = vec![GroveDbOp::insert_op(
             vec![],
@@ -430,10 +553,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_batch_root_one_item_update_flags_op_worst_case_costs() {
+        let db = make_empty_grovedb();
+        let tx = db.start_transaction().unwrap();
+
+        db.insert(
+            EMPTY_PATH,
+            b"key1",
+            Element::new_item(b"cat".to_vec()),
+            None,
+            Some(&tx),
+        )
+        .unwrap()
+        .expect("successful root item insert");
+
+        let ops = vec![GroveDbOp::update_flags_op(
+            vec![],
+            b"key1".to_vec(),
+            Some(b"dog".to_vec()),
+        )];
+        let mut paths = HashMap::new();
+        paths.insert(KeyInfoPath(vec![]), MaxElementsNumber(1));
+        let worst_case_cost = GroveDb::estimated_case_operations_for_batch(
+            WorstCaseCostsType(paths),
+            ops.clone(),
+            None,
+            |_cost, _old_flags, _new_flags| Ok(false),
+            |_flags, _removed_key_bytes, _removed_value_bytes| {
+                Ok((NoStorageRemoval, NoStorageRemoval))
+            },
+        )
+        .cost_as_result()
+        .expect("expected to get worst case costs");
+
+        let cost = db.apply_batch(ops, None, Some(&tx)).cost;
+        // the element read to rewrite it with its new flags is accounted for
+        assert!(worst_case_cost.seek_count >= cost.seek_count);
+        assert!(worst_case_cost.storage_loaded_bytes >= cost.storage_loaded_bytes);
+    }
+
     #[test]
     fn test_batch_root_one_tree_insert_op_under_element_worst_case_costs() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"0", Element::empty_tree(), None, Some(&tx))
             .unwrap()
@@ -490,7 +653,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_tree_insert_op_in_sub_tree_worst_case_costs() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"0", Element::empty_tree(), None, Some(&tx))
             .unwrap()
@@ -542,10 +705,82 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_batch_cousin_with_key_reference_insert_op_worst_case_costs() {
+        let db = make_empty_grovedb();
+        let tx = db.start_transaction().unwrap();
+
+        db.insert(EMPTY_PATH, b"0", Element::empty_tree(), None, Some(&tx))
+            .unwrap()
+            .expect("successful root tree leaf insert");
+        for key in [b"docs".as_ref(), b"index"] {
+            db.insert([b"0"].as_ref(), key, Element::empty_tree(), None, Some(&tx))
+                .unwrap()
+                .expect("successful subtree insert");
+        }
+        db.insert(
+            [b"0".as_ref(), b"docs"].as_ref(),
+            b"doc1",
+            Element::new_item(b"cat".to_vec()),
+            None,
+            Some(&tx),
+        )
+        .unwrap()
+        .expect("successful item insert");
+
+        let ops = vec![GroveDbOp::insert_op(
+            vec![b"0".to_vec(), b"index".to_vec()],
+            b"doc1".to_vec(),
+            Element::new_reference(ReferencePathType::CousinWithKeyReference(
+                1,
+                b"docs".to_vec(),
+                b"doc1".to_vec(),
+            )),
+        )];
+        let mut paths = HashMap::new();
+        paths.insert(KeyInfoPath(vec![]), MaxElementsNumber(1));
+        paths.insert(
+            KeyInfoPath(vec![KeyInfo::KnownKey(b"0".to_vec())]),
+            MaxElementsNumber(2),
+        );
+        paths.insert(
+            KeyInfoPath(vec![
+                KeyInfo::KnownKey(b"0".to_vec()),
+                KeyInfo::KnownKey(b"index".to_vec()),
+            ]),
+            MaxElementsNumber(0),
+        );
+        let worst_case_cost = GroveDb::estimated_case_operations_for_batch(
+            WorstCaseCostsType(paths),
+            ops.clone(),
+            None,
+            |_cost, _old_flags, _new_flags| Ok(false),
+            |_flags, _removed_key_bytes, _removed_value_bytes| {
+                Ok((NoStorageRemoval, NoStorageRemoval))
+            },
+        )
+        .cost_as_result()
+        .expect("expected to get worst case costs");
+
+        let cost = db.apply_batch(ops, None, Some(&tx)).cost;
+        assert!(
+            worst_case_cost.worse_or_eq_than(&cost),
+            "not worse {:?} \n than {:?}",
+            worst_case_cost,
+            cost
+        );
+        // the size of the reference is known, so is the worst case cost of
+        // inserting it
+        assert_eq!(
+            cost.storage_cost.added_bytes,
+            worst_case_cost.storage_cost.added_bytes
+        );
+    }
+
     #[test]
     fn test_batch_worst_case_costs() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"keyb", Element::empty_tree(), None, Some(&tx))
             .unwrap()
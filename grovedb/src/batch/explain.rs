@@ -0,0 +1,228 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Explanation of how a batch is going to be applied
+
+use grovedb_costs::OperationCost;
+
+use crate::{
+    batch::{key_info::KeyInfo, BatchApplyOptions, GroveDbOp, Op},
+    metrics::Metrics,
+    Element, Error, GroveDb,
+};
+
+/// The shape an operation takes once the batch is consolidated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsolidatedOpType {
+    /// The operation is applied as given
+    Unchanged,
+    /// The tree insertion is merged with the root hash propagated from
+    /// operations on the subtree it creates
+    InsertTreeWithRootHash,
+    /// The operation is dropped because a later operation on the same path
+    /// and key replaces it
+    Superseded {
+        /// Index of the superseding operation
+        by: usize,
+    },
+}
+
+/// Explanation of a single batch operation
+#[derive(Debug, Clone)]
+pub struct ExplainedOp {
+    /// Operation as given
+    pub op: GroveDbOp,
+    /// Storage prefix of the subtree the operation is applied to
+    pub subtree_prefix: Vec<u8>,
+    /// Operation type after consolidation
+    pub consolidated_op: ConsolidatedOpType,
+    /// Position at which the operation is executed within the batch, `None`
+    /// if it is superseded. Deeper subtrees are executed first, then paths and
+    /// keys in ascending order.
+    pub execution_order: Option<usize>,
+    /// Qualified paths of the trees whose root hash is updated by this
+    /// operation, from the closest one up to the root leaf
+    pub propagation_targets: Vec<Vec<Vec<u8>>>,
+    /// Cost of applying the operation alone, including its propagation, on
+    /// top of the operations of the batch which are not superseded and are on
+    /// shallower paths, or given before it on paths of the same length
+    pub projected_cost: OperationCost,
+}
+
+/// Explanation of a batch
+#[derive(Debug, Clone)]
+pub struct BatchExplanation {
+    /// Explanations of the operations, in the order they were given
+    pub ops: Vec<ExplainedOp>,
+    /// Cost of applying the whole batch
+    pub projected_cost: OperationCost,
+}
+
+fn known_key(key: &KeyInfo) -> Result<Vec<u8>, Error> {
+    match key {
+        KeyInfo::KnownKey(key) => Ok(key.clone()),
+        KeyInfo::MaxKeySize { .. } => Err(Error::InvalidInput(
+            "batch explanation requires operations with known keys",
+        )),
+    }
+}
+
+fn is_tree_insertion(op: &Op) -> bool {
+    match op {
+        Op::Insert { element } | Op::Replace { element } | Op::Patch { element, .. } => matches!(
+            element,
+            Element::Tree(..)
+                | Element::SumTree(..)
+                | Element::CountTree(..)
+                | Element::BigSumTree(..)
+                | Element::AggregateTree(..)
+        ),
+        _ => false,
+    }
+}
+
+impl GroveDb {
+    /// Explains how a batch would be applied without applying it. Costs are
+    /// projected by applying the batch within transactions which are rolled
+    /// back, so they reflect only committed state. These applications are
+    /// not counted in the metrics.
+    pub fn explain_batch(
+        &self,
+        ops: Vec<GroveDbOp>,
+        batch_apply_options: Option<BatchApplyOptions>,
+    ) -> Result<BatchExplanation, Error> {
+        let mut qualified_paths = Vec::with_capacity(ops.len());
+        for op in ops.iter() {
+            if matches!(
+                op.op,
+                Op::ReplaceTreeRootKey { .. } | Op::InsertTreeWithRootHash { .. }
+            ) {
+                return Err(Error::InvalidBatchOperation(
+                    "replace and insert tree hash are internal operations only",
+                ));
+            }
+            let mut path = op
+                .path
+                .iterator()
+                .map(known_key)
+                .collect::<Result<Vec<_>, Error>>()?;
+            path.push(known_key(&op.key)?);
+            qualified_paths.push(path);
+        }
+
+        // Later operations on the same path and key replace earlier ones
+        let superseded_by: Vec<Option<usize>> = (0..ops.len())
+            .map(|i| {
+                (i + 1..ops.len())
+                    .rev()
+                    .find(|&j| qualified_paths[j] == qualified_paths[i])
+            })
+            .collect();
+        let live: Vec<usize> = (0..ops.len())
+            .filter(|&i| superseded_by[i].is_none())
+            .collect();
+
+        let mut execution: Vec<usize> = live.clone();
+        execution.sort_by(|&a, &b| {
+            ops[b]
+                .path
+                .len()
+                .cmp(&ops[a].path.len())
+                .then_with(|| ops[a].path.cmp(&ops[b].path))
+                .then_with(|| ops[a].key.cmp(&ops[b].key))
+        });
+        let mut execution_order = vec![None; ops.len()];
+        for (position, &i) in execution.iter().enumerate() {
+            execution_order[i] = Some(position);
+        }
+
+        // Operations are applied alone parents first, so that later ones find
+        // the subtrees they depend on
+        let mut projected_costs = vec![OperationCost::default(); ops.len()];
+        let mut dependency_order = live.clone();
+        dependency_order.sort_by_key(|&i| ops[i].path.len());
+        let transaction = self.start_transaction()?;
+        for &i in dependency_order.iter() {
+            let cost = Metrics::suspended(|| {
+                self.apply_batch(
+                    vec![ops[i].clone()],
+                    batch_apply_options.clone(),
+                    Some(&transaction),
+                )
+            });
+            projected_costs[i] = cost.cost;
+            cost.value?;
+        }
+        drop(transaction);
+
+        let transaction = self.start_transaction()?;
+        let batch_cost = Metrics::suspended(|| {
+            self.apply_batch(ops.clone(), batch_apply_options, Some(&transaction))
+        });
+        drop(transaction);
+        let projected_cost = batch_cost.cost;
+        batch_cost.value?;
+
+        let explained_ops = ops
+            .into_iter()
+            .enumerate()
+            .map(|(i, op)| {
+                let path = &qualified_paths[i][..qualified_paths[i].len() - 1];
+                let subtree_prefix = self.db.subtree_prefix(path.into()).unwrap();
+                let consolidated_op = if let Some(by) = superseded_by[i] {
+                    ConsolidatedOpType::Superseded { by }
+                } else if is_tree_insertion(&op.op)
+                    && live.iter().any(|&j| {
+                        let subtree_path = &qualified_paths[i];
+                        let path_j = &qualified_paths[j][..qualified_paths[j].len() - 1];
+                        path_j.starts_with(subtree_path)
+                    })
+                {
+                    ConsolidatedOpType::InsertTreeWithRootHash
+                } else {
+                    ConsolidatedOpType::Unchanged
+                };
+                let propagation_targets =
+                    (1..=path.len()).rev().map(|l| path[..l].to_vec()).collect();
+                ExplainedOp {
+                    op,
+                    subtree_prefix,
+                    consolidated_op,
+                    execution_order: execution_order[i],
+                    propagation_targets,
+                    projected_cost: std::mem::take(&mut projected_costs[i]),
+                }
+            })
+            .collect();
+
+        Ok(BatchExplanation {
+            ops: explained_ops,
+            projected_cost,
+        })
+    }
+}
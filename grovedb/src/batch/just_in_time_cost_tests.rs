@@ -43,7 +43,7 @@ mod tests {
     #[test]
     fn test_partial_costs_with_no_new_operations_are_same_as_apply_batch() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"documents", Element::empty_tree(), None, None)
             .cost_as_result()
@@ -134,7 +134,7 @@ mod tests {
     #[test]
     fn test_partial_costs_with_add_balance_operations() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"documents", Element::empty_tree(), None, None)
             .cost_as_result()
@@ -39,12 +39,15 @@ use grovedb_storage::worst_case_costs::WorstKeyLength;
 #[cfg(feature = "full")]
 use grovedb_visualize::{Drawer, Visualize};
 
+#[cfg(feature = "full")]
+use serde::{Deserialize, Serialize};
+
 #[cfg(feature = "full")]
 use crate::batch::key_info::KeyInfo::{KnownKey, MaxKeySize};
 
 /// Key info
 #[cfg(feature = "full")]
-#[derive(Clone, Eq, Debug)]
+#[derive(Clone, Eq, Debug, Serialize, Deserialize)]
 pub enum KeyInfo {
     /// Known key
     KnownKey(Vec<u8>),
@@ -33,6 +33,12 @@ mod batch_structure;
 #[cfg(feature = "estimated_costs")]
 pub mod estimated_costs;
 
+mod builder;
+mod conditional;
+mod cost_breakdown;
+
+mod explain;
+
 pub mod key_info;
 
 mod mode;
@@ -50,6 +56,8 @@ mod single_insert_cost_tests;
 mod single_sum_item_deletion_cost_tests;
 #[cfg(test)]
 mod single_sum_item_insert_cost_tests;
+mod validation;
+mod wire_format;
 
 use core::fmt;
 use std::{
@@ -58,16 +66,20 @@ use std::{
     hash::{Hash, Hasher},
     ops::{Add, AddAssign},
     slice::Iter,
+    sync::Arc,
     vec::IntoIter,
 };
 
+pub use builder::{BatchBuilder, DeleteOpBuilder, ElementOpBuilder, ElementOpKeyBuilder};
+use conditional::ConditionsByQualifiedPath;
 #[cfg(feature = "estimated_costs")]
 use estimated_costs::{
     average_case_costs::AverageCaseTreeCacheKnownPaths,
     worst_case_costs::WorstCaseTreeCacheKnownPaths,
 };
+pub use explain::{BatchExplanation, ConsolidatedOpType, ExplainedOp};
 use grovedb_costs::{
-    cost_return_on_error, cost_return_on_error_no_add,
+    cost_return_on_error, cost_return_on_error_default, cost_return_on_error_no_add,
     storage_cost::{
         removal::{StorageRemovedBytes, StorageRemovedBytes::BasicStorageRemoval},
         StorageCost,
@@ -79,8 +91,8 @@ use grovedb_merk::{
         kv::ValueDefinedCostType::{LayeredValueDefinedCost, SpecializedValueDefinedCost},
         value_hash, NULL_HASH,
     },
-    CryptoHash, Error as MerkError, Merk, MerkType, RootHashKeyAndSum,
-    TreeFeatureType::{BasicMerk, SummedMerk},
+    CryptoHash, Error as MerkError, MemoryBudget, Merk, MerkType, RootHashKeyAndSum,
+    TreeFeatureType::{AggregatedMerk, BasicMerk, BigSummedMerk, CountedMerk, SummedMerk},
 };
 use grovedb_path::SubtreePath;
 use grovedb_storage::{
@@ -92,14 +104,25 @@ use integer_encoding::VarInt;
 use itertools::Itertools;
 use key_info::{KeyInfo, KeyInfo::KnownKey};
 pub use options::BatchApplyOptions;
+use serde::{Deserialize, Serialize};
+pub use validation::InvalidOp;
+pub use wire_format::BATCH_FORMAT_VERSION;
 
 pub use crate::batch::batch_structure::{OpsByLevelPath, OpsByPath};
 #[cfg(feature = "estimated_costs")]
 use crate::batch::estimated_costs::EstimatedCostsType;
 use crate::{
     batch::{batch_structure::BatchStructure, mode::BatchRunMode},
-    element::{MaxReferenceHop, SUM_ITEM_COST_SIZE, SUM_TREE_COST_SIZE, TREE_COST_SIZE},
-    operations::get::MAX_REFERENCE_HOPS,
+    element::{
+        storage_flags::{Epoch, RemovalStrategy, StorageFlags},
+        ElementDeleteExt, ElementGetExt, ElementInsertExt, MaxReferenceHop, MAX_ELEMENT_FLAGS_SIZE,
+        SUM_ITEM_COST_SIZE,
+    },
+    metrics::OperationKind,
+    operations::{
+        get::MAX_REFERENCE_HOPS,
+        insertion_metadata::{deleted_keys, written_keys},
+    },
     reference_path::{
         path_from_reference_path_type, path_from_reference_qualified_path_type, ReferencePathType,
     },
@@ -107,7 +130,10 @@ use crate::{
 };
 
 /// Operations
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+///
+/// Variants are encoded by index in the batch wire format, so new variants
+/// are only ever appended.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub enum Op {
     /// Replace tree root key
     ReplaceTreeRootKey {
@@ -116,7 +142,7 @@ pub enum Op {
         /// Root key
         root_key: Option<Vec<u8>>,
         /// Sum
-        sum: Option<i64>,
+        sum: Option<i128>,
     },
     /// Insert
     Insert {
@@ -145,6 +171,12 @@ pub enum Op {
         flags: Option<ElementFlags>,
         /// Sum
         sum: Option<i64>,
+        /// Count, set when the tree is a count tree
+        count: Option<u64>,
+        /// Big sum, set when the tree is a big sum tree
+        big_sum: Option<i128>,
+        /// Aggregate id and aggregate, set when the tree is an aggregate tree
+        aggregate: Option<(u8, Option<i128>)>,
     },
     /// Refresh the reference with information provided
     /// Providing this information is necessary to be able to calculate
@@ -158,12 +190,37 @@ pub enum Op {
         flags: Option<ElementFlags>,
         trust_refresh_reference: bool,
     },
+    /// Update the flags of the element stored under the key, the rest of the
+    /// element is kept as is
+    UpdateFlags {
+        /// Flags
+        flags: Option<ElementFlags>,
+    },
     /// Delete
     Delete,
     /// Delete tree
     DeleteTree,
     /// Delete sum tree
     DeleteSumTree,
+    /// Insert, skipped when an element is already stored under the key
+    InsertIfNotExists {
+        /// Element
+        element: Element,
+    },
+    /// Replace, failing the batch unless the element stored under the key has
+    /// the expected value hash
+    ReplaceExpecting {
+        /// Element
+        element: Element,
+        /// Value hash the stored element must have
+        expected_value_hash: [u8; 32],
+    },
+    /// Delete, skipped unless the element stored under the key is the
+    /// expected one, flags included
+    DeleteIfValueMatches {
+        /// Element the stored one must be equal to
+        expected: Element,
+    },
 }
 
 impl PartialOrd for Op {
@@ -213,7 +270,7 @@ impl PartialEq<Vec<Vec<u8>>> for KnownKeysPath {
 }
 
 /// Key info path
-#[derive(PartialOrd, Ord, Eq, Clone, Debug, Default)]
+#[derive(PartialOrd, Ord, Eq, Clone, Debug, Default, Serialize, Deserialize)]
 pub struct KeyInfoPath(pub Vec<KeyInfo>);
 
 impl Hash for KeyInfoPath {
@@ -350,7 +407,7 @@ impl KeyInfoPath {
 }
 
 /// Batch operation
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GroveDbOp {
     /// Path to a subtree - subject to an operation
     pub path: KeyInfoPath,
@@ -373,28 +430,44 @@ impl fmt::Debug for GroveDbOp {
             Op::Insert { element } => match element {
                 Element::Item(..) => "Insert Item",
                 Element::Reference(..) => "Insert Ref",
+                Element::BidirectionalReference(..) => "Insert Bidirectional Ref",
                 Element::Tree(..) => "Insert Tree",
                 Element::SumTree(..) => "Insert Sum Tree",
+                Element::CountTree(..) => "Insert Count Tree",
+                Element::BigSumTree(..) => "Insert Big Sum Tree",
+                Element::AggregateTree(..) => "Insert Aggregate Tree",
                 Element::SumItem(..) => "Insert Sum Item",
             },
             Op::Replace { element } => match element {
                 Element::Item(..) => "Replace Item",
                 Element::Reference(..) => "Replace Ref",
+                Element::BidirectionalReference(..) => "Replace Bidirectional Ref",
                 Element::Tree(..) => "Replace Tree",
                 Element::SumTree(..) => "Replace Sum Tree",
+                Element::CountTree(..) => "Replace Count Tree",
+                Element::BigSumTree(..) => "Replace Big Sum Tree",
+                Element::AggregateTree(..) => "Replace Aggregate Tree",
                 Element::SumItem(..) => "Replace Sum Item",
             },
             Op::Patch { element, .. } => match element {
                 Element::Item(..) => "Patch Item",
                 Element::Reference(..) => "Patch Ref",
+                Element::BidirectionalReference(..) => "Patch Bidirectional Ref",
                 Element::Tree(..) => "Patch Tree",
                 Element::SumTree(..) => "Patch Sum Tree",
+                Element::CountTree(..) => "Patch Count Tree",
+                Element::BigSumTree(..) => "Patch Big Sum Tree",
+                Element::AggregateTree(..) => "Patch Aggregate Tree",
                 Element::SumItem(..) => "Patch Sum Item",
             },
             Op::RefreshReference { .. } => "Refresh Reference",
+            Op::UpdateFlags { .. } => "Update Flags",
             Op::Delete => "Delete",
             Op::DeleteTree => "Delete Tree",
             Op::DeleteSumTree => "Delete Sum Tree",
+            Op::InsertIfNotExists { .. } => "Insert If Not Exists",
+            Op::ReplaceExpecting { .. } => "Replace Expecting",
+            Op::DeleteIfValueMatches { .. } => "Delete If Value Matches",
             Op::ReplaceTreeRootKey { .. } => "Replace Tree Hash and Root Key",
             Op::InsertTreeWithRootHash { .. } => "Insert Tree Hash and Root Key",
         };
@@ -503,6 +576,29 @@ impl GroveDbOp {
         }
     }
 
+    /// An update flags op using a known owned path and known key
+    pub fn update_flags_op(path: Vec<Vec<u8>>, key: Vec<u8>, flags: Option<ElementFlags>) -> Self {
+        let path = KeyInfoPath::from_known_owned_path(path);
+        Self {
+            path,
+            key: KnownKey(key),
+            op: Op::UpdateFlags { flags },
+        }
+    }
+
+    /// An update flags op
+    pub fn update_flags_estimated_op(
+        path: KeyInfoPath,
+        key: KeyInfo,
+        flags: Option<ElementFlags>,
+    ) -> Self {
+        Self {
+            path,
+            key,
+            op: Op::UpdateFlags { flags },
+        }
+    }
+
     /// A delete op using a known owned path and known key
     pub fn delete_op(path: Vec<Vec<u8>>, key: Vec<u8>) -> Self {
         let path = KeyInfoPath::from_known_owned_path(path);
@@ -549,6 +645,47 @@ impl GroveDbOp {
         }
     }
 
+    /// An insert op using a known owned path and known key, skipped when an
+    /// element is already stored under the key
+    pub fn insert_if_not_exists_op(path: Vec<Vec<u8>>, key: Vec<u8>, element: Element) -> Self {
+        let path = KeyInfoPath::from_known_owned_path(path);
+        Self {
+            path,
+            key: KnownKey(key),
+            op: Op::InsertIfNotExists { element },
+        }
+    }
+
+    /// A replace op using a known owned path and known key, failing the batch
+    /// unless the element stored under the key has `expected_value_hash`
+    pub fn replace_expecting_op(
+        path: Vec<Vec<u8>>,
+        key: Vec<u8>,
+        element: Element,
+        expected_value_hash: [u8; 32],
+    ) -> Self {
+        let path = KeyInfoPath::from_known_owned_path(path);
+        Self {
+            path,
+            key: KnownKey(key),
+            op: Op::ReplaceExpecting {
+                element,
+                expected_value_hash,
+            },
+        }
+    }
+
+    /// A delete op using a known owned path and known key, skipped unless the
+    /// element stored under the key is `expected`
+    pub fn delete_if_value_matches_op(path: Vec<Vec<u8>>, key: Vec<u8>, expected: Element) -> Self {
+        let path = KeyInfoPath::from_known_owned_path(path);
+        Self {
+            path,
+            key: KnownKey(key),
+            op: Op::DeleteIfValueMatches { expected },
+        }
+    }
+
     /// Verify consistency of operations
     pub fn verify_consistency_of_operations(ops: &Vec<GroveDbOp>) -> GroveDbOpConsistencyResults {
         let ops_len = ops.len();
@@ -670,6 +807,10 @@ impl GroveDbOpConsistencyResults {
 struct TreeCacheMerkByPath<S, F> {
     merks: HashMap<Vec<Vec<u8>>, Merk<S>>,
     get_merk_fn: F,
+    /// Conditions checked again before applying resolved conditional
+    /// operations
+    conditions: ConditionsByQualifiedPath,
+    memory_budget: Option<Arc<MemoryBudget>>,
 }
 
 impl<S, F> fmt::Debug for TreeCacheMerkByPath<S, F> {
@@ -702,6 +843,96 @@ where
     F: FnMut(&[Vec<u8>], bool) -> CostResult<Merk<S>, Error>,
     S: StorageContext<'db>,
 {
+    /// Gets the element stored under `key` in `merk`, the element must exist
+    fn get_element_to_update_flags(merk: &Merk<S>, key: &[u8]) -> CostResult<Element, Error> {
+        let mut cost = OperationCost::default();
+        let value = cost_return_on_error!(&mut cost, merk.get(key, true).map_err(Error::MerkError));
+        let value = cost_return_on_error_no_add!(
+            &cost,
+            value.ok_or_else(|| Error::PathKeyNotFound(format!(
+                "key to update flags of not found: {}",
+                hex::encode(key)
+            )))
+        );
+        Element::deserialize(value.as_slice())
+            .map_err(|_| Error::CorruptedData(String::from("unable to deserialize element")))
+            .wrap_with_cost(cost)
+    }
+
+    /// Turns an update of the flags of the element stored under `key` into
+    /// the operation writing the element back with the new flags. Items are
+    /// patched, references are refreshed with their current reference path and
+    /// trees are reinserted with the root hash of their subtree.
+    fn update_flags_into_op(
+        &mut self,
+        merk: &Merk<S>,
+        path: &[Vec<u8>],
+        key: &[u8],
+        flags: Option<ElementFlags>,
+    ) -> CostResult<Op, Error> {
+        let mut cost = OperationCost::default();
+        let mut element =
+            cost_return_on_error!(&mut cost, Self::get_element_to_update_flags(merk, key));
+        let old_size = element.serialized_size() as i32;
+        *element.get_flags_mut() = flags;
+
+        // trees are reinserted with the root hash of their subtree
+        let (root_key, flags, sum, count, big_sum, aggregate) = match element {
+            Element::Item(..) | Element::SumItem(..) => {
+                let change_in_bytes = element.serialized_size() as i32 - old_size;
+                return Ok(Op::Patch {
+                    element,
+                    change_in_bytes,
+                })
+                .wrap_with_cost(cost);
+            }
+            // refreshing would turn it into a plain reference
+            Element::BidirectionalReference(..) => {
+                return Ok(Op::Replace { element }).wrap_with_cost(cost);
+            }
+            Element::Reference(reference_path_type, max_reference_hop, flags) => {
+                return Ok(Op::RefreshReference {
+                    reference_path_type,
+                    max_reference_hop,
+                    flags,
+                    trust_refresh_reference: true,
+                })
+                .wrap_with_cost(cost);
+            }
+            Element::Tree(root_key, flags) => (root_key, flags, None, None, None, None),
+            Element::SumTree(root_key, sum_value, flags) => {
+                (root_key, flags, Some(sum_value), None, None, None)
+            }
+            Element::CountTree(root_key, count_value, flags) => {
+                (root_key, flags, None, Some(count_value), None, None)
+            }
+            Element::BigSumTree(root_key, sum_value, flags) => {
+                (root_key, flags, None, None, Some(sum_value), None)
+            }
+            Element::AggregateTree(root_key, aggregate_id, aggregate_value, flags) => (
+                root_key,
+                flags,
+                None,
+                None,
+                None,
+                Some((aggregate_id, aggregate_value)),
+            ),
+        };
+        let mut subtree_path = path.to_vec();
+        subtree_path.push(key.to_vec());
+        let subtree = cost_return_on_error!(&mut cost, (self.get_merk_fn)(&subtree_path, false));
+        Ok(Op::InsertTreeWithRootHash {
+            hash: subtree.root_hash().unwrap_add_cost(&mut cost),
+            root_key,
+            flags,
+            sum,
+            count,
+            big_sum,
+            aggregate,
+        })
+        .wrap_with_cost(cost)
+    }
+
     /// Processes a reference, determining whether it can be retrieved from a
     /// batch operation.
     ///
@@ -839,7 +1070,7 @@ where
                     let val_hash = value_hash(&serialized).unwrap_add_cost(&mut cost);
                     Ok(val_hash).wrap_with_cost(cost)
                 }
-                Element::Reference(path, ..) => {
+                Element::Reference(path, ..) | Element::BidirectionalReference(path, ..) => {
                     let path = cost_return_on_error_no_add!(
                         &cost,
                         path_from_reference_qualified_path_type(path, qualified_path)
@@ -850,7 +1081,11 @@ where
                         recursions_allowed - 1,
                     )
                 }
-                Element::Tree(..) | Element::SumTree(..) => Err(Error::InvalidBatchOperation(
+                Element::Tree(..)
+                | Element::SumTree(..)
+                | Element::CountTree(..)
+                | Element::BigSumTree(..)
+                | Element::AggregateTree(..) => Err(Error::InvalidBatchOperation(
                     "references can not point to trees being updated",
                 ))
                 .wrap_with_cost(cost),
@@ -895,7 +1130,8 @@ where
                             let val_hash = value_hash(&serialized).unwrap_add_cost(&mut cost);
                             Ok(val_hash).wrap_with_cost(cost)
                         }
-                        Element::Reference(path, ..) => {
+                        Element::Reference(path, ..)
+                        | Element::BidirectionalReference(path, ..) => {
                             let path = cost_return_on_error_no_add!(
                                 &cost,
                                 path_from_reference_qualified_path_type(
@@ -909,12 +1145,14 @@ where
                                 recursions_allowed - 1,
                             )
                         }
-                        Element::Tree(..) | Element::SumTree(..) => {
-                            Err(Error::InvalidBatchOperation(
-                                "references can not point to trees being updated",
-                            ))
-                            .wrap_with_cost(cost)
-                        }
+                        Element::Tree(..)
+                        | Element::SumTree(..)
+                        | Element::CountTree(..)
+                        | Element::BigSumTree(..)
+                        | Element::AggregateTree(..) => Err(Error::InvalidBatchOperation(
+                            "references can not point to trees being updated",
+                        ))
+                        .wrap_with_cost(cost),
                     }
                 }
                 Op::RefreshReference {
@@ -935,12 +1173,63 @@ where
                         reference_info,
                     )
                 }
+                Op::UpdateFlags { flags } => {
+                    // The element being referenced only has its flags changed, the flags
+                    // of items are part of their value hash
+                    let (key, path) = qualified_path.split_last().unwrap(); // already checked
+                    let merk_wrapped = self
+                        .merks
+                        .remove(path)
+                        .map(|x| Ok(x).wrap_with_cost(Default::default()))
+                        .unwrap_or_else(|| (self.get_merk_fn)(path, false));
+                    let merk = cost_return_on_error!(&mut cost, merk_wrapped);
+                    let mut element = cost_return_on_error!(
+                        &mut cost,
+                        Self::get_element_to_update_flags(&merk, key)
+                    );
+                    *element.get_flags_mut() = flags.clone();
+                    match element {
+                        Element::Item(..) | Element::SumItem(..) => {
+                            let serialized =
+                                cost_return_on_error_no_add!(&cost, element.serialize());
+                            let val_hash = value_hash(&serialized).unwrap_add_cost(&mut cost);
+                            Ok(val_hash).wrap_with_cost(cost)
+                        }
+                        Element::Reference(path, ..)
+                        | Element::BidirectionalReference(path, ..) => {
+                            let path = cost_return_on_error_no_add!(
+                                &cost,
+                                path_from_reference_qualified_path_type(path, qualified_path)
+                            );
+                            self.follow_reference_get_value_hash(
+                                path.as_slice(),
+                                ops_by_qualified_paths,
+                                recursions_allowed - 1,
+                            )
+                            .add_cost(cost)
+                        }
+                        Element::Tree(..)
+                        | Element::SumTree(..)
+                        | Element::CountTree(..)
+                        | Element::BigSumTree(..)
+                        | Element::AggregateTree(..) => Err(Error::InvalidBatchOperation(
+                            "references can not point to trees being updated",
+                        ))
+                        .wrap_with_cost(cost),
+                    }
+                }
                 Op::Delete | Op::DeleteTree | Op::DeleteSumTree => {
                     Err(Error::InvalidBatchOperation(
                         "references can not point to something currently being deleted",
                     ))
                     .wrap_with_cost(cost)
                 }
+                Op::InsertIfNotExists { .. }
+                | Op::ReplaceExpecting { .. }
+                | Op::DeleteIfValueMatches { .. } => Err(Error::CorruptedCodeExecution(
+                    "conditional operations are resolved before execution",
+                ))
+                .wrap_with_cost(cost),
             }
         } else {
             self.process_reference(
@@ -973,6 +1262,22 @@ where
             let mut merk =
                 cost_return_on_error!(&mut cost, (self.get_merk_fn)(&inserted_path, true));
             merk.is_sum_tree = is_sum_tree;
+            merk.is_count_tree = matches!(
+                &op.op,
+                Op::Insert { element } | Op::Replace { element } | Op::Patch { element, .. }
+                    if element.is_count_tree()
+            );
+            merk.is_big_sum_tree = matches!(
+                &op.op,
+                Op::Insert { element } | Op::Replace { element } | Op::Patch { element, .. }
+                    if element.is_big_sum_tree()
+            );
+            merk.aggregate_id = match &op.op {
+                Op::Insert { element } | Op::Replace { element } | Op::Patch { element, .. } => {
+                    element.aggregate_id()
+                }
+                _ => None,
+            };
             e.insert(merk);
         }
 
@@ -1013,18 +1318,82 @@ where
             .map(|x| Ok(x).wrap_with_cost(Default::default()))
             .unwrap_or_else(|| (self.get_merk_fn)(path, false));
         let mut merk = cost_return_on_error!(&mut cost, merk_wrapped);
+        if let Some(memory_budget) = &self.memory_budget {
+            merk.set_memory_budget(memory_budget.clone());
+        }
         let is_sum_tree = merk.is_sum_tree;
 
         let mut batch_operations: Vec<(Vec<u8>, _)> = vec![];
         for (key_info, op) in ops_at_path_by_key.into_iter() {
+            // We have an update flags Op, this means the element on disk is written back
+            // with its new flags using the Op matching its type
+            let op = match op {
+                Op::UpdateFlags { flags } => cost_return_on_error!(
+                    &mut cost,
+                    self.update_flags_into_op(&merk, path, key_info.as_slice(), flags)
+                ),
+                op => op,
+            };
+            if !self.conditions.is_empty() {
+                let qualified_path = [path.as_slice(), &[key_info.get_key_clone()]].concat();
+                if let Some(condition) = self.conditions.get(&qualified_path) {
+                    cost_return_on_error!(&mut cost, condition.check(&merk, key_info.as_slice()));
+                }
+            }
+            if let Op::Replace { .. } = op {
+                if batch_apply_options.validate_insertion_does_not_override_tree {
+                    let maybe_element_bytes = cost_return_on_error!(
+                        &mut cost,
+                        merk.get(key_info.as_slice(), true)
+                            .map_err(Error::MerkError)
+                    );
+                    let Some(element_bytes) = maybe_element_bytes else {
+                        return Err(Error::PathKeyNotFound(format!(
+                            "key to replace not found: {}",
+                            hex::encode(key_info.as_slice())
+                        )))
+                        .wrap_with_cost(cost);
+                    };
+                    let replaced_element = cost_return_on_error_no_add!(
+                        &cost,
+                        Element::deserialize(element_bytes.as_slice()).map_err(|_| {
+                            Error::CorruptedData(String::from("unable to deserialize element"))
+                        })
+                    );
+                    if replaced_element.is_tree() {
+                        return Err(Error::OverrideNotAllowed(
+                            "insertion not allowed to override tree",
+                        ))
+                        .wrap_with_cost(cost);
+                    }
+                } else {
+                    let exists = cost_return_on_error!(
+                        &mut cost,
+                        merk.exists_by_traversing_tree(key_info.as_slice())
+                            .map_err(Error::MerkError)
+                    );
+                    if !exists {
+                        return Err(Error::PathKeyNotFound(format!(
+                            "key to replace not found: {}",
+                            hex::encode(key_info.as_slice())
+                        )))
+                        .wrap_with_cost(cost);
+                    }
+                }
+            }
             match op {
                 Op::Insert { element } | Op::Replace { element } | Op::Patch { element, .. } => {
                     match &element {
-                        Element::Reference(path_reference, element_max_reference_hop, _) => {
+                        Element::Reference(path_reference, element_max_reference_hop, _)
+                        | Element::BidirectionalReference(
+                            path_reference,
+                            element_max_reference_hop,
+                            _,
+                        ) => {
                             let merk_feature_type = cost_return_on_error!(
                                 &mut cost,
                                 element
-                                    .get_feature_type(is_sum_tree)
+                                    .get_feature_type_in_merk(&merk)
                                     .wrap_with_cost(OperationCost::default())
                             );
                             let path_reference = cost_return_on_error!(
@@ -1062,11 +1431,15 @@ where
                                 )
                             );
                         }
-                        Element::Tree(..) | Element::SumTree(..) => {
+                        Element::Tree(..)
+                        | Element::SumTree(..)
+                        | Element::CountTree(..)
+                        | Element::BigSumTree(..)
+                        | Element::AggregateTree(..) => {
                             let merk_feature_type = cost_return_on_error!(
                                 &mut cost,
                                 element
-                                    .get_feature_type(is_sum_tree)
+                                    .get_feature_type_in_merk(&merk)
                                     .wrap_with_cost(OperationCost::default())
                             );
                             cost_return_on_error!(
@@ -1084,7 +1457,7 @@ where
                             let merk_feature_type = cost_return_on_error!(
                                 &mut cost,
                                 element
-                                    .get_feature_type(is_sum_tree)
+                                    .get_feature_type_in_merk(&merk)
                                     .wrap_with_cost(OperationCost::default())
                             );
                             if batch_apply_options.validate_insertion_does_not_override {
@@ -1147,13 +1520,23 @@ where
                         )
                     };
 
-                    let Element::Reference(path_reference, max_reference_hop, _) = &element else {
+                    let (Element::Reference(path_reference, max_reference_hop, _)
+                    | Element::BidirectionalReference(path_reference, max_reference_hop, _)) =
+                        &element
+                    else {
                         return Err(Error::InvalidInput(
                             "trying to refresh a an element that is not a reference",
-                        )).wrap_with_cost(cost)
+                        ))
+                        .wrap_with_cost(cost);
                     };
 
-                    let merk_feature_type = if is_sum_tree {
+                    let merk_feature_type = if let Some(aggregate_id) = merk.aggregate_id {
+                        AggregatedMerk(aggregate_id, None)
+                    } else if merk.is_count_tree {
+                        CountedMerk(1)
+                    } else if merk.is_big_sum_tree {
+                        BigSummedMerk(0)
+                    } else if is_sum_tree {
                         SummedMerk(0)
                     } else {
                         BasicMerk
@@ -1194,6 +1577,21 @@ where
                         )
                     );
                 }
+                Op::UpdateFlags { .. } => {
+                    return Err(Error::CorruptedCodeExecution(
+                        "update flags operations are turned into other operations before \
+                         execution",
+                    ))
+                    .wrap_with_cost(cost);
+                }
+                Op::InsertIfNotExists { .. }
+                | Op::ReplaceExpecting { .. }
+                | Op::DeleteIfValueMatches { .. } => {
+                    return Err(Error::CorruptedCodeExecution(
+                        "conditional operations are resolved before execution",
+                    ))
+                    .wrap_with_cost(cost);
+                }
                 Op::Delete => {
                     cost_return_on_error!(
                         &mut cost,
@@ -1250,15 +1648,44 @@ where
                     root_key,
                     flags,
                     sum,
+                    count,
+                    big_sum,
+                    aggregate,
                 } => {
-                    let element = match sum {
-                        None => Element::new_tree_with_flags(root_key, flags),
-                        Some(sum_value) => Element::new_sum_tree_with_flags_and_sum_value(
-                            root_key, sum_value, flags,
-                        ),
+                    let element = match (sum, count, big_sum, aggregate) {
+                        (_, _, _, Some((aggregate_id, aggregate_value))) => {
+                            Element::new_aggregate_tree_with_flags_and_aggregate_value(
+                                root_key,
+                                aggregate_id,
+                                aggregate_value,
+                                flags,
+                            )
+                        }
+                        (_, _, Some(big_sum_value), None) => {
+                            Element::new_big_sum_tree_with_flags_and_sum_value(
+                                root_key,
+                                big_sum_value,
+                                flags,
+                            )
+                        }
+                        (_, Some(count_value), None, None) => {
+                            Element::new_count_tree_with_flags_and_count_value(
+                                root_key,
+                                count_value,
+                                flags,
+                            )
+                        }
+                        (Some(sum_value), None, None, None) => {
+                            Element::new_sum_tree_with_flags_and_sum_value(
+                                root_key, sum_value, flags,
+                            )
+                        }
+                        (None, None, None, None) => Element::new_tree_with_flags(root_key, flags),
                     };
-                    let merk_feature_type =
-                        cost_return_on_error_no_add!(&cost, element.get_feature_type(is_sum_tree));
+                    let merk_feature_type = cost_return_on_error_no_add!(
+                        &cost,
+                        element.get_feature_type_in_merk(&merk)
+                    );
 
                     cost_return_on_error!(
                         &mut cost,
@@ -1273,6 +1700,7 @@ where
                 }
             }
         }
+        let mut flags_error = None;
         cost_return_on_error!(
             &mut cost,
             merk.apply_unchecked::<_, Vec<u8>, _, _, _>(
@@ -1305,28 +1733,54 @@ where
                                     ),
                                 })?;
                             if changed {
-                                let flags_len = new_flags.len() as u32;
+                                let flags_len = match u32::try_from(new_flags.len()) {
+                                    Ok(flags_len) if new_flags.len() <= MAX_ELEMENT_FLAGS_SIZE => {
+                                        flags_len
+                                    }
+                                    _ => {
+                                        let error = Error::InvalidElementFlags(format!(
+                                            "updated flags are {} bytes, the maximum is {}",
+                                            new_flags.len(),
+                                            MAX_ELEMENT_FLAGS_SIZE
+                                        ));
+                                        let message = error.to_string();
+                                        flags_error = Some(error);
+                                        return Err(MerkError::ClientCorruptionError(message));
+                                    }
+                                };
                                 new_value.clone_from(&new_element.serialize().map_err(|e| {
                                     MerkError::ClientCorruptionError(e.to_string())
                                 })?);
+                                // the updated element must decode back to itself, otherwise
+                                // the costs below would not match what gets stored
+                                if Element::deserialize(new_value.as_slice()).ok().as_ref()
+                                    != Some(&new_element)
+                                {
+                                    let error = Error::InvalidElementFlags(
+                                        "element with updated flags does not round trip"
+                                            .to_string(),
+                                    );
+                                    let message = error.to_string();
+                                    flags_error = Some(error);
+                                    return Err(MerkError::ClientCorruptionError(message));
+                                }
+                                let flags_cost = flags_len + flags_len.required_space() as u32;
                                 // we need to give back the value defined cost in the case that the
                                 // new element is a tree
                                 match new_element {
-                                    Element::Tree(..) | Element::SumTree(..) => {
-                                        let tree_cost_size = if new_element.is_sum_tree() {
-                                            SUM_TREE_COST_SIZE
-                                        } else {
-                                            TREE_COST_SIZE
-                                        };
-                                        let tree_value_cost = tree_cost_size
-                                            + flags_len
-                                            + flags_len.required_space() as u32;
+                                    Element::Tree(..)
+                                    | Element::SumTree(..)
+                                    | Element::CountTree(..)
+                                    | Element::BigSumTree(..)
+                                    | Element::AggregateTree(..) => {
+                                        let tree_value_cost =
+                                            new_element.get_specialized_cost().map_err(|e| {
+                                                MerkError::ClientCorruptionError(e.to_string())
+                                            })? + flags_cost;
                                         Ok((true, Some(LayeredValueDefinedCost(tree_value_cost))))
                                     }
                                     Element::SumItem(..) => {
-                                        let sum_item_value_cost = SUM_ITEM_COST_SIZE
-                                            + flags_len
-                                            + flags_len.required_space() as u32;
+                                        let sum_item_value_cost = SUM_ITEM_COST_SIZE + flags_cost;
                                         Ok((
                                             true,
                                             Some(SpecializedValueDefinedCost(sum_item_value_cost)),
@@ -1356,12 +1810,12 @@ where
                     }
                 },
             )
-            .map_err(|e| Error::CorruptedData(e.to_string()))
+            .map_err(|e| flags_error.unwrap_or_else(|| Error::from_merk_apply_error(e)))
         );
         let r = merk
             .root_hash_key_and_sum()
             .add_cost(cost)
-            .map_err(Error::MerkError);
+            .map_err(Error::from_merk_apply_error);
         // We need to reinsert the merk
         self.merks.insert(path.clone(), merk);
         r
@@ -1379,6 +1833,7 @@ impl GroveDb {
     fn apply_batch_structure<C: TreeCache<F, SR>, F, SR>(
         batch_structure: BatchStructure<C, F, SR>,
         batch_apply_options: Option<BatchApplyOptions>,
+        mut path_costs: Option<&mut Vec<(Vec<Vec<u8>>, OperationCost)>>,
     ) -> CostResult<Option<OpsByLevelPath>, Error>
     where
         F: FnMut(&StorageCost, Option<ElementFlags>, &mut ElementFlags) -> Result<bool, Error>,
@@ -1408,17 +1863,17 @@ impl GroveDb {
                 if current_level == 0 {
                     // execute the ops at this path
                     // ignoring sum as root tree cannot be summed
-                    let (_root_hash, calculated_root_key, _sum) = cost_return_on_error!(
-                        &mut cost,
-                        merk_tree_cache.execute_ops_on_path(
-                            &path,
-                            ops_at_path,
-                            &ops_by_qualified_paths,
-                            &batch_apply_options,
-                            &mut flags_update,
-                            &mut split_removal_bytes,
-                        )
+                    let path_result = merk_tree_cache.execute_ops_on_path(
+                        &path,
+                        ops_at_path,
+                        &ops_by_qualified_paths,
+                        &batch_apply_options,
+                        &mut flags_update,
+                        &mut split_removal_bytes,
                     );
+                    let mut path_cost = path_result.cost.clone();
+                    let (_root_hash, calculated_root_key, _sum) =
+                        cost_return_on_error!(&mut cost, path_result);
                     if batch_apply_options.base_root_storage_is_free {
                         // the base root is free
                         let mut update_root_cost = cost_return_on_error_no_add!(
@@ -1428,25 +1883,31 @@ impl GroveDb {
                                 .cost_as_result()
                         );
                         update_root_cost.storage_cost = StorageCost::default();
+                        path_cost.add_assign(update_root_cost.clone());
                         cost.add_assign(update_root_cost);
                     } else {
-                        cost_return_on_error!(
-                            &mut cost,
-                            merk_tree_cache.update_base_merk_root_key(calculated_root_key)
-                        );
+                        let update_root_result =
+                            merk_tree_cache.update_base_merk_root_key(calculated_root_key);
+                        path_cost.add_assign(update_root_result.cost.clone());
+                        cost_return_on_error!(&mut cost, update_root_result);
+                    }
+                    if let Some(path_costs) = path_costs.as_mut() {
+                        path_costs.push((path.to_path(), path_cost));
                     }
                 } else {
-                    let (root_hash, calculated_root_key, sum_value) = cost_return_on_error!(
-                        &mut cost,
-                        merk_tree_cache.execute_ops_on_path(
-                            &path,
-                            ops_at_path,
-                            &ops_by_qualified_paths,
-                            &batch_apply_options,
-                            &mut flags_update,
-                            &mut split_removal_bytes,
-                        )
+                    let path_result = merk_tree_cache.execute_ops_on_path(
+                        &path,
+                        ops_at_path,
+                        &ops_by_qualified_paths,
+                        &batch_apply_options,
+                        &mut flags_update,
+                        &mut split_removal_bytes,
                     );
+                    if let Some(path_costs) = path_costs.as_mut() {
+                        path_costs.push((path.to_path(), path_result.cost.clone()));
+                    }
+                    let (root_hash, calculated_root_key, sum_value) =
+                        cost_return_on_error!(&mut cost, path_result);
 
                     if current_level > 0 {
                         // We need to propagate up this root hash, this means adding grove_db
@@ -1495,6 +1956,9 @@ impl GroveDb {
                                                                 root_key: calculated_root_key,
                                                                 flags: flags.clone(),
                                                                 sum: None,
+                                                                count: None,
+                                                                big_sum: None,
+                                                                aggregate: None,
                                                             };
                                                     } else if let Element::SumTree(.., flags) =
                                                         element
@@ -1504,7 +1968,62 @@ impl GroveDb {
                                                                 hash: root_hash,
                                                                 root_key: calculated_root_key,
                                                                 flags: flags.clone(),
-                                                                sum: sum_value,
+                                                                sum: sum_value
+                                                                    .map(|sum| sum as i64),
+                                                                count: None,
+                                                                big_sum: None,
+                                                                aggregate: None,
+                                                            };
+                                                    } else if let Element::CountTree(.., flags) =
+                                                        element
+                                                    {
+                                                        *mutable_occupied_entry =
+                                                            Op::InsertTreeWithRootHash {
+                                                                hash: root_hash,
+                                                                root_key: calculated_root_key,
+                                                                flags: flags.clone(),
+                                                                sum: None,
+                                                                count: Some(
+                                                                    sum_value.unwrap_or_default()
+                                                                        as u64,
+                                                                ),
+                                                                big_sum: None,
+                                                                aggregate: None,
+                                                            };
+                                                    } else if let Element::BigSumTree(.., flags) =
+                                                        element
+                                                    {
+                                                        *mutable_occupied_entry =
+                                                            Op::InsertTreeWithRootHash {
+                                                                hash: root_hash,
+                                                                root_key: calculated_root_key,
+                                                                flags: flags.clone(),
+                                                                sum: None,
+                                                                count: None,
+                                                                big_sum: Some(
+                                                                    sum_value.unwrap_or_default(),
+                                                                ),
+                                                                aggregate: None,
+                                                            };
+                                                    } else if let Element::AggregateTree(
+                                                        _,
+                                                        aggregate_id,
+                                                        _,
+                                                        flags,
+                                                    ) = element
+                                                    {
+                                                        *mutable_occupied_entry =
+                                                            Op::InsertTreeWithRootHash {
+                                                                hash: root_hash,
+                                                                root_key: calculated_root_key,
+                                                                flags: flags.clone(),
+                                                                sum: None,
+                                                                count: None,
+                                                                big_sum: None,
+                                                                aggregate: Some((
+                                                                    *aggregate_id,
+                                                                    sum_value,
+                                                                )),
                                                             };
                                                     } else {
                                                         return Err(Error::InvalidBatchOperation(
@@ -1520,6 +2039,13 @@ impl GroveDb {
                                                     ))
                                                     .wrap_with_cost(cost);
                                                 }
+                                                Op::UpdateFlags { .. } => {
+                                                    return Err(Error::InvalidBatchOperation(
+                                                        "modification of tree when its flags \
+                                                         are updated",
+                                                    ))
+                                                    .wrap_with_cost(cost);
+                                                }
                                                 Op::Delete | Op::DeleteTree | Op::DeleteSumTree => {
                                                     if calculated_root_key.is_some() {
                                                         return Err(Error::InvalidBatchOperation(
@@ -1529,6 +2055,15 @@ impl GroveDb {
                                                         .wrap_with_cost(cost);
                                                     }
                                                 }
+                                                Op::InsertIfNotExists { .. }
+                                                | Op::ReplaceExpecting { .. }
+                                                | Op::DeleteIfValueMatches { .. } => {
+                                                    return Err(Error::CorruptedCodeExecution(
+                                                        "conditional operations are resolved \
+                                                         before execution",
+                                                    ))
+                                                    .wrap_with_cost(cost);
+                                                }
                                             }
                                         }
                                     }
@@ -1577,9 +2112,11 @@ impl GroveDb {
     /// Method to propagate updated subtree root hashes up to GroveDB root
     /// If the pause height is set in the batch apply options
     /// Then return the list of leftover operations
+    /// The cost of executing each subtree is pushed to `path_costs` if given
     fn apply_body<'db, S: StorageContext<'db>>(
         &self,
         ops: Vec<GroveDbOp>,
+        conditions: ConditionsByQualifiedPath,
         batch_apply_options: Option<BatchApplyOptions>,
         update_element_flags_function: impl FnMut(
             &StorageCost,
@@ -1595,6 +2132,7 @@ impl GroveDb {
             Error,
         >,
         get_merk_fn: impl FnMut(&[Vec<u8>], bool) -> CostResult<Merk<S>, Error>,
+        path_costs: Option<&mut Vec<(Vec<Vec<u8>>, OperationCost)>>,
     ) -> CostResult<Option<OpsByLevelPath>, Error> {
         let mut cost = OperationCost::default();
         let batch_structure = cost_return_on_error!(
@@ -1606,10 +2144,15 @@ impl GroveDb {
                 TreeCacheMerkByPath {
                     merks: Default::default(),
                     get_merk_fn,
+                    conditions,
+                    memory_budget: batch_apply_options
+                        .as_ref()
+                        .and_then(|options| options.retained_tree_memory_budget)
+                        .map(MemoryBudget::new),
                 }
             )
         );
-        Self::apply_batch_structure(batch_structure, batch_apply_options).add_cost(cost)
+        Self::apply_batch_structure(batch_structure, batch_apply_options, path_costs).add_cost(cost)
     }
 
     /// Method to propagate updated subtree root hashes up to GroveDB root
@@ -1646,10 +2189,15 @@ impl GroveDb {
                 TreeCacheMerkByPath {
                     merks: Default::default(),
                     get_merk_fn,
+                    conditions: Default::default(),
+                    memory_budget: batch_apply_options
+                        .as_ref()
+                        .and_then(|options| options.retained_tree_memory_budget)
+                        .map(MemoryBudget::new),
                 }
             )
         );
-        Self::apply_batch_structure(batch_structure, batch_apply_options).add_cost(cost)
+        Self::apply_batch_structure(batch_structure, batch_apply_options, None).add_cost(cost)
     }
 
     /// Applies operations on GroveDB without batching
@@ -1662,7 +2210,20 @@ impl GroveDb {
         let mut cost = OperationCost::default();
         for op in ops.into_iter() {
             match op.op {
-                Op::Insert { element } | Op::Replace { element } => {
+                Op::Replace { element } => {
+                    let path_slices: Vec<&[u8]> =
+                        op.path.iterator().map(|p| p.as_slice()).collect();
+                    cost_return_on_error!(
+                        &mut cost,
+                        self.replace(
+                            path_slices.as_slice(),
+                            op.key.as_slice(),
+                            element,
+                            transaction,
+                        )
+                    );
+                }
+                Op::Insert { element } => {
                     // TODO: paths in batches is something to think about
                     let path_slices: Vec<&[u8]> =
                         op.path.iterator().map(|p| p.as_slice()).collect();
@@ -1703,65 +2264,301 @@ impl GroveDb {
         batch_apply_options: Option<BatchApplyOptions>,
         transaction: TransactionArg,
     ) -> CostResult<(), Error> {
+        let ops = cost_return_on_error_default!(self.encode_ops_flags(ops));
+        let epoch = batch_apply_options
+            .as_ref()
+            .map_or(0, |batch_options| batch_options.epoch);
         self.apply_batch_with_element_flags_update(
             ops,
             batch_apply_options,
-            |_cost, _old_flags, _new_flags| Ok(false),
-            |_flags, key_bytes_to_remove, value_bytes_to_remove| {
-                Ok((
-                    BasicStorageRemoval(key_bytes_to_remove),
-                    BasicStorageRemoval(value_bytes_to_remove),
-                ))
+            |cost, old_flags, new_flags| self.flags_codec.merge(epoch, cost, old_flags, new_flags),
+            |flags, removed_key_bytes, removed_value_bytes| {
+                self.flags_codec
+                    .decode_removed_bytes(flags, removed_key_bytes, removed_value_bytes)
             },
             transaction,
         )
     }
 
-    /// Applies batch on GroveDB
-    pub fn apply_partial_batch(
+    /// Applies logically separate batches, like the state transitions of a
+    /// block, inside a single transaction. A batch that fails leaves nothing
+    /// behind without affecting the others: the writes of a batch are staged
+    /// and only written to the transaction once the whole batch is applied, so
+    /// no savepoint is needed. Returns the result and cost of every batch, with
+    /// their combined cost.
+    pub fn apply_batches(
         &self,
-        ops: Vec<GroveDbOp>,
+        batches: Vec<Vec<GroveDbOp>>,
         batch_apply_options: Option<BatchApplyOptions>,
-        cost_based_add_on_operations: impl FnMut(
-            &OperationCost,
-            &Option<OpsByLevelPath>,
-        ) -> Result<Vec<GroveDbOp>, Error>,
-        transaction: TransactionArg,
-    ) -> CostResult<(), Error> {
-        self.apply_partial_batch_with_element_flags_update(
-            ops,
-            batch_apply_options,
-            |_cost, _old_flags, _new_flags| Ok(false),
-            |_flags, key_bytes_to_remove, value_bytes_to_remove| {
-                Ok((
-                    BasicStorageRemoval(key_bytes_to_remove),
-                    BasicStorageRemoval(value_bytes_to_remove),
-                ))
-            },
-            cost_based_add_on_operations,
-            transaction,
-        )
+        transaction: &Transaction,
+    ) -> CostResult<Vec<CostResult<(), Error>>, Error> {
+        let mut cost = OperationCost::default();
+        let mut results = Vec::with_capacity(batches.len());
+        for ops in batches {
+            let result = self.apply_batch(ops, batch_apply_options.clone(), Some(transaction));
+            cost += result.cost.clone();
+            results.push(result);
+        }
+        Ok(results).wrap_with_cost(cost)
     }
 
-    /// Opens transactional merk at path with given storage batch context.
-    /// Returns CostResult.
-    pub fn open_batch_transactional_merk_at_path<'db, B: AsRef<[u8]>>(
-        &'db self,
-        storage_batch: &'db StorageBatch,
-        path: SubtreePath<B>,
-        tx: &'db Transaction,
-        new_merk: bool,
-    ) -> CostResult<Merk<PrefixedRocksDbTransactionContext<'db>>, Error> {
+    /// Applies the longest prefix of the operations whose cost, applied as
+    /// one batch, stays within `cost_limit`, every resource of which is a
+    /// limit for the same resource of the cost. The operations left out are
+    /// returned unapplied. The prefix is found by applying candidate
+    /// prefixes and rolling them back, and is then applied once: the cost is
+    /// the one `apply_batch` returns for it, the search not being charged. A
+    /// batch that can't be applied returns its error without applying
+    /// anything. Without a transaction the operations are applied in one that
+    /// is committed before returning.
+    pub fn apply_batch_within_cost_limit(
+        &self,
+        mut ops: Vec<GroveDbOp>,
+        cost_limit: &OperationCost,
+        batch_apply_options: Option<BatchApplyOptions>,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<GroveDbOp>, Error> {
         let mut cost = OperationCost::default();
-        let storage = self
-            .db
-            .get_transactional_storage_context(path.clone(), Some(storage_batch), tx)
-            .unwrap_add_cost(&mut cost);
 
-        if let Some((parent_path, parent_key)) = path.derive_parent() {
-            if new_merk {
-                // TODO: can this be a sum tree
-                Ok(Merk::open_empty(storage, MerkType::LayeredMerk, false)).wrap_with_cost(cost)
+        let Some(transaction) = transaction else {
+            let transaction = cost_return_on_error_no_add!(&cost, self.start_transaction());
+            let remaining = cost_return_on_error!(
+                &mut cost,
+                self.apply_batch_within_cost_limit(
+                    ops,
+                    cost_limit,
+                    batch_apply_options,
+                    Some(&transaction)
+                )
+            );
+            return self
+                .commit_transaction(transaction)
+                .map_ok(|_| remaining)
+                .add_cost(cost);
+        };
+
+        // Candidate prefixes are applied and rolled back like in
+        // `estimate_batch_cost`, but a failed rollback, which leaves the writes
+        // of the prefix in the transaction, is the outer error so that it isn't
+        // taken for a prefix that doesn't fit
+        let fits = |len: usize| -> Result<Result<bool, Error>, Error> {
+            self.set_savepoint(transaction);
+            let applied = self.apply_batch(
+                ops[..len].to_vec(),
+                batch_apply_options.clone(),
+                Some(transaction),
+            );
+            self.rollback_to_savepoint(transaction)?;
+            Ok(applied
+                .value
+                .map(|_| cost_limit.worse_or_eq_than(&applied.cost)))
+        };
+        let applied_len = match fits(ops.len()) {
+            Err(e) | Ok(Err(e)) => return Err(e).wrap_with_cost(cost),
+            Ok(Ok(true)) => ops.len(),
+            Ok(Ok(false)) => {
+                // The empty prefix fits and the whole batch doesn't
+                let (mut fitting, mut exceeding) = (0, ops.len());
+                while exceeding - fitting > 1 {
+                    let middle = fitting + (exceeding - fitting) / 2;
+                    match fits(middle) {
+                        Err(e) => return Err(e).wrap_with_cost(cost),
+                        Ok(Ok(true)) => fitting = middle,
+                        Ok(_) => exceeding = middle,
+                    }
+                }
+                fitting
+            }
+        };
+
+        let remaining = ops.split_off(applied_len);
+        if !ops.is_empty() {
+            cost_return_on_error!(
+                &mut cost,
+                self.apply_batch(ops, batch_apply_options, Some(transaction))
+            );
+        }
+        Ok(remaining).wrap_with_cost(cost)
+    }
+
+    /// Runs the batch through the whole `apply_batch` pipeline against the
+    /// current state, including the writes of `transaction`, and discards its
+    /// writes, leaving the savepoints of `transaction` as they were. The cost
+    /// is the one applying the batch would incur against that state, and a
+    /// batch that can't be applied returns the error applying it would.
+    pub fn estimate_batch_cost(
+        &self,
+        ops: Vec<GroveDbOp>,
+        batch_apply_options: Option<BatchApplyOptions>,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        match transaction {
+            Some(transaction) => {
+                self.set_savepoint(transaction);
+                let applied = self.apply_batch(ops, batch_apply_options, Some(transaction));
+                let cost = applied.cost;
+                cost_return_on_error_no_add!(&cost, self.rollback_to_savepoint(transaction));
+                applied.value.wrap_with_cost(cost)
+            }
+            None => {
+                let transaction = cost_return_on_error_default!(self.start_transaction());
+                let applied = self.apply_batch(ops, batch_apply_options, Some(&transaction));
+                let cost = applied.cost;
+                cost_return_on_error_no_add!(&cost, self.rollback_transaction(&transaction));
+                applied.value.wrap_with_cost(cost)
+            }
+        }
+    }
+
+    /// Applies batch on GroveDB into `storage_batch` without committing it,
+    /// so that writes outside of the trees, like aux data, can be committed
+    /// atomically with it. It is applied with the default batch options: no
+    /// insertion metadata is recorded and flags are merged in epoch 0.
+    pub(crate) fn apply_batch_into_storage_batch(
+        &self,
+        ops: Vec<GroveDbOp>,
+        storage_batch: &StorageBatch,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        if ops.is_empty() {
+            return Ok(()).wrap_with_cost(OperationCost::default());
+        }
+        let ops = cost_return_on_error_default!(self.encode_ops_flags(ops));
+        let BatchApplyOptions {
+            insertion_metadata,
+            epoch,
+            ..
+        } = BatchApplyOptions::default();
+        let mut cost = OperationCost::default();
+        cost_return_on_error!(
+            &mut cost,
+            self.record_insertion_metadata(
+                written_keys(&ops),
+                deleted_keys(&ops),
+                insertion_metadata,
+                storage_batch,
+                transaction
+            )
+        );
+        cost_return_on_error!(
+            &mut cost,
+            self.prune_reverse_key_index(deleted_keys(&ops), storage_batch, transaction)
+        );
+        cost_return_on_error!(
+            &mut cost,
+            self.record_reverse_key_index(written_keys(&ops), storage_batch, transaction)
+        );
+        cost_return_on_error!(
+            &mut cost,
+            self.record_backlinks(&ops, storage_batch, transaction)
+        );
+        cost_return_on_error!(
+            &mut cost,
+            self.record_prefix_registry(&ops, storage_batch, transaction)
+        );
+        #[cfg(feature = "audit_log")]
+        let audit_entries =
+            cost_return_on_error_no_add!(&cost, crate::operations::audit_log::batch_entries(&ops));
+        let applied = if let Some(tx) = transaction {
+            self.apply_body(
+                ops,
+                ConditionsByQualifiedPath::new(),
+                None,
+                |cost, old_flags, new_flags| {
+                    self.flags_codec.merge(epoch, cost, old_flags, new_flags)
+                },
+                |flags, removed_key_bytes, removed_value_bytes| {
+                    self.flags_codec.decode_removed_bytes(
+                        flags,
+                        removed_key_bytes,
+                        removed_value_bytes,
+                    )
+                },
+                |path, new_merk| {
+                    self.open_batch_transactional_merk_at_path(
+                        storage_batch,
+                        path.into(),
+                        tx,
+                        new_merk,
+                    )
+                },
+                None,
+            )
+            .map_ok(|_| ())
+        } else {
+            self.apply_body(
+                ops,
+                ConditionsByQualifiedPath::new(),
+                None,
+                |cost, old_flags, new_flags| {
+                    self.flags_codec.merge(epoch, cost, old_flags, new_flags)
+                },
+                |flags, removed_key_bytes, removed_value_bytes| {
+                    self.flags_codec.decode_removed_bytes(
+                        flags,
+                        removed_key_bytes,
+                        removed_value_bytes,
+                    )
+                },
+                |path, new_merk| self.open_batch_merk_at_path(storage_batch, path.into(), new_merk),
+                None,
+            )
+            .map_ok(|_| ())
+        };
+        #[cfg(feature = "audit_log")]
+        let applied = self.audit_mutation(applied, |_| audit_entries, storage_batch, transaction);
+        applied.add_cost(cost)
+    }
+
+    /// Applies batch on GroveDB
+    pub fn apply_partial_batch(
+        &self,
+        ops: Vec<GroveDbOp>,
+        batch_apply_options: Option<BatchApplyOptions>,
+        cost_based_add_on_operations: impl FnMut(
+            &OperationCost,
+            &Option<OpsByLevelPath>,
+        ) -> Result<Vec<GroveDbOp>, Error>,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let ops = cost_return_on_error_default!(self.encode_ops_flags(ops));
+        let epoch = batch_apply_options
+            .as_ref()
+            .map_or(0, |batch_options| batch_options.epoch);
+        self.apply_partial_batch_with_element_flags_update(
+            ops,
+            batch_apply_options,
+            |cost, old_flags, new_flags| self.flags_codec.merge(epoch, cost, old_flags, new_flags),
+            |flags, removed_key_bytes, removed_value_bytes| {
+                self.flags_codec
+                    .decode_removed_bytes(flags, removed_key_bytes, removed_value_bytes)
+            },
+            cost_based_add_on_operations,
+            transaction,
+        )
+    }
+
+    /// Opens transactional merk at path with given storage batch context.
+    /// Returns CostResult.
+    pub fn open_batch_transactional_merk_at_path<'db, B: AsRef<[u8]>>(
+        &'db self,
+        storage_batch: &'db StorageBatch,
+        path: SubtreePath<B>,
+        tx: &'db Transaction,
+        new_merk: bool,
+    ) -> CostResult<Merk<PrefixedRocksDbTransactionContext<'db>>, Error> {
+        let mut cost = OperationCost::default();
+        let storage = self
+            .db
+            .get_transactional_storage_context(path.clone(), Some(storage_batch), tx)
+            .unwrap_add_cost(&mut cost);
+
+        if let Some((parent_path, parent_key)) = path.derive_parent() {
+            if new_merk {
+                // TODO: can this be a sum tree
+                let mut merk = Merk::open_empty(storage, MerkType::LayeredMerk, false);
+                merk.tree_aggregates = Some(self.tree_aggregates.clone());
+                Ok(merk).wrap_with_cost(cost)
             } else {
                 let parent_storage = self
                     .db
@@ -1776,14 +2573,29 @@ impl GroveDb {
                         ))
                     })
                 );
-                let is_sum_tree = element.is_sum_tree();
-                if let Element::Tree(root_key, _) | Element::SumTree(root_key, ..) = element {
+                let is_sum_tree = element.uses_sum_nodes();
+                let is_count_tree = element.is_count_tree();
+                let is_big_sum_tree = element.is_big_sum_tree();
+                let aggregate_id = element.aggregate_id();
+                if let Element::Tree(root_key, _)
+                | Element::SumTree(root_key, ..)
+                | Element::CountTree(root_key, ..)
+                | Element::BigSumTree(root_key, ..)
+                | Element::AggregateTree(root_key, ..) = element
+                {
                     Merk::open_layered_with_root_key(storage, root_key, is_sum_tree)
                         .map_err(|_| {
                             Error::CorruptedData(
                                 "cannot open a subtree with given root key".to_owned(),
                             )
                         })
+                        .map_ok(|mut merk| {
+                            merk.is_count_tree = is_count_tree;
+                            merk.is_big_sum_tree = is_big_sum_tree;
+                            merk.aggregate_id = aggregate_id;
+                            merk.tree_aggregates = Some(self.tree_aggregates.clone());
+                            merk
+                        })
                         .add_cost(cost)
                 } else {
                     Err(Error::CorruptedPath(
@@ -1822,7 +2634,9 @@ impl GroveDb {
             } else {
                 MerkType::LayeredMerk
             };
-            Ok(Merk::open_empty(storage, merk_type, false)).wrap_with_cost(local_cost)
+            let mut merk = Merk::open_empty(storage, merk_type, false);
+            merk.tree_aggregates = Some(self.tree_aggregates.clone());
+            Ok(merk).wrap_with_cost(local_cost)
         } else if let Some((base_path, last)) = path.derive_parent() {
             let parent_storage = self
                 .db
@@ -1832,12 +2646,27 @@ impl GroveDb {
                 &mut local_cost,
                 Element::get_from_storage(&parent_storage, last)
             );
-            let is_sum_tree = element.is_sum_tree();
-            if let Element::Tree(root_key, _) | Element::SumTree(root_key, ..) = element {
+            let is_sum_tree = element.uses_sum_nodes();
+            let is_count_tree = element.is_count_tree();
+            let is_big_sum_tree = element.is_big_sum_tree();
+            let aggregate_id = element.aggregate_id();
+            if let Element::Tree(root_key, _)
+            | Element::SumTree(root_key, ..)
+            | Element::CountTree(root_key, ..)
+            | Element::BigSumTree(root_key, ..)
+            | Element::AggregateTree(root_key, ..) = element
+            {
                 Merk::open_layered_with_root_key(storage, root_key, is_sum_tree)
                     .map_err(|_| {
                         Error::CorruptedData("cannot open a subtree with given root key".to_owned())
                     })
+                    .map_ok(|mut merk| {
+                        merk.is_count_tree = is_count_tree;
+                        merk.is_big_sum_tree = is_big_sum_tree;
+                        merk.aggregate_id = aggregate_id;
+                        merk.tree_aggregates = Some(self.tree_aggregates.clone());
+                        merk
+                    })
                     .add_cost(local_cost)
             } else {
                 Err(Error::CorruptedData(
@@ -1871,9 +2700,82 @@ impl GroveDb {
             Error,
         >,
         transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        self.apply_batch_recording_path_costs(
+            ops,
+            batch_apply_options,
+            update_element_flags_function,
+            split_removal_bytes_function,
+            None,
+            None,
+            transaction,
+        )
+    }
+
+    /// Applies batch of operations on GroveDB whose elements carry
+    /// `StorageFlags`. Bytes added by updates are recorded as paid for in
+    /// `epoch` and removed bytes are refunded per epoch, taken with
+    /// `removal_strategy`.
+    pub fn apply_batch_with_storage_flags(
+        &self,
+        ops: Vec<GroveDbOp>,
+        batch_apply_options: Option<BatchApplyOptions>,
+        epoch: Epoch,
+        removal_strategy: RemovalStrategy,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        self.apply_batch_with_element_flags_update(
+            ops,
+            batch_apply_options,
+            |cost, old_flags, new_flags| {
+                StorageFlags::update_element_flags(
+                    epoch,
+                    removal_strategy,
+                    cost,
+                    old_flags,
+                    new_flags,
+                )
+            },
+            |flags, removed_key_bytes, removed_value_bytes| {
+                StorageFlags::split_removal_bytes(
+                    removal_strategy,
+                    flags,
+                    removed_key_bytes,
+                    removed_value_bytes,
+                )
+            },
+            transaction,
+        )
+    }
+
+    /// Applies batch of operations on GroveDB, pushing the cost of executing
+    /// each subtree to `path_costs` and the storage cost of each written key
+    /// to `key_costs` if given
+    pub(crate) fn apply_batch_recording_path_costs(
+        &self,
+        ops: Vec<GroveDbOp>,
+        batch_apply_options: Option<BatchApplyOptions>,
+        update_element_flags_function: impl FnMut(
+            &StorageCost,
+            Option<ElementFlags>,
+            &mut ElementFlags,
+        ) -> Result<bool, Error>,
+        split_removal_bytes_function: impl FnMut(
+            &mut ElementFlags,
+            u32, // key removed bytes
+            u32, // value removed bytes
+        ) -> Result<
+            (StorageRemovedBytes, StorageRemovedBytes),
+            Error,
+        >,
+        path_costs: Option<&mut Vec<(Vec<Vec<u8>>, OperationCost)>>,
+        key_costs: Option<&mut Vec<(Vec<u8>, OperationCost)>>,
+        transaction: TransactionArg,
     ) -> CostResult<(), Error> {
         let mut cost = OperationCost::default();
 
+        let (ops, conditions) =
+            cost_return_on_error!(&mut cost, self.resolve_conditional_ops(ops, transaction));
         if ops.is_empty() {
             return Ok(()).wrap_with_cost(cost);
         }
@@ -1896,9 +2798,74 @@ impl GroveDb {
             }
         }
 
+        for op in &ops {
+            if let Op::Insert { element } | Op::Replace { element } | Op::Patch { element, .. } =
+                &op.op
+            {
+                cost_return_on_error_no_add!(
+                    &cost,
+                    self.check_tree_aggregate_is_registered(element)
+                );
+            }
+        }
+
+        let referrer_handling = batch_apply_options
+            .as_ref()
+            .map(|batch_options| batch_options.referrer_handling)
+            .unwrap_or_default();
+        let ops = cost_return_on_error!(
+            &mut cost,
+            self.handle_referrers_of_batch(ops, referrer_handling, transaction)
+        );
+
         // `StorageBatch` allows us to collect operations on different subtrees before
         // execution
         let storage_batch = StorageBatch::new();
+        let insertion_metadata = batch_apply_options
+            .as_ref()
+            .and_then(|batch_options| batch_options.insertion_metadata);
+        cost_return_on_error!(
+            &mut cost,
+            self.record_insertion_metadata(
+                written_keys(&ops),
+                deleted_keys(&ops),
+                insertion_metadata,
+                &storage_batch,
+                transaction
+            )
+        );
+        cost_return_on_error!(
+            &mut cost,
+            self.prune_reverse_key_index(deleted_keys(&ops), &storage_batch, transaction)
+        );
+        cost_return_on_error!(
+            &mut cost,
+            self.record_reverse_key_index(written_keys(&ops), &storage_batch, transaction)
+        );
+        cost_return_on_error!(
+            &mut cost,
+            self.record_backlinks(&ops, &storage_batch, transaction)
+        );
+        cost_return_on_error!(
+            &mut cost,
+            self.record_prefix_registry(&ops, &storage_batch, transaction)
+        );
+        #[cfg(feature = "audit_log")]
+        let audit_entries =
+            cost_return_on_error_no_add!(&cost, crate::operations::audit_log::batch_entries(&ops));
+
+        // Writes are counted once the batch is committed
+        let written_segments: Vec<Vec<u8>> = ops
+            .iter()
+            .map(|op| {
+                op.path
+                    .iterator()
+                    .next()
+                    .map(KeyInfo::as_slice)
+                    .unwrap_or(op.key.as_slice())
+                    .to_vec()
+            })
+            .collect();
 
         // With the only one difference (if there is a transaction) do the following:
         // 2. If nothing left to do and we were on a non-leaf subtree or we're done with
@@ -1915,6 +2882,7 @@ impl GroveDb {
                 &mut cost,
                 self.apply_body(
                     ops,
+                    conditions,
                     batch_apply_options,
                     update_element_flags_function,
                     split_removal_bytes_function,
@@ -1925,38 +2893,65 @@ impl GroveDb {
                             tx,
                             new_merk,
                         )
-                    }
+                    },
+                    path_costs,
                 )
             );
 
+            #[cfg(feature = "audit_log")]
+            {
+                let recorded =
+                    self.record_audit_log(audit_entries, &mut cost, &storage_batch, transaction);
+                cost_return_on_error_no_add!(&cost, recorded);
+            }
+
             // TODO: compute batch costs
-            cost_return_on_error!(
-                &mut cost,
-                self.db
-                    .commit_multi_context_batch(storage_batch, Some(tx))
-                    .map_err(|e| e.into())
-            );
+            let committed = match key_costs {
+                Some(key_costs) => self.db.commit_multi_context_batch_recording_key_costs(
+                    storage_batch,
+                    Some(tx),
+                    key_costs,
+                ),
+                None => self.db.commit_multi_context_batch(storage_batch, Some(tx)),
+            };
+            cost_return_on_error!(&mut cost, committed.map_err(|e| e.into()));
         } else {
             cost_return_on_error!(
                 &mut cost,
                 self.apply_body(
                     ops,
+                    conditions,
                     batch_apply_options,
                     update_element_flags_function,
                     split_removal_bytes_function,
                     |path, new_merk| {
                         self.open_batch_merk_at_path(&storage_batch, path.into(), new_merk)
-                    }
+                    },
+                    path_costs,
                 )
             );
 
+            #[cfg(feature = "audit_log")]
+            {
+                let recorded =
+                    self.record_audit_log(audit_entries, &mut cost, &storage_batch, None);
+                cost_return_on_error_no_add!(&cost, recorded);
+            }
+
             // TODO: compute batch costs
-            cost_return_on_error!(
-                &mut cost,
-                self.db
-                    .commit_multi_context_batch(storage_batch, None)
-                    .map_err(|e| e.into())
-            );
+            let committed = match key_costs {
+                Some(key_costs) => self.db.commit_multi_context_batch_recording_key_costs(
+                    storage_batch,
+                    None,
+                    key_costs,
+                ),
+                None => self.db.commit_multi_context_batch(storage_batch, None),
+            };
+            cost_return_on_error!(&mut cost, committed.map_err(|e| e.into()));
+        }
+        for segment in written_segments.iter() {
+            self.metrics
+                .record_segment(OperationKind::Write, Some(segment));
         }
         Ok(()).wrap_with_cost(cost)
     }
@@ -1990,6 +2985,8 @@ impl GroveDb {
     ) -> CostResult<(), Error> {
         let mut cost = OperationCost::default();
 
+        let (ops, conditions) =
+            cost_return_on_error!(&mut cost, self.resolve_conditional_ops(ops, transaction));
         if ops.is_empty() {
             return Ok(()).wrap_with_cost(cost);
         }
@@ -2019,6 +3016,35 @@ impl GroveDb {
         // `StorageBatch` allows us to collect operations on different subtrees before
         // execution
         let storage_batch = StorageBatch::new();
+        cost_return_on_error!(
+            &mut cost,
+            self.record_insertion_metadata(
+                written_keys(&ops),
+                deleted_keys(&ops),
+                batch_apply_options.insertion_metadata,
+                &storage_batch,
+                transaction
+            )
+        );
+        cost_return_on_error!(
+            &mut cost,
+            self.prune_reverse_key_index(deleted_keys(&ops), &storage_batch, transaction)
+        );
+        cost_return_on_error!(
+            &mut cost,
+            self.record_reverse_key_index(written_keys(&ops), &storage_batch, transaction)
+        );
+        cost_return_on_error!(
+            &mut cost,
+            self.record_backlinks(&ops, &storage_batch, transaction)
+        );
+        cost_return_on_error!(
+            &mut cost,
+            self.record_prefix_registry(&ops, &storage_batch, transaction)
+        );
+        #[cfg(feature = "audit_log")]
+        let mut audit_entries =
+            cost_return_on_error_no_add!(&cost, crate::operations::audit_log::batch_entries(&ops));
 
         // With the only one difference (if there is a transaction) do the following:
         // 2. If nothing left to do and we were on a non-leaf subtree or we're done with
@@ -2035,6 +3061,7 @@ impl GroveDb {
                 &mut cost,
                 self.apply_body(
                     ops,
+                    conditions,
                     Some(batch_apply_options.clone()),
                     &mut update_element_flags_function,
                     &mut split_removal_bytes_function,
@@ -2045,7 +3072,8 @@ impl GroveDb {
                             tx,
                             new_merk,
                         )
-                    }
+                    },
+                    None,
                 )
             );
             // if we paused at the root height, the left over operations would be to replace
@@ -2074,6 +3102,12 @@ impl GroveDb {
             // we are trying to finalize
             batch_apply_options.batch_pause_height = None;
 
+            #[cfg(feature = "audit_log")]
+            audit_entries.extend(cost_return_on_error_no_add!(
+                &cost,
+                crate::operations::audit_log::batch_entries(&new_operations)
+            ));
+
             let continue_storage_batch = StorageBatch::new();
 
             cost_return_on_error!(
@@ -2095,6 +3129,17 @@ impl GroveDb {
                 )
             );
 
+            #[cfg(feature = "audit_log")]
+            {
+                let recorded = self.record_audit_log(
+                    audit_entries,
+                    &mut cost,
+                    &continue_storage_batch,
+                    transaction,
+                );
+                cost_return_on_error_no_add!(&cost, recorded);
+            }
+
             // let's build the write batch
             let continued_pending_costs = cost_return_on_error!(
                 &mut cost,
@@ -2117,12 +3162,14 @@ impl GroveDb {
                 &mut cost,
                 self.apply_body(
                     ops,
+                    conditions,
                     Some(batch_apply_options.clone()),
                     &mut update_element_flags_function,
                     &mut split_removal_bytes_function,
                     |path, new_merk| {
                         self.open_batch_merk_at_path(&storage_batch, path.into(), new_merk)
-                    }
+                    },
+                    None,
                 )
             );
 
@@ -2150,6 +3197,12 @@ impl GroveDb {
             // we are trying to finalize
             batch_apply_options.batch_pause_height = None;
 
+            #[cfg(feature = "audit_log")]
+            audit_entries.extend(cost_return_on_error_no_add!(
+                &cost,
+                crate::operations::audit_log::batch_entries(&new_operations)
+            ));
+
             let continue_storage_batch = StorageBatch::new();
 
             cost_return_on_error!(
@@ -2166,6 +3219,17 @@ impl GroveDb {
                 )
             );
 
+            #[cfg(feature = "audit_log")]
+            {
+                let recorded = self.record_audit_log(
+                    audit_entries,
+                    &mut cost,
+                    &continue_storage_batch,
+                    transaction,
+                );
+                cost_return_on_error_no_add!(&cost, recorded);
+            }
+
             // let's build the write batch
             let continued_pending_costs = cost_return_on_error!(
                 &mut cost,
@@ -2229,7 +3293,7 @@ impl GroveDb {
                 );
                 cost_return_on_error!(
                     &mut cost,
-                    Self::apply_batch_structure(batch_structure, batch_apply_options)
+                    Self::apply_batch_structure(batch_structure, batch_apply_options, None)
                 );
             }
 
@@ -2247,7 +3311,7 @@ impl GroveDb {
                 );
                 cost_return_on_error!(
                     &mut cost,
-                    Self::apply_batch_structure(batch_structure, batch_apply_options)
+                    Self::apply_batch_structure(batch_structure, batch_apply_options, None)
                 );
             }
         }
@@ -2258,8 +3322,12 @@ impl GroveDb {
 
 #[cfg(test)]
 mod tests {
-    use grovedb_costs::storage_cost::removal::StorageRemovedBytes::NoStorageRemoval;
+    use grovedb_costs::storage_cost::removal::{
+        Identifier,
+        StorageRemovedBytes::{NoStorageRemoval, SectionedStorageRemoval},
+    };
     use grovedb_merk::proofs::Query;
+    use grovedb_storage::rocksdb_storage::RocksDbStorage;
 
     use super::*;
     use crate::{
@@ -2407,6 +3475,10 @@ mod tests {
                     disable_operation_consistency_check: true,
                     base_root_storage_is_free: true,
                     batch_pause_height: None,
+                    retained_tree_memory_budget: None,
+                    referrer_handling: Default::default(),
+                    insertion_metadata: None,
+                    epoch: 0,
                 }),
                 None
             )
@@ -2418,7 +3490,7 @@ mod tests {
     #[test]
     fn test_batch_validation_ok_on_transaction() {
         let db = make_test_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"keyb", Element::empty_tree(), None, Some(&tx))
             .unwrap()
@@ -2505,7 +3577,7 @@ mod tests {
     #[test]
     fn test_batch_add_other_element_in_sub_tree() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
         // let's start by inserting a tree structure
         let ops = vec![
             GroveDbOp::insert_op(vec![], b"1".to_vec(), Element::empty_tree()),
@@ -2854,7 +3926,7 @@ mod tests {
     #[test]
     fn test_batch_produces_same_result() {
         let db = make_test_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let ops = grove_db_ops_for_contract_insert();
         db.apply_batch(ops, None, Some(&tx))
@@ -2864,7 +3936,7 @@ mod tests {
         db.root_hash(None).unwrap().expect("cannot get root hash");
 
         let db = make_test_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let ops = grove_db_ops_for_contract_insert();
         db.apply_batch(ops.clone(), None, Some(&tx))
@@ -2894,7 +3966,7 @@ mod tests {
     #[test]
     fn test_batch_contract_with_document_produces_same_result() {
         let db = make_test_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let ops = grove_db_ops_for_contract_insert();
         db.apply_batch(ops, None, Some(&tx))
@@ -2904,7 +3976,7 @@ mod tests {
         db.root_hash(None).unwrap().expect("cannot get root hash");
 
         let db = make_test_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let ops = grove_db_ops_for_contract_insert();
         let document_ops = grove_db_ops_for_contract_document_insert();
@@ -3157,6 +4229,10 @@ mod tests {
                     disable_operation_consistency_check: false,
                     base_root_storage_is_free: true,
                     batch_pause_height: None,
+                    retained_tree_memory_budget: None,
+                    referrer_handling: Default::default(),
+                    insertion_metadata: None,
+                    epoch: 0,
                 }),
                 None
             )
@@ -3196,6 +4272,10 @@ mod tests {
                     deleting_non_empty_trees_returns_error: true,
                     base_root_storage_is_free: true,
                     batch_pause_height: None,
+                    retained_tree_memory_budget: None,
+                    referrer_handling: Default::default(),
+                    insertion_metadata: None,
+                    epoch: 0,
                 }),
                 None
             )
@@ -3229,6 +4309,10 @@ mod tests {
                     disable_operation_consistency_check: false,
                     base_root_storage_is_free: true,
                     batch_pause_height: None,
+                    retained_tree_memory_budget: None,
+                    referrer_handling: Default::default(),
+                    insertion_metadata: None,
+                    epoch: 0,
                 }),
                 None
             )
@@ -3506,4 +4590,815 @@ mod tests {
             Err(Error::ReferenceLimit)
         ));
     }
+
+    #[test]
+    fn test_explain_batch() {
+        let db = make_test_grovedb();
+        let ops = vec![
+            GroveDbOp::insert_op(
+                vec![TEST_LEAF.to_vec()],
+                b"key1".to_vec(),
+                Element::new_item(b"ayy".to_vec()),
+            ),
+            GroveDbOp::insert_op(
+                vec![TEST_LEAF.to_vec(), b"key2".to_vec()],
+                b"key3".to_vec(),
+                Element::new_item(b"ayy".to_vec()),
+            ),
+            GroveDbOp::insert_op(
+                vec![TEST_LEAF.to_vec()],
+                b"key2".to_vec(),
+                Element::empty_tree(),
+            ),
+            GroveDbOp::insert_op(
+                vec![TEST_LEAF.to_vec()],
+                b"key1".to_vec(),
+                Element::new_item(b"ayy2".to_vec()),
+            ),
+        ];
+        // duplicate operations are only allowed without consistency checks
+        let options = BatchApplyOptions {
+            disable_operation_consistency_check: true,
+            ..Default::default()
+        };
+        db.metrics().reset();
+        let explanation = db
+            .explain_batch(ops.clone(), Some(options.clone()))
+            .expect("cannot explain batch");
+
+        let explained = &explanation.ops;
+        assert_eq!(
+            explained[0].consolidated_op,
+            ConsolidatedOpType::Superseded { by: 3 }
+        );
+        assert_eq!(explained[0].execution_order, None);
+        assert_eq!(explained[0].projected_cost, OperationCost::default());
+        assert_eq!(
+            explained[2].consolidated_op,
+            ConsolidatedOpType::InsertTreeWithRootHash
+        );
+        assert_eq!(explained[3].consolidated_op, ConsolidatedOpType::Unchanged);
+
+        // the deeper subtree is executed first
+        assert_eq!(explained[1].execution_order, Some(0));
+        assert_eq!(explained[3].execution_order, Some(1));
+        assert_eq!(explained[2].execution_order, Some(2));
+
+        assert_eq!(
+            explained[1].propagation_targets,
+            vec![
+                vec![TEST_LEAF.to_vec(), b"key2".to_vec()],
+                vec![TEST_LEAF.to_vec()]
+            ]
+        );
+        assert_eq!(
+            explained[1].subtree_prefix,
+            RocksDbStorage::build_prefix([TEST_LEAF, b"key2"].as_ref().into()).unwrap()
+        );
+        assert!(explained[1].projected_cost.seek_count > 0);
+        assert!(explanation.projected_cost.seek_count > 0);
+
+        // nothing was applied or counted in the metrics
+        assert!(db
+            .get([TEST_LEAF].as_ref(), b"key2", None)
+            .unwrap()
+            .is_err());
+        assert_eq!(db.metrics().snapshot().total.writes, 0);
+
+        // writes are counted once the batch is committed, superseded ones
+        // included
+        db.apply_batch(ops, Some(options), None)
+            .unwrap()
+            .expect("cannot apply batch");
+        assert_eq!(db.metrics().snapshot().total.writes, 4);
+        let ops = vec![GroveDbOp::insert_op(
+            vec![TEST_LEAF.to_vec(), b"missing".to_vec()],
+            b"key".to_vec(),
+            Element::new_item(b"ayy".to_vec()),
+        )];
+        assert!(db.apply_batch(ops, None, None).unwrap().is_err());
+        assert_eq!(db.metrics().snapshot().total.writes, 4);
+    }
+
+    #[test]
+    fn test_batch_apply_with_retained_tree_memory_budget() {
+        let budgeted_db = make_test_grovedb();
+        let unbounded_db = make_test_grovedb();
+        let options = BatchApplyOptions {
+            retained_tree_memory_budget: Some(1),
+            ..Default::default()
+        };
+
+        let mut ops = vec![];
+        for tree in 0u8..4 {
+            ops.push(GroveDbOp::insert_op(
+                vec![TEST_LEAF.to_vec()],
+                vec![tree],
+                Element::empty_tree(),
+            ));
+            for item in 0u8..50 {
+                ops.push(GroveDbOp::insert_op(
+                    vec![TEST_LEAF.to_vec(), vec![tree]],
+                    vec![item],
+                    Element::new_item(vec![tree, item]),
+                ));
+            }
+        }
+        budgeted_db
+            .apply_batch(ops.clone(), Some(options.clone()), None)
+            .unwrap()
+            .expect("cannot apply batch");
+        unbounded_db
+            .apply_batch(ops, None, None)
+            .unwrap()
+            .expect("cannot apply batch");
+
+        // Referencing an unchanged item reads back the subtree pruned earlier in
+        // the batch, whose nodes are partly rewritten but not yet committed
+        let ops = vec![
+            GroveDbOp::replace_op(
+                vec![TEST_LEAF.to_vec(), vec![2]],
+                vec![5],
+                Element::new_item(b"updated".to_vec()),
+            ),
+            GroveDbOp::insert_op(
+                vec![TEST_LEAF.to_vec()],
+                b"ref".to_vec(),
+                Element::new_reference(ReferencePathType::AbsolutePathReference(vec![
+                    TEST_LEAF.to_vec(),
+                    vec![2],
+                    vec![7],
+                ])),
+            ),
+        ];
+        budgeted_db
+            .apply_batch(ops.clone(), Some(options), None)
+            .unwrap()
+            .expect("cannot apply batch");
+        unbounded_db
+            .apply_batch(ops, None, None)
+            .unwrap()
+            .expect("cannot apply batch");
+
+        assert_eq!(
+            budgeted_db.root_hash(None).unwrap().unwrap(),
+            unbounded_db.root_hash(None).unwrap().unwrap()
+        );
+        assert_eq!(
+            budgeted_db
+                .get([TEST_LEAF].as_ref(), b"ref", None)
+                .unwrap()
+                .expect("cannot get element"),
+            Element::new_item(vec![2, 7])
+        );
+    }
+
+    #[test]
+    fn test_replace_fails_if_absent() {
+        let db = make_test_grovedb();
+        assert!(matches!(
+            db.replace(
+                [TEST_LEAF].as_ref(),
+                b"key",
+                Element::new_item(b"value".to_vec()),
+                None,
+            )
+            .unwrap(),
+            Err(Error::PathKeyNotFound(_))
+        ));
+        let ops = vec![GroveDbOp::replace_op(
+            vec![TEST_LEAF.to_vec()],
+            b"key".to_vec(),
+            Element::new_item(b"value".to_vec()),
+        )];
+        assert!(matches!(
+            db.apply_batch(ops.clone(), None, None).unwrap(),
+            Err(Error::PathKeyNotFound(_))
+        ));
+
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"key",
+            Element::new_item(b"old".to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("cannot insert item");
+        db.apply_batch(ops, None, None)
+            .unwrap()
+            .expect("cannot apply batch");
+        db.replace(
+            [TEST_LEAF].as_ref(),
+            b"key",
+            Element::new_item(b"other".to_vec()),
+            None,
+        )
+        .unwrap()
+        .expect("cannot replace item");
+        assert_eq!(
+            db.get([TEST_LEAF].as_ref(), b"key", None)
+                .unwrap()
+                .expect("cannot get element"),
+            Element::new_item(b"other".to_vec())
+        );
+        assert!(matches!(
+            db.replace(
+                EMPTY_PATH,
+                TEST_LEAF,
+                Element::new_item(b"value".to_vec()),
+                None,
+            )
+            .unwrap(),
+            Err(Error::OverrideNotAllowed(_))
+        ));
+    }
+
+    #[test]
+    fn test_batch_rejects_oversized_flags_from_update() {
+        let db = make_test_grovedb();
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"key",
+            Element::new_item_with_flags(b"value".to_vec(), Some(vec![0])),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("cannot insert item");
+
+        let ops = vec![GroveDbOp::insert_op(
+            vec![TEST_LEAF.to_vec()],
+            b"key".to_vec(),
+            Element::new_item_with_flags(b"bigger value".to_vec(), Some(vec![0])),
+        )];
+        let result = db
+            .apply_batch_with_element_flags_update(
+                ops,
+                None,
+                |_cost, _old_flags, new_flags| {
+                    *new_flags = vec![0; MAX_ELEMENT_FLAGS_SIZE + 1];
+                    Ok(true)
+                },
+                |_flags, _removed_key_bytes, _removed_value_bytes| {
+                    Ok((NoStorageRemoval, NoStorageRemoval))
+                },
+                None,
+            )
+            .unwrap();
+        assert!(matches!(result, Err(Error::InvalidElementFlags(_))));
+
+        assert_eq!(
+            db.get([TEST_LEAF].as_ref(), b"key", None)
+                .unwrap()
+                .expect("cannot get element"),
+            Element::new_item_with_flags(b"value".to_vec(), Some(vec![0]))
+        );
+    }
+
+    #[test]
+    fn test_batch_with_storage_flags_refunds_per_epoch() {
+        let db = make_test_grovedb();
+        let flags = StorageFlags::new_single_epoch(0, None).serialize();
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"key",
+            Element::new_item_with_flags(b"value".to_vec(), Some(flags.clone())),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("cannot insert item");
+
+        // the update in epoch 2 pays for the bytes it adds
+        let ops = vec![GroveDbOp::insert_op(
+            vec![TEST_LEAF.to_vec()],
+            b"key".to_vec(),
+            Element::new_item_with_flags(b"a much bigger value".to_vec(), Some(flags)),
+        )];
+        db.apply_batch_with_storage_flags(ops, None, 2, RemovalStrategy::OldestFirst, None)
+            .unwrap()
+            .expect("cannot apply batch");
+        let element = db
+            .get([TEST_LEAF].as_ref(), b"key", None)
+            .unwrap()
+            .expect("cannot get element");
+        let storage_flags =
+            StorageFlags::deserialize(element.get_flags().as_ref().expect("expected flags"))
+                .expect("expected storage flags");
+        assert_eq!(storage_flags.base_epoch(), 0);
+        let base_epoch_bytes = storage_flags.bytes_per_epoch()[&0];
+        let epoch_2_bytes = storage_flags.bytes_per_epoch()[&2];
+        assert!(base_epoch_bytes > 0);
+        assert!(epoch_2_bytes > 0);
+
+        // the deletion refunds every epoch
+        let ops = vec![GroveDbOp::delete_op(
+            vec![TEST_LEAF.to_vec()],
+            b"key".to_vec(),
+        )];
+        let cost = db
+            .apply_batch_with_storage_flags(ops, None, 3, RemovalStrategy::OldestFirst, None)
+            .cost;
+        let SectionedStorageRemoval(removed_bytes) = cost.storage_cost.removed_bytes else {
+            panic!("expected removal sectioned by epoch");
+        };
+        let refunds = &removed_bytes[&Identifier::default()];
+        assert_eq!(refunds.get(2), Some(&epoch_2_bytes));
+        assert!(refunds.get(0).copied().unwrap_or_default() > base_epoch_bytes);
+        assert_eq!(refunds.get(3), None);
+    }
+
+    #[test]
+    fn test_apply_batches_rolls_back_failed_batches_only() {
+        let db = make_test_grovedb();
+        let tx = db.start_transaction().unwrap();
+        let batches = vec![
+            vec![GroveDbOp::insert_op(
+                vec![TEST_LEAF.to_vec()],
+                b"a".to_vec(),
+                Element::new_item(b"1".to_vec()),
+            )],
+            vec![
+                GroveDbOp::insert_op(
+                    vec![TEST_LEAF.to_vec()],
+                    b"b".to_vec(),
+                    Element::new_item(b"2".to_vec()),
+                ),
+                GroveDbOp::insert_op(
+                    vec![b"missing".to_vec()],
+                    b"c".to_vec(),
+                    Element::new_item(b"3".to_vec()),
+                ),
+            ],
+            vec![GroveDbOp::insert_op(
+                vec![ANOTHER_TEST_LEAF.to_vec()],
+                b"d".to_vec(),
+                Element::new_item(b"4".to_vec()),
+            )],
+        ];
+        let applied = db.apply_batches(batches, None, &tx);
+        let results = applied.value.expect("expected batches to be applied");
+        assert!(results[0].value.is_ok());
+        assert!(results[1].value.is_err());
+        assert!(results[2].value.is_ok());
+        assert_eq!(
+            applied.cost,
+            results
+                .iter()
+                .fold(OperationCost::default(), |total, result| total
+                    + result.cost.clone())
+        );
+        db.commit_transaction(tx)
+            .unwrap()
+            .expect("cannot commit transaction");
+
+        assert!(db.get([TEST_LEAF].as_ref(), b"a", None).unwrap().is_ok());
+        assert!(db.get([TEST_LEAF].as_ref(), b"b", None).unwrap().is_err());
+        assert!(db
+            .get([ANOTHER_TEST_LEAF].as_ref(), b"d", None)
+            .unwrap()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_apply_batches_leaves_no_savepoint() {
+        let db = make_test_grovedb();
+        let tx = db.start_transaction().unwrap();
+        db.set_savepoint(&tx);
+        let batches = vec![
+            vec![GroveDbOp::insert_op(
+                vec![TEST_LEAF.to_vec()],
+                b"a".to_vec(),
+                Element::new_item(b"1".to_vec()),
+            )],
+            vec![GroveDbOp::insert_op(
+                vec![b"missing".to_vec()],
+                b"b".to_vec(),
+                Element::new_item(b"2".to_vec()),
+            )],
+        ];
+        db.apply_batches(batches, None, &tx)
+            .unwrap()
+            .expect("expected batches to be applied");
+
+        // Rolling back goes to the savepoint of the caller
+        db.rollback_to_savepoint(&tx)
+            .expect("expected to roll back");
+        assert!(db
+            .get([TEST_LEAF].as_ref(), b"a", Some(&tx))
+            .unwrap()
+            .is_err());
+        assert!(db.rollback_to_savepoint(&tx).is_err());
+    }
+
+    #[test]
+    fn test_estimate_batch_cost_discards_writes() {
+        let db = make_test_grovedb();
+        let tx = db.start_transaction().unwrap();
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"a",
+            Element::new_item(b"1".to_vec()),
+            None,
+            Some(&tx),
+        )
+        .unwrap()
+        .expect("successful insert");
+        let ops = vec![
+            GroveDbOp::insert_op(
+                vec![TEST_LEAF.to_vec()],
+                b"a".to_vec(),
+                Element::new_item(b"2".to_vec()),
+            ),
+            GroveDbOp::insert_op(
+                vec![ANOTHER_TEST_LEAF.to_vec()],
+                b"b".to_vec(),
+                Element::new_item(b"3".to_vec()),
+            ),
+        ];
+
+        let estimated_cost = db
+            .estimate_batch_cost(ops.clone(), None, Some(&tx))
+            .cost_as_result()
+            .expect("expected batch to be estimated");
+        assert_eq!(
+            db.get([TEST_LEAF].as_ref(), b"a", Some(&tx))
+                .unwrap()
+                .expect("successful get"),
+            Element::new_item(b"1".to_vec())
+        );
+        assert!(db
+            .get([ANOTHER_TEST_LEAF].as_ref(), b"b", Some(&tx))
+            .unwrap()
+            .is_err());
+
+        let applied_cost = db
+            .apply_batch(ops, None, Some(&tx))
+            .cost_as_result()
+            .expect("expected batch to be applied");
+        assert_eq!(estimated_cost, applied_cost);
+
+        // Without a transaction the estimate runs against the committed state
+        let ops = vec![GroveDbOp::insert_op(
+            vec![b"missing".to_vec()],
+            b"c".to_vec(),
+            Element::new_item(b"4".to_vec()),
+        )];
+        assert!(db.estimate_batch_cost(ops, None, None).value.is_err());
+        assert!(db.get([TEST_LEAF].as_ref(), b"a", None).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_validate_batch() {
+        let db = make_test_grovedb();
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"old_tree",
+            Element::empty_tree(),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+        let ops = vec![
+            GroveDbOp::insert_op(
+                vec![TEST_LEAF.to_vec()],
+                b"tree".to_vec(),
+                Element::empty_tree(),
+            ),
+            GroveDbOp::insert_op(
+                vec![TEST_LEAF.to_vec(), b"tree".to_vec()],
+                b"a".to_vec(),
+                Element::new_item(b"1".to_vec()),
+            ),
+            GroveDbOp::insert_op(
+                vec![TEST_LEAF.to_vec(), b"missing".to_vec()],
+                b"b".to_vec(),
+                Element::new_item(b"2".to_vec()),
+            ),
+            GroveDbOp::insert_op(
+                vec![ANOTHER_TEST_LEAF.to_vec()],
+                b"ref".to_vec(),
+                Element::new_reference(ReferencePathType::AbsolutePathReference(vec![
+                    TEST_LEAF.to_vec(),
+                    b"tree".to_vec(),
+                    b"a".to_vec(),
+                ])),
+            ),
+            GroveDbOp::insert_op(
+                vec![ANOTHER_TEST_LEAF.to_vec()],
+                b"dangling".to_vec(),
+                Element::new_reference(ReferencePathType::AbsolutePathReference(vec![
+                    TEST_LEAF.to_vec(),
+                    b"nothing".to_vec(),
+                ])),
+            ),
+            GroveDbOp::delete_op(vec![TEST_LEAF.to_vec()], b"nothing".to_vec()),
+            GroveDbOp::insert_op(
+                vec![TEST_LEAF.to_vec()],
+                b"old_tree".to_vec(),
+                Element::new_item(b"3".to_vec()),
+            ),
+            GroveDbOp::insert_op(
+                vec![TEST_LEAF.to_vec()],
+                b"dup".to_vec(),
+                Element::new_item(b"4".to_vec()),
+            ),
+            GroveDbOp::insert_op(
+                vec![TEST_LEAF.to_vec()],
+                b"dup".to_vec(),
+                Element::new_item(b"5".to_vec()),
+            ),
+        ];
+
+        let invalid_ops = db
+            .validate_batch(&ops, None)
+            .unwrap()
+            .expect("expected batch to be validated");
+        assert_eq!(
+            invalid_ops.iter().map(|op| op.index).collect::<Vec<_>>(),
+            vec![2, 4, 5, 6, 7, 8]
+        );
+        assert!(matches!(
+            invalid_ops[0].error,
+            Error::InvalidParentLayerPath(_)
+        ));
+        assert!(matches!(invalid_ops[1].error, Error::MissingReference(_)));
+        assert!(matches!(invalid_ops[2].error, Error::PathKeyNotFound(_)));
+        assert!(matches!(invalid_ops[3].error, Error::WrongElementType(_)));
+        assert!(matches!(
+            invalid_ops[4].error,
+            Error::InvalidBatchOperation(_)
+        ));
+
+        // Nothing was staged
+        assert!(db
+            .get([TEST_LEAF].as_ref(), b"tree", None)
+            .unwrap()
+            .is_err());
+
+        let valid_ops = ops.into_iter().take(2).collect::<Vec<_>>();
+        assert!(db
+            .validate_batch(&valid_ops, None)
+            .unwrap()
+            .expect("expected batch to be validated")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_conditional_batch_operations() {
+        let db = make_test_grovedb();
+        for (key, value) in [(b"a", b"1"), (b"b", b"2"), (b"d", b"4")] {
+            db.insert(
+                [TEST_LEAF].as_ref(),
+                key,
+                Element::new_item(value.to_vec()),
+                None,
+                None,
+            )
+            .unwrap()
+            .expect("successful insert");
+        }
+        let ops = vec![
+            GroveDbOp::insert_if_not_exists_op(
+                vec![TEST_LEAF.to_vec()],
+                b"a".to_vec(),
+                Element::new_item(b"9".to_vec()),
+            ),
+            GroveDbOp::insert_if_not_exists_op(
+                vec![TEST_LEAF.to_vec()],
+                b"c".to_vec(),
+                Element::new_item(b"3".to_vec()),
+            ),
+            GroveDbOp::delete_if_value_matches_op(
+                vec![TEST_LEAF.to_vec()],
+                b"b".to_vec(),
+                Element::new_item(b"2".to_vec()),
+            ),
+            GroveDbOp::delete_if_value_matches_op(
+                vec![TEST_LEAF.to_vec()],
+                b"d".to_vec(),
+                Element::new_item(b"5".to_vec()),
+            ),
+        ];
+        db.apply_batch(ops, None, None)
+            .unwrap()
+            .expect("expected batch to be applied");
+        let get = |key: &[u8]| db.get([TEST_LEAF].as_ref(), key, None).unwrap();
+        assert_eq!(
+            get(b"a").expect("successful get"),
+            Element::new_item(b"1".to_vec())
+        );
+        assert_eq!(
+            get(b"c").expect("successful get"),
+            Element::new_item(b"3".to_vec())
+        );
+        assert!(matches!(get(b"b"), Err(Error::PathKeyNotFound(_))));
+        assert_eq!(
+            get(b"d").expect("successful get"),
+            Element::new_item(b"4".to_vec())
+        );
+
+        // A replace without the expected value hash fails the whole batch
+        let stored_value_hash = value_hash(
+            &Element::new_item(b"1".to_vec())
+                .serialize()
+                .expect("expected to serialize"),
+        )
+        .unwrap();
+        let replace_ops = |expected_value_hash| {
+            vec![
+                GroveDbOp::replace_expecting_op(
+                    vec![TEST_LEAF.to_vec()],
+                    b"a".to_vec(),
+                    Element::new_item(b"10".to_vec()),
+                    expected_value_hash,
+                ),
+                GroveDbOp::insert_op(
+                    vec![TEST_LEAF.to_vec()],
+                    b"e".to_vec(),
+                    Element::new_item(b"5".to_vec()),
+                ),
+            ]
+        };
+        assert!(matches!(
+            db.apply_batch(replace_ops([0; 32]), None, None).unwrap(),
+            Err(Error::BatchOperationConditionFailed(_))
+        ));
+        assert!(get(b"e").is_err());
+        db.apply_batch(replace_ops(stored_value_hash), None, None)
+            .unwrap()
+            .expect("expected batch to be applied");
+        assert_eq!(
+            get(b"a").expect("successful get"),
+            Element::new_item(b"10".to_vec())
+        );
+        assert!(get(b"e").is_ok());
+    }
+
+    #[test]
+    fn test_conditions_are_checked_again_when_applying() {
+        let db = make_test_grovedb();
+        let ops = vec![GroveDbOp::insert_if_not_exists_op(
+            vec![TEST_LEAF.to_vec()],
+            b"a".to_vec(),
+            Element::new_item(b"1".to_vec()),
+        )];
+        let (ops, conditions) = db
+            .resolve_conditional_ops(ops, None)
+            .unwrap()
+            .expect("expected to resolve");
+        assert_eq!(
+            ops[0].op,
+            Op::Insert {
+                element: Element::new_item(b"1".to_vec())
+            }
+        );
+
+        // The element is inserted between the resolution and the application
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"a",
+            Element::new_item(b"2".to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+
+        let storage_batch = StorageBatch::new();
+        let applied = db
+            .apply_body(
+                ops,
+                conditions,
+                None,
+                |_, _, _| Ok(false),
+                |_, removed_key_bytes, removed_value_bytes| {
+                    Ok((
+                        BasicStorageRemoval(removed_key_bytes),
+                        BasicStorageRemoval(removed_value_bytes),
+                    ))
+                },
+                |path, new_merk| db.open_batch_merk_at_path(&storage_batch, path.into(), new_merk),
+                None,
+            )
+            .unwrap();
+        assert!(matches!(
+            applied,
+            Err(Error::BatchOperationConditionFailed(_))
+        ));
+        assert_eq!(
+            db.get([TEST_LEAF].as_ref(), b"a", None)
+                .unwrap()
+                .expect("successful get"),
+            Element::new_item(b"2".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_apply_batch_within_cost_limit() {
+        let db = make_test_grovedb();
+        let ops: Vec<GroveDbOp> = (0u8..4)
+            .map(|i| {
+                GroveDbOp::insert_op(
+                    vec![TEST_LEAF.to_vec()],
+                    vec![i],
+                    Element::new_item(b"value".to_vec()),
+                )
+            })
+            .collect();
+
+        // The limit only fits the first two insertions
+        let cost_limit = db
+            .estimate_batch_cost(ops[..2].to_vec(), None, None)
+            .cost_as_result()
+            .expect("expected batch to be estimated");
+
+        let applied = db.apply_batch_within_cost_limit(ops.clone(), &cost_limit, None, None);
+        assert_eq!(applied.cost, cost_limit);
+        assert_eq!(
+            applied.value.expect("expected operations to be applied"),
+            ops[2..].to_vec()
+        );
+        for (i, op) in ops.iter().enumerate() {
+            let result = db
+                .get([TEST_LEAF].as_ref(), op.key.as_slice(), None)
+                .unwrap();
+            assert_eq!(result.is_ok(), i < 2);
+        }
+
+        // A limit fitting everything applies everything
+        let remaining = db
+            .apply_batch_within_cost_limit(
+                ops[2..].to_vec(),
+                &OperationCost {
+                    seek_count: u16::MAX,
+                    storage_cost: StorageCost {
+                        added_bytes: u32::MAX,
+                        replaced_bytes: u32::MAX,
+                        removed_bytes: NoStorageRemoval,
+                    },
+                    storage_loaded_bytes: u32::MAX,
+                    hash_node_calls: u32::MAX,
+                },
+                None,
+                None,
+            )
+            .unwrap()
+            .expect("expected operations to be applied");
+        assert!(remaining.is_empty());
+        assert!(db.get([TEST_LEAF].as_ref(), &[3], None).unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_apply_batch_within_cost_limit_leaves_no_savepoint() {
+        let db = make_test_grovedb();
+        let ops: Vec<GroveDbOp> = (0u8..4)
+            .map(|i| {
+                GroveDbOp::insert_op(
+                    vec![TEST_LEAF.to_vec()],
+                    vec![i],
+                    Element::new_item(b"value".to_vec()),
+                )
+            })
+            .collect();
+
+        let tx = db.start_transaction().unwrap();
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"before",
+            Element::new_item(b"value".to_vec()),
+            None,
+            Some(&tx),
+        )
+        .unwrap()
+        .expect("successful insert");
+        db.set_savepoint(&tx);
+        // The limit is estimated against the writes of the transaction, which
+        // make the insertions replace more bytes
+        let cost_limit = db
+            .estimate_batch_cost(ops[..2].to_vec(), None, Some(&tx))
+            .cost_as_result()
+            .expect("expected batch to be estimated");
+        let remaining = db
+            .apply_batch_within_cost_limit(ops.clone(), &cost_limit, None, Some(&tx))
+            .unwrap()
+            .expect("expected operations to be applied");
+        assert_eq!(remaining, ops[2..].to_vec());
+
+        // Rolling back goes to the savepoint of the caller, undoing the whole
+        // prefix
+        db.rollback_to_savepoint(&tx)
+            .expect("expected to roll back");
+        assert!(db
+            .get([TEST_LEAF].as_ref(), &[0], Some(&tx))
+            .unwrap()
+            .is_err());
+        assert!(db
+            .get([TEST_LEAF].as_ref(), b"before", Some(&tx))
+            .unwrap()
+            .is_ok());
+        assert!(db.rollback_to_savepoint(&tx).is_err());
+    }
 }
@@ -47,7 +47,7 @@ mod tests {
     #[test]
     fn test_batch_two_insert_empty_tree_same_level_added_bytes_match_non_batch() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let non_batch_cost_1 = db
             .insert(EMPTY_PATH, b"key1", Element::empty_tree(), None, Some(&tx))
@@ -73,7 +73,7 @@ mod tests {
     #[test]
     fn test_batch_three_inserts_elements_same_level_added_bytes_match_non_batch() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let non_batch_cost_1 = db
             .insert(EMPTY_PATH, b"key1", Element::empty_tree(), None, Some(&tx))
@@ -123,7 +123,7 @@ mod tests {
     #[test]
     fn test_batch_four_inserts_elements_multi_level_added_bytes_match_non_batch() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let non_batch_cost_1 = db
             .insert(EMPTY_PATH, b"key1", Element::empty_tree(), None, Some(&tx))
@@ -199,7 +199,7 @@ mod tests {
     #[test]
     fn test_batch_root_two_insert_tree_cost_same_level() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let ops = vec![
             GroveDbOp::insert_op(vec![], b"key1".to_vec(), Element::empty_tree()),
@@ -254,7 +254,7 @@ mod tests {
     #[test]
     fn test_batch_root_two_insert_tree_cost_different_level() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let ops = vec![
             GroveDbOp::insert_op(vec![], b"key1".to_vec(), Element::empty_tree()),
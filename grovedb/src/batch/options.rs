@@ -32,7 +32,14 @@
 use grovedb_merk::MerkOptions;
 
 #[cfg(feature = "full")]
-use crate::operations::{delete::DeleteOptions, insert::InsertOptions};
+use crate::{
+    element::storage_flags::Epoch,
+    operations::{
+        delete::{DeleteOptions, ReferrerHandling},
+        insert::InsertOptions,
+        insertion_metadata::InsertionMetadata,
+    },
+};
 
 /// Batch apply options
 #[cfg(feature = "full")]
@@ -53,6 +60,19 @@ pub struct BatchApplyOptions {
     /// At what height do we want to pause applying batch operations
     /// Most of the time this should be not set
     pub batch_pause_height: Option<u8>,
+    /// Limit in bytes of the tree nodes retained in memory by all the Merks
+    /// opened while applying the batch, least recently used subtrees being
+    /// pruned when it is exceeded
+    pub retained_tree_memory_budget: Option<usize>,
+    /// What to do with the references pointing at the elements deleted by
+    /// the batch, or into the subtrees it deletes
+    pub referrer_handling: ReferrerHandling,
+    /// Metadata recorded for the elements written by the batch into subtrees
+    /// with insertion metadata enabled
+    pub insertion_metadata: Option<InsertionMetadata>,
+    /// Epoch the batch is applied in, given to the flags codec merging the
+    /// flags of updated elements
+    pub epoch: Epoch,
 }
 
 #[cfg(feature = "full")]
@@ -66,6 +86,10 @@ impl Default for BatchApplyOptions {
             disable_operation_consistency_check: false,
             base_root_storage_is_free: true,
             batch_pause_height: None,
+            retained_tree_memory_budget: None,
+            referrer_handling: ReferrerHandling::Ignore,
+            insertion_metadata: None,
+            epoch: 0,
         }
     }
 }
@@ -79,6 +103,7 @@ impl BatchApplyOptions {
             validate_insertion_does_not_override_tree: self
                 .validate_insertion_does_not_override_tree,
             base_root_storage_is_free: self.base_root_storage_is_free,
+            insertion_metadata: self.insertion_metadata,
         }
     }
 
@@ -89,6 +114,8 @@ impl BatchApplyOptions {
             deleting_non_empty_trees_returns_error: self.deleting_non_empty_trees_returns_error,
             base_root_storage_is_free: self.base_root_storage_is_free,
             validate_tree_at_path_exists: false,
+            referrer_handling: self.referrer_handling,
+            insertion_metadata: self.insertion_metadata,
         }
     }
 
@@ -52,7 +52,7 @@ mod tests {
             .cost_as_result()
             .expect("expected to insert successfully");
 
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let non_batch_cost = db
             .delete(EMPTY_PATH, b"key1", None, Some(&tx))
@@ -114,7 +114,7 @@ mod tests {
             .cost_as_result()
             .expect("expected to insert successfully");
 
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let non_batch_cost = db
             .delete(EMPTY_PATH, b"key1", None, Some(&tx))
@@ -310,7 +310,7 @@ mod tests {
             .cost_as_result()
             .expect("expected to insert successfully");
 
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let non_batch_cost = db
             .delete(EMPTY_PATH, b"key1", None, Some(&tx))
@@ -378,7 +378,7 @@ mod tests {
             .cost_as_result()
             .expect("expected to insert successfully");
 
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let non_batch_cost = db
             .delete_with_sectional_storage_function(
@@ -494,7 +494,7 @@ mod tests {
             .cost_as_result()
             .expect("expected to insert successfully");
 
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let non_batch_cost = db
             .delete(EMPTY_PATH, b"key1", None, Some(&tx))
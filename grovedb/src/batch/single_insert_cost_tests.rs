@@ -55,7 +55,7 @@ mod tests {
     #[test]
     fn test_batch_one_insert_costs_match_non_batch() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let non_batch_cost = db
             .insert(EMPTY_PATH, b"key1", Element::empty_tree(), None, Some(&tx))
@@ -73,7 +73,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_insert_tree_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let ops = vec![GroveDbOp::insert_op(
             vec![],
@@ -137,7 +137,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_insert_item_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let ops = vec![GroveDbOp::insert_op(
             vec![],
@@ -200,7 +200,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_insert_tree_under_parent_item_in_same_merk_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let cost = db
             .insert(
@@ -294,7 +294,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_insert_tree_under_parent_tree_in_same_merk_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"0", Element::empty_tree(), None, Some(&tx))
             .unwrap()
@@ -371,7 +371,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_insert_tree_under_parent_tree_in_different_merk_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"0", Element::empty_tree(), None, Some(&tx))
             .unwrap()
@@ -452,7 +452,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_insert_cost_right_below_value_required_cost_of_2() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let ops = vec![GroveDbOp::insert_op(
             vec![],
@@ -514,7 +514,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_insert_cost_right_above_value_required_cost_of_2() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let ops = vec![GroveDbOp::insert_op(
             vec![],
@@ -576,7 +576,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_update_item_bigger_cost_no_flags() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
         db.insert(EMPTY_PATH, b"tree", Element::empty_tree(), None, None)
             .unwrap()
             .expect("expected to insert tree");
@@ -632,7 +632,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_update_item_bigger_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
         db.insert(EMPTY_PATH, b"tree", Element::empty_tree(), None, None)
             .unwrap()
             .expect("expected to insert tree");
@@ -711,7 +711,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_update_item_smaller_cost_no_flags() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
         db.insert(EMPTY_PATH, b"tree", Element::empty_tree(), None, None)
             .unwrap()
             .expect("expected to insert tree");
@@ -766,7 +766,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_update_item_smaller_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
         db.insert(EMPTY_PATH, b"tree", Element::empty_tree(), None, None)
             .unwrap()
             .expect("expected to insert tree");
@@ -846,7 +846,7 @@ mod tests {
     #[test]
     fn test_batch_root_one_update_tree_bigger_flags_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
         db.insert(EMPTY_PATH, b"tree", Element::empty_tree(), None, None)
             .unwrap()
             .expect("expected to insert tree");
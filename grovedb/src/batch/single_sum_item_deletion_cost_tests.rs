@@ -46,7 +46,7 @@ mod tests {
             .cost_as_result()
             .expect("expected to insert successfully");
 
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let non_batch_cost = db
             .delete(EMPTY_PATH, b"key1", None, Some(&tx))
@@ -95,7 +95,7 @@ mod tests {
             .cost_as_result()
             .expect("expected to insert successfully");
 
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let non_batch_cost = db
             .delete([b"sum_tree".as_slice()].as_ref(), b"key1", None, Some(&tx))
@@ -137,7 +137,7 @@ mod tests {
             .cost_as_result()
             .expect("expected to insert successfully");
 
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let non_batch_cost = db
             .delete(EMPTY_PATH, b"key1", None, Some(&tx))
@@ -44,7 +44,7 @@ mod tests {
     #[test]
     fn test_batch_one_sum_item_insert_costs_match_non_batch() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(
             EMPTY_PATH,
@@ -78,7 +78,7 @@ mod tests {
     #[test]
     fn test_batch_one_insert_sum_tree_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let ops = vec![GroveDbOp::insert_op(
             vec![],
@@ -143,7 +143,7 @@ mod tests {
     #[test]
     fn test_batch_one_insert_sum_tree_under_parent_tree_in_same_merk_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"0", Element::empty_tree(), None, Some(&tx))
             .unwrap()
@@ -221,7 +221,7 @@ mod tests {
     #[test]
     fn test_batch_one_insert_sum_tree_under_parent_sum_tree_in_same_merk_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"0", Element::empty_sum_tree(), None, Some(&tx))
             .unwrap()
@@ -299,7 +299,7 @@ mod tests {
     #[test]
     fn test_batch_one_insert_sum_tree_under_parent_tree_in_different_merk_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"0", Element::empty_tree(), None, Some(&tx))
             .unwrap()
@@ -381,7 +381,7 @@ mod tests {
     #[test]
     fn test_batch_one_insert_sum_tree_under_parent_sum_tree_in_different_merk_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"0", Element::empty_sum_tree(), None, Some(&tx))
             .unwrap()
@@ -464,7 +464,7 @@ mod tests {
     #[test]
     fn test_batch_one_insert_sum_item_cost_right_below_value_required_cost_of_2() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(
             EMPTY_PATH,
@@ -537,7 +537,7 @@ mod tests {
     #[test]
     fn test_batch_one_insert_sum_item_cost_right_above_value_required_cost_of_2() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(
             EMPTY_PATH,
@@ -610,7 +610,7 @@ mod tests {
     #[test]
     fn test_batch_one_update_sum_item_bigger_no_flags() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
         db.insert(EMPTY_PATH, b"tree", Element::empty_sum_tree(), None, None)
             .unwrap()
             .expect("expected to insert tree");
@@ -666,7 +666,7 @@ mod tests {
     #[test]
     fn test_batch_one_update_sum_item_bigger_with_flags() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
         db.insert(EMPTY_PATH, b"tree", Element::empty_sum_tree(), None, None)
             .unwrap()
             .expect("expected to insert tree");
@@ -722,7 +722,7 @@ mod tests {
     #[test]
     fn test_batch_one_update_sum_item_smaller_no_flags() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
         db.insert(EMPTY_PATH, b"tree", Element::empty_sum_tree(), None, None)
             .unwrap()
             .expect("expected to insert tree");
@@ -778,7 +778,7 @@ mod tests {
     #[test]
     fn test_batch_one_update_sum_item_smaller_with_flags() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
         db.insert(EMPTY_PATH, b"tree", Element::empty_sum_tree(), None, None)
             .unwrap()
             .expect("expected to insert tree");
@@ -0,0 +1,268 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Pre-flight validation of batches
+
+use grovedb_costs::{cost_return_on_error, CostResult, CostsExt, OperationCost};
+use grovedb_visualize::DebugByteVectors;
+
+use crate::{
+    batch::{key_info::KeyInfo, GroveDbOp, Op},
+    reference_path::path_from_reference_path_type,
+    Element, Error, GroveDb, TransactionArg,
+};
+
+/// Operation of a batch rejected by [`GroveDb::validate_batch`]
+#[derive(Debug)]
+pub struct InvalidOp {
+    /// Index of the operation within the batch
+    pub index: usize,
+    /// Why applying the operation would fail or be invalid
+    pub error: Error,
+}
+
+/// What the operations of a batch do to an element
+enum BatchEffect<'a> {
+    Written(&'a Element),
+    Deleted,
+}
+
+fn known_qualified_path(op: &GroveDbOp) -> Result<Vec<Vec<u8>>, Error> {
+    op.path
+        .iterator()
+        .chain(std::iter::once(&op.key))
+        .map(|key| match key {
+            KeyInfo::KnownKey(key) => Ok(key.clone()),
+            KeyInfo::MaxKeySize { .. } => Err(Error::InvalidInput(
+                "batch validation requires operations with known keys",
+            )),
+        })
+        .collect()
+}
+
+fn is_delete(op: &Op) -> bool {
+    matches!(op, Op::Delete | Op::DeleteTree | Op::DeleteSumTree)
+}
+
+/// Effect of the batch on the element at `qualified_path`, `None` if the batch
+/// leaves it as stored
+fn batch_effect<'a>(
+    ops: &'a [GroveDbOp],
+    qualified_paths: &[Option<Vec<Vec<u8>>>],
+    qualified_path: &[Vec<u8>],
+) -> Option<BatchEffect<'a>> {
+    let mut effect = None;
+    for (op, op_path) in ops.iter().zip(qualified_paths) {
+        let Some(op_path) = op_path else {
+            continue;
+        };
+        if is_delete(&op.op)
+            && op_path.len() < qualified_path.len()
+            && qualified_path.starts_with(op_path)
+        {
+            return Some(BatchEffect::Deleted);
+        }
+        if op_path.as_slice() == qualified_path {
+            match &op.op {
+                Op::Insert { element }
+                | Op::Replace { element }
+                | Op::Patch { element, .. }
+                | Op::ReplaceExpecting { element, .. } => {
+                    effect = Some(BatchEffect::Written(element))
+                }
+                op if is_delete(op) => effect = Some(BatchEffect::Deleted),
+                _ => {}
+            }
+        }
+    }
+    effect
+}
+
+impl GroveDb {
+    /// Checks a batch against the current state, including the writes of
+    /// `transaction`, without staging anything. Returns the operations that
+    /// would make applying it fail or leave the state inconsistent:
+    /// internal operations, several operations on the same path and key,
+    /// operations on subtrees that don't exist or are deleted by the batch,
+    /// trees replaced by elements of another type, deletions of missing
+    /// elements, and references whose target doesn't exist once the batch is
+    /// applied. An empty result doesn't guarantee the batch applies, as some
+    /// failures depend on the batch options.
+    pub fn validate_batch(
+        &self,
+        ops: &[GroveDbOp],
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<InvalidOp>, Error> {
+        let mut cost = OperationCost::default();
+        let qualified_paths: Vec<Option<Vec<Vec<u8>>>> =
+            ops.iter().map(|op| known_qualified_path(op).ok()).collect();
+
+        let mut invalid_ops = Vec::new();
+        for index in 0..ops.len() {
+            let checked = cost_return_on_error!(
+                &mut cost,
+                self.validate_batch_op(ops, &qualified_paths, index, transaction)
+            );
+            if let Err(error) = checked {
+                invalid_ops.push(InvalidOp { index, error });
+            }
+        }
+        Ok(invalid_ops).wrap_with_cost(cost)
+    }
+
+    /// Validates a single operation of a batch, the outer error being a
+    /// storage failure
+    fn validate_batch_op(
+        &self,
+        ops: &[GroveDbOp],
+        qualified_paths: &[Option<Vec<Vec<u8>>>],
+        index: usize,
+        transaction: TransactionArg,
+    ) -> CostResult<Result<(), Error>, Error> {
+        let mut cost = OperationCost::default();
+        let op = &ops[index];
+
+        if matches!(
+            op.op,
+            Op::ReplaceTreeRootKey { .. } | Op::InsertTreeWithRootHash { .. }
+        ) {
+            return Ok(Err(Error::InvalidBatchOperation(
+                "replace and insert tree hash are internal operations only",
+            )))
+            .wrap_with_cost(cost);
+        }
+        let qualified_path = match known_qualified_path(op) {
+            Ok(qualified_path) => qualified_path,
+            Err(e) => return Ok(Err(e)).wrap_with_cost(cost),
+        };
+        if qualified_paths
+            .iter()
+            .enumerate()
+            .any(|(i, path)| i != index && path.as_ref() == Some(&qualified_path))
+        {
+            return Ok(Err(Error::InvalidBatchOperation(
+                "another operation of the batch is on the same path and key",
+            )))
+            .wrap_with_cost(cost);
+        }
+        let (key, path) = qualified_path
+            .split_last()
+            .expect("qualified paths end with the key");
+
+        if !path.is_empty() {
+            let subtree = cost_return_on_error!(
+                &mut cost,
+                self.element_after_batch(ops, qualified_paths, path, transaction)
+            );
+            if !matches!(subtree, Some(element) if element.is_tree()) {
+                return Ok(Err(Error::InvalidParentLayerPath(format!(
+                    "subtree {:?} doesn't exist once the batch is applied",
+                    DebugByteVectors(path.to_vec())
+                ))))
+                .wrap_with_cost(cost);
+            }
+        }
+
+        let existing = cost_return_on_error!(
+            &mut cost,
+            self.get_raw_optional(path.into(), key, transaction)
+        );
+        let checked = match &op.op {
+            Op::Insert { element }
+            | Op::Replace { element }
+            | Op::Patch { element, .. }
+            | Op::ReplaceExpecting { element, .. } => {
+                if matches!(&existing, Some(e) if e.is_tree() && e.kind() != element.kind()) {
+                    Err(Error::WrongElementType(
+                        "a tree can't be replaced by an element of another type",
+                    ))
+                } else if let Element::Reference(reference_path_type, ..)
+                | Element::BidirectionalReference(reference_path_type, ..) = element
+                {
+                    let target = match path_from_reference_path_type(
+                        reference_path_type.clone(),
+                        path,
+                        Some(key.as_slice()),
+                    ) {
+                        Ok(target) => target,
+                        Err(e) => return Ok(Err(e)).wrap_with_cost(cost),
+                    };
+                    let target_element = cost_return_on_error!(
+                        &mut cost,
+                        self.element_after_batch(ops, qualified_paths, &target, transaction)
+                    );
+                    if target_element.is_some() {
+                        Ok(())
+                    } else {
+                        Err(Error::MissingReference(format!(
+                            "reference target {:?} doesn't exist once the batch is applied",
+                            DebugByteVectors(target)
+                        )))
+                    }
+                } else {
+                    Ok(())
+                }
+            }
+            Op::Delete | Op::DeleteTree | Op::DeleteSumTree | Op::UpdateFlags { .. }
+                if existing.is_none() =>
+            {
+                Err(Error::PathKeyNotFound(format!(
+                    "element {} to change doesn't exist",
+                    hex::encode(key)
+                )))
+            }
+            Op::DeleteTree | Op::DeleteSumTree if !matches!(&existing, Some(e) if e.is_tree()) => {
+                Err(Error::WrongElementType(
+                    "tree deletion of an element that is not a tree",
+                ))
+            }
+            _ => Ok(()),
+        };
+        Ok(checked).wrap_with_cost(cost)
+    }
+
+    /// The element at `qualified_path` once the batch is applied
+    fn element_after_batch(
+        &self,
+        ops: &[GroveDbOp],
+        qualified_paths: &[Option<Vec<Vec<u8>>>],
+        qualified_path: &[Vec<u8>],
+        transaction: TransactionArg,
+    ) -> CostResult<Option<Element>, Error> {
+        match batch_effect(ops, qualified_paths, qualified_path) {
+            Some(BatchEffect::Written(element)) => {
+                Ok(Some(element.clone())).wrap_with_cost(OperationCost::default())
+            }
+            Some(BatchEffect::Deleted) => Ok(None).wrap_with_cost(OperationCost::default()),
+            None => match qualified_path.split_last() {
+                None => Ok(None).wrap_with_cost(OperationCost::default()),
+                Some((key, path)) => self.get_raw_optional(path.into(), key, transaction),
+            },
+        }
+    }
+}
@@ -0,0 +1,157 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Wire format of batches
+//!
+//! A batch is encoded as a version byte followed by its operations, encoded
+//! with bincode using varint integers, so that the same operations always
+//! encode to the same bytes. Enum variants are encoded by index, which is why
+//! operations, elements and reference path types only ever get new variants
+//! appended.
+
+use bincode::Options;
+
+use crate::{batch::GroveDbOp, Error};
+
+/// Version of the batch wire format written by this version of GroveDB
+pub const BATCH_FORMAT_VERSION: u8 = 1;
+
+fn bincode_options() -> impl Options {
+    bincode::DefaultOptions::default()
+        .with_varint_encoding()
+        .reject_trailing_bytes()
+}
+
+impl GroveDbOp {
+    /// Encodes operations in the batch wire format, to be transmitted to
+    /// other nodes and decoded with [`GroveDbOp::decode_batch`]
+    pub fn encode_batch(ops: &[GroveDbOp]) -> Result<Vec<u8>, Error> {
+        let mut bytes = vec![BATCH_FORMAT_VERSION];
+        bincode_options()
+            .serialize_into(&mut bytes, ops)
+            .map_err(|_| Error::CorruptedData("unable to encode batch".to_owned()))?;
+        Ok(bytes)
+    }
+
+    /// Decodes operations encoded with [`GroveDbOp::encode_batch`]
+    pub fn decode_batch(bytes: &[u8]) -> Result<Vec<GroveDbOp>, Error> {
+        let Some((&version, encoded_ops)) = bytes.split_first() else {
+            return Err(Error::CorruptedData("empty batch encoding".to_owned()));
+        };
+        if version != BATCH_FORMAT_VERSION {
+            return Err(Error::UnsupportedSchemaVersion(format!(
+                "batch has version {}, this version of GroveDB supports version {}",
+                version, BATCH_FORMAT_VERSION
+            )));
+        }
+        bincode_options()
+            .deserialize(encoded_ops)
+            .map_err(|_| Error::CorruptedData("unable to decode batch".to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        batch::{key_info::KeyInfo, KeyInfoPath},
+        reference_path::ReferencePathType,
+        Element,
+    };
+
+    #[test]
+    fn test_batch_round_trip() {
+        let ops = vec![
+            GroveDbOp::insert_op(
+                vec![b"leaf".to_vec()],
+                b"item".to_vec(),
+                Element::new_item_with_flags(b"value".to_vec(), Some(vec![1, 2])),
+            ),
+            GroveDbOp::insert_op(
+                vec![b"leaf".to_vec()],
+                b"ref".to_vec(),
+                Element::new_reference(ReferencePathType::CousinWithKeyReference(
+                    1,
+                    b"other".to_vec(),
+                    b"item".to_vec(),
+                )),
+            ),
+            GroveDbOp::replace_expecting_op(
+                vec![b"leaf".to_vec()],
+                b"tree".to_vec(),
+                Element::empty_sum_tree(),
+                [7; 32],
+            ),
+            GroveDbOp::update_flags_op(vec![], b"leaf".to_vec(), None),
+            GroveDbOp::delete_tree_op(vec![b"leaf".to_vec()], b"old".to_vec(), true),
+            GroveDbOp::delete_estimated_op(
+                KeyInfoPath(vec![KeyInfo::KnownKey(b"leaf".to_vec())]),
+                KeyInfo::MaxKeySize {
+                    unique_id: b"id".to_vec(),
+                    max_size: 32,
+                },
+            ),
+        ];
+
+        let bytes = GroveDbOp::encode_batch(&ops).expect("expected to encode batch");
+        assert_eq!(bytes[0], BATCH_FORMAT_VERSION);
+        assert_eq!(
+            GroveDbOp::encode_batch(&ops).expect("expected to encode batch"),
+            bytes
+        );
+        assert_eq!(
+            GroveDbOp::decode_batch(&bytes).expect("expected to decode batch"),
+            ops
+        );
+    }
+
+    #[test]
+    fn test_decode_batch_rejects_invalid_encodings() {
+        let mut bytes = GroveDbOp::encode_batch(&[GroveDbOp::delete_op(
+            vec![b"leaf".to_vec()],
+            b"key".to_vec(),
+        )])
+        .expect("expected to encode batch");
+
+        assert!(matches!(
+            GroveDbOp::decode_batch(&[]),
+            Err(Error::CorruptedData(_))
+        ));
+        bytes.push(0);
+        assert!(matches!(
+            GroveDbOp::decode_batch(&bytes),
+            Err(Error::CorruptedData(_))
+        ));
+        bytes.pop();
+        bytes[0] = BATCH_FORMAT_VERSION + 1;
+        assert!(matches!(
+            GroveDbOp::decode_batch(&bytes),
+            Err(Error::UnsupportedSchemaVersion(_))
+        ));
+    }
+}
@@ -0,0 +1,48 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Writes the proof conformance corpus to the file given as argument, or to
+//! stdout if there is none
+
+use std::{fs::File, io, io::Write};
+
+use grovedb::conformance::ConformanceSuite;
+use tempfile::TempDir;
+
+fn main() {
+    let tmp_dir = TempDir::new().expect("cannot create temporary directory");
+    let suite = ConformanceSuite::generate(tmp_dir.path()).expect("cannot generate suite");
+
+    let writer: Box<dyn Write> = match std::env::args().nth(1) {
+        Some(path) => Box::new(File::create(path).expect("cannot create corpus file")),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut writer = io::BufWriter::new(writer);
+    suite.write_to(&mut writer).expect("cannot write corpus");
+    writer.flush().expect("cannot write corpus");
+}
@@ -0,0 +1,289 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Cost model calibration
+//!
+//! Runs a standardized workload against a RocksDB backed GroveDB and relates
+//! the measured time to the costs reported for it, giving the time taken by
+//! one unit of each cost: a node hash call, a seek and a written byte.
+//!
+//! Every workload does more than the unit it calibrates, so units are
+//! derived in order: hash calls from hashing alone, seeks from reads, which
+//! don't hash, and written bytes from insertions, after removing the time
+//! their seeks and hash calls account for.
+
+use std::{
+    io,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use grovedb_costs::OperationCost;
+use grovedb_merk::tree::value_hash;
+
+use crate::{Element, Error, GroveDb};
+
+const CALIBRATION_LEAF: &[u8] = b"calibration";
+
+/// Parameters of the calibration workload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CalibrationWorkload {
+    /// Number of values hashed to calibrate hash calls
+    pub hashes: u32,
+    /// Number of items inserted, then read
+    pub items: u32,
+    /// Size of the inserted values
+    pub value_size: usize,
+}
+
+impl Default for CalibrationWorkload {
+    fn default() -> Self {
+        CalibrationWorkload {
+            hashes: 100_000,
+            items: 10_000,
+            value_size: 256,
+        }
+    }
+}
+
+/// Time and costs of a part of the workload
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Measurement {
+    /// Number of operations run
+    pub operations: u64,
+    /// Time taken by the operations
+    pub elapsed: Duration,
+    /// Seeks reported
+    pub seeks: u64,
+    /// Node hash calls reported
+    pub hash_node_calls: u64,
+    /// Bytes reported as added or replaced
+    pub written_bytes: u64,
+    /// Bytes reported as loaded
+    pub loaded_bytes: u64,
+}
+
+impl Measurement {
+    /// Adds the costs of an operation
+    fn add(&mut self, cost: &OperationCost) {
+        self.operations += 1;
+        self.seeks += cost.seek_count as u64;
+        self.hash_node_calls += cost.hash_node_calls as u64;
+        self.written_bytes +=
+            cost.storage_cost.added_bytes as u64 + cost.storage_cost.replaced_bytes as u64;
+        self.loaded_bytes += cost.storage_loaded_bytes as u64;
+    }
+}
+
+/// Measured time per cost unit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CalibrationTable {
+    /// Workload the table was measured with
+    pub workload: CalibrationWorkload,
+    /// Hashing measurement
+    pub hashing: Measurement,
+    /// Insertion measurement
+    pub insertion: Measurement,
+    /// Read measurement
+    pub read: Measurement,
+    /// Nanoseconds per node hash call
+    pub nanos_per_hash_node_call: f64,
+    /// Nanoseconds per seek
+    pub nanos_per_seek: f64,
+    /// Nanoseconds per written byte
+    pub nanos_per_written_byte: f64,
+}
+
+impl CalibrationTable {
+    /// Runs `workload` against a new database at the given path and derives
+    /// the time per cost unit from it
+    pub fn measure<P: AsRef<Path>>(
+        db_path: P,
+        workload: CalibrationWorkload,
+    ) -> Result<Self, Error> {
+        let hashing = measure_hashing(workload);
+        let nanos_per_hash_node_call =
+            nanos_per(hashing.elapsed.as_nanos() as f64, hashing.hash_node_calls);
+
+        let db = GroveDb::open(db_path)?;
+        db.insert(
+            &[] as &[&[u8]],
+            CALIBRATION_LEAF,
+            Element::empty_tree(),
+            None,
+            None,
+        )
+        .unwrap()?;
+        let insertion = measure_insertion(&db, workload)?;
+        let read = measure_read(&db, workload)?;
+
+        let nanos_per_seek = nanos_per(read.elapsed.as_nanos() as f64, read.seeks);
+        let accounted_nanos = insertion.seeks as f64 * nanos_per_seek
+            + insertion.hash_node_calls as f64 * nanos_per_hash_node_call;
+        let write_nanos = (insertion.elapsed.as_nanos() as f64 - accounted_nanos).max(0.0);
+        let nanos_per_written_byte = nanos_per(write_nanos, insertion.written_bytes);
+
+        Ok(CalibrationTable {
+            workload,
+            hashing,
+            insertion,
+            read,
+            nanos_per_hash_node_call,
+            nanos_per_seek,
+            nanos_per_written_byte,
+        })
+    }
+
+    /// Writes the table as text, one cost unit per line followed by the
+    /// measurements it was derived from
+    pub fn write_to<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "unit               nanoseconds")?;
+        writeln!(
+            writer,
+            "hash_node_call     {:.3}",
+            self.nanos_per_hash_node_call
+        )?;
+        writeln!(writer, "seek               {:.3}", self.nanos_per_seek)?;
+        writeln!(
+            writer,
+            "written_byte       {:.3}",
+            self.nanos_per_written_byte
+        )?;
+        writeln!(writer)?;
+        writeln!(
+            writer,
+            "workload  operations  elapsed_ns  seeks  hash_node_calls  written_bytes  loaded_bytes"
+        )?;
+        for (name, measurement) in [
+            ("hashing", &self.hashing),
+            ("insertion", &self.insertion),
+            ("read", &self.read),
+        ] {
+            writeln!(
+                writer,
+                "{name}  {}  {}  {}  {}  {}  {}",
+                measurement.operations,
+                measurement.elapsed.as_nanos(),
+                measurement.seeks,
+                measurement.hash_node_calls,
+                measurement.written_bytes,
+                measurement.loaded_bytes
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Nanoseconds per unit, or zero without units
+fn nanos_per(elapsed_nanos: f64, units: u64) -> f64 {
+    if units == 0 {
+        0.0
+    } else {
+        elapsed_nanos / units as f64
+    }
+}
+
+/// Hashes `workload.hashes` values of the workload value size
+fn measure_hashing(workload: CalibrationWorkload) -> Measurement {
+    let value = vec![0xab; workload.value_size];
+    let mut measurement = Measurement::default();
+    let start = Instant::now();
+    for _ in 0..workload.hashes {
+        let cost = value_hash(&value).cost;
+        measurement.add(&cost);
+    }
+    measurement.elapsed = start.elapsed();
+    measurement
+}
+
+/// Inserts `workload.items` items one at a time
+fn measure_insertion(db: &GroveDb, workload: CalibrationWorkload) -> Result<Measurement, Error> {
+    let mut measurement = Measurement::default();
+    let start = Instant::now();
+    for i in 0..workload.items {
+        let element = Element::new_item(vec![0xab; workload.value_size]);
+        let result = db.insert(
+            [CALIBRATION_LEAF].as_ref(),
+            &i.to_be_bytes(),
+            element,
+            None,
+            None,
+        );
+        measurement.add(&result.cost);
+        result.value?;
+    }
+    measurement.elapsed = start.elapsed();
+    Ok(measurement)
+}
+
+/// Reads back every inserted item
+fn measure_read(db: &GroveDb, workload: CalibrationWorkload) -> Result<Measurement, Error> {
+    let mut measurement = Measurement::default();
+    let start = Instant::now();
+    for i in 0..workload.items {
+        let result = db.get_raw([CALIBRATION_LEAF].as_ref().into(), &i.to_be_bytes(), None);
+        measurement.add(&result.cost);
+        result.value?;
+    }
+    measurement.elapsed = start.elapsed();
+    Ok(measurement)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_calibration_table_relates_time_to_costs() {
+        let tmp_dir = TempDir::new().unwrap();
+        let workload = CalibrationWorkload {
+            hashes: 100,
+            items: 50,
+            value_size: 64,
+        };
+        let table =
+            CalibrationTable::measure(tmp_dir.path(), workload).expect("expected to calibrate");
+
+        assert_eq!(table.hashing.operations, 100);
+        assert_eq!(table.insertion.operations, 50);
+        assert_eq!(table.read.operations, 50);
+        assert!(table.read.seeks > 0);
+        assert!(table.insertion.written_bytes > 0);
+        assert!(table.nanos_per_hash_node_call.is_finite());
+        assert!(table.nanos_per_seek.is_finite());
+        assert!(table.nanos_per_written_byte >= 0.0);
+
+        let mut output = Vec::new();
+        table
+            .write_to(&mut output)
+            .expect("expected to write table");
+        assert!(String::from_utf8(output).unwrap().starts_with("unit"));
+    }
+}
@@ -0,0 +1,503 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Proof conformance suite
+//!
+//! Generates a deterministic corpus of cases made of a database state, a path
+//! query, the proof produced for it and the expected verification result, to
+//! check wire compatibility of other implementations and future versions.
+//!
+//! The corpus is written as text, one record per line with byte strings hex
+//! encoded:
+//!
+//! ```text
+//! grovedb-proof-conformance 1
+//! insert <path> <key> <serialized element>
+//! state_root_hash <hash>
+//! case <name>
+//! query <path> <limit> <offset> <query>
+//! proof <proof>
+//! verified_root_hash <hash>
+//! result <path> <key> <value> <value hash>
+//! end
+//! ```
+//!
+//! Paths are written as `[segment,segment]`, absent limits and offsets as
+//! `none`, and a failed verification as `error <message>` instead of the
+//! `verified_root_hash` and `result` records.
+
+use std::{io, path::Path};
+
+use grovedb_merk::proofs::{query::SubqueryBranch, Query};
+
+use crate::{
+    operations::proof::util::ProvedPathKeyValues, query_result_type::PathKeyElementTrio,
+    reference_path::ReferencePathType, Element, Error, GroveDb, PathQuery, QueryItem, SizedQuery,
+};
+
+/// Version of the corpus format
+pub const CONFORMANCE_CORPUS_VERSION: u32 = 1;
+
+const ITEMS_LEAF: &[u8] = b"items";
+const SUMS_LEAF: &[u8] = b"sums";
+const COUNTS_LEAF: &[u8] = b"counts";
+const NESTED_LEAF: &[u8] = b"nested";
+
+/// A single conformance case
+pub struct ConformanceCase {
+    /// Unique name of the case
+    pub name: String,
+    /// Query the proof is generated for
+    pub path_query: PathQuery,
+    /// Proof bytes
+    pub proof: Vec<u8>,
+    /// Root hash and proved values returned by verification
+    pub verification: Result<([u8; 32], ProvedPathKeyValues), Error>,
+}
+
+/// Conformance cases sharing a database state
+pub struct ConformanceSuite {
+    /// Insertions building the database state, applied in order
+    pub state: Vec<PathKeyElementTrio>,
+    /// Root hash of the database state
+    pub root_hash: [u8; 32],
+    /// Cases, proved against the database state
+    pub cases: Vec<ConformanceCase>,
+}
+
+impl ConformanceSuite {
+    /// Builds the database state in a new database at the given path and
+    /// generates the proofs of all cases. The result only depends on the
+    /// proof format, so generating it twice gives the same suite.
+    pub fn generate<P: AsRef<Path>>(db_path: P) -> Result<Self, Error> {
+        let db = GroveDb::open(db_path)?;
+
+        let state = conformance_state();
+        for (path, key, element) in state.iter() {
+            let path: Vec<&[u8]> = path.iter().map(|segment| segment.as_slice()).collect();
+            db.insert(path.as_slice(), key, element.clone(), None, None)
+                .unwrap()?;
+        }
+        let root_hash = db.root_hash(None).unwrap()?;
+
+        let cases = conformance_queries()
+            .into_iter()
+            .map(|(name, path_query)| {
+                let proof = db.prove_query(&path_query).unwrap()?;
+                let verification = GroveDb::verify_query_raw(&proof, &path_query);
+                Ok(ConformanceCase {
+                    name,
+                    path_query,
+                    proof,
+                    verification,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(ConformanceSuite {
+            state,
+            root_hash,
+            cases,
+        })
+    }
+
+    /// Writes the corpus in the text format described in the module
+    /// documentation
+    pub fn write_to<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(
+            writer,
+            "grovedb-proof-conformance {CONFORMANCE_CORPUS_VERSION}"
+        )?;
+        for (path, key, element) in self.state.iter() {
+            let element = element
+                .serialize()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+            writeln!(
+                writer,
+                "insert {} {} {}",
+                encode_path(path),
+                hex::encode(key),
+                hex::encode(element)
+            )?;
+        }
+        writeln!(writer, "state_root_hash {}", hex::encode(self.root_hash))?;
+
+        for case in self.cases.iter() {
+            writeln!(writer, "case {}", case.name)?;
+            writeln!(
+                writer,
+                "query {} {} {} {}",
+                encode_path(&case.path_query.path),
+                encode_optional(case.path_query.query.limit),
+                encode_optional(case.path_query.query.offset),
+                encode_query(&case.path_query.query.query)
+            )?;
+            writeln!(writer, "proof {}", hex::encode(&case.proof))?;
+            match &case.verification {
+                Ok((root_hash, result_set)) => {
+                    writeln!(writer, "verified_root_hash {}", hex::encode(root_hash))?;
+                    for proved in result_set.iter() {
+                        writeln!(
+                            writer,
+                            "result {} {} {} {}",
+                            encode_path(&proved.path),
+                            hex::encode(&proved.key),
+                            hex::encode(&proved.value),
+                            hex::encode(proved.proof)
+                        )?;
+                    }
+                }
+                Err(e) => writeln!(writer, "error {e}")?,
+            }
+            writeln!(writer, "end")?;
+        }
+        Ok(())
+    }
+}
+
+/// One subtree of every element type, with items addressed by single byte
+/// keys so query bounds can fall inside, between and outside of them
+fn conformance_state() -> Vec<PathKeyElementTrio> {
+    let mut state = vec![
+        (vec![], ITEMS_LEAF.to_vec(), Element::empty_tree()),
+        (vec![], SUMS_LEAF.to_vec(), Element::empty_sum_tree()),
+        (vec![], COUNTS_LEAF.to_vec(), Element::empty_count_tree()),
+        (vec![], NESTED_LEAF.to_vec(), Element::empty_tree()),
+    ];
+    for i in (1u8..=15).step_by(2) {
+        state.push((
+            vec![ITEMS_LEAF.to_vec()],
+            vec![i],
+            Element::new_item(vec![b'v', i]),
+        ));
+    }
+    state.push((
+        vec![ITEMS_LEAF.to_vec()],
+        vec![16],
+        Element::new_reference(ReferencePathType::SiblingReference(vec![1])),
+    ));
+    for (i, value) in [(1u8, 5i64), (2, -3), (3, 40)] {
+        state.push((
+            vec![SUMS_LEAF.to_vec()],
+            vec![i],
+            Element::new_sum_item(value),
+        ));
+    }
+    state.push((
+        vec![SUMS_LEAF.to_vec()],
+        vec![4],
+        Element::new_item(b"not summed".to_vec()),
+    ));
+    state.push((
+        vec![ITEMS_LEAF.to_vec()],
+        vec![17],
+        Element::new_reference(ReferencePathType::AbsolutePathReference(vec![
+            SUMS_LEAF.to_vec(),
+            vec![2],
+        ])),
+    ));
+    for i in 1u8..=3 {
+        state.push((
+            vec![COUNTS_LEAF.to_vec()],
+            vec![i],
+            Element::new_item(vec![b'c', i]),
+        ));
+    }
+    for subtree in [b"a", b"b", b"c"] {
+        state.push((
+            vec![NESTED_LEAF.to_vec()],
+            subtree.to_vec(),
+            Element::empty_tree(),
+        ));
+        for i in 1u8..=3 {
+            state.push((
+                vec![NESTED_LEAF.to_vec(), subtree.to_vec()],
+                vec![i],
+                Element::new_item(vec![subtree[0], i]),
+            ));
+        }
+    }
+    state
+}
+
+/// Every query item variant in both directions with and without a limit,
+/// followed by queries over each element type, subqueries and absent data
+fn conformance_queries() -> Vec<(String, PathQuery)> {
+    let items: [(&str, QueryItem); 10] = [
+        ("key", QueryItem::Key(vec![5])),
+        ("range", QueryItem::Range(vec![3]..vec![11])),
+        (
+            "range_inclusive",
+            QueryItem::RangeInclusive(vec![3]..=vec![11]),
+        ),
+        ("range_full", QueryItem::RangeFull(..)),
+        ("range_from", QueryItem::RangeFrom(vec![6]..)),
+        ("range_to", QueryItem::RangeTo(..vec![9])),
+        (
+            "range_to_inclusive",
+            QueryItem::RangeToInclusive(..=vec![9]),
+        ),
+        ("range_after", QueryItem::RangeAfter(vec![5]..)),
+        ("range_after_to", QueryItem::RangeAfterTo(vec![5]..vec![13])),
+        (
+            "range_after_to_inclusive",
+            QueryItem::RangeAfterToInclusive(vec![5]..=vec![13]),
+        ),
+    ];
+
+    let mut queries = Vec::new();
+    for (item_name, item) in items.iter() {
+        for left_to_right in [true, false] {
+            for limit in [None, Some(2)] {
+                let name = format!(
+                    "items_{item_name}_{}_{}",
+                    if left_to_right { "asc" } else { "desc" },
+                    limit.map_or("unlimited".to_owned(), |l| format!("limit_{l}"))
+                );
+                let query =
+                    Query::new_single_query_item_with_direction(item.clone(), left_to_right);
+                queries.push((
+                    name,
+                    PathQuery::new(
+                        vec![ITEMS_LEAF.to_vec()],
+                        SizedQuery::new(query, limit, None),
+                    ),
+                ));
+            }
+        }
+    }
+
+    let mut multiple_items = Query::new();
+    multiple_items.insert_key(vec![1]);
+    multiple_items.insert_range(vec![6]..vec![10]);
+    multiple_items.insert_range_after(vec![14]..);
+    queries.push((
+        "items_multiple_items".to_owned(),
+        PathQuery::new_unsized(vec![ITEMS_LEAF.to_vec()], multiple_items),
+    ));
+    queries.push((
+        "items_absent_key".to_owned(),
+        PathQuery::new_single_key(vec![ITEMS_LEAF.to_vec()], vec![4]),
+    ));
+    queries.push((
+        "items_references".to_owned(),
+        PathQuery::new_single_query_item(
+            vec![ITEMS_LEAF.to_vec()],
+            QueryItem::RangeFrom(vec![16]..),
+        ),
+    ));
+    queries.push((
+        "root_trees".to_owned(),
+        PathQuery::new_single_query_item(vec![], QueryItem::RangeFull(..)),
+    ));
+    queries.push((
+        "sum_tree_items".to_owned(),
+        PathQuery::new_single_query_item(vec![SUMS_LEAF.to_vec()], QueryItem::RangeFull(..)),
+    ));
+    queries.push((
+        "count_tree_items".to_owned(),
+        PathQuery::new_single_query_item(vec![COUNTS_LEAF.to_vec()], QueryItem::RangeFull(..)),
+    ));
+    queries.push((
+        "absent_path".to_owned(),
+        PathQuery::new_single_query_item(
+            vec![NESTED_LEAF.to_vec(), b"z".to_vec()],
+            QueryItem::RangeFull(..),
+        ),
+    ));
+
+    for left_to_right in [true, false] {
+        for limit in [None, Some(4)] {
+            let mut query = Query::new_with_direction(left_to_right);
+            query.insert_all();
+            query.set_subquery(Query::new_single_query_item_with_direction(
+                QueryItem::RangeTo(..vec![3]),
+                left_to_right,
+            ));
+            let name = format!(
+                "nested_subquery_{}_{}",
+                if left_to_right { "asc" } else { "desc" },
+                limit.map_or("unlimited".to_owned(), |l| format!("limit_{l}"))
+            );
+            queries.push((
+                name,
+                PathQuery::new(
+                    vec![NESTED_LEAF.to_vec()],
+                    SizedQuery::new(query, limit, None),
+                ),
+            ));
+        }
+    }
+
+    let mut subquery_key = Query::new();
+    subquery_key.insert_range_inclusive(b"a".to_vec()..=b"b".to_vec());
+    subquery_key.set_subquery_key(vec![2]);
+    queries.push((
+        "nested_subquery_key".to_owned(),
+        PathQuery::new_unsized(vec![NESTED_LEAF.to_vec()], subquery_key),
+    ));
+
+    let mut conditional = Query::new();
+    conditional.insert_all();
+    conditional.add_conditional_subquery(
+        QueryItem::Key(b"b".to_vec()),
+        None,
+        Some(Query::new_single_key(vec![3])),
+    );
+    queries.push((
+        "nested_conditional_subquery".to_owned(),
+        PathQuery::new_unsized(vec![NESTED_LEAF.to_vec()], conditional),
+    ));
+
+    queries
+}
+
+fn encode_path(path: &[Vec<u8>]) -> String {
+    let segments: Vec<String> = path.iter().map(hex::encode).collect();
+    format!("[{}]", segments.join(","))
+}
+
+fn encode_optional(value: Option<u16>) -> String {
+    value.map_or("none".to_owned(), |v| v.to_string())
+}
+
+fn encode_query_item(item: &QueryItem) -> String {
+    match item {
+        QueryItem::Key(key) => format!("key({})", hex::encode(key)),
+        QueryItem::Range(range) => format!(
+            "range({}..{})",
+            hex::encode(&range.start),
+            hex::encode(&range.end)
+        ),
+        QueryItem::RangeInclusive(range) => format!(
+            "range_inclusive({}..={})",
+            hex::encode(range.start()),
+            hex::encode(range.end())
+        ),
+        QueryItem::RangeFull(..) => "range_full(..)".to_owned(),
+        QueryItem::RangeFrom(range) => format!("range_from({}..)", hex::encode(&range.start)),
+        QueryItem::RangeTo(range) => format!("range_to(..{})", hex::encode(&range.end)),
+        QueryItem::RangeToInclusive(range) => {
+            format!("range_to_inclusive(..={})", hex::encode(&range.end))
+        }
+        QueryItem::RangeAfter(range) => format!("range_after({}<..)", hex::encode(&range.start)),
+        QueryItem::RangeAfterTo(range) => format!(
+            "range_after_to({}<..{})",
+            hex::encode(&range.start),
+            hex::encode(&range.end)
+        ),
+        QueryItem::RangeAfterToInclusive(range) => format!(
+            "range_after_to_inclusive({}<..={})",
+            hex::encode(range.start()),
+            hex::encode(range.end())
+        ),
+    }
+}
+
+fn encode_subquery_branch(branch: &SubqueryBranch) -> String {
+    format!(
+        "subquery_path={};subquery={}",
+        branch
+            .subquery_path
+            .as_ref()
+            .map_or("none".to_owned(), |path| encode_path(path)),
+        branch
+            .subquery
+            .as_ref()
+            .map_or("none".to_owned(), |query| encode_query(query))
+    )
+}
+
+/// Encodes a query as
+/// `{<asc|desc>;items=<item>,..;subquery_path=..;subquery=..;conditional=..}`
+fn encode_query(query: &Query) -> String {
+    let items: Vec<String> = query.items.iter().map(encode_query_item).collect();
+    let conditional: Vec<String> = query
+        .conditional_subquery_branches
+        .iter()
+        .flatten()
+        .map(|(item, branch)| {
+            format!(
+                "{}=>({})",
+                encode_query_item(item),
+                encode_subquery_branch(branch)
+            )
+        })
+        .collect();
+    format!(
+        "{{{};items={};{};conditional={}}}",
+        if query.left_to_right { "asc" } else { "desc" },
+        items.join(","),
+        encode_subquery_branch(&query.default_subquery_branch),
+        conditional.join(",")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_conformance_suite_is_deterministic_and_verifies() {
+        let tmp_dir = TempDir::new().unwrap();
+        let suite = ConformanceSuite::generate(tmp_dir.path()).expect("expected to generate suite");
+        let mut corpus = Vec::new();
+        suite
+            .write_to(&mut corpus)
+            .expect("expected to write corpus");
+
+        let other_tmp_dir = TempDir::new().unwrap();
+        let other_suite =
+            ConformanceSuite::generate(other_tmp_dir.path()).expect("expected to generate suite");
+        let mut other_corpus = Vec::new();
+        other_suite
+            .write_to(&mut other_corpus)
+            .expect("expected to write corpus");
+        assert_eq!(corpus, other_corpus);
+
+        assert_eq!(suite.cases.len(), 53);
+        for case in suite.cases.iter() {
+            let (root_hash, _) = case
+                .verification
+                .as_ref()
+                .unwrap_or_else(|e| panic!("case {} failed to verify: {e}", case.name));
+            assert_eq!(root_hash, &suite.root_hash, "case {}", case.name);
+        }
+
+        let items_key = suite
+            .cases
+            .iter()
+            .find(|case| case.name == "items_key_asc_unlimited")
+            .expect("expected key case");
+        let (_, result_set) = items_key.verification.as_ref().unwrap();
+        assert_eq!(result_set.len(), 1);
+        assert_eq!(result_set[0].key, vec![5]);
+    }
+}
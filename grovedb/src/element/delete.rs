@@ -29,20 +29,52 @@
 //! Delete
 //! Implements functions in Element for deleting
 
-#[cfg(feature = "full")]
 use grovedb_costs::{storage_cost::removal::StorageRemovedBytes, CostResult, CostsExt};
-#[cfg(feature = "full")]
 use grovedb_merk::{BatchEntry, Error as MerkError, Merk, MerkOptions, Op};
-#[cfg(feature = "full")]
 use grovedb_storage::StorageContext;
 
-#[cfg(feature = "full")]
 use crate::{Element, Error};
 
-impl Element {
-    #[cfg(feature = "full")]
+/// Deletions of elements from a Merk, `Element` being defined in
+/// `grovedb-verify`
+pub trait ElementDeleteExt {
     /// Delete an element from Merk under a key
-    pub fn delete<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+    fn delete<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+        merk: &mut Merk<S>,
+        key: K,
+        merk_options: Option<MerkOptions>,
+        is_layered: bool,
+        is_sum: bool,
+    ) -> CostResult<(), Error>;
+
+    /// Delete an element from Merk under a key
+    fn delete_with_sectioned_removal_bytes<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+        merk: &mut Merk<S>,
+        key: K,
+        merk_options: Option<MerkOptions>,
+        is_layered: bool,
+        is_in_sum_tree: bool,
+        sectioned_removal: &mut impl FnMut(
+            &Vec<u8>,
+            u32,
+            u32,
+        ) -> Result<
+            (StorageRemovedBytes, StorageRemovedBytes),
+            MerkError,
+        >,
+    ) -> CostResult<(), Error>;
+
+    /// Delete an element from Merk under a key to batch operations
+    fn delete_into_batch_operations<K: AsRef<[u8]>>(
+        key: K,
+        is_layered: bool,
+        is_sum: bool,
+        batch_operations: &mut Vec<BatchEntry<K>>,
+    ) -> CostResult<(), Error>;
+}
+
+impl ElementDeleteExt for Element {
+    fn delete<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
         merk: &mut Merk<S>,
         key: K,
         merk_options: Option<MerkOptions>,
@@ -61,12 +93,10 @@ impl Element {
             Self::specialized_costs_for_key_value(key, value, uses_sum_nodes)
                 .map_err(|e| MerkError::ClientCorruptionError(e.to_string()))
         })
-        .map_err(|e| Error::CorruptedData(e.to_string()))
+        .map_err(Error::from_merk_apply_error)
     }
 
-    #[cfg(feature = "full")]
-    /// Delete an element from Merk under a key
-    pub fn delete_with_sectioned_removal_bytes<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+    fn delete_with_sectioned_removal_bytes<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
         merk: &mut Merk<S>,
         key: K,
         merk_options: Option<MerkOptions>,
@@ -100,12 +130,10 @@ impl Element {
             &mut |_costs, _old_value, _value| Ok((false, None)),
             sectioned_removal,
         )
-        .map_err(|e| Error::CorruptedData(e.to_string()))
+        .map_err(Error::from_merk_apply_error)
     }
 
-    #[cfg(feature = "full")]
-    /// Delete an element from Merk under a key to batch operations
-    pub fn delete_into_batch_operations<K: AsRef<[u8]>>(
+    fn delete_into_batch_operations<K: AsRef<[u8]>>(
         key: K,
         is_layered: bool,
         is_sum: bool,
@@ -29,21 +29,26 @@
 //! Exists
 //! Implements in Element functions for checking if stuff exists
 
-#[cfg(feature = "full")]
 use grovedb_costs::CostResult;
-#[cfg(feature = "full")]
 use grovedb_merk::Merk;
-#[cfg(feature = "full")]
 use grovedb_storage::StorageContext;
 
-#[cfg(feature = "full")]
 use crate::{Element, Error};
 
-impl Element {
-    #[cfg(feature = "full")]
+/// Existence checks of elements in a Merk, `Element` being defined in
+/// `grovedb-verify`
+pub trait ElementExistsExt {
     /// Helper function that returns whether an element at the key for the
     /// element already exists.
-    pub fn element_at_key_already_exists<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+    fn element_at_key_already_exists<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+        &self,
+        merk: &mut Merk<S>,
+        key: K,
+    ) -> CostResult<bool, Error>;
+}
+
+impl ElementExistsExt for Element {
+    fn element_at_key_already_exists<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
         &self,
         merk: &mut Merk<S>,
         key: K,
@@ -29,28 +29,85 @@
 //! Get
 //! Implements functions in Element for getting
 
-#[cfg(feature = "full")]
 use grovedb_costs::{
     cost_return_on_error, cost_return_on_error_no_add, CostResult, CostsExt, OperationCost,
 };
-use grovedb_merk::tree::kv::KV;
-#[cfg(feature = "full")]
-use grovedb_merk::Merk;
-#[cfg(feature = "full")]
-use grovedb_merk::{ed::Decode, tree::TreeInner};
-#[cfg(feature = "full")]
+use grovedb_merk::{
+    ed::Decode,
+    tree::{kv::KV, TreeInner},
+    Merk,
+};
 use grovedb_storage::StorageContext;
 use integer_encoding::VarInt;
 
-use crate::element::{SUM_ITEM_COST_SIZE, SUM_TREE_COST_SIZE, TREE_COST_SIZE};
-#[cfg(feature = "full")]
-use crate::{Element, Error, Hash};
+use crate::{
+    element::{
+        AGGREGATE_TREE_COST_SIZE, BIG_SUM_TREE_COST_SIZE, COUNT_TREE_COST_SIZE, SUM_ITEM_COST_SIZE,
+        SUM_TREE_COST_SIZE, TREE_COST_SIZE,
+    },
+    Element, Error, Hash,
+};
+
+/// Reads of elements from a Merk, `Element` being defined in `grovedb-verify`
+pub trait ElementGetExt {
+    /// Get an element from Merk under a key; path should be resolved and proper
+    /// Merk should be loaded by this moment
+    fn get<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+        merk: &Merk<S>,
+        key: K,
+        allow_cache: bool,
+    ) -> CostResult<Element, Error>;
+
+    /// Get an element from Merk under a key; path should be resolved and proper
+    /// Merk should be loaded by this moment
+    fn get_optional<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+        merk: &Merk<S>,
+        key: K,
+        allow_cache: bool,
+    ) -> CostResult<Option<Element>, Error>;
+
+    /// Get the elements from Merk under keys, in the order of the keys with
+    /// `None` for the missing ones, looking them all up in storage at once;
+    /// path should be resolved and proper Merk should be loaded by this moment
+    fn get_many<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+        merk: &Merk<S>,
+        keys: &[K],
+    ) -> CostResult<Vec<Option<Element>>, Error>;
+
+    /// Get an element directly from storage under a key
+    /// Merk does not need to be loaded
+    /// Errors if element doesn't exist
+    fn get_from_storage<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+        storage: &S,
+        key: K,
+    ) -> CostResult<Element, Error>;
+
+    /// Get an element directly from storage under a key
+    /// Merk does not need to be loaded
+    fn get_optional_from_storage<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+        storage: &S,
+        key: K,
+    ) -> CostResult<Option<Element>, Error>;
 
-impl Element {
-    #[cfg(feature = "full")]
     /// Get an element from Merk under a key; path should be resolved and proper
     /// Merk should be loaded by this moment
-    pub fn get<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+    fn get_with_absolute_refs<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+        merk: &Merk<S>,
+        path: &[&[u8]],
+        key: K,
+        allow_cache: bool,
+    ) -> CostResult<Element, Error>;
+
+    /// Get an element's value hash from Merk under a key
+    fn get_value_hash<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+        merk: &Merk<S>,
+        key: K,
+        allow_cache: bool,
+    ) -> CostResult<Option<Hash>, Error>;
+}
+
+impl ElementGetExt for Element {
+    fn get<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
         merk: &Merk<S>,
         key: K,
         allow_cache: bool,
@@ -66,10 +123,7 @@ impl Element {
         })
     }
 
-    #[cfg(feature = "full")]
-    /// Get an element from Merk under a key; path should be resolved and proper
-    /// Merk should be loaded by this moment
-    pub fn get_optional<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+    fn get_optional<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
         merk: &Merk<S>,
         key: K,
         allow_cache: bool,
@@ -95,11 +149,37 @@ impl Element {
         Ok(element).wrap_with_cost(cost)
     }
 
-    #[cfg(feature = "full")]
-    /// Get an element directly from storage under a key
-    /// Merk does not need to be loaded
-    /// Errors if element doesn't exist
-    pub fn get_from_storage<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+    fn get_many<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+        merk: &Merk<S>,
+        keys: &[K],
+    ) -> CostResult<Vec<Option<Element>>, Error> {
+        let mut cost = OperationCost::default();
+
+        let values = cost_return_on_error!(
+            &mut cost,
+            merk.get_many(keys)
+                .map_err(|e| Error::CorruptedData(e.to_string()))
+        );
+        let elements = cost_return_on_error_no_add!(
+            &cost,
+            values
+                .into_iter()
+                .map(|value_opt| {
+                    value_opt
+                        .map(|value| {
+                            Self::deserialize(value.as_slice()).map_err(|_| {
+                                Error::CorruptedData(String::from("unable to deserialize element"))
+                            })
+                        })
+                        .transpose()
+                })
+                .collect::<Result<Vec<_>, Error>>()
+        );
+
+        Ok(elements).wrap_with_cost(cost)
+    }
+
+    fn get_from_storage<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
         storage: &S,
         key: K,
     ) -> CostResult<Element, Error> {
@@ -114,10 +194,7 @@ impl Element {
         })
     }
 
-    #[cfg(feature = "full")]
-    /// Get an element directly from storage under a key
-    /// Merk does not need to be loaded
-    pub fn get_optional_from_storage<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+    fn get_optional_from_storage<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
         storage: &S,
         key: K,
     ) -> CostResult<Option<Element>, Error> {
@@ -152,7 +229,9 @@ impl Element {
                 .transpose()
         );
         match &element {
-            Some(Element::Item(..)) | Some(Element::Reference(..)) => {
+            Some(Element::Item(..))
+            | Some(Element::Reference(..))
+            | Some(Element::BidirectionalReference(..)) => {
                 // while the loaded item might be a sum item, it is given for free
                 // as it would be very hard to know in advance
                 cost.storage_loaded_bytes = KV::value_byte_cost_size_for_key_and_value_lengths(
@@ -175,9 +254,19 @@ impl Element {
                         false,
                     )
             }
-            Some(Element::Tree(_, flags)) | Some(Element::SumTree(_, _, flags)) => {
+            Some(Element::Tree(_, flags))
+            | Some(Element::SumTree(_, _, flags))
+            | Some(Element::CountTree(_, _, flags))
+            | Some(Element::BigSumTree(_, _, flags))
+            | Some(Element::AggregateTree(.., flags)) => {
                 let tree_cost_size = if element.as_ref().unwrap().is_sum_tree() {
                     SUM_TREE_COST_SIZE
+                } else if element.as_ref().unwrap().is_count_tree() {
+                    COUNT_TREE_COST_SIZE
+                } else if element.as_ref().unwrap().is_big_sum_tree() {
+                    BIG_SUM_TREE_COST_SIZE
+                } else if element.as_ref().unwrap().is_aggregate_tree() {
+                    AGGREGATE_TREE_COST_SIZE
                 } else {
                     TREE_COST_SIZE
                 };
@@ -198,10 +287,7 @@ impl Element {
         Ok(element).wrap_with_cost(cost)
     }
 
-    #[cfg(feature = "full")]
-    /// Get an element from Merk under a key; path should be resolved and proper
-    /// Merk should be loaded by this moment
-    pub fn get_with_absolute_refs<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+    fn get_with_absolute_refs<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
         merk: &Merk<S>,
         path: &[&[u8]],
         key: K,
@@ -219,9 +305,7 @@ impl Element {
         Ok(absolute_element).wrap_with_cost(cost)
     }
 
-    #[cfg(feature = "full")]
-    /// Get an element's value hash from Merk under a key
-    pub fn get_value_hash<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+    fn get_value_hash<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
         merk: &Merk<S>,
         key: K,
         allow_cache: bool,
@@ -238,13 +322,13 @@ impl Element {
     }
 }
 
-#[cfg(feature = "full")]
 #[cfg(test)]
 mod tests {
     use grovedb_path::SubtreePath;
     use grovedb_storage::{rocksdb_storage::test_utils::TempStorage, Storage, StorageBatch};
 
     use super::*;
+    use crate::element::ElementInsertExt;
 
     #[test]
     fn test_cache_changes_cost() {
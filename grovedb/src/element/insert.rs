@@ -29,30 +29,142 @@
 //! Insert
 //! Implements functions in Element for inserting into Merk
 
-use grovedb_costs::cost_return_on_error_default;
-#[cfg(feature = "full")]
 use grovedb_costs::{
-    cost_return_on_error, cost_return_on_error_no_add, CostResult, CostsExt, OperationCost,
+    cost_return_on_error, cost_return_on_error_default, cost_return_on_error_no_add, CostResult,
+    CostsExt, OperationCost,
 };
-#[cfg(feature = "full")]
 use grovedb_merk::{BatchEntry, Error as MerkError, Merk, MerkOptions, Op, TreeFeatureType};
-#[cfg(feature = "full")]
 use grovedb_storage::StorageContext;
-#[cfg(feature = "full")]
 use integer_encoding::VarInt;
 
-use crate::Element::SumItem;
-#[cfg(feature = "full")]
-use crate::{Element, Error, Hash};
+use crate::{
+    element::{ElementExistsExt, ElementGetExt},
+    Element,
+    Element::SumItem,
+    Error, Hash,
+};
 
-impl Element {
-    #[cfg(feature = "full")]
+/// Insertions of elements into a Merk, `Element` being defined in
+/// `grovedb-verify`
+pub trait ElementInsertExt {
     /// Insert an element in Merk under a key; path should be resolved and
     /// proper Merk should be loaded by this moment
     /// If transaction is not passed, the batch will be written immediately.
     /// If transaction is passed, the operation will be committed on the
     /// transaction commit.
-    pub fn insert<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+    fn insert<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+        &self,
+        merk: &mut Merk<S>,
+        key: K,
+        options: Option<MerkOptions>,
+    ) -> CostResult<(), Error>;
+
+    /// Add to batch operations a "Put" op with key and serialized element.
+    /// Return CostResult.
+    fn insert_into_batch_operations<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        batch_operations: &mut Vec<BatchEntry<K>>,
+        feature_type: TreeFeatureType,
+    ) -> CostResult<(), Error>;
+
+    /// Insert an element in Merk under a key if it doesn't yet exist; path
+    /// should be resolved and proper Merk should be loaded by this moment
+    /// If transaction is not passed, the batch will be written immediately.
+    /// If transaction is passed, the operation will be committed on the
+    /// transaction commit.
+    fn insert_if_not_exists<'db, S: StorageContext<'db>>(
+        &self,
+        merk: &mut Merk<S>,
+        key: &[u8],
+        options: Option<MerkOptions>,
+    ) -> CostResult<bool, Error>;
+
+    /// Adds a "Put" op to batch operations with the element and key if it
+    /// doesn't exist yet. Returns CostResult.
+    fn insert_if_not_exists_into_batch_operations<'db, S: StorageContext<'db>, K: AsRef<[u8]>>(
+        &self,
+        merk: &mut Merk<S>,
+        key: K,
+        batch_operations: &mut Vec<BatchEntry<K>>,
+        feature_type: TreeFeatureType,
+    ) -> CostResult<bool, Error>;
+
+    /// Insert an element in Merk under a key if the value is different from
+    /// what already exists; path should be resolved and proper Merk should
+    /// be loaded by this moment If transaction is not passed, the batch
+    /// will be written immediately. If transaction is passed, the operation
+    /// will be committed on the transaction commit.
+    /// The bool represents if we indeed inserted.
+    /// If the value changed we return the old element.
+    fn insert_if_changed_value<'db, S: StorageContext<'db>>(
+        &self,
+        merk: &mut Merk<S>,
+        key: &[u8],
+        options: Option<MerkOptions>,
+    ) -> CostResult<(bool, Option<Element>), Error>;
+
+    /// Adds a "Put" op to batch operations with the element and key if the
+    /// value is different from what already exists; Returns CostResult.
+    /// The bool represents if we indeed inserted.
+    /// If the value changed we return the old element.
+    fn insert_if_changed_value_into_batch_operations<'db, S: StorageContext<'db>, K: AsRef<[u8]>>(
+        &self,
+        merk: &mut Merk<S>,
+        key: K,
+        batch_operations: &mut Vec<BatchEntry<K>>,
+        feature_type: TreeFeatureType,
+    ) -> CostResult<(bool, Option<Element>), Error>;
+
+    /// Insert a reference element in Merk under a key; path should be resolved
+    /// and proper Merk should be loaded by this moment
+    /// If transaction is not passed, the batch will be written immediately.
+    /// If transaction is passed, the operation will be committed on the
+    /// transaction commit.
+    fn insert_reference<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+        &self,
+        merk: &mut Merk<S>,
+        key: K,
+        referenced_value: Hash,
+        options: Option<MerkOptions>,
+    ) -> CostResult<(), Error>;
+
+    /// Adds a "Put" op to batch operations with reference and key. Returns
+    /// CostResult.
+    fn insert_reference_into_batch_operations<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        referenced_value: Hash,
+        batch_operations: &mut Vec<BatchEntry<K>>,
+        feature_type: TreeFeatureType,
+    ) -> CostResult<(), Error>;
+
+    /// Insert a tree element in Merk under a key; path should be resolved
+    /// and proper Merk should be loaded by this moment
+    /// If transaction is not passed, the batch will be written immediately.
+    /// If transaction is passed, the operation will be committed on the
+    /// transaction commit.
+    fn insert_subtree<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+        &self,
+        merk: &mut Merk<S>,
+        key: K,
+        subtree_root_hash: Hash,
+        options: Option<MerkOptions>,
+    ) -> CostResult<(), Error>;
+
+    /// Adds a "Put" op to batch operations for a subtree and key
+    fn insert_subtree_into_batch_operations<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        subtree_root_hash: Hash,
+        is_replace: bool,
+        batch_operations: &mut Vec<BatchEntry<K>>,
+        feature_type: TreeFeatureType,
+    ) -> CostResult<(), Error>;
+}
+
+impl ElementInsertExt for Element {
+    fn insert<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
         &self,
         merk: &mut Merk<S>,
         key: K,
@@ -60,13 +172,12 @@ impl Element {
     ) -> CostResult<(), Error> {
         let serialized = cost_return_on_error_default!(self.serialize());
 
-        if !merk.is_sum_tree && self.is_sum_item() {
+        if (!merk.is_sum_tree || merk.is_count_tree) && self.is_sum_item() {
             return Err(Error::InvalidInput("cannot add sum item to non sum tree"))
                 .wrap_with_cost(Default::default());
         }
 
-        let merk_feature_type =
-            cost_return_on_error_default!(self.get_feature_type(merk.is_sum_tree));
+        let merk_feature_type = cost_return_on_error_default!(self.get_feature_type_in_merk(merk));
         let batch_operations = if matches!(self, SumItem(..)) {
             let value_cost = cost_return_on_error_default!(self.get_specialized_cost());
 
@@ -93,13 +204,10 @@ impl Element {
                     .map_err(|e| MerkError::ClientCorruptionError(e.to_string()))
             },
         )
-        .map_err(|e| Error::CorruptedData(e.to_string()))
+        .map_err(Error::from_merk_apply_error)
     }
 
-    #[cfg(feature = "full")]
-    /// Add to batch operations a "Put" op with key and serialized element.
-    /// Return CostResult.
-    pub fn insert_into_batch_operations<K: AsRef<[u8]>>(
+    fn insert_into_batch_operations<K: AsRef<[u8]>>(
         &self,
         key: K,
         batch_operations: &mut Vec<BatchEntry<K>>,
@@ -129,13 +237,7 @@ impl Element {
         Ok(()).wrap_with_cost(Default::default())
     }
 
-    #[cfg(feature = "full")]
-    /// Insert an element in Merk under a key if it doesn't yet exist; path
-    /// should be resolved and proper Merk should be loaded by this moment
-    /// If transaction is not passed, the batch will be written immediately.
-    /// If transaction is passed, the operation will be committed on the
-    /// transaction commit.
-    pub fn insert_if_not_exists<'db, S: StorageContext<'db>>(
+    fn insert_if_not_exists<'db, S: StorageContext<'db>>(
         &self,
         merk: &mut Merk<S>,
         key: &[u8],
@@ -152,14 +254,7 @@ impl Element {
         }
     }
 
-    #[cfg(feature = "full")]
-    /// Adds a "Put" op to batch operations with the element and key if it
-    /// doesn't exist yet. Returns CostResult.
-    pub fn insert_if_not_exists_into_batch_operations<
-        'db,
-        S: StorageContext<'db>,
-        K: AsRef<[u8]>,
-    >(
+    fn insert_if_not_exists_into_batch_operations<'db, S: StorageContext<'db>, K: AsRef<[u8]>>(
         &self,
         merk: &mut Merk<S>,
         key: K,
@@ -182,15 +277,7 @@ impl Element {
         }
     }
 
-    #[cfg(feature = "full")]
-    /// Insert an element in Merk under a key if the value is different from
-    /// what already exists; path should be resolved and proper Merk should
-    /// be loaded by this moment If transaction is not passed, the batch
-    /// will be written immediately. If transaction is passed, the operation
-    /// will be committed on the transaction commit.
-    /// The bool represents if we indeed inserted.
-    /// If the value changed we return the old element.
-    pub fn insert_if_changed_value<'db, S: StorageContext<'db>>(
+    fn insert_if_changed_value<'db, S: StorageContext<'db>>(
         &self,
         merk: &mut Merk<S>,
         key: &[u8],
@@ -213,12 +300,7 @@ impl Element {
         }
     }
 
-    #[cfg(feature = "full")]
-    /// Adds a "Put" op to batch operations with the element and key if the
-    /// value is different from what already exists; Returns CostResult.
-    /// The bool represents if we indeed inserted.
-    /// If the value changed we return the old element.
-    pub fn insert_if_changed_value_into_batch_operations<
+    fn insert_if_changed_value_into_batch_operations<
         'db,
         S: StorageContext<'db>,
         K: AsRef<[u8]>,
@@ -249,13 +331,7 @@ impl Element {
         }
     }
 
-    #[cfg(feature = "full")]
-    /// Insert a reference element in Merk under a key; path should be resolved
-    /// and proper Merk should be loaded by this moment
-    /// If transaction is not passed, the batch will be written immediately.
-    /// If transaction is passed, the operation will be committed on the
-    /// transaction commit.
-    pub fn insert_reference<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+    fn insert_reference<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
         &self,
         merk: &mut Merk<S>,
         key: K,
@@ -270,7 +346,7 @@ impl Element {
         let mut cost = OperationCost::default();
         let merk_feature_type = cost_return_on_error!(
             &mut cost,
-            self.get_feature_type(merk.is_sum_tree)
+            self.get_feature_type_in_merk(merk)
                 .wrap_with_cost(OperationCost::default())
         );
 
@@ -288,13 +364,10 @@ impl Element {
                     .map_err(|e| MerkError::ClientCorruptionError(e.to_string()))
             },
         )
-        .map_err(|e| Error::CorruptedData(e.to_string()))
+        .map_err(Error::from_merk_apply_error)
     }
 
-    #[cfg(feature = "full")]
-    /// Adds a "Put" op to batch operations with reference and key. Returns
-    /// CostResult.
-    pub fn insert_reference_into_batch_operations<K: AsRef<[u8]>>(
+    fn insert_reference_into_batch_operations<K: AsRef<[u8]>>(
         &self,
         key: K,
         referenced_value: Hash,
@@ -314,13 +387,7 @@ impl Element {
         Ok(()).wrap_with_cost(Default::default())
     }
 
-    #[cfg(feature = "full")]
-    /// Insert a tree element in Merk under a key; path should be resolved
-    /// and proper Merk should be loaded by this moment
-    /// If transaction is not passed, the batch will be written immediately.
-    /// If transaction is passed, the operation will be committed on the
-    /// transaction commit.
-    pub fn insert_subtree<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
+    fn insert_subtree<'db, K: AsRef<[u8]>, S: StorageContext<'db>>(
         &self,
         merk: &mut Merk<S>,
         key: K,
@@ -334,7 +401,7 @@ impl Element {
 
         let cost = OperationCost::default();
         let merk_feature_type =
-            cost_return_on_error_no_add!(&cost, self.get_feature_type(merk.is_sum_tree));
+            cost_return_on_error_no_add!(&cost, self.get_feature_type_in_merk(merk));
 
         let tree_cost = cost_return_on_error_no_add!(&cost, self.get_specialized_cost());
 
@@ -357,12 +424,10 @@ impl Element {
                     .map_err(|e| MerkError::ClientCorruptionError(e.to_string()))
             },
         )
-        .map_err(|e| Error::CorruptedData(e.to_string()))
+        .map_err(Error::from_merk_apply_error)
     }
 
-    #[cfg(feature = "full")]
-    /// Adds a "Put" op to batch operations for a subtree and key
-    pub fn insert_subtree_into_batch_operations<K: AsRef<[u8]>>(
+    fn insert_subtree_into_batch_operations<K: AsRef<[u8]>>(
         &self,
         key: K,
         subtree_root_hash: Hash,
@@ -400,7 +465,6 @@ impl Element {
     }
 }
 
-#[cfg(feature = "full")]
 #[cfg(test)]
 mod tests {
     use grovedb_merk::test_utils::{empty_path_merk, empty_path_merk_read_only, TempMerk};
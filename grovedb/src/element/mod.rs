@@ -29,92 +29,34 @@
 //! Module for subtrees handling.
 //! Subtrees handling is isolated so basically this module is about adapting
 //! Merk API to GroveDB needs.
+//!
+//! Elements are defined in `grovedb-verify`, along with their encoding. The
+//! operations reading and writing them in a Merk, their queries and storage
+//! flags are kept here, as extension traits of `Element`.
 
-#[cfg(feature = "full")]
-mod constructor;
 #[cfg(feature = "full")]
 mod delete;
 #[cfg(feature = "full")]
 mod exists;
 #[cfg(feature = "full")]
 mod get;
-#[cfg(any(feature = "full", feature = "verify"))]
-pub(crate) mod helpers;
 #[cfg(feature = "full")]
 mod insert;
-#[cfg(any(feature = "full", feature = "verify"))]
+#[cfg(feature = "full")]
 mod query;
-#[cfg(any(feature = "full", feature = "verify"))]
-mod serialize;
 #[cfg(feature = "full")]
-use core::fmt;
+pub mod storage_flags;
 
-#[cfg(any(feature = "full", feature = "verify"))]
-use grovedb_merk::estimated_costs::SUM_VALUE_EXTRA_COST;
 #[cfg(feature = "full")]
-use grovedb_merk::estimated_costs::{LAYER_COST_SIZE, SUM_LAYER_COST_SIZE};
+pub use delete::ElementDeleteExt;
 #[cfg(feature = "full")]
-use grovedb_visualize::visualize_to_vec;
-#[cfg(any(feature = "full", feature = "verify"))]
-use serde::{Deserialize, Serialize};
-
-#[cfg(any(feature = "full", feature = "verify"))]
-use crate::reference_path::ReferencePathType;
-
-#[cfg(any(feature = "full", feature = "verify"))]
-/// Optional meta-data to be stored per element
-pub type ElementFlags = Vec<u8>;
-
-#[cfg(any(feature = "full", feature = "verify"))]
-/// Optional single byte to represent the maximum number of reference hop to
-/// base element
-pub type MaxReferenceHop = Option<u8>;
-
+pub use exists::ElementExistsExt;
 #[cfg(feature = "full")]
-/// The cost of a tree
-pub const TREE_COST_SIZE: u32 = LAYER_COST_SIZE; // 3
-#[cfg(any(feature = "full", feature = "verify"))]
-/// The cost of a sum item
-///
-/// It is 11 because we have 9 bytes for the sum value
-/// 1 byte for the item type
-/// 1 byte for the flags option
-pub const SUM_ITEM_COST_SIZE: u32 = SUM_VALUE_EXTRA_COST + 2; // 11
+pub use get::ElementGetExt;
+pub use grovedb_verify::element::*;
 #[cfg(feature = "full")]
-/// The cost of a sum tree
-pub const SUM_TREE_COST_SIZE: u32 = SUM_LAYER_COST_SIZE; // 12
-
-#[cfg(any(feature = "full", feature = "verify"))]
-/// int 64 sum value
-pub type SumValue = i64;
-
-#[cfg(any(feature = "full", feature = "verify"))]
-/// Variants of GroveDB stored entities
-///
-/// ONLY APPEND TO THIS LIST!!! Because
-/// of how serialization works.
-#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
-pub enum Element {
-    /// An ordinary value
-    Item(Vec<u8>, Option<ElementFlags>),
-    /// A reference to an object by its path
-    Reference(ReferencePathType, MaxReferenceHop, Option<ElementFlags>),
-    /// A subtree, contains the a prefixed key representing the root of the
-    /// subtree.
-    Tree(Option<Vec<u8>>, Option<ElementFlags>),
-    /// Signed integer value that can be totaled in a sum tree
-    SumItem(SumValue, Option<ElementFlags>),
-    /// Same as Element::Tree but underlying Merk sums value of it's summable
-    /// nodes
-    SumTree(Option<Vec<u8>>, SumValue, Option<ElementFlags>),
-}
-
+pub use insert::ElementInsertExt;
 #[cfg(feature = "full")]
-impl fmt::Debug for Element {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut v = Vec::new();
-        visualize_to_vec(&mut v, self);
-
-        f.write_str(&String::from_utf8_lossy(&v))
-    }
-}
+pub(crate) use query::check_keys_only;
+#[cfg(feature = "full")]
+pub use query::ElementQueryExt;
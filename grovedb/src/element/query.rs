@@ -27,39 +27,34 @@
 // DEALINGS IN THE SOFTWARE.
 
 //! Query
-//! Implements functions in Element for querying
+//! Implements the queries of elements, which need a GroveDB
 
-#[cfg(feature = "full")]
 use grovedb_costs::{
-    cost_return_on_error, cost_return_on_error_no_add, CostContext, CostResult, CostsExt,
-    OperationCost,
+    cost_return_on_error, cost_return_on_error_no_add, CostResult, CostsExt, OperationCost,
+};
+use grovedb_merk::{
+    proofs::{query::query_item::QueryItem, Query},
+    CryptoHash,
 };
-#[cfg(feature = "full")]
-use grovedb_merk::proofs::query::query_item::QueryItem;
-#[cfg(any(feature = "full", feature = "verify"))]
-use grovedb_merk::proofs::Query;
 use grovedb_path::SubtreePath;
-#[cfg(feature = "full")]
-use grovedb_storage::{rocksdb_storage::RocksDbStorage, RawIterator, StorageContext};
+use grovedb_storage::{
+    rocksdb_storage::RocksDbStorage, RawIterator, RawIteratorOptions, StorageContext,
+};
 
-use crate::query_result_type::Path;
-#[cfg(feature = "full")]
 use crate::{
-    element::helpers::raw_decode,
+    element::{helpers::raw_decode_with_value_hash, ElementGetExt},
     query_result_type::{
-        KeyElementPair, QueryResultElement, QueryResultElements, QueryResultType,
+        Path, QueryResultElement, QueryResultElements, QueryResultType,
         QueryResultType::{
             QueryElementResultType, QueryKeyElementPairResultType,
-            QueryPathKeyElementTrioResultType,
+            QueryPathKeyElementTrioResultType, QueryPathKeyResultType,
+            QueryPathKeyValueHashResultType,
         },
     },
     util::{merk_optional_tx, storage_context_optional_tx},
-    Error, PathQuery, TransactionArg,
+    Element, Error, PathQuery, SizedQuery, TransactionArg,
 };
-#[cfg(any(feature = "full", feature = "verify"))]
-use crate::{Element, SizedQuery};
 
-#[cfg(feature = "full")]
 /// Path query push arguments
 pub struct PathQueryPushArgs<'db, 'ctx, 'a>
 where
@@ -69,22 +64,94 @@ where
     pub transaction: TransactionArg<'db, 'ctx>,
     pub key: Option<&'a [u8]>,
     pub element: Element,
+    pub value_hash: Option<CryptoHash>,
     pub path: &'a [&'a [u8]],
     pub subquery_path: Option<Path>,
     pub subquery: Option<Query>,
     pub left_to_right: bool,
     pub allow_get_raw: bool,
     pub allow_cache: bool,
+    pub iterator_options: &'a RawIteratorOptions,
     pub result_type: QueryResultType,
     pub results: &'a mut Vec<QueryResultElement>,
     pub limit: &'a mut Option<u16>,
     pub offset: &'a mut Option<u16>,
 }
 
-impl Element {
-    #[cfg(feature = "full")]
+/// Queries of the elements of subtrees, `Element` being defined in
+/// `grovedb-verify`
+pub trait ElementQueryExt {
     /// Returns a vector of result elements based on given query
-    pub fn get_query(
+    fn get_query(
+        storage: &RocksDbStorage,
+        merk_path: &[&[u8]],
+        query: &Query,
+        result_type: QueryResultType,
+        transaction: TransactionArg,
+    ) -> CostResult<QueryResultElements, Error>;
+
+    /// Get values of result elements coming from given query
+    fn get_query_values(
+        storage: &RocksDbStorage,
+        merk_path: &[&[u8]],
+        query: &Query,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<Element>, Error>;
+
+    /// Returns a vector of result elements and the number of skipped items
+    /// based on given query.
+    ///
+    /// The limit and offset of the sized query form one budget shared by all
+    /// subqueries: the number of returned elements is the amount of the limit
+    /// consumed and the number of skipped items is the amount of the offset
+    /// consumed, summed over every subtree the query reached.
+    fn get_query_apply_function(
+        storage: &RocksDbStorage,
+        path: &[&[u8]],
+        sized_query: &SizedQuery,
+        allow_get_raw: bool,
+        allow_cache: bool,
+        iterator_options: &RawIteratorOptions,
+        result_type: QueryResultType,
+        transaction: TransactionArg,
+        add_element_function: fn(PathQueryPushArgs) -> CostResult<(), Error>,
+    ) -> CostResult<(QueryResultElements, u16), Error>;
+
+    /// Returns a vector of elements excluding trees, and the number of skipped
+    /// elements
+    fn get_path_query(
+        storage: &RocksDbStorage,
+        path_query: &PathQuery,
+        allow_cache: bool,
+        iterator_options: &RawIteratorOptions,
+        result_type: QueryResultType,
+        transaction: TransactionArg,
+    ) -> CostResult<(QueryResultElements, u16), Error>;
+
+    /// Returns a vector of elements including trees, and the number of skipped
+    /// elements
+    fn get_raw_path_query(
+        storage: &RocksDbStorage,
+        path_query: &PathQuery,
+        allow_cache: bool,
+        iterator_options: &RawIteratorOptions,
+        result_type: QueryResultType,
+        transaction: TransactionArg,
+    ) -> CostResult<(QueryResultElements, u16), Error>;
+
+    /// Returns a vector of elements, and the number of skipped elements
+    fn get_sized_query(
+        storage: &RocksDbStorage,
+        path: &[&[u8]],
+        sized_query: &SizedQuery,
+        allow_cache: bool,
+        result_type: QueryResultType,
+        transaction: TransactionArg,
+    ) -> CostResult<(QueryResultElements, u16), Error>;
+}
+
+impl ElementQueryExt for Element {
+    fn get_query(
         storage: &RocksDbStorage,
         merk_path: &[&[u8]],
         query: &Query,
@@ -103,9 +170,7 @@ impl Element {
         .map_ok(|(elements, _)| elements)
     }
 
-    #[cfg(feature = "full")]
-    /// Get values of result elements coming from given query
-    pub fn get_query_values(
+    fn get_query_values(
         storage: &RocksDbStorage,
         merk_path: &[&[u8]],
         query: &Query,
@@ -125,22 +190,22 @@ impl Element {
                 .filter_map(|result_item| match result_item {
                     QueryResultElement::ElementResultItem(element) => Some(element),
                     QueryResultElement::KeyElementPairResultItem(_) => None,
-                    QueryResultElement::PathKeyElementTrioResultItem(_) => None,
+                    QueryResultElement::PathKeyElementTrioResultItem(_)
+                    | QueryResultElement::PathKeyResultItem(_)
+                    | QueryResultElement::PathKeyValueHashResultItem(_) => None,
                 })
                 .collect();
             Ok(elements).wrap_with_cost(OperationCost::default())
         })
     }
 
-    #[cfg(feature = "full")]
-    /// Returns a vector of result elements and the number of skipped items
-    /// based on given query
-    pub fn get_query_apply_function(
+    fn get_query_apply_function(
         storage: &RocksDbStorage,
         path: &[&[u8]],
         sized_query: &SizedQuery,
         allow_get_raw: bool,
         allow_cache: bool,
+        iterator_options: &RawIteratorOptions,
         result_type: QueryResultType,
         transaction: TransactionArg,
         add_element_function: fn(PathQueryPushArgs) -> CostResult<(), Error>,
@@ -153,53 +218,23 @@ impl Element {
         let original_offset = sized_query.offset;
         let mut offset = original_offset;
 
-        if sized_query.query.left_to_right {
-            for item in sized_query.query.iter() {
-                cost_return_on_error!(
-                    &mut cost,
-                    Self::query_item(
-                        storage,
-                        item,
-                        &mut results,
-                        path,
-                        sized_query,
-                        transaction,
-                        &mut limit,
-                        &mut offset,
-                        allow_get_raw,
-                        allow_cache,
-                        result_type,
-                        add_element_function,
-                    )
-                );
-                if limit == Some(0) {
-                    break;
-                }
-            }
-        } else {
-            for item in sized_query.query.rev_iter() {
-                cost_return_on_error!(
-                    &mut cost,
-                    Self::query_item(
-                        storage,
-                        item,
-                        &mut results,
-                        path,
-                        sized_query,
-                        transaction,
-                        &mut limit,
-                        &mut offset,
-                        allow_get_raw,
-                        allow_cache,
-                        result_type,
-                        add_element_function,
-                    )
-                );
-                if limit == Some(0) {
-                    break;
-                }
-            }
-        }
+        cost_return_on_error!(
+            &mut cost,
+            query_items_with_limit_offset(
+                storage,
+                &mut results,
+                path,
+                sized_query,
+                transaction,
+                &mut limit,
+                &mut offset,
+                allow_get_raw,
+                allow_cache,
+                iterator_options,
+                result_type,
+                add_element_function,
+            )
+        );
 
         let skipped = if let Some(original_offset_unwrapped) = original_offset {
             original_offset_unwrapped - offset.unwrap()
@@ -209,16 +244,17 @@ impl Element {
         Ok((QueryResultElements::from_elements(results), skipped)).wrap_with_cost(cost)
     }
 
-    #[cfg(feature = "full")]
-    /// Returns a vector of elements excluding trees, and the number of skipped
-    /// elements
-    pub fn get_path_query(
+    fn get_path_query(
         storage: &RocksDbStorage,
         path_query: &PathQuery,
         allow_cache: bool,
+        iterator_options: &RawIteratorOptions,
         result_type: QueryResultType,
         transaction: TransactionArg,
     ) -> CostResult<(QueryResultElements, u16), Error> {
+        if let Err(e) = check_keys_only(path_query, result_type) {
+            return Err(e).wrap_with_cost(OperationCost::default());
+        }
         let path_slices = path_query
             .path
             .iter()
@@ -230,22 +266,24 @@ impl Element {
             &path_query.query,
             false,
             allow_cache,
+            iterator_options,
             result_type,
             transaction,
-            Element::path_query_push,
+            path_query_push,
         )
     }
 
-    #[cfg(feature = "full")]
-    /// Returns a vector of elements including trees, and the number of skipped
-    /// elements
-    pub fn get_raw_path_query(
+    fn get_raw_path_query(
         storage: &RocksDbStorage,
         path_query: &PathQuery,
         allow_cache: bool,
+        iterator_options: &RawIteratorOptions,
         result_type: QueryResultType,
         transaction: TransactionArg,
     ) -> CostResult<(QueryResultElements, u16), Error> {
+        if let Err(e) = check_keys_only(path_query, result_type) {
+            return Err(e).wrap_with_cost(OperationCost::default());
+        }
         let path_slices = path_query
             .path
             .iter()
@@ -257,15 +295,14 @@ impl Element {
             &path_query.query,
             true,
             allow_cache,
+            iterator_options,
             result_type,
             transaction,
-            Element::path_query_push,
+            path_query_push,
         )
     }
 
-    #[cfg(feature = "full")]
-    /// Returns a vector of elements, and the number of skipped elements
-    pub fn get_sized_query(
+    fn get_sized_query(
         storage: &RocksDbStorage,
         path: &[&[u8]],
         sized_query: &SizedQuery,
@@ -279,90 +316,221 @@ impl Element {
             sized_query,
             false,
             allow_cache,
+            &RawIteratorOptions::default(),
             result_type,
             transaction,
-            Element::path_query_push,
+            path_query_push,
         )
     }
+}
 
-    #[cfg(feature = "full")]
-    /// Push arguments to path query
-    fn path_query_push(args: PathQueryPushArgs) -> CostResult<(), Error> {
-        let mut cost = OperationCost::default();
+/// Checks a keys only path query can be run with `result_type`: it can't have
+/// subqueries, and only the path keys or value hashes of its results are
+/// returned, never elements
+pub(crate) fn check_keys_only(
+    path_query: &PathQuery,
+    result_type: QueryResultType,
+) -> Result<(), Error> {
+    if !path_query.is_keys_only() {
+        Ok(())
+    } else if path_query.query.query.has_subquery() {
+        Err(Error::NotSupported(
+            "keys only path queries can't have subqueries",
+        ))
+    } else if matches!(
+        result_type,
+        QueryPathKeyResultType | QueryPathKeyValueHashResultType
+    ) {
+        Ok(())
+    } else {
+        Err(Error::NotSupported(
+            "keys only path queries don't return elements",
+        ))
+    }
+}
 
-        let PathQueryPushArgs {
-            storage,
-            transaction,
-            key,
-            element,
-            path,
-            subquery_path,
-            subquery,
-            left_to_right,
-            allow_get_raw,
-            allow_cache,
-            result_type,
-            results,
-            limit,
-            offset,
-        } = args;
-        if element.is_tree() {
-            let mut path_vec = path.to_vec();
-            let key = cost_return_on_error_no_add!(
-                &cost,
-                key.ok_or(Error::MissingParameter(
-                    "the key must be provided when using a subquery path",
-                ))
-            );
-            path_vec.push(key);
+/// Applies every item of the sized query to the subtree at `path`,
+/// spending the shared `limit` and `offset` budget. Subqueries recurse
+/// into this function with the same budget, so the limit and offset
+/// count results across all subtrees rather than per subtree.
+fn query_items_with_limit_offset(
+    storage: &RocksDbStorage,
+    results: &mut Vec<QueryResultElement>,
+    path: &[&[u8]],
+    sized_query: &SizedQuery,
+    transaction: TransactionArg,
+    limit: &mut Option<u16>,
+    offset: &mut Option<u16>,
+    allow_get_raw: bool,
+    allow_cache: bool,
+    iterator_options: &RawIteratorOptions,
+    result_type: QueryResultType,
+    add_element_function: fn(PathQueryPushArgs) -> CostResult<(), Error>,
+) -> CostResult<(), Error> {
+    let mut cost = OperationCost::default();
+
+    let items: Box<dyn Iterator<Item = &QueryItem>> = if sized_query.query.left_to_right {
+        Box::new(sized_query.query.iter())
+    } else {
+        Box::new(sized_query.query.rev_iter())
+    };
+    for item in items {
+        if *limit == Some(0) {
+            break;
+        }
+        cost_return_on_error!(
+            &mut cost,
+            query_item(
+                storage,
+                item,
+                results,
+                path,
+                sized_query,
+                transaction,
+                limit,
+                offset,
+                allow_get_raw,
+                allow_cache,
+                iterator_options,
+                result_type,
+                add_element_function,
+            )
+        );
+    }
+    Ok(()).wrap_with_cost(cost)
+}
 
-            if let Some(subquery) = subquery {
-                if let Some(subquery_path) = &subquery_path {
-                    path_vec.extend(subquery_path.iter().map(|k| k.as_slice()));
-                }
+/// Push arguments to path query
+fn path_query_push(args: PathQueryPushArgs) -> CostResult<(), Error> {
+    let mut cost = OperationCost::default();
+
+    let PathQueryPushArgs {
+        storage,
+        transaction,
+        key,
+        element,
+        value_hash,
+        path,
+        subquery_path,
+        subquery,
+        left_to_right,
+        allow_get_raw,
+        allow_cache,
+        iterator_options,
+        result_type,
+        results,
+        limit,
+        offset,
+    } = args;
+    if element.is_tree() {
+        let mut path_vec = path.to_vec();
+        let key = cost_return_on_error_no_add!(
+            &cost,
+            key.ok_or(Error::MissingParameter(
+                "the key must be provided when using a subquery path",
+            ))
+        );
+        path_vec.push(key);
 
-                let inner_query = SizedQuery::new(subquery, *limit, *offset);
-                let path_vec_owned = path_vec.iter().map(|x| x.to_vec()).collect();
-                let inner_path_query = PathQuery::new(path_vec_owned, inner_query);
+        if let Some(subquery) = subquery {
+            if let Some(subquery_path) = &subquery_path {
+                path_vec.extend(subquery_path.iter().map(|k| k.as_slice()));
+            }
 
-                let (mut sub_elements, skipped) = cost_return_on_error!(
-                    &mut cost,
-                    Element::get_path_query(
-                        storage,
-                        &inner_path_query,
-                        allow_cache,
-                        result_type,
-                        transaction
-                    )
-                );
+            let inner_query = SizedQuery::new(subquery, None, None);
+            // The upper bound refers to keys of the queried subtree only
+            let inner_iterator_options = RawIteratorOptions {
+                iterate_upper_bound: None,
+                ..iterator_options.clone()
+            };
 
-                if let Some(limit) = limit {
-                    *limit = limit.saturating_sub(sub_elements.len() as u16);
-                }
-                if let Some(offset) = offset {
-                    *offset = offset.saturating_sub(skipped);
-                }
-                results.append(&mut sub_elements.elements);
-            } else if let Some(subquery_path) = subquery_path {
-                if offset.unwrap_or(0) == 0 {
-                    if let Some((subquery_path_last_key, subquery_path_front_keys)) =
-                        &subquery_path.split_last()
-                    {
-                        path_vec.extend(subquery_path_front_keys.iter().map(|k| k.as_slice()));
-
-                        let subtree_path: SubtreePath<_> = path_vec.as_slice().into();
-
-                        match result_type {
-                            QueryElementResultType => {
-                                merk_optional_tx!(
-                                    &mut cost,
-                                    storage,
-                                    subtree_path,
-                                    None,
-                                    transaction,
-                                    subtree,
-                                    {
-                                        results.push(QueryResultElement::ElementResultItem(
+            cost_return_on_error!(
+                &mut cost,
+                query_items_with_limit_offset(
+                    storage,
+                    results,
+                    path_vec.as_slice(),
+                    &inner_query,
+                    transaction,
+                    limit,
+                    offset,
+                    false,
+                    allow_cache,
+                    &inner_iterator_options,
+                    result_type,
+                    path_query_push,
+                )
+            );
+        } else if let Some(subquery_path) = subquery_path {
+            if offset.unwrap_or(0) == 0 {
+                if let Some((subquery_path_last_key, subquery_path_front_keys)) =
+                    &subquery_path.split_last()
+                {
+                    path_vec.extend(subquery_path_front_keys.iter().map(|k| k.as_slice()));
+
+                    let subtree_path: SubtreePath<_> = path_vec.as_slice().into();
+
+                    match result_type {
+                        QueryElementResultType => {
+                            merk_optional_tx!(
+                                &mut cost,
+                                storage,
+                                subtree_path,
+                                None,
+                                transaction,
+                                subtree,
+                                {
+                                    results.push(QueryResultElement::ElementResultItem(
+                                        cost_return_on_error!(
+                                            &mut cost,
+                                            Element::get_with_absolute_refs(
+                                                &subtree,
+                                                path_vec.as_slice(),
+                                                subquery_path_last_key.as_slice(),
+                                                allow_cache,
+                                            )
+                                        ),
+                                    ));
+                                }
+                            );
+                        }
+                        QueryKeyElementPairResultType => {
+                            merk_optional_tx!(
+                                &mut cost,
+                                storage,
+                                subtree_path,
+                                None,
+                                transaction,
+                                subtree,
+                                {
+                                    results.push(QueryResultElement::KeyElementPairResultItem((
+                                        subquery_path_last_key.to_vec(),
+                                        cost_return_on_error!(
+                                            &mut cost,
+                                            Element::get_with_absolute_refs(
+                                                &subtree,
+                                                path_vec.as_slice(),
+                                                subquery_path_last_key.as_slice(),
+                                                allow_cache,
+                                            )
+                                        ),
+                                    )));
+                                }
+                            );
+                        }
+                        QueryPathKeyElementTrioResultType => {
+                            merk_optional_tx!(
+                                &mut cost,
+                                storage,
+                                subtree_path,
+                                None,
+                                transaction,
+                                subtree,
+                                {
+                                    results.push(QueryResultElement::PathKeyElementTrioResultItem(
+                                        (
+                                            path_vec.iter().map(|p| p.to_vec()).collect(),
+                                            subquery_path_last_key.to_vec(),
                                             cost_return_on_error!(
                                                 &mut cost,
                                                 Element::get_with_absolute_refs(
@@ -372,253 +540,228 @@ impl Element {
                                                     allow_cache,
                                                 )
                                             ),
-                                        ));
-                                    }
-                                );
-                            }
-                            QueryKeyElementPairResultType => {
-                                merk_optional_tx!(
-                                    &mut cost,
-                                    storage,
-                                    subtree_path,
-                                    None,
-                                    transaction,
-                                    subtree,
-                                    {
-                                        results.push(QueryResultElement::KeyElementPairResultItem(
-                                            (
-                                                subquery_path_last_key.to_vec(),
-                                                cost_return_on_error!(
-                                                    &mut cost,
-                                                    Element::get_with_absolute_refs(
-                                                        &subtree,
-                                                        path_vec.as_slice(),
-                                                        subquery_path_last_key.as_slice(),
-                                                        allow_cache,
-                                                    )
-                                                ),
-                                            ),
-                                        ));
-                                    }
-                                );
-                            }
-                            QueryPathKeyElementTrioResultType => {
-                                merk_optional_tx!(
-                                    &mut cost,
-                                    storage,
-                                    subtree_path,
-                                    None,
-                                    transaction,
-                                    subtree,
-                                    {
-                                        results.push(
-                                            QueryResultElement::PathKeyElementTrioResultItem((
-                                                path_vec.iter().map(|p| p.to_vec()).collect(),
-                                                subquery_path_last_key.to_vec(),
-                                                cost_return_on_error!(
-                                                    &mut cost,
-                                                    Element::get_with_absolute_refs(
-                                                        &subtree,
-                                                        path_vec.as_slice(),
-                                                        subquery_path_last_key.as_slice(),
-                                                        allow_cache,
-                                                    )
-                                                ),
-                                            )),
-                                        );
-                                    }
-                                );
-                            }
+                                        ),
+                                    ));
+                                }
+                            );
+                        }
+                        QueryPathKeyResultType | QueryPathKeyValueHashResultType => {
+                            merk_optional_tx!(
+                                &mut cost,
+                                storage,
+                                subtree_path,
+                                None,
+                                transaction,
+                                subtree,
+                                {
+                                    let value_hash = cost_return_on_error!(
+                                        &mut cost,
+                                        subtree
+                                            .get_value_hash(
+                                                subquery_path_last_key.as_slice(),
+                                                allow_cache
+                                            )
+                                            .map_err(Error::MerkError)
+                                    );
+                                    let Some(value_hash) = value_hash else {
+                                        return Err(Error::PathKeyNotFound(format!(
+                                            "key not found in Merk for get: {}",
+                                            hex::encode(subquery_path_last_key)
+                                        )))
+                                        .wrap_with_cost(cost);
+                                    };
+                                    let path = path_vec.iter().map(|p| p.to_vec()).collect();
+                                    let key = subquery_path_last_key.to_vec();
+                                    results.push(match result_type {
+                                        QueryPathKeyResultType => {
+                                            QueryResultElement::PathKeyResultItem((path, key))
+                                        }
+                                        _ => QueryResultElement::PathKeyValueHashResultItem((
+                                            path, key, value_hash,
+                                        )),
+                                    });
+                                }
+                            );
                         }
-                    } else {
-                        return Err(Error::CorruptedCodeExecution(
-                            "subquery_paths can not be empty",
-                        ))
-                        .wrap_with_cost(cost);
-                    };
-
-                    if let Some(limit) = limit {
-                        *limit -= 1;
                     }
-                } else if let Some(offset) = offset {
-                    *offset -= 1;
+                } else {
+                    return Err(Error::CorruptedCodeExecution(
+                        "subquery_paths can not be empty",
+                    ))
+                    .wrap_with_cost(cost);
+                };
+
+                if let Some(limit) = limit {
+                    *limit -= 1;
                 }
-            } else if allow_get_raw {
-                cost_return_on_error_no_add!(
-                    &cost,
-                    Element::basic_push(PathQueryPushArgs {
-                        storage,
-                        transaction,
-                        key: Some(key),
-                        element,
-                        path,
-                        subquery_path,
-                        subquery,
-                        left_to_right,
-                        allow_get_raw,
-                        allow_cache,
-                        result_type,
-                        results,
-                        limit,
-                        offset,
-                    })
-                );
-            } else {
-                return Err(Error::InvalidPath(
-                    "you must provide a subquery or a subquery_path when interacting with a Tree \
-                     of trees"
-                        .to_owned(),
-                ))
-                .wrap_with_cost(cost);
+            } else if let Some(offset) = offset {
+                *offset -= 1;
             }
-        } else {
+        } else if allow_get_raw {
             cost_return_on_error_no_add!(
                 &cost,
-                Element::basic_push(PathQueryPushArgs {
+                basic_push(PathQueryPushArgs {
                     storage,
                     transaction,
-                    key,
+                    key: Some(key),
                     element,
+                    value_hash,
                     path,
                     subquery_path,
                     subquery,
                     left_to_right,
                     allow_get_raw,
                     allow_cache,
+                    iterator_options,
                     result_type,
                     results,
                     limit,
                     offset,
                 })
             );
+        } else {
+            return Err(Error::InvalidPath(
+                "you must provide a subquery or a subquery_path when interacting with a Tree \
+                 of trees"
+                    .to_owned(),
+            ))
+            .wrap_with_cost(cost);
         }
-        Ok(()).wrap_with_cost(cost)
-    }
-
-    #[cfg(any(feature = "full", feature = "verify"))]
-    /// Takes a sized query and a key and returns subquery key and subquery as
-    /// tuple
-    pub fn subquery_paths_and_value_for_sized_query(
-        sized_query: &SizedQuery,
-        key: &[u8],
-    ) -> (Option<Path>, Option<Query>) {
-        if let Some(conditional_subquery_branches) =
-            &sized_query.query.conditional_subquery_branches
-        {
-            for (query_item, subquery_branch) in conditional_subquery_branches {
-                if query_item.contains(key) {
-                    let subquery_path = subquery_branch.subquery_path.clone();
-                    let subquery = subquery_branch
-                        .subquery
-                        .as_ref()
-                        .map(|query| *query.clone());
-                    return (subquery_path, subquery);
-                }
-            }
-        }
-        let subquery_path = sized_query
-            .query
-            .default_subquery_branch
-            .subquery_path
-            .clone();
-        let subquery = sized_query
-            .query
-            .default_subquery_branch
-            .subquery
-            .as_ref()
-            .map(|query| *query.clone());
-        (subquery_path, subquery)
+    } else {
+        cost_return_on_error_no_add!(
+            &cost,
+            basic_push(PathQueryPushArgs {
+                storage,
+                transaction,
+                key,
+                element,
+                value_hash,
+                path,
+                subquery_path,
+                subquery,
+                left_to_right,
+                allow_get_raw,
+                allow_cache,
+                iterator_options,
+                result_type,
+                results,
+                limit,
+                offset,
+            })
+        );
     }
+    Ok(()).wrap_with_cost(cost)
+}
 
-    #[cfg(feature = "full")]
-    // TODO: refactor
-    fn query_item(
-        storage: &RocksDbStorage,
-        item: &QueryItem,
-        results: &mut Vec<QueryResultElement>,
-        path: &[&[u8]],
-        sized_query: &SizedQuery,
-        transaction: TransactionArg,
-        limit: &mut Option<u16>,
-        offset: &mut Option<u16>,
-        allow_get_raw: bool,
-        allow_cache: bool,
-        result_type: QueryResultType,
-        add_element_function: fn(PathQueryPushArgs) -> CostResult<(), Error>,
-    ) -> CostResult<(), Error> {
-        let mut cost = OperationCost::default();
-
-        let subtree_path: SubtreePath<_> = path.into();
-
-        if !item.is_range() {
-            // this is a query on a key
-            if let QueryItem::Key(key) = item {
-                let element_res = merk_optional_tx!(
-                    &mut cost,
-                    storage,
-                    subtree_path,
-                    None,
-                    transaction,
-                    subtree,
-                    { Element::get(&subtree, key, allow_cache).unwrap_add_cost(&mut cost) }
-                );
-                match element_res {
-                    Ok(element) => {
-                        let (subquery_path, subquery) =
-                            Self::subquery_paths_and_value_for_sized_query(sized_query, key);
-                        add_element_function(PathQueryPushArgs {
-                            storage,
-                            transaction,
-                            key: Some(key.as_slice()),
-                            element,
-                            path,
-                            subquery_path,
-                            subquery,
-                            left_to_right: sized_query.query.left_to_right,
-                            allow_get_raw,
-                            allow_cache,
-                            result_type,
-                            results,
-                            limit,
-                            offset,
-                        })
+// TODO: refactor
+fn query_item(
+    storage: &RocksDbStorage,
+    item: &QueryItem,
+    results: &mut Vec<QueryResultElement>,
+    path: &[&[u8]],
+    sized_query: &SizedQuery,
+    transaction: TransactionArg,
+    limit: &mut Option<u16>,
+    offset: &mut Option<u16>,
+    allow_get_raw: bool,
+    allow_cache: bool,
+    iterator_options: &RawIteratorOptions,
+    result_type: QueryResultType,
+    add_element_function: fn(PathQueryPushArgs) -> CostResult<(), Error>,
+) -> CostResult<(), Error> {
+    let mut cost = OperationCost::default();
+
+    let subtree_path: SubtreePath<_> = path.into();
+
+    if !item.is_range() {
+        // this is a query on a key
+        if let QueryItem::Key(key) = item {
+            let element_res = merk_optional_tx!(
+                &mut cost,
+                storage,
+                subtree_path,
+                None,
+                transaction,
+                subtree,
+                {
+                    Element::get(&subtree, key, allow_cache)
                         .unwrap_add_cost(&mut cost)
-                    }
-                    Err(Error::PathKeyNotFound(_)) => Ok(()),
-                    Err(e) => Err(e),
+                        .and_then(|element| {
+                            // only value hash results need the extra lookup
+                            let value_hash = match result_type {
+                                QueryPathKeyValueHashResultType => subtree
+                                    .get_value_hash(key, allow_cache)
+                                    .unwrap_add_cost(&mut cost)
+                                    .map_err(Error::MerkError)?,
+                                _ => None,
+                            };
+                            Ok((element, value_hash))
+                        })
+                }
+            );
+            match element_res {
+                Ok((element, value_hash)) => {
+                    let Some((subquery_path, subquery)) =
+                        Element::subquery_paths_and_value_for_element(sized_query, key, &element)
+                    else {
+                        return Ok(()).wrap_with_cost(cost);
+                    };
+                    add_element_function(PathQueryPushArgs {
+                        storage,
+                        transaction,
+                        key: Some(key.as_slice()),
+                        element,
+                        value_hash,
+                        path,
+                        subquery_path,
+                        subquery,
+                        left_to_right: sized_query.query.left_to_right,
+                        allow_get_raw,
+                        allow_cache,
+                        iterator_options,
+                        result_type,
+                        results,
+                        limit,
+                        offset,
+                    })
+                    .unwrap_add_cost(&mut cost)
                 }
-            } else {
-                Err(Error::InternalError(
-                    "QueryItem must be a Key if not a range",
-                ))
+                Err(Error::PathKeyNotFound(_)) => Ok(()),
+                Err(e) => Err(e),
             }
         } else {
-            // this is a query on a range
-            storage_context_optional_tx!(storage, subtree_path, None, transaction, ctx, {
-                let ctx = ctx.unwrap_add_cost(&mut cost);
-                let mut iter = ctx.raw_iter();
+            Err(Error::InternalError(
+                "QueryItem must be a Key if not a range",
+            ))
+        }
+    } else {
+        // this is a query on a range
+        storage_context_optional_tx!(storage, subtree_path, None, transaction, ctx, {
+            let ctx = ctx.unwrap_add_cost(&mut cost);
+            let mut iter = ctx.raw_iter_with_options(iterator_options);
 
-                item.seek_for_iter(&mut iter, sized_query.query.left_to_right)
-                    .unwrap_add_cost(&mut cost);
+            item.seek_for_iter(&mut iter, sized_query.query.left_to_right)
+                .unwrap_add_cost(&mut cost);
 
-                while item
-                    .iter_is_valid_for_type(&iter, *limit, sized_query.query.left_to_right)
+            while item
+                .iter_is_valid_for_type(&iter, *limit, sized_query.query.left_to_right)
+                .unwrap_add_cost(&mut cost)
+            {
+                let (element, value_hash) = cost_return_on_error_no_add!(
+                    &cost,
+                    raw_decode_with_value_hash(
+                        iter.value()
+                            .unwrap_add_cost(&mut cost)
+                            .expect("if key exists then value should too")
+                    )
+                );
+                let key = iter
+                    .key()
                     .unwrap_add_cost(&mut cost)
+                    .expect("key should exist");
+                if let Some((subquery_path, subquery)) =
+                    Element::subquery_paths_and_value_for_element(sized_query, key, &element)
                 {
-                    let element = cost_return_on_error_no_add!(
-                        &cost,
-                        raw_decode(
-                            iter.value()
-                                .unwrap_add_cost(&mut cost)
-                                .expect("if key exists then value should too")
-                        )
-                    );
-                    let key = iter
-                        .key()
-                        .unwrap_add_cost(&mut cost)
-                        .expect("key should exist");
-                    let (subquery_path, subquery) =
-                        Self::subquery_paths_and_value_for_sized_query(sized_query, key);
                     cost_return_on_error!(
                         &mut cost,
                         add_element_function(PathQueryPushArgs {
@@ -626,84 +769,98 @@ impl Element {
                             transaction,
                             key: Some(key),
                             element,
+                            value_hash: Some(value_hash),
                             path,
                             subquery_path,
                             subquery,
                             left_to_right: sized_query.query.left_to_right,
                             allow_get_raw,
                             allow_cache,
+                            iterator_options,
                             result_type,
                             results,
                             limit,
                             offset,
                         })
                     );
-                    if sized_query.query.left_to_right {
-                        iter.next().unwrap_add_cost(&mut cost);
-                    } else {
-                        iter.prev().unwrap_add_cost(&mut cost);
-                    }
-                    cost.seek_count += 1;
                 }
-                Ok(())
-            })
-        }
-        .wrap_with_cost(cost)
+                if sized_query.query.left_to_right {
+                    iter.next().unwrap_add_cost(&mut cost);
+                } else {
+                    iter.prev().unwrap_add_cost(&mut cost);
+                }
+                cost.seek_count += 1;
+            }
+            Ok(())
+        })
     }
+    .wrap_with_cost(cost)
+}
 
-    #[cfg(feature = "full")]
-    fn basic_push(args: PathQueryPushArgs) -> Result<(), Error> {
-        let PathQueryPushArgs {
-            path,
-            key,
-            element,
-            result_type,
-            results,
-            limit,
-            offset,
-            ..
-        } = args;
-
-        let element = element.convert_if_reference_to_absolute_reference(path, key)?;
-
-        if offset.unwrap_or(0) == 0 {
-            match result_type {
-                QueryResultType::QueryElementResultType => {
-                    results.push(QueryResultElement::ElementResultItem(element));
-                }
-                QueryResultType::QueryKeyElementPairResultType => {
-                    let key = key.ok_or(Error::CorruptedPath("basic push must have a key"))?;
-                    results.push(QueryResultElement::KeyElementPairResultItem((
-                        Vec::from(key),
-                        element,
-                    )));
-                }
-                QueryResultType::QueryPathKeyElementTrioResultType => {
-                    let key = key.ok_or(Error::CorruptedPath("basic push must have a key"))?;
-                    let path = path.iter().map(|a| a.to_vec()).collect();
-                    results.push(QueryResultElement::PathKeyElementTrioResultItem((
-                        path,
-                        Vec::from(key),
-                        element,
-                    )));
-                }
+fn basic_push(args: PathQueryPushArgs) -> Result<(), Error> {
+    let PathQueryPushArgs {
+        path,
+        key,
+        element,
+        value_hash,
+        result_type,
+        results,
+        limit,
+        offset,
+        ..
+    } = args;
+
+    let element = element.convert_if_reference_to_absolute_reference(path, key)?;
+
+    if offset.unwrap_or(0) == 0 {
+        match result_type {
+            QueryResultType::QueryElementResultType => {
+                results.push(QueryResultElement::ElementResultItem(element));
+            }
+            QueryResultType::QueryKeyElementPairResultType => {
+                let key = key.ok_or(Error::CorruptedPath("basic push must have a key"))?;
+                results.push(QueryResultElement::KeyElementPairResultItem((
+                    Vec::from(key),
+                    element,
+                )));
             }
-            if let Some(limit) = limit {
-                *limit -= 1;
+            QueryResultType::QueryPathKeyElementTrioResultType => {
+                let key = key.ok_or(Error::CorruptedPath("basic push must have a key"))?;
+                let path = path.iter().map(|a| a.to_vec()).collect();
+                results.push(QueryResultElement::PathKeyElementTrioResultItem((
+                    path,
+                    Vec::from(key),
+                    element,
+                )));
+            }
+            QueryResultType::QueryPathKeyResultType => {
+                let key = key.ok_or(Error::CorruptedPath("basic push must have a key"))?;
+                let path = path.iter().map(|a| a.to_vec()).collect();
+                results.push(QueryResultElement::PathKeyResultItem((
+                    path,
+                    Vec::from(key),
+                )));
+            }
+            QueryResultType::QueryPathKeyValueHashResultType => {
+                let key = key.ok_or(Error::CorruptedPath("basic push must have a key"))?;
+                let value_hash = value_hash.ok_or(Error::CorruptedCodeExecution(
+                    "value hash results must have a value hash",
+                ))?;
+                let path = path.iter().map(|a| a.to_vec()).collect();
+                results.push(QueryResultElement::PathKeyValueHashResultItem((
+                    path,
+                    Vec::from(key),
+                    value_hash,
+                )));
             }
-        } else if let Some(offset) = offset {
-            *offset -= 1;
         }
-        Ok(())
-    }
-
-    #[cfg(feature = "full")]
-    /// Iterator
-    pub fn iterator<I: RawIterator>(mut raw_iter: I) -> CostContext<ElementsIterator<I>> {
-        let mut cost = OperationCost::default();
-        raw_iter.seek_to_first().unwrap_add_cost(&mut cost);
-        ElementsIterator::new(raw_iter).wrap_with_cost(cost)
+        if let Some(limit) = limit {
+            *limit -= 1;
+        }
+    } else if let Some(offset) = offset {
+        *offset -= 1;
     }
+    Ok(())
 }
 
 #[cfg(feature = "full")]
@@ -952,7 +1109,9 @@ mod tests {
                 QueryResultElement::KeyElementPairResultItem(key_element_pair) => {
                     Some(key_element_pair)
                 }
-                QueryResultElement::PathKeyElementTrioResultItem(_) => None,
+                QueryResultElement::PathKeyElementTrioResultItem(_)
+                | QueryResultElement::PathKeyResultItem(_)
+                | QueryResultElement::PathKeyValueHashResultItem(_) => None,
             })
             .collect();
         assert_eq!(
@@ -986,7 +1145,9 @@ mod tests {
                 QueryResultElement::KeyElementPairResultItem(key_element_pair) => {
                     Some(key_element_pair)
                 }
-                QueryResultElement::PathKeyElementTrioResultItem(_) => None,
+                QueryResultElement::PathKeyElementTrioResultItem(_)
+                | QueryResultElement::PathKeyResultItem(_)
+                | QueryResultElement::PathKeyValueHashResultItem(_) => None,
             })
             .collect();
         assert_eq!(
@@ -1363,49 +1524,3 @@ mod tests {
         assert_eq!(skipped, 1);
     }
 }
-
-#[cfg(feature = "full")]
-pub struct ElementsIterator<I: RawIterator> {
-    raw_iter: I,
-}
-
-#[cfg(feature = "full")]
-impl<I: RawIterator> ElementsIterator<I> {
-    pub fn new(raw_iter: I) -> Self {
-        ElementsIterator { raw_iter }
-    }
-
-    pub fn next_element(&mut self) -> CostResult<Option<KeyElementPair>, Error> {
-        let mut cost = OperationCost::default();
-
-        Ok(if self.raw_iter.valid().unwrap_add_cost(&mut cost) {
-            if let Some((key, value)) = self
-                .raw_iter
-                .key()
-                .unwrap_add_cost(&mut cost)
-                .zip(self.raw_iter.value().unwrap_add_cost(&mut cost))
-            {
-                let element = cost_return_on_error_no_add!(&cost, raw_decode(value));
-                let key_vec = key.to_vec();
-                self.raw_iter.next().unwrap_add_cost(&mut cost);
-                Some((key_vec, element))
-            } else {
-                None
-            }
-        } else {
-            None
-        })
-        .wrap_with_cost(cost)
-    }
-
-    pub fn fast_forward(&mut self, key: &[u8]) -> Result<(), Error> {
-        while self.raw_iter.valid().unwrap() {
-            if self.raw_iter.key().unwrap().unwrap() == key {
-                break;
-            } else {
-                self.raw_iter.next().unwrap();
-            }
-        }
-        Ok(())
-    }
-}
@@ -0,0 +1,405 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Epoch based storage flags
+//!
+//! Element flags recording in which epochs the bytes of an element were
+//! paid for, so that removals can be refunded per epoch.
+
+use std::collections::BTreeMap;
+
+use grovedb_costs::storage_cost::{
+    removal::{
+        Identifier, StorageRemovalPerEpochByIdentifier, StorageRemovedBytes,
+        StorageRemovedBytes::{NoStorageRemoval, SectionedStorageRemoval},
+    },
+    transition::OperationStorageTransitionType,
+    StorageCost,
+};
+use integer_encoding::VarInt;
+use intmap::IntMap;
+
+use crate::{element::ElementFlags, Error};
+
+/// Epoch index
+pub type Epoch = u16;
+
+const SINGLE_EPOCH: u8 = 0;
+const MULTI_EPOCH: u8 = 1;
+const SINGLE_EPOCH_OWNED: u8 = 2;
+const MULTI_EPOCH_OWNED: u8 = 3;
+
+/// How removed bytes are taken from the epochs that paid for them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemovalStrategy {
+    /// Bytes are removed from the oldest epoch first
+    OldestFirst,
+    /// Bytes are removed from every epoch in proportion to the bytes it holds
+    Proportional,
+}
+
+/// Storage flags of an element
+///
+/// The base epoch is the epoch the element was created in. While no bytes
+/// were added in a later epoch all the bytes of the element belong to the
+/// base epoch, afterwards the bytes of every epoch, the base epoch included,
+/// are tracked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageFlags {
+    base_epoch: Epoch,
+    bytes_per_epoch: BTreeMap<Epoch, u32>,
+    owner_id: Option<Identifier>,
+}
+
+impl StorageFlags {
+    /// Flags of an element created in `base_epoch`
+    pub fn new_single_epoch(base_epoch: Epoch, owner_id: Option<Identifier>) -> Self {
+        StorageFlags {
+            base_epoch,
+            bytes_per_epoch: BTreeMap::new(),
+            owner_id,
+        }
+    }
+
+    /// The epoch the element was created in
+    pub fn base_epoch(&self) -> Epoch {
+        self.base_epoch
+    }
+
+    /// The owner of the element, if any
+    pub fn owner_id(&self) -> Option<&Identifier> {
+        self.owner_id.as_ref()
+    }
+
+    /// Bytes per epoch, empty while all the bytes belong to the base epoch
+    pub fn bytes_per_epoch(&self) -> &BTreeMap<Epoch, u32> {
+        &self.bytes_per_epoch
+    }
+
+    /// Whether the bytes of the element were paid for in a single epoch
+    pub fn is_single_epoch(&self) -> bool {
+        self.bytes_per_epoch.is_empty()
+    }
+
+    /// Records `added_bytes` paid for in `epoch`. `stored_bytes` is the size
+    /// of the element before the addition, it is attributed to the base epoch
+    /// when the epochs start being tracked.
+    pub fn add_bytes_in_epoch(&mut self, epoch: Epoch, added_bytes: u32, stored_bytes: u32) {
+        if added_bytes == 0 || (epoch == self.base_epoch && self.is_single_epoch()) {
+            return;
+        }
+        if self.is_single_epoch() {
+            self.bytes_per_epoch.insert(self.base_epoch, stored_bytes);
+        }
+        let bytes = self.bytes_per_epoch.entry(epoch).or_default();
+        *bytes = bytes.saturating_add(added_bytes);
+    }
+
+    /// Removes `removed_bytes` from the epochs that paid for them and returns
+    /// the bytes removed per epoch. Bytes beyond the tracked ones are taken
+    /// from the base epoch.
+    pub fn remove_bytes(
+        &mut self,
+        removed_bytes: u32,
+        strategy: RemovalStrategy,
+    ) -> BTreeMap<Epoch, u32> {
+        let mut removed_per_epoch = BTreeMap::new();
+        if removed_bytes == 0 {
+            return removed_per_epoch;
+        }
+        if self.is_single_epoch() {
+            removed_per_epoch.insert(self.base_epoch, removed_bytes);
+            return removed_per_epoch;
+        }
+
+        let tracked_bytes = self
+            .bytes_per_epoch
+            .values()
+            .fold(0u64, |total, bytes| total + *bytes as u64);
+        let mut remaining = removed_bytes;
+        if strategy == RemovalStrategy::Proportional && tracked_bytes > 0 {
+            for (epoch, bytes) in self.bytes_per_epoch.iter() {
+                let share = (removed_bytes as u64 * *bytes as u64 / tracked_bytes)
+                    .min(*bytes as u64) as u32;
+                if share > 0 {
+                    removed_per_epoch.insert(*epoch, share);
+                    remaining -= share;
+                }
+            }
+        }
+        // whatever is left over after rounding is taken oldest first
+        for (epoch, bytes) in self.bytes_per_epoch.iter() {
+            if remaining == 0 {
+                break;
+            }
+            let removed = removed_per_epoch.entry(*epoch).or_default();
+            let taken = remaining.min(*bytes - *removed);
+            *removed += taken;
+            remaining -= taken;
+        }
+        if remaining > 0 {
+            let removed = removed_per_epoch.entry(self.base_epoch).or_default();
+            *removed = removed.saturating_add(remaining);
+        }
+
+        removed_per_epoch.retain(|_, removed| *removed > 0);
+        for (epoch, removed) in removed_per_epoch.iter() {
+            if let Some(bytes) = self.bytes_per_epoch.get_mut(epoch) {
+                *bytes = bytes.saturating_sub(*removed);
+            }
+        }
+        let base_epoch = self.base_epoch;
+        self.bytes_per_epoch
+            .retain(|epoch, bytes| *epoch == base_epoch || *bytes > 0);
+        if self.bytes_per_epoch.len() == 1 {
+            // only the base epoch is left
+            self.bytes_per_epoch.clear();
+        }
+        removed_per_epoch
+    }
+
+    /// The refunds of bytes removed per epoch, sectioned by owner
+    pub fn refunds(&self, removed_per_epoch: &BTreeMap<Epoch, u32>) -> StorageRemovedBytes {
+        if removed_per_epoch.values().all(|removed| *removed == 0) {
+            return NoStorageRemoval;
+        }
+        let mut removed_bytes_for_owner = IntMap::new();
+        for (epoch, removed) in removed_per_epoch {
+            removed_bytes_for_owner.insert(*epoch as u64, *removed);
+        }
+        let mut removed_bytes = StorageRemovalPerEpochByIdentifier::default();
+        removed_bytes.insert(self.owner_id.unwrap_or_default(), removed_bytes_for_owner);
+        SectionedStorageRemoval(removed_bytes)
+    }
+
+    /// Splits the bytes removed from an element carrying these flags into
+    /// the refunds of its key and of its value. The key was paid for in the
+    /// base epoch.
+    pub fn split_removed_bytes(
+        &mut self,
+        removed_key_bytes: u32,
+        removed_value_bytes: u32,
+        strategy: RemovalStrategy,
+    ) -> (StorageRemovedBytes, StorageRemovedBytes) {
+        let key_refunds = self.refunds(&BTreeMap::from([(self.base_epoch, removed_key_bytes)]));
+        let removed_per_epoch = self.remove_bytes(removed_value_bytes, strategy);
+        (key_refunds, self.refunds(&removed_per_epoch))
+    }
+
+    /// Merges the flags of an updated element, to be used as the flags
+    /// update function of `apply_batch_with_element_flags_update`. The old
+    /// flags are kept, with the bytes added in `epoch` recorded and the
+    /// bytes removed taken out with `strategy`. Returns whether the new flags
+    /// changed.
+    pub fn update_element_flags(
+        epoch: Epoch,
+        strategy: RemovalStrategy,
+        cost: &StorageCost,
+        old_flags: Option<ElementFlags>,
+        new_flags: &mut ElementFlags,
+    ) -> Result<bool, Error> {
+        let Some(old_flags) = old_flags else {
+            return Ok(false);
+        };
+        let mut merged_flags = StorageFlags::deserialize(&old_flags).map_err(|_| {
+            Error::JustInTimeElementFlagsClientError("old element flags are not storage flags")
+        })?;
+        match cost.transition_type() {
+            OperationStorageTransitionType::OperationUpdateBiggerSize => {
+                merged_flags.add_bytes_in_epoch(epoch, cost.added_bytes, cost.replaced_bytes)
+            }
+            OperationStorageTransitionType::OperationUpdateSmallerSize => {
+                merged_flags.remove_bytes(cost.removed_bytes.total_removed_bytes(), strategy);
+            }
+            OperationStorageTransitionType::OperationUpdateSameSize => {}
+            // a replacement pays for the element anew
+            _ => return Ok(false),
+        }
+        let merged_flags = merged_flags.serialize();
+        if *new_flags == merged_flags {
+            Ok(false)
+        } else {
+            *new_flags = merged_flags;
+            Ok(true)
+        }
+    }
+
+    /// Splits removed bytes by epoch using the storage flags of the removed
+    /// element, to be used as the split removal bytes function of
+    /// `apply_batch_with_element_flags_update`
+    pub fn split_removal_bytes(
+        strategy: RemovalStrategy,
+        flags: &mut ElementFlags,
+        removed_key_bytes: u32,
+        removed_value_bytes: u32,
+    ) -> Result<(StorageRemovedBytes, StorageRemovedBytes), Error> {
+        let mut storage_flags = StorageFlags::deserialize(flags).map_err(|_| {
+            Error::SplitRemovalBytesClientError("element flags are not storage flags")
+        })?;
+        Ok(storage_flags.split_removed_bytes(removed_key_bytes, removed_value_bytes, strategy))
+    }
+
+    /// Serializes the flags
+    pub fn serialize(&self) -> ElementFlags {
+        let flags_type = match (self.is_single_epoch(), self.owner_id.is_some()) {
+            (true, false) => SINGLE_EPOCH,
+            (false, false) => MULTI_EPOCH,
+            (true, true) => SINGLE_EPOCH_OWNED,
+            (false, true) => MULTI_EPOCH_OWNED,
+        };
+        let mut flags = vec![flags_type];
+        if let Some(owner_id) = &self.owner_id {
+            flags.extend_from_slice(owner_id);
+        }
+        flags.extend_from_slice(&self.base_epoch.to_be_bytes());
+        for (epoch, bytes) in self.bytes_per_epoch.iter() {
+            flags.extend_from_slice(&epoch.to_be_bytes());
+            flags.extend(bytes.encode_var_vec());
+        }
+        flags
+    }
+
+    /// Deserializes flags written by `serialize`
+    pub fn deserialize(flags: &[u8]) -> Result<Self, Error> {
+        let invalid = |reason: &str| Error::InvalidElementFlags(format!("storage flags {reason}"));
+        let (flags_type, mut rest) = flags.split_first().ok_or_else(|| invalid("are empty"))?;
+        let owner_id = match *flags_type {
+            SINGLE_EPOCH | MULTI_EPOCH => None,
+            SINGLE_EPOCH_OWNED | MULTI_EPOCH_OWNED => {
+                if rest.len() < 32 {
+                    return Err(invalid("are missing the owner id"));
+                }
+                let (owner_id, after_owner_id) = rest.split_at(32);
+                rest = after_owner_id;
+                Some(owner_id.try_into().expect("owner id is 32 bytes"))
+            }
+            _ => return Err(invalid("have an unknown type")),
+        };
+        let read_epoch = |rest: &mut &[u8]| -> Result<Epoch, Error> {
+            if rest.len() < 2 {
+                return Err(invalid("are missing an epoch"));
+            }
+            let (epoch, after_epoch) = rest.split_at(2);
+            *rest = after_epoch;
+            Ok(Epoch::from_be_bytes([epoch[0], epoch[1]]))
+        };
+        let base_epoch = read_epoch(&mut rest)?;
+
+        let mut bytes_per_epoch = BTreeMap::new();
+        if *flags_type == MULTI_EPOCH || *flags_type == MULTI_EPOCH_OWNED {
+            while !rest.is_empty() {
+                let epoch = read_epoch(&mut rest)?;
+                let (bytes, length) =
+                    u32::decode_var(rest).ok_or_else(|| invalid("have invalid byte counts"))?;
+                rest = &rest[length..];
+                if bytes_per_epoch.keys().next_back() >= Some(&epoch) {
+                    return Err(invalid("epochs are not in increasing order"));
+                }
+                bytes_per_epoch.insert(epoch, bytes);
+            }
+            if bytes_per_epoch.len() < 2 || !bytes_per_epoch.contains_key(&base_epoch) {
+                return Err(invalid("do not track the base epoch and a later epoch"));
+            }
+        } else if !rest.is_empty() {
+            return Err(invalid("have trailing bytes"));
+        }
+
+        Ok(StorageFlags {
+            base_epoch,
+            bytes_per_epoch,
+            owner_id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_storage_flags_round_trip() {
+        let mut flags = StorageFlags::new_single_epoch(3, Some([7; 32]));
+        assert_eq!(
+            StorageFlags::deserialize(&flags.serialize()).unwrap(),
+            flags
+        );
+        flags.add_bytes_in_epoch(5, 40, 100);
+        assert_eq!(
+            StorageFlags::deserialize(&flags.serialize()).unwrap(),
+            flags
+        );
+
+        assert!(StorageFlags::deserialize(&[]).is_err());
+        assert!(StorageFlags::deserialize(&[SINGLE_EPOCH, 0]).is_err());
+        assert!(StorageFlags::deserialize(&[SINGLE_EPOCH, 0, 1, 0]).is_err());
+        assert!(StorageFlags::deserialize(&[MULTI_EPOCH, 0, 1, 0, 1, 5]).is_err());
+    }
+
+    #[test]
+    fn test_storage_flags_removal_strategies() {
+        let mut single = StorageFlags::new_single_epoch(1, None);
+        single.add_bytes_in_epoch(1, 10, 100);
+        assert!(single.is_single_epoch());
+        assert_eq!(
+            single.remove_bytes(30, RemovalStrategy::OldestFirst),
+            BTreeMap::from([(1, 30)])
+        );
+
+        let mut flags = StorageFlags::new_single_epoch(1, None);
+        flags.add_bytes_in_epoch(2, 50, 100);
+        flags.add_bytes_in_epoch(4, 50, 150);
+        assert_eq!(
+            flags.bytes_per_epoch(),
+            &BTreeMap::from([(1, 100), (2, 50), (4, 50)])
+        );
+
+        let mut oldest_first = flags.clone();
+        assert_eq!(
+            oldest_first.remove_bytes(120, RemovalStrategy::OldestFirst),
+            BTreeMap::from([(1, 100), (2, 20)])
+        );
+        assert_eq!(
+            oldest_first.bytes_per_epoch(),
+            &BTreeMap::from([(1, 0), (2, 30), (4, 50)])
+        );
+
+        let mut proportional = flags.clone();
+        assert_eq!(
+            proportional.remove_bytes(101, RemovalStrategy::Proportional),
+            BTreeMap::from([(1, 51), (2, 25), (4, 25)])
+        );
+
+        // removing everything goes back to a single epoch
+        let mut all = flags;
+        assert_eq!(
+            all.remove_bytes(210, RemovalStrategy::Proportional),
+            BTreeMap::from([(1, 110), (2, 50), (4, 50)])
+        );
+        assert!(all.is_single_epoch());
+    }
+}
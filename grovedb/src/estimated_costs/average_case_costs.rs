@@ -48,7 +48,10 @@ use integer_encoding::VarInt;
 
 use crate::{
     batch::{key_info::KeyInfo, KeyInfoPath},
-    element::{SUM_ITEM_COST_SIZE, SUM_TREE_COST_SIZE, TREE_COST_SIZE},
+    element::{
+        AGGREGATE_TREE_COST_SIZE, BIG_SUM_TREE_COST_SIZE, COUNT_TREE_COST_SIZE, SUM_ITEM_COST_SIZE,
+        SUM_TREE_COST_SIZE, TREE_COST_SIZE,
+    },
     Element, ElementFlags, Error, GroveDb,
 };
 
@@ -192,13 +195,23 @@ impl GroveDb {
         let mut cost = OperationCost::default();
         let key_len = key.max_length() as u32;
         match value {
-            Element::Tree(_, flags) | Element::SumTree(_, _, flags) => {
+            Element::Tree(_, flags)
+            | Element::SumTree(_, _, flags)
+            | Element::CountTree(_, _, flags)
+            | Element::BigSumTree(_, _, flags)
+            | Element::AggregateTree(.., flags) => {
                 let flags_len = flags.as_ref().map_or(0, |flags| {
                     let flags_len = flags.len() as u32;
                     flags_len + flags_len.required_space() as u32
                 });
                 let tree_cost_size = if value.is_sum_tree() {
                     SUM_TREE_COST_SIZE
+                } else if value.is_count_tree() {
+                    COUNT_TREE_COST_SIZE
+                } else if value.is_big_sum_tree() {
+                    BIG_SUM_TREE_COST_SIZE
+                } else if value.is_aggregate_tree() {
+                    AGGREGATE_TREE_COST_SIZE
                 } else {
                     TREE_COST_SIZE
                 };
@@ -232,13 +245,23 @@ impl GroveDb {
         let mut cost = OperationCost::default();
         let key_len = key.max_length() as u32;
         match value {
-            Element::Tree(_, flags) | Element::SumTree(_, _, flags) => {
+            Element::Tree(_, flags)
+            | Element::SumTree(_, _, flags)
+            | Element::CountTree(_, _, flags)
+            | Element::BigSumTree(_, _, flags)
+            | Element::AggregateTree(.., flags) => {
                 let flags_len = flags.as_ref().map_or(0, |flags| {
                     let flags_len = flags.len() as u32;
                     flags_len + flags_len.required_space() as u32
                 });
                 let tree_cost_size = if value.is_sum_tree() {
                     SUM_TREE_COST_SIZE
+                } else if value.is_count_tree() {
+                    COUNT_TREE_COST_SIZE
+                } else if value.is_big_sum_tree() {
+                    BIG_SUM_TREE_COST_SIZE
+                } else if value.is_aggregate_tree() {
+                    AGGREGATE_TREE_COST_SIZE
                 } else {
                     TREE_COST_SIZE
                 };
@@ -284,6 +307,38 @@ impl GroveDb {
         .wrap_with_cost(cost)
     }
 
+    /// Add average case for replacement of an existing element into merk
+    /// The key is first looked up by traversing the tree, loading the node
+    /// holding it from storage
+    pub fn average_case_merk_replace_existing_element(
+        key: &KeyInfo,
+        value: &Element,
+        layer_element_estimates: &EstimatedLayerInformation,
+        propagate: bool,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+        let value_size = cost_return_on_error_no_add!(
+            &cost,
+            layer_element_estimates
+                .estimated_layer_sizes
+                .value_with_feature_and_flags_size()
+                .map_err(Error::MerkError)
+        );
+        add_average_case_get_merk_node(
+            &mut cost,
+            key.max_length() as u32,
+            value_size,
+            layer_element_estimates.is_sum_tree,
+        );
+        Self::average_case_merk_replace_element(
+            key,
+            value,
+            layer_element_estimates.is_sum_tree,
+            propagate.then_some(layer_element_estimates),
+        )
+        .add_cost(cost)
+    }
+
     /// Add average case for patching an element in merk
     /// This only propagates on 1 level
     /// As higher level propagation is done in batching
@@ -326,6 +381,47 @@ impl GroveDb {
         .wrap_with_cost(cost)
     }
 
+    /// Add average case for updating the flags of an element in merk
+    /// The element is first read to rewrite it with the new flags
+    /// This only propagates on 1 level
+    /// As higher level propagation is done in batching
+    pub fn average_case_merk_update_element_flags(
+        key: &KeyInfo,
+        estimated_layer_information: &EstimatedLayerInformation,
+        propagate: bool,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+        let key_len = key.max_length() as u32;
+        let value_len = cost_return_on_error_no_add!(
+            &cost,
+            estimated_layer_information
+                .estimated_layer_sizes
+                .value_with_feature_and_flags_size()
+                .map_err(Error::MerkError)
+        );
+        add_average_case_get_merk_node(
+            &mut cost,
+            key_len,
+            value_len,
+            estimated_layer_information.is_sum_tree,
+        );
+        // on average the flags keep their size, only the element is rewritten
+        add_cost_case_merk_patch(
+            &mut cost,
+            key_len,
+            value_len,
+            0,
+            estimated_layer_information.is_sum_tree,
+        );
+        if propagate {
+            add_average_case_merk_propagate(&mut cost, estimated_layer_information)
+                .map_err(Error::MerkError)
+        } else {
+            Ok(())
+        }
+        .wrap_with_cost(cost)
+    }
+
     /// Add average case for deletion into Merk
     pub fn average_case_merk_delete_element(
         key: &KeyInfo,
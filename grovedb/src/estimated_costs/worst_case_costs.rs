@@ -50,7 +50,10 @@ use integer_encoding::VarInt;
 
 use crate::{
     batch::{key_info::KeyInfo, KeyInfoPath},
-    element::{SUM_ITEM_COST_SIZE, SUM_TREE_COST_SIZE, TREE_COST_SIZE},
+    element::{
+        AGGREGATE_TREE_COST_SIZE, BIG_SUM_TREE_COST_SIZE, COUNT_TREE_COST_SIZE, SUM_ITEM_COST_SIZE,
+        SUM_TREE_COST_SIZE, TREE_COST_SIZE,
+    },
     Element, ElementFlags, Error, GroveDb,
 };
 
@@ -174,13 +177,23 @@ impl GroveDb {
         let mut cost = OperationCost::default();
         let key_len = key.max_length() as u32;
         match value {
-            Element::Tree(_, flags) | Element::SumTree(_, _, flags) => {
+            Element::Tree(_, flags)
+            | Element::SumTree(_, _, flags)
+            | Element::CountTree(_, _, flags)
+            | Element::BigSumTree(_, _, flags)
+            | Element::AggregateTree(.., flags) => {
                 let flags_len = flags.as_ref().map_or(0, |flags| {
                     let flags_len = flags.len() as u32;
                     flags_len + flags_len.required_space() as u32
                 });
                 let tree_cost_size = if value.is_sum_tree() {
                     SUM_TREE_COST_SIZE
+                } else if value.is_count_tree() {
+                    COUNT_TREE_COST_SIZE
+                } else if value.is_big_sum_tree() {
+                    BIG_SUM_TREE_COST_SIZE
+                } else if value.is_aggregate_tree() {
+                    AGGREGATE_TREE_COST_SIZE
                 } else {
                     TREE_COST_SIZE
                 };
@@ -219,13 +232,23 @@ impl GroveDb {
         let mut cost = OperationCost::default();
         let key_len = key.max_length() as u32;
         match value {
-            Element::Tree(_, flags) | Element::SumTree(_, _, flags) => {
+            Element::Tree(_, flags)
+            | Element::SumTree(_, _, flags)
+            | Element::CountTree(_, _, flags)
+            | Element::BigSumTree(_, _, flags)
+            | Element::AggregateTree(.., flags) => {
                 let flags_len = flags.as_ref().map_or(0, |flags| {
                     let flags_len = flags.len() as u32;
                     flags_len + flags_len.required_space() as u32
                 });
                 let tree_cost_size = if value.is_sum_tree() {
                     SUM_TREE_COST_SIZE
+                } else if value.is_count_tree() {
+                    COUNT_TREE_COST_SIZE
+                } else if value.is_big_sum_tree() {
+                    BIG_SUM_TREE_COST_SIZE
+                } else if value.is_aggregate_tree() {
+                    AGGREGATE_TREE_COST_SIZE
                 } else {
                     TREE_COST_SIZE
                 };
@@ -265,6 +288,31 @@ impl GroveDb {
         .wrap_with_cost(cost)
     }
 
+    /// Add worst case for replacement of an existing element in merk
+    /// The key is first looked up by traversing the tree, which in the worst
+    /// case has to load the node holding the biggest value from storage
+    pub fn worst_case_merk_replace_existing_element(
+        key: &KeyInfo,
+        value: &Element,
+        in_parent_tree_using_sums: bool,
+        propagate_for_level: Option<&WorstCaseLayerInformation>,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+        add_worst_case_get_merk_node(
+            &mut cost,
+            key.max_length() as u32,
+            MERK_BIGGEST_VALUE_SIZE,
+            in_parent_tree_using_sums,
+        );
+        Self::worst_case_merk_replace_element(
+            key,
+            value,
+            in_parent_tree_using_sums,
+            propagate_for_level,
+        )
+        .add_cost(cost)
+    }
+
     /// Add worst case for patch in merk
     /// This only propagates on 1 level
     /// As higher level propagation is done in batching
@@ -307,6 +355,44 @@ impl GroveDb {
         .wrap_with_cost(cost)
     }
 
+    /// Add worst case for updating the flags of an element in merk, the
+    /// element is assumed to be of the biggest size and to have had no flags
+    /// The element is first read to rewrite it with the new flags
+    /// This only propagates on 1 level
+    /// As higher level propagation is done in batching
+    pub fn worst_case_merk_update_element_flags(
+        key: &KeyInfo,
+        flags: &Option<ElementFlags>,
+        in_parent_tree_using_sums: bool,
+        propagate_for_level: Option<&WorstCaseLayerInformation>,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+        let key_len = key.max_length() as u32;
+        let flags_len = flags.as_ref().map_or(0, |flags| {
+            let flags_len = flags.len() as u32;
+            flags_len + flags_len.required_space() as u32
+        });
+        add_worst_case_get_merk_node(
+            &mut cost,
+            key_len,
+            MERK_BIGGEST_VALUE_SIZE,
+            in_parent_tree_using_sums,
+        );
+        add_cost_case_merk_patch(
+            &mut cost,
+            key_len,
+            MERK_BIGGEST_VALUE_SIZE + flags_len,
+            flags_len as i32,
+            in_parent_tree_using_sums,
+        );
+        if let Some(level) = propagate_for_level {
+            add_worst_case_merk_propagate(&mut cost, level).map_err(Error::MerkError)
+        } else {
+            Ok(())
+        }
+        .wrap_with_cost(cost)
+    }
+
     /// Add worst case cost for deletion into merk
     pub fn worst_case_merk_delete_element(
         key: &KeyInfo,
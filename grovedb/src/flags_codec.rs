@@ -0,0 +1,200 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Element flags codecs
+//!
+//! A codec registered when opening GroveDb interprets the element flags of
+//! the application: it encodes the flags of inserted elements, merges the
+//! flags of elements updated by batches and splits the storage removed with
+//! an element by its flags. The codec holds no state changing over time, the
+//! epoch of a batch being given in its options.
+
+use grovedb_costs::storage_cost::{
+    removal::{StorageRemovedBytes, StorageRemovedBytes::BasicStorageRemoval},
+    StorageCost,
+};
+use grovedb_merk::Error as MerkError;
+
+use crate::{
+    batch::{GroveDbOp, Op},
+    element::storage_flags::{Epoch, RemovalStrategy, StorageFlags},
+    Element, ElementFlags, Error, GroveDb,
+};
+
+/// Interpreter of element flags
+pub trait ElementFlagsCodec: Send + Sync {
+    /// Encodes the flags of an element about to be inserted, the returned
+    /// flags are stored
+    fn encode(&self, flags: ElementFlags) -> Result<ElementFlags, Error> {
+        Ok(flags)
+    }
+
+    /// Decodes the flags of a removed element into the storage removed for
+    /// its key and for its value
+    fn decode_removed_bytes(
+        &self,
+        _flags: &mut ElementFlags,
+        removed_key_bytes: u32,
+        removed_value_bytes: u32,
+    ) -> Result<(StorageRemovedBytes, StorageRemovedBytes), Error> {
+        Ok((
+            BasicStorageRemoval(removed_key_bytes),
+            BasicStorageRemoval(removed_value_bytes),
+        ))
+    }
+
+    /// Merges the flags of the element replaced by a batch update applied in
+    /// `epoch` into the flags of the new element, returns whether the new
+    /// flags changed
+    fn merge(
+        &self,
+        _epoch: Epoch,
+        _cost: &StorageCost,
+        _old_flags: Option<ElementFlags>,
+        _new_flags: &mut ElementFlags,
+    ) -> Result<bool, Error> {
+        Ok(false)
+    }
+}
+
+/// Codec storing flags as they are given, used when no codec is registered
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RawFlagsCodec;
+
+impl ElementFlagsCodec for RawFlagsCodec {}
+
+/// Codec of `StorageFlags`, paying for added bytes in the epoch of the batch
+#[derive(Debug, Clone, Copy)]
+pub struct StorageFlagsCodec {
+    removal_strategy: RemovalStrategy,
+}
+
+impl StorageFlagsCodec {
+    /// New codec refunding removed bytes with `removal_strategy`
+    pub fn new(removal_strategy: RemovalStrategy) -> Self {
+        StorageFlagsCodec { removal_strategy }
+    }
+}
+
+impl ElementFlagsCodec for StorageFlagsCodec {
+    fn encode(&self, flags: ElementFlags) -> Result<ElementFlags, Error> {
+        StorageFlags::deserialize(&flags)?;
+        Ok(flags)
+    }
+
+    fn decode_removed_bytes(
+        &self,
+        flags: &mut ElementFlags,
+        removed_key_bytes: u32,
+        removed_value_bytes: u32,
+    ) -> Result<(StorageRemovedBytes, StorageRemovedBytes), Error> {
+        StorageFlags::split_removal_bytes(
+            self.removal_strategy,
+            flags,
+            removed_key_bytes,
+            removed_value_bytes,
+        )
+    }
+
+    fn merge(
+        &self,
+        epoch: Epoch,
+        cost: &StorageCost,
+        old_flags: Option<ElementFlags>,
+        new_flags: &mut ElementFlags,
+    ) -> Result<bool, Error> {
+        StorageFlags::update_element_flags(epoch, self.removal_strategy, cost, old_flags, new_flags)
+    }
+}
+
+impl GroveDb {
+    /// The codec interpreting element flags
+    pub fn flags_codec(&self) -> &dyn ElementFlagsCodec {
+        self.flags_codec.as_ref()
+    }
+
+    /// Encodes flags about to be inserted
+    fn encode_flags(&self, flags: &mut Option<ElementFlags>) -> Result<(), Error> {
+        if let Some(given_flags) = flags.take() {
+            *flags = Some(self.flags_codec.encode(given_flags)?);
+        }
+        Ok(())
+    }
+
+    /// Encodes the flags of an element about to be inserted
+    pub(crate) fn encode_element_flags(&self, mut element: Element) -> Result<Element, Error> {
+        self.encode_flags(element.get_flags_mut())?;
+        Ok(element)
+    }
+
+    /// Splits the storage removed with a serialized element by the flags of
+    /// the element
+    pub(crate) fn decode_removed_bytes(
+        &self,
+        value: &[u8],
+        removed_key_bytes: u32,
+        removed_value_bytes: u32,
+    ) -> Result<(StorageRemovedBytes, StorageRemovedBytes), MerkError> {
+        let mut element = Element::deserialize(value)
+            .map_err(|e| MerkError::ClientCorruptionError(e.to_string()))?;
+        match element.get_flags_mut() {
+            None => Ok((
+                BasicStorageRemoval(removed_key_bytes),
+                BasicStorageRemoval(removed_value_bytes),
+            )),
+            Some(flags) => self
+                .flags_codec
+                .decode_removed_bytes(flags, removed_key_bytes, removed_value_bytes)
+                .map_err(|e| MerkError::ClientCorruptionError(e.to_string())),
+        }
+    }
+
+    /// Encodes the flags of the elements inserted by batch operations
+    pub(crate) fn encode_ops_flags(
+        &self,
+        mut ops: Vec<GroveDbOp>,
+    ) -> Result<Vec<GroveDbOp>, Error> {
+        for op in ops.iter_mut() {
+            match &mut op.op {
+                Op::Insert { element }
+                | Op::Replace { element }
+                | Op::Patch { element, .. }
+                | Op::InsertIfNotExists { element }
+                | Op::ReplaceExpecting { element, .. }
+                | Op::DeleteIfValueMatches { expected: element } => {
+                    self.encode_flags(element.get_flags_mut())?
+                }
+                Op::InsertTreeWithRootHash { flags, .. }
+                | Op::RefreshReference { flags, .. }
+                | Op::UpdateFlags { flags } => self.encode_flags(flags)?,
+                _ => {}
+            }
+        }
+        Ok(ops)
+    }
+}
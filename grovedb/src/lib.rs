@@ -143,42 +143,66 @@
 #[cfg(feature = "full")]
 extern crate core;
 
+#[cfg(feature = "async")]
+mod async_grovedb;
 #[cfg(feature = "full")]
 pub mod batch;
+#[cfg(feature = "full")]
+pub mod calibration;
+#[cfg(feature = "full")]
+pub mod conformance;
 #[cfg(any(feature = "full", feature = "verify"))]
 pub mod element;
-#[cfg(any(feature = "full", feature = "verify"))]
-pub mod error;
 #[cfg(feature = "estimated_costs")]
 mod estimated_costs;
+#[cfg(feature = "full")]
+pub mod flags_codec;
+#[cfg(feature = "full")]
+pub mod metrics;
+#[cfg(feature = "full")]
+mod nested_transaction;
 #[cfg(any(feature = "full", feature = "verify"))]
 pub mod operations;
-#[cfg(any(feature = "full", feature = "verify"))]
-mod query;
-#[cfg(any(feature = "full", feature = "verify"))]
-pub mod query_result_type;
-#[cfg(any(feature = "full", feature = "verify"))]
-pub mod reference_path;
 #[cfg(feature = "full")]
-mod replication;
+mod read_snapshot;
+#[cfg(feature = "full")]
+pub mod replication;
+#[cfg(feature = "full")]
+mod secondary_refresh;
+#[cfg(all(feature = "full", any(test, feature = "test_utils")))]
+pub mod test_utils;
 #[cfg(all(test, feature = "full"))]
 mod tests;
 #[cfg(feature = "full")]
+mod transaction;
+#[cfg(feature = "full")]
 mod util;
-mod versioning;
 #[cfg(feature = "full")]
 mod visualize;
 
 #[cfg(feature = "full")]
-use std::{collections::HashMap, option::Option::None, path::Path};
+use std::{
+    collections::HashMap,
+    option::Option::None,
+    path::Path,
+    sync::{atomic::AtomicBool, Arc},
+};
 
-#[cfg(any(feature = "full", feature = "verify"))]
+#[cfg(feature = "async")]
+pub use async_grovedb::{AsyncGroveDb, AsyncQueryStream, BlockingTask};
+#[cfg(feature = "full")]
 use element::helpers;
+#[cfg(feature = "full")]
+pub use element::storage_flags::{RemovalStrategy, StorageFlags};
 #[cfg(any(feature = "full", feature = "verify"))]
 pub use element::Element;
 #[cfg(feature = "full")]
 pub use element::ElementFlags;
 #[cfg(feature = "full")]
+use element::{ElementGetExt, ElementInsertExt};
+#[cfg(feature = "full")]
+pub use flags_codec::{ElementFlagsCodec, RawFlagsCodec, StorageFlagsCodec};
+#[cfg(feature = "full")]
 use grovedb_costs::{
     cost_return_on_error, cost_return_on_error_no_add, CostResult, CostsExt, OperationCost,
 };
@@ -189,22 +213,29 @@ pub use grovedb_merk::estimated_costs::{
     },
     worst_case_costs::WorstCaseLayerInformation,
 };
-#[cfg(any(feature = "full", feature = "verify"))]
-pub use grovedb_merk::proofs::query::query_item::QueryItem;
-#[cfg(any(feature = "full", feature = "verify"))]
-pub use grovedb_merk::proofs::Query;
+#[cfg(feature = "full")]
+pub use grovedb_merk::TreeStats;
 #[cfg(feature = "full")]
 use grovedb_merk::{
     self,
     tree::{combine_hash, value_hash},
     BatchEntry, CryptoHash, KVIterator, Merk,
 };
+#[cfg(feature = "full")]
+pub use grovedb_merk::{TreeAggregate, TreeAggregates};
+#[cfg(feature = "full")]
 use grovedb_path::SubtreePath;
 #[cfg(feature = "full")]
 use grovedb_storage::rocksdb_storage::PrefixedRocksDbImmediateStorageContext;
 #[cfg(feature = "full")]
 use grovedb_storage::rocksdb_storage::RocksDbStorage;
 #[cfg(feature = "full")]
+pub use grovedb_storage::rocksdb_storage::{
+    HierarchyUsage, PrefixScheme, RocksDbMemoryUsage, RocksDbStorageBuilder,
+};
+#[cfg(feature = "full")]
+pub use grovedb_storage::RawIteratorOptions;
+#[cfg(feature = "full")]
 use grovedb_storage::{
     rocksdb_storage::{PrefixedRocksDbStorageContext, PrefixedRocksDbTransactionContext},
     StorageBatch,
@@ -212,41 +243,332 @@ use grovedb_storage::{
 #[cfg(feature = "full")]
 use grovedb_storage::{Storage, StorageContext};
 #[cfg(feature = "full")]
+use grovedb_verify::versioning;
+#[cfg(any(feature = "full", feature = "verify"))]
+pub use grovedb_verify::{
+    error, query_result_type, reference_path, PathQuery, ProofVersion, Query, QueryCursor,
+    QueryItem, SizedQuery,
+};
+#[cfg(feature = "full")]
 use grovedb_visualize::DebugByteVectors;
+#[cfg(feature = "full")]
+pub use nested_transaction::NestedTransaction;
 #[cfg(any(feature = "full", feature = "verify"))]
-pub use query::{PathQuery, SizedQuery};
+pub use operations::aggregate::Aggregate;
+#[cfg(feature = "async")]
+pub use operations::get::QueryStream;
+#[cfg(feature = "full")]
+pub use operations::insertion_metadata::InsertionMetadata;
+#[cfg(feature = "full")]
+pub use operations::scan::{ScanCursor, ScanPage};
+#[cfg(feature = "full")]
+pub use operations::value_size_report::{ValueSizeHistogram, ValueSizeReport};
+#[cfg(feature = "full")]
+pub use read_snapshot::ReadSnapshot;
 #[cfg(feature = "full")]
 pub use replication::{BufferedRestorer, Restorer, SiblingsChunkProducer, SubtreeChunkProducer};
+#[cfg(feature = "full")]
+pub use secondary_refresh::SecondaryRefresh;
+#[cfg(feature = "full")]
+pub use transaction::{StorageTransaction, Transaction};
 
 #[cfg(any(feature = "full", feature = "verify"))]
 pub use crate::error::Error;
 #[cfg(feature = "full")]
 use crate::helpers::raw_decode;
 #[cfg(feature = "full")]
+use crate::metrics::Metrics;
+#[cfg(feature = "full")]
 use crate::util::{root_merk_optional_tx, storage_context_optional_tx};
 
 #[cfg(feature = "full")]
 type Hash = [u8; 32];
 
 /// GroveDb
+///
+/// All operations take `&self` and the handle is `Send` and `Sync`, so it can
+/// be shared between threads, for instance in an `Arc`, without a lock around
+/// it. Readers proceed while a writer works in a transaction, whose writes
+/// they don't see until it is committed.
 pub struct GroveDb {
     #[cfg(feature = "full")]
     db: RocksDbStorage,
+    #[cfg(feature = "full")]
+    metrics: Metrics,
+    #[cfg(feature = "full")]
+    reverse_key_index_enabled: AtomicBool,
+    #[cfg(feature = "full")]
+    prefix_registry_enabled: AtomicBool,
+    #[cfg(feature = "full")]
+    bidirectional_references_in_use: AtomicBool,
+    #[cfg(feature = "full")]
+    referrer_index_enabled: AtomicBool,
+    #[cfg(feature = "audit_log")]
+    audit_log: operations::audit_log::AuditLogState,
+    #[cfg(feature = "full")]
+    flags_codec: Arc<dyn ElementFlagsCodec>,
+    /// Aggregates maintained by the aggregate trees of this GroveDB
+    #[cfg(feature = "full")]
+    tree_aggregates: Arc<TreeAggregates>,
 }
 
-/// Transaction
-#[cfg(feature = "full")]
-pub type Transaction<'db> = <RocksDbStorage as Storage<'db>>::Transaction;
 /// TransactionArg
 #[cfg(feature = "full")]
 pub type TransactionArg<'db, 'a> = Option<&'a Transaction<'db>>;
 
+/// Checks performed when opening a GroveDB, a failed check being reported as
+/// `Error::StorageCorruption`
+#[cfg(feature = "full")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpenVerification {
+    /// Enables RocksDB paranoid checks, validating the manifest and the files
+    pub paranoid_checks: bool,
+    /// Checks that the trees of the root subtree match the root hashes of
+    /// their subtrees, without descending further
+    pub check_root_hash: bool,
+}
+
+/// Options of opening a GroveDB, see [`GroveDb::open_with_options`]
+#[cfg(feature = "full")]
+#[derive(Clone)]
+pub struct OpenOptions {
+    /// Builder of the storage, configuring its prefix scheme or column family
+    /// tuning
+    pub storage_builder: RocksDbStorageBuilder,
+    /// Codec interpreting element flags
+    pub flags_codec: Arc<dyn ElementFlagsCodec>,
+    /// Checks performed when opening
+    pub verification: OpenVerification,
+    /// Aggregates maintained by the aggregate trees. A GroveDB holding
+    /// aggregate trees must always be opened with the same aggregates.
+    pub tree_aggregates: TreeAggregates,
+}
+
+#[cfg(feature = "full")]
+impl Default for OpenOptions {
+    fn default() -> Self {
+        OpenOptions {
+            storage_builder: RocksDbStorageBuilder::new(),
+            flags_codec: Arc::new(RawFlagsCodec),
+            verification: OpenVerification::default(),
+            tree_aggregates: TreeAggregates::default(),
+        }
+    }
+}
+
 #[cfg(feature = "full")]
 impl GroveDb {
     /// Opens a given path
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
-        let db = RocksDbStorage::default_rocksdb_with_path(path)?;
-        Ok(GroveDb { db })
+        Self::open_with_options(path, OpenOptions::default())
+    }
+
+    /// Opens a given path, element flags being interpreted by `flags_codec`
+    pub fn open_with_flags_codec<P: AsRef<Path>>(
+        path: P,
+        flags_codec: Arc<dyn ElementFlagsCodec>,
+    ) -> Result<Self, Error> {
+        Self::open_with_options(
+            path,
+            OpenOptions {
+                flags_codec,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Opens a given path performing the `verification` checks, so a corrupted
+    /// database is reported as `Error::StorageCorruption` instead of failing
+    /// on first use
+    pub fn open_with_verification<P: AsRef<Path>>(
+        path: P,
+        flags_codec: Arc<dyn ElementFlagsCodec>,
+        verification: OpenVerification,
+    ) -> Result<Self, Error> {
+        Self::open_with_options(
+            path,
+            OpenOptions {
+                flags_codec,
+                verification,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Opens a given path, subtree prefixes being derived with a hash keyed by
+    /// `salt` so that databases created with different salts use different
+    /// storage keys for the same paths. The salt is stored on creation, and
+    /// opening an existing database with another salt is an error.
+    pub fn open_with_prefix_salt<P: AsRef<Path>>(path: P, salt: [u8; 32]) -> Result<Self, Error> {
+        Self::open_with_options(
+            path,
+            OpenOptions {
+                storage_builder: RocksDbStorageBuilder::new().prefix_salt(salt),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Opens a given path with a storage configured by `storage_builder`, like
+    /// its prefix scheme or column family tuning, performing the
+    /// `verification` checks
+    pub fn open_with_storage_builder<P: AsRef<Path>>(
+        path: P,
+        storage_builder: RocksDbStorageBuilder,
+        flags_codec: Arc<dyn ElementFlagsCodec>,
+        verification: OpenVerification,
+    ) -> Result<Self, Error> {
+        Self::open_with_options(
+            path,
+            OpenOptions {
+                storage_builder,
+                flags_codec,
+                verification,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Opens a given path with the given `options`
+    pub fn open_with_options<P: AsRef<Path>>(path: P, options: OpenOptions) -> Result<Self, Error> {
+        let OpenOptions {
+            storage_builder,
+            flags_codec,
+            verification,
+            tree_aggregates,
+        } = options;
+        let db = storage_builder
+            .paranoid_checks(verification.paranoid_checks)
+            .open(path)
+            .map_err(Self::storage_open_error)?;
+        let grove_db = Self::with_storage(db, flags_codec, tree_aggregates);
+        if verification.check_root_hash {
+            grove_db.verify_root_subtrees()?;
+        }
+        grove_db.load_state()?;
+        Ok(grove_db)
+    }
+
+    /// Opens a read-only secondary GroveDB following the primary GroveDB at
+    /// `primary_path`, keeping its own logs at `secondary_path`. The
+    /// secondary sees the state of the primary as of the last call to
+    /// [`Self::try_catch_up_with_primary`]. It serves reads and queries
+    /// outside of transactions only: writes are an error, and transactions,
+    /// read snapshots and proofs, which need a transaction, are not available.
+    pub fn open_as_secondary<P: AsRef<Path>>(
+        primary_path: P,
+        secondary_path: P,
+    ) -> Result<Self, Error> {
+        Self::open_as_secondary_with_options(primary_path, secondary_path, OpenOptions::default())
+    }
+
+    /// Opens a read-only secondary GroveDB following the primary GroveDB at
+    /// `primary_path`, see [`Self::open_as_secondary`], with the given
+    /// `options`
+    pub fn open_as_secondary_with_options<P: AsRef<Path>>(
+        primary_path: P,
+        secondary_path: P,
+        options: OpenOptions,
+    ) -> Result<Self, Error> {
+        let OpenOptions {
+            storage_builder,
+            flags_codec,
+            verification,
+            tree_aggregates,
+        } = options;
+        let db = storage_builder
+            .paranoid_checks(verification.paranoid_checks)
+            .open_as_secondary(primary_path, secondary_path)
+            .map_err(Self::storage_open_error)?;
+        let grove_db = Self::with_storage(db, flags_codec, tree_aggregates);
+        if verification.check_root_hash {
+            grove_db.verify_root_subtrees()?;
+        }
+        grove_db.load_state()?;
+        Ok(grove_db)
+    }
+
+    /// Whether this GroveDB is a read-only secondary, see
+    /// [`Self::open_as_secondary`]
+    pub fn is_secondary(&self) -> bool {
+        self.db.is_secondary()
+    }
+
+    /// Catches up a secondary GroveDB with the writes of its primary, which
+    /// become visible to the reads started afterwards. See
+    /// [`Self::refresh_secondary_in_background`] to catch up periodically.
+    pub fn try_catch_up_with_primary(&self) -> Result<(), Error> {
+        self.db.try_catch_up_with_primary()?;
+        self.load_state()
+    }
+
+    /// Reports the errors of opening the storage that concern GroveDB
+    fn storage_open_error(e: grovedb_storage::error::Error) -> Error {
+        match e {
+            grovedb_storage::error::Error::UnknownColumnFamilies(column_families) => {
+                Error::UnsupportedSchemaVersion(format!(
+                    "database has column families {:?} unknown to this version, it was likely \
+                     written by a newer version of GroveDB",
+                    column_families
+                ))
+            }
+            grovedb_storage::error::Error::Corruption(details) => {
+                Error::StorageCorruption { details }
+            }
+            e => e.into(),
+        }
+    }
+
+    /// GroveDB over the storage `db`, whose state still has to be loaded
+    fn with_storage(
+        db: RocksDbStorage,
+        flags_codec: Arc<dyn ElementFlagsCodec>,
+        tree_aggregates: TreeAggregates,
+    ) -> Self {
+        GroveDb {
+            db,
+            metrics: Metrics::default(),
+            reverse_key_index_enabled: AtomicBool::new(false),
+            prefix_registry_enabled: AtomicBool::new(false),
+            bidirectional_references_in_use: AtomicBool::new(false),
+            referrer_index_enabled: AtomicBool::new(false),
+            #[cfg(feature = "audit_log")]
+            audit_log: Default::default(),
+            flags_codec,
+            tree_aggregates: Arc::new(tree_aggregates),
+        }
+    }
+
+    /// Loads the state kept in storage by the optional features
+    fn load_state(&self) -> Result<(), Error> {
+        self.load_reverse_key_index_state()?;
+        self.load_prefix_registry_state()?;
+        self.load_backlinks_state()?;
+        #[cfg(feature = "audit_log")]
+        self.load_audit_log_state()?;
+        Ok(())
+    }
+
+    /// Counters of the operations performed on this GroveDB
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Checks that the aggregate of an aggregate tree about to be written is
+    /// registered on this GroveDB, as nothing could be inserted into the tree
+    /// otherwise
+    pub(crate) fn check_tree_aggregate_is_registered(
+        &self,
+        element: &Element,
+    ) -> Result<(), Error> {
+        match element.aggregate_id() {
+            Some(aggregate_id) => self
+                .tree_aggregates
+                .get(aggregate_id)
+                .map(|_| ())
+                .map_err(Error::MerkError),
+            None => Ok(()),
+        }
     }
 
     /// Opens the transactional Merk at the given path. Returns CostResult.
@@ -281,12 +603,27 @@ impl GroveDb {
                     ))
                 })
             );
-            let is_sum_tree = element.is_sum_tree();
-            if let Element::Tree(root_key, _) | Element::SumTree(root_key, ..) = element {
+            let is_sum_tree = element.uses_sum_nodes();
+            let is_count_tree = element.is_count_tree();
+            let is_big_sum_tree = element.is_big_sum_tree();
+            let aggregate_id = element.aggregate_id();
+            if let Element::Tree(root_key, _)
+            | Element::SumTree(root_key, ..)
+            | Element::CountTree(root_key, ..)
+            | Element::BigSumTree(root_key, ..)
+            | Element::AggregateTree(root_key, ..) = element
+            {
                 Merk::open_layered_with_root_key(storage, root_key, is_sum_tree)
                     .map_err(|_| {
                         Error::CorruptedData("cannot open a subtree with given root key".to_owned())
                     })
+                    .map_ok(|mut merk| {
+                        merk.is_count_tree = is_count_tree;
+                        merk.is_big_sum_tree = is_big_sum_tree;
+                        merk.aggregate_id = aggregate_id;
+                        merk.tree_aggregates = Some(self.tree_aggregates.clone());
+                        merk
+                    })
                     .add_cost(cost)
             } else {
                 Err(Error::CorruptedPath(
@@ -332,13 +669,28 @@ impl GroveDb {
                     ))
                 })
                 .unwrap()?;
-            let is_sum_tree = element.is_sum_tree();
-            if let Element::Tree(root_key, _) | Element::SumTree(root_key, ..) = element {
+            let is_sum_tree = element.uses_sum_nodes();
+            let is_count_tree = element.is_count_tree();
+            let is_big_sum_tree = element.is_big_sum_tree();
+            let aggregate_id = element.aggregate_id();
+            if let Element::Tree(root_key, _)
+            | Element::SumTree(root_key, ..)
+            | Element::CountTree(root_key, ..)
+            | Element::BigSumTree(root_key, ..)
+            | Element::AggregateTree(root_key, ..) = element
+            {
                 Merk::open_layered_with_root_key(storage, root_key, is_sum_tree)
                     .map_err(|_| {
                         Error::CorruptedData("cannot open a subtree with given root key".to_owned())
                     })
                     .unwrap()
+                    .map(|mut merk| {
+                        merk.is_count_tree = is_count_tree;
+                        merk.is_big_sum_tree = is_big_sum_tree;
+                        merk.aggregate_id = aggregate_id;
+                        merk.tree_aggregates = Some(self.tree_aggregates.clone());
+                        merk
+                    })
             } else {
                 Err(Error::CorruptedPath(
                     "cannot open a subtree as parent exists but is not a tree",
@@ -383,12 +735,27 @@ impl GroveDb {
                     ))
                 })
             );
-            let is_sum_tree = element.is_sum_tree();
-            if let Element::Tree(root_key, _) | Element::SumTree(root_key, ..) = element {
+            let is_sum_tree = element.uses_sum_nodes();
+            let is_count_tree = element.is_count_tree();
+            let is_big_sum_tree = element.is_big_sum_tree();
+            let aggregate_id = element.aggregate_id();
+            if let Element::Tree(root_key, _)
+            | Element::SumTree(root_key, ..)
+            | Element::CountTree(root_key, ..)
+            | Element::BigSumTree(root_key, ..)
+            | Element::AggregateTree(root_key, ..) = element
+            {
                 Merk::open_layered_with_root_key(storage, root_key, is_sum_tree)
                     .map_err(|_| {
                         Error::CorruptedData("cannot open a subtree with given root key".to_owned())
                     })
+                    .map_ok(|mut merk| {
+                        merk.is_count_tree = is_count_tree;
+                        merk.is_big_sum_tree = is_big_sum_tree;
+                        merk.aggregate_id = aggregate_id;
+                        merk.tree_aggregates = Some(self.tree_aggregates.clone());
+                        merk
+                    })
                     .add_cost(cost)
             } else {
                 Err(Error::CorruptedPath(
@@ -408,6 +775,42 @@ impl GroveDb {
         self.db.create_checkpoint(path).map_err(|e| e.into())
     }
 
+    /// Writes a fully compacted copy of the committed state to `dst_path`,
+    /// suitable for publishing as a bootstrap database, and returns its root
+    /// hash
+    pub fn clone_compacted<P: AsRef<Path>>(&self, dst_path: P) -> Result<Hash, Error> {
+        self.create_checkpoint(&dst_path)?;
+        let clone = Self::open_with_options(
+            &dst_path,
+            OpenOptions {
+                flags_codec: self.flags_codec.clone(),
+                tree_aggregates: self.tree_aggregates.as_ref().clone(),
+                ..Default::default()
+            },
+        )?;
+        clone.db.compact();
+        clone.root_hash(None).unwrap()
+    }
+
+    /// Returns the memory held by the storage caches. GroveDB itself keeps no
+    /// Merk or prefix cache between operations: Merks opened while applying a
+    /// batch are dropped once it is applied, their retained tree nodes being
+    /// bounded by `BatchApplyOptions::retained_tree_memory_budget`.
+    pub fn memory_usage(&self) -> Result<RocksDbMemoryUsage, Error> {
+        self.db.memory_usage().map_err(|e| e.into())
+    }
+
+    /// Returns the number and size of the storage keys of the subtree at
+    /// `path` and of all its descendants, read with a single range scan per
+    /// column family. Requires the hierarchical prefix scheme.
+    pub fn hierarchy_usage<'b, B, P>(&self, path: P) -> Result<HierarchyUsage, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        self.db.hierarchy_usage(path.into()).map_err(|e| e.into())
+    }
+
     /// Returns root key of GroveDb.
     /// Will be `None` if GroveDb is empty.
     pub fn root_key(&self, transaction: TransactionArg) -> CostResult<Vec<u8>, Error> {
@@ -466,7 +869,9 @@ impl GroveDb {
             );
             let (root_hash, root_key, sum) = cost_return_on_error!(
                 &mut cost,
-                child_tree.root_hash_key_and_sum().map_err(Error::MerkError)
+                child_tree
+                    .root_hash_key_and_sum()
+                    .map_err(Error::from_merk_apply_error)
             );
             cost_return_on_error!(
                 &mut cost,
@@ -513,7 +918,9 @@ impl GroveDb {
             );
             let (root_hash, root_key, sum) = cost_return_on_error!(
                 &mut cost,
-                child_tree.root_hash_key_and_sum().map_err(Error::MerkError)
+                child_tree
+                    .root_hash_key_and_sum()
+                    .map_err(Error::from_merk_apply_error)
             );
             cost_return_on_error!(
                 &mut cost,
@@ -558,7 +965,9 @@ impl GroveDb {
             );
             let (root_hash, root_key, sum) = cost_return_on_error!(
                 &mut cost,
-                child_tree.root_hash_key_and_sum().map_err(Error::MerkError)
+                child_tree
+                    .root_hash_key_and_sum()
+                    .map_err(Error::from_merk_apply_error)
             );
             cost_return_on_error!(
                 &mut cost,
@@ -582,7 +991,7 @@ impl GroveDb {
         key: K,
         maybe_root_key: Option<Vec<u8>>,
         root_tree_hash: Hash,
-        sum: Option<i64>,
+        sum: Option<i128>,
     ) -> CostResult<(), Error> {
         let key_ref = key.as_ref();
 
@@ -592,11 +1001,33 @@ impl GroveDb {
                 tree.insert_subtree(parent_tree, key_ref, root_tree_hash, None)
             } else if let Element::SumTree(.., flag) = element {
                 let tree = Element::new_sum_tree_with_flags_and_sum_value(
+                    maybe_root_key,
+                    sum.unwrap_or_default() as i64,
+                    flag,
+                );
+                tree.insert_subtree(parent_tree, key.as_ref(), root_tree_hash, None)
+            } else if let Element::CountTree(.., flag) = element {
+                let tree = Element::new_count_tree_with_flags_and_count_value(
+                    maybe_root_key,
+                    sum.unwrap_or_default() as u64,
+                    flag,
+                );
+                tree.insert_subtree(parent_tree, key.as_ref(), root_tree_hash, None)
+            } else if let Element::BigSumTree(.., flag) = element {
+                let tree = Element::new_big_sum_tree_with_flags_and_sum_value(
                     maybe_root_key,
                     sum.unwrap_or_default(),
                     flag,
                 );
                 tree.insert_subtree(parent_tree, key.as_ref(), root_tree_hash, None)
+            } else if let Element::AggregateTree(_, aggregate_id, _, flag) = element {
+                let tree = Element::new_aggregate_tree_with_flags_and_aggregate_value(
+                    maybe_root_key,
+                    aggregate_id,
+                    sum,
+                    flag,
+                );
+                tree.insert_subtree(parent_tree, key.as_ref(), root_tree_hash, None)
             } else {
                 Err(Error::InvalidPath(
                     "can only propagate on tree items".to_owned(),
@@ -617,7 +1048,7 @@ impl GroveDb {
         key: K,
         maybe_root_key: Option<Vec<u8>>,
         root_tree_hash: Hash,
-        sum: Option<i64>,
+        sum: Option<i128>,
         batch_operations: &mut Vec<BatchEntry<K>>,
     ) -> CostResult<(), Error> {
         let mut cost = OperationCost::default();
@@ -626,7 +1057,7 @@ impl GroveDb {
                 let tree = Element::new_tree_with_flags(maybe_root_key, flag);
                 let merk_feature_type = cost_return_on_error!(
                     &mut cost,
-                    tree.get_feature_type(parent_tree.is_sum_tree)
+                    tree.get_feature_type_in_merk(parent_tree)
                         .wrap_with_cost(OperationCost::default())
                 );
                 tree.insert_subtree_into_batch_operations(
@@ -638,13 +1069,68 @@ impl GroveDb {
                 )
             } else if let Element::SumTree(.., flag) = element {
                 let tree = Element::new_sum_tree_with_flags_and_sum_value(
+                    maybe_root_key,
+                    sum.unwrap_or_default() as i64,
+                    flag,
+                );
+                let merk_feature_type = cost_return_on_error!(
+                    &mut cost,
+                    tree.get_feature_type_in_merk(parent_tree)
+                        .wrap_with_cost(OperationCost::default())
+                );
+                tree.insert_subtree_into_batch_operations(
+                    key,
+                    root_tree_hash,
+                    true,
+                    batch_operations,
+                    merk_feature_type,
+                )
+            } else if let Element::CountTree(.., flag) = element {
+                let tree = Element::new_count_tree_with_flags_and_count_value(
+                    maybe_root_key,
+                    sum.unwrap_or_default() as u64,
+                    flag,
+                );
+                let merk_feature_type = cost_return_on_error!(
+                    &mut cost,
+                    tree.get_feature_type_in_merk(parent_tree)
+                        .wrap_with_cost(OperationCost::default())
+                );
+                tree.insert_subtree_into_batch_operations(
+                    key,
+                    root_tree_hash,
+                    true,
+                    batch_operations,
+                    merk_feature_type,
+                )
+            } else if let Element::BigSumTree(.., flag) = element {
+                let tree = Element::new_big_sum_tree_with_flags_and_sum_value(
                     maybe_root_key,
                     sum.unwrap_or_default(),
                     flag,
                 );
                 let merk_feature_type = cost_return_on_error!(
                     &mut cost,
-                    tree.get_feature_type(parent_tree.is_sum_tree)
+                    tree.get_feature_type_in_merk(parent_tree)
+                        .wrap_with_cost(OperationCost::default())
+                );
+                tree.insert_subtree_into_batch_operations(
+                    key,
+                    root_tree_hash,
+                    true,
+                    batch_operations,
+                    merk_feature_type,
+                )
+            } else if let Element::AggregateTree(_, aggregate_id, _, flag) = element {
+                let tree = Element::new_aggregate_tree_with_flags_and_aggregate_value(
+                    maybe_root_key,
+                    aggregate_id,
+                    sum,
+                    flag,
+                );
+                let merk_feature_type = cost_return_on_error!(
+                    &mut cost,
+                    tree.get_feature_type_in_merk(parent_tree)
                         .wrap_with_cost(OperationCost::default())
                 );
                 tree.insert_subtree_into_batch_operations(
@@ -705,7 +1191,8 @@ impl GroveDb {
     }
 
     /// Starts database transaction. Please note that you have to start
-    /// underlying storage transaction manually.
+    /// underlying storage transaction manually. A read-only secondary GroveDB
+    /// can't start transactions and returns a read-only storage error.
     ///
     /// ## Examples:
     /// ```
@@ -729,7 +1216,7 @@ impl GroveDb {
     /// )
     /// .unwrap()?;
     ///
-    /// let tx = db.start_transaction();
+    /// let tx = db.start_transaction()?;
     ///
     /// let subtree_key = b"subtree_key";
     /// db.insert(
@@ -759,23 +1246,75 @@ impl GroveDb {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn start_transaction(&self) -> Transaction {
-        self.db.start_transaction()
+    pub fn start_transaction(&self) -> Result<Transaction, Error> {
+        Ok(Transaction::new(self.db.start_transaction()?))
     }
 
     /// Commits previously started db transaction. For more details on the
     /// transaction usage, please check [`GroveDb::start_transaction`]
     pub fn commit_transaction(&self, transaction: Transaction) -> CostResult<(), Error> {
-        self.db.commit_transaction(transaction).map_err(Into::into)
+        self.db
+            .commit_transaction(transaction.into_inner())
+            .map_err(Into::into)
     }
 
     /// Rollbacks previously started db transaction to initial state.
     /// For more details on the transaction usage, please check
     /// [`GroveDb::start_transaction`]
     pub fn rollback_transaction(&self, transaction: &Transaction) -> Result<(), Error> {
+        transaction
+            .savepoints
+            .lock()
+            .expect("savepoints lock")
+            .clear();
         Ok(self.db.rollback_transaction(transaction)?)
     }
 
+    /// Records the current state of a transaction so that later changes can
+    /// be undone with [`GroveDb::rollback_to_savepoint`] without discarding
+    /// the whole transaction. Savepoints stack, so nested sub-operations can
+    /// each set their own, and each savepoint is either rolled back to or
+    /// released with [`GroveDb::release_savepoint`].
+    pub fn set_savepoint(&self, transaction: &Transaction) {
+        let mut savepoints = transaction.savepoints.lock().expect("savepoints lock");
+        self.db.set_transaction_savepoint(transaction);
+        savepoints.push(true);
+    }
+
+    /// Rolls back a transaction to its most recent savepoint and removes that
+    /// savepoint, along with the released savepoints set after it. Fails if no
+    /// savepoint was set.
+    pub fn rollback_to_savepoint(&self, transaction: &Transaction) -> Result<(), Error> {
+        let mut savepoints = transaction.savepoints.lock().expect("savepoints lock");
+        if !savepoints.contains(&true) {
+            return Err(Error::InvalidInput("no savepoint to roll back to"));
+        }
+        // Rolling back to a released savepoint first undoes the changes made
+        // since it, which belong to the savepoint set before it
+        while let Some(live) = savepoints.pop() {
+            self.db.rollback_transaction_to_savepoint(transaction)?;
+            if live {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the most recent savepoint of a transaction not released yet,
+    /// keeping the changes made since it. The storage can't drop savepoints, so it is
+    /// only marked as released and is rolled back through when rolling back to
+    /// the savepoint set before it. Fails if no savepoint was set.
+    pub fn release_savepoint(&self, transaction: &Transaction) -> Result<(), Error> {
+        let mut savepoints = transaction.savepoints.lock().expect("savepoints lock");
+        let live = savepoints
+            .iter_mut()
+            .rev()
+            .find(|live| **live)
+            .ok_or(Error::InvalidInput("no savepoint to release"))?;
+        *live = false;
+        Ok(())
+    }
+
     /// Method to visualize hash mismatch after verification
     pub fn visualize_verify_grovedb(&self) -> HashMap<String, (String, String, String)> {
         self.verify_grovedb()
@@ -852,4 +1391,52 @@ impl GroveDb {
         }
         issues
     }
+
+    /// Checks that the value hashes of the trees of the root subtree match
+    /// the root hashes of those trees, without descending further.
+    fn verify_root_subtrees(&self) -> Result<(), Error> {
+        let corruption = |details: String| Error::StorageCorruption { details };
+        let root_merk = self
+            .open_non_transactional_merk_at_path(SubtreePath::empty(), None)
+            .unwrap()?;
+
+        let mut all_query = Query::new();
+        all_query.insert_all();
+        let mut element_iterator =
+            KVIterator::new(root_merk.storage.raw_iter(), &all_query).unwrap();
+
+        while let Some((key, element_value)) = element_iterator.next_kv().unwrap() {
+            let element = raw_decode(&element_value).map_err(|e| {
+                corruption(format!(
+                    "undecodable root element {}: {}",
+                    hex::encode(&key),
+                    e
+                ))
+            })?;
+            if !element.is_tree() {
+                continue;
+            }
+            let (kv_value, element_value_hash) = root_merk
+                .get_value_and_value_hash(&key, true)
+                .unwrap()
+                .map_err(Error::MerkError)?
+                .ok_or_else(|| corruption(format!("missing root tree {}", hex::encode(&key))))?;
+            let inner_merk = self
+                .open_non_transactional_merk_at_path([key.as_slice()].as_ref().into(), None)
+                .unwrap()?;
+            let root_hash = inner_merk.root_hash().unwrap();
+
+            let actual_value_hash = value_hash(&kv_value).unwrap();
+            let combined_value_hash = combine_hash(&actual_value_hash, &root_hash).unwrap();
+            if combined_value_hash != element_value_hash {
+                return Err(corruption(format!(
+                    "root hash mismatch for tree {}: expected {}, stored {}",
+                    hex::encode(&key),
+                    hex::encode(combined_value_hash),
+                    hex::encode(element_value_hash)
+                )));
+            }
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,189 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Operation metrics
+
+use std::{cell::Cell, collections::BTreeMap, sync::Mutex};
+
+use grovedb_merk::tree::value_hash;
+use grovedb_path::SubtreePath;
+
+/// Kind of a counted operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperationKind {
+    /// Element read
+    Read,
+    /// Element insertion or deletion, counted once it is committed
+    Write,
+    /// Path query
+    Query,
+}
+
+/// Number of operations of each kind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OperationCounters {
+    /// Element reads
+    pub reads: u64,
+    /// Element insertions and deletions
+    pub writes: u64,
+    /// Path queries
+    pub queries: u64,
+}
+
+impl OperationCounters {
+    fn increment(&mut self, kind: OperationKind) {
+        let counter = match kind {
+            OperationKind::Read => &mut self.reads,
+            OperationKind::Write => &mut self.writes,
+            OperationKind::Query => &mut self.queries,
+        };
+        *counter = counter.saturating_add(1);
+    }
+}
+
+/// How the top-level path segment of an operation is turned into a tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathTagMode {
+    /// Hex encoding of at most the given number of leading bytes of the
+    /// segment
+    Truncated(usize),
+    /// Hex encoding of the first 8 bytes of the segment's hash, for segments
+    /// such as identifiers whose prefixes don't discriminate
+    Hashed,
+}
+
+impl PathTagMode {
+    fn tag(&self, segment: &[u8]) -> String {
+        match self {
+            PathTagMode::Truncated(len) => hex::encode(&segment[..segment.len().min(*len)]),
+            PathTagMode::Hashed => hex::encode(&value_hash(segment).unwrap()[..8]),
+        }
+    }
+}
+
+/// Configuration of counters tagged by top-level path segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathTagging {
+    /// How segments are turned into tags
+    pub mode: PathTagMode,
+    /// Maximum number of distinct tags, operations on top-level trees beyond
+    /// it are counted as over the cardinality limit
+    pub max_tags: usize,
+}
+
+/// Snapshot of the operation counters
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// Counters of all operations
+    pub total: OperationCounters,
+    /// Counters by top-level path segment tag, empty unless path tagging is
+    /// enabled
+    pub by_path_tag: BTreeMap<String, OperationCounters>,
+    /// Counters of operations whose tag didn't fit under the cardinality limit
+    pub over_cardinality_limit: OperationCounters,
+}
+
+thread_local! {
+    static SUSPENDED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Counters of the operations performed on a GroveDB
+#[derive(Debug, Default)]
+pub struct Metrics {
+    state: Mutex<MetricsState>,
+}
+
+#[derive(Debug, Default)]
+struct MetricsState {
+    path_tagging: Option<PathTagging>,
+    snapshot: MetricsSnapshot,
+}
+
+impl Metrics {
+    /// Enables or disables counting operations by top-level path segment,
+    /// clearing the counters gathered by path tag so far
+    pub fn set_path_tagging(&self, path_tagging: Option<PathTagging>) {
+        let mut state = self.state.lock().expect("metrics lock");
+        state.path_tagging = path_tagging;
+        state.snapshot.by_path_tag.clear();
+        state.snapshot.over_cardinality_limit = OperationCounters::default();
+    }
+
+    /// Current values of the counters
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.state.lock().expect("metrics lock").snapshot.clone()
+    }
+
+    /// Resets all the counters to zero
+    pub fn reset(&self) {
+        self.state.lock().expect("metrics lock").snapshot = MetricsSnapshot::default();
+    }
+
+    /// Counts an operation at the given path and key, tagging it with the
+    /// top-level segment, which is the key itself for operations on the root
+    /// tree
+    pub(crate) fn record<B: AsRef<[u8]>>(
+        &self,
+        kind: OperationKind,
+        path: &SubtreePath<B>,
+        key: Option<&[u8]>,
+    ) {
+        let top_level_segment = path.clone().into_reverse_iter().last().or(key);
+        self.record_segment(kind, top_level_segment);
+    }
+
+    /// Runs `f` without counting the operations it performs on the current
+    /// thread, for operations which are rolled back
+    pub(crate) fn suspended<T>(f: impl FnOnce() -> T) -> T {
+        let was_suspended = SUSPENDED.with(|suspended| suspended.replace(true));
+        let result = f();
+        SUSPENDED.with(|suspended| suspended.set(was_suspended));
+        result
+    }
+
+    /// Counts an operation on the given top-level segment
+    pub(crate) fn record_segment(&self, kind: OperationKind, top_level_segment: Option<&[u8]>) {
+        if SUSPENDED.with(Cell::get) {
+            return;
+        }
+        let mut state = self.state.lock().expect("metrics lock");
+        state.snapshot.total.increment(kind);
+        let (Some(path_tagging), Some(segment)) = (state.path_tagging, top_level_segment) else {
+            return;
+        };
+        let tag = path_tagging.mode.tag(segment);
+        let snapshot = &mut state.snapshot;
+        if let Some(counters) = snapshot.by_path_tag.get_mut(&tag) {
+            counters.increment(kind);
+        } else if snapshot.by_path_tag.len() < path_tagging.max_tags {
+            snapshot.by_path_tag.entry(tag).or_default().increment(kind);
+        } else {
+            snapshot.over_cardinality_limit.increment(kind);
+        }
+    }
+}
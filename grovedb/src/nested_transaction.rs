@@ -0,0 +1,103 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Nested transactions
+//!
+//! A nested transaction is a child of an existing transaction built on top
+//! of its savepoints. Writes are made on the parent transaction, committing
+//! the child releases its savepoint and keeps the writes in the parent, and
+//! rolling the child back discards only the changes made since it was
+//! started.
+
+use crate::{Error, GroveDb, Transaction};
+
+/// Child of a transaction whose changes can be discarded without discarding
+/// the changes of its parent.
+///
+/// Operations take part in a nested transaction by being given
+/// [`NestedTransaction::transaction`]. Savepoints they set must be rolled
+/// back or released before the nested transaction ends. A nested transaction
+/// that is dropped without being committed is rolled back.
+pub struct NestedTransaction<'a, 'db: 'a> {
+    db: &'a GroveDb,
+    transaction: &'a Transaction<'db>,
+    finished: bool,
+}
+
+impl GroveDb {
+    /// Starts a nested transaction on top of `transaction`
+    pub fn start_nested_transaction<'a, 'db>(
+        &'a self,
+        transaction: &'a Transaction<'db>,
+    ) -> NestedTransaction<'a, 'db> {
+        NestedTransaction::new(self, transaction)
+    }
+}
+
+impl<'a, 'db: 'a> NestedTransaction<'a, 'db> {
+    fn new(db: &'a GroveDb, transaction: &'a Transaction<'db>) -> Self {
+        db.set_savepoint(transaction);
+        NestedTransaction {
+            db,
+            transaction,
+            finished: false,
+        }
+    }
+
+    /// Transaction to pass to operations made within the nested transaction
+    pub fn transaction(&self) -> &'a Transaction<'db> {
+        self.transaction
+    }
+
+    /// Starts a nested transaction on top of this one
+    pub fn start_nested_transaction(&self) -> NestedTransaction<'_, 'db> {
+        NestedTransaction::new(self.db, self.transaction)
+    }
+
+    /// Keeps the changes of the nested transaction in its parent
+    pub fn commit(mut self) -> Result<(), Error> {
+        self.finished = true;
+        self.db.release_savepoint(self.transaction)
+    }
+
+    /// Discards the changes made since the nested transaction was started
+    pub fn rollback(mut self) -> Result<(), Error> {
+        self.finished = true;
+        self.db.rollback_to_savepoint(self.transaction)
+    }
+}
+
+impl Drop for NestedTransaction<'_, '_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            // Errors can't be reported from a drop, an explicit rollback
+            // should be used to handle them
+            let _ = self.db.rollback_to_savepoint(self.transaction);
+        }
+    }
+}
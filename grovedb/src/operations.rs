@@ -28,15 +28,59 @@
 
 //! Operations for the manipulation of GroveDB state
 
+#[cfg(any(feature = "full", feature = "verify"))]
+pub mod aggregate;
+#[cfg(feature = "full")]
+pub mod append_log;
+#[cfg(feature = "audit_log")]
+pub mod audit_log;
+#[cfg(feature = "full")]
+pub(crate) mod auto_key;
 #[cfg(feature = "full")]
 pub(crate) mod auxiliary;
 #[cfg(feature = "full")]
+pub(crate) mod backlinks;
+#[cfg(feature = "full")]
+pub(crate) mod copy_subtree;
+#[cfg(feature = "full")]
+pub(crate) mod count_in_subtree;
+#[cfg(feature = "full")]
+pub(crate) mod counter;
+#[cfg(feature = "full")]
+pub(crate) mod create_path;
+#[cfg(feature = "full")]
 pub mod delete;
 #[cfg(feature = "full")]
 pub(crate) mod get;
 #[cfg(feature = "full")]
 pub mod insert;
 #[cfg(feature = "full")]
+pub mod insertion_metadata;
+#[cfg(feature = "full")]
 pub(crate) mod is_empty_tree;
+#[cfg(feature = "full")]
+pub(crate) mod last_modified;
+#[cfg(feature = "full")]
+pub(crate) mod prefetch;
+#[cfg(feature = "full")]
+pub(crate) mod prefix_registry;
 #[cfg(any(feature = "full", feature = "verify"))]
 pub mod proof;
+#[cfg(feature = "full")]
+pub(crate) mod rename;
+#[cfg(feature = "full")]
+pub(crate) mod reverse_key_index;
+#[cfg(feature = "full")]
+pub mod scan;
+#[cfg(feature = "full")]
+pub mod snapshot;
+#[cfg(feature = "full")]
+pub(crate) mod subtree_stats;
+#[cfg(feature = "full")]
+pub(crate) mod sum_of_subtree;
+#[cfg(feature = "full")]
+pub(crate) mod update;
+#[cfg(feature = "full")]
+pub(crate) mod update_flags;
+#[cfg(feature = "full")]
+pub mod value_size_report;
@@ -0,0 +1,88 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Aggregate queries
+//!
+//! Aggregate queries return a single value computed over the elements a path
+//! query matches instead of the elements themselves. Their proofs let a
+//! client check the aggregate against a root hash. The count of all the
+//! elements of a count tree is read from, and proven with, the count tree
+//! element. Other counts are proven with keys only proofs, which don't carry
+//! the counted elements but hold their keys, so they grow linearly with the
+//! count.
+
+#[cfg(feature = "full")]
+use grovedb_costs::{cost_return_on_error, CostResult, CostsExt, OperationCost};
+pub use grovedb_verify::aggregate::Aggregate;
+#[cfg(feature = "full")]
+use grovedb_verify::aggregate::{matches_whole_subtree, total_of_sum_items};
+
+#[cfg(feature = "full")]
+use crate::{Element, Error, GroveDb, PathQuery, TransactionArg};
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Computes `aggregate` over the elements matched by `path_query`
+    pub fn query_aggregate(
+        &self,
+        path_query: &PathQuery,
+        aggregate: Aggregate,
+        transaction: TransactionArg,
+    ) -> CostResult<i64, Error> {
+        let mut cost = OperationCost::default();
+
+        let result = match aggregate {
+            Aggregate::Count => {
+                if let Some((key, parent_path)) = path_query
+                    .path
+                    .split_last()
+                    .filter(|_| matches_whole_subtree(path_query))
+                {
+                    let element = cost_return_on_error!(
+                        &mut cost,
+                        self.get_raw_optional(parent_path.into(), key, transaction)
+                    );
+                    if let Some(Element::CountTree(_, count, _)) = element {
+                        return Ok(count as i64).wrap_with_cost(cost);
+                    }
+                }
+                let keys =
+                    cost_return_on_error!(&mut cost, self.query_path_keys(path_query, transaction));
+                Ok(keys.len() as i64)
+            }
+            Aggregate::Sum => {
+                let (values, _) = cost_return_on_error!(
+                    &mut cost,
+                    self.query_sums(path_query, true, transaction)
+                );
+                total_of_sum_items(values)
+            }
+        };
+        result.wrap_with_cost(cost)
+    }
+}
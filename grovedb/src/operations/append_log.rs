@@ -0,0 +1,253 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Append-only log subtrees
+//!
+//! An append-only log is an ordinary subtree whose entries are stored under
+//! the auto-increment keys of the subtree, used as sequence numbers. The
+//! bounds of the log are kept in the auxiliary storage of the subtree, so
+//! sequence numbers are never reused, even after all entries were truncated.
+
+#[cfg(feature = "full")]
+use grovedb_costs::{
+    cost_return_on_error, cost_return_on_error_no_add, CostResult, CostsExt, OperationCost,
+};
+#[cfg(feature = "full")]
+use grovedb_merk::proofs::{query::query_item::QueryItem, Query};
+use grovedb_path::SubtreePath;
+#[cfg(feature = "full")]
+use grovedb_storage::{Storage, StorageBatch, StorageContext};
+
+#[cfg(feature = "full")]
+use crate::{
+    batch::GroveDbOp, metrics::OperationKind, operations::auto_key::decode_auto_key,
+    query_result_type::QueryResultType::QueryKeyElementPairResultType,
+    transaction::storage_transaction, util::storage_context_optional_tx, Element, Error, GroveDb,
+    PathQuery, SizedQuery, TransactionArg,
+};
+
+/// Aux key of the sequence number of the first entry of a log
+#[cfg(feature = "full")]
+const FIRST_SEQ_AUX_KEY: &[u8] = b"append_log/first";
+
+#[cfg(feature = "full")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// Bounds of an append-only log
+pub struct AppendLogBounds {
+    /// Sequence number of the first entry that wasn't truncated
+    pub first: u64,
+    /// Sequence number the next appended entry will get
+    pub next: u64,
+}
+
+#[cfg(feature = "full")]
+impl AppendLogBounds {
+    /// Number of entries in the log
+    pub fn len(&self) -> u64 {
+        self.next - self.first
+    }
+
+    /// Whether the log has no entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Returns the bounds of the append-only log at `path`
+    pub fn append_log_bounds<'b, B, P>(
+        &self,
+        path: P,
+        transaction: TransactionArg,
+    ) -> CostResult<AppendLogBounds, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        let next = cost_return_on_error!(&mut cost, self.next_auto_key(path.clone(), transaction));
+        storage_context_optional_tx!(self.db, path, None, transaction, storage, {
+            let first = cost_return_on_error!(
+                &mut cost,
+                storage
+                    .unwrap_add_cost(&mut cost)
+                    .get_aux(FIRST_SEQ_AUX_KEY)
+                    .map_err(Into::into)
+            );
+            let first = cost_return_on_error_no_add!(
+                &cost,
+                first.map_or(Ok(0), |first| decode_auto_key(&first))
+            );
+            Ok(AppendLogBounds { first, next }).wrap_with_cost(cost)
+        })
+    }
+
+    /// Appends `element` to the append-only log at `path`, returning its
+    /// sequence number. Trees can't be appended.
+    pub fn append<'b, B, P>(
+        &self,
+        path: P,
+        element: Element,
+        transaction: TransactionArg,
+    ) -> CostResult<u64, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        if element.is_tree() {
+            return Err(Error::InvalidInput("trees can't be appended to a log"))
+                .wrap_with_cost(cost);
+        }
+        let key =
+            cost_return_on_error!(&mut cost, self.insert_auto_key(path, element, transaction));
+        decode_auto_key(&key).wrap_with_cost(cost)
+    }
+
+    /// Reads up to `limit` entries of the append-only log at `path`, starting
+    /// with the entry with sequence number `from_seq`
+    pub fn read_range<'b, B, P>(
+        &self,
+        path: P,
+        from_seq: u64,
+        limit: u16,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<(u64, Element)>, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        let path_query = PathQuery::new(
+            path.to_vec(),
+            SizedQuery::new(
+                Query::new_single_query_item(QueryItem::RangeFrom(
+                    from_seq.to_be_bytes().to_vec()..,
+                )),
+                Some(limit),
+                None,
+            ),
+        );
+        let (results, _) = cost_return_on_error!(
+            &mut cost,
+            self.query_raw(
+                &path_query,
+                true,
+                QueryKeyElementPairResultType,
+                transaction
+            )
+        );
+
+        results
+            .to_key_elements()
+            .into_iter()
+            .map(|(key, element)| Ok((decode_auto_key(&key)?, element)))
+            .collect::<Result<Vec<_>, Error>>()
+            .wrap_with_cost(cost)
+    }
+
+    /// Removes the entries of the append-only log at `path` with sequence
+    /// numbers lower than `before_seq`. Returns the number of removed
+    /// entries.
+    ///
+    /// Truncating every entry clears the log subtree with
+    /// [GroveDb::clear_subtree], deleting its storage over the subtree prefix.
+    pub fn truncate_front<'b, B, P>(
+        &self,
+        path: P,
+        before_seq: u64,
+        transaction: TransactionArg,
+    ) -> CostResult<u64, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        let bounds =
+            cost_return_on_error!(&mut cost, self.append_log_bounds(path.clone(), transaction));
+        let new_first = before_seq.min(bounds.next);
+        if new_first <= bounds.first {
+            return Ok(0).wrap_with_cost(cost);
+        }
+
+        let recorded_path = path.clone();
+        let storage_batch = StorageBatch::new();
+        if new_first == bounds.next && !path.is_root() {
+            // Every entry goes, so the log's storage is deleted over its whole
+            // prefix instead of unlinking each entry from the Merk
+            cost_return_on_error!(
+                &mut cost,
+                self.clear_subtree_into_storage_batch(
+                    path.clone(),
+                    None,
+                    &storage_batch,
+                    transaction
+                )
+            );
+        } else {
+            // Remaining entries stay in the Merk, which has to unlink every
+            // removed node and rebalance, so entries are deleted in one batch
+            let path_vec = path.to_vec();
+            let ops = (bounds.first..new_first)
+                .map(|seq| GroveDbOp::delete_op(path_vec.clone(), seq.to_be_bytes().to_vec()))
+                .collect();
+            cost_return_on_error!(
+                &mut cost,
+                self.apply_batch_into_storage_batch(ops, &storage_batch, transaction)
+            );
+        }
+        storage_context_optional_tx!(self.db, path, Some(&storage_batch), transaction, storage, {
+            cost_return_on_error!(
+                &mut cost,
+                storage
+                    .unwrap_add_cost(&mut cost)
+                    .put_aux(FIRST_SEQ_AUX_KEY, &new_first.to_be_bytes(), None)
+                    .map_err(Into::into)
+            );
+        });
+
+        self.db
+            .commit_multi_context_batch(storage_batch, storage_transaction(transaction))
+            .map_err(Into::into)
+            .map_ok(|_| {
+                self.metrics
+                    .record(OperationKind::Write, &recorded_path, None);
+                new_first - bounds.first
+            })
+            .add_cost(cost)
+    }
+}
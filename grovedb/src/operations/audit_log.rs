@@ -0,0 +1,771 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Mutation audit log
+//!
+//! With the `audit_log` feature, every mutation committed through GroveDB
+//! appends a record to a log kept in the meta storage of the root subtree,
+//! out of the auxiliary storage of users. A record lists the path, key, kind
+//! of operation and value hash of every element written or deleted, together
+//! with the cost of the mutation and the root hash it was applied on. Each
+//! record commits to the hash of the previous one, so altering or removing
+//! records still in the log breaks the chain checked by `verify_audit_log`.
+//! Pruning drops the oldest records and keeps the hash of the last pruned one
+//! as the anchor of the chain.
+//!
+//! A mutation stages its record as pending, under a key of its own, in the
+//! same storage batch as the mutation, so the record is committed if and
+//! only if the mutation is, and concurrent mutations or transactions never
+//! write the same audit log keys: the log adds no conflict to the ones of the
+//! mutations themselves. Committed pending records get their sequence numbers
+//! and are chained one writer at a time, before the log is read or pruned.
+//! Bookkeeping is not charged to mutations: the pending record is written
+//! with prepaid storage and the seek of its write is taken out of the cost
+//! of the mutation.
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+};
+
+use grovedb_costs::{
+    cost_return_on_error, cost_return_on_error_no_add,
+    storage_cost::{
+        key_value_cost::KeyValueStorageCost, removal::StorageRemovedBytes::BasicStorageRemoval,
+        StorageCost,
+    },
+    CostResult, CostsExt, OperationCost,
+};
+use grovedb_merk::tree::{value_hash, NULL_HASH};
+use grovedb_path::SubtreePath;
+use grovedb_storage::{RawIterator, Storage, StorageBatch, StorageContext};
+
+use crate::{
+    batch::{GroveDbOp, Op},
+    operations::append_log::AppendLogBounds,
+    util::meta_storage_context_optional_tx,
+    Element, Error, GroveDb, Hash, TransactionArg,
+};
+
+/// Meta key of the sequence number of the first record of the log
+const FIRST_SEQ_META_KEY: &[u8] = b"audit_log/first";
+
+/// Meta key of the sequence number of the next record of the log
+const NEXT_SEQ_META_KEY: &[u8] = b"audit_log/next";
+
+/// Meta key of the hash of the last record appended to the log
+const LAST_HASH_META_KEY: &[u8] = b"audit_log/last_hash";
+
+/// Meta key of the hash of the last pruned record, anchoring the chain
+const ANCHOR_META_KEY: &[u8] = b"audit_log/anchor";
+
+/// Prefix of the meta keys of the records
+const RECORD_META_PREFIX: &[u8] = b"audit_log/record/";
+
+/// Prefix of the meta keys of the records not yet appended to the log
+const PENDING_META_PREFIX: &[u8] = b"audit_log/pending/";
+
+/// Meta key of the record with sequence number `sequence`
+fn record_meta_key(sequence: u64) -> Vec<u8> {
+    let mut meta_key = RECORD_META_PREFIX.to_vec();
+    meta_key.extend_from_slice(&sequence.to_be_bytes());
+    meta_key
+}
+
+/// Meta key of the pending record with id `id`
+fn pending_meta_key(id: u64) -> Vec<u8> {
+    let mut meta_key = PENDING_META_PREFIX.to_vec();
+    meta_key.extend_from_slice(&id.to_be_bytes());
+    meta_key
+}
+
+#[derive(Default)]
+/// Audit log state of a GroveDB handle
+pub(crate) struct AuditLogState {
+    /// Id of the next pending record
+    next_pending_id: AtomicU64,
+    /// Held while appending pending records to the log
+    append_lock: Mutex<()>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Kind of a logged operation
+pub enum AuditOpKind {
+    /// An element was inserted
+    Insert,
+    /// An existing element was replaced
+    Replace,
+    /// An existing element was patched
+    Patch,
+    /// An element was deleted
+    Delete,
+    /// A reference was refreshed
+    RefreshReference,
+    /// The flags of an existing element were updated
+    UpdateFlags,
+    /// A subtree was cleared
+    Clear,
+    /// A counter was incremented
+    IncrementCounter,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// An element written or deleted by a mutation
+pub struct AuditLogEntry {
+    /// Path of the subtree
+    pub path: Vec<Vec<u8>>,
+    /// Key of the element
+    pub key: Vec<u8>,
+    /// Kind of operation
+    pub op: AuditOpKind,
+    /// Hash of the serialized element written, or of the encoded delta of a
+    /// counter increment, if any
+    pub value_hash: Option<Hash>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A mutation recorded in the audit log
+pub struct AuditLogRecord {
+    /// Sequence number of the record
+    pub sequence: u64,
+    /// Hash of the previous record
+    pub previous_hash: Hash,
+    /// Root hash of GroveDB before the mutation
+    pub root_hash_before: Hash,
+    /// Elements written or deleted
+    pub entries: Vec<AuditLogEntry>,
+    /// Cost of the mutation, removed bytes being recorded as a total
+    pub cost: OperationCost,
+}
+
+impl AuditLogRecord {
+    /// Hash of the record, committed to by the next record
+    pub fn hash(&self) -> Hash {
+        value_hash(&self.encode()).unwrap()
+    }
+
+    /// Serializes the record
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&self.sequence.to_be_bytes());
+        bytes.extend_from_slice(&self.previous_hash);
+        bytes.extend_from_slice(&self.root_hash_before);
+        bytes.extend_from_slice(&self.cost.seek_count.to_be_bytes());
+        for value in [
+            self.cost.storage_cost.added_bytes,
+            self.cost.storage_cost.replaced_bytes,
+            self.cost.storage_cost.removed_bytes.total_removed_bytes(),
+            self.cost.storage_loaded_bytes,
+            self.cost.hash_node_calls,
+        ] {
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+        bytes.extend_from_slice(&(self.entries.len() as u32).to_be_bytes());
+        for entry in &self.entries {
+            bytes.extend_from_slice(&(entry.path.len() as u32).to_be_bytes());
+            for segment in entry.path.iter().chain(std::iter::once(&entry.key)) {
+                bytes.extend_from_slice(&(segment.len() as u32).to_be_bytes());
+                bytes.extend_from_slice(segment);
+            }
+            bytes.push(match entry.op {
+                AuditOpKind::Insert => 0,
+                AuditOpKind::Replace => 1,
+                AuditOpKind::Patch => 2,
+                AuditOpKind::Delete => 3,
+                AuditOpKind::RefreshReference => 4,
+                AuditOpKind::UpdateFlags => 5,
+                AuditOpKind::Clear => 6,
+                AuditOpKind::IncrementCounter => 7,
+            });
+            match &entry.value_hash {
+                Some(hash) => {
+                    bytes.push(1);
+                    bytes.extend_from_slice(hash);
+                }
+                None => bytes.push(0),
+            }
+        }
+        bytes
+    }
+
+    /// Deserializes a record
+    fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        let mut decoder = Decoder(bytes);
+        let sequence = u64::from_be_bytes(decoder.take_array()?);
+        let previous_hash = decoder.take_array()?;
+        let root_hash_before = decoder.take_array()?;
+        let seek_count = u16::from_be_bytes(decoder.take_array()?);
+        let added_bytes = decoder.take_u32()?;
+        let replaced_bytes = decoder.take_u32()?;
+        let removed_bytes = decoder.take_u32()?;
+        let storage_loaded_bytes = decoder.take_u32()?;
+        let hash_node_calls = decoder.take_u32()?;
+        let mut entries = Vec::new();
+        for _ in 0..decoder.take_u32()? {
+            let mut path = Vec::new();
+            for _ in 0..decoder.take_u32()? {
+                path.push(decoder.take_bytes()?);
+            }
+            let key = decoder.take_bytes()?;
+            let op = match decoder.take_array::<1>()? {
+                [0] => AuditOpKind::Insert,
+                [1] => AuditOpKind::Replace,
+                [2] => AuditOpKind::Patch,
+                [3] => AuditOpKind::Delete,
+                [4] => AuditOpKind::RefreshReference,
+                [5] => AuditOpKind::UpdateFlags,
+                [6] => AuditOpKind::Clear,
+                [7] => AuditOpKind::IncrementCounter,
+                _ => return Err(Decoder::corrupted()),
+            };
+            let value_hash = match decoder.take_array::<1>()? {
+                [0] => None,
+                [1] => Some(decoder.take_array()?),
+                _ => return Err(Decoder::corrupted()),
+            };
+            entries.push(AuditLogEntry {
+                path,
+                key,
+                op,
+                value_hash,
+            });
+        }
+        if !decoder.0.is_empty() {
+            return Err(Decoder::corrupted());
+        }
+        Ok(AuditLogRecord {
+            sequence,
+            previous_hash,
+            root_hash_before,
+            entries,
+            cost: OperationCost {
+                seek_count,
+                storage_cost: StorageCost {
+                    added_bytes,
+                    replaced_bytes,
+                    removed_bytes: BasicStorageRemoval(removed_bytes),
+                },
+                storage_loaded_bytes,
+                hash_node_calls,
+            },
+        })
+    }
+}
+
+/// Reads the fields of an encoded record
+struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+    fn corrupted() -> Error {
+        Error::CorruptedData("malformed audit log record".to_owned())
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < len {
+            return Err(Self::corrupted());
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        Ok(self.take(N)?.try_into().expect("N bytes"))
+    }
+
+    fn take_u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_be_bytes(self.take_array()?))
+    }
+
+    fn take_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        let len = self.take_u32()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+}
+
+/// Audit log entry of a written element
+fn write_entry(
+    path: Vec<Vec<u8>>,
+    key: Vec<u8>,
+    op: AuditOpKind,
+    element: &Element,
+) -> Result<AuditLogEntry, Error> {
+    Ok(AuditLogEntry {
+        path,
+        key,
+        op,
+        value_hash: Some(value_hash(&element.serialize()?).unwrap()),
+    })
+}
+
+/// Audit log entry of an operation writing no element
+fn entry(path: Vec<Vec<u8>>, key: Vec<u8>, op: AuditOpKind) -> AuditLogEntry {
+    AuditLogEntry {
+        path,
+        key,
+        op,
+        value_hash: None,
+    }
+}
+
+/// Audit log entries of the operations of a batch
+pub(crate) fn batch_entries(ops: &[GroveDbOp]) -> Result<Vec<AuditLogEntry>, Error> {
+    ops.iter()
+        .map(|op| {
+            let path = op.path.to_path();
+            let key = op.key.as_slice().to_vec();
+            match &op.op {
+                Op::Insert { element } | Op::InsertIfNotExists { element } => {
+                    write_entry(path, key, AuditOpKind::Insert, element)
+                }
+                Op::Replace { element } | Op::ReplaceExpecting { element, .. } => {
+                    write_entry(path, key, AuditOpKind::Replace, element)
+                }
+                Op::Patch { element, .. } => write_entry(path, key, AuditOpKind::Patch, element),
+                Op::RefreshReference { .. } => Ok(entry(path, key, AuditOpKind::RefreshReference)),
+                Op::UpdateFlags { .. } => Ok(entry(path, key, AuditOpKind::UpdateFlags)),
+                Op::Delete
+                | Op::DeleteTree
+                | Op::DeleteSumTree
+                | Op::DeleteIfValueMatches { .. } => Ok(entry(path, key, AuditOpKind::Delete)),
+                Op::ReplaceTreeRootKey { .. } | Op::InsertTreeWithRootHash { .. } => {
+                    Ok(entry(path, key, AuditOpKind::Insert))
+                }
+            }
+        })
+        .collect()
+}
+
+/// Audit log entry of an element inserted outside of a batch
+pub(crate) fn insert_entry(
+    path: Vec<Vec<u8>>,
+    key: &[u8],
+    element: &Element,
+) -> Result<AuditLogEntry, Error> {
+    write_entry(path, key.to_vec(), AuditOpKind::Insert, element)
+}
+
+/// Audit log entry of an element deleted outside of a batch
+pub(crate) fn delete_entry(path: Vec<Vec<u8>>, key: &[u8]) -> AuditLogEntry {
+    entry(path, key.to_vec(), AuditOpKind::Delete)
+}
+
+/// Audit log entries of `element` being renamed from `old_key` to `new_key`
+pub(crate) fn rename_entries(
+    path: Vec<Vec<u8>>,
+    old_key: &[u8],
+    new_key: &[u8],
+    element: &Element,
+) -> Result<Vec<AuditLogEntry>, Error> {
+    Ok(vec![
+        entry(path.clone(), old_key.to_vec(), AuditOpKind::Delete),
+        write_entry(path, new_key.to_vec(), AuditOpKind::Insert, element)?,
+    ])
+}
+
+/// Audit log entry of the subtree at `subtree_path` being cleared
+pub(crate) fn clear_entry(mut subtree_path: Vec<Vec<u8>>) -> AuditLogEntry {
+    let key = subtree_path.pop().unwrap_or_default();
+    entry(subtree_path, key, AuditOpKind::Clear)
+}
+
+/// Audit log entry of the counter under `key` being incremented by the
+/// encoded `delta`
+pub(crate) fn increment_counter_entry(
+    path: Vec<Vec<u8>>,
+    key: &[u8],
+    delta: &[u8],
+) -> AuditLogEntry {
+    AuditLogEntry {
+        path,
+        key: key.to_vec(),
+        op: AuditOpKind::IncrementCounter,
+        value_hash: Some(value_hash(delta).unwrap()),
+    }
+}
+
+impl GroveDb {
+    /// Returns the bounds of the audit log
+    pub fn audit_log_bounds(
+        &self,
+        transaction: TransactionArg,
+    ) -> CostResult<AppendLogBounds, Error> {
+        let mut cost = OperationCost::default();
+
+        cost_return_on_error!(&mut cost, self.append_pending_audit_records());
+        let first = cost_return_on_error!(
+            &mut cost,
+            self.get_audit_log_u64(FIRST_SEQ_META_KEY, transaction)
+        );
+        let next = cost_return_on_error!(
+            &mut cost,
+            self.get_audit_log_u64(NEXT_SEQ_META_KEY, transaction)
+        );
+        Ok(AppendLogBounds { first, next }).wrap_with_cost(cost)
+    }
+
+    /// Returns up to `limit` records of the audit log, starting at sequence
+    /// number `from`. The records of the mutations of a transaction are
+    /// appended to the log once it is committed.
+    pub fn read_audit_log(
+        &self,
+        from: u64,
+        limit: u64,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<AuditLogRecord>, Error> {
+        let mut cost = OperationCost::default();
+
+        let bounds = cost_return_on_error!(&mut cost, self.audit_log_bounds(transaction));
+        let start = from.max(bounds.first);
+        let end = start.saturating_add(limit).min(bounds.next);
+        let mut records = Vec::new();
+        for sequence in start..end {
+            let bytes = cost_return_on_error!(
+                &mut cost,
+                self.get_audit_log_meta(&record_meta_key(sequence), transaction)
+            );
+            let bytes = cost_return_on_error_no_add!(
+                &cost,
+                bytes.ok_or_else(|| Error::CorruptedData(format!(
+                    "audit log record {sequence} is missing"
+                )))
+            );
+            records.push(cost_return_on_error_no_add!(
+                &cost,
+                AuditLogRecord::decode(&bytes)
+            ));
+        }
+        Ok(records).wrap_with_cost(cost)
+    }
+
+    /// Checks that the records still in the audit log form an unbroken hash
+    /// chain starting at the anchor left by pruning and ending at the hash of
+    /// the last appended record
+    pub fn verify_audit_log(&self, transaction: TransactionArg) -> CostResult<bool, Error> {
+        let mut cost = OperationCost::default();
+
+        let bounds = cost_return_on_error!(&mut cost, self.audit_log_bounds(transaction));
+        let mut expected_hash = cost_return_on_error!(
+            &mut cost,
+            self.get_audit_log_hash(ANCHOR_META_KEY, transaction)
+        );
+        let records = cost_return_on_error!(
+            &mut cost,
+            self.read_audit_log(bounds.first, bounds.len(), transaction)
+        );
+        for (sequence, record) in (bounds.first..).zip(records) {
+            if record.sequence != sequence || record.previous_hash != expected_hash {
+                return Ok(false).wrap_with_cost(cost);
+            }
+            expected_hash = record.hash();
+        }
+        let last_hash = cost_return_on_error!(
+            &mut cost,
+            self.get_audit_log_hash(LAST_HASH_META_KEY, transaction)
+        );
+        Ok(last_hash == expected_hash).wrap_with_cost(cost)
+    }
+
+    /// Removes the records of the audit log with a sequence number lower than
+    /// `before`, keeping the hash of the last removed record as the anchor of
+    /// the chain
+    pub fn prune_audit_log(
+        &self,
+        before: u64,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        let bounds = cost_return_on_error!(&mut cost, self.audit_log_bounds(transaction));
+        let before = before.min(bounds.next);
+        if before <= bounds.first {
+            return Ok(()).wrap_with_cost(cost);
+        }
+        let last_pruned =
+            cost_return_on_error!(&mut cost, self.read_audit_log(before - 1, 1, transaction));
+        let anchor = last_pruned
+            .first()
+            .map(AuditLogRecord::hash)
+            .unwrap_or(NULL_HASH);
+
+        let batch = StorageBatch::new();
+        meta_storage_context_optional_tx!(self.db, Some(&batch), transaction, storage, {
+            let storage = storage.unwrap_add_cost(&mut cost);
+            for sequence in bounds.first..before {
+                cost_return_on_error!(
+                    &mut cost,
+                    storage
+                        .delete_meta(record_meta_key(sequence), None)
+                        .map_err(Into::into)
+                );
+            }
+            cost_return_on_error!(
+                &mut cost,
+                storage
+                    .put_meta(FIRST_SEQ_META_KEY, &before.to_be_bytes(), None)
+                    .map_err(Into::into)
+            );
+            cost_return_on_error!(
+                &mut cost,
+                storage
+                    .put_meta(ANCHOR_META_KEY, &anchor, None)
+                    .map_err(Into::into)
+            );
+        });
+
+        self.db
+            .commit_multi_context_batch(batch, transaction)
+            .map_err(Into::into)
+            .add_cost(cost)
+    }
+
+    /// Prunes the audit log down to its last `max_records` records
+    pub fn rotate_audit_log(
+        &self,
+        max_records: u64,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        let bounds = cost_return_on_error!(&mut cost, self.audit_log_bounds(transaction));
+        self.prune_audit_log(bounds.next.saturating_sub(max_records), transaction)
+            .add_cost(cost)
+    }
+
+    /// Stages into `batch` the audit log record of a mutation once it is
+    /// applied, taking its entries from its result; no record is staged if
+    /// there are none
+    pub(crate) fn audit_mutation<T>(
+        &self,
+        mutation: CostResult<T, Error>,
+        entries: impl FnOnce(&T) -> Vec<AuditLogEntry>,
+        batch: &StorageBatch,
+        transaction: TransactionArg,
+    ) -> CostResult<T, Error> {
+        let mut cost = OperationCost::default();
+
+        let result = cost_return_on_error!(&mut cost, mutation);
+        let recorded = self.record_audit_log(entries(&result), &mut cost, batch, transaction);
+        cost_return_on_error_no_add!(&cost, recorded);
+        Ok(result).wrap_with_cost(cost)
+    }
+
+    /// Stages into `batch` the pending audit log record of a mutation about
+    /// to be committed with it, `mutation_cost` being the cost of the
+    /// mutation so far. The seek of the write of the record, which the
+    /// commit of `batch` adds, is taken out of `mutation_cost`.
+    pub(crate) fn record_audit_log(
+        &self,
+        entries: Vec<AuditLogEntry>,
+        mutation_cost: &mut OperationCost,
+        batch: &StorageBatch,
+        transaction: TransactionArg,
+    ) -> Result<(), Error> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        // Read for the record, not charged to the mutation
+        let root_hash_before = self.root_hash(transaction).unwrap()?;
+        // Sequence number and previous hash are set once appended to the log
+        let record = AuditLogRecord {
+            sequence: 0,
+            previous_hash: NULL_HASH,
+            root_hash_before,
+            entries,
+            cost: mutation_cost.clone(),
+        };
+
+        let id = self
+            .audit_log
+            .next_pending_id
+            .fetch_add(1, Ordering::SeqCst);
+        meta_storage_context_optional_tx!(self.db, Some(batch), transaction, storage, {
+            storage
+                .unwrap()
+                .put_meta(
+                    pending_meta_key(id),
+                    &record.encode(),
+                    Some(KeyValueStorageCost::default()),
+                )
+                .unwrap()?;
+        });
+        mutation_cost.seek_count = mutation_cost.seek_count.saturating_sub(1);
+        Ok(())
+    }
+
+    /// Appends the pending records of committed mutations to the audit log
+    /// in the order they were staged. Records are appended by one writer at
+    /// a time, outside of any transaction.
+    fn append_pending_audit_records(&self) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        let _append_lock = self.audit_log.append_lock.lock().expect("audit log lock");
+        let mut sequence =
+            cost_return_on_error!(&mut cost, self.get_audit_log_u64(NEXT_SEQ_META_KEY, None));
+        let mut previous_hash =
+            cost_return_on_error!(&mut cost, self.get_audit_log_hash(LAST_HASH_META_KEY, None));
+
+        let batch = StorageBatch::new();
+        let storage = self
+            .db
+            .get_storage_context(SubtreePath::empty(), Some(&batch))
+            .unwrap_add_cost(&mut cost);
+        let mut appended = false;
+        let mut iter = storage.raw_iter_meta();
+        iter.seek(PENDING_META_PREFIX).unwrap_add_cost(&mut cost);
+        while iter.valid().unwrap_add_cost(&mut cost) {
+            let Some(pending_key) = iter.key().unwrap_add_cost(&mut cost) else {
+                break;
+            };
+            if !pending_key.starts_with(PENDING_META_PREFIX) {
+                break;
+            }
+            let bytes = iter.value().unwrap_add_cost(&mut cost).unwrap_or_default();
+            let mut record = cost_return_on_error_no_add!(&cost, AuditLogRecord::decode(bytes));
+            record.sequence = sequence;
+            record.previous_hash = previous_hash;
+            previous_hash = record.hash();
+            cost_return_on_error!(
+                &mut cost,
+                storage
+                    .put_meta(record_meta_key(sequence), &record.encode(), None)
+                    .map_err(Into::into)
+            );
+            cost_return_on_error!(
+                &mut cost,
+                storage.delete_meta(pending_key, None).map_err(Into::into)
+            );
+            sequence += 1;
+            appended = true;
+            iter.next().unwrap_add_cost(&mut cost);
+        }
+        if !appended {
+            return Ok(()).wrap_with_cost(cost);
+        }
+        cost_return_on_error!(
+            &mut cost,
+            storage
+                .put_meta(NEXT_SEQ_META_KEY, &sequence.to_be_bytes(), None)
+                .map_err(Into::into)
+        );
+        cost_return_on_error!(
+            &mut cost,
+            storage
+                .put_meta(LAST_HASH_META_KEY, &previous_hash, None)
+                .map_err(Into::into)
+        );
+        drop(iter);
+        drop(storage);
+
+        self.db
+            .commit_multi_context_batch(batch, None)
+            .map_err(Into::into)
+            .add_cost(cost)
+    }
+
+    /// Sets the id of the next pending record of the audit log after the
+    /// ones left pending
+    pub(crate) fn load_audit_log_state(&self) -> Result<(), Error> {
+        let storage = self
+            .db
+            .get_storage_context(SubtreePath::empty(), None)
+            .unwrap();
+        let mut iter = storage.raw_iter_meta();
+        iter.seek_for_prev(pending_meta_key(u64::MAX)).unwrap();
+        let next_pending_id = match iter.key().unwrap() {
+            Some(pending_key) if iter.valid().unwrap() => pending_key
+                .strip_prefix(PENDING_META_PREFIX)
+                .and_then(|id| id.try_into().ok())
+                .map_or(0, |id| u64::from_be_bytes(id).saturating_add(1)),
+            _ => 0,
+        };
+        self.audit_log
+            .next_pending_id
+            .store(next_pending_id, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Reads a meta entry of the audit log
+    fn get_audit_log_meta(
+        &self,
+        meta_key: &[u8],
+        transaction: TransactionArg,
+    ) -> CostResult<Option<Vec<u8>>, Error> {
+        let mut cost = OperationCost::default();
+
+        meta_storage_context_optional_tx!(self.db, None, transaction, storage, {
+            storage
+                .unwrap_add_cost(&mut cost)
+                .get_meta(meta_key)
+                .map_err(Into::into)
+                .add_cost(cost)
+        })
+    }
+
+    /// Reads a sequence number of the audit log, zero if not set
+    fn get_audit_log_u64(
+        &self,
+        meta_key: &[u8],
+        transaction: TransactionArg,
+    ) -> CostResult<u64, Error> {
+        self.get_audit_log_meta(meta_key, transaction)
+            .map(|result| {
+                result.and_then(|bytes| match bytes {
+                    None => Ok(0),
+                    Some(bytes) => {
+                        bytes
+                            .as_slice()
+                            .try_into()
+                            .map(u64::from_be_bytes)
+                            .map_err(|_| {
+                                Error::CorruptedData(
+                                    "audit log sequence number is not 8 bytes".to_owned(),
+                                )
+                            })
+                    }
+                })
+            })
+    }
+
+    /// Reads a hash of the audit log, the null hash if not set
+    fn get_audit_log_hash(
+        &self,
+        meta_key: &[u8],
+        transaction: TransactionArg,
+    ) -> CostResult<Hash, Error> {
+        self.get_audit_log_meta(meta_key, transaction)
+            .map(|result| {
+                result.and_then(|bytes| match bytes {
+                    None => Ok(NULL_HASH),
+                    Some(bytes) => bytes.as_slice().try_into().map_err(|_| {
+                        Error::CorruptedData("audit log hash is not 32 bytes".to_owned())
+                    }),
+                })
+            })
+    }
+}
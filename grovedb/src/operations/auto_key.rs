@@ -0,0 +1,144 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Auto-increment keys
+//!
+//! Subtrees can have keys allocated by GroveDB: sequential `u64` numbers
+//! encoded big-endian, so that key order is allocation order. The next key
+//! to allocate is kept in the auxiliary storage of the subtree and updated in
+//! the same batch as the insertion, so concurrent allocations in different
+//! transactions conflict instead of reusing a key.
+
+#[cfg(feature = "full")]
+use grovedb_costs::{
+    cost_return_on_error, cost_return_on_error_no_add, CostResult, CostsExt, OperationCost,
+};
+use grovedb_path::SubtreePath;
+#[cfg(feature = "full")]
+use grovedb_storage::{Storage, StorageBatch, StorageContext};
+
+#[cfg(feature = "full")]
+use crate::{
+    batch::GroveDbOp, metrics::OperationKind, transaction::storage_transaction,
+    util::storage_context_optional_tx, Element, Error, GroveDb, TransactionArg,
+};
+
+/// Aux key of the next key to allocate in a subtree
+#[cfg(feature = "full")]
+const NEXT_AUTO_KEY_AUX_KEY: &[u8] = b"auto_key/next";
+
+/// Decodes an auto-increment key
+#[cfg(feature = "full")]
+pub(crate) fn decode_auto_key(bytes: &[u8]) -> Result<u64, Error> {
+    bytes
+        .try_into()
+        .map(u64::from_be_bytes)
+        .map_err(|_| Error::CorruptedData("auto-increment key is not a u64".to_owned()))
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Inserts `element` into the subtree at `path` under the next
+    /// auto-increment key of the subtree, returning the key
+    pub fn insert_auto_key<'b, B, P>(
+        &self,
+        path: P,
+        element: Element,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<u8>, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        cost_return_on_error!(
+            &mut cost,
+            self.check_subtree_exists_path_not_found(path.clone(), transaction)
+        );
+        let next = cost_return_on_error!(&mut cost, self.next_auto_key(path.clone(), transaction));
+        let key = next.to_be_bytes().to_vec();
+        let recorded_path = path.clone();
+
+        let storage_batch = StorageBatch::new();
+        cost_return_on_error!(
+            &mut cost,
+            self.apply_batch_into_storage_batch(
+                vec![GroveDbOp::insert_op(path.to_vec(), key.clone(), element)],
+                &storage_batch,
+                transaction
+            )
+        );
+        let following = cost_return_on_error_no_add!(
+            &cost,
+            next.checked_add(1)
+                .ok_or(Error::InvalidInput("auto-increment keys are exhausted"))
+        );
+        storage_context_optional_tx!(self.db, path, Some(&storage_batch), transaction, storage, {
+            cost_return_on_error!(
+                &mut cost,
+                storage
+                    .unwrap_add_cost(&mut cost)
+                    .put_aux(NEXT_AUTO_KEY_AUX_KEY, &following.to_be_bytes(), None)
+                    .map_err(Into::into)
+            );
+        });
+
+        self.db
+            .commit_multi_context_batch(storage_batch, storage_transaction(transaction))
+            .map_err(Into::into)
+            .map_ok(|_| {
+                self.metrics
+                    .record(OperationKind::Write, &recorded_path, Some(key.as_slice()));
+                key
+            })
+            .add_cost(cost)
+    }
+
+    /// Returns the next auto-increment key of the subtree at `path`
+    pub(crate) fn next_auto_key<B: AsRef<[u8]>>(
+        &self,
+        path: SubtreePath<B>,
+        transaction: TransactionArg,
+    ) -> CostResult<u64, Error> {
+        let mut cost = OperationCost::default();
+
+        storage_context_optional_tx!(self.db, path, None, transaction, storage, {
+            let next = cost_return_on_error!(
+                &mut cost,
+                storage
+                    .unwrap_add_cost(&mut cost)
+                    .get_aux(NEXT_AUTO_KEY_AUX_KEY)
+                    .map_err(Into::into)
+            );
+            next.map_or(Ok(0), |next| decode_auto_key(&next))
+                .wrap_with_cost(cost)
+        })
+    }
+}
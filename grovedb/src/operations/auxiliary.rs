@@ -38,7 +38,10 @@ use grovedb_storage::StorageContext;
 use grovedb_storage::{Storage, StorageBatch};
 
 #[cfg(feature = "full")]
-use crate::{util::meta_storage_context_optional_tx, Error, GroveDb, TransactionArg};
+use crate::{
+    transaction::storage_transaction, util::meta_storage_context_optional_tx, Error, GroveDb,
+    TransactionArg,
+};
 
 #[cfg(feature = "full")]
 impl GroveDb {
@@ -65,7 +68,7 @@ impl GroveDb {
         });
 
         self.db
-            .commit_multi_context_batch(batch, transaction)
+            .commit_multi_context_batch(batch, storage_transaction(transaction))
             .add_cost(cost)
             .map_err(Into::into)
     }
@@ -92,7 +95,7 @@ impl GroveDb {
         });
 
         self.db
-            .commit_multi_context_batch(batch, transaction)
+            .commit_multi_context_batch(batch, storage_transaction(transaction))
             .add_cost(cost)
             .map_err(Into::into)
     }
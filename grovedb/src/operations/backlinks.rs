@@ -0,0 +1,728 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+//! Backlinks of references
+//!
+//! Inserting a bidirectional reference also writes a backlink for the element
+//! it points to: an entry in the meta storage of the subtree of the target,
+//! under the length prefixed key of the target followed by the hash of the
+//! qualified path of the reference, holding that qualified path. Backlinks
+//! are kept out of the Merk trees, so they don't show up in queries, counts
+//! or sums and don't change root hashes. They are written and removed in the
+//! same batch as their reference, and removed along with the subtree holding
+//! either their target or their reference.
+//!
+//! Once the referrer index is enabled, plain references get backlinks too,
+//! in a namespace of their own, so that deletions can find the references
+//! left dangling without scanning the grove.
+//!
+//! A backlinks proof proves the element and, for each of its referrers, the
+//! bidirectional reference pointing to it. As backlinks aren't covered by the
+//! root hash, it can't prove that no referrer was left out.
+
+#[cfg(feature = "full")]
+use std::sync::atomic::Ordering;
+
+#[cfg(feature = "full")]
+use bincode::Options;
+#[cfg(feature = "full")]
+use grovedb_costs::{
+    cost_return_on_error, cost_return_on_error_no_add, CostResult, CostsExt, OperationCost,
+};
+#[cfg(feature = "full")]
+use grovedb_merk::tree::value_hash;
+#[cfg(feature = "full")]
+use grovedb_path::SubtreePath;
+#[cfg(feature = "full")]
+use grovedb_storage::{RawIterator, Storage, StorageBatch, StorageContext};
+#[cfg(feature = "full")]
+use grovedb_verify::backlinks::{backlinks_serialization_options, BacklinksProof, ReferrerProof};
+
+#[cfg(feature = "full")]
+use crate::{
+    batch::{GroveDbOp, Op},
+    operations::rename::renamed_subtree_path,
+    reference_path::{path_from_reference_path_type, ReferencePathType},
+    util::storage_context_optional_tx,
+    Element, Error, GroveDb, PathQuery, TransactionArg,
+};
+
+/// Prefix of the meta keys of the backlinks of bidirectional references
+#[cfg(feature = "full")]
+const BACKLINKS_META_PREFIX: &[u8] = b"backlinks/";
+
+/// Prefix of the meta keys of the backlinks of plain references, written
+/// once the referrer index is enabled
+#[cfg(feature = "full")]
+const REFERRERS_META_PREFIX: &[u8] = b"referrers/";
+
+/// Meta key, in the root subtree, marking the referrer index as enabled
+#[cfg(feature = "full")]
+const REFERRER_INDEX_ENABLED_META_KEY: &[u8] = b"referrer_index_enabled";
+
+/// Meta key, in the root subtree, marking the grove as holding bidirectional
+/// references
+#[cfg(feature = "full")]
+const IN_USE_META_KEY: &[u8] = b"bidirectional_references_in_use";
+
+/// Smallest meta key a backlink of the element under `target_key` can have
+/// in the `namespace` of the backlinks of a kind of references
+#[cfg(feature = "full")]
+fn backlinks_prefix(namespace: &[u8], target_key: &[u8]) -> Result<Vec<u8>, Error> {
+    let target_key_len = u8::try_from(target_key.len()).map_err(|_| {
+        Error::InvalidInput("the key of the referenced element is too long to hold backlinks")
+    })?;
+    let mut key = namespace.to_vec();
+    key.push(target_key_len);
+    key.extend_from_slice(target_key);
+    Ok(key)
+}
+
+/// Whether a meta key is the one of a backlink
+#[cfg(feature = "full")]
+pub(crate) fn is_backlink_meta_key(meta_key: &[u8]) -> bool {
+    meta_key.starts_with(BACKLINKS_META_PREFIX) || meta_key.starts_with(REFERRERS_META_PREFIX)
+}
+
+/// Serializes the qualified path of a referrer
+#[cfg(feature = "full")]
+fn encode_referrer(referrer: &[Vec<u8>]) -> Result<Vec<u8>, Error> {
+    backlinks_serialization_options()
+        .serialize(referrer)
+        .map_err(|_| Error::CorruptedData("unable to serialize referrer path".to_owned()))
+}
+
+/// Deserializes the qualified path of a referrer
+#[cfg(feature = "full")]
+fn decode_referrer(encoded: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+    backlinks_serialization_options()
+        .deserialize(encoded)
+        .map_err(|_| Error::CorruptedData("unable to deserialize backlink".to_owned()))
+}
+
+/// A backlink to stage: the path of the subtree of the target, the meta key
+/// of the backlink and the encoded qualified path of the reference
+#[cfg(feature = "full")]
+type Backlink = (Vec<Vec<u8>>, Vec<u8>, Vec<u8>);
+
+/// The backlink, in `namespace`, of the reference with `reference_path`
+/// stored under `key` in the subtree at `path`
+#[cfg(feature = "full")]
+fn backlink_of(
+    namespace: &[u8],
+    reference_path: ReferencePathType,
+    path: &[Vec<u8>],
+    key: &[u8],
+) -> Result<Backlink, Error> {
+    let mut target_path = path_from_reference_path_type(reference_path, path, Some(key))?;
+    let target_key = target_path.pop().ok_or(Error::InvalidPath(
+        "reference path cannot be empty".to_owned(),
+    ))?;
+    let mut referrer = path.to_vec();
+    referrer.push(key.to_vec());
+    let encoded_referrer = encode_referrer(&referrer)?;
+    let mut meta_key = backlinks_prefix(namespace, &target_key)?;
+    meta_key.extend_from_slice(&value_hash(&encoded_referrer).unwrap());
+    Ok((target_path, meta_key, encoded_referrer))
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Returns the qualified paths of the bidirectional references pointing
+    /// to the element under `key` in the subtree at `path`
+    pub fn get_backlinks(
+        &self,
+        path: Vec<Vec<u8>>,
+        key: &[u8],
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<Vec<Vec<u8>>>, Error> {
+        let cost = OperationCost::default();
+
+        let prefix =
+            cost_return_on_error_no_add!(&cost, backlinks_prefix(BACKLINKS_META_PREFIX, key));
+        self.referrers_with_prefix(&path, &prefix, transaction)
+            .add_cost(cost)
+    }
+
+    /// Generate a proof of the bidirectional references pointing to the
+    /// element under `key` in the subtree at `path`, see
+    /// `GroveDb::verify_backlinks`
+    pub fn prove_backlinks(&self, path: Vec<Vec<u8>>, key: &[u8]) -> CostResult<Vec<u8>, Error> {
+        let mut cost = OperationCost::default();
+
+        let referrers =
+            cost_return_on_error!(&mut cost, self.get_backlinks(path.clone(), key, None));
+        let element_proof = cost_return_on_error!(
+            &mut cost,
+            self.prove_query(&PathQuery::new_single_key(path, key.to_vec()))
+        );
+        let mut referrer_proofs = Vec::with_capacity(referrers.len());
+        for referrer in referrers {
+            let Some((referrer_key, referrer_path)) = referrer.split_last() else {
+                return Err(Error::CorruptedData("empty referrer path".to_owned()))
+                    .wrap_with_cost(cost);
+            };
+            let reference = cost_return_on_error!(
+                &mut cost,
+                self.get_raw(referrer_path.into(), referrer_key, None)
+            );
+            let reference = cost_return_on_error_no_add!(&cost, reference.serialize());
+            let proof = cost_return_on_error!(
+                &mut cost,
+                self.prove_query(&PathQuery::new_single_key(
+                    referrer_path.to_vec(),
+                    referrer_key.clone()
+                ))
+            );
+            referrer_proofs.push(ReferrerProof {
+                referrer,
+                reference,
+                proof,
+            });
+        }
+
+        backlinks_serialization_options()
+            .serialize(&BacklinksProof {
+                element_proof,
+                referrers: referrer_proofs,
+            })
+            .map_err(|_| Error::CorruptedData("unable to serialize backlinks proof".to_owned()))
+            .wrap_with_cost(cost)
+    }
+
+    /// Enables the referrer index, writing the backlinks of the plain
+    /// references already in the grove. Backlinks of plain references are
+    /// written from now on, which deletions handling their referrers rely on.
+    pub fn enable_referrer_index(&self) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        // Enabled first so that references written during the backfill get
+        // their backlinks
+        self.referrer_index_enabled.store(true, Ordering::SeqCst);
+        cost_return_on_error!(
+            &mut cost,
+            self.put_root_meta(REFERRER_INDEX_ENABLED_META_KEY)
+        );
+
+        let mut cursor = None;
+        loop {
+            let page = cost_return_on_error!(&mut cost, self.scan(cursor.as_ref(), u16::MAX, None));
+            let batch = StorageBatch::new();
+            for (path, key, element) in page.elements {
+                if let Element::Reference(reference_path, ..) = element {
+                    let (target_path, meta_key, encoded_referrer) = cost_return_on_error_no_add!(
+                        &cost,
+                        backlink_of(REFERRERS_META_PREFIX, reference_path, &path, &key)
+                    );
+                    cost_return_on_error!(
+                        &mut cost,
+                        self.stage_backlink(
+                            target_path,
+                            meta_key,
+                            Some(encoded_referrer),
+                            &batch,
+                            None
+                        )
+                    );
+                }
+            }
+            cost_return_on_error!(
+                &mut cost,
+                self.db
+                    .commit_multi_context_batch(batch, None)
+                    .map_err(Into::into)
+            );
+            cursor = page.cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        Ok(()).wrap_with_cost(cost)
+    }
+
+    /// Whether the referrer index is enabled
+    pub fn is_referrer_index_enabled(&self) -> bool {
+        self.referrer_index_enabled.load(Ordering::SeqCst)
+    }
+
+    /// Returns the qualified paths of the references pointing to the element
+    /// under `key` in the subtree at `path`, or to any element of that subtree
+    /// if `key` is `None`. Requires the referrer index to be enabled, the
+    /// returned references may since have been renamed away.
+    pub(crate) fn referrers_of(
+        &self,
+        path: &[Vec<u8>],
+        key: Option<&[u8]>,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<Vec<Vec<u8>>>, Error> {
+        let mut cost = OperationCost::default();
+
+        let mut referrers = Vec::new();
+        for namespace in [BACKLINKS_META_PREFIX, REFERRERS_META_PREFIX] {
+            let prefix = match key {
+                Some(key) => cost_return_on_error_no_add!(&cost, backlinks_prefix(namespace, key)),
+                None => namespace.to_vec(),
+            };
+            referrers.extend(cost_return_on_error!(
+                &mut cost,
+                self.referrers_with_prefix(path, &prefix, transaction)
+            ));
+        }
+        Ok(referrers).wrap_with_cost(cost)
+    }
+
+    /// Returns the referrers held by the backlinks of the subtree at `path`
+    /// whose meta keys start with `prefix`
+    fn referrers_with_prefix(
+        &self,
+        path: &[Vec<u8>],
+        prefix: &[u8],
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<Vec<Vec<u8>>>, Error> {
+        let mut cost = OperationCost::default();
+
+        let mut referrers = Vec::new();
+        storage_context_optional_tx!(self.db, path.into(), None, transaction, storage, {
+            let storage = storage.unwrap_add_cost(&mut cost);
+            let mut iter = storage.raw_iter_meta();
+            iter.seek(prefix).unwrap_add_cost(&mut cost);
+            while iter.valid().unwrap_add_cost(&mut cost) {
+                match iter.key().unwrap_add_cost(&mut cost) {
+                    Some(meta_key) if meta_key.starts_with(prefix) => {}
+                    _ => break,
+                }
+                let encoded = iter.value().unwrap_add_cost(&mut cost).unwrap_or_default();
+                referrers.push(cost_return_on_error_no_add!(
+                    &cost,
+                    decode_referrer(encoded)
+                ));
+                iter.next().unwrap_add_cost(&mut cost);
+            }
+        });
+        Ok(referrers).wrap_with_cost(cost)
+    }
+
+    /// Whether bidirectional references were ever inserted in the grove
+    pub(crate) fn has_bidirectional_references(&self) -> bool {
+        self.bidirectional_references_in_use.load(Ordering::SeqCst)
+    }
+
+    /// Whether backlinks are maintained, in which case writes check whether
+    /// they replace a reference
+    pub(crate) fn maintains_backlinks(&self) -> bool {
+        self.has_bidirectional_references() || self.is_referrer_index_enabled()
+    }
+
+    /// Namespace and reference path of the backlink of `element`, if it is a
+    /// reference having one
+    pub(crate) fn backlinked_reference(
+        &self,
+        element: &Element,
+    ) -> Option<(&'static [u8], ReferencePathType)> {
+        match element {
+            Element::BidirectionalReference(reference_path, ..) => {
+                Some((BACKLINKS_META_PREFIX, reference_path.clone()))
+            }
+            Element::Reference(reference_path, ..) if self.is_referrer_index_enabled() => {
+                Some((REFERRERS_META_PREFIX, reference_path.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Loads whether bidirectional references were ever inserted and whether
+    /// the referrer index is enabled
+    pub(crate) fn load_backlinks_state(&self) -> Result<(), Error> {
+        let storage = self
+            .db
+            .get_storage_context(SubtreePath::empty(), None)
+            .unwrap();
+        let in_use = storage.get_meta(IN_USE_META_KEY).unwrap()?.is_some();
+        let referrer_index_enabled = storage
+            .get_meta(REFERRER_INDEX_ENABLED_META_KEY)
+            .unwrap()?
+            .is_some();
+        self.bidirectional_references_in_use
+            .store(in_use, Ordering::SeqCst);
+        self.referrer_index_enabled
+            .store(referrer_index_enabled, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Sets a marker meta key in the root subtree, outside of any transaction
+    fn put_root_meta(&self, meta_key: &[u8]) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        let batch = StorageBatch::new();
+        {
+            let storage = self
+                .db
+                .get_storage_context(SubtreePath::empty(), Some(&batch))
+                .unwrap_add_cost(&mut cost);
+            cost_return_on_error!(
+                &mut cost,
+                storage.put_meta(meta_key, &[1], None).map_err(Into::into)
+            );
+        }
+        self.db
+            .commit_multi_context_batch(batch, None)
+            .map_err(Into::into)
+            .add_cost(cost)
+    }
+
+    /// Stages into `batch` the writes of the backlinks of the references
+    /// inserted by the operations of a batch, and the removal of the
+    /// backlinks of the ones they replace or delete
+    pub(crate) fn record_backlinks(
+        &self,
+        ops: &[GroveDbOp],
+        batch: &StorageBatch,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        let inserts_bidirectional_references = ops.iter().any(|op| {
+            matches!(
+                &op.op,
+                Op::Insert { element } | Op::Replace { element } | Op::Patch { element, .. }
+                    if element.is_bidirectional_reference()
+            )
+        });
+        if inserts_bidirectional_references && !self.has_bidirectional_references() {
+            // Marked before the references are written, so a failed batch
+            // only costs the checks of later writes
+            cost_return_on_error!(&mut cost, self.put_root_meta(IN_USE_META_KEY));
+            self.bidirectional_references_in_use
+                .store(true, Ordering::SeqCst);
+        }
+        if !self.maintains_backlinks() {
+            return Ok(()).wrap_with_cost(cost);
+        }
+
+        let mut inserted_backlinks = Vec::new();
+        let mut removed_backlinks = Vec::new();
+        let mut deleted_subtrees = Vec::new();
+        for op in ops {
+            let path = op.path.to_path();
+            let key = op.key.get_key_clone();
+            let element = match &op.op {
+                Op::Insert { element } | Op::Replace { element } | Op::Patch { element, .. } => {
+                    Some(element)
+                }
+                Op::Delete => None,
+                Op::DeleteTree | Op::DeleteSumTree => {
+                    let mut subtree_path = path;
+                    subtree_path.push(key);
+                    deleted_subtrees.push(subtree_path);
+                    continue;
+                }
+                _ => continue,
+            };
+
+            if let Some((namespace, reference_path)) =
+                element.and_then(|element| self.backlinked_reference(element))
+            {
+                inserted_backlinks.push(cost_return_on_error_no_add!(
+                    &cost,
+                    backlink_of(namespace, reference_path, &path, &key)
+                ));
+            }
+
+            let existing = self
+                .get_raw(path.as_slice().into(), &key, transaction)
+                .unwrap_add_cost(&mut cost);
+            if let Some((namespace, reference_path)) = existing
+                .ok()
+                .and_then(|existing| self.backlinked_reference(&existing))
+            {
+                removed_backlinks.push(cost_return_on_error_no_add!(
+                    &cost,
+                    backlink_of(namespace, reference_path, &path, &key)
+                ));
+            }
+        }
+
+        cost_return_on_error!(
+            &mut cost,
+            self.remove_backlinks_of_subtrees(deleted_subtrees, batch, transaction)
+        );
+        cost_return_on_error!(
+            &mut cost,
+            self.stage_backlinks(removed_backlinks, inserted_backlinks, batch, transaction)
+        );
+        Ok(()).wrap_with_cost(cost)
+    }
+
+    /// Stages into `batch` the backlink changes of renaming `old_key` to
+    /// `new_key` in the subtree at `path`, holding `element`: the backlink of
+    /// a renamed reference is moved to its new key, and so are the ones of the
+    /// references of a renamed subtree to their new paths. The subtree moves
+    /// drop the backlinks they hold, as the references pointing into a
+    /// renamed subtree are left dangling. Must be called before the rename is
+    /// committed.
+    pub(crate) fn record_renamed_backlinks(
+        &self,
+        path: &[Vec<u8>],
+        old_key: &[u8],
+        new_key: &[u8],
+        element: &Element,
+        batch: &StorageBatch,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        if !self.maintains_backlinks() {
+            return Ok(()).wrap_with_cost(cost);
+        }
+
+        // Old and new qualified paths of the moved references
+        let mut moved = Vec::new();
+        if let Some((namespace, reference_path)) = self.backlinked_reference(element) {
+            moved.push((
+                path.to_vec(),
+                old_key.to_vec(),
+                path.to_vec(),
+                new_key.to_vec(),
+                namespace,
+                reference_path,
+            ));
+        }
+        let mut old_subtree_path = path.to_vec();
+        old_subtree_path.push(old_key.to_vec());
+        if element.is_tree() {
+            let subtree_path: SubtreePath<Vec<u8>> = old_subtree_path.as_slice().into();
+            let subtrees_paths =
+                cost_return_on_error!(&mut cost, self.find_subtrees(&subtree_path, transaction));
+            for old_path in subtrees_paths {
+                let new_path = renamed_subtree_path(&old_path, path.len(), new_key);
+                let references = cost_return_on_error!(
+                    &mut cost,
+                    self.backlinked_references_in(&old_path, transaction)
+                );
+                for (key, namespace, reference_path) in references {
+                    moved.push((
+                        old_path.clone(),
+                        key.clone(),
+                        new_path.clone(),
+                        key,
+                        namespace,
+                        reference_path,
+                    ));
+                }
+            }
+        }
+
+        let mut removed_backlinks = Vec::new();
+        let mut inserted_backlinks = Vec::new();
+        for (old_path, old_key, new_path, new_key, namespace, reference_path) in moved {
+            removed_backlinks.push(cost_return_on_error_no_add!(
+                &cost,
+                backlink_of(namespace, reference_path.clone(), &old_path, &old_key)
+            ));
+            let inserted = cost_return_on_error_no_add!(
+                &cost,
+                backlink_of(namespace, reference_path, &new_path, &new_key)
+            );
+            // Nothing is left to point to in the old subtree
+            if !inserted.0.starts_with(&old_subtree_path) {
+                inserted_backlinks.push(inserted);
+            }
+        }
+
+        self.stage_backlinks(removed_backlinks, inserted_backlinks, batch, transaction)
+            .add_cost(cost)
+    }
+
+    /// Stages into `batch` the removal of the backlinks held by the subtrees
+    /// at the given paths and by the subtrees below them, and of the
+    /// backlinks of the references they hold, if backlinks are maintained.
+    /// Must be called before the deletions are committed.
+    pub(crate) fn remove_backlinks_of_subtrees(
+        &self,
+        deleted: impl IntoIterator<Item = Vec<Vec<u8>>>,
+        batch: &StorageBatch,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        if !self.maintains_backlinks() {
+            return Ok(()).wrap_with_cost(cost);
+        }
+
+        let mut removed = Vec::new();
+        for path in deleted {
+            let exists = cost_return_on_error!(
+                &mut cost,
+                self.check_subtree_exists(path.as_slice(), transaction)
+            );
+            if exists {
+                let subtree_path: SubtreePath<Vec<u8>> = path.as_slice().into();
+                removed.extend(cost_return_on_error!(
+                    &mut cost,
+                    self.find_subtrees(&subtree_path, transaction)
+                ));
+            }
+        }
+
+        let mut removed_backlinks = Vec::new();
+        for path in removed {
+            // The targets are removed with the subtree
+            storage_context_optional_tx!(
+                self.db,
+                path.as_slice().into(),
+                Some(batch),
+                transaction,
+                storage,
+                {
+                    let storage = storage.unwrap_add_cost(&mut cost);
+                    for namespace in [BACKLINKS_META_PREFIX, REFERRERS_META_PREFIX] {
+                        let mut iter = storage.raw_iter_meta();
+                        iter.seek(namespace).unwrap_add_cost(&mut cost);
+                        while iter.valid().unwrap_add_cost(&mut cost) {
+                            let Some(meta_key) = iter.key().unwrap_add_cost(&mut cost) else {
+                                break;
+                            };
+                            if !meta_key.starts_with(namespace) {
+                                break;
+                            }
+                            cost_return_on_error!(
+                                &mut cost,
+                                storage.delete_meta(meta_key, None).map_err(Into::into)
+                            );
+                            iter.next().unwrap_add_cost(&mut cost);
+                        }
+                    }
+                }
+            );
+
+            // So are the references pointing elsewhere
+            let references =
+                cost_return_on_error!(&mut cost, self.backlinked_references_in(&path, transaction));
+            for (key, namespace, reference_path) in references {
+                removed_backlinks.push(cost_return_on_error_no_add!(
+                    &cost,
+                    backlink_of(namespace, reference_path, &path, &key)
+                ));
+            }
+        }
+
+        self.stage_backlinks(removed_backlinks, Vec::new(), batch, transaction)
+            .add_cost(cost)
+    }
+
+    /// Returns the key, backlink namespace and reference path of every
+    /// reference having a backlink in the subtree at `path`
+    fn backlinked_references_in(
+        &self,
+        path: &[Vec<u8>],
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<(Vec<u8>, &'static [u8], ReferencePathType)>, Error> {
+        let mut cost = OperationCost::default();
+
+        let mut references = Vec::new();
+        storage_context_optional_tx!(self.db, path.into(), None, transaction, storage, {
+            let storage = storage.unwrap_add_cost(&mut cost);
+            let mut elements = Element::iterator(storage.raw_iter()).unwrap_add_cost(&mut cost);
+            while let Some((key, element)) =
+                cost_return_on_error!(&mut cost, elements.next_element())
+            {
+                if let Some((namespace, reference_path)) = self.backlinked_reference(&element) {
+                    references.push((key, namespace, reference_path));
+                }
+            }
+        });
+        Ok(references).wrap_with_cost(cost)
+    }
+
+    /// Stages into `batch` the removal of `removed` backlinks, except the
+    /// ones also in `inserted`, followed by the writes of `inserted` ones
+    fn stage_backlinks(
+        &self,
+        removed: Vec<Backlink>,
+        inserted: Vec<Backlink>,
+        batch: &StorageBatch,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        for (target_path, meta_key, _) in removed {
+            // A backlink written again is kept
+            if !inserted.iter().any(|(inserted_path, inserted_key, _)| {
+                inserted_path == &target_path && inserted_key == &meta_key
+            }) {
+                cost_return_on_error!(
+                    &mut cost,
+                    self.stage_backlink(target_path, meta_key, None, batch, transaction)
+                );
+            }
+        }
+        for (target_path, meta_key, encoded_referrer) in inserted {
+            cost_return_on_error!(
+                &mut cost,
+                self.stage_backlink(
+                    target_path,
+                    meta_key,
+                    Some(encoded_referrer),
+                    batch,
+                    transaction
+                )
+            );
+        }
+        Ok(()).wrap_with_cost(cost)
+    }
+
+    /// Stages into `batch` the write, or the removal if `encoded_referrer` is
+    /// `None`, of a backlink in the subtree at `target_path`
+    fn stage_backlink(
+        &self,
+        target_path: Vec<Vec<u8>>,
+        meta_key: Vec<u8>,
+        encoded_referrer: Option<Vec<u8>>,
+        batch: &StorageBatch,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        storage_context_optional_tx!(
+            self.db,
+            target_path.as_slice().into(),
+            Some(batch),
+            transaction,
+            storage,
+            {
+                let storage = storage.unwrap_add_cost(&mut cost);
+                let staged = match &encoded_referrer {
+                    Some(encoded_referrer) => storage.put_meta(&meta_key, encoded_referrer, None),
+                    None => storage.delete_meta(&meta_key, None),
+                };
+                cost_return_on_error!(&mut cost, staged.map_err(Into::into));
+            }
+        );
+        Ok(()).wrap_with_cost(cost)
+    }
+}
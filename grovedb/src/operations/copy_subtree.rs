@@ -0,0 +1,432 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Copy subtree operation
+//!
+//! Duplicates a subtree, with all of its nested subtrees, under another key.
+//! The copy is streamed: elements are read in pages and written with a batch
+//! per page, all within one transaction, so only the paths of the copied
+//! subtrees are held in memory. Its cost accounts for every written byte and
+//! for the propagation up to the root of each batch, and sums and counts of
+//! the copied trees are computed the way the batch computes them for any
+//! insertion.
+//!
+//! References are copied once every other element is, so that their targets
+//! exist. A reference to an element of the copied subtree is made to point to
+//! the copy of that element, any other reference keeps pointing to the same
+//! element; references that wouldn't resolve to their target from their new
+//! position are rewritten as absolute references. Backlinks, kept in the meta
+//! storage, aren't copied: no reference points to the copied elements yet,
+//! while the copied bidirectional references get backlinks of their own when
+//! inserted.
+
+#[cfg(feature = "full")]
+use std::collections::HashSet;
+
+#[cfg(feature = "full")]
+use grovedb_costs::{
+    cost_return_on_error, cost_return_on_error_no_add, CostResult, CostsExt, OperationCost,
+};
+#[cfg(feature = "full")]
+use grovedb_merk::proofs::Query;
+use grovedb_path::SubtreePath;
+
+#[cfg(feature = "full")]
+use crate::{
+    batch::GroveDbOp,
+    metrics::{Metrics, OperationKind},
+    query_result_type::QueryResultType::QueryKeyElementPairResultType,
+    reference_path::{path_from_reference_path_type, ReferencePathType},
+    Element, Error, GroveDb, PathQuery, SizedQuery, TransactionArg,
+};
+
+/// Number of elements read, and written with a batch, at once
+#[cfg(feature = "full")]
+const COPY_PAGE_SIZE: u16 = 1024;
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Copies the subtree at `src_path` and all of its descendants to
+    /// `dst_key` in the subtree at `dst_path`. The element under `dst_key`
+    /// must not exist.
+    ///
+    /// References to elements of the copied subtree point to their copies,
+    /// other references to the same elements as in the source. Without a
+    /// transaction, the copy is made in one of its own, so it is written
+    /// entirely or not at all.
+    pub fn copy_subtree<'b, 'c, B, C, P, Q>(
+        &self,
+        src_path: P,
+        dst_path: Q,
+        dst_key: &[u8],
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        C: AsRef<[u8]> + 'c,
+        P: Into<SubtreePath<'b, B>>,
+        Q: Into<SubtreePath<'c, C>>,
+    {
+        let mut cost = OperationCost::default();
+        let src_path: SubtreePath<B> = src_path.into();
+        let dst_path: SubtreePath<C> = dst_path.into();
+
+        let Some((src_parent_path, src_key)) = src_path.derive_parent() else {
+            return Err(Error::InvalidPath(
+                "the root tree can't be copied".to_owned(),
+            ))
+            .wrap_with_cost(cost);
+        };
+
+        let internal_transaction = if transaction.is_none() {
+            Some(cost_return_on_error_no_add!(
+                &cost,
+                self.start_transaction()
+            ))
+        } else {
+            None
+        };
+        let transaction = transaction.or(internal_transaction.as_ref());
+
+        let element = cost_return_on_error!(
+            &mut cost,
+            self.get_raw(src_parent_path, src_key, transaction)
+        );
+        if !element.is_tree() {
+            return Err(Error::WrongElementType("only trees can be copied")).wrap_with_cost(cost);
+        }
+        if cost_return_on_error!(
+            &mut cost,
+            self.has_raw(dst_path.clone(), dst_key, transaction)
+        ) {
+            return Err(Error::OverrideNotAllowed(
+                "copying not allowed to override an existing element",
+            ))
+            .wrap_with_cost(cost);
+        }
+
+        // The subtrees are listed before anything is written, so a subtree
+        // can be copied into one of its own descendants
+        let subtrees_paths =
+            cost_return_on_error!(&mut cost, self.find_subtrees(&src_path, transaction));
+
+        let copy = SubtreeCopy {
+            src_path: src_path.to_vec(),
+            dst_path: dst_path.to_vec(),
+            dst_key: dst_key.to_vec(),
+        };
+        // The copy is counted as a single write once committed, not as the
+        // batches it is made of
+        let root_op = GroveDbOp::insert_op(
+            copy.dst_path.clone(),
+            copy.dst_key.clone(),
+            emptied_tree(element),
+        );
+        cost_return_on_error!(
+            &mut cost,
+            Metrics::suspended(|| self.apply_batch(vec![root_op], None, transaction))
+        );
+
+        for copy_references in [false, true] {
+            let mut batch = CopyBatch::default();
+            for subtree_path in &subtrees_paths {
+                let mut last_key = None;
+                loop {
+                    let page = cost_return_on_error!(
+                        &mut cost,
+                        self.copy_page(subtree_path, last_key.as_deref(), transaction)
+                    );
+                    let exhausted = page.len() < COPY_PAGE_SIZE as usize;
+                    last_key = page.last().map(|(key, _)| key.clone());
+
+                    for (key, element) in page {
+                        if copy.is_copy_root(subtree_path, &key)
+                            || is_reference(&element) != copy_references
+                        {
+                            continue;
+                        }
+                        let mut copied_path = copy.copied_path(subtree_path);
+                        copied_path.push(key.clone());
+                        if batch.copied_ahead.remove(&copied_path) {
+                            continue;
+                        }
+                        if !copy_references {
+                            copied_path.pop();
+                            batch.ops.push(GroveDbOp::insert_op(
+                                copied_path,
+                                key,
+                                emptied_tree(element),
+                            ));
+                            continue;
+                        }
+                        batch.in_batch.insert(copied_path);
+                        cost_return_on_error!(
+                            &mut cost,
+                            self.push_copied_reference(
+                                &copy,
+                                subtree_path.clone(),
+                                key,
+                                element,
+                                &mut batch,
+                                transaction,
+                            )
+                        );
+                    }
+                    let ops = batch.take_ops();
+                    if !ops.is_empty() {
+                        cost_return_on_error!(
+                            &mut cost,
+                            Metrics::suspended(|| self.apply_batch(ops, None, transaction))
+                        );
+                    }
+                    if exhausted {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let copied = if let Some(internal_transaction) = internal_transaction {
+            self.commit_transaction(internal_transaction).add_cost(cost)
+        } else {
+            Ok(()).wrap_with_cost(cost)
+        };
+        copied.map_ok(|_| {
+            self.metrics
+                .record(OperationKind::Write, &dst_path, Some(dst_key))
+        })
+    }
+
+    /// Reads a page of the elements of the subtree at `path`, starting right
+    /// after `last_key`, or from the first one without it
+    fn copy_page(
+        &self,
+        path: &[Vec<u8>],
+        last_key: Option<&[u8]>,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<(Vec<u8>, Element)>, Error> {
+        let mut query = Query::new();
+        match last_key {
+            Some(last_key) => query.insert_range_after(last_key.to_vec()..),
+            None => query.insert_all(),
+        }
+        let path_query = PathQuery::new(
+            path.to_vec(),
+            SizedQuery::new(query, Some(COPY_PAGE_SIZE), None),
+        );
+        self.query_raw(
+            &path_query,
+            true,
+            QueryKeyElementPairResultType,
+            transaction,
+        )
+        .map_ok(|(results, _)| results.to_key_elements())
+    }
+
+    /// Pushes to `batch` the insertion of the copy of the reference under
+    /// `key` of the subtree at `path`, along with the ones of the references
+    /// of the copied subtree it points to, directly or through other
+    /// references, that aren't copied yet. Those are copied ahead of their
+    /// turn.
+    fn push_copied_reference(
+        &self,
+        copy: &SubtreeCopy,
+        path: Vec<Vec<u8>>,
+        key: Vec<u8>,
+        element: Element,
+        batch: &mut CopyBatch,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        let mut next = Some((path, key, element));
+        while let Some((path, key, element)) = next.take() {
+            let copied_path = copy.copied_path(&path);
+            let (element, mut target) = cost_return_on_error_no_add!(
+                &cost,
+                copy.copied_reference(element, &path, &copied_path, &key)
+            );
+            batch
+                .ops
+                .push(GroveDbOp::insert_op(copied_path, key, element));
+
+            // Targets outside of the copy, or already copied, are found by
+            // the batch
+            if !target.starts_with(&copy.copy_root_path()) || batch.in_batch.contains(&target) {
+                continue;
+            }
+            let Some(target_key) = target.pop() else {
+                continue;
+            };
+            if cost_return_on_error!(
+                &mut cost,
+                self.has_raw(target.as_slice(), &target_key, transaction)
+            ) {
+                continue;
+            }
+            let target_src_path = copy.source_path(&target);
+            let target_element = cost_return_on_error!(
+                &mut cost,
+                self.get_raw_optional(target_src_path.as_slice().into(), &target_key, transaction)
+            );
+            // Every element other than a reference is already copied, and a
+            // missing target fails the batch as it would for any reference
+            if let Some(target_element) = target_element.filter(is_reference) {
+                target.push(target_key.clone());
+                batch.in_batch.insert(target.clone());
+                batch.copied_ahead.insert(target);
+                next = Some((target_src_path, target_key, target_element));
+            }
+        }
+
+        Ok(()).wrap_with_cost(cost)
+    }
+}
+
+/// Operations of the batch copying a page of elements
+#[cfg(feature = "full")]
+#[derive(Default)]
+struct CopyBatch {
+    ops: Vec<GroveDbOp>,
+    /// Qualified paths in the copy of the references inserted by `ops`
+    in_batch: HashSet<Vec<Vec<u8>>>,
+    /// Qualified paths in the copy of the references copied ahead of their
+    /// turn, for a reference pointing to them to find them, to be skipped
+    /// once their turn comes
+    copied_ahead: HashSet<Vec<Vec<u8>>>,
+}
+
+#[cfg(feature = "full")]
+impl CopyBatch {
+    /// Takes the operations of the batch, to start the next one
+    fn take_ops(&mut self) -> Vec<GroveDbOp> {
+        self.in_batch.clear();
+        std::mem::take(&mut self.ops)
+    }
+}
+
+/// Source and destination of a subtree copy
+#[cfg(feature = "full")]
+struct SubtreeCopy {
+    src_path: Vec<Vec<u8>>,
+    dst_path: Vec<Vec<u8>>,
+    dst_key: Vec<u8>,
+}
+
+#[cfg(feature = "full")]
+impl SubtreeCopy {
+    /// Path of the root of the copy
+    fn copy_root_path(&self) -> Vec<Vec<u8>> {
+        let mut path = self.dst_path.clone();
+        path.push(self.dst_key.clone());
+        path
+    }
+
+    /// Whether `key` of the subtree at `path` is the root of the copy, which
+    /// is found in the source when it is copied into one of its descendants
+    fn is_copy_root(&self, path: &[Vec<u8>], key: &[u8]) -> bool {
+        path == self.dst_path.as_slice() && key == self.dst_key.as_slice()
+    }
+
+    /// Path in the copy of the source path `path`
+    fn copied_path(&self, path: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        let mut copied_path = self.copy_root_path();
+        copied_path.extend_from_slice(&path[self.src_path.len()..]);
+        copied_path
+    }
+
+    /// Source path of the path `path` in the copy
+    fn source_path(&self, path: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        let mut source_path = self.src_path.clone();
+        source_path.extend_from_slice(&path[self.dst_path.len() + 1..]);
+        source_path
+    }
+
+    /// Returns the copy of the reference under `key` of the subtree at the
+    /// source path `path`, to be inserted at `copied_path`, and the qualified
+    /// path of the element it points to
+    fn copied_reference(
+        &self,
+        element: Element,
+        path: &[Vec<u8>],
+        copied_path: &[Vec<u8>],
+        key: &[u8],
+    ) -> Result<(Element, Vec<Vec<u8>>), Error> {
+        let (reference_path, max_hop, flags) = match &element {
+            Element::Reference(reference_path, max_hop, flags)
+            | Element::BidirectionalReference(reference_path, max_hop, flags) => {
+                (reference_path.clone(), *max_hop, flags.clone())
+            }
+            _ => return Err(Error::CorruptedCodeExecution("expected a reference")),
+        };
+
+        let target = path_from_reference_path_type(reference_path.clone(), path, Some(key))?;
+        let copied_target = if target.starts_with(&self.src_path) {
+            self.copied_path(&target)
+        } else {
+            target
+        };
+        let resolved =
+            path_from_reference_path_type(reference_path.clone(), copied_path, Some(key));
+        if resolved.ok().as_ref() == Some(&copied_target) {
+            return Ok((element, copied_target));
+        }
+
+        let reference_path = ReferencePathType::AbsolutePathReference(copied_target.clone());
+        let element = if element.is_bidirectional_reference() {
+            Element::BidirectionalReference(reference_path, max_hop, flags)
+        } else {
+            Element::Reference(reference_path, max_hop, flags)
+        };
+        Ok((element, copied_target))
+    }
+}
+
+#[cfg(feature = "full")]
+fn is_reference(element: &Element) -> bool {
+    matches!(
+        element,
+        Element::Reference(..) | Element::BidirectionalReference(..)
+    )
+}
+
+/// Copied trees are inserted empty, their root keys, sums and counts being
+/// rebuilt from the copied elements inserted into them
+#[cfg(feature = "full")]
+fn emptied_tree(element: Element) -> Element {
+    match element {
+        Element::Tree(_, flags) => Element::empty_tree_with_flags(flags),
+        Element::SumTree(.., flags) => Element::empty_sum_tree_with_flags(flags),
+        Element::CountTree(.., flags) => Element::empty_count_tree_with_flags(flags),
+        Element::BigSumTree(.., flags) => Element::empty_big_sum_tree_with_flags(flags),
+        Element::AggregateTree(_, aggregate_id, _, flags) => {
+            Element::empty_aggregate_tree_with_flags(aggregate_id, flags)
+        }
+        element => element,
+    }
+}
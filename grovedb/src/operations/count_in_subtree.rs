@@ -0,0 +1,74 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Subtree count operations
+
+#[cfg(feature = "full")]
+use grovedb_costs::{cost_return_on_error, CostResult, CostsExt, OperationCost};
+use grovedb_path::SubtreePath;
+
+#[cfg(feature = "full")]
+use crate::{Element, Error, GroveDb, TransactionArg};
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Get the number of elements held by the count tree at the given path.
+    /// The count is maintained on the count tree element stored in the
+    /// parent, so it is read in a single lookup whatever the subtree size.
+    pub fn count_in_subtree<'b, B, P>(
+        &self,
+        path: P,
+        transaction: TransactionArg,
+    ) -> CostResult<u64, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        let (parent_path, key) = match path.derive_parent() {
+            Some(parent) => parent,
+            None => {
+                return Err(Error::InvalidPath(
+                    "the root tree is not a count tree".to_owned(),
+                ))
+                .wrap_with_cost(cost)
+            }
+        };
+
+        let element = cost_return_on_error!(&mut cost, self.get_raw(parent_path, key, transaction));
+        match element {
+            Element::CountTree(_, count, _) => Ok(count).wrap_with_cost(cost),
+            _ => Err(Error::WrongElementType(
+                "the subtree at the given path is not a count tree",
+            ))
+            .wrap_with_cost(cost),
+        }
+    }
+}
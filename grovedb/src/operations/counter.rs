@@ -0,0 +1,347 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Merge-operator backed counters
+//!
+//! Increments of a counter are written as merge operands into the auxiliary
+//! storage of its subtree and folded by the storage on read and compaction, so
+//! an increment reads neither the counter nor the tree. Transactions
+//! incrementing the same counter concurrently still conflict on commit. The
+//! committed value of a counter is a sum item in the subtree under the
+//! counter's key; pending increments are folded into it, and therefore into
+//! the tree hash, only at sync points chosen by the caller with
+//! [`GroveDb::sync_counter`] or [`GroveDb::sync_counters`].
+
+#[cfg(feature = "full")]
+use grovedb_costs::{
+    cost_return_on_error, cost_return_on_error_no_add, CostResult, CostsExt, OperationCost,
+};
+use grovedb_path::SubtreePath;
+#[cfg(feature = "full")]
+use grovedb_storage::{
+    counter::{decode_counter, encode_counter},
+    RawIterator, Storage, StorageBatch, StorageContext,
+};
+
+#[cfg(feature = "full")]
+use crate::{
+    batch::GroveDbOp, metrics::OperationKind, transaction::storage_transaction,
+    util::storage_context_optional_tx, Element, Error, GroveDb, TransactionArg,
+};
+
+/// Prefix of the auxiliary storage keys holding pending counter increments
+#[cfg(feature = "full")]
+const PENDING_COUNTER_PREFIX: &[u8] = b"pending_counter/";
+
+#[cfg(feature = "full")]
+fn pending_counter_key(key: &[u8]) -> Vec<u8> {
+    let mut pending_key = PENDING_COUNTER_PREFIX.to_vec();
+    pending_key.extend_from_slice(key);
+    pending_key
+}
+
+#[cfg(feature = "full")]
+fn decode_pending_counter(bytes: &[u8]) -> Result<i64, Error> {
+    decode_counter(bytes)
+        .ok_or_else(|| Error::CorruptedData("unable to decode pending counter".to_owned()))
+}
+
+/// Value of a committed counter, a missing counter counts as zero
+#[cfg(feature = "full")]
+fn committed_counter_value(element: Option<&Element>) -> Result<i64, Error> {
+    match element {
+        None => Ok(0),
+        Some(Element::SumItem(value, _)) => Ok(*value),
+        Some(_) => Err(Error::WrongElementType("a counter must be a sum item")),
+    }
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Adds `delta` to the counter under `key` of the subtree at `path`. The
+    /// increment is pending until the next sync of the counter and doesn't
+    /// change the tree hash.
+    pub fn increment_counter<'b, B, P>(
+        &self,
+        path: P,
+        key: &[u8],
+        delta: i64,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        cost_return_on_error!(
+            &mut cost,
+            self.check_subtree_exists_path_not_found(path.clone(), transaction)
+        );
+
+        let batch = StorageBatch::new();
+        #[cfg(feature = "audit_log")]
+        let path_vec = path.to_vec();
+        let recorded_path = path.clone();
+        storage_context_optional_tx!(self.db, path, Some(&batch), transaction, storage, {
+            cost_return_on_error!(
+                &mut cost,
+                storage
+                    .unwrap_add_cost(&mut cost)
+                    .merge_aux(pending_counter_key(key), &encode_counter(delta))
+                    .map_err(Into::into)
+            );
+        });
+        #[cfg(feature = "audit_log")]
+        {
+            let entries = vec![crate::operations::audit_log::increment_counter_entry(
+                path_vec,
+                key,
+                &encode_counter(delta),
+            )];
+            let recorded = self.record_audit_log(entries, &mut cost, &batch, transaction);
+            cost_return_on_error_no_add!(&cost, recorded);
+        }
+
+        self.db
+            .commit_multi_context_batch(batch, storage_transaction(transaction))
+            .add_cost(cost)
+            .map_err(Into::into)
+            .map_ok(|_| {
+                self.metrics
+                    .record(OperationKind::Write, &recorded_path, Some(key))
+            })
+    }
+
+    /// Returns the value of the counter under `key` of the subtree at `path`,
+    /// including its pending increments
+    pub fn get_counter<'b, B, P>(
+        &self,
+        path: P,
+        key: &[u8],
+        transaction: TransactionArg,
+    ) -> CostResult<i64, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        let element = cost_return_on_error!(
+            &mut cost,
+            self.get_raw_optional(path.clone(), key, transaction)
+        );
+        let committed =
+            cost_return_on_error_no_add!(&cost, committed_counter_value(element.as_ref()));
+        let pending =
+            cost_return_on_error!(&mut cost, self.get_pending_counter(path, key, transaction));
+
+        committed
+            .checked_add(pending)
+            .ok_or(Error::InvalidInput("counter overflow"))
+            .wrap_with_cost(cost)
+    }
+
+    /// Folds the pending increments of the counter under `key` of the subtree
+    /// at `path` into its committed value. Returns the new committed value.
+    pub fn sync_counter<'b, B, P>(
+        &self,
+        path: P,
+        key: &[u8],
+        transaction: TransactionArg,
+    ) -> CostResult<i64, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        let pending = cost_return_on_error!(
+            &mut cost,
+            self.get_pending_counter(path.clone(), key, transaction)
+        );
+        if pending == 0 {
+            return self.get_counter(path, key, transaction).add_cost(cost);
+        }
+        let mut synced = cost_return_on_error!(
+            &mut cost,
+            self.sync_pending_counters(path, vec![(key.to_vec(), pending)], transaction)
+        );
+
+        synced
+            .pop()
+            .map(|(_, value)| value)
+            .ok_or(Error::CorruptedCodeExecution("synced counter is missing"))
+            .wrap_with_cost(cost)
+    }
+
+    /// Folds the pending increments of all counters of the subtree at `path`
+    /// into their committed values. Returns the keys of the synced counters
+    /// with their new committed values.
+    pub fn sync_counters<'b, B, P>(
+        &self,
+        path: P,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<(Vec<u8>, i64)>, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        cost_return_on_error!(
+            &mut cost,
+            self.check_subtree_exists_path_not_found(path.clone(), transaction)
+        );
+
+        let mut pending_counters = Vec::new();
+        storage_context_optional_tx!(self.db, path.clone(), None, transaction, storage, {
+            let storage = storage.unwrap_add_cost(&mut cost);
+            let mut iter = storage.raw_iter_aux();
+            iter.seek(PENDING_COUNTER_PREFIX).unwrap_add_cost(&mut cost);
+            while iter.valid().unwrap_add_cost(&mut cost) {
+                let Some(pending_key) = iter.key().unwrap_add_cost(&mut cost) else {
+                    break;
+                };
+                let Some(key) = pending_key.strip_prefix(PENDING_COUNTER_PREFIX) else {
+                    break;
+                };
+                let key = key.to_vec();
+                let pending = cost_return_on_error_no_add!(
+                    &cost,
+                    decode_pending_counter(iter.value().unwrap_add_cost(&mut cost).unwrap_or(&[]))
+                );
+                if pending != 0 {
+                    pending_counters.push((key, pending));
+                }
+                iter.next().unwrap_add_cost(&mut cost);
+            }
+        });
+
+        self.sync_pending_counters(path, pending_counters, transaction)
+            .add_cost(cost)
+    }
+
+    fn get_pending_counter<B: AsRef<[u8]>>(
+        &self,
+        path: SubtreePath<B>,
+        key: &[u8],
+        transaction: TransactionArg,
+    ) -> CostResult<i64, Error> {
+        let mut cost = OperationCost::default();
+
+        storage_context_optional_tx!(self.db, path, None, transaction, storage, {
+            let pending = cost_return_on_error!(
+                &mut cost,
+                storage
+                    .unwrap_add_cost(&mut cost)
+                    .get_aux(pending_counter_key(key))
+                    .map_err(Into::into)
+            );
+            pending
+                .map_or(Ok(0), |pending| decode_pending_counter(&pending))
+                .wrap_with_cost(cost)
+        })
+    }
+
+    /// Inserts the new committed values of counters and subtracts the synced
+    /// increments from the pending ones in a single batch. Increments made
+    /// concurrently stay pending.
+    fn sync_pending_counters<B: AsRef<[u8]>>(
+        &self,
+        path: SubtreePath<B>,
+        pending_counters: Vec<(Vec<u8>, i64)>,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<(Vec<u8>, i64)>, Error> {
+        let mut cost = OperationCost::default();
+        if pending_counters.is_empty() {
+            return Ok(Vec::new()).wrap_with_cost(cost);
+        }
+
+        let path_vec = path.to_vec();
+        let mut ops = Vec::with_capacity(pending_counters.len());
+        let mut synced = Vec::with_capacity(pending_counters.len());
+        for (key, pending) in pending_counters.iter() {
+            let element = cost_return_on_error!(
+                &mut cost,
+                self.get_raw_optional(path.clone(), key, transaction)
+            );
+            let committed =
+                cost_return_on_error_no_add!(&cost, committed_counter_value(element.as_ref()));
+            let value = cost_return_on_error_no_add!(
+                &cost,
+                committed
+                    .checked_add(*pending)
+                    .ok_or(Error::InvalidInput("counter overflow"))
+            );
+            let flags = element.and_then(|element| element.get_flags_owned());
+            ops.push(GroveDbOp::insert_op(
+                path_vec.clone(),
+                key.clone(),
+                Element::new_sum_item_with_flags(value, flags),
+            ));
+            synced.push((key.clone(), value));
+        }
+
+        let storage_batch = StorageBatch::new();
+        cost_return_on_error!(
+            &mut cost,
+            self.apply_batch_into_storage_batch(ops, &storage_batch, transaction)
+        );
+
+        let recorded_path = path.clone();
+        storage_context_optional_tx!(self.db, path, Some(&storage_batch), transaction, storage, {
+            let storage = storage.unwrap_add_cost(&mut cost);
+            for (key, pending) in pending_counters.iter() {
+                cost_return_on_error!(
+                    &mut cost,
+                    storage
+                        .merge_aux(
+                            pending_counter_key(key),
+                            &encode_counter(pending.wrapping_neg())
+                        )
+                        .map_err(Into::into)
+                );
+            }
+        });
+
+        self.db
+            .commit_multi_context_batch(storage_batch, storage_transaction(transaction))
+            .map_err(Into::into)
+            .map_ok(|_| {
+                self.metrics
+                    .record(OperationKind::Write, &recorded_path, None);
+                synced
+            })
+            .add_cost(cost)
+    }
+}
@@ -0,0 +1,142 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Creation of the missing subtrees along a path
+
+#[cfg(feature = "full")]
+use grovedb_costs::{
+    cost_return_on_error, cost_return_on_error_no_add, CostResult, CostsExt, OperationCost,
+};
+use grovedb_path::SubtreePath;
+
+#[cfg(feature = "full")]
+use crate::{
+    batch::{GroveDbOp, Op},
+    element::ElementGetExt,
+    util::storage_context_optional_tx,
+    Element, Error, GroveDb, TransactionArg,
+};
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Inserts an element, first creating an empty tree for every segment of
+    /// the path that doesn't exist yet. The trees and the element are written
+    /// in a single batch, so either all of them are inserted or none is. A
+    /// tree created by someone else in the meantime is kept rather than
+    /// replaced by an empty one.
+    pub fn insert_create_path<'b, B, P>(
+        &self,
+        path: P,
+        key: &[u8],
+        element: Element,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+
+        let path_vec = path.into().to_vec();
+        let mut ops =
+            cost_return_on_error!(&mut cost, self.create_path_ops(&path_vec, &[], transaction));
+        ops.push(GroveDbOp::insert_op(path_vec, key.to_vec(), element));
+        self.apply_batch(ops, None, transaction).add_cost(cost)
+    }
+
+    /// Returns the operations inserting an empty tree for every segment of
+    /// the path that doesn't exist yet, parents first, to be added to a batch
+    /// writing below the path. Trees inserted by `pending_ops` are treated as
+    /// existing, so several paths sharing a prefix can be created in the same
+    /// batch. Errors if a segment of the path is an element other than a tree.
+    /// The trees are inserted only if they still don't exist when the batch
+    /// is applied, which is checked again while the batch is executed.
+    pub fn create_path_ops(
+        &self,
+        path: &[Vec<u8>],
+        pending_ops: &[GroveDbOp],
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<GroveDbOp>, Error> {
+        let mut cost = OperationCost::default();
+
+        let mut ops = Vec::new();
+        // Once a segment is created by the batch, the ones below can't exist yet
+        let mut created = false;
+        for (depth, key) in path.iter().enumerate() {
+            let parent_path = &path[..depth];
+            if pending_ops
+                .iter()
+                .any(|op| inserts_tree_at(op, parent_path, key))
+            {
+                created = true;
+                continue;
+            }
+            if !created {
+                let subtree_path: SubtreePath<_> = parent_path.into();
+                let element = storage_context_optional_tx!(
+                    self.db,
+                    subtree_path,
+                    None,
+                    transaction,
+                    storage,
+                    {
+                        Element::get_optional_from_storage(&storage.unwrap_add_cost(&mut cost), key)
+                            .unwrap_add_cost(&mut cost)
+                    }
+                );
+                match cost_return_on_error_no_add!(&cost, element) {
+                    Some(element) if element.is_tree() => continue,
+                    Some(_) => {
+                        return Err(Error::InvalidPath(format!(
+                            "element at key {} of the path is not a tree",
+                            hex::encode(key)
+                        )))
+                        .wrap_with_cost(cost)
+                    }
+                    None => created = true,
+                }
+            }
+            ops.push(GroveDbOp::insert_if_not_exists_op(
+                parent_path.to_vec(),
+                key.clone(),
+                Element::empty_tree(),
+            ));
+        }
+        Ok(ops).wrap_with_cost(cost)
+    }
+}
+
+#[cfg(feature = "full")]
+fn inserts_tree_at(op: &GroveDbOp, path: &[Vec<u8>], key: &[u8]) -> bool {
+    match &op.op {
+        Op::Insert { element } | Op::Replace { element } | Op::InsertIfNotExists { element } => {
+            element.is_tree() && op.key.as_slice() == key && op.path.to_path() == path
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,277 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Clear subtree
+
+use std::collections::HashMap;
+
+use grovedb_costs::{cost_return_on_error, CostResult, CostsExt, OperationCost};
+use grovedb_merk::Merk;
+use grovedb_path::SubtreePath;
+use grovedb_storage::{
+    rocksdb_storage::{PrefixedRocksDbStorageContext, PrefixedRocksDbTransactionContext},
+    Storage, StorageBatch,
+};
+
+use crate::{
+    metrics::OperationKind, transaction::storage_transaction, Error, GroveDb, Transaction,
+    TransactionArg,
+};
+
+#[cfg(feature = "full")]
+#[derive(Clone)]
+/// Clear options
+pub struct ClearOptions {
+    /// Look for subtrees in the cleared subtree. Only skip it when the
+    /// subtree is known to hold no subtrees, as the storage of nested subtrees
+    /// would otherwise be left behind
+    pub check_for_subtrees: bool,
+    /// Allow clearing a subtree holding subtrees, which are then deleted
+    pub allow_deleting_subtrees: bool,
+    /// Clearing a subtree holding subtrees while not allowed returns error
+    pub trying_to_clear_with_subtrees_returns_error: bool,
+}
+
+#[cfg(feature = "full")]
+impl Default for ClearOptions {
+    fn default() -> Self {
+        ClearOptions {
+            check_for_subtrees: true,
+            allow_deleting_subtrees: false,
+            trying_to_clear_with_subtrees_returns_error: true,
+        }
+    }
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Removes every element of the subtree at `path` while keeping the tree
+    /// element holding it, which becomes an empty tree. Entries are removed
+    /// from storage directly, without going through Merk deletions, and the
+    /// new root of the subtree is propagated once.
+    ///
+    /// Returns whether the subtree was cleared, which it isn't when it holds
+    /// subtrees the options neither allow deleting nor turn into an error.
+    /// The backlinks held by the cleared subtrees, and the ones of the
+    /// references they hold, are removed with them.
+    pub fn clear_subtree<'b, B, P>(
+        &self,
+        path: P,
+        options: Option<ClearOptions>,
+        transaction: TransactionArg,
+    ) -> CostResult<bool, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+        let recorded_path = path.clone();
+        let batch = StorageBatch::new();
+        let cleared = cost_return_on_error!(
+            &mut cost,
+            self.clear_subtree_into_storage_batch(path, options, &batch, transaction)
+        );
+        if !cleared {
+            return Ok(false).wrap_with_cost(cost);
+        }
+
+        self.db
+            .commit_multi_context_batch(batch, storage_transaction(transaction))
+            .map_err(Into::into)
+            .map_ok(|_| {
+                self.metrics
+                    .record(OperationKind::Write, &recorded_path, None);
+                true
+            })
+            .add_cost(cost)
+    }
+
+    /// Stages the clearing of the subtree at `path` into `batch`, see
+    /// [GroveDb::clear_subtree]
+    pub(crate) fn clear_subtree_into_storage_batch<B: AsRef<[u8]>>(
+        &self,
+        path: SubtreePath<B>,
+        options: Option<ClearOptions>,
+        batch: &StorageBatch,
+        transaction: TransactionArg,
+    ) -> CostResult<bool, Error> {
+        let mut cost = OperationCost::default();
+        let options = options.unwrap_or_default();
+
+        if path.is_root() {
+            return Err(Error::InvalidPath(
+                "the root tree can't be cleared".to_owned(),
+            ))
+            .wrap_with_cost(cost);
+        }
+        cost_return_on_error!(
+            &mut cost,
+            self.check_subtree_exists_path_not_found(path.clone(), transaction)
+        );
+
+        // The cleared subtree comes first, followed by the nested subtrees
+        let subtrees_paths = if options.check_for_subtrees {
+            cost_return_on_error!(&mut cost, self.find_subtrees(&path, transaction))
+        } else {
+            vec![path.to_vec()]
+        };
+        if subtrees_paths.len() > 1 && !options.allow_deleting_subtrees {
+            return if options.trying_to_clear_with_subtrees_returns_error {
+                Err(Error::ClearingTreeWithSubtreesNotAllowed(
+                    "trying to clear a tree holding subtrees, but options not allowing this",
+                ))
+            } else {
+                Ok(false)
+            }
+            .wrap_with_cost(cost);
+        }
+
+        // The deletion is only staged in `batch`, so the nested subtrees can
+        // still be found
+        let path_len = subtrees_paths[0].len();
+        cost_return_on_error!(
+            &mut cost,
+            self.unregister_subtree_prefixes(
+                subtrees_paths
+                    .iter()
+                    .filter(|subtree_path| subtree_path.len() == path_len + 1)
+                    .cloned(),
+                batch,
+                transaction
+            )
+        );
+
+        cost_return_on_error!(
+            &mut cost,
+            self.remove_backlinks_of_subtrees(std::iter::once(path.to_vec()), batch, transaction)
+        );
+        cost_return_on_error!(
+            &mut cost,
+            self.prune_reverse_key_index_below(path.to_vec(), batch, transaction)
+        );
+
+        let cleared = if let Some(transaction) = transaction {
+            self.clear_subtree_on_transaction(path, &subtrees_paths, transaction, batch)
+        } else {
+            self.clear_subtree_without_transaction(path, &subtrees_paths, batch)
+        };
+        cost_return_on_error!(&mut cost, cleared);
+        #[cfg(feature = "audit_log")]
+        {
+            let entries = vec![crate::operations::audit_log::clear_entry(
+                subtrees_paths[0].clone(),
+            )];
+            let recorded = self.record_audit_log(entries, &mut cost, batch, transaction);
+            grovedb_costs::cost_return_on_error_no_add!(&cost, recorded);
+        }
+
+        Ok(true).wrap_with_cost(cost)
+    }
+
+    fn clear_subtree_on_transaction<B: AsRef<[u8]>>(
+        &self,
+        path: SubtreePath<B>,
+        subtrees_paths: &[Vec<Vec<u8>>],
+        transaction: &Transaction,
+        batch: &StorageBatch,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        for subtree_path in subtrees_paths.iter().skip(1) {
+            let p: SubtreePath<_> = subtree_path.as_slice().into();
+            let mut storage = self
+                .db
+                .get_transactional_storage_context(p, Some(batch), transaction)
+                .unwrap_add_cost(&mut cost);
+            cost_return_on_error!(
+                &mut cost,
+                storage.clear().map_err(|e| {
+                    Error::CorruptedData(format!("unable to cleanup tree from storage: {e}"))
+                })
+            );
+        }
+
+        let mut subtree = cost_return_on_error!(
+            &mut cost,
+            self.open_transactional_merk_at_path(path.clone(), transaction, Some(batch))
+        );
+        cost_return_on_error!(
+            &mut cost,
+            subtree.clear().map_err(|e| {
+                Error::CorruptedData(format!("unable to cleanup tree from storage: {e}"))
+            })
+        );
+
+        let mut merk_cache: HashMap<SubtreePath<B>, Merk<PrefixedRocksDbTransactionContext>> =
+            HashMap::default();
+        merk_cache.insert(path.clone(), subtree);
+        self.propagate_changes_with_transaction(merk_cache, path, transaction, batch)
+            .add_cost(cost)
+    }
+
+    fn clear_subtree_without_transaction<B: AsRef<[u8]>>(
+        &self,
+        path: SubtreePath<B>,
+        subtrees_paths: &[Vec<Vec<u8>>],
+        batch: &StorageBatch,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        for subtree_path in subtrees_paths.iter().skip(1) {
+            let p: SubtreePath<_> = subtree_path.as_slice().into();
+            let mut inner_subtree = cost_return_on_error!(
+                &mut cost,
+                self.open_non_transactional_merk_at_path(p, Some(batch))
+            );
+            cost_return_on_error!(
+                &mut cost,
+                inner_subtree.clear().map_err(|e| {
+                    Error::CorruptedData(format!("unable to cleanup tree from storage: {e}"))
+                })
+            );
+        }
+
+        let mut subtree = cost_return_on_error!(
+            &mut cost,
+            self.open_non_transactional_merk_at_path(path.clone(), Some(batch))
+        );
+        cost_return_on_error!(
+            &mut cost,
+            subtree.clear().map_err(|e| {
+                Error::CorruptedData(format!("unable to cleanup tree from storage: {e}"))
+            })
+        );
+
+        let mut merk_cache: HashMap<SubtreePath<B>, Merk<PrefixedRocksDbStorageContext>> =
+            HashMap::default();
+        merk_cache.insert(path.clone(), subtree);
+        self.propagate_changes_without_transaction(merk_cache, path, batch)
+            .add_cost(cost)
+    }
+}
@@ -29,15 +29,15 @@
 //! Delete up tree
 
 use grovedb_costs::{
-    cost_return_on_error, cost_return_on_error_no_add,
-    storage_cost::removal::{StorageRemovedBytes, StorageRemovedBytes::BasicStorageRemoval},
+    cost_return_on_error, cost_return_on_error_no_add, storage_cost::removal::StorageRemovedBytes,
     CostResult, CostsExt, OperationCost,
 };
 use grovedb_path::SubtreePath;
 
 use crate::{
-    batch::GroveDbOp, operations::delete::DeleteOptions, ElementFlags, Error, GroveDb,
-    TransactionArg,
+    batch::{BatchApplyOptions, GroveDbOp},
+    operations::{delete::DeleteOptions, insertion_metadata::InsertionMetadata},
+    ElementFlags, Error, GroveDb, TransactionArg,
 };
 
 #[cfg(feature = "full")]
@@ -54,6 +54,9 @@ pub struct DeleteUpTreeOptions {
     pub validate_tree_at_path_exists: bool,
     /// Stop path height
     pub stop_path_height: Option<u16>,
+    /// Block height and timestamp of the deletions, recorded by the subtrees
+    /// tracking the last modification of their elements
+    pub insertion_metadata: Option<InsertionMetadata>,
 }
 
 #[cfg(feature = "full")]
@@ -65,6 +68,7 @@ impl Default for DeleteUpTreeOptions {
             base_root_storage_is_free: true,
             validate_tree_at_path_exists: false,
             stop_path_height: None,
+            insertion_metadata: None,
         }
     }
 }
@@ -77,6 +81,8 @@ impl DeleteUpTreeOptions {
             deleting_non_empty_trees_returns_error: self.deleting_non_empty_trees_returns_error,
             base_root_storage_is_free: self.base_root_storage_is_free,
             validate_tree_at_path_exists: self.validate_tree_at_path_exists,
+            insertion_metadata: self.insertion_metadata,
+            ..Default::default()
         }
     }
 }
@@ -101,11 +107,9 @@ impl GroveDb {
             key,
             options,
             transaction,
-            |_, removed_key_bytes, removed_value_bytes| {
-                Ok((
-                    BasicStorageRemoval(removed_key_bytes),
-                    (BasicStorageRemoval(removed_value_bytes)),
-                ))
+            |flags, removed_key_bytes, removed_value_bytes| {
+                self.flags_codec
+                    .decode_removed_bytes(flags, removed_key_bytes, removed_value_bytes)
             },
         )
     }
@@ -159,7 +163,10 @@ impl GroveDb {
         let ops_len = ops.len();
         self.apply_batch_with_element_flags_update(
             ops,
-            None,
+            Some(BatchApplyOptions {
+                insertion_metadata: options.insertion_metadata,
+                ..Default::default()
+            }),
             |_, _, _| Ok(false),
             split_removal_bytes_function,
             transaction,
@@ -31,18 +31,24 @@
 #[cfg(feature = "estimated_costs")]
 mod average_case;
 #[cfg(feature = "full")]
+mod clear_subtree;
+#[cfg(feature = "full")]
 mod delete_up_tree;
+#[cfg(feature = "full")]
+mod referrers;
 #[cfg(feature = "estimated_costs")]
 mod worst_case;
 
 #[cfg(feature = "full")]
 use std::collections::{BTreeSet, HashMap};
 
+#[cfg(feature = "full")]
+pub use clear_subtree::ClearOptions;
 #[cfg(feature = "full")]
 pub use delete_up_tree::DeleteUpTreeOptions;
 #[cfg(feature = "full")]
 use grovedb_costs::{
-    cost_return_on_error,
+    cost_return_on_error, cost_return_on_error_no_add,
     storage_cost::removal::{StorageRemovedBytes, StorageRemovedBytes::BasicStorageRemoval},
     CostResult, CostsExt, OperationCost,
 };
@@ -54,11 +60,17 @@ use grovedb_storage::{
     rocksdb_storage::{PrefixedRocksDbStorageContext, PrefixedRocksDbTransactionContext},
     Storage, StorageBatch, StorageContext,
 };
+#[cfg(feature = "full")]
+pub use referrers::ReferrerHandling;
 
 use crate::util::merk_optional_tx_path_not_empty;
 #[cfg(feature = "full")]
 use crate::{
-    batch::{GroveDbOp, Op},
+    batch::{BatchApplyOptions, GroveDbOp, Op},
+    element::ElementDeleteExt,
+    metrics::OperationKind,
+    operations::insertion_metadata::InsertionMetadata,
+    transaction::storage_transaction,
     util::{storage_context_optional_tx, storage_context_with_parent_optional_tx},
     Element, ElementFlags, Error, GroveDb, Transaction, TransactionArg,
 };
@@ -75,6 +87,12 @@ pub struct DeleteOptions {
     pub base_root_storage_is_free: bool,
     /// Validate tree at path exists
     pub validate_tree_at_path_exists: bool,
+    /// What `delete` does with the references pointing at the deleted
+    /// element or into the deleted subtree
+    pub referrer_handling: ReferrerHandling,
+    /// Block height and timestamp of the deletion, recorded by the subtrees
+    /// tracking the last modification of their elements
+    pub insertion_metadata: Option<InsertionMetadata>,
 }
 
 #[cfg(feature = "full")]
@@ -85,6 +103,8 @@ impl Default for DeleteOptions {
             deleting_non_empty_trees_returns_error: true,
             base_root_storage_is_free: true,
             validate_tree_at_path_exists: false,
+            referrer_handling: ReferrerHandling::Ignore,
+            insertion_metadata: None,
         }
     }
 }
@@ -112,30 +132,170 @@ impl GroveDb {
         B: AsRef<[u8]> + 'b,
         P: Into<SubtreePath<'b, B>>,
     {
+        let path: SubtreePath<B> = path.into();
         let options = options.unwrap_or_default();
+        if options.referrer_handling != ReferrerHandling::Ignore {
+            return self.delete_handling_referrers(path, key, options, transaction);
+        }
+        if self.maintains_backlinks() {
+            return self.delete_maintaining_backlinks(path, key, options, transaction);
+        }
+        self.delete_element(path, key, options, transaction)
+    }
+
+    /// Deletes an element, through a batch when it is a reference having a
+    /// backlink so that its backlink is deleted too
+    fn delete_maintaining_backlinks<B: AsRef<[u8]>>(
+        &self,
+        path: SubtreePath<B>,
+        key: &[u8],
+        options: DeleteOptions,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        let existing = self
+            .get_raw(path.clone(), key, transaction)
+            .unwrap_add_cost(&mut cost);
+        if existing
+            .ok()
+            .and_then(|existing| self.backlinked_reference(&existing))
+            .is_none()
+        {
+            return self
+                .delete_element(path, key, options, transaction)
+                .add_cost(cost);
+        }
+        self.apply_batch(
+            vec![GroveDbOp::delete_op(path.to_vec(), key.to_vec())],
+            Some(BatchApplyOptions {
+                base_root_storage_is_free: options.base_root_storage_is_free,
+                insertion_metadata: options.insertion_metadata,
+                ..Default::default()
+            }),
+            transaction,
+        )
+        .add_cost(cost)
+    }
+
+    /// Deletes an element, the references and backlinks it affects being
+    /// left as is
+    fn delete_element<B: AsRef<[u8]>>(
+        &self,
+        path: SubtreePath<B>,
+        key: &[u8],
+        options: DeleteOptions,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
         let batch = StorageBatch::new();
+        #[cfg(feature = "audit_log")]
+        let audit_entry = crate::operations::audit_log::delete_entry(path.to_vec(), key);
+        let recorded_path = path.clone();
 
-        let collect_costs = self
-            .delete_internal(
-                path.into(),
-                key,
-                &options,
-                transaction,
-                &mut |_, removed_key_bytes, removed_value_bytes| {
-                    Ok((
-                        BasicStorageRemoval(removed_key_bytes),
-                        BasicStorageRemoval(removed_value_bytes),
-                    ))
-                },
-                &batch,
+        let collect_costs = self.delete_internal(
+            path,
+            key,
+            &options,
+            transaction,
+            &mut |value, removed_key_bytes, removed_value_bytes| {
+                self.decode_removed_bytes(value, removed_key_bytes, removed_value_bytes)
+            },
+            &batch,
+        );
+
+        #[cfg(feature = "audit_log")]
+        let collect_costs = self.audit_mutation(
+            collect_costs,
+            |deleted| deleted.then_some(audit_entry).into_iter().collect(),
+            &batch,
+            transaction,
+        );
+
+        // Elements which are not deleted, as non empty trees the options don't
+        // allow deleting, are not counted
+        collect_costs
+            .flat_map_ok(|deleted| {
+                self.db
+                    .commit_multi_context_batch(batch, storage_transaction(transaction))
+                    .map_err(Into::into)
+                    .map_ok(|_| deleted)
+            })
+            .map_ok(|deleted| {
+                if deleted {
+                    self.metrics
+                        .record(OperationKind::Write, &recorded_path, Some(key));
+                }
+            })
+    }
+
+    /// Deletes an element, failing if references point at it or into the
+    /// subtree it holds, or deleting them too, depending on the referrer
+    /// handling of `options`. Cascading deletions are applied in a single
+    /// transaction.
+    fn delete_handling_referrers<B: AsRef<[u8]>>(
+        &self,
+        path: SubtreePath<B>,
+        key: &[u8],
+        options: DeleteOptions,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        let mut qualified_path = path.to_vec();
+        qualified_path.push(key.to_vec());
+        let referrers = cost_return_on_error!(
+            &mut cost,
+            self.referrers_of_deleted(
+                &[qualified_path],
+                &BTreeSet::new(),
+                options.referrer_handling,
+                transaction
             )
-            .map_ok(|_| ());
+        );
+        let handling = options.referrer_handling;
+        let options = DeleteOptions {
+            referrer_handling: ReferrerHandling::Ignore,
+            ..options
+        };
+        if referrers.is_empty() {
+            return self
+                .delete(path, key, Some(options), transaction)
+                .add_cost(cost);
+        }
+        if handling != ReferrerHandling::Cascade {
+            return Err(Error::DanglingReferences(referrers)).wrap_with_cost(cost);
+        }
 
-        collect_costs.flat_map_ok(|_| {
-            self.db
-                .commit_multi_context_batch(batch, transaction)
-                .map_err(Into::into)
-        })
+        let referrer_ops = referrers
+            .into_iter()
+            .map(|(path, key)| GroveDbOp::delete_op(path, key))
+            .collect();
+        let referrer_batch_options = Some(BatchApplyOptions {
+            insertion_metadata: options.insertion_metadata,
+            ..Default::default()
+        });
+        match transaction {
+            Some(transaction) => {
+                cost_return_on_error!(
+                    &mut cost,
+                    self.delete(path, key, Some(options), Some(transaction))
+                );
+                self.apply_batch(referrer_ops, referrer_batch_options, Some(transaction))
+                    .add_cost(cost)
+            }
+            None => {
+                let transaction = cost_return_on_error_no_add!(&cost, self.start_transaction());
+                cost_return_on_error!(
+                    &mut cost,
+                    self.delete(path, key, Some(options), Some(&transaction))
+                );
+                cost_return_on_error!(
+                    &mut cost,
+                    self.apply_batch(referrer_ops, referrer_batch_options, Some(&transaction))
+                );
+                self.commit_transaction(transaction).add_cost(cost)
+            }
+        }
     }
 
     /// Delete element with sectional storage function
@@ -156,37 +316,45 @@ impl GroveDb {
     ) -> CostResult<(), Error> {
         let options = options.unwrap_or_default();
         let batch = StorageBatch::new();
+        #[cfg(feature = "audit_log")]
+        let audit_entry = crate::operations::audit_log::delete_entry(path.to_vec(), key);
 
-        let collect_costs = self
-            .delete_internal(
-                path,
-                key,
-                &options,
-                transaction,
-                &mut |value, removed_key_bytes, removed_value_bytes| {
-                    let mut element = Element::deserialize(value.as_slice())
-                        .map_err(|e| MerkError::ClientCorruptionError(e.to_string()))?;
-                    let maybe_flags = element.get_flags_mut();
-                    match maybe_flags {
-                        None => Ok((
-                            BasicStorageRemoval(removed_key_bytes),
-                            BasicStorageRemoval(removed_value_bytes),
-                        )),
-                        Some(flags) => (split_removal_bytes_function)(
-                            flags,
-                            removed_key_bytes,
-                            removed_value_bytes,
-                        )
-                        .map_err(|e| MerkError::ClientCorruptionError(e.to_string())),
-                    }
-                },
-                &batch,
-            )
-            .map_ok(|_| ());
+        let collect_costs = self.delete_internal(
+            path,
+            key,
+            &options,
+            transaction,
+            &mut |value, removed_key_bytes, removed_value_bytes| {
+                let mut element = Element::deserialize(value.as_slice())
+                    .map_err(|e| MerkError::ClientCorruptionError(e.to_string()))?;
+                let maybe_flags = element.get_flags_mut();
+                match maybe_flags {
+                    None => Ok((
+                        BasicStorageRemoval(removed_key_bytes),
+                        BasicStorageRemoval(removed_value_bytes),
+                    )),
+                    Some(flags) => (split_removal_bytes_function)(
+                        flags,
+                        removed_key_bytes,
+                        removed_value_bytes,
+                    )
+                    .map_err(|e| MerkError::ClientCorruptionError(e.to_string())),
+                }
+            },
+            &batch,
+        );
+
+        #[cfg(feature = "audit_log")]
+        let collect_costs = self.audit_mutation(
+            collect_costs,
+            |deleted| deleted.then_some(audit_entry).into_iter().collect(),
+            &batch,
+            transaction,
+        );
 
         collect_costs.flat_map_ok(|_| {
             self.db
-                .commit_multi_context_batch(batch, transaction)
+                .commit_multi_context_batch(batch, storage_transaction(transaction))
                 .map_err(Into::into)
         })
     }
@@ -202,24 +370,33 @@ impl GroveDb {
         B: AsRef<[u8]> + 'b,
         P: Into<SubtreePath<'b, B>>,
     {
+        let path: SubtreePath<B> = path.into();
         let batch = StorageBatch::new();
+        #[cfg(feature = "audit_log")]
+        let audit_entry = crate::operations::audit_log::delete_entry(path.to_vec(), key);
 
         let collect_costs = self.delete_if_empty_tree_with_sectional_storage_function(
-            path.into(),
+            path,
             key,
             transaction,
-            &mut |_, removed_key_bytes, removed_value_bytes| {
-                Ok((
-                    BasicStorageRemoval(removed_key_bytes),
-                    (BasicStorageRemoval(removed_value_bytes)),
-                ))
+            &mut |flags, removed_key_bytes, removed_value_bytes| {
+                self.flags_codec
+                    .decode_removed_bytes(flags, removed_key_bytes, removed_value_bytes)
             },
             &batch,
         );
 
+        #[cfg(feature = "audit_log")]
+        let collect_costs = self.audit_mutation(
+            collect_costs,
+            |deleted| deleted.then_some(audit_entry).into_iter().collect(),
+            &batch,
+            transaction,
+        );
+
         collect_costs.flat_map_ok(|r| {
             self.db
-                .commit_multi_context_batch(batch, transaction)
+                .commit_multi_context_batch(batch, storage_transaction(transaction))
                 .map_err(Into::into)
                 .map_ok(|_| r)
         })
@@ -307,6 +484,9 @@ impl GroveDb {
                     match element {
                         Element::Tree(..) => (true, false),
                         Element::SumTree(..) => (true, true),
+                        Element::CountTree(..) => (true, true),
+                        Element::BigSumTree(..) => (true, true),
+                        Element::AggregateTree(..) => (true, true),
                         _ => (false, false),
                     }
                 }
@@ -396,7 +576,11 @@ impl GroveDb {
         >,
         batch: &StorageBatch,
     ) -> CostResult<bool, Error> {
-        if let Some(transaction) = transaction {
+        let parent_path = path.to_vec();
+        let mut deleted_path = parent_path.clone();
+        deleted_path.push(key.to_vec());
+
+        let collect_costs = if let Some(transaction) = transaction {
             self.delete_internal_on_transaction(
                 path,
                 key,
@@ -407,7 +591,45 @@ impl GroveDb {
             )
         } else {
             self.delete_internal_without_transaction(path, key, options, sectioned_removal, batch)
-        }
+        };
+
+        // The deletion is only staged in `batch`, so the subtrees it removes
+        // can still be found
+        collect_costs.flat_map_ok(|deleted| {
+            if deleted {
+                self.remove_backlinks_of_subtrees(
+                    std::iter::once(deleted_path.clone()),
+                    batch,
+                    transaction,
+                )
+                .flat_map_ok(|_| {
+                    self.unregister_subtree_prefixes(
+                        std::iter::once(deleted_path),
+                        batch,
+                        transaction,
+                    )
+                })
+                .flat_map_ok(|_| {
+                    self.prune_reverse_key_index(
+                        std::iter::once((parent_path.clone(), key)),
+                        batch,
+                        transaction,
+                    )
+                })
+                .flat_map_ok(|_| {
+                    self.record_insertion_metadata(
+                        std::iter::empty(),
+                        std::iter::once((parent_path, key)),
+                        options.insertion_metadata,
+                        batch,
+                        transaction,
+                    )
+                })
+                .map_ok(|_| deleted)
+            } else {
+                Ok(deleted).wrap_with_cost(OperationCost::default())
+            }
+        })
     }
 
     fn delete_internal_on_transaction<B: AsRef<[u8]>>(
@@ -737,9 +959,14 @@ mod tests {
     use pretty_assertions::assert_eq;
 
     use crate::{
-        operations::delete::{delete_up_tree::DeleteUpTreeOptions, DeleteOptions},
+        batch::{BatchApplyOptions, GroveDbOp},
+        operations::delete::{
+            delete_up_tree::DeleteUpTreeOptions, ClearOptions, DeleteOptions, ReferrerHandling,
+        },
+        reference_path::ReferencePathType,
         tests::{
-            common::EMPTY_PATH, make_empty_grovedb, make_test_grovedb, ANOTHER_TEST_LEAF, TEST_LEAF,
+            common::EMPTY_PATH, make_empty_grovedb, make_test_grovedb, TempGroveDb,
+            ANOTHER_TEST_LEAF, TEST_LEAF,
         },
         Element, Error,
     };
@@ -790,7 +1017,7 @@ mod tests {
         let _element = Element::new_item(b"ayy".to_vec());
 
         let db = make_test_grovedb();
-        let transaction = db.start_transaction();
+        let transaction = db.start_transaction().unwrap();
 
         // Insert some nested subtrees
         db.insert(
@@ -824,7 +1051,9 @@ mod tests {
             .unwrap(),
             Err(Error::PathParentLayerNotFound(_))
         ));
-        transaction.commit().expect("cannot commit transaction");
+        db.commit_transaction(transaction)
+            .unwrap()
+            .expect("cannot commit transaction");
         assert!(matches!(
             db.get([TEST_LEAF].as_ref(), b"key1", None).unwrap(),
             Err(Error::PathKeyNotFound(_))
@@ -840,7 +1069,7 @@ mod tests {
         let element = Element::new_item(b"value".to_vec());
         let db = make_test_grovedb();
 
-        let transaction = db.start_transaction();
+        let transaction = db.start_transaction().unwrap();
 
         // Insert some nested subtrees
         db.insert(
@@ -901,7 +1130,7 @@ mod tests {
         //                   |
         // Level 3:          A: value
 
-        let transaction = db.start_transaction();
+        let transaction = db.start_transaction().unwrap();
 
         let deleted = db
             .delete_if_empty_tree([TEST_LEAF].as_ref(), b"level1-A", Some(&transaction))
@@ -1057,7 +1286,7 @@ mod tests {
         let element = Element::new_item(b"ayy".to_vec());
 
         let db = make_test_grovedb();
-        let transaction = db.start_transaction();
+        let transaction = db.start_transaction().unwrap();
 
         // Insert some nested subtrees
         db.insert(
@@ -1120,7 +1349,9 @@ mod tests {
             .unwrap(),
             Err(Error::PathParentLayerNotFound(_))
         ));
-        transaction.commit().expect("cannot commit transaction");
+        db.commit_transaction(transaction)
+            .unwrap()
+            .expect("cannot commit transaction");
         assert!(matches!(
             db.get([TEST_LEAF].as_ref(), b"key1", None).unwrap(),
             Err(Error::PathKeyNotFound(_))
@@ -1222,10 +1453,251 @@ mod tests {
         assert_ne!(root_hash, db.root_hash(None).unwrap().unwrap());
     }
 
+    /// Inserts an item at `[TEST_LEAF, b"tree"]` `b"key"`, a reference to it at
+    /// `[ANOTHER_TEST_LEAF]` `b"ref"` and a reference to that reference at
+    /// `[ANOTHER_TEST_LEAF]` `b"ref_of_ref"`
+    fn make_grovedb_with_referenced_item() -> TempGroveDb {
+        let db = make_test_grovedb();
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"tree",
+            Element::empty_tree(),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful tree insert");
+        db.insert(
+            [TEST_LEAF, b"tree"].as_ref(),
+            b"key",
+            Element::new_item(b"ayy".to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful item insert");
+        db.insert(
+            [ANOTHER_TEST_LEAF].as_ref(),
+            b"ref",
+            Element::new_reference(ReferencePathType::AbsolutePathReference(vec![
+                TEST_LEAF.to_vec(),
+                b"tree".to_vec(),
+                b"key".to_vec(),
+            ])),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful reference insert");
+        // Backfills the backlink of the first reference, the second one gets
+        // its backlink when inserted
+        db.enable_referrer_index()
+            .unwrap()
+            .expect("successful referrer index enabling");
+        db.insert(
+            [ANOTHER_TEST_LEAF].as_ref(),
+            b"ref_of_ref",
+            Element::new_reference(ReferencePathType::SiblingReference(b"ref".to_vec())),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful reference insert");
+        db
+    }
+
+    #[test]
+    fn test_delete_handling_referrers_requires_referrer_index() {
+        let db = make_test_grovedb();
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"key",
+            Element::new_item(b"ayy".to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful item insert");
+
+        let result = db
+            .delete(
+                [TEST_LEAF].as_ref(),
+                b"key",
+                Some(DeleteOptions {
+                    referrer_handling: ReferrerHandling::Fail,
+                    ..Default::default()
+                }),
+                None,
+            )
+            .unwrap();
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+        assert!(db.get([TEST_LEAF].as_ref(), b"key", None).unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_delete_fails_with_dangling_referrers() {
+        let db = make_grovedb_with_referenced_item();
+
+        let result = db
+            .delete(
+                [TEST_LEAF, b"tree"].as_ref(),
+                b"key",
+                Some(DeleteOptions {
+                    referrer_handling: ReferrerHandling::Fail,
+                    ..Default::default()
+                }),
+                None,
+            )
+            .unwrap();
+        match result {
+            Err(Error::DanglingReferences(referrers)) => assert_eq!(
+                referrers,
+                vec![(vec![ANOTHER_TEST_LEAF.to_vec()], b"ref".to_vec())]
+            ),
+            _ => panic!("expected dangling references, got {result:?}"),
+        }
+        assert!(db
+            .get([TEST_LEAF, b"tree"].as_ref(), b"key", None)
+            .unwrap()
+            .is_ok());
+
+        // Deleting an unreferenced element succeeds
+        db.delete(
+            [ANOTHER_TEST_LEAF].as_ref(),
+            b"ref_of_ref",
+            Some(DeleteOptions {
+                referrer_handling: ReferrerHandling::Fail,
+                ..Default::default()
+            }),
+            None,
+        )
+        .unwrap()
+        .expect("expected to delete the unreferenced reference");
+    }
+
+    #[test]
+    fn test_delete_finds_renamed_referrers() {
+        let db = make_grovedb_with_referenced_item();
+        db.rename_key([ANOTHER_TEST_LEAF].as_ref(), b"ref", b"renamed", None)
+            .unwrap()
+            .expect("successful reference rename");
+
+        // The backlink of the reference moved with it
+        let result = db
+            .delete(
+                [TEST_LEAF, b"tree"].as_ref(),
+                b"key",
+                Some(DeleteOptions {
+                    referrer_handling: ReferrerHandling::Fail,
+                    ..Default::default()
+                }),
+                None,
+            )
+            .unwrap();
+        match result {
+            Err(Error::DanglingReferences(referrers)) => assert_eq!(
+                referrers,
+                vec![(vec![ANOTHER_TEST_LEAF.to_vec()], b"renamed".to_vec())]
+            ),
+            _ => panic!("expected dangling references, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_delete_cascades_to_referrers() {
+        let db = make_grovedb_with_referenced_item();
+
+        // References into a deleted subtree are deleted too, and so are the
+        // references to them
+        db.delete(
+            [TEST_LEAF].as_ref(),
+            b"tree",
+            Some(DeleteOptions {
+                allow_deleting_non_empty_trees: true,
+                deleting_non_empty_trees_returns_error: false,
+                referrer_handling: ReferrerHandling::Cascade,
+                ..Default::default()
+            }),
+            None,
+        )
+        .unwrap()
+        .expect("expected to delete the tree and its referrers");
+        for key in [b"ref".as_slice(), b"ref_of_ref"] {
+            assert!(matches!(
+                db.get_raw([ANOTHER_TEST_LEAF].as_ref().into(), key, None)
+                    .unwrap(),
+                Err(Error::PathKeyNotFound(_))
+            ));
+        }
+        assert!(matches!(
+            db.get([TEST_LEAF].as_ref(), b"tree", None).unwrap(),
+            Err(Error::PathKeyNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_batch_delete_handles_referrers() {
+        let db = make_grovedb_with_referenced_item();
+        let ops = vec![GroveDbOp::delete_op(
+            vec![TEST_LEAF.to_vec(), b"tree".to_vec()],
+            b"key".to_vec(),
+        )];
+
+        let result = db
+            .apply_batch(
+                ops.clone(),
+                Some(BatchApplyOptions {
+                    referrer_handling: ReferrerHandling::Fail,
+                    ..Default::default()
+                }),
+                None,
+            )
+            .unwrap();
+        assert!(matches!(result, Err(Error::DanglingReferences(_))));
+
+        // A reference replaced in the same batch doesn't dangle
+        let mut replacing_ops = ops.clone();
+        replacing_ops.push(GroveDbOp::insert_op(
+            vec![ANOTHER_TEST_LEAF.to_vec()],
+            b"ref".to_vec(),
+            Element::new_item(b"replacement".to_vec()),
+        ));
+        let transaction = db.start_transaction().unwrap();
+        db.apply_batch(
+            replacing_ops,
+            Some(BatchApplyOptions {
+                referrer_handling: ReferrerHandling::Fail,
+                ..Default::default()
+            }),
+            Some(&transaction),
+        )
+        .unwrap()
+        .expect("expected the replaced reference not to dangle");
+        drop(transaction);
+
+        db.apply_batch(
+            ops,
+            Some(BatchApplyOptions {
+                referrer_handling: ReferrerHandling::Cascade,
+                ..Default::default()
+            }),
+            None,
+        )
+        .unwrap()
+        .expect("expected to delete the item and its referrers");
+        for key in [b"ref".as_slice(), b"ref_of_ref"] {
+            assert!(matches!(
+                db.get_raw([ANOTHER_TEST_LEAF].as_ref().into(), key, None)
+                    .unwrap(),
+                Err(Error::PathKeyNotFound(_))
+            ));
+        }
+    }
+
     #[test]
     fn test_delete_one_item_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let insertion_cost = db
             .insert(
@@ -1293,7 +1765,7 @@ mod tests {
     #[test]
     fn test_delete_one_sum_item_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(
             EMPTY_PATH,
@@ -1370,7 +1842,7 @@ mod tests {
     #[test]
     fn test_delete_one_item_in_sum_tree_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(
             EMPTY_PATH,
@@ -1444,4 +1916,150 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_clear_subtree() {
+        let populate = |db: &TempGroveDb, with_items: bool| {
+            db.insert(
+                [TEST_LEAF].as_ref(),
+                b"tree",
+                Element::empty_sum_tree(),
+                None,
+                None,
+            )
+            .unwrap()
+            .expect("successful insert");
+            if with_items {
+                for (key, value) in [(b"a", 3), (b"b", 4), (b"c", 5)] {
+                    db.insert(
+                        [TEST_LEAF, b"tree"].as_ref(),
+                        key,
+                        Element::new_sum_item(value),
+                        None,
+                        None,
+                    )
+                    .unwrap()
+                    .expect("successful insert");
+                }
+            }
+        };
+        let db = make_test_grovedb();
+        populate(&db, true);
+        let expected_db = make_test_grovedb();
+        populate(&expected_db, false);
+
+        let cleared = db
+            .clear_subtree([TEST_LEAF, b"tree"].as_ref(), None, None)
+            .unwrap()
+            .expect("successful clear");
+        assert!(cleared);
+
+        assert!(matches!(
+            db.get([TEST_LEAF, b"tree"].as_ref(), b"a", None).unwrap(),
+            Err(Error::PathKeyNotFound(_))
+        ));
+        assert_eq!(
+            db.get([TEST_LEAF].as_ref(), b"tree", None)
+                .unwrap()
+                .expect("successful get"),
+            Element::empty_sum_tree()
+        );
+        assert_eq!(
+            db.root_hash(None).unwrap().unwrap(),
+            expected_db.root_hash(None).unwrap().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_clear_subtree_with_subtrees() {
+        let db = make_test_grovedb();
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"tree",
+            Element::empty_tree(),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+        db.insert(
+            [TEST_LEAF, b"tree"].as_ref(),
+            b"inner",
+            Element::empty_tree(),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+        db.insert(
+            [TEST_LEAF, b"tree", b"inner"].as_ref(),
+            b"item",
+            Element::new_item(b"value".to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+
+        assert!(matches!(
+            db.clear_subtree([TEST_LEAF, b"tree"].as_ref(), None, None)
+                .unwrap(),
+            Err(Error::ClearingTreeWithSubtreesNotAllowed(_))
+        ));
+        let cleared = db
+            .clear_subtree(
+                [TEST_LEAF, b"tree"].as_ref(),
+                Some(ClearOptions {
+                    trying_to_clear_with_subtrees_returns_error: false,
+                    ..Default::default()
+                }),
+                None,
+            )
+            .unwrap()
+            .expect("expected no error");
+        assert!(!cleared);
+
+        let transaction = db.start_transaction().unwrap();
+        let cleared = db
+            .clear_subtree(
+                [TEST_LEAF, b"tree"].as_ref(),
+                Some(ClearOptions {
+                    allow_deleting_subtrees: true,
+                    ..Default::default()
+                }),
+                Some(&transaction),
+            )
+            .unwrap()
+            .expect("successful clear");
+        assert!(cleared);
+        db.commit_transaction(transaction)
+            .unwrap()
+            .expect("successful commit");
+
+        assert!(db
+            .get([TEST_LEAF, b"tree", b"inner"].as_ref(), b"item", None)
+            .unwrap()
+            .is_err());
+        assert!(matches!(
+            db.get([TEST_LEAF, b"tree"].as_ref(), b"inner", None)
+                .unwrap(),
+            Err(Error::PathKeyNotFound(_))
+        ));
+
+        let expected_db = make_test_grovedb();
+        expected_db
+            .insert(
+                [TEST_LEAF].as_ref(),
+                b"tree",
+                Element::empty_tree(),
+                None,
+                None,
+            )
+            .unwrap()
+            .expect("successful insert");
+        assert_eq!(
+            db.root_hash(None).unwrap().unwrap(),
+            expected_db.root_hash(None).unwrap().unwrap()
+        );
+    }
 }
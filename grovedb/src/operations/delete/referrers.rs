@@ -0,0 +1,229 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Referrers of deleted elements
+//!
+//! Deleting an element can silently leave the references pointing at it
+//! dangling. When asked to, deletions look up the references pointing at the
+//! deleted elements, or inside deleted subtrees, through the backlinks of the
+//! referrer index, and either fail or delete them too.
+
+#[cfg(feature = "full")]
+use std::collections::BTreeSet;
+
+#[cfg(feature = "full")]
+use grovedb_costs::{
+    cost_return_on_error, cost_return_on_error_no_add, CostResult, CostsExt, OperationCost,
+};
+
+#[cfg(feature = "full")]
+use grovedb_path::SubtreePath;
+
+#[cfg(feature = "full")]
+use crate::{
+    batch::{GroveDbOp, Op},
+    reference_path::path_from_reference_path_type,
+    Element, Error, GroveDb, TransactionArg,
+};
+
+#[cfg(feature = "full")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// What to do with the references pointing at deleted elements
+pub enum ReferrerHandling {
+    /// Leave the references dangling
+    #[default]
+    Ignore,
+    /// Fail with `Error::DanglingReferences` listing the references
+    Fail,
+    /// Delete the references too, and the references pointing at them
+    Cascade,
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Returns the path and key of the references pointing at the elements at
+    /// the qualified paths of `deleted`, or into the subtrees they hold.
+    /// References at the qualified paths of `skipped` are left out, and so
+    /// are references that are deleted themselves. With
+    /// `ReferrerHandling::Cascade` the references pointing at the returned
+    /// references are returned too. Requires the referrer index unless
+    /// referrers are ignored.
+    pub(crate) fn referrers_of_deleted(
+        &self,
+        deleted: &[Vec<Vec<u8>>],
+        skipped: &BTreeSet<Vec<Vec<u8>>>,
+        handling: ReferrerHandling,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<(Vec<Vec<u8>>, Vec<u8>)>, Error> {
+        let mut cost = OperationCost::default();
+
+        if handling == ReferrerHandling::Ignore || deleted.is_empty() {
+            return Ok(Vec::new()).wrap_with_cost(cost);
+        }
+        if !self.is_referrer_index_enabled() {
+            return Err(Error::InvalidInput(
+                "handling the referrers of deleted elements requires the referrer index",
+            ))
+            .wrap_with_cost(cost);
+        }
+
+        let mut deleted = deleted.to_vec();
+        let mut pending = deleted.clone();
+        let mut referrers = Vec::new();
+        let mut checked = BTreeSet::new();
+        while !pending.is_empty() {
+            // Backlinks may be stale, each candidate is checked to be a
+            // reference pointing at a deleted element
+            let mut candidates = Vec::new();
+            for qualified_path in pending.drain(..) {
+                let Some((key, path)) = qualified_path.split_last() else {
+                    continue;
+                };
+                candidates.extend(cost_return_on_error!(
+                    &mut cost,
+                    self.referrers_of(path, Some(key), transaction)
+                ));
+                let is_subtree = cost_return_on_error!(
+                    &mut cost,
+                    self.check_subtree_exists(qualified_path.as_slice(), transaction)
+                );
+                if is_subtree {
+                    let subtree_path: SubtreePath<Vec<u8>> = qualified_path.as_slice().into();
+                    let subtrees_paths = cost_return_on_error!(
+                        &mut cost,
+                        self.find_subtrees(&subtree_path, transaction)
+                    );
+                    for subtree_path in subtrees_paths {
+                        candidates.extend(cost_return_on_error!(
+                            &mut cost,
+                            self.referrers_of(&subtree_path, None, transaction)
+                        ));
+                    }
+                }
+            }
+
+            let is_deleted = |qualified_path: &Vec<Vec<u8>>| {
+                deleted
+                    .iter()
+                    .any(|deleted_path| qualified_path.starts_with(deleted_path))
+            };
+            let mut new_referrers = Vec::new();
+            for candidate in candidates {
+                if skipped.contains(&candidate)
+                    || is_deleted(&candidate)
+                    || !checked.insert(candidate.clone())
+                {
+                    continue;
+                }
+                let Some((key, path)) = candidate.split_last() else {
+                    continue;
+                };
+                let element = match self
+                    .get_raw(path.into(), key, transaction)
+                    .unwrap_add_cost(&mut cost)
+                {
+                    Ok(element) => element,
+                    Err(Error::PathKeyNotFound(_))
+                    | Err(Error::PathNotFound(_))
+                    | Err(Error::PathParentLayerNotFound(_)) => continue,
+                    Err(e) => return Err(e).wrap_with_cost(cost),
+                };
+                if let Element::Reference(reference_path, ..)
+                | Element::BidirectionalReference(reference_path, ..) = element
+                {
+                    let target = cost_return_on_error_no_add!(
+                        &cost,
+                        path_from_reference_path_type(reference_path, path, Some(key))
+                    );
+                    if is_deleted(&target) {
+                        new_referrers.push(candidate);
+                    }
+                }
+            }
+
+            deleted.extend(new_referrers.iter().cloned());
+            if handling == ReferrerHandling::Cascade {
+                pending.extend(new_referrers.iter().cloned());
+            }
+            referrers.extend(new_referrers);
+        }
+
+        let referrers = referrers
+            .into_iter()
+            .map(|mut path| {
+                let key = path.pop().expect("qualified paths hold the key");
+                (path, key)
+            })
+            .collect();
+        Ok(referrers).wrap_with_cost(cost)
+    }
+
+    /// Applies `handling` to the references pointing at the elements deleted
+    /// by `ops`, returns the ops with the deletions of the references when
+    /// cascading
+    pub(crate) fn handle_referrers_of_batch(
+        &self,
+        mut ops: Vec<GroveDbOp>,
+        handling: ReferrerHandling,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<GroveDbOp>, Error> {
+        let mut cost = OperationCost::default();
+
+        let qualified_path = |op: &GroveDbOp| {
+            let mut qualified_path = op.path.to_path();
+            qualified_path.push(op.key.get_key_clone());
+            qualified_path
+        };
+        let deleted: Vec<Vec<Vec<u8>>> = ops
+            .iter()
+            .filter(|op| matches!(op.op, Op::Delete | Op::DeleteTree | Op::DeleteSumTree))
+            .map(qualified_path)
+            .collect();
+        // References written by the batch are replaced, they don't dangle
+        let written: BTreeSet<Vec<Vec<u8>>> = ops.iter().map(qualified_path).collect();
+
+        let referrers = cost_return_on_error!(
+            &mut cost,
+            self.referrers_of_deleted(&deleted, &written, handling, transaction)
+        );
+        if referrers.is_empty() {
+            return Ok(ops).wrap_with_cost(cost);
+        }
+        match handling {
+            ReferrerHandling::Cascade => {
+                ops.extend(
+                    referrers
+                        .into_iter()
+                        .map(|(path, key)| GroveDbOp::delete_op(path, key)),
+                );
+                Ok(ops).wrap_with_cost(cost)
+            }
+            _ => Err(Error::DanglingReferences(referrers)).wrap_with_cost(cost),
+        }
+    }
+}
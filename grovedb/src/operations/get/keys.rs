@@ -0,0 +1,140 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Keys only query operations
+
+use grovedb_costs::{cost_return_on_error, CostResult, CostsExt, OperationCost};
+use grovedb_merk::KVIterator;
+use grovedb_path::SubtreePath;
+use grovedb_storage::StorageContext;
+
+use crate::{util::merk_optional_tx, Element, Error, GroveDb, PathQuery, Query, TransactionArg};
+
+impl GroveDb {
+    /// Returns the keys of the subtree at `path` matched by `query`, walking
+    /// the keys without decoding the elements
+    pub fn query_keys<'b, B, P>(
+        &self,
+        path: P,
+        query: &Query,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<Vec<u8>>, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        self.query_keys_with_limit_offset(path.into(), query, None, None, transaction)
+    }
+
+    /// Returns the keys matched by a path query without subqueries, walking
+    /// the keys without decoding the elements
+    pub fn query_path_keys(
+        &self,
+        path_query: &PathQuery,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<Vec<u8>>, Error> {
+        if path_query.query.query.has_subquery() {
+            return Err(Error::NotSupported(
+                "keys only path queries can't have subqueries",
+            ))
+            .wrap_with_cost(OperationCost::default());
+        }
+        let path_slices = path_query
+            .path
+            .iter()
+            .map(|segment| segment.as_slice())
+            .collect::<Vec<_>>();
+        self.query_keys_with_limit_offset(
+            path_slices.as_slice().into(),
+            &path_query.query.query,
+            path_query.query.limit,
+            path_query.query.offset,
+            transaction,
+        )
+    }
+
+    fn query_keys_with_limit_offset<B: AsRef<[u8]>>(
+        &self,
+        path: SubtreePath<B>,
+        query: &Query,
+        limit: Option<u16>,
+        offset: Option<u16>,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<Vec<u8>>, Error> {
+        let mut cost = OperationCost::default();
+
+        cost_return_on_error!(
+            &mut cost,
+            self.check_subtree_exists_path_not_found(path.clone(), transaction)
+        );
+        merk_optional_tx!(&mut cost, self.db, path, None, transaction, subtree, {
+            let mut iterator =
+                KVIterator::new(subtree.storage.raw_iter(), query).unwrap_add_cost(&mut cost);
+            let mut offset = offset.unwrap_or(0);
+            let mut keys = Vec::new();
+            while limit.map_or(true, |limit| keys.len() < limit as usize) {
+                match iterator.next_key().unwrap_add_cost(&mut cost) {
+                    Some(_) if offset > 0 => offset -= 1,
+                    Some(key) => keys.push(key),
+                    None => break,
+                }
+            }
+            Ok(keys).wrap_with_cost(cost)
+        })
+    }
+
+    /// Counts the elements of the subtree at `path` matched by `query`,
+    /// walking the keys without decoding the elements
+    pub fn count_keys<'b, B, P>(
+        &self,
+        path: P,
+        query: &Query,
+        transaction: TransactionArg,
+    ) -> CostResult<u64, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        cost_return_on_error!(
+            &mut cost,
+            self.check_subtree_exists_path_not_found(path.clone(), transaction)
+        );
+        merk_optional_tx!(&mut cost, self.db, path, None, transaction, subtree, {
+            let mut iterator =
+                KVIterator::new(subtree.storage.raw_iter(), query).unwrap_add_cost(&mut cost);
+            let mut count = 0;
+            while iterator.next_key().unwrap_add_cost(&mut cost).is_some() {
+                count += 1;
+            }
+            Ok(count).wrap_with_cost(cost)
+        })
+    }
+}
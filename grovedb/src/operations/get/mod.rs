@@ -31,10 +31,16 @@
 #[cfg(feature = "estimated_costs")]
 mod average_case;
 #[cfg(feature = "full")]
+mod keys;
+#[cfg(feature = "full")]
 mod query;
+#[cfg(feature = "async")]
+mod query_stream;
 #[cfg(feature = "estimated_costs")]
 mod worst_case;
 
+#[cfg(feature = "async")]
+pub use query_stream::QueryStream;
 #[cfg(feature = "full")]
 use std::collections::HashSet;
 
@@ -47,8 +53,10 @@ use grovedb_storage::StorageContext;
 
 #[cfg(feature = "full")]
 use crate::{
+    element::ElementGetExt,
+    metrics::OperationKind,
     reference_path::{path_from_reference_path_type, path_from_reference_qualified_path_type},
-    util::storage_context_optional_tx,
+    util::{merk_optional_tx, storage_context_optional_tx},
     Element, Error, GroveDb, Transaction, TransactionArg,
 };
 
@@ -89,7 +97,8 @@ impl GroveDb {
             &mut cost,
             self.get_raw_caching_optional(path.clone(), key, allow_cache, transaction)
         ) {
-            Element::Reference(reference_path, ..) => {
+            Element::Reference(reference_path, ..)
+            | Element::BidirectionalReference(reference_path, ..) => {
                 let path_owned = cost_return_on_error!(
                     &mut cost,
                     path_from_reference_path_type(reference_path, &path.to_vec(), Some(key))
@@ -102,6 +111,56 @@ impl GroveDb {
         }
     }
 
+    /// Get the elements under `keys` in the subtree at `path`, in the order
+    /// of the keys with `None` for the keys not found. The subtree is opened
+    /// once and the elements are read with a single storage lookup, instead of
+    /// once per key; references are followed as by `get`.
+    pub fn get_many<'b, B, P, K>(
+        &self,
+        path: P,
+        keys: &[K],
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<Option<Element>>, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+        K: AsRef<[u8]>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        let elements = cost_return_on_error!(
+            &mut cost,
+            self.get_many_raw(path.clone(), keys, transaction)
+        );
+        let mut followed_elements = Vec::with_capacity(elements.len());
+        for (element, key) in elements.into_iter().zip(keys) {
+            let followed_element = match element {
+                Some(
+                    Element::Reference(reference_path, ..)
+                    | Element::BidirectionalReference(reference_path, ..),
+                ) => {
+                    let path_owned = cost_return_on_error!(
+                        &mut cost,
+                        path_from_reference_path_type(
+                            reference_path,
+                            &path.to_vec(),
+                            Some(key.as_ref())
+                        )
+                        .wrap_with_cost(OperationCost::default())
+                    );
+                    Some(cost_return_on_error!(
+                        &mut cost,
+                        self.follow_reference(path_owned.as_slice().into(), true, transaction)
+                    ))
+                }
+                other => other,
+            };
+            followed_elements.push(followed_element);
+        }
+        Ok(followed_elements).wrap_with_cost(cost)
+    }
+
     /// Return the Element that a reference points to.
     /// If the reference points to another reference, keep following until
     /// base element is reached.
@@ -145,7 +204,8 @@ impl GroveDb {
             }
             visited.insert(current_path.clone());
             match current_element {
-                Element::Reference(reference_path, ..) => {
+                Element::Reference(reference_path, ..)
+                | Element::BidirectionalReference(reference_path, ..) => {
                     current_path = cost_return_on_error!(
                         &mut cost,
                         path_from_reference_qualified_path_type(reference_path, &current_path)
@@ -178,6 +238,7 @@ impl GroveDb {
         allow_cache: bool,
         transaction: TransactionArg,
     ) -> CostResult<Element, Error> {
+        self.metrics.record(OperationKind::Read, &path, Some(key));
         if let Some(transaction) = transaction {
             self.get_raw_on_transaction_caching_optional(path, key, allow_cache, transaction)
         } else {
@@ -205,6 +266,7 @@ impl GroveDb {
         allow_cache: bool,
         transaction: TransactionArg,
     ) -> CostResult<Option<Element>, Error> {
+        self.metrics.record(OperationKind::Read, &path, Some(key));
         if let Some(transaction) = transaction {
             self.get_raw_optional_on_transaction_caching_optional(
                 path,
@@ -217,6 +279,30 @@ impl GroveDb {
         }
     }
 
+    /// Get the elements under `keys` in the subtree at `path`, in the order
+    /// of the keys with `None` for the keys not found, without following
+    /// references
+    pub fn get_many_raw<B: AsRef<[u8]>, K: AsRef<[u8]>>(
+        &self,
+        path: SubtreePath<B>,
+        keys: &[K],
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<Option<Element>>, Error> {
+        let mut cost = OperationCost::default();
+        for key in keys {
+            self.metrics
+                .record(OperationKind::Read, &path, Some(key.as_ref()));
+        }
+
+        cost_return_on_error!(
+            &mut cost,
+            self.check_subtree_exists_path_not_found(path.clone(), transaction)
+        );
+        merk_optional_tx!(&mut cost, self.db, path, None, transaction, subtree, {
+            Element::get_many(&subtree, keys).add_cost(cost)
+        })
+    }
+
     /// Get tree item without following references
     pub(crate) fn get_raw_on_transaction_caching_optional<B: AsRef<[u8]>>(
         &self,
@@ -348,7 +434,41 @@ impl GroveDb {
         })
     }
 
-    fn check_subtree_exists<B: AsRef<[u8]>>(
+    /// Checks that every segment of the path resolves to a tree element.
+    /// Elements are read straight from their parent's storage, so no Merk is
+    /// opened and only the small tree elements along the path are loaded.
+    pub fn check_subtree_exists<'b, B, P>(
+        &self,
+        path: P,
+        transaction: TransactionArg,
+    ) -> CostResult<bool, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+
+        let path_vec = path.into().to_vec();
+        for (depth, key) in path_vec.iter().enumerate() {
+            let parent_path: SubtreePath<_> = path_vec[..depth].into();
+            let element =
+                storage_context_optional_tx!(self.db, parent_path, None, transaction, storage, {
+                    Element::get_optional_from_storage(&storage.unwrap_add_cost(&mut cost), key)
+                        .unwrap_add_cost(&mut cost)
+                });
+            match cost_return_on_error_no_add!(&cost, element) {
+                Some(Element::Tree(..))
+                | Some(Element::SumTree(..))
+                | Some(Element::CountTree(..))
+                | Some(Element::BigSumTree(..))
+                | Some(Element::AggregateTree(..)) => {}
+                _ => return Ok(false).wrap_with_cost(cost),
+            }
+        }
+        Ok(true).wrap_with_cost(cost)
+    }
+
+    fn check_subtree_exists_or_else<B: AsRef<[u8]>>(
         &self,
         path: SubtreePath<B>,
         transaction: TransactionArg,
@@ -374,7 +494,11 @@ impl GroveDb {
             }
             .unwrap_add_cost(&mut cost);
             match element {
-                Ok(Element::Tree(..)) | Ok(Element::SumTree(..)) => Ok(()).wrap_with_cost(cost),
+                Ok(Element::Tree(..))
+                | Ok(Element::SumTree(..))
+                | Ok(Element::CountTree(..))
+                | Ok(Element::BigSumTree(..))
+                | Ok(Element::AggregateTree(..)) => Ok(()).wrap_with_cost(cost),
                 Ok(_) | Err(Error::PathKeyNotFound(_)) => Err(error_fn()).wrap_with_cost(cost),
                 Err(e) => Err(e).wrap_with_cost(cost),
             }
@@ -392,7 +516,7 @@ impl GroveDb {
     where
         B: AsRef<[u8]> + 'b,
     {
-        self.check_subtree_exists(path.clone(), transaction, || {
+        self.check_subtree_exists_or_else(path.clone(), transaction, || {
             Error::PathNotFound(format!(
                 "subtree doesn't exist at path {:?}",
                 path.to_vec()
@@ -409,7 +533,7 @@ impl GroveDb {
         path: SubtreePath<B>,
         transaction: TransactionArg,
     ) -> CostResult<(), Error> {
-        self.check_subtree_exists(path, transaction, || {
+        self.check_subtree_exists_or_else(path, transaction, || {
             Error::InvalidPath("subtree doesn't exist".to_owned())
         })
     }
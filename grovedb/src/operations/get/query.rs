@@ -28,20 +28,29 @@
 
 //! Query operations
 
+#[cfg(feature = "full")]
+use std::collections::BTreeMap;
+
 use grovedb_costs::cost_return_on_error_default;
 #[cfg(feature = "full")]
 use grovedb_costs::{
     cost_return_on_error, cost_return_on_error_no_add, CostResult, CostsExt, OperationCost,
 };
 #[cfg(feature = "full")]
+use grovedb_storage::RawIteratorOptions;
+#[cfg(feature = "full")]
 use integer_encoding::VarInt;
 
 use crate::query_result_type::PathKeyOptionalElementTrio;
+#[cfg(feature = "parallel")]
+use crate::Transaction;
 #[cfg(feature = "full")]
 use crate::{
+    element::{check_keys_only, ElementQueryExt},
+    metrics::OperationKind,
     query_result_type::{QueryResultElement, QueryResultElements, QueryResultType},
     reference_path::ReferencePathType,
-    Element, Error, GroveDb, PathQuery, TransactionArg,
+    Element, Error, GroveDb, PathQuery, Query, TransactionArg,
 };
 
 #[cfg(feature = "full")]
@@ -67,7 +76,10 @@ impl GroveDb {
         let results_wrapped = elements
             .into_iterator()
             .map(|result_item| match result_item {
-                QueryResultElement::ElementResultItem(Element::Reference(reference_path, ..)) => {
+                QueryResultElement::ElementResultItem(
+                    Element::Reference(reference_path, ..)
+                    | Element::BidirectionalReference(reference_path, ..),
+                ) => {
                     match reference_path {
                         ReferencePathType::AbsolutePathReference(absolute_path) => {
                             // While `map` on iterator is lazy, we should accumulate costs even if
@@ -123,6 +135,222 @@ where {
         Ok(result).wrap_with_cost(cost)
     }
 
+    /// Runs several path queries together and returns the results of each
+    /// one, in the order of `path_queries`, with a single combined cost.
+    ///
+    /// Queries on the same subtree that have no subqueries, limit or offset
+    /// are merged and run as one query, so the subtree is opened and its
+    /// prefix computed once for the whole group; the merged results are then
+    /// split back per query. A query with subqueries and several top level
+    /// items, without limit or offset, is split into one unit per item, which
+    /// spans its own subtrees. Other queries are run on their own.
+    ///
+    /// Units are run in the order of the queries they come from and running
+    /// stops at the first failing one, whose error is returned.
+    ///
+    /// With the `parallel` feature and no transaction, the units are run on a
+    /// bounded number of worker threads, each reading from its own snapshot
+    /// transaction, all of them pinned to the same state. Results and costs
+    /// are still combined in the order of the units, so neither depends on
+    /// the scheduling or on the feature.
+    pub fn query_many(
+        &self,
+        path_queries: &[PathQuery],
+        allow_cache: bool,
+        result_type: QueryResultType,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<QueryResultElements>, Error> {
+        let mut cost = OperationCost::default();
+
+        for path_query in path_queries {
+            cost_return_on_error_no_add!(&cost, check_keys_only(path_query, result_type));
+        }
+
+        let mut units: Vec<(Vec<usize>, Option<PathQuery>)> = Vec::new();
+        let mut groups: BTreeMap<(&[Vec<u8>], bool), usize> = BTreeMap::new();
+        for (i, path_query) in path_queries.iter().enumerate() {
+            let sized_query = &path_query.query;
+            let unlimited = sized_query.limit.is_none() && sized_query.offset.is_none();
+            // merged queries don't look up value hashes
+            if unlimited
+                && !sized_query.query.has_subquery()
+                && !matches!(
+                    result_type,
+                    QueryResultType::QueryPathKeyValueHashResultType
+                )
+            {
+                let group = (path_query.path.as_slice(), sized_query.query.left_to_right);
+                match groups.get(&group) {
+                    Some(&unit) => units[unit].0.push(i),
+                    None => {
+                        groups.insert(group, units.len());
+                        units.push((vec![i], None));
+                    }
+                }
+            } else if unlimited
+                && sized_query.query.has_subquery()
+                && sized_query.query.items.len() > 1
+            {
+                // the results of the units of a query, in the order the
+                // query visits its items, are the results of the query
+                let items = &sized_query.query.items;
+                let ordered_items: Vec<_> = if sized_query.query.left_to_right {
+                    items.iter().collect()
+                } else {
+                    items.iter().rev().collect()
+                };
+                for item in ordered_items {
+                    let mut query = sized_query.query.clone();
+                    query.items = vec![item.clone()];
+                    units.push((
+                        vec![i],
+                        Some(PathQuery::new_unsized(path_query.path.clone(), query)),
+                    ));
+                }
+            } else {
+                units.push((vec![i], None));
+            }
+        }
+
+        let run_unit = |unit: usize, transaction: TransactionArg| {
+            let (indexes, split_query) = &units[unit];
+            self.query_many_unit(
+                path_queries,
+                indexes,
+                split_query.as_ref(),
+                allow_cache,
+                result_type,
+                transaction,
+            )
+        };
+        #[cfg(feature = "parallel")]
+        let unit_results = if transaction.is_none() {
+            let worker_count =
+                std::thread::available_parallelism().map_or(1, |parallelism| parallelism.get());
+            cost_return_on_error_no_add!(
+                &cost,
+                self.run_on_workers(worker_count, units.len(), run_unit)
+            )
+        } else {
+            run_in_order(units.len(), |unit| run_unit(unit, transaction))
+        };
+        #[cfg(not(feature = "parallel"))]
+        let unit_results = run_in_order(units.len(), |unit| run_unit(unit, transaction));
+
+        let mut results: Vec<Vec<QueryResultElement>> =
+            path_queries.iter().map(|_| Vec::new()).collect();
+        for ((indexes, split_query), unit_result) in units.iter().zip(unit_results) {
+            // units are claimed in order and stop being run after the first
+            // failing one, so every unit before it has a result
+            let unit_result = cost_return_on_error_no_add!(
+                &cost,
+                unit_result.ok_or(Error::CorruptedCodeExecution(
+                    "a query unit before the failing one wasn't run",
+                ))
+            );
+            let unit_elements = cost_return_on_error!(&mut cost, unit_result);
+            for (&i, elements) in indexes.iter().zip(unit_elements) {
+                if split_query.is_some() {
+                    results[i].extend(elements);
+                } else {
+                    results[i] = elements;
+                }
+            }
+        }
+
+        Ok(results
+            .into_iter()
+            .map(|elements| QueryResultElements { elements })
+            .collect())
+        .wrap_with_cost(cost)
+    }
+
+    /// Runs one unit of `query_many`: either a single query, the part of a
+    /// single query given by `split_query`, or several merged queries on the
+    /// same subtree whose results are split back per query. Returns the
+    /// results of each of the `indexes`, in order.
+    fn query_many_unit(
+        &self,
+        path_queries: &[PathQuery],
+        indexes: &[usize],
+        split_query: Option<&PathQuery>,
+        allow_cache: bool,
+        result_type: QueryResultType,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<Vec<QueryResultElement>>, Error> {
+        let mut cost = OperationCost::default();
+
+        if let [i] = indexes {
+            return self
+                .query(
+                    split_query.unwrap_or(&path_queries[*i]),
+                    allow_cache,
+                    result_type,
+                    transaction,
+                )
+                .map_ok(|(elements, _)| vec![elements.elements]);
+        }
+
+        let path = &path_queries[indexes[0]].path;
+        let mut query =
+            Query::new_with_direction(path_queries[indexes[0]].query.query.left_to_right);
+        for &i in indexes {
+            query.insert_items(path_queries[i].query.query.items.clone());
+        }
+        let merged = PathQuery::new_unsized(path.clone(), query);
+        let (elements, _) = cost_return_on_error!(
+            &mut cost,
+            self.query(
+                &merged,
+                allow_cache,
+                QueryResultType::QueryKeyElementPairResultType,
+                transaction
+            )
+        );
+
+        let mut results: Vec<Vec<QueryResultElement>> =
+            indexes.iter().map(|_| Vec::new()).collect();
+        for (key, element) in elements.to_key_elements() {
+            for (&i, query_results) in indexes.iter().zip(results.iter_mut()) {
+                let matches = path_queries[i]
+                    .query
+                    .query
+                    .items
+                    .iter()
+                    .any(|item| item.contains(&key));
+                if !matches {
+                    continue;
+                }
+                let result = match result_type {
+                    QueryResultType::QueryElementResultType => {
+                        QueryResultElement::ElementResultItem(element.clone())
+                    }
+                    QueryResultType::QueryKeyElementPairResultType => {
+                        QueryResultElement::KeyElementPairResultItem((key.clone(), element.clone()))
+                    }
+                    QueryResultType::QueryPathKeyElementTrioResultType => {
+                        QueryResultElement::PathKeyElementTrioResultItem((
+                            path.clone(),
+                            key.clone(),
+                            element.clone(),
+                        ))
+                    }
+                    QueryResultType::QueryPathKeyResultType => {
+                        QueryResultElement::PathKeyResultItem((path.clone(), key.clone()))
+                    }
+                    QueryResultType::QueryPathKeyValueHashResultType => {
+                        return Err(Error::CorruptedCodeExecution(
+                            "value hash queries can't be merged",
+                        ))
+                        .wrap_with_cost(cost);
+                    }
+                };
+                query_results.push(result);
+            }
+        }
+        Ok(results).wrap_with_cost(cost)
+    }
+
     /// Prove a path query as either verbose or non verbose
     pub fn get_proved_path_query(
         &self,
@@ -150,7 +378,8 @@ where {
         transaction: TransactionArg,
     ) -> Result<Element, Error> {
         match element {
-            Element::Reference(reference_path, ..) => {
+            Element::Reference(reference_path, ..)
+            | Element::BidirectionalReference(reference_path, ..) => {
                 match reference_path {
                     ReferencePathType::AbsolutePathReference(absolute_path) => {
                         // While `map` on iterator is lazy, we should accumulate costs
@@ -178,7 +407,11 @@ where {
                 }
             }
             Element::Item(..) | Element::SumItem(..) => Ok(element),
-            Element::Tree(..) | Element::SumTree(..) => Err(Error::InvalidQuery(
+            Element::Tree(..)
+            | Element::SumTree(..)
+            | Element::CountTree(..)
+            | Element::BigSumTree(..)
+            | Element::AggregateTree(..) => Err(Error::InvalidQuery(
                 "path_queries can only refer to items and references",
             )),
         }
@@ -191,12 +424,38 @@ where {
         allow_cache: bool,
         result_type: QueryResultType,
         transaction: TransactionArg,
+    ) -> CostResult<(QueryResultElements, u16), Error> {
+        self.query_with_iterator_options(
+            path_query,
+            allow_cache,
+            &RawIteratorOptions::default(),
+            result_type,
+            transaction,
+        )
+    }
+
+    /// Returns the result set after applying a path query, tuning the storage
+    /// iterators used for range scans with `iterator_options`. The upper
+    /// bound only applies to keys of the subtree at the path query's path.
+    pub fn query_with_iterator_options(
+        &self,
+        path_query: &PathQuery,
+        allow_cache: bool,
+        iterator_options: &RawIteratorOptions,
+        result_type: QueryResultType,
+        transaction: TransactionArg,
     ) -> CostResult<(QueryResultElements, u16), Error> {
         let mut cost = OperationCost::default();
 
         let (elements, skipped) = cost_return_on_error!(
             &mut cost,
-            self.query_raw(path_query, allow_cache, result_type, transaction)
+            self.query_raw_with_iterator_options(
+                path_query,
+                allow_cache,
+                iterator_options,
+                result_type,
+                transaction
+            )
         );
 
         let results_wrapped = elements
@@ -237,7 +496,8 @@ where {
             .map(|result_item| match result_item {
                 QueryResultElement::ElementResultItem(element) => {
                     match element {
-                        Element::Reference(reference_path, ..) => {
+                        Element::Reference(reference_path, ..)
+                        | Element::BidirectionalReference(reference_path, ..) => {
                             match reference_path {
                                 ReferencePathType::AbsolutePathReference(absolute_path) => {
                                     // While `map` on iterator is lazy, we should accumulate costs
@@ -268,7 +528,11 @@ where {
                         }
                         Element::Item(item, _) => Ok(item),
                         Element::SumItem(item, _) => Ok(item.encode_var_vec()),
-                        Element::Tree(..) | Element::SumTree(..) => Err(Error::InvalidQuery(
+                        Element::Tree(..)
+                        | Element::SumTree(..)
+                        | Element::CountTree(..)
+                        | Element::BigSumTree(..)
+                        | Element::AggregateTree(..) => Err(Error::InvalidQuery(
                             "path_queries can only refer to items and references",
                         )),
                     }
@@ -307,7 +571,8 @@ where {
             .map(|result_item| match result_item {
                 QueryResultElement::ElementResultItem(element) => {
                     match element {
-                        Element::Reference(reference_path, ..) => {
+                        Element::Reference(reference_path, ..)
+                        | Element::BidirectionalReference(reference_path, ..) => {
                             match reference_path {
                                 ReferencePathType::AbsolutePathReference(absolute_path) => {
                                     // While `map` on iterator is lazy, we should accumulate costs
@@ -337,12 +602,15 @@ where {
                             }
                         }
                         Element::SumItem(item, _) => Ok(item),
-                        Element::Tree(..) | Element::SumTree(..) | Element::Item(..) => {
-                            Err(Error::InvalidQuery(
-                                "path_queries over sum items can only refer to sum items and \
+                        Element::Tree(..)
+                        | Element::SumTree(..)
+                        | Element::CountTree(..)
+                        | Element::BigSumTree(..)
+                        | Element::AggregateTree(..)
+                        | Element::Item(..) => Err(Error::InvalidQuery(
+                            "path_queries over sum items can only refer to sum items and \
                                  references",
-                            ))
-                        }
+                        )),
                     }
                 }
                 _ => Err(Error::CorruptedCodeExecution(
@@ -363,7 +631,39 @@ where {
         result_type: QueryResultType,
         transaction: TransactionArg,
     ) -> CostResult<(QueryResultElements, u16), Error> {
-        Element::get_raw_path_query(&self.db, path_query, allow_cache, result_type, transaction)
+        self.query_raw_with_iterator_options(
+            path_query,
+            allow_cache,
+            &RawIteratorOptions::default(),
+            result_type,
+            transaction,
+        )
+    }
+
+    /// Returns result elements and number of elements skipped given path
+    /// query, tuning the storage iterators used for range scans with
+    /// `iterator_options`. The upper bound only applies to keys of the subtree
+    /// at the path query's path.
+    pub fn query_raw_with_iterator_options(
+        &self,
+        path_query: &PathQuery,
+        allow_cache: bool,
+        iterator_options: &RawIteratorOptions,
+        result_type: QueryResultType,
+        transaction: TransactionArg,
+    ) -> CostResult<(QueryResultElements, u16), Error> {
+        self.metrics.record_segment(
+            OperationKind::Query,
+            path_query.path.first().map(Vec::as_slice),
+        );
+        Element::get_raw_path_query(
+            &self.db,
+            path_query,
+            allow_cache,
+            iterator_options,
+            result_type,
+            transaction,
+        )
     }
 
     /// Splits the result set of a path query by query path.
@@ -454,6 +754,94 @@ where {
     }
 }
 
+/// Runs `f` on the units `0..count` in order, stopping after the first one
+/// which fails. Units that weren't run have no result.
+#[cfg(feature = "full")]
+fn run_in_order<T, F>(count: usize, f: F) -> Vec<Option<CostResult<T, Error>>>
+where
+    F: Fn(usize) -> CostResult<T, Error>,
+{
+    let mut results = Vec::with_capacity(count);
+    for unit in 0..count {
+        let result = f(unit);
+        let failed = result.value.is_err();
+        results.push(Some(result));
+        if failed {
+            break;
+        }
+    }
+    results.resize_with(count, || None);
+    results
+}
+
+#[cfg(feature = "parallel")]
+impl GroveDb {
+    /// Runs `f` on the units `0..count` on at most `worker_count` threads,
+    /// which claim units in order and stop claiming new ones once a unit
+    /// fails. Each worker reads from its own snapshot transaction, all of
+    /// them pinned to the same state; if writes keep them from being pinned
+    /// together, the units are run in order in one snapshot transaction.
+    /// Units that weren't run have no result.
+    fn run_on_workers<T, F>(
+        &self,
+        worker_count: usize,
+        count: usize,
+        f: F,
+    ) -> Result<Vec<Option<CostResult<T, Error>>>, Error>
+    where
+        T: Send,
+        F: Fn(usize, TransactionArg) -> CostResult<T, Error> + Sync,
+    {
+        use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+        let worker_count = worker_count.min(count);
+        if worker_count <= 1 {
+            return Ok(run_in_order(count, |unit| f(unit, None)));
+        }
+        let Some(transactions) = self.db.start_snapshot_transactions(worker_count)? else {
+            let transaction = self.snapshot()?;
+            return Ok(run_in_order(count, |unit| {
+                f(unit, Some(transaction.transaction()))
+            }));
+        };
+
+        let next_unit = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let mut results: Vec<Option<CostResult<T, Error>>> = Vec::with_capacity(count);
+        results.resize_with(count, || None);
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = transactions
+                .into_iter()
+                .map(|transaction| {
+                    let (next_unit, failed, f) = (&next_unit, &failed, &f);
+                    scope.spawn(move || {
+                        let transaction = Transaction::new(transaction);
+                        let mut worker_results = Vec::new();
+                        while !failed.load(Ordering::Acquire) {
+                            let unit = next_unit.fetch_add(1, Ordering::AcqRel);
+                            if unit >= count {
+                                break;
+                            }
+                            let result = f(unit, Some(&transaction));
+                            if result.value.is_err() {
+                                failed.store(true, Ordering::Release);
+                            }
+                            worker_results.push((unit, result));
+                        }
+                        worker_results
+                    })
+                })
+                .collect();
+            for worker in workers {
+                for (unit, result) in worker.join().expect("query worker panicked") {
+                    results[unit] = Some(result);
+                }
+            }
+        });
+        Ok(results)
+    }
+}
+
 #[cfg(feature = "full")]
 #[cfg(test)]
 mod tests {
@@ -1529,4 +1917,40 @@ mod tests {
             None
         ); // because we didn't query for it
     }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_run_on_workers_stops_after_failing_unit() {
+        use grovedb_costs::{CostsExt, OperationCost};
+
+        use crate::Error;
+
+        let db = make_test_grovedb();
+        let results = db
+            .run_on_workers(4, 64, |unit, transaction| {
+                assert!(transaction.is_some());
+                if unit == 10 {
+                    Err(Error::InternalError("failing unit"))
+                } else {
+                    Ok(unit)
+                }
+                .wrap_with_cost(OperationCost {
+                    seek_count: 1,
+                    ..Default::default()
+                })
+            })
+            .expect("expected to start snapshot transactions");
+        assert_eq!(results.len(), 64);
+        // units before the failing one were all run, in any order
+        for (unit, result) in results.iter().enumerate().take(10) {
+            assert_eq!(
+                result.as_ref().map(|result| result.value.as_ref().ok()),
+                Some(Some(&unit))
+            );
+        }
+        assert!(matches!(
+            results[10].as_ref().map(|result| &result.value),
+            Some(Err(Error::InternalError(_)))
+        ));
+    }
 }
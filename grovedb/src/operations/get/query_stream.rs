@@ -0,0 +1,436 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Streaming path query results
+
+use grovedb_costs::{CostContext, OperationCost};
+use grovedb_merk::{
+    proofs::{query::query_item::QueryItem, Query},
+    CryptoHash,
+};
+use grovedb_path::SubtreePath;
+use grovedb_storage::{
+    rocksdb_storage::{
+        PrefixedRocksDbStorageContext, PrefixedRocksDbTransactionContext, RocksDbStorage,
+    },
+    RawIterator, Storage, StorageContext,
+};
+
+use crate::{
+    element::{check_keys_only, helpers::raw_decode_with_value_hash},
+    query_result_type::{QueryResultElement, QueryResultType},
+    Element, Error, GroveDb, PathQuery, SizedQuery, TransactionArg,
+};
+
+type NoTxRawIterator<'db> =
+    <PrefixedRocksDbStorageContext<'db> as StorageContext<'db>>::RawIterator;
+type TxRawIterator<'db> =
+    <PrefixedRocksDbTransactionContext<'db> as StorageContext<'db>>::RawIterator;
+
+/// Raw iterator over a subtree, either within a transaction or not
+enum StreamRawIterator<'db> {
+    NoTx(NoTxRawIterator<'db>),
+    Tx(TxRawIterator<'db>),
+}
+
+impl<'db> StreamRawIterator<'db> {
+    fn new(
+        db: &'db RocksDbStorage,
+        path: &[Vec<u8>],
+        transaction: TransactionArg<'db, 'db>,
+        cost: &mut OperationCost,
+    ) -> Self {
+        let subtree_path: SubtreePath<_> = path.into();
+        if let Some(tx) = transaction {
+            StreamRawIterator::Tx(
+                db.get_transactional_storage_context(subtree_path, None, tx)
+                    .unwrap_add_cost(cost)
+                    .raw_iter(),
+            )
+        } else {
+            StreamRawIterator::NoTx(
+                db.get_storage_context(subtree_path, None)
+                    .unwrap_add_cost(cost)
+                    .raw_iter(),
+            )
+        }
+    }
+}
+
+impl<'db> RawIterator for StreamRawIterator<'db> {
+    fn seek_to_first(&mut self) -> CostContext<()> {
+        match self {
+            StreamRawIterator::NoTx(iter) => iter.seek_to_first(),
+            StreamRawIterator::Tx(iter) => iter.seek_to_first(),
+        }
+    }
+
+    fn seek_to_last(&mut self) -> CostContext<()> {
+        match self {
+            StreamRawIterator::NoTx(iter) => iter.seek_to_last(),
+            StreamRawIterator::Tx(iter) => iter.seek_to_last(),
+        }
+    }
+
+    fn seek<K: AsRef<[u8]>>(&mut self, key: K) -> CostContext<()> {
+        match self {
+            StreamRawIterator::NoTx(iter) => iter.seek(key),
+            StreamRawIterator::Tx(iter) => iter.seek(key),
+        }
+    }
+
+    fn seek_for_prev<K: AsRef<[u8]>>(&mut self, key: K) -> CostContext<()> {
+        match self {
+            StreamRawIterator::NoTx(iter) => iter.seek_for_prev(key),
+            StreamRawIterator::Tx(iter) => iter.seek_for_prev(key),
+        }
+    }
+
+    fn next(&mut self) -> CostContext<()> {
+        match self {
+            StreamRawIterator::NoTx(iter) => iter.next(),
+            StreamRawIterator::Tx(iter) => iter.next(),
+        }
+    }
+
+    fn prev(&mut self) -> CostContext<()> {
+        match self {
+            StreamRawIterator::NoTx(iter) => iter.prev(),
+            StreamRawIterator::Tx(iter) => iter.prev(),
+        }
+    }
+
+    fn value(&self) -> CostContext<Option<&[u8]>> {
+        match self {
+            StreamRawIterator::NoTx(iter) => iter.value(),
+            StreamRawIterator::Tx(iter) => iter.value(),
+        }
+    }
+
+    fn key(&self) -> CostContext<Option<&[u8]>> {
+        match self {
+            StreamRawIterator::NoTx(iter) => iter.key(),
+            StreamRawIterator::Tx(iter) => iter.key(),
+        }
+    }
+
+    fn valid(&self) -> CostContext<bool> {
+        match self {
+            StreamRawIterator::NoTx(iter) => iter.valid(),
+            StreamRawIterator::Tx(iter) => iter.valid(),
+        }
+    }
+}
+
+/// A subtree being visited by the stream
+struct Frame<'db> {
+    path: Vec<Vec<u8>>,
+    query: SizedQuery,
+    /// Number of query items already visited, in iteration order
+    visited_items: usize,
+    /// Query item currently being iterated over
+    current: Option<(QueryItem, StreamRawIterator<'db>)>,
+}
+
+impl<'db> Frame<'db> {
+    fn new(path: Vec<Vec<u8>>, query: Query) -> Self {
+        Frame {
+            path,
+            query: SizedQuery::new(query, None, None),
+            visited_items: 0,
+            current: None,
+        }
+    }
+
+    /// Takes the next query item to visit, respecting query direction
+    fn next_item(&mut self) -> Option<QueryItem> {
+        let items = &self.query.query.items;
+        let index = if self.query.query.left_to_right {
+            self.visited_items
+        } else {
+            items.len().checked_sub(self.visited_items + 1)?
+        };
+        let item = items.get(index)?.clone();
+        self.visited_items += 1;
+        Some(item)
+    }
+}
+
+/// Stream of path query results which lazily iterates over the queried
+/// subtrees, so only the results asked for are read from storage.
+/// Results match those of `GroveDb::query_raw`. Iterating blocks on storage,
+/// `AsyncGroveDb::query_stream` polls the results from an async executor.
+pub struct QueryStream<'db> {
+    db: &'db RocksDbStorage,
+    transaction: TransactionArg<'db, 'db>,
+    result_type: QueryResultType,
+    limit: Option<u16>,
+    offset: Option<u16>,
+    frames: Vec<Frame<'db>>,
+    /// Error checking the path query, yielded as the only result
+    error: Option<Error>,
+    cost: OperationCost,
+}
+
+impl<'db> QueryStream<'db> {
+    /// Accumulated cost of the storage operations done so far
+    pub fn cost(&self) -> &OperationCost {
+        &self.cost
+    }
+
+    /// Advances the query until the next result, `None` means the query is
+    /// exhausted. The stream ends after an error.
+    fn next_result(&mut self) -> Option<Result<QueryResultElement, Error>> {
+        let result = self.advance();
+        if !matches!(result, Some(Ok(_))) {
+            self.frames.clear();
+        }
+        result
+    }
+
+    fn advance(&mut self) -> Option<Result<QueryResultElement, Error>> {
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
+        }
+        loop {
+            if self.limit == Some(0) {
+                return None;
+            }
+            let frame = self.frames.last_mut()?;
+
+            let Some((item, iter)) = &mut frame.current else {
+                if let Some(item) = frame.next_item() {
+                    let mut iter = StreamRawIterator::new(
+                        self.db,
+                        &frame.path,
+                        self.transaction,
+                        &mut self.cost,
+                    );
+                    item.seek_for_iter(&mut iter, frame.query.query.left_to_right)
+                        .unwrap_add_cost(&mut self.cost);
+                    frame.current = Some((item, iter));
+                } else {
+                    self.frames.pop();
+                }
+                continue;
+            };
+
+            let left_to_right = frame.query.query.left_to_right;
+            if !item
+                .iter_is_valid_for_type(iter, self.limit, left_to_right)
+                .unwrap_add_cost(&mut self.cost)
+            {
+                frame.current = None;
+                continue;
+            }
+
+            let (element, value_hash) = match raw_decode_with_value_hash(
+                iter.value()
+                    .unwrap_add_cost(&mut self.cost)
+                    .expect("if key exists then value should too"),
+            ) {
+                Ok(decoded) => decoded,
+                Err(e) => return Some(Err(e)),
+            };
+            let key = iter
+                .key()
+                .unwrap_add_cost(&mut self.cost)
+                .expect("key should exist")
+                .to_vec();
+            if left_to_right {
+                iter.next().unwrap_add_cost(&mut self.cost);
+            } else {
+                iter.prev().unwrap_add_cost(&mut self.cost);
+            }
+            self.cost.seek_count += 1;
+
+            let Some((subquery_path, subquery)) =
+                Element::subquery_paths_and_value_for_element(&frame.query, &key, &element)
+            else {
+                continue;
+            };
+            let path = frame.path.clone();
+
+            match self.push_element(path, key, element, value_hash, subquery_path, subquery) {
+                Ok(Some(result)) => return Some(Ok(result)),
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+
+    /// Either yields the element or, for trees with a subquery, schedules the
+    /// subtree to be visited
+    fn push_element(
+        &mut self,
+        path: Vec<Vec<u8>>,
+        key: Vec<u8>,
+        element: Element,
+        value_hash: CryptoHash,
+        subquery_path: Option<Vec<Vec<u8>>>,
+        subquery: Option<Query>,
+    ) -> Result<Option<QueryResultElement>, Error> {
+        if element.is_tree() && (subquery.is_some() || subquery_path.is_some()) {
+            let mut subtree_path = path;
+            subtree_path.push(key);
+
+            if let Some(subquery) = subquery {
+                if let Some(subquery_path) = subquery_path {
+                    subtree_path.extend(subquery_path);
+                }
+                self.frames.push(Frame::new(subtree_path, subquery));
+                return Ok(None);
+            }
+
+            let subquery_path = subquery_path.expect("checked above");
+            let Some((last_key, front_keys)) = subquery_path.split_last() else {
+                return Err(Error::CorruptedCodeExecution(
+                    "subquery_paths can not be empty",
+                ));
+            };
+            if self.offset.unwrap_or(0) > 0 {
+                self.skip();
+                return Ok(None);
+            }
+            subtree_path.extend(front_keys.iter().cloned());
+            let (element, value_hash) = self.get_element(&subtree_path, last_key)?;
+            Ok(Some(self.take(
+                subtree_path,
+                last_key.clone(),
+                element,
+                value_hash,
+            )))
+        } else {
+            if self.offset.unwrap_or(0) > 0 {
+                self.skip();
+                return Ok(None);
+            }
+            let path_slices: Vec<&[u8]> = path.iter().map(|k| k.as_slice()).collect();
+            let element =
+                element.convert_if_reference_to_absolute_reference(&path_slices, Some(&key))?;
+            Ok(Some(self.take(path, key, element, value_hash)))
+        }
+    }
+
+    /// Reads an element under a subquery path directly, along with its value
+    /// hash
+    fn get_element(
+        &mut self,
+        path: &[Vec<u8>],
+        key: &[u8],
+    ) -> Result<(Element, CryptoHash), Error> {
+        let mut iter = StreamRawIterator::new(self.db, path, self.transaction, &mut self.cost);
+        iter.seek(key).unwrap_add_cost(&mut self.cost);
+        if iter.key().unwrap_add_cost(&mut self.cost) != Some(key) {
+            return Err(Error::PathKeyNotFound(format!(
+                "key not found in Merk for get: {}",
+                hex::encode(key)
+            )));
+        }
+        let (element, value_hash) = raw_decode_with_value_hash(
+            iter.value()
+                .unwrap_add_cost(&mut self.cost)
+                .expect("if key exists then value should too"),
+        )?;
+        let path_slices: Vec<&[u8]> = path.iter().map(|k| k.as_slice()).collect();
+        let element =
+            element.convert_if_reference_to_absolute_reference(&path_slices, Some(key))?;
+        Ok((element, value_hash))
+    }
+
+    fn skip(&mut self) {
+        if let Some(offset) = &mut self.offset {
+            *offset -= 1;
+        }
+    }
+
+    fn take(
+        &mut self,
+        path: Vec<Vec<u8>>,
+        key: Vec<u8>,
+        element: Element,
+        value_hash: CryptoHash,
+    ) -> QueryResultElement {
+        if let Some(limit) = &mut self.limit {
+            *limit -= 1;
+        }
+        match self.result_type {
+            QueryResultType::QueryElementResultType => {
+                QueryResultElement::ElementResultItem(element)
+            }
+            QueryResultType::QueryKeyElementPairResultType => {
+                QueryResultElement::KeyElementPairResultItem((key, element))
+            }
+            QueryResultType::QueryPathKeyElementTrioResultType => {
+                QueryResultElement::PathKeyElementTrioResultItem((path, key, element))
+            }
+            QueryResultType::QueryPathKeyResultType => {
+                QueryResultElement::PathKeyResultItem((path, key))
+            }
+            QueryResultType::QueryPathKeyValueHashResultType => {
+                QueryResultElement::PathKeyValueHashResultItem((path, key, value_hash))
+            }
+        }
+    }
+}
+
+impl<'db> Iterator for QueryStream<'db> {
+    type Item = Result<QueryResultElement, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_result()
+    }
+}
+
+impl GroveDb {
+    /// Returns a stream of path query results which are read from storage only
+    /// when iterated over, so large result sets don't have to be collected
+    /// first.
+    /// Yields the same results as `query_raw`; the cost spent so far is
+    /// available on the stream.
+    pub fn query_stream<'db>(
+        &'db self,
+        path_query: &PathQuery,
+        result_type: QueryResultType,
+        transaction: TransactionArg<'db, 'db>,
+    ) -> QueryStream<'db> {
+        QueryStream {
+            db: &self.db,
+            transaction,
+            result_type,
+            limit: path_query.query.limit,
+            offset: path_query.query.offset,
+            frames: vec![Frame::new(
+                path_query.path.clone(),
+                path_query.query.query.clone(),
+            )],
+            error: check_keys_only(path_query, result_type).err(),
+            cost: OperationCost::default(),
+        }
+    }
+}
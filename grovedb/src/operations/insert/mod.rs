@@ -33,7 +33,8 @@ use std::{collections::HashMap, option::Option::None};
 
 #[cfg(feature = "full")]
 use grovedb_costs::{
-    cost_return_on_error, cost_return_on_error_no_add, CostResult, CostsExt, OperationCost,
+    cost_return_on_error, cost_return_on_error_default, cost_return_on_error_no_add, CostResult,
+    CostsExt, OperationCost,
 };
 #[cfg(feature = "full")]
 use grovedb_merk::{tree::NULL_HASH, Merk, MerkOptions};
@@ -46,8 +47,13 @@ use grovedb_storage::{Storage, StorageBatch};
 
 #[cfg(feature = "full")]
 use crate::{
-    reference_path::path_from_reference_path_type, Element, Error, GroveDb, Transaction,
-    TransactionArg,
+    batch::{BatchApplyOptions, GroveDbOp},
+    element::{ElementGetExt, ElementInsertExt},
+    metrics::OperationKind,
+    operations::insertion_metadata::InsertionMetadata,
+    reference_path::path_from_reference_path_type,
+    transaction::storage_transaction,
+    Element, Error, GroveDb, Transaction, TransactionArg,
 };
 
 #[cfg(feature = "full")]
@@ -60,6 +66,9 @@ pub struct InsertOptions {
     pub validate_insertion_does_not_override_tree: bool,
     /// Base root storage is free
     pub base_root_storage_is_free: bool,
+    /// Metadata recorded for the element if its subtree has insertion
+    /// metadata enabled
+    pub insertion_metadata: Option<InsertionMetadata>,
 }
 
 #[cfg(feature = "full")]
@@ -69,6 +78,7 @@ impl Default for InsertOptions {
             validate_insertion_does_not_override: false,
             validate_insertion_does_not_override_tree: true,
             base_root_storage_is_free: true,
+            insertion_metadata: None,
         }
     }
 }
@@ -103,32 +113,125 @@ impl GroveDb {
         P: Into<SubtreePath<'b, B>>,
     {
         let subtree_path: SubtreePath<B> = path.into();
-        let batch = StorageBatch::new();
-
-        let collect_costs = if let Some(transaction) = transaction {
-            self.insert_on_transaction(
+        // The first bidirectional reference of the grove starts the
+        // maintenance of backlinks
+        if self.maintains_backlinks() || self.backlinked_reference(&element).is_some() {
+            return self.insert_maintaining_backlinks(
                 subtree_path,
                 key,
                 element,
-                options.unwrap_or_default(),
+                options,
                 transaction,
-                &batch,
-            )
+            );
+        }
+        self.insert_element(subtree_path, key, element, options, transaction)
+    }
+
+    /// Inserts an element, through a batch when it is a reference having a
+    /// backlink or replaces one, so that backlinks are maintained
+    fn insert_maintaining_backlinks<B: AsRef<[u8]>>(
+        &self,
+        path: SubtreePath<B>,
+        key: &[u8],
+        element: Element,
+        options: Option<InsertOptions>,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        if self.backlinked_reference(&element).is_none() {
+            let existing = self
+                .get_raw(path.clone(), key, transaction)
+                .unwrap_add_cost(&mut cost);
+            if existing
+                .ok()
+                .and_then(|existing| self.backlinked_reference(&existing))
+                .is_none()
+            {
+                return self
+                    .insert_element(path, key, element, options, transaction)
+                    .add_cost(cost);
+            }
+        }
+
+        let options = options.unwrap_or_default();
+        self.apply_batch(
+            vec![GroveDbOp::insert_op(path.to_vec(), key.to_vec(), element)],
+            Some(BatchApplyOptions {
+                validate_insertion_does_not_override: options.validate_insertion_does_not_override,
+                validate_insertion_does_not_override_tree: options
+                    .validate_insertion_does_not_override_tree,
+                base_root_storage_is_free: options.base_root_storage_is_free,
+                insertion_metadata: options.insertion_metadata,
+                ..Default::default()
+            }),
+            transaction,
+        )
+        .add_cost(cost)
+    }
+
+    /// Inserts an element, the backlinks it affects being left as is
+    fn insert_element<B: AsRef<[u8]>>(
+        &self,
+        subtree_path: SubtreePath<B>,
+        key: &[u8],
+        element: Element,
+        options: Option<InsertOptions>,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let element = cost_return_on_error_default!(self.encode_element_flags(element));
+        cost_return_on_error_default!(self.check_tree_aggregate_is_registered(&element));
+        let batch = StorageBatch::new();
+        let path_vec = subtree_path.to_vec();
+        let created_subtree = element.is_tree().then(|| {
+            let mut subtree_path = path_vec.clone();
+            subtree_path.push(key.to_vec());
+            subtree_path
+        });
+        #[cfg(feature = "audit_log")]
+        let audit_entry = cost_return_on_error_default!(
+            crate::operations::audit_log::insert_entry(path_vec.clone(), key, &element)
+        );
+
+        let options = options.unwrap_or_default();
+        let insertion_metadata = options.insertion_metadata;
+        let recorded_path = subtree_path.clone();
+
+        let collect_costs = if let Some(transaction) = transaction {
+            self.insert_on_transaction(subtree_path, key, element, options, transaction, &batch)
         } else {
-            self.insert_without_transaction(
-                subtree_path,
-                key,
-                element,
-                options.unwrap_or_default(),
-                &batch,
-            )
+            self.insert_without_transaction(subtree_path, key, element, options, &batch)
         };
 
-        collect_costs.flat_map_ok(|_| {
-            self.db
-                .commit_multi_context_batch(batch, transaction)
-                .map_err(Into::into)
-        })
+        let collect_costs = collect_costs
+            .flat_map_ok(|_| {
+                self.record_insertion_metadata(
+                    std::iter::once((path_vec.clone(), key)),
+                    std::iter::empty(),
+                    insertion_metadata,
+                    &batch,
+                    transaction,
+                )
+            })
+            .flat_map_ok(|_| {
+                self.record_reverse_key_index(std::iter::once((path_vec, key)), &batch, transaction)
+            })
+            .flat_map_ok(|_| self.register_subtree_prefixes(created_subtree, &batch, transaction));
+
+        #[cfg(feature = "audit_log")]
+        let collect_costs =
+            self.audit_mutation(collect_costs, |_| vec![audit_entry], &batch, transaction);
+
+        collect_costs
+            .flat_map_ok(|_| {
+                self.db
+                    .commit_multi_context_batch(batch, storage_transaction(transaction))
+                    .map_err(Into::into)
+            })
+            .map_ok(|_| {
+                self.metrics
+                    .record(OperationKind::Write, &recorded_path, Some(key))
+            })
     }
 
     fn insert_on_transaction<'db, 'b, B: AsRef<[u8]>>(
@@ -246,7 +349,8 @@ impl GroveDb {
         }
 
         match element {
-            Element::Reference(ref reference_path, ..) => {
+            Element::Reference(ref reference_path, ..)
+            | Element::BidirectionalReference(ref reference_path, ..) => {
                 let path = path.to_vec(); // TODO: need for support for references in path library
                 let reference_path = cost_return_on_error!(
                     &mut cost,
@@ -297,7 +401,11 @@ impl GroveDb {
                     )
                 );
             }
-            Element::Tree(ref value, _) | Element::SumTree(ref value, ..) => {
+            Element::Tree(ref value, _)
+            | Element::SumTree(ref value, ..)
+            | Element::CountTree(ref value, ..)
+            | Element::BigSumTree(ref value, ..)
+            | Element::AggregateTree(ref value, ..) => {
                 if value.is_some() {
                     return Err(Error::InvalidCodeExecution(
                         "a tree should be empty at the moment of insertion when not using batches",
@@ -381,7 +489,8 @@ impl GroveDb {
         }
 
         match element {
-            Element::Reference(ref reference_path, ..) => {
+            Element::Reference(ref reference_path, ..)
+            | Element::BidirectionalReference(ref reference_path, ..) => {
                 let reference_path = cost_return_on_error!(
                     &mut cost,
                     path_from_reference_path_type(reference_path.clone(), path, Some(key))
@@ -429,7 +538,11 @@ impl GroveDb {
                     )
                 );
             }
-            Element::Tree(ref value, _) | Element::SumTree(ref value, ..) => {
+            Element::Tree(ref value, _)
+            | Element::SumTree(ref value, ..)
+            | Element::CountTree(ref value, ..)
+            | Element::BigSumTree(ref value, ..)
+            | Element::AggregateTree(ref value, ..) => {
                 if value.is_some() {
                     return Err(Error::InvalidCodeExecution(
                         "a tree should be empty at the moment of insertion when not using batches",
@@ -492,6 +605,8 @@ impl GroveDb {
     /// Insert if the value changed
     /// We return if the value was inserted
     /// If the value was changed then we return the previous element
+    /// The stored element is compared flags included, so re-submitting an
+    /// identical element writes nothing and is only charged for the read
     pub fn insert_if_changed_value<'b, B, P>(
         &self,
         path: P,
@@ -522,6 +637,36 @@ impl GroveDb {
                 .add_cost(cost)
         }
     }
+
+    /// Replaces the element stored under an existing key, erroring with
+    /// `PathKeyNotFound` instead of inserting when the key doesn't exist.
+    /// The existence check and the write are applied as one batch.
+    pub fn replace<'b, B, P>(
+        &self,
+        path: P,
+        key: &[u8],
+        element: Element,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let subtree_path: SubtreePath<B> = path.into();
+
+        self.apply_batch(
+            vec![GroveDbOp::replace_op(
+                subtree_path.to_vec(),
+                key.to_vec(),
+                element,
+            )],
+            Some(BatchApplyOptions {
+                validate_insertion_does_not_override_tree: true,
+                ..Default::default()
+            }),
+            transaction,
+        )
+    }
 }
 
 #[cfg(feature = "full")]
@@ -592,7 +737,7 @@ mod tests {
         let item_key = b"key3";
 
         let db = make_test_grovedb();
-        let transaction = db.start_transaction();
+        let transaction = db.start_transaction().unwrap();
 
         // Check that there's no such key in the DB
         let result = db.get([TEST_LEAF].as_ref(), item_key, None).unwrap();
@@ -637,7 +782,7 @@ mod tests {
         let subtree_key = b"subtree_key";
 
         let db = make_test_grovedb();
-        let transaction = db.start_transaction();
+        let transaction = db.start_transaction().unwrap();
 
         // Check that there's no such key in the DB
         let result = db.get([TEST_LEAF].as_ref(), subtree_key, None).unwrap();
@@ -697,10 +842,46 @@ mod tests {
         assert!(matches!(result, Err(Error::InvalidParentLayerPath(_))));
     }
 
+    #[test]
+    fn test_insert_if_changed_value() {
+        let db = make_test_grovedb();
+        let element = Element::new_item_with_flags(b"value".to_vec(), Some(vec![1]));
+        db.insert([TEST_LEAF].as_ref(), b"key1", element.clone(), None, None)
+            .unwrap()
+            .expect("successful insert");
+
+        // Re-submitting the same element is only charged for the read
+        let result = db.insert_if_changed_value([TEST_LEAF].as_ref(), b"key1", element, None);
+        assert_eq!(result.value.expect("successful insert"), (false, None));
+        assert_eq!(result.cost.storage_cost, StorageCost::default());
+
+        // Changed flags alone are written
+        let reflagged = Element::new_item_with_flags(b"value".to_vec(), Some(vec![2]));
+        let result =
+            db.insert_if_changed_value([TEST_LEAF].as_ref(), b"key1", reflagged.clone(), None);
+        assert_eq!(
+            result.value.expect("successful insert"),
+            (
+                true,
+                Some(Element::new_item_with_flags(
+                    b"value".to_vec(),
+                    Some(vec![1])
+                ))
+            )
+        );
+        assert!(result.cost.storage_cost.replaced_bytes > 0);
+        assert_eq!(
+            db.get([TEST_LEAF].as_ref(), b"key1", None)
+                .unwrap()
+                .expect("successful get"),
+            reflagged
+        );
+    }
+
     #[test]
     fn test_one_insert_item_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let cost = db
             .insert(
@@ -759,7 +940,7 @@ mod tests {
     #[test]
     fn test_one_insert_sum_item_in_sum_tree_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"s", Element::empty_sum_tree(), None, Some(&tx))
             .unwrap()
@@ -817,7 +998,7 @@ mod tests {
     #[test]
     fn test_one_insert_sum_item_under_sum_item_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"s", Element::empty_sum_tree(), None, Some(&tx))
             .unwrap()
@@ -885,7 +1066,7 @@ mod tests {
     #[test]
     fn test_one_insert_bigger_sum_item_under_sum_item_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"s", Element::empty_sum_tree(), None, Some(&tx))
             .unwrap()
@@ -954,7 +1135,7 @@ mod tests {
     #[test]
     fn test_one_insert_item_cost_with_flags() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let cost = db
             .insert(
@@ -1013,7 +1194,7 @@ mod tests {
     #[test]
     fn test_one_insert_empty_tree_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let cost = db
             .insert(EMPTY_PATH, b"key1", Element::empty_tree(), None, Some(&tx))
@@ -1065,7 +1246,7 @@ mod tests {
     #[test]
     fn test_one_insert_empty_sum_tree_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let cost = db
             .insert(
@@ -1124,7 +1305,7 @@ mod tests {
     #[test]
     fn test_one_insert_empty_tree_cost_with_flags() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let cost = db
             .insert(
@@ -1186,7 +1367,7 @@ mod tests {
     #[test]
     fn test_one_insert_item_cost_under_tree() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"tree", Element::empty_tree(), None, Some(&tx))
             .unwrap()
@@ -1257,7 +1438,7 @@ mod tests {
     #[test]
     fn test_one_insert_item_with_apple_flags_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         let cost = db
             .insert(
@@ -1320,7 +1501,7 @@ mod tests {
     #[test]
     fn test_one_insert_item_with_flags_cost_under_tree() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"tree", Element::empty_tree(), None, Some(&tx))
             .unwrap()
@@ -1405,7 +1586,7 @@ mod tests {
     #[test]
     fn test_one_insert_item_with_flags_cost_under_tree_with_flags() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(
             EMPTY_PATH,
@@ -1498,7 +1679,7 @@ mod tests {
     #[test]
     fn test_one_update_item_same_cost_at_root() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(
             EMPTY_PATH,
@@ -1564,7 +1745,7 @@ mod tests {
     #[test]
     fn test_one_update_same_cost_in_underlying_tree() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"tree", Element::empty_tree(), None, Some(&tx))
             .unwrap()
@@ -1608,7 +1789,7 @@ mod tests {
     #[test]
     fn test_one_update_same_cost_in_underlying_sum_tree_bigger_sum_item() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(
             EMPTY_PATH,
@@ -1659,7 +1840,7 @@ mod tests {
     fn test_one_update_same_cost_in_underlying_sum_tree_bigger_sum_item_parent_sum_tree_already_big(
     ) {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(
             EMPTY_PATH,
@@ -1719,7 +1900,7 @@ mod tests {
     #[test]
     fn test_one_update_same_cost_in_underlying_sum_tree_smaller_sum_item() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(
             EMPTY_PATH,
@@ -1769,7 +1950,7 @@ mod tests {
     #[test]
     fn test_one_update_bigger_cost() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"tree", Element::empty_tree(), None, Some(&tx))
             .unwrap()
@@ -1813,7 +1994,7 @@ mod tests {
     #[test]
     fn test_one_update_tree_bigger_cost_with_flags() {
         let db = make_empty_grovedb();
-        let tx = db.start_transaction();
+        let tx = db.start_transaction().unwrap();
 
         db.insert(EMPTY_PATH, b"tree", Element::empty_tree(), None, Some(&tx))
             .unwrap()
@@ -1838,6 +2019,7 @@ mod tests {
                     validate_insertion_does_not_override: false,
                     validate_insertion_does_not_override_tree: false,
                     base_root_storage_is_free: true,
+                    insertion_metadata: None,
                 }),
                 Some(&tx),
             )
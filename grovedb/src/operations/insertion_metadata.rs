@@ -0,0 +1,324 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Insertion metadata
+//!
+//! Subtrees can be configured to record, for every element written into
+//! them, the block height and timestamp supplied by the application at write
+//! time, in `InsertOptions` or `BatchApplyOptions`. The metadata is kept in
+//! the auxiliary storage of the subtree, next to the element, and is written
+//! in the same batch as the element so both are committed atomically. It is
+//! meant for audit trails and doesn't contribute to any hash.
+
+#[cfg(feature = "full")]
+use std::collections::BTreeMap;
+
+#[cfg(feature = "full")]
+use grovedb_costs::{cost_return_on_error, CostResult, CostsExt, OperationCost};
+use grovedb_path::SubtreePath;
+#[cfg(feature = "full")]
+use grovedb_storage::{Storage, StorageBatch, StorageContext};
+
+#[cfg(feature = "full")]
+use crate::{
+    batch::{GroveDbOp, Op},
+    query_result_type::QueryResultType::QueryPathKeyElementTrioResultType,
+    transaction::storage_transaction,
+    util::storage_context_optional_tx,
+    Element, Error, GroveDb, PathQuery, TransactionArg,
+};
+
+/// Aux key marking a subtree as recording insertion metadata
+#[cfg(feature = "full")]
+const ENABLED_AUX_KEY: &[u8] = b"insertion_metadata_enabled";
+
+/// Prefix of the aux keys holding the insertion metadata of elements
+#[cfg(feature = "full")]
+const METADATA_AUX_PREFIX: &[u8] = b"insertion_metadata/";
+
+#[cfg(feature = "full")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// Application supplied information about when an element was written
+pub struct InsertionMetadata {
+    /// Block height
+    pub block_height: u64,
+    /// Timestamp
+    pub timestamp: u64,
+}
+
+#[cfg(feature = "full")]
+impl InsertionMetadata {
+    /// Serializes the metadata
+    fn encode(&self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[..8].copy_from_slice(&self.block_height.to_be_bytes());
+        bytes[8..].copy_from_slice(&self.timestamp.to_be_bytes());
+        bytes
+    }
+
+    /// Deserializes the metadata
+    fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != 16 {
+            return Err(Error::CorruptedData(
+                "insertion metadata is not 16 bytes".to_owned(),
+            ));
+        }
+        let (block_height, timestamp) = bytes.split_at(8);
+        Ok(InsertionMetadata {
+            block_height: u64::from_be_bytes(block_height.try_into().expect("8 bytes")),
+            timestamp: u64::from_be_bytes(timestamp.try_into().expect("8 bytes")),
+        })
+    }
+}
+
+/// Aux key of the insertion metadata of `key`
+#[cfg(feature = "full")]
+fn metadata_aux_key(key: &[u8]) -> Vec<u8> {
+    let mut aux_key = METADATA_AUX_PREFIX.to_vec();
+    aux_key.extend_from_slice(key);
+    aux_key
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Enables or disables recording of insertion metadata for the subtree at
+    /// `path`. Metadata already recorded is kept.
+    pub fn configure_insertion_metadata<'b, B, P>(
+        &self,
+        path: P,
+        enabled: bool,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        cost_return_on_error!(
+            &mut cost,
+            self.check_subtree_exists_path_not_found(path.clone(), transaction)
+        );
+
+        let batch = StorageBatch::new();
+        storage_context_optional_tx!(self.db, path, Some(&batch), transaction, storage, {
+            let storage = storage.unwrap_add_cost(&mut cost);
+            let result = if enabled {
+                storage.put_aux(ENABLED_AUX_KEY, &[1], None)
+            } else {
+                storage.delete_aux(ENABLED_AUX_KEY, None)
+            };
+            cost_return_on_error!(&mut cost, result.map_err(Into::into));
+        });
+
+        self.db
+            .commit_multi_context_batch(batch, storage_transaction(transaction))
+            .map_err(Into::into)
+            .add_cost(cost)
+    }
+
+    /// Returns the element at `path` and `key` together with its insertion
+    /// metadata, if any was recorded
+    pub fn get_with_insertion_metadata<'b, B, P>(
+        &self,
+        path: P,
+        key: &[u8],
+        transaction: TransactionArg,
+    ) -> CostResult<(Element, Option<InsertionMetadata>), Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        let element =
+            cost_return_on_error!(&mut cost, self.get_raw(path.clone(), key, transaction));
+        let metadata = cost_return_on_error!(
+            &mut cost,
+            self.get_insertion_metadata(path, key, transaction)
+        );
+        Ok((element, metadata)).wrap_with_cost(cost)
+    }
+
+    /// Runs `path_query` without following references and returns every
+    /// result together with its insertion metadata, if any was recorded
+    pub fn query_with_insertion_metadata(
+        &self,
+        path_query: &PathQuery,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<(Vec<Vec<u8>>, Vec<u8>, Element, Option<InsertionMetadata>)>, Error> {
+        let mut cost = OperationCost::default();
+
+        let (results, _) = cost_return_on_error!(
+            &mut cost,
+            self.query_raw(
+                path_query,
+                true,
+                QueryPathKeyElementTrioResultType,
+                transaction
+            )
+        );
+
+        let mut results_with_metadata = Vec::new();
+        for (path, key, element) in results.to_path_key_elements() {
+            let metadata = cost_return_on_error!(
+                &mut cost,
+                self.get_insertion_metadata(path.as_slice().into(), &key, transaction)
+            );
+            results_with_metadata.push((path, key, element, metadata));
+        }
+        Ok(results_with_metadata).wrap_with_cost(cost)
+    }
+
+    /// Returns the insertion metadata recorded for `key` in the subtree at
+    /// `path`
+    fn get_insertion_metadata<B: AsRef<[u8]>>(
+        &self,
+        path: SubtreePath<B>,
+        key: &[u8],
+        transaction: TransactionArg,
+    ) -> CostResult<Option<InsertionMetadata>, Error> {
+        let mut cost = OperationCost::default();
+
+        storage_context_optional_tx!(self.db, path, None, transaction, storage, {
+            let bytes = cost_return_on_error!(
+                &mut cost,
+                storage
+                    .unwrap_add_cost(&mut cost)
+                    .get_aux(metadata_aux_key(key))
+                    .map_err(Into::into)
+            );
+            bytes
+                .map(|bytes| InsertionMetadata::decode(&bytes))
+                .transpose()
+                .wrap_with_cost(cost)
+        })
+    }
+
+    /// Whether the subtree at `path` records insertion metadata
+    fn is_insertion_metadata_enabled<B: AsRef<[u8]>>(
+        &self,
+        path: SubtreePath<B>,
+        transaction: TransactionArg,
+    ) -> CostResult<bool, Error> {
+        let mut cost = OperationCost::default();
+
+        storage_context_optional_tx!(self.db, path, None, transaction, storage, {
+            storage
+                .unwrap_add_cost(&mut cost)
+                .get_aux(ENABLED_AUX_KEY)
+                .map_ok(|enabled| enabled.is_some())
+                .map_err(Into::into)
+                .add_cost(cost)
+        })
+    }
+
+    /// Stages into `batch` the insertion metadata of the elements written at
+    /// the given paths and keys, for the subtrees that have it enabled, and
+    /// the last modification of the elements written and deleted. With no
+    /// metadata, nothing is recorded and the metadata of overwritten
+    /// elements is left untouched.
+    pub(crate) fn record_insertion_metadata<'k>(
+        &self,
+        written: impl IntoIterator<Item = (Vec<Vec<u8>>, &'k [u8])>,
+        deleted: impl IntoIterator<Item = (Vec<Vec<u8>>, &'k [u8])>,
+        metadata: Option<InsertionMetadata>,
+        batch: &StorageBatch,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+        let Some(metadata) = metadata else {
+            return Ok(()).wrap_with_cost(cost);
+        };
+        let written: Vec<_> = written.into_iter().collect();
+        let mut enabled_subtrees = BTreeMap::new();
+
+        for (path, key) in written.iter() {
+            let enabled = match enabled_subtrees.get(path) {
+                Some(enabled) => *enabled,
+                None => {
+                    let enabled = cost_return_on_error!(
+                        &mut cost,
+                        self.is_insertion_metadata_enabled(path.as_slice().into(), transaction)
+                    );
+                    enabled_subtrees.insert(path.clone(), enabled);
+                    enabled
+                }
+            };
+            if !enabled {
+                continue;
+            }
+
+            let subtree_path: SubtreePath<_> = path.as_slice().into();
+            let aux_key = metadata_aux_key(key);
+            storage_context_optional_tx!(
+                self.db,
+                subtree_path,
+                Some(batch),
+                transaction,
+                storage,
+                {
+                    cost_return_on_error!(
+                        &mut cost,
+                        storage
+                            .unwrap_add_cost(&mut cost)
+                            .put_aux(aux_key, &metadata.encode(), None)
+                            .map_err(Into::into)
+                    );
+                }
+            );
+        }
+
+        let modified: Vec<_> = written.into_iter().chain(deleted).collect();
+        self.record_last_modified(&modified, metadata.block_height, batch, transaction)
+            .add_cost(cost)
+    }
+}
+
+/// Paths and keys of the elements written by a batch
+#[cfg(feature = "full")]
+pub(crate) fn written_keys(ops: &[GroveDbOp]) -> impl Iterator<Item = (Vec<Vec<u8>>, &[u8])> {
+    ops.iter()
+        .filter(|op| {
+            matches!(
+                op.op,
+                Op::Insert { .. } | Op::Replace { .. } | Op::Patch { .. } | Op::UpdateFlags { .. }
+            )
+        })
+        .map(|op| (op.path.to_path(), op.key.as_slice()))
+}
+
+/// Paths and keys of the elements deleted by a batch
+#[cfg(feature = "full")]
+pub(crate) fn deleted_keys(ops: &[GroveDbOp]) -> impl Iterator<Item = (Vec<Vec<u8>>, &[u8])> {
+    ops.iter()
+        .filter(|op| matches!(op.op, Op::Delete | Op::DeleteTree | Op::DeleteSumTree))
+        .map(|op| (op.path.to_path(), op.key.as_slice()))
+}
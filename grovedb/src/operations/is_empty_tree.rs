@@ -58,4 +58,31 @@ impl GroveDb {
             Ok(subtree.is_empty_tree().unwrap_add_cost(&mut cost)).wrap_with_cost(cost)
         })
     }
+
+    /// Check if the tree has fewer than `limit` elements, without iterating
+    /// over more than `limit` of them
+    pub fn is_empty_tree_with_limit<'b, B, P>(
+        &self,
+        path: P,
+        limit: usize,
+        transaction: TransactionArg,
+    ) -> CostResult<bool, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        cost_return_on_error!(
+            &mut cost,
+            self.check_subtree_exists_path_not_found(path.clone(), transaction)
+        );
+        merk_optional_tx!(&mut cost, self.db, path, None, transaction, subtree, {
+            Ok(subtree
+                .has_fewer_elements_than(limit)
+                .unwrap_add_cost(&mut cost))
+            .wrap_with_cost(cost)
+        })
+    }
 }
@@ -0,0 +1,260 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Last modified tracking
+//!
+//! Subtrees can be configured to record the block height of the last
+//! modification of each of their elements, the height being the one of the
+//! insertion metadata supplied with the write or the deletion. An element is
+//! modified when it is written, flags updates included, or deleted, and a
+//! tree element also when anything is modified anywhere below it, so the
+//! elements changed since a given height can be found by only descending
+//! into the trees changed since then. A deleted element leaves its height
+//! behind as a tombstone, so deletions are found the same way. The heights
+//! are kept in the meta storage of the subtree, apart from the auxiliary
+//! storage left to users, and don't contribute to any hash.
+
+#[cfg(feature = "full")]
+use std::collections::{BTreeMap, BTreeSet};
+
+#[cfg(feature = "full")]
+use grovedb_costs::{
+    cost_return_on_error, cost_return_on_error_no_add, CostResult, CostsExt, OperationCost,
+};
+use grovedb_path::SubtreePath;
+#[cfg(feature = "full")]
+use grovedb_storage::{RawIterator, Storage, StorageBatch, StorageContext};
+
+#[cfg(feature = "full")]
+use crate::{
+    transaction::storage_transaction, util::storage_context_optional_tx, Error, GroveDb,
+    TransactionArg,
+};
+
+/// Meta key marking a subtree as tracking the last modification of elements
+#[cfg(feature = "full")]
+const ENABLED_META_KEY: &[u8] = b"last_modified_enabled";
+
+/// Prefix of the meta keys holding the last modification heights of elements
+#[cfg(feature = "full")]
+const HEIGHT_META_PREFIX: &[u8] = b"last_modified/";
+
+/// Meta key of the last modification height of `key`
+#[cfg(feature = "full")]
+fn height_meta_key(key: &[u8]) -> Vec<u8> {
+    let mut meta_key = HEIGHT_META_PREFIX.to_vec();
+    meta_key.extend_from_slice(key);
+    meta_key
+}
+
+/// Deserializes a last modification height
+#[cfg(feature = "full")]
+fn decode_height(bytes: &[u8]) -> Result<u64, Error> {
+    bytes
+        .try_into()
+        .map(u64::from_be_bytes)
+        .map_err(|_| Error::CorruptedData("last modification height is not 8 bytes".to_owned()))
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Enables or disables tracking of the last modification of the elements
+    /// of the subtree at `path`. Heights already recorded are kept.
+    pub fn configure_last_modified_tracking<'b, B, P>(
+        &self,
+        path: P,
+        enabled: bool,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        cost_return_on_error!(
+            &mut cost,
+            self.check_subtree_exists_path_not_found(path.clone(), transaction)
+        );
+
+        let batch = StorageBatch::new();
+        storage_context_optional_tx!(self.db, path, Some(&batch), transaction, storage, {
+            let storage = storage.unwrap_add_cost(&mut cost);
+            let result = if enabled {
+                storage.put_meta(ENABLED_META_KEY, &[1], None)
+            } else {
+                storage.delete_meta(ENABLED_META_KEY, None)
+            };
+            cost_return_on_error!(&mut cost, result.map_err(Into::into));
+        });
+
+        self.db
+            .commit_multi_context_batch(batch, storage_transaction(transaction))
+            .map_err(Into::into)
+            .add_cost(cost)
+    }
+
+    /// Returns the paths and keys of the elements modified at or after
+    /// `block_height` in the subtree at `path` and, through the changed trees
+    /// also tracking modifications, in its descendants. Keys deleted at or
+    /// after `block_height` are returned as well.
+    pub fn changed_since<'b, B, P>(
+        &self,
+        path: P,
+        block_height: u64,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<(Vec<Vec<u8>>, Vec<u8>)>, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+
+        let mut changed = Vec::new();
+        let mut subtrees = vec![path.into().to_vec()];
+        while let Some(subtree) = subtrees.pop() {
+            let subtree_path: SubtreePath<_> = subtree.as_slice().into();
+            let mut changed_keys = Vec::new();
+            storage_context_optional_tx!(self.db, subtree_path, None, transaction, storage, {
+                let storage = storage.unwrap_add_cost(&mut cost);
+                let mut iter = storage.raw_iter_meta();
+                iter.seek(HEIGHT_META_PREFIX).unwrap_add_cost(&mut cost);
+                while iter.valid().unwrap_add_cost(&mut cost) {
+                    let Some(meta_key) = iter.key().unwrap_add_cost(&mut cost) else {
+                        break;
+                    };
+                    let Some(key) = meta_key.strip_prefix(HEIGHT_META_PREFIX) else {
+                        break;
+                    };
+                    let key = key.to_vec();
+                    let height = cost_return_on_error_no_add!(
+                        &cost,
+                        decode_height(iter.value().unwrap_add_cost(&mut cost).unwrap_or_default())
+                    );
+                    if height >= block_height {
+                        changed_keys.push(key);
+                    }
+                    iter.next().unwrap_add_cost(&mut cost);
+                }
+            });
+
+            for key in changed_keys {
+                let element = cost_return_on_error!(
+                    &mut cost,
+                    self.get_raw_optional(subtree.as_slice().into(), &key, transaction)
+                );
+                if matches!(element, Some(element) if element.is_tree()) {
+                    let mut child = subtree.clone();
+                    child.push(key.clone());
+                    subtrees.push(child);
+                }
+                changed.push((subtree.clone(), key));
+            }
+        }
+        changed.sort();
+        Ok(changed).wrap_with_cost(cost)
+    }
+
+    /// Whether the subtree at `path` tracks the last modification of elements
+    fn is_last_modified_tracking_enabled<B: AsRef<[u8]>>(
+        &self,
+        path: SubtreePath<B>,
+        transaction: TransactionArg,
+    ) -> CostResult<bool, Error> {
+        let mut cost = OperationCost::default();
+
+        storage_context_optional_tx!(self.db, path, None, transaction, storage, {
+            storage
+                .unwrap_add_cost(&mut cost)
+                .get_meta(ENABLED_META_KEY)
+                .map_ok(|enabled| enabled.is_some())
+                .map_err(Into::into)
+                .add_cost(cost)
+        })
+    }
+
+    /// Stages into `batch` the last modification height of the elements
+    /// written or deleted at the given paths and keys and of the tree elements
+    /// above them, for the subtrees that have tracking enabled
+    pub(crate) fn record_last_modified(
+        &self,
+        modified: &[(Vec<Vec<u8>>, &[u8])],
+        block_height: u64,
+        batch: &StorageBatch,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        let mut modified_with_ancestors = BTreeSet::new();
+        for (path, key) in modified {
+            modified_with_ancestors.insert((path.clone(), key.to_vec()));
+            for depth in 0..path.len() {
+                modified_with_ancestors.insert((path[..depth].to_vec(), path[depth].clone()));
+            }
+        }
+
+        let mut enabled_subtrees = BTreeMap::new();
+        for (path, key) in modified_with_ancestors {
+            let enabled = match enabled_subtrees.get(&path) {
+                Some(enabled) => *enabled,
+                None => {
+                    let enabled = cost_return_on_error!(
+                        &mut cost,
+                        self.is_last_modified_tracking_enabled(path.as_slice().into(), transaction)
+                    );
+                    enabled_subtrees.insert(path.clone(), enabled);
+                    enabled
+                }
+            };
+            if !enabled {
+                continue;
+            }
+
+            let subtree_path: SubtreePath<_> = path.as_slice().into();
+            storage_context_optional_tx!(
+                self.db,
+                subtree_path,
+                Some(batch),
+                transaction,
+                storage,
+                {
+                    cost_return_on_error!(
+                        &mut cost,
+                        storage
+                            .unwrap_add_cost(&mut cost)
+                            .put_meta(height_meta_key(&key), &block_height.to_be_bytes(), None)
+                            .map_err(Into::into)
+                    );
+                }
+            );
+        }
+
+        Ok(()).wrap_with_cost(cost)
+    }
+}
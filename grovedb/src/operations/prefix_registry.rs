@@ -0,0 +1,342 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Prefix registry
+//!
+//! Subtrees are stored under a prefix hashed from their path, which can't be
+//! reversed. When enabled, GroveDB maintains a registry from the prefix of
+//! every subtree to its path, so tooling inspecting the storage directly can
+//! tell which subtree a prefix belongs to. Entries are added when subtrees
+//! are created and removed with them, in the same storage batch.
+
+#[cfg(feature = "full")]
+use std::sync::atomic::Ordering;
+
+#[cfg(feature = "full")]
+use grovedb_costs::{
+    cost_return_on_error, cost_return_on_error_no_add, CostResult, CostsExt, OperationCost,
+};
+#[cfg(feature = "full")]
+use grovedb_path::SubtreePath;
+#[cfg(feature = "full")]
+use grovedb_storage::{
+    rocksdb_storage::RocksDbStorage, RawIterator, Storage, StorageBatch, StorageContext,
+};
+
+#[cfg(feature = "full")]
+use crate::{
+    batch::{GroveDbOp, Op},
+    util::meta_storage_context_optional_tx,
+    Error, GroveDb, TransactionArg,
+};
+
+/// Aux key marking the prefix registry as enabled
+#[cfg(feature = "full")]
+const ENABLED_AUX_KEY: &[u8] = b"prefix_registry_enabled";
+
+/// Prefix of the aux keys of prefix registry entries
+#[cfg(feature = "full")]
+const ENTRY_AUX_PREFIX: &[u8] = b"prefix_registry/";
+
+/// Aux key of the registry entry of the subtree at `path` in `db`
+#[cfg(feature = "full")]
+fn entry_aux_key(db: &RocksDbStorage, path: &[Vec<u8>]) -> Vec<u8> {
+    let mut aux_key = ENTRY_AUX_PREFIX.to_vec();
+    aux_key.extend_from_slice(&db.subtree_prefix(path.into()).unwrap());
+    aux_key
+}
+
+/// Encodes a path as its length prefixed segments
+#[cfg(feature = "full")]
+fn encode_path(path: &[Vec<u8>]) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    for segment in path {
+        encoded.extend_from_slice(&(segment.len() as u32).to_be_bytes());
+        encoded.extend_from_slice(segment);
+    }
+    encoded
+}
+
+/// Decodes a path encoded by `encode_path`
+#[cfg(feature = "full")]
+fn decode_path(mut encoded: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+    let corrupted = || Error::CorruptedData("malformed prefix registry entry".to_owned());
+    let mut path = Vec::new();
+    while !encoded.is_empty() {
+        let len_bytes: [u8; 4] = encoded
+            .get(..4)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(corrupted)?;
+        let len = u32::from_be_bytes(len_bytes) as usize;
+        let segment = encoded.get(4..4 + len).ok_or_else(corrupted)?;
+        path.push(segment.to_vec());
+        encoded = &encoded[4 + len..];
+    }
+    Ok(path)
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Enables the prefix registry, registering the subtrees already in the
+    /// grove. Subtrees created from now on are registered as they are
+    /// created.
+    pub fn enable_prefix_registry(&self) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        self.prefix_registry_enabled.store(true, Ordering::SeqCst);
+        cost_return_on_error!(&mut cost, self.put_aux(ENABLED_AUX_KEY, &[1], None, None));
+
+        let subtrees = cost_return_on_error!(
+            &mut cost,
+            self.find_subtrees(&SubtreePath::from(&[] as &[&[u8]]), None)
+        );
+        let batch = StorageBatch::new();
+        cost_return_on_error!(
+            &mut cost,
+            self.register_subtree_prefixes(subtrees, &batch, None)
+        );
+        self.db
+            .commit_multi_context_batch(batch, None)
+            .map_err(Into::into)
+            .add_cost(cost)
+    }
+
+    /// Disables the prefix registry and removes its entries
+    pub fn disable_prefix_registry(&self) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        if !self.is_prefix_registry_enabled() {
+            return Ok(()).wrap_with_cost(cost);
+        }
+        let entries = cost_return_on_error!(&mut cost, self.registered_prefixes(None));
+        self.prefix_registry_enabled.store(false, Ordering::SeqCst);
+        let batch = StorageBatch::new();
+        meta_storage_context_optional_tx!(self.db, Some(&batch), None, storage, {
+            let storage = storage.unwrap_add_cost(&mut cost);
+            cost_return_on_error!(
+                &mut cost,
+                storage
+                    .delete_aux(ENABLED_AUX_KEY, None)
+                    .map_err(Into::into)
+            );
+            for (_, path) in entries {
+                cost_return_on_error!(
+                    &mut cost,
+                    storage
+                        .delete_aux(entry_aux_key(&self.db, &path), None)
+                        .map_err(Into::into)
+                );
+            }
+        });
+        self.db
+            .commit_multi_context_batch(batch, None)
+            .map_err(Into::into)
+            .add_cost(cost)
+    }
+
+    /// Whether the prefix registry is enabled
+    pub fn is_prefix_registry_enabled(&self) -> bool {
+        self.prefix_registry_enabled.load(Ordering::SeqCst)
+    }
+
+    /// Returns the path of the subtree stored under `prefix`, if any.
+    /// Requires the prefix registry to be enabled.
+    pub fn path_of_prefix(
+        &self,
+        prefix: &[u8],
+        transaction: TransactionArg,
+    ) -> CostResult<Option<Vec<Vec<u8>>>, Error> {
+        let mut cost = OperationCost::default();
+
+        if !self.is_prefix_registry_enabled() {
+            return Err(Error::InvalidInput("prefix registry is not enabled")).wrap_with_cost(cost);
+        }
+
+        let mut aux_key = ENTRY_AUX_PREFIX.to_vec();
+        aux_key.extend_from_slice(prefix);
+        let encoded = cost_return_on_error!(&mut cost, self.get_aux(aux_key, transaction));
+        let path = cost_return_on_error_no_add!(
+            &cost,
+            encoded.map(|encoded| decode_path(&encoded)).transpose()
+        );
+        Ok(path).wrap_with_cost(cost)
+    }
+
+    /// Returns every registered prefix with the path of its subtree, ordered
+    /// by prefix. Requires the prefix registry to be enabled.
+    pub fn registered_prefixes(
+        &self,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<(Vec<u8>, Vec<Vec<u8>>)>, Error> {
+        let mut cost = OperationCost::default();
+
+        if !self.is_prefix_registry_enabled() {
+            return Err(Error::InvalidInput("prefix registry is not enabled")).wrap_with_cost(cost);
+        }
+
+        let mut entries = Vec::new();
+        meta_storage_context_optional_tx!(self.db, None, transaction, storage, {
+            let storage = storage.unwrap_add_cost(&mut cost);
+            let mut iter = storage.raw_iter_aux();
+            iter.seek(ENTRY_AUX_PREFIX).unwrap_add_cost(&mut cost);
+            while iter.valid().unwrap_add_cost(&mut cost) {
+                let Some(aux_key) = iter.key().unwrap_add_cost(&mut cost) else {
+                    break;
+                };
+                let Some(prefix) = aux_key.strip_prefix(ENTRY_AUX_PREFIX) else {
+                    break;
+                };
+                let prefix = prefix.to_vec();
+                let encoded = iter.value().unwrap_add_cost(&mut cost).unwrap_or_default();
+                let path = cost_return_on_error_no_add!(&cost, decode_path(encoded));
+                entries.push((prefix, path));
+                iter.next().unwrap_add_cost(&mut cost);
+            }
+        });
+        Ok(entries).wrap_with_cost(cost)
+    }
+
+    /// Loads whether the prefix registry was enabled
+    pub(crate) fn load_prefix_registry_state(&self) -> Result<(), Error> {
+        let enabled = self.get_aux(ENABLED_AUX_KEY, None).unwrap()?.is_some();
+        self.prefix_registry_enabled
+            .store(enabled, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Stages into `batch` the registry entries of the subtrees created at
+    /// the given paths, if the registry is enabled
+    pub(crate) fn register_subtree_prefixes(
+        &self,
+        created: impl IntoIterator<Item = Vec<Vec<u8>>>,
+        batch: &StorageBatch,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        if !self.is_prefix_registry_enabled() {
+            return Ok(()).wrap_with_cost(cost);
+        }
+
+        meta_storage_context_optional_tx!(self.db, Some(batch), transaction, storage, {
+            let storage = storage.unwrap_add_cost(&mut cost);
+            for path in created {
+                cost_return_on_error!(
+                    &mut cost,
+                    storage
+                        .put_aux(entry_aux_key(&self.db, &path), &encode_path(&path), None)
+                        .map_err(Into::into)
+                );
+            }
+        });
+
+        Ok(()).wrap_with_cost(cost)
+    }
+
+    /// Stages into `batch` the removal of the registry entries of the subtrees
+    /// at the given paths and of the subtrees below them, if the registry is
+    /// enabled. Must be called before the deletions are committed.
+    pub(crate) fn unregister_subtree_prefixes(
+        &self,
+        deleted: impl IntoIterator<Item = Vec<Vec<u8>>>,
+        batch: &StorageBatch,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        if !self.is_prefix_registry_enabled() {
+            return Ok(()).wrap_with_cost(cost);
+        }
+
+        let mut removed = Vec::new();
+        for path in deleted {
+            let exists = cost_return_on_error!(
+                &mut cost,
+                self.check_subtree_exists(path.as_slice(), transaction)
+            );
+            if exists {
+                let subtree_path: SubtreePath<Vec<u8>> = path.as_slice().into();
+                removed.extend(cost_return_on_error!(
+                    &mut cost,
+                    self.find_subtrees(&subtree_path, transaction)
+                ));
+            }
+        }
+
+        meta_storage_context_optional_tx!(self.db, Some(batch), transaction, storage, {
+            let storage = storage.unwrap_add_cost(&mut cost);
+            for path in removed {
+                cost_return_on_error!(
+                    &mut cost,
+                    storage
+                        .delete_aux(entry_aux_key(&self.db, &path), None)
+                        .map_err(Into::into)
+                );
+            }
+        });
+
+        Ok(()).wrap_with_cost(cost)
+    }
+
+    /// Stages into `batch` the registry changes of the subtrees created and
+    /// deleted by the operations of a batch
+    pub(crate) fn record_prefix_registry(
+        &self,
+        ops: &[GroveDbOp],
+        batch: &StorageBatch,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        if !self.is_prefix_registry_enabled() {
+            return Ok(()).wrap_with_cost(cost);
+        }
+
+        let subtree_path = |op: &GroveDbOp| {
+            let mut path = op.path.to_path();
+            path.push(op.key.get_key_clone());
+            path
+        };
+        let created = ops.iter().filter_map(|op| match &op.op {
+            Op::Insert { element } | Op::Replace { element } if element.is_tree() => {
+                Some(subtree_path(op))
+            }
+            _ => None,
+        });
+        let deleted = ops.iter().filter_map(|op| match &op.op {
+            Op::DeleteTree | Op::DeleteSumTree => Some(subtree_path(op)),
+            _ => None,
+        });
+        cost_return_on_error!(
+            &mut cost,
+            self.unregister_subtree_prefixes(deleted, batch, transaction)
+        );
+        self.register_subtree_prefixes(created, batch, transaction)
+            .add_cost(cost)
+    }
+}
@@ -27,10 +27,14 @@
 // DEALINGS IN THE SOFTWARE.
 
 //! Proof operations
+//!
+//! Proofs are verified by `grovedb-verify`, whose proof modules are
+//! re-exported here.
 
 #[cfg(feature = "full")]
 mod generate;
-#[cfg(any(feature = "full", feature = "verify"))]
-pub mod util;
-#[cfg(any(feature = "full", feature = "verify"))]
 pub mod verify;
+
+#[cfg(feature = "full")]
+use grovedb_verify::proof::compact;
+pub use grovedb_verify::proof::{display, util};
@@ -39,27 +39,40 @@ use grovedb_costs::{
 };
 #[cfg(feature = "full")]
 use grovedb_merk::{
-    proofs::{encode_into, Node, Op},
+    proofs::{encode_into, query::ElementKindAction, Node, Op},
     tree::value_hash,
     KVIterator, Merk, ProofWithoutEncodingResult,
 };
 use grovedb_path::SubtreePath;
 #[cfg(feature = "full")]
-use grovedb_storage::StorageContext;
+use grovedb_storage::{Storage, StorageContext};
+#[cfg(feature = "full")]
+use grovedb_verify::aggregate::matches_whole_subtree;
 
 #[cfg(feature = "full")]
 use crate::element::helpers::raw_decode;
 #[cfg(feature = "full")]
 use crate::{
+    element::ElementGetExt,
     operations::proof::util::{
-        reduce_limit_and_offset_by, write_to_vec, ProofTokenType, EMPTY_TREE_HASH,
+        deduplicate_merk_proofs, reduce_limit_and_offset_by, write_to_vec, ProofTokenType,
+        EMPTY_TREE_HASH,
     },
     reference_path::path_from_reference_path_type,
-    Element, Error, GroveDb, PathQuery, Query,
+    util::root_merk_optional_tx,
+    Element, Error, GroveDb, PathQuery, Query, Transaction, TransactionArg,
 };
 use crate::{
-    operations::proof::util::{write_slice_of_slice_to_slice, write_slice_to_vec},
-    versioning::{prepend_version_to_bytes, PROOF_VERSION},
+    operations::{
+        aggregate::Aggregate,
+        proof::{
+            compact::compact_merk_proofs,
+            util::{write_slice_of_slice_to_slice, write_slice_to_vec},
+        },
+    },
+    versioning::{
+        prepend_version_to_bytes, read_and_consume_proof_version, ProofVersion, PROOF_VERSION,
+    },
 };
 
 #[cfg(feature = "full")]
@@ -96,7 +109,28 @@ impl GroveDb {
     /// Proofs generated with this can only be verified by the path query used
     /// to generate them.
     pub fn prove_query(&self, query: &PathQuery) -> CostResult<Vec<u8>, Error> {
-        self.prove_internal(query, false)
+        self.prove_internal(query, false, None)
+    }
+
+    /// Generate a minimalistic proof for a given path query in the given
+    /// proof version, `ProofVersion::V2` proofs are usually smaller but can
+    /// only be verified by versions of GroveDB knowing that encoding
+    pub fn prove_query_with_version(
+        &self,
+        query: &PathQuery,
+        version: ProofVersion,
+    ) -> CostResult<Vec<u8>, Error> {
+        let mut cost = OperationCost::default();
+
+        let proof = cost_return_on_error!(&mut cost, self.prove_internal(query, false, None));
+        if version == ProofVersion::V1 {
+            return Ok(proof).wrap_with_cost(cost);
+        }
+
+        let (_, tokens) =
+            cost_return_on_error_no_add!(&cost, read_and_consume_proof_version(proof.as_slice()));
+        let compact_tokens = cost_return_on_error_no_add!(&cost, compact_merk_proofs(tokens));
+        prepend_version_to_bytes(compact_tokens, version.into()).wrap_with_cost(cost)
     }
 
     /// Generate a verbose proof for a given path query
@@ -107,23 +141,172 @@ impl GroveDb {
         //      when using a path query that has a limit and offset value,
         //      to get the expected behaviour, you need to know exactly
         //      how the proving internals work and how your state looks.
-        self.prove_internal(query, true)
+        self.prove_internal(query, true, None)
+    }
+
+    /// Generate a proof of the aggregated sum of the sum tree at the given
+    /// path. The proof covers the sum tree element in the parent subtree,
+    /// which carries the sum, so none of the summed items are included.
+    pub fn prove_sum_of_subtree(&self, path: Vec<Vec<u8>>) -> CostResult<Vec<u8>, Error> {
+        let query = cost_return_on_error_default!(PathQuery::for_subtree_element(path));
+        self.prove_query(&query)
+    }
+
+    /// Generate a proof of the number of elements in the count tree at the
+    /// given path. Like sum proofs, only the count tree element in the parent
+    /// subtree is proven, so the cost doesn't grow with the count.
+    pub fn prove_count_in_subtree(&self, path: Vec<Vec<u8>>) -> CostResult<Vec<u8>, Error> {
+        let query = cost_return_on_error_default!(PathQuery::for_subtree_element(path));
+        self.prove_query(&query)
+    }
+
+    /// Generate a proof of the aggregate of the aggregate tree at the given
+    /// path. The aggregate is committed to by the aggregate tree element in
+    /// the parent subtree, which is the only element proven.
+    pub fn prove_aggregate_of_subtree(&self, path: Vec<Vec<u8>>) -> CostResult<Vec<u8>, Error> {
+        let query = cost_return_on_error_default!(PathQuery::for_subtree_element(path));
+        self.prove_query(&query)
+    }
+
+    /// Generate a proof of `aggregate` over the elements matched by the
+    /// given path query. The count of all the elements of a count tree is
+    /// proven with the count tree element alone, so the proof size doesn't
+    /// depend on the count. Other counts are proven with a keys only proof:
+    /// the counted elements aren't included, but their keys are, so the proof
+    /// grows linearly with the count.
+    pub fn prove_aggregate(
+        &self,
+        query: &PathQuery,
+        aggregate: Aggregate,
+    ) -> CostResult<Vec<u8>, Error> {
+        let mut cost = OperationCost::default();
+
+        match aggregate {
+            Aggregate::Count => {
+                if let Some((key, parent_path)) = query
+                    .path
+                    .split_last()
+                    .filter(|_| matches_whole_subtree(query))
+                {
+                    // a missing path is proven absent by the keys only proof
+                    let element = self
+                        .get_raw_optional(parent_path.into(), key, None)
+                        .unwrap_add_cost(&mut cost);
+                    if let Ok(Some(Element::CountTree(..))) = element {
+                        return self
+                            .prove_count_in_subtree(query.path.clone())
+                            .add_cost(cost);
+                    }
+                }
+                self.prove_query(&query.clone().with_keys_only())
+                    .add_cost(cost)
+            }
+            Aggregate::Sum => self.prove_query(query),
+        }
+    }
+
+    /// Generate a proof that the subtree at the given path is empty, or that
+    /// it isn't. The proof covers the tree element in the parent subtree,
+    /// whose hash commits to the root hash of the subtree.
+    pub fn prove_is_empty_tree(&self, path: Vec<Vec<u8>>) -> CostResult<Vec<u8>, Error> {
+        let query = cost_return_on_error_default!(PathQuery::for_subtree_element(path));
+        self.prove_query(&query)
+    }
+
+    /// Generate a proof that nothing exists at the given path. When the
+    /// absence occurs above the last segment, the proof goes down to the
+    /// deepest existing ancestor and shows the next segment is missing there.
+    pub fn prove_path_absence(&self, path: Vec<Vec<u8>>) -> CostResult<Vec<u8>, Error> {
+        let query = cost_return_on_error_default!(PathQuery::for_subtree_element(path));
+        self.prove_query(&query)
+    }
+
+    /// Generate a proof that no element exists under the given key of the
+    /// subtree at the given path. The proof holds the neighbours of the key,
+    /// or shows the path itself is absent.
+    pub fn prove_path_key_absence(
+        &self,
+        path: Vec<Vec<u8>>,
+        key: &[u8],
+    ) -> CostResult<Vec<u8>, Error> {
+        self.prove_query(&PathQuery::new_single_key(path, key.to_vec()))
+    }
+
+    /// Generate a proof of the element under the given key of the root leaf,
+    /// or of its absence. This is a bare merk proof of the root tree, smaller
+    /// than a path query proof as it carries no version and no layers.
+    pub fn prove_root_leaf(&self, key: &[u8]) -> CostResult<Vec<u8>, Error> {
+        let mut cost = OperationCost::default();
+        let transaction: TransactionArg = None;
+
+        root_merk_optional_tx!(&mut cost, self.db, None, transaction, subtree, {
+            let proof_result = cost_return_on_error!(
+                &mut cost,
+                subtree
+                    .prove_without_encoding(Query::new_single_key(key.to_vec()), None, None)
+                    .map_err(Error::MerkError)
+            );
+
+            let mut proof_bytes = Vec::with_capacity(128);
+            encode_into(proof_result.proof.iter(), &mut proof_bytes);
+            Ok(proof_bytes).wrap_with_cost(cost)
+        })
     }
 
-    /// Generates a verbose or non verbose proof based on a bool
-    fn prove_internal(&self, query: &PathQuery, is_verbose: bool) -> CostResult<Vec<u8>, Error> {
+    /// Generates a verbose or non verbose proof based on a bool, reading
+    /// through `transaction` if given or a snapshot transaction of its own
+    /// otherwise, so all the layers are proven as of the same state
+    pub(crate) fn prove_internal(
+        &self,
+        query: &PathQuery,
+        is_verbose: bool,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<u8>, Error> {
         let mut cost = OperationCost::default();
 
+        let own_transaction;
+        let transaction = match transaction {
+            Some(transaction) => transaction,
+            None if self.is_secondary() => {
+                return Err(Error::NotSupported(
+                    "proofs can't be generated on a secondary GroveDB",
+                ))
+                .wrap_with_cost(cost)
+            }
+            None => {
+                own_transaction = cost_return_on_error_no_add!(
+                    &cost,
+                    self.db
+                        .start_snapshot_transaction()
+                        .map(Transaction::new)
+                        .map_err(Error::from)
+                );
+                &own_transaction
+            }
+        };
+
         let mut proof_result =
             cost_return_on_error_default!(prepend_version_to_bytes(vec![], PROOF_VERSION));
+        let version_length = proof_result.len();
 
         let mut limit: Option<u16> = query.query.limit;
         let mut offset: Option<u16> = query.query.offset;
 
+        // the merk proofs of leaf subtrees apply the limit and offset to every
+        // element, including those skipped for their kind
+        if query.query.query.has_element_kind_actions()
+            && (query.is_keys_only() || limit.is_some() || offset.is_some())
+        {
+            return Err(Error::NotSupported(
+                "element kind actions can't be proved with a limit, an offset or keys only",
+            ))
+            .wrap_with_cost(cost);
+        }
+
         let path_slices = query.path.iter().map(|x| x.as_slice()).collect::<Vec<_>>();
 
         let subtree_exists = self
-            .check_subtree_exists_path_not_found(path_slices.as_slice().into(), None)
+            .check_subtree_exists_path_not_found(path_slices.as_slice().into(), Some(transaction))
             .unwrap_add_cost(&mut cost);
 
         // if the subtree at the given path doesn't exists, prove that this path
@@ -139,7 +322,8 @@ impl GroveDb {
                     self.generate_and_store_absent_path_proof(
                         &path_slices,
                         &mut proof_result,
-                        is_verbose
+                        is_verbose,
+                        transaction
                     )
                 );
                 // return the absence proof no need to continue proof generation
@@ -156,23 +340,48 @@ impl GroveDb {
             );
         }
 
+        if query.is_keys_only() {
+            cost_return_on_error!(
+                &mut cost,
+                self.prove_keys_only(
+                    &mut proof_result,
+                    path_slices.clone(),
+                    query,
+                    is_verbose,
+                    transaction
+                )
+            );
+        } else {
+            cost_return_on_error!(
+                &mut cost,
+                self.prove_subqueries(
+                    &mut proof_result,
+                    path_slices.clone(),
+                    query,
+                    &mut limit,
+                    &mut offset,
+                    true,
+                    is_verbose,
+                    transaction
+                )
+            );
+        }
         cost_return_on_error!(
             &mut cost,
-            self.prove_subqueries(
-                &mut proof_result,
-                path_slices.clone(),
-                query,
-                &mut limit,
-                &mut offset,
-                true,
-                is_verbose
-            )
-        );
-        cost_return_on_error!(
-            &mut cost,
-            self.prove_path(&mut proof_result, path_slices, is_verbose)
+            self.prove_path(&mut proof_result, path_slices, is_verbose, transaction)
         );
 
+        // layers shared by several paths of a merged query are proven more
+        // than once, keep the first proof of each and refer to it afterwards
+        if !is_verbose {
+            let tokens = cost_return_on_error_no_add!(
+                &cost,
+                deduplicate_merk_proofs(&proof_result[version_length..])
+            );
+            proof_result.truncate(version_length);
+            proof_result.extend(tokens);
+        }
+
         Ok(proof_result).wrap_with_cost(cost)
     }
 
@@ -187,13 +396,14 @@ impl GroveDb {
         current_offset: &mut Option<u16>,
         is_first_call: bool,
         is_verbose: bool,
+        transaction: &Transaction,
     ) -> CostResult<(), Error> {
         let mut cost = OperationCost::default();
         let mut to_add_to_result_set: u16 = 0;
 
         let subtree = cost_return_on_error!(
             &mut cost,
-            self.open_non_transactional_merk_at_path(path.as_slice().into(), None)
+            self.open_transactional_merk_at_path(path.as_slice().into(), transaction, None)
         );
         if subtree.root_hash().unwrap_add_cost(&mut cost) == EMPTY_TREE_HASH {
             cost_return_on_error_no_add!(
@@ -216,7 +426,8 @@ impl GroveDb {
                         ProofTokenType::SizedMerk,
                         proofs,
                         is_verbose,
-                        path.iter().last().unwrap_or(&(&[][..]))
+                        path.iter().last().unwrap_or(&(&[][..])),
+                        transaction
                     )
                 );
             }
@@ -232,10 +443,18 @@ impl GroveDb {
             let mut encountered_absence = false;
 
             let element = cost_return_on_error_no_add!(&cost, raw_decode(&value_bytes));
-            match element {
-                Element::Tree(root_key, _) | Element::SumTree(root_key, ..) => {
-                    let (mut subquery_path, subquery_value) =
-                        Element::subquery_paths_and_value_for_sized_query(&query.query, &key);
+            match &element {
+                Element::Tree(root_key, _)
+                | Element::SumTree(root_key, ..)
+                | Element::CountTree(root_key, ..)
+                | Element::BigSumTree(root_key, ..)
+                | Element::AggregateTree(root_key, ..) => {
+                    let Some((mut subquery_path, subquery_value)) =
+                        Element::subquery_paths_and_value_for_element(&query.query, &key, &element)
+                    else {
+                        // the query skips this tree
+                        continue;
+                    };
 
                     if subquery_value.is_none() && subquery_path.is_none() {
                         // this element should be added to the result set
@@ -261,7 +480,8 @@ impl GroveDb {
                                 ProofTokenType::Merk,
                                 proofs,
                                 is_verbose,
-                                path.iter().last().unwrap_or(&Default::default())
+                                path.iter().last().unwrap_or(&Default::default()),
+                                transaction
                             )
                         );
                     }
@@ -276,9 +496,10 @@ impl GroveDb {
                             for subkey in subquery_path.iter() {
                                 let inner_subtree = cost_return_on_error!(
                                     &mut cost,
-                                    self.open_non_transactional_merk_at_path(
+                                    self.open_transactional_merk_at_path(
                                         new_path.as_slice().into(),
-                                        None,
+                                        transaction,
+                                        None
                                     )
                                 );
 
@@ -295,7 +516,8 @@ impl GroveDb {
                                         ProofTokenType::Merk,
                                         proofs,
                                         is_verbose,
-                                        new_path.iter().last().unwrap_or(&Default::default())
+                                        new_path.iter().last().unwrap_or(&Default::default()),
+                                        transaction
                                     )
                                 );
 
@@ -304,7 +526,7 @@ impl GroveDb {
                                 if self
                                     .check_subtree_exists_path_not_found(
                                         new_path.as_slice().into(),
-                                        None,
+                                        Some(transaction),
                                     )
                                     .unwrap_add_cost(&mut cost)
                                     .is_err()
@@ -330,8 +552,9 @@ impl GroveDb {
                         for subkey in subquery_path.iter() {
                             let inner_subtree = cost_return_on_error!(
                                 &mut cost,
-                                self.open_non_transactional_merk_at_path(
+                                self.open_transactional_merk_at_path(
                                     new_path.as_slice().into(),
+                                    transaction,
                                     None
                                 )
                             );
@@ -349,7 +572,8 @@ impl GroveDb {
                                     ProofTokenType::Merk,
                                     proofs,
                                     is_verbose,
-                                    new_path.iter().last().unwrap_or(&Default::default())
+                                    new_path.iter().last().unwrap_or(&Default::default()),
+                                    transaction
                                 )
                             );
 
@@ -360,7 +584,7 @@ impl GroveDb {
                             if self
                                 .check_subtree_exists_path_not_found(
                                     new_path.as_slice().into(),
-                                    None,
+                                    Some(transaction),
                                 )
                                 .unwrap_add_cost(&mut cost)
                                 .is_err()
@@ -387,7 +611,10 @@ impl GroveDb {
                     let new_path_query = PathQuery::new_unsized(new_path_owned, query.unwrap());
 
                     if self
-                        .check_subtree_exists_path_not_found(new_path.as_slice().into(), None)
+                        .check_subtree_exists_path_not_found(
+                            new_path.as_slice().into(),
+                            Some(transaction),
+                        )
                         .unwrap_add_cost(&mut cost)
                         .is_err()
                     {
@@ -404,6 +631,7 @@ impl GroveDb {
                             current_offset,
                             false,
                             is_verbose,
+                            transaction,
                         )
                     );
 
@@ -412,7 +640,11 @@ impl GroveDb {
                     }
                 }
                 _ => {
-                    to_add_to_result_set += 1;
+                    if query.query.query.element_kind_action(&key, element.kind())
+                        != Some(&ElementKindAction::Skip)
+                    {
+                        to_add_to_result_set += 1;
+                    }
                 }
             }
         }
@@ -430,7 +662,8 @@ impl GroveDb {
                     ProofTokenType::SizedMerk,
                     proofs,
                     is_verbose,
-                    path.iter().last().unwrap_or(&Default::default())
+                    path.iter().last().unwrap_or(&Default::default()),
+                    transaction
                 )
             );
 
@@ -444,6 +677,69 @@ impl GroveDb {
         Ok(()).wrap_with_cost(cost)
     }
 
+    /// Proves the keys matched by a keys only path query, the matched nodes
+    /// carrying their value hash instead of their value
+    fn prove_keys_only(
+        &self,
+        proofs: &mut Vec<u8>,
+        path: Vec<&[u8]>,
+        query: &PathQuery,
+        is_verbose: bool,
+        transaction: &Transaction,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        if query.query.query.has_subquery() {
+            return Err(Error::NotSupported(
+                "keys only path queries can't have subqueries",
+            ))
+            .wrap_with_cost(cost);
+        }
+
+        let subtree = cost_return_on_error!(
+            &mut cost,
+            self.open_transactional_merk_at_path(path.as_slice().into(), transaction, None)
+        );
+        if subtree.root_hash().unwrap_add_cost(&mut cost) == EMPTY_TREE_HASH {
+            cost_return_on_error_no_add!(
+                &cost,
+                write_to_vec(proofs, &[ProofTokenType::EmptyTree.into()])
+            );
+            return Ok(()).wrap_with_cost(cost);
+        }
+
+        let mut proof_result = cost_return_on_error!(
+            &mut cost,
+            subtree
+                .prove_without_encoding(
+                    query.query.query.clone(),
+                    query.query.limit,
+                    query.query.offset
+                )
+                .map_err(Error::MerkError)
+        );
+        for op in proof_result.proof.iter_mut() {
+            if let Op::Push(node) | Op::PushInverted(node) = op {
+                if let Node::KVValueHash(key, _, value_hash) = node {
+                    *node = Node::KVDigest(std::mem::take(key), *value_hash);
+                }
+            }
+        }
+
+        cost_return_on_error_no_add!(
+            &cost,
+            Self::store_merk_proof(
+                &proof_result,
+                ProofTokenType::SizedMerk,
+                proofs,
+                is_verbose,
+                path.last().copied().unwrap_or_default()
+            )
+        );
+
+        Ok(()).wrap_with_cost(cost)
+    }
+
     /// Given a path, construct and append a set of proofs that shows there is
     /// a valid path from the root of the db to that point.
     fn prove_path(
@@ -451,6 +747,7 @@ impl GroveDb {
         proof_result: &mut Vec<u8>,
         path_slices: Vec<&[u8]>,
         is_verbose: bool,
+        transaction: &Transaction,
     ) -> CostResult<(), Error> {
         let mut cost = OperationCost::default();
 
@@ -459,7 +756,7 @@ impl GroveDb {
         while let Some((key, path_slice)) = split_path {
             let subtree = cost_return_on_error!(
                 &mut cost,
-                self.open_non_transactional_merk_at_path(path_slice.into(), None)
+                self.open_transactional_merk_at_path(path_slice.into(), transaction, None)
             );
             let mut query = Query::new();
             query.insert_key(key.to_vec());
@@ -474,7 +771,8 @@ impl GroveDb {
                     ProofTokenType::Merk,
                     proof_result,
                     is_verbose,
-                    path_slice.iter().last().unwrap_or(&Default::default())
+                    path_slice.iter().last().unwrap_or(&Default::default()),
+                    transaction
                 )
             );
             split_path = path_slice.split_last();
@@ -494,6 +792,7 @@ impl GroveDb {
         proofs: &mut Vec<u8>,
         is_verbose: bool,
         key: &[u8],
+        transaction: &Transaction,
     ) -> CostResult<(Option<u16>, Option<u16>), Error>
     where
         S: StorageContext<'a> + 'a,
@@ -509,27 +808,46 @@ impl GroveDb {
 
         let mut cost = OperationCost::default();
 
-        let mut proof_result = subtree
-            .prove_without_encoding(query.clone(), limit_offset.0, limit_offset.1)
-            .unwrap()
-            .expect("should generate proof");
+        let mut proof_result = cost_return_on_error!(
+            &mut cost,
+            subtree
+                .prove_without_encoding(query.clone(), limit_offset.0, limit_offset.1)
+                .map_err(Error::MerkError)
+        );
 
-        cost_return_on_error!(&mut cost, self.post_process_proof(path, &mut proof_result));
+        cost_return_on_error!(
+            &mut cost,
+            self.post_process_proof(path, &mut proof_result, transaction)
+        );
 
+        cost_return_on_error_no_add!(
+            &cost,
+            Self::store_merk_proof(&proof_result, proof_token_type, proofs, is_verbose, key)
+        );
+
+        Ok((proof_result.limit, proof_result.offset)).wrap_with_cost(cost)
+    }
+
+    /// Encodes a merk proof and appends it to a proof list
+    fn store_merk_proof(
+        proof_result: &ProofWithoutEncodingResult,
+        proof_token_type: ProofTokenType,
+        proofs: &mut Vec<u8>,
+        is_verbose: bool,
+        key: &[u8],
+    ) -> Result<(), Error> {
         let mut proof_bytes = Vec::with_capacity(128);
         encode_into(proof_result.proof.iter(), &mut proof_bytes);
 
-        cost_return_on_error_no_add!(&cost, write_to_vec(proofs, &[proof_token_type.into()]));
+        write_to_vec(proofs, &[proof_token_type.into()])?;
 
         // if is verbose, write the key
         if is_verbose {
-            cost_return_on_error_no_add!(&cost, write_slice_to_vec(proofs, key));
+            write_slice_to_vec(proofs, key)?;
         }
 
         // write the merk proof
-        cost_return_on_error_no_add!(&cost, write_slice_to_vec(proofs, &proof_bytes));
-
-        Ok((proof_result.limit, proof_result.offset)).wrap_with_cost(cost)
+        write_slice_to_vec(proofs, &proof_bytes)
     }
 
     /// Serializes a path and add it to the proof vector
@@ -554,6 +872,7 @@ impl GroveDb {
         path_slices: &[&[u8]],
         proof_result: &mut Vec<u8>,
         is_verbose: bool,
+        transaction: &Transaction,
     ) -> CostResult<(), Error> {
         let mut cost = OperationCost::default();
 
@@ -566,13 +885,28 @@ impl GroveDb {
         let mut split_path = path_slices.split_first();
         while let Some((key, path_slice)) = split_path {
             let subtree = self
-                .open_non_transactional_merk_at_path(current_path.as_slice().into(), None)
+                .open_transactional_merk_at_path(current_path.as_slice().into(), transaction, None)
                 .unwrap_add_cost(&mut cost);
 
             if subtree.is_err() {
                 break;
             }
 
+            // nothing can be proven in an empty tree, the next key is absent from it
+            if subtree
+                .as_ref()
+                .expect("confirmed not error above")
+                .root_hash()
+                .unwrap_add_cost(&mut cost)
+                == EMPTY_TREE_HASH
+            {
+                cost_return_on_error_no_add!(
+                    &cost,
+                    write_to_vec(proof_result, &[ProofTokenType::EmptyTree.into()])
+                );
+                break;
+            }
+
             let has_item = Element::get(
                 subtree.as_ref().expect("confirmed not error above"),
                 key,
@@ -592,7 +926,8 @@ impl GroveDb {
                     ProofTokenType::Merk,
                     proof_result,
                     is_verbose,
-                    current_path.iter().last().unwrap_or(&(&[][..]))
+                    current_path.iter().last().unwrap_or(&(&[][..])),
+                    transaction
                 )
             );
 
@@ -616,6 +951,7 @@ impl GroveDb {
         &self,
         path: &SubtreePath<B>,
         proof_result: &mut ProofWithoutEncodingResult,
+        transaction: &Transaction,
     ) -> CostResult<(), Error> {
         let mut cost = OperationCost::default();
 
@@ -625,7 +961,10 @@ impl GroveDb {
                     Node::KV(key, value) | Node::KVValueHash(key, value, ..) => {
                         let elem = Element::deserialize(value);
                         match elem {
-                            Ok(Element::Reference(reference_path, ..)) => {
+                            Ok(
+                                Element::Reference(reference_path, ..)
+                                | Element::BidirectionalReference(reference_path, ..),
+                            ) => {
                                 let absolute_path = cost_return_on_error!(
                                     &mut cost,
                                     path_from_reference_path_type(
@@ -641,7 +980,7 @@ impl GroveDb {
                                     self.follow_reference(
                                         absolute_path.as_slice().into(),
                                         true,
-                                        None
+                                        Some(transaction)
                                     )
                                 );
 
@@ -680,7 +1019,9 @@ mod tests {
     use grovedb_storage::StorageBatch;
 
     use crate::{
-        operations::proof::util::{ProofReader, ProofTokenType},
+        operations::proof::util::{
+            deduplicate_merk_proofs, write_slice_to_vec, write_to_vec, ProofReader, ProofTokenType,
+        },
         tests::{common::EMPTY_PATH, make_deep_tree, TEST_LEAF},
         GroveDb,
     };
@@ -708,6 +1049,7 @@ mod tests {
         query.insert_all();
 
         let batch = StorageBatch::new();
+        let transaction = db.start_transaction().unwrap();
 
         let merk = db
             .open_non_transactional_merk_at_path(
@@ -728,6 +1070,7 @@ mod tests {
             &mut proof,
             true,
             b"innertree",
+            &transaction,
         )
         .unwrap()
         .unwrap();
@@ -762,6 +1105,7 @@ mod tests {
             &mut proof,
             true,
             &[],
+            &transaction,
         )
         .unwrap()
         .unwrap();
@@ -793,6 +1137,7 @@ mod tests {
         let path = vec![TEST_LEAF, b"innertree"];
 
         let batch = StorageBatch::new();
+        let transaction = db.start_transaction().unwrap();
 
         let merk = db
             .open_non_transactional_merk_at_path(path.as_slice().into(), Some(&batch))
@@ -808,6 +1153,7 @@ mod tests {
             &mut proofs,
             true,
             path.iter().last().unwrap_or(&(&[][..])),
+            &transaction,
         )
         .unwrap()
         .unwrap();
@@ -828,6 +1174,7 @@ mod tests {
             &mut proofs,
             true,
             path.iter().last().unwrap_or(&(&[][..])),
+            &transaction,
         )
         .unwrap()
         .unwrap();
@@ -848,6 +1195,7 @@ mod tests {
             &mut proofs,
             true,
             path.iter().last().unwrap_or(&(&[][..])),
+            &transaction,
         )
         .unwrap()
         .unwrap();
@@ -902,4 +1250,37 @@ mod tests {
         let reading_result = proof_reader.read_verbose_proof_at_key(b"unknown_key");
         assert!(reading_result.is_err())
     }
+
+    #[test]
+    fn test_deduplicate_merk_proofs() {
+        let tokens = [
+            (ProofTokenType::Merk, b"layer".as_slice()),
+            (ProofTokenType::SizedMerk, b"leaf".as_slice()),
+            (ProofTokenType::Merk, b"layer".as_slice()),
+            (ProofTokenType::EmptyTree, b"".as_slice()),
+            (ProofTokenType::SizedMerk, b"leaf".as_slice()),
+            (ProofTokenType::Merk, b"other layer".as_slice()),
+        ];
+        let mut proof = vec![];
+        for (proof_token_type, merk_proof) in tokens {
+            write_to_vec(&mut proof, &[proof_token_type.into()]).unwrap();
+            if proof_token_type != ProofTokenType::EmptyTree {
+                write_slice_to_vec(&mut proof, merk_proof).unwrap();
+            }
+        }
+
+        let deduplicated = deduplicate_merk_proofs(&proof).unwrap();
+        assert!(deduplicated.len() < proof.len());
+
+        // the deduplicated proof reads back as the original one
+        let mut original_reader = ProofReader::new(&proof);
+        let mut reader = ProofReader::new(&deduplicated);
+        while !original_reader.is_empty() {
+            assert_eq!(
+                reader.read_proof().unwrap(),
+                original_reader.read_proof().unwrap()
+            );
+        }
+        assert!(reader.is_empty());
+    }
 }
@@ -0,0 +1,545 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Rename key operations
+
+#[cfg(feature = "full")]
+use std::collections::HashMap;
+
+#[cfg(feature = "full")]
+use grovedb_costs::{
+    cost_return_on_error, cost_return_on_error_no_add, CostResult, CostsExt, OperationCost,
+};
+#[cfg(feature = "full")]
+use grovedb_merk::{Error as MerkError, Merk};
+use grovedb_path::SubtreePath;
+#[cfg(feature = "full")]
+use grovedb_storage::{
+    rocksdb_storage::{PrefixedRocksDbStorageContext, PrefixedRocksDbTransactionContext},
+    RawIterator, Storage, StorageBatch, StorageContext,
+};
+
+#[cfg(feature = "full")]
+use crate::{
+    element::{ElementDeleteExt, ElementGetExt, ElementInsertExt},
+    metrics::OperationKind,
+    operations::backlinks::is_backlink_meta_key,
+    reference_path::path_from_reference_path_type,
+    transaction::storage_transaction,
+    Element, Error, GroveDb, Hash, Transaction, TransactionArg,
+};
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Moves the element stored under `old_key` to `new_key` within the same
+    /// subtree. When the element is a tree, the storage of the whole subtree
+    /// and its descendants is moved to the prefixes derived from the new key.
+    /// Both keys are changed within a single Merk batch, so the parent and
+    /// its ancestors are rehashed and charged once, as opposed to a delete
+    /// followed by an insert.
+    ///
+    /// The backlinks of the renamed references, and of the references of a
+    /// renamed subtree, follow them. References pointing into a renamed
+    /// subtree are not updated and lose their backlinks.
+    pub fn rename_key<'b, B, P>(
+        &self,
+        path: P,
+        old_key: &[u8],
+        new_key: &[u8],
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        let element =
+            cost_return_on_error!(&mut cost, self.get_raw(path.clone(), old_key, transaction));
+        if old_key == new_key {
+            return Ok(()).wrap_with_cost(cost);
+        }
+        if cost_return_on_error!(&mut cost, self.has_raw(path.clone(), new_key, transaction)) {
+            return Err(Error::OverrideNotAllowed(
+                "renaming not allowed to override an existing element",
+            ))
+            .wrap_with_cost(cost);
+        }
+
+        let batch = StorageBatch::new();
+        let renamed = if let Some(transaction) = transaction {
+            self.rename_key_on_transaction(
+                path.clone(),
+                old_key,
+                new_key,
+                element.clone(),
+                transaction,
+                &batch,
+            )
+        } else {
+            self.rename_key_without_transaction(
+                path.clone(),
+                old_key,
+                new_key,
+                element.clone(),
+                &batch,
+            )
+        };
+        cost_return_on_error!(&mut cost, renamed);
+        cost_return_on_error!(
+            &mut cost,
+            self.record_renamed_backlinks(
+                &path.to_vec(),
+                old_key,
+                new_key,
+                &element,
+                &batch,
+                transaction
+            )
+        );
+        cost_return_on_error!(
+            &mut cost,
+            self.record_renamed_reverse_key_index(
+                &path.to_vec(),
+                old_key,
+                new_key,
+                &element,
+                &batch,
+                transaction
+            )
+        );
+        #[cfg(feature = "audit_log")]
+        {
+            let entries = cost_return_on_error_no_add!(
+                &cost,
+                crate::operations::audit_log::rename_entries(
+                    path.to_vec(),
+                    old_key,
+                    new_key,
+                    &element
+                )
+            );
+            let recorded = self.record_audit_log(entries, &mut cost, &batch, transaction);
+            cost_return_on_error_no_add!(&cost, recorded);
+        }
+
+        self.db
+            .commit_multi_context_batch(batch, storage_transaction(transaction))
+            .map_err(Into::into)
+            .map_ok(|_| {
+                self.metrics
+                    .record(OperationKind::Write, &path, Some(old_key))
+            })
+            .add_cost(cost)
+    }
+
+    fn rename_key_on_transaction<B: AsRef<[u8]>>(
+        &self,
+        path: SubtreePath<B>,
+        old_key: &[u8],
+        new_key: &[u8],
+        element: Element,
+        transaction: &Transaction,
+        batch: &StorageBatch,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+        let parent_path = path.to_vec();
+
+        let mut subtree_root_hash = None;
+        if element.is_tree() {
+            let old_subtree_path = path.derive_owned_with_child(old_key);
+            let old_subtree_path_ref = SubtreePath::from(&old_subtree_path);
+            let old_subtree = cost_return_on_error!(
+                &mut cost,
+                self.open_transactional_merk_at_path(
+                    old_subtree_path_ref.clone(),
+                    transaction,
+                    Some(batch)
+                )
+            );
+            subtree_root_hash = Some(old_subtree.root_hash().unwrap_add_cost(&mut cost));
+
+            let subtrees_paths = cost_return_on_error!(
+                &mut cost,
+                self.find_subtrees(&old_subtree_path_ref, Some(transaction))
+            );
+            let new_subtrees_paths: Vec<_> = subtrees_paths
+                .iter()
+                .map(|old_path| renamed_subtree_path(old_path, parent_path.len(), new_key))
+                .collect();
+            cost_return_on_error!(
+                &mut cost,
+                self.unregister_subtree_prefixes(
+                    std::iter::once(old_subtree_path.to_vec()),
+                    batch,
+                    Some(transaction)
+                )
+            );
+            cost_return_on_error!(
+                &mut cost,
+                self.register_subtree_prefixes(
+                    new_subtrees_paths.iter().cloned(),
+                    batch,
+                    Some(transaction)
+                )
+            );
+            for (old_path, new_path) in subtrees_paths.into_iter().zip(new_subtrees_paths) {
+                let old_storage = self
+                    .db
+                    .get_transactional_storage_context(
+                        old_path.as_slice().into(),
+                        Some(batch),
+                        transaction,
+                    )
+                    .unwrap_add_cost(&mut cost);
+                let new_storage = self
+                    .db
+                    .get_transactional_storage_context(
+                        new_path.as_slice().into(),
+                        Some(batch),
+                        transaction,
+                    )
+                    .unwrap_add_cost(&mut cost);
+                cost_return_on_error!(&mut cost, move_subtree_storage(&old_storage, &new_storage));
+            }
+        }
+
+        let referenced_value_hash = if let Element::Reference(ref reference_path, ..) = element {
+            let reference_path = cost_return_on_error!(
+                &mut cost,
+                path_from_reference_path_type(reference_path.clone(), &parent_path, Some(new_key))
+                    .wrap_with_cost(OperationCost::default())
+            );
+            let (referenced_key, referenced_path) = cost_return_on_error_no_add!(
+                &cost,
+                reference_path.split_last().ok_or(Error::InvalidPath(
+                    "reference path cannot be empty".to_owned()
+                ))
+            );
+            let subtree_for_reference = cost_return_on_error!(
+                &mut cost,
+                self.open_transactional_merk_at_path(
+                    referenced_path.into(),
+                    transaction,
+                    Some(batch)
+                )
+            );
+            let referenced_value_hash = cost_return_on_error!(
+                &mut cost,
+                Element::get_value_hash(&subtree_for_reference, referenced_key, true)
+            );
+            Some(cost_return_on_error_no_add!(
+                &cost,
+                referenced_value_hash.ok_or_else(|| missing_reference_error(&reference_path))
+            ))
+        } else {
+            None
+        };
+
+        let mut parent = cost_return_on_error!(
+            &mut cost,
+            self.open_transactional_merk_at_path(path.clone(), transaction, Some(batch))
+        );
+        cost_return_on_error!(
+            &mut cost,
+            rename_in_merk(
+                &mut parent,
+                old_key,
+                new_key,
+                &element,
+                subtree_root_hash,
+                referenced_value_hash
+            )
+        );
+
+        let mut merk_cache: HashMap<SubtreePath<B>, Merk<PrefixedRocksDbTransactionContext>> =
+            HashMap::default();
+        merk_cache.insert(path.clone(), parent);
+        cost_return_on_error!(
+            &mut cost,
+            self.propagate_changes_with_transaction(merk_cache, path, transaction, batch)
+        );
+
+        Ok(()).wrap_with_cost(cost)
+    }
+
+    fn rename_key_without_transaction<B: AsRef<[u8]>>(
+        &self,
+        path: SubtreePath<B>,
+        old_key: &[u8],
+        new_key: &[u8],
+        element: Element,
+        batch: &StorageBatch,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+        let parent_path = path.to_vec();
+
+        let mut subtree_root_hash = None;
+        if element.is_tree() {
+            let old_subtree_path = path.derive_owned_with_child(old_key);
+            let old_subtree_path_ref = SubtreePath::from(&old_subtree_path);
+            let old_subtree = cost_return_on_error!(
+                &mut cost,
+                self.open_non_transactional_merk_at_path(old_subtree_path_ref.clone(), Some(batch))
+            );
+            subtree_root_hash = Some(old_subtree.root_hash().unwrap_add_cost(&mut cost));
+
+            let subtrees_paths =
+                cost_return_on_error!(&mut cost, self.find_subtrees(&old_subtree_path_ref, None));
+            let new_subtrees_paths: Vec<_> = subtrees_paths
+                .iter()
+                .map(|old_path| renamed_subtree_path(old_path, parent_path.len(), new_key))
+                .collect();
+            cost_return_on_error!(
+                &mut cost,
+                self.unregister_subtree_prefixes(
+                    std::iter::once(old_subtree_path.to_vec()),
+                    batch,
+                    None
+                )
+            );
+            cost_return_on_error!(
+                &mut cost,
+                self.register_subtree_prefixes(new_subtrees_paths.iter().cloned(), batch, None)
+            );
+            for (old_path, new_path) in subtrees_paths.into_iter().zip(new_subtrees_paths) {
+                let old_storage = self
+                    .db
+                    .get_storage_context(old_path.as_slice().into(), Some(batch))
+                    .unwrap_add_cost(&mut cost);
+                let new_storage = self
+                    .db
+                    .get_storage_context(new_path.as_slice().into(), Some(batch))
+                    .unwrap_add_cost(&mut cost);
+                cost_return_on_error!(&mut cost, move_subtree_storage(&old_storage, &new_storage));
+            }
+        }
+
+        let referenced_value_hash = if let Element::Reference(ref reference_path, ..) = element {
+            let reference_path = cost_return_on_error!(
+                &mut cost,
+                path_from_reference_path_type(reference_path.clone(), &parent_path, Some(new_key))
+                    .wrap_with_cost(OperationCost::default())
+            );
+            let (referenced_key, referenced_path) = cost_return_on_error_no_add!(
+                &cost,
+                reference_path.split_last().ok_or(Error::InvalidPath(
+                    "reference path cannot be empty".to_owned()
+                ))
+            );
+            let subtree_for_reference = cost_return_on_error!(
+                &mut cost,
+                self.open_non_transactional_merk_at_path(referenced_path.into(), Some(batch))
+            );
+            // when there is no transaction, we don't want to use caching
+            let referenced_value_hash = cost_return_on_error!(
+                &mut cost,
+                Element::get_value_hash(&subtree_for_reference, referenced_key, false)
+            );
+            Some(cost_return_on_error_no_add!(
+                &cost,
+                referenced_value_hash.ok_or_else(|| missing_reference_error(&reference_path))
+            ))
+        } else {
+            None
+        };
+
+        let mut parent = cost_return_on_error!(
+            &mut cost,
+            self.open_non_transactional_merk_at_path(path.clone(), Some(batch))
+        );
+        cost_return_on_error!(
+            &mut cost,
+            rename_in_merk(
+                &mut parent,
+                old_key,
+                new_key,
+                &element,
+                subtree_root_hash,
+                referenced_value_hash
+            )
+        );
+
+        let mut merk_cache: HashMap<SubtreePath<B>, Merk<PrefixedRocksDbStorageContext>> =
+            HashMap::default();
+        merk_cache.insert(path.clone(), parent);
+        cost_return_on_error!(
+            &mut cost,
+            self.propagate_changes_without_transaction(merk_cache, path, batch)
+        );
+
+        Ok(()).wrap_with_cost(cost)
+    }
+}
+
+/// Replaces the segment of the renamed key in an absolute path of one of the
+/// renamed subtree's descendants.
+#[cfg(feature = "full")]
+pub(crate) fn renamed_subtree_path(
+    old_path: &[Vec<u8>],
+    parent_len: usize,
+    new_key: &[u8],
+) -> Vec<Vec<u8>> {
+    let mut new_path = old_path.to_vec();
+    new_path[parent_len] = new_key.to_vec();
+    new_path
+}
+
+/// Moves every raw entry of a subtree to another prefix, in all column
+/// families so that the subtree's auxiliary data (auto-key cursors, counters,
+/// ...) follows it. Merk nodes only refer to their children by key, so the
+/// entries are valid as they are.
+#[cfg(feature = "full")]
+fn move_subtree_storage<'db, S: StorageContext<'db>>(from: &S, to: &S) -> CostResult<(), Error> {
+    let mut cost = OperationCost::default();
+
+    cost_return_on_error!(
+        &mut cost,
+        move_column(from.raw_iter(), |key, value| {
+            to.put(key, value, None, None)
+                .flat_map_ok(|_| from.delete(key, None))
+        })
+    );
+    cost_return_on_error!(
+        &mut cost,
+        move_column(from.raw_iter_aux(), |key, value| {
+            to.put_aux(key, value, None)
+                .flat_map_ok(|_| from.delete_aux(key, None))
+        })
+    );
+    cost_return_on_error!(
+        &mut cost,
+        move_column(from.raw_iter_roots(), |key, value| {
+            to.put_root(key, value, None)
+                .flat_map_ok(|_| from.delete_root(key, None))
+        })
+    );
+    move_column(from.raw_iter_meta(), |key, value| {
+        // Backlinks are rewritten by `GroveDb::record_renamed_backlinks`
+        if is_backlink_meta_key(key) {
+            return from.delete_meta(key, None);
+        }
+        to.put_meta(key, value, None)
+            .flat_map_ok(|_| from.delete_meta(key, None))
+    })
+    .add_cost(cost)
+}
+
+/// Calls `move_entry` for every entry of a raw iterator
+#[cfg(feature = "full")]
+fn move_column<I: RawIterator>(
+    mut iter: I,
+    move_entry: impl Fn(&[u8], &[u8]) -> CostResult<(), grovedb_storage::Error>,
+) -> CostResult<(), Error> {
+    let mut cost = OperationCost::default();
+
+    iter.seek_to_first().unwrap_add_cost(&mut cost);
+    while iter.valid().unwrap_add_cost(&mut cost) {
+        let key = iter.key().unwrap_add_cost(&mut cost);
+        let value = iter.value().unwrap_add_cost(&mut cost);
+        if let (Some(key), Some(value)) = (key, value) {
+            cost_return_on_error!(&mut cost, move_entry(key, value).map_err(Into::into));
+        }
+        iter.next().unwrap_add_cost(&mut cost);
+    }
+
+    Ok(()).wrap_with_cost(cost)
+}
+
+/// Deletes `old_key` and puts the element under `new_key` in one Merk batch.
+#[cfg(feature = "full")]
+fn rename_in_merk<'db, S: StorageContext<'db>>(
+    merk: &mut Merk<S>,
+    old_key: &[u8],
+    new_key: &[u8],
+    element: &Element,
+    subtree_root_hash: Option<Hash>,
+    referenced_value_hash: Option<Hash>,
+) -> CostResult<(), Error> {
+    let mut cost = OperationCost::default();
+    let uses_sum_nodes = merk.is_sum_tree;
+    let feature_type = cost_return_on_error_no_add!(&cost, element.get_feature_type_in_merk(merk));
+
+    let mut batch_operations = Vec::with_capacity(2);
+    cost_return_on_error!(
+        &mut cost,
+        Element::delete_into_batch_operations(
+            old_key,
+            element.is_tree(),
+            uses_sum_nodes,
+            &mut batch_operations
+        )
+    );
+    if let Some(subtree_root_hash) = subtree_root_hash {
+        cost_return_on_error!(
+            &mut cost,
+            element.insert_subtree_into_batch_operations(
+                new_key,
+                subtree_root_hash,
+                false,
+                &mut batch_operations,
+                feature_type
+            )
+        );
+    } else if let Some(referenced_value_hash) = referenced_value_hash {
+        cost_return_on_error!(
+            &mut cost,
+            element.insert_reference_into_batch_operations(
+                new_key,
+                referenced_value_hash,
+                &mut batch_operations,
+                feature_type
+            )
+        );
+    } else {
+        cost_return_on_error!(
+            &mut cost,
+            element.insert_into_batch_operations(new_key, &mut batch_operations, feature_type)
+        );
+    }
+    // Merk batches must be sorted by key
+    batch_operations.sort_by(|a, b| a.0.cmp(b.0));
+
+    merk.apply_with_specialized_costs::<_, Vec<u8>>(&batch_operations, &[], None, &|key, value| {
+        Element::specialized_costs_for_key_value(key, value, uses_sum_nodes)
+            .map_err(|e| MerkError::ClientCorruptionError(e.to_string()))
+    })
+    .map_err(Error::from_merk_apply_error)
+    .add_cost(cost)
+}
+
+#[cfg(feature = "full")]
+fn missing_reference_error(reference_path: &[Vec<u8>]) -> Error {
+    let reference_string = reference_path
+        .iter()
+        .map(hex::encode)
+        .collect::<Vec<String>>()
+        .join("/");
+    Error::MissingReference(format!("reference {reference_string} can not be found"))
+}
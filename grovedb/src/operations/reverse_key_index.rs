@@ -0,0 +1,530 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Reverse key index
+//!
+//! When enabled, GroveDB maintains an index from keys to the paths of the
+//! subtrees holding them, so the subtrees a key lives in can be found
+//! without scanning the whole grove. Entries are added by insertions and
+//! batches, and removed by deletions, renames and clears, in the same storage
+//! batch as the elements. Deleting or clearing a subtree reads every element
+//! below it to remove their entries. Lookups still check that the key is
+//! present, so entries written by versions that didn't prune the index are
+//! never returned.
+
+#[cfg(feature = "full")]
+use std::sync::atomic::Ordering;
+
+#[cfg(feature = "full")]
+use grovedb_costs::{
+    cost_return_on_error, cost_return_on_error_no_add, CostResult, CostsExt, OperationCost,
+};
+#[cfg(feature = "full")]
+use grovedb_path::SubtreePath;
+#[cfg(feature = "full")]
+use grovedb_storage::{RawIterator, Storage, StorageBatch, StorageContext};
+
+#[cfg(feature = "full")]
+use crate::{
+    operations::rename::renamed_subtree_path,
+    util::{meta_storage_context_optional_tx, storage_context_optional_tx},
+    Element, Error, GroveDb, TransactionArg,
+};
+
+/// Aux key marking the reverse key index as enabled
+#[cfg(feature = "full")]
+const ENABLED_AUX_KEY: &[u8] = b"reverse_key_index_enabled";
+
+/// Prefix of the aux keys of reverse key index entries
+#[cfg(feature = "full")]
+const ENTRY_AUX_PREFIX: &[u8] = b"reverse_key_index/";
+
+/// Aux key prefix shared by the entries of `key`
+#[cfg(feature = "full")]
+fn entries_prefix(key: &[u8]) -> Vec<u8> {
+    let mut prefix = ENTRY_AUX_PREFIX.to_vec();
+    prefix.extend_from_slice(&(key.len() as u32).to_be_bytes());
+    prefix.extend_from_slice(key);
+    prefix
+}
+
+/// Aux key of the entry recording that `key` lives in the subtree at `path`
+#[cfg(feature = "full")]
+fn entry_aux_key(key: &[u8], path: &[Vec<u8>]) -> Vec<u8> {
+    let mut aux_key = entries_prefix(key);
+    for segment in path {
+        aux_key.extend_from_slice(&(segment.len() as u32).to_be_bytes());
+        aux_key.extend_from_slice(segment);
+    }
+    aux_key
+}
+
+/// Decodes the path encoded after the entries prefix of an entry
+#[cfg(feature = "full")]
+fn decode_entry_path(mut encoded: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+    let corrupted = || Error::CorruptedData("malformed reverse key index entry".to_owned());
+    let mut path = Vec::new();
+    while !encoded.is_empty() {
+        let len_bytes: [u8; 4] = encoded
+            .get(..4)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(corrupted)?;
+        let len = u32::from_be_bytes(len_bytes) as usize;
+        let segment = encoded.get(4..4 + len).ok_or_else(corrupted)?;
+        path.push(segment.to_vec());
+        encoded = &encoded[4 + len..];
+    }
+    Ok(path)
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Enables the reverse key index, indexing the elements already in the
+    /// grove. Elements written from now on are indexed as they are written.
+    pub fn enable_reverse_key_index(&self) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        // Enabled first so that writes made during the backfill are indexed
+        self.reverse_key_index_enabled.store(true, Ordering::SeqCst);
+        cost_return_on_error!(&mut cost, self.put_aux(ENABLED_AUX_KEY, &[1], None, None));
+
+        let mut cursor = None;
+        loop {
+            let page = cost_return_on_error!(&mut cost, self.scan(cursor.as_ref(), u16::MAX, None));
+            let batch = StorageBatch::new();
+            cost_return_on_error!(
+                &mut cost,
+                self.record_reverse_key_index(
+                    page.elements
+                        .iter()
+                        .map(|(path, key, _)| (path.clone(), key.as_slice())),
+                    &batch,
+                    None
+                )
+            );
+            cost_return_on_error!(
+                &mut cost,
+                self.db
+                    .commit_multi_context_batch(batch, None)
+                    .map_err(Into::into)
+            );
+            cursor = page.cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        Ok(()).wrap_with_cost(cost)
+    }
+
+    /// Whether the reverse key index is enabled
+    pub fn is_reverse_key_index_enabled(&self) -> bool {
+        self.reverse_key_index_enabled.load(Ordering::SeqCst)
+    }
+
+    /// Returns the paths of the subtrees that hold `key`.
+    /// Requires the reverse key index to be enabled.
+    pub fn find_paths_of_key(
+        &self,
+        key: &[u8],
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<Vec<Vec<u8>>>, Error> {
+        let mut cost = OperationCost::default();
+
+        if !self.is_reverse_key_index_enabled() {
+            return Err(Error::InvalidInput("reverse key index is not enabled"))
+                .wrap_with_cost(cost);
+        }
+
+        let prefix = entries_prefix(key);
+        let mut indexed_paths = Vec::new();
+        meta_storage_context_optional_tx!(self.db, None, transaction, storage, {
+            let storage = storage.unwrap_add_cost(&mut cost);
+            let mut iter = storage.raw_iter_aux();
+            iter.seek(&prefix).unwrap_add_cost(&mut cost);
+            while iter.valid().unwrap_add_cost(&mut cost) {
+                let Some(entry) = iter.key().unwrap_add_cost(&mut cost) else {
+                    break;
+                };
+                let Some(encoded_path) = entry.strip_prefix(prefix.as_slice()) else {
+                    break;
+                };
+                indexed_paths.push(cost_return_on_error_no_add!(
+                    &cost,
+                    decode_entry_path(encoded_path)
+                ));
+                iter.next().unwrap_add_cost(&mut cost);
+            }
+        });
+
+        let mut paths = Vec::new();
+        for path in indexed_paths {
+            let exists =
+                cost_return_on_error!(&mut cost, self.has_raw(path.as_slice(), key, transaction));
+            if exists {
+                paths.push(path);
+            }
+        }
+        Ok(paths).wrap_with_cost(cost)
+    }
+
+    /// Loads whether the reverse key index was enabled
+    pub(crate) fn load_reverse_key_index_state(&self) -> Result<(), Error> {
+        let enabled = self.get_aux(ENABLED_AUX_KEY, None).unwrap()?.is_some();
+        self.reverse_key_index_enabled
+            .store(enabled, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Stages into `batch` the reverse key index entries of the elements
+    /// written at the given paths and keys, if the index is enabled
+    pub(crate) fn record_reverse_key_index<'k>(
+        &self,
+        written: impl IntoIterator<Item = (Vec<Vec<u8>>, &'k [u8])>,
+        batch: &StorageBatch,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        if !self.is_reverse_key_index_enabled() {
+            return Ok(()).wrap_with_cost(cost);
+        }
+
+        meta_storage_context_optional_tx!(self.db, Some(batch), transaction, storage, {
+            let storage = storage.unwrap_add_cost(&mut cost);
+            for (path, key) in written {
+                cost_return_on_error!(
+                    &mut cost,
+                    storage
+                        .put_aux(entry_aux_key(key, &path), &[], None)
+                        .map_err(Into::into)
+                );
+            }
+        });
+
+        Ok(()).wrap_with_cost(cost)
+    }
+
+    /// Stages into `batch` the removal of the reverse key index entries of the
+    /// elements deleted at the given paths and keys and, for deleted trees, of
+    /// the elements below them, if the index is enabled. Must be called before
+    /// the deletions are committed.
+    pub(crate) fn prune_reverse_key_index<'k>(
+        &self,
+        deleted: impl IntoIterator<Item = (Vec<Vec<u8>>, &'k [u8])>,
+        batch: &StorageBatch,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        if !self.is_reverse_key_index_enabled() {
+            return Ok(()).wrap_with_cost(cost);
+        }
+
+        let mut removed = Vec::new();
+        for (path, key) in deleted {
+            let mut subtree_path = path.clone();
+            subtree_path.push(key.to_vec());
+            removed.push((path, key.to_vec()));
+            let is_tree = cost_return_on_error!(
+                &mut cost,
+                self.check_subtree_exists(subtree_path.as_slice(), transaction)
+            );
+            if is_tree {
+                removed.extend(cost_return_on_error!(
+                    &mut cost,
+                    self.elements_below(subtree_path, transaction)
+                ));
+            }
+        }
+
+        self.delete_reverse_key_index_entries(removed, batch, transaction)
+            .add_cost(cost)
+    }
+
+    /// Stages into `batch` the removal of the reverse key index entries of the
+    /// elements of the subtree at `path` and of the subtrees below it, if the
+    /// index is enabled. Must be called before the subtree is cleared.
+    pub(crate) fn prune_reverse_key_index_below(
+        &self,
+        path: Vec<Vec<u8>>,
+        batch: &StorageBatch,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        if !self.is_reverse_key_index_enabled() {
+            return Ok(()).wrap_with_cost(cost);
+        }
+
+        let removed = cost_return_on_error!(&mut cost, self.elements_below(path, transaction));
+        self.delete_reverse_key_index_entries(removed, batch, transaction)
+            .add_cost(cost)
+    }
+
+    /// Stages into `batch` the reverse key index changes of renaming `old_key`
+    /// to `new_key` in the subtree at `path`, moving the entries of the
+    /// elements below a renamed tree to their new paths, if the index is
+    /// enabled. Must be called before the rename is committed.
+    pub(crate) fn record_renamed_reverse_key_index(
+        &self,
+        path: &[Vec<u8>],
+        old_key: &[u8],
+        new_key: &[u8],
+        element: &Element,
+        batch: &StorageBatch,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        if !self.is_reverse_key_index_enabled() {
+            return Ok(()).wrap_with_cost(cost);
+        }
+
+        let mut removed = vec![(path.to_vec(), old_key.to_vec())];
+        let mut written = vec![(path.to_vec(), new_key.to_vec())];
+        if element.is_tree() {
+            let mut subtree_path = path.to_vec();
+            subtree_path.push(old_key.to_vec());
+            let below =
+                cost_return_on_error!(&mut cost, self.elements_below(subtree_path, transaction));
+            written.extend(below.iter().map(|(element_path, key)| {
+                (
+                    renamed_subtree_path(element_path, path.len(), new_key),
+                    key.clone(),
+                )
+            }));
+            removed.extend(below);
+        }
+
+        cost_return_on_error!(
+            &mut cost,
+            self.delete_reverse_key_index_entries(removed, batch, transaction)
+        );
+        self.record_reverse_key_index(
+            written
+                .iter()
+                .map(|(path, key)| (path.clone(), key.as_slice())),
+            batch,
+            transaction,
+        )
+        .add_cost(cost)
+    }
+
+    /// Paths and keys of the elements of the subtree at `path` and of the
+    /// subtrees below it
+    fn elements_below(
+        &self,
+        path: Vec<Vec<u8>>,
+        transaction: TransactionArg,
+    ) -> CostResult<Vec<(Vec<Vec<u8>>, Vec<u8>)>, Error> {
+        let mut cost = OperationCost::default();
+
+        let mut elements = Vec::new();
+        let mut queue = vec![path];
+        while let Some(path) = queue.pop() {
+            let subtree_path: SubtreePath<Vec<u8>> = path.as_slice().into();
+            storage_context_optional_tx!(self.db, subtree_path, None, transaction, storage, {
+                let storage = storage.unwrap_add_cost(&mut cost);
+                let mut raw_iter = Element::iterator(storage.raw_iter()).unwrap_add_cost(&mut cost);
+                while let Some((key, element)) =
+                    cost_return_on_error!(&mut cost, raw_iter.next_element())
+                {
+                    if element.is_tree() {
+                        let mut subtree_path = path.clone();
+                        subtree_path.push(key.clone());
+                        queue.push(subtree_path);
+                    }
+                    elements.push((path.clone(), key));
+                }
+            })
+        }
+        Ok(elements).wrap_with_cost(cost)
+    }
+
+    /// Stages into `batch` the removal of the reverse key index entries of the
+    /// given paths and keys
+    fn delete_reverse_key_index_entries(
+        &self,
+        removed: Vec<(Vec<Vec<u8>>, Vec<u8>)>,
+        batch: &StorageBatch,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        meta_storage_context_optional_tx!(self.db, Some(batch), transaction, storage, {
+            let storage = storage.unwrap_add_cost(&mut cost);
+            for (path, key) in removed {
+                cost_return_on_error!(
+                    &mut cost,
+                    storage
+                        .delete_aux(entry_aux_key(&key, &path), None)
+                        .map_err(Into::into)
+                );
+            }
+        });
+
+        Ok(()).wrap_with_cost(cost)
+    }
+}
+
+#[cfg(feature = "full")]
+#[cfg(test)]
+mod tests {
+    use super::entry_aux_key;
+    use crate::{
+        operations::delete::{ClearOptions, DeleteUpTreeOptions},
+        tests::{make_test_grovedb, TempGroveDb, TEST_LEAF},
+        Element,
+    };
+
+    fn has_entry(db: &TempGroveDb, key: &[u8], path: &[&[u8]]) -> bool {
+        let path: Vec<Vec<u8>> = path.iter().map(|segment| segment.to_vec()).collect();
+        db.get_aux(entry_aux_key(key, &path), None)
+            .unwrap()
+            .expect("successful get aux")
+            .is_some()
+    }
+
+    #[test]
+    fn test_reverse_key_index_is_pruned() {
+        let db = make_test_grovedb();
+        db.enable_reverse_key_index()
+            .unwrap()
+            .expect("successful enable");
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"tree",
+            Element::empty_tree(),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+        db.insert(
+            [TEST_LEAF, b"tree"].as_ref(),
+            b"a",
+            Element::new_item(b"a".to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+        db.insert(
+            [TEST_LEAF, b"tree"].as_ref(),
+            b"inner",
+            Element::empty_tree(),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+        db.insert(
+            [TEST_LEAF, b"tree", b"inner"].as_ref(),
+            b"b",
+            Element::new_item(b"b".to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+
+        // renaming a tree moves the entries of the elements below it
+        db.rename_key([TEST_LEAF].as_ref(), b"tree", b"renamed", None)
+            .unwrap()
+            .expect("successful rename");
+        assert!(!has_entry(&db, b"tree", &[TEST_LEAF]));
+        assert!(!has_entry(&db, b"a", &[TEST_LEAF, b"tree"]));
+        assert!(!has_entry(&db, b"b", &[TEST_LEAF, b"tree", b"inner"]));
+        assert!(has_entry(&db, b"renamed", &[TEST_LEAF]));
+        assert!(has_entry(&db, b"a", &[TEST_LEAF, b"renamed"]));
+        assert!(has_entry(&db, b"inner", &[TEST_LEAF, b"renamed"]));
+        assert!(has_entry(&db, b"b", &[TEST_LEAF, b"renamed", b"inner"]));
+
+        // clearing a subtree removes the entries below it
+        db.clear_subtree(
+            [TEST_LEAF, b"renamed"].as_ref(),
+            Some(ClearOptions {
+                allow_deleting_subtrees: true,
+                ..Default::default()
+            }),
+            None,
+        )
+        .unwrap()
+        .expect("successful clear");
+        assert!(has_entry(&db, b"renamed", &[TEST_LEAF]));
+        assert!(!has_entry(&db, b"a", &[TEST_LEAF, b"renamed"]));
+        assert!(!has_entry(&db, b"inner", &[TEST_LEAF, b"renamed"]));
+        assert!(!has_entry(&db, b"b", &[TEST_LEAF, b"renamed", b"inner"]));
+
+        // deleting up the tree removes the entries of every deleted level
+        db.insert(
+            [TEST_LEAF, b"renamed"].as_ref(),
+            b"c",
+            Element::new_item(b"c".to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+        assert!(has_entry(&db, b"c", &[TEST_LEAF, b"renamed"]));
+        db.delete_up_tree_while_empty(
+            [TEST_LEAF, b"renamed"].as_ref(),
+            b"c",
+            &DeleteUpTreeOptions {
+                stop_path_height: Some(0),
+                ..Default::default()
+            },
+            None,
+        )
+        .unwrap()
+        .expect("successful delete up tree");
+        assert!(db
+            .get([TEST_LEAF].as_ref(), b"renamed", None)
+            .unwrap()
+            .is_err());
+        assert!(!has_entry(&db, b"c", &[TEST_LEAF, b"renamed"]));
+        assert!(!has_entry(&db, b"renamed", &[TEST_LEAF]));
+
+        // deleting an element removes its entry
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"d",
+            Element::new_item(b"d".to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+        assert!(has_entry(&db, b"d", &[TEST_LEAF]));
+        db.delete([TEST_LEAF].as_ref(), b"d", None, None)
+            .unwrap()
+            .expect("successful delete");
+        assert!(!has_entry(&db, b"d", &[TEST_LEAF]));
+    }
+}
@@ -0,0 +1,182 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Full grove scan
+//!
+//! Visits every element of the grove in a deterministic order, a page at a
+//! time: the elements of a subtree are visited in key order, and the
+//! elements of a subtree are visited right after the tree element holding
+//! it. Each page ends with a cursor the next page resumes from, so a scan
+//! can be spread over many calls, which is what backfilling external
+//! indexes and consistency checkers need.
+
+#[cfg(feature = "full")]
+use grovedb_costs::{cost_return_on_error, CostResult, CostsExt, OperationCost};
+use grovedb_path::SubtreePath;
+#[cfg(feature = "full")]
+use grovedb_storage::StorageContext;
+
+#[cfg(feature = "full")]
+use crate::{util::storage_context_optional_tx, Element, Error, GroveDb, TransactionArg};
+
+#[cfg(feature = "full")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Position of a scan, pointing at the last element that was returned
+pub struct ScanCursor {
+    /// Path of the subtree of the last returned element
+    pub path: Vec<Vec<u8>>,
+    /// Key of the last returned element
+    pub key: Vec<u8>,
+}
+
+#[cfg(feature = "full")]
+/// A page of a scan
+pub struct ScanPage {
+    /// Path, key and element of every element of the page, in scan order
+    pub elements: Vec<(Vec<Vec<u8>>, Vec<u8>, Element)>,
+    /// Cursor to resume the scan from, `None` once the whole grove was
+    /// visited
+    pub cursor: Option<ScanCursor>,
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Returns up to `limit` elements of the grove in scan order, starting
+    /// right after `start_cursor`, or from the beginning without one.
+    ///
+    /// Pages read without a transaction reflect the state of the grove at the
+    /// time each of them is read: a scan over a changing grove may miss
+    /// elements written behind its cursor.
+    pub fn scan(
+        &self,
+        start_cursor: Option<&ScanCursor>,
+        limit: u16,
+        transaction: TransactionArg,
+    ) -> CostResult<ScanPage, Error> {
+        let mut cost = OperationCost::default();
+
+        if limit == 0 {
+            return Err(Error::InvalidInput("scan limit must be greater than 0"))
+                .wrap_with_cost(cost);
+        }
+
+        let resume_from: Vec<Vec<u8>> = start_cursor
+            .map(|cursor| {
+                let mut position = cursor.path.clone();
+                position.push(cursor.key.clone());
+                position
+            })
+            .unwrap_or_default();
+        let mut elements = Vec::new();
+        cost_return_on_error!(
+            &mut cost,
+            self.scan_subtree(
+                &mut Vec::new(),
+                &resume_from,
+                limit as usize,
+                &mut elements,
+                transaction
+            )
+        );
+
+        let cursor = if elements.len() == limit as usize {
+            elements.last().map(|(path, key, _)| ScanCursor {
+                path: path.clone(),
+                key: key.clone(),
+            })
+        } else {
+            None
+        };
+        Ok(ScanPage { elements, cursor }).wrap_with_cost(cost)
+    }
+
+    /// Appends to `elements` the elements of the subtree at `path` and of its
+    /// descendants, in scan order, until `limit` elements were collected.
+    /// `resume_from` is the position of the last returned element relative to
+    /// `path`; elements up to it are skipped.
+    fn scan_subtree(
+        &self,
+        path: &mut Vec<Vec<u8>>,
+        resume_from: &[Vec<u8>],
+        limit: usize,
+        elements: &mut Vec<(Vec<Vec<u8>>, Vec<u8>, Element)>,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        // The element the scan resumes after was already returned, but the
+        // subtree under it may not have been visited yet
+        if let Some((resumed_key, rest)) = resume_from.split_first() {
+            path.push(resumed_key.clone());
+            cost_return_on_error!(
+                &mut cost,
+                self.scan_subtree(path, rest, limit, elements, transaction)
+            );
+            path.pop();
+            if elements.len() == limit {
+                return Ok(()).wrap_with_cost(cost);
+            }
+        }
+
+        let subtree_path_vec = path.clone();
+        let subtree_path: SubtreePath<Vec<u8>> = subtree_path_vec.as_slice().into();
+        storage_context_optional_tx!(self.db, subtree_path, None, transaction, storage, {
+            let storage = storage.unwrap_add_cost(&mut cost);
+            let mut element_iter = match resume_from.first() {
+                Some(resumed_key) => Element::iterator_from(storage.raw_iter(), resumed_key),
+                None => Element::iterator(storage.raw_iter()),
+            }
+            .unwrap_add_cost(&mut cost);
+            while let Some((key, element)) =
+                cost_return_on_error!(&mut cost, element_iter.next_element())
+            {
+                if resume_from.first() == Some(&key) {
+                    continue;
+                }
+                let is_tree = element.is_tree();
+                elements.push((path.clone(), key.clone(), element));
+                if elements.len() == limit {
+                    return Ok(()).wrap_with_cost(cost);
+                }
+                if is_tree {
+                    path.push(key);
+                    cost_return_on_error!(
+                        &mut cost,
+                        self.scan_subtree(path, &[], limit, elements, transaction)
+                    );
+                    path.pop();
+                    if elements.len() == limit {
+                        return Ok(()).wrap_with_cost(cost);
+                    }
+                }
+            }
+        });
+
+        Ok(()).wrap_with_cost(cost)
+    }
+}
@@ -0,0 +1,265 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Snapshot export and import
+//!
+//! Serializes the whole storage of a GroveDB, every subtree along with the
+//! auxiliary, roots and meta column families, into a portable stream that
+//! can be imported into an empty GroveDB.
+//!
+//! The stream starts with a header:
+//!
+//! | field         | size | content                                       |
+//! |---------------|------|-----------------------------------------------|
+//! | magic         | 8    | `GROVESNP`                                    |
+//! | version       | 1    | [`SNAPSHOT_VERSION`]                          |
+//! | root hash     | 32   | root hash of the exported GroveDB             |
+//! | prefix scheme | 1    | `0` for hashed, `1` for hierarchical prefixes |
+//! | salt flag     | 1    | `1` if a prefix salt follows, `0` otherwise   |
+//! | salt          | 32   | prefix salt, only present if the flag is set  |
+//!
+//! followed by the records, each being a column family byte (`0` data, `1`
+//! aux, `2` roots, `3` meta), the length of the key as a big endian `u32`,
+//! the key, the length of the value as a big endian `u32` and the value. The
+//! stream ends with an `0xFF` byte and the number of records as a big endian
+//! `u64`.
+//!
+//! Prefixes are derived from paths using the prefix scheme and salt of the
+//! storage, so a snapshot can only be imported into a GroveDB configured
+//! with the same ones.
+
+use std::io::{Read, Write};
+
+use grovedb_storage::rocksdb_storage::{PrefixScheme, StorageColumnFamily};
+
+use crate::{Error, GroveDb};
+
+/// Magic bytes a snapshot starts with
+const SNAPSHOT_MAGIC: &[u8; 8] = b"GROVESNP";
+
+/// Version of the snapshot format written by this version of GroveDB
+pub const SNAPSHOT_VERSION: u8 = 1;
+
+/// Byte marking the end of the records of a snapshot
+const END_OF_RECORDS: u8 = 0xFF;
+
+fn column_family_to_byte(column_family: StorageColumnFamily) -> u8 {
+    match column_family {
+        StorageColumnFamily::Data => 0,
+        StorageColumnFamily::Aux => 1,
+        StorageColumnFamily::Roots => 2,
+        StorageColumnFamily::Meta => 3,
+    }
+}
+
+fn column_family_from_byte(byte: u8) -> Option<StorageColumnFamily> {
+    match byte {
+        0 => Some(StorageColumnFamily::Data),
+        1 => Some(StorageColumnFamily::Aux),
+        2 => Some(StorageColumnFamily::Roots),
+        3 => Some(StorageColumnFamily::Meta),
+        _ => None,
+    }
+}
+
+fn prefix_scheme_to_byte(prefix_scheme: PrefixScheme) -> u8 {
+    match prefix_scheme {
+        PrefixScheme::Hashed => 0,
+        PrefixScheme::Hierarchical => 1,
+    }
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> Result<[u8; N], Error> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes).map_err(Error::SnapshotIo)?;
+    Ok(bytes)
+}
+
+fn read_bytes<R: Read>(reader: &mut R) -> Result<Vec<u8>, Error> {
+    let len = u32::from_be_bytes(read_array(reader)?);
+    let mut bytes = Vec::new();
+    reader
+        .take(len as u64)
+        .read_to_end(&mut bytes)
+        .map_err(Error::SnapshotIo)?;
+    if bytes.len() != len as usize {
+        return Err(Error::CorruptedData(
+            "snapshot ends in the middle of a record".to_owned(),
+        ));
+    }
+    Ok(bytes)
+}
+
+fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> Result<(), Error> {
+    let len = u32::try_from(bytes.len())
+        .map_err(|_| Error::CorruptedData("record too large for a snapshot".to_owned()))?;
+    writer
+        .write_all(&len.to_be_bytes())
+        .and_then(|_| writer.write_all(bytes))
+        .map_err(Error::SnapshotIo)
+}
+
+/// Reads the next record of a snapshot, counting it in `count`, or checks
+/// the trailer and returns `None` once the end of the records is reached
+fn read_record<R: Read>(
+    reader: &mut R,
+    count: &mut u64,
+) -> Result<Option<(StorageColumnFamily, Vec<u8>, Vec<u8>)>, Error> {
+    let [column_family]: [u8; 1] = read_array(reader)?;
+    if column_family == END_OF_RECORDS {
+        let expected_count = u64::from_be_bytes(read_array(reader)?);
+        if expected_count != *count {
+            return Err(Error::CorruptedData(format!(
+                "snapshot has {} records, its trailer expects {}",
+                count, expected_count
+            )));
+        }
+        return Ok(None);
+    }
+    let column_family = column_family_from_byte(column_family).ok_or_else(|| {
+        Error::CorruptedData(format!("invalid snapshot column family {}", column_family))
+    })?;
+    let key = read_bytes(reader)?;
+    let value = read_bytes(reader)?;
+    *count += 1;
+    Ok(Some((column_family, key, value)))
+}
+
+impl GroveDb {
+    /// Writes a snapshot of the whole GroveDB into `writer`, in the format
+    /// described in the [module documentation](self). The root hash and the
+    /// records are read from a snapshot of the storage pinned when called,
+    /// so writes committed while the snapshot is exported are not part of it.
+    pub fn export_snapshot<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        let read_snapshot = self.snapshot()?;
+        let root_hash = self.root_hash(Some(read_snapshot.transaction())).unwrap()?;
+
+        writer
+            .write_all(SNAPSHOT_MAGIC)
+            .and_then(|_| writer.write_all(&[SNAPSHOT_VERSION]))
+            .and_then(|_| writer.write_all(&root_hash))
+            .and_then(|_| writer.write_all(&[prefix_scheme_to_byte(self.db.prefix_scheme())]))
+            .and_then(|_| match self.db.prefix_salt() {
+                Some(salt) => writer.write_all(&[1]).and_then(|_| writer.write_all(&salt)),
+                None => writer.write_all(&[0]),
+            })
+            .map_err(Error::SnapshotIo)?;
+
+        let mut count: u64 = 0;
+        self.db.for_each_record(
+            read_snapshot.transaction(),
+            |column_family, key, value| -> Result<(), Error> {
+                writer
+                    .write_all(&[column_family_to_byte(column_family)])
+                    .map_err(Error::SnapshotIo)?;
+                write_bytes(&mut writer, key)?;
+                write_bytes(&mut writer, value)?;
+                count += 1;
+                Ok(())
+            },
+        )?;
+
+        writer
+            .write_all(&[END_OF_RECORDS])
+            .and_then(|_| writer.write_all(&count.to_be_bytes()))
+            .and_then(|_| writer.flush())
+            .map_err(Error::SnapshotIo)
+    }
+
+    /// Imports a snapshot written by [`Self::export_snapshot`] from `reader`.
+    /// The GroveDB must be empty and use the prefix scheme and salt of the
+    /// exported one. Records are written in bounded chunks while the
+    /// snapshot is read, and removed again if a record turns out truncated
+    /// or malformed, so such a snapshot leaves the GroveDB empty. If the root hash of the imported state doesn't match the one
+    /// of the snapshot, an error is returned and the GroveDB should be
+    /// discarded.
+    pub fn import_snapshot<R: Read>(&self, mut reader: R) -> Result<(), Error> {
+        let magic: [u8; 8] = read_array(&mut reader)?;
+        if &magic != SNAPSHOT_MAGIC {
+            return Err(Error::CorruptedData("not a GroveDB snapshot".to_owned()));
+        }
+        let [version]: [u8; 1] = read_array(&mut reader)?;
+        if version != SNAPSHOT_VERSION {
+            return Err(Error::UnsupportedSchemaVersion(format!(
+                "snapshot has version {}, this version of GroveDB supports version {}",
+                version, SNAPSHOT_VERSION
+            )));
+        }
+        let root_hash: [u8; 32] = read_array(&mut reader)?;
+        let [prefix_scheme]: [u8; 1] = read_array(&mut reader)?;
+        if prefix_scheme != prefix_scheme_to_byte(self.db.prefix_scheme()) {
+            return Err(Error::InvalidInput(
+                "snapshot prefix scheme doesn't match the one of the storage",
+            ));
+        }
+        let salt = match read_array::<_, 1>(&mut reader)? {
+            [0] => None,
+            [1] => Some(read_array::<_, 32>(&mut reader)?),
+            _ => {
+                return Err(Error::CorruptedData(
+                    "invalid snapshot prefix salt flag".to_owned(),
+                ))
+            }
+        };
+        if salt != self.db.prefix_salt() {
+            return Err(Error::InvalidInput(
+                "snapshot prefix salt doesn't match the one of the storage",
+            ));
+        }
+
+        let mut count: u64 = 0;
+        let mut ended = false;
+        let records = std::iter::from_fn(|| {
+            if ended {
+                return None;
+            }
+            let record = read_record(&mut reader, &mut count);
+            if !matches!(record, Ok(Some(_))) {
+                ended = true;
+            }
+            record.transpose()
+        });
+        self.db.import_records(records)?;
+
+        self.load_reverse_key_index_state()?;
+        self.load_prefix_registry_state()?;
+        self.load_backlinks_state()?;
+        #[cfg(feature = "audit_log")]
+        self.load_audit_log_state()?;
+
+        let imported_root_hash = self.root_hash(None).unwrap()?;
+        if imported_root_hash != root_hash {
+            return Err(Error::CorruptedData(format!(
+                "imported root hash {} doesn't match the snapshot root hash {}",
+                hex::encode(imported_root_hash),
+                hex::encode(root_hash)
+            )));
+        }
+        Ok(())
+    }
+}
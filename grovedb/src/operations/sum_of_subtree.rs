@@ -0,0 +1,146 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Subtree sum operations
+
+#[cfg(feature = "full")]
+use grovedb_costs::{cost_return_on_error, CostResult, CostsExt, OperationCost};
+use grovedb_path::SubtreePath;
+
+#[cfg(feature = "full")]
+use crate::{Element, Error, GroveDb, TransactionArg};
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Get the aggregated sum of the sum tree at the given path.
+    /// The sum is read from the sum tree element stored in the parent, so the
+    /// subtree itself doesn't need to be iterated.
+    pub fn sum_of_subtree<'b, B, P>(
+        &self,
+        path: P,
+        transaction: TransactionArg,
+    ) -> CostResult<i64, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        let (parent_path, key) = match path.derive_parent() {
+            Some(parent) => parent,
+            None => {
+                return Err(Error::InvalidPath(
+                    "the root tree is not a sum tree".to_owned(),
+                ))
+                .wrap_with_cost(cost)
+            }
+        };
+
+        let element = cost_return_on_error!(&mut cost, self.get_raw(parent_path, key, transaction));
+        match element {
+            Element::SumTree(_, sum, _) => Ok(sum).wrap_with_cost(cost),
+            _ => Err(Error::WrongElementType(
+                "the subtree at the given path is not a sum tree",
+            ))
+            .wrap_with_cost(cost),
+        }
+    }
+
+    /// Get the aggregated sum of the sum tree or big sum tree at the given
+    /// path, as a 128 bit integer.
+    pub fn big_sum_of_subtree<'b, B, P>(
+        &self,
+        path: P,
+        transaction: TransactionArg,
+    ) -> CostResult<i128, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        let (parent_path, key) = match path.derive_parent() {
+            Some(parent) => parent,
+            None => {
+                return Err(Error::InvalidPath(
+                    "the root tree is not a sum tree".to_owned(),
+                ))
+                .wrap_with_cost(cost)
+            }
+        };
+
+        let element = cost_return_on_error!(&mut cost, self.get_raw(parent_path, key, transaction));
+        match element {
+            Element::SumTree(_, sum, _) => Ok(sum as i128).wrap_with_cost(cost),
+            Element::BigSumTree(_, sum, _) => Ok(sum).wrap_with_cost(cost),
+            _ => Err(Error::WrongElementType(
+                "the subtree at the given path is not a sum tree",
+            ))
+            .wrap_with_cost(cost),
+        }
+    }
+
+    /// Get the aggregate of the aggregate tree at the given path, `None` if
+    /// none of its elements has a value. Like sums, the aggregate is read
+    /// from the aggregate tree element stored in the parent.
+    pub fn aggregate_of_subtree<'b, B, P>(
+        &self,
+        path: P,
+        transaction: TransactionArg,
+    ) -> CostResult<Option<i128>, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        let (parent_path, key) = match path.derive_parent() {
+            Some(parent) => parent,
+            None => {
+                return Err(Error::InvalidPath(
+                    "the root tree is not an aggregate tree".to_owned(),
+                ))
+                .wrap_with_cost(cost)
+            }
+        };
+
+        let element = cost_return_on_error!(&mut cost, self.get_raw(parent_path, key, transaction));
+        match element {
+            Element::AggregateTree(_, _, aggregate_value, _) => {
+                Ok(aggregate_value).wrap_with_cost(cost)
+            }
+            _ => Err(Error::WrongElementType(
+                "the subtree at the given path is not an aggregate tree",
+            ))
+            .wrap_with_cost(cost),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Read-modify-write operations
+
+#[cfg(feature = "full")]
+use grovedb_costs::{
+    cost_return_on_error, cost_return_on_error_no_add, CostResult, CostsExt, OperationCost,
+};
+use grovedb_path::SubtreePath;
+#[cfg(feature = "full")]
+use grovedb_storage::Storage;
+
+#[cfg(feature = "full")]
+use crate::{Element, Error, GroveDb, Transaction, TransactionArg};
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Loads the element stored under `key` of the subtree at `path` without
+    /// following references, passes it to `f` and writes back the result: the
+    /// returned element replaces the loaded one, and `None` deletes it.
+    /// Without a transaction argument the update runs in its own snapshot
+    /// transaction, which is committed before returning: if the element is
+    /// changed and committed by someone else after the update started, the
+    /// commit fails instead of losing either write. With a transaction
+    /// argument, reading and writing happen within that transaction and are
+    /// only protected against concurrent changes as far as it is. Returns the
+    /// element written back.
+    pub fn update<'b, B, P, F>(
+        &self,
+        path: P,
+        key: &[u8],
+        f: F,
+        transaction: TransactionArg,
+    ) -> CostResult<Option<Element>, Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+        F: FnOnce(Option<Element>) -> Option<Element>,
+    {
+        let mut cost = OperationCost::default();
+        let path: SubtreePath<B> = path.into();
+
+        match transaction {
+            Some(transaction) => self.update_on_transaction(path, key, f, transaction),
+            None => {
+                let transaction = cost_return_on_error_no_add!(
+                    &cost,
+                    self.db
+                        .start_snapshot_transaction()
+                        .map(Transaction::new)
+                        .map_err(Error::from)
+                );
+                let updated = cost_return_on_error!(
+                    &mut cost,
+                    self.update_on_transaction(path, key, f, &transaction)
+                );
+                self.commit_transaction(transaction)
+                    .map_ok(|_| updated)
+                    .add_cost(cost)
+            }
+        }
+    }
+
+    fn update_on_transaction<B, F>(
+        &self,
+        path: SubtreePath<B>,
+        key: &[u8],
+        f: F,
+        transaction: &Transaction,
+    ) -> CostResult<Option<Element>, Error>
+    where
+        B: AsRef<[u8]>,
+        F: FnOnce(Option<Element>) -> Option<Element>,
+    {
+        let mut cost = OperationCost::default();
+
+        let existing = cost_return_on_error!(
+            &mut cost,
+            self.get_raw_optional(path.clone(), key, Some(transaction))
+        );
+        let existed = existing.is_some();
+
+        match f(existing) {
+            Some(element) => self
+                .insert(path, key, element.clone(), None, Some(transaction))
+                .map_ok(|_| Some(element))
+                .add_cost(cost),
+            None if existed => self
+                .delete(path, key, None, Some(transaction))
+                .map_ok(|_| None)
+                .add_cost(cost),
+            None => Ok(None).wrap_with_cost(cost),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Flags only updates
+//!
+//! Rewrites the flags of a stored element while keeping the rest of the
+//! element as is. The element doesn't have to be read and inserted back by
+//! the caller, and the storage costs are the ones of the change in size of
+//! the flags.
+
+#[cfg(feature = "full")]
+use grovedb_costs::CostResult;
+use grovedb_path::SubtreePath;
+
+#[cfg(feature = "full")]
+use crate::{batch::GroveDbOp, ElementFlags, Error, GroveDb, TransactionArg};
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Replaces the flags of the element under `key` of the subtree at `path`
+    /// with `new_flags`. Items and references keep their value, trees keep
+    /// their subtree. Fails if there is no element under `key`.
+    pub fn update_element_flags<'b, B, P>(
+        &self,
+        path: P,
+        key: &[u8],
+        new_flags: Option<ElementFlags>,
+        transaction: TransactionArg,
+    ) -> CostResult<(), Error>
+    where
+        B: AsRef<[u8]> + 'b,
+        P: Into<SubtreePath<'b, B>>,
+    {
+        let path: SubtreePath<B> = path.into();
+        self.apply_batch(
+            vec![GroveDbOp::update_flags_op(
+                path.to_vec(),
+                key.to_vec(),
+                new_flags,
+            )],
+            None,
+            transaction,
+        )
+    }
+}
@@ -0,0 +1,133 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Value size report
+//!
+//! A maintenance scan over the whole grove measuring the serialized size of
+//! every element, to find the subtrees and elements bloating proofs and disk.
+
+#[cfg(feature = "full")]
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap},
+};
+
+#[cfg(feature = "full")]
+use grovedb_costs::{cost_return_on_error, CostResult, CostsExt, OperationCost};
+
+#[cfg(feature = "full")]
+use crate::{Error, GroveDb, TransactionArg};
+
+#[cfg(feature = "full")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Histogram of the value sizes of a subtree
+pub struct ValueSizeHistogram {
+    /// Number of values by size bucket: bucket 0 counts empty values and
+    /// bucket `i` counts values of `2^(i-1)` to `2^i - 1` bytes
+    pub buckets: Vec<u64>,
+    /// Number of values
+    pub count: u64,
+    /// Sum of the sizes of the values
+    pub total_bytes: u64,
+}
+
+#[cfg(feature = "full")]
+impl ValueSizeHistogram {
+    /// Bucket a value of `size` bytes is counted in
+    pub fn bucket_of(size: usize) -> usize {
+        (usize::BITS - size.leading_zeros()) as usize
+    }
+
+    /// Counts a value of `size` bytes
+    fn add(&mut self, size: usize) {
+        let bucket = Self::bucket_of(size);
+        if self.buckets.len() <= bucket {
+            self.buckets.resize(bucket + 1, 0);
+        }
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.total_bytes += size as u64;
+    }
+}
+
+#[cfg(feature = "full")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Value sizes of the grove
+pub struct ValueSizeReport {
+    /// Histogram of the value sizes of each subtree, by subtree path
+    pub histograms: BTreeMap<Vec<Vec<u8>>, ValueSizeHistogram>,
+    /// Path, key and value size of the largest elements, largest first
+    pub largest: Vec<(Vec<Vec<u8>>, Vec<u8>, usize)>,
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Scans the whole grove and reports the value sizes of every subtree
+    /// along with the `largest` biggest elements. Value sizes are the sizes
+    /// of the serialized elements.
+    pub fn value_size_report(
+        &self,
+        largest: usize,
+        transaction: TransactionArg,
+    ) -> CostResult<ValueSizeReport, Error> {
+        let mut cost = OperationCost::default();
+        let mut histograms: BTreeMap<Vec<Vec<u8>>, ValueSizeHistogram> = BTreeMap::new();
+        let mut largest_heap = BinaryHeap::with_capacity(largest.saturating_add(1));
+
+        let mut cursor = None;
+        loop {
+            let page =
+                cost_return_on_error!(&mut cost, self.scan(cursor.as_ref(), u16::MAX, transaction));
+            for (path, key, element) in page.elements {
+                let size = element.serialized_size();
+                histograms.entry(path.clone()).or_default().add(size);
+                if largest > 0 {
+                    largest_heap.push(Reverse((size, path, key)));
+                    if largest_heap.len() > largest {
+                        largest_heap.pop();
+                    }
+                }
+            }
+            cursor = page.cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        let largest = largest_heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((size, path, key))| (path, key, size))
+            .collect();
+        Ok(ValueSizeReport {
+            histograms,
+            largest,
+        })
+        .wrap_with_cost(cost)
+    }
+}
@@ -0,0 +1,85 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Read snapshots
+
+use grovedb_costs::CostResult;
+use grovedb_storage::Storage;
+
+use crate::{Error, GroveDb, PathQuery, Transaction};
+
+/// Read-only view of a GroveDb pinned to the state it had when the snapshot
+/// was taken. Gets and queries made with [`ReadSnapshot::transaction`] don't
+/// see writes committed afterwards.
+///
+/// Proofs of the pinned state are generated with
+/// [`GroveDb::prove_query_in_snapshot`] and
+/// [`GroveDb::prove_verbose_in_snapshot`].
+///
+/// The snapshot is backed by a transaction which is never committed, so
+/// writes made with it are discarded when the snapshot is dropped.
+pub struct ReadSnapshot<'db> {
+    transaction: Transaction<'db>,
+}
+
+impl GroveDb {
+    /// Takes a snapshot of the current state of the database to read from,
+    /// failing on a read-only secondary GroveDB
+    pub fn snapshot(&self) -> Result<ReadSnapshot<'_>, Error> {
+        Ok(ReadSnapshot {
+            transaction: Transaction::new(self.db.start_snapshot_transaction()?),
+        })
+    }
+
+    /// Generate a minimalistic proof for a given path query, as of the state
+    /// pinned by `snapshot`, see [`Self::prove_query`]
+    pub fn prove_query_in_snapshot(
+        &self,
+        query: &PathQuery,
+        snapshot: &ReadSnapshot,
+    ) -> CostResult<Vec<u8>, Error> {
+        self.prove_internal(query, false, Some(snapshot.transaction()))
+    }
+
+    /// Generate a verbose proof for a given path query, as of the state
+    /// pinned by `snapshot`, see [`Self::prove_verbose`]
+    pub fn prove_verbose_in_snapshot(
+        &self,
+        query: &PathQuery,
+        snapshot: &ReadSnapshot,
+    ) -> CostResult<Vec<u8>, Error> {
+        self.prove_internal(query, true, Some(snapshot.transaction()))
+    }
+}
+
+impl<'db> ReadSnapshot<'db> {
+    /// Transaction to pass to reads made from the snapshot
+    pub fn transaction(&self) -> &Transaction<'db> {
+        &self.transaction
+    }
+}
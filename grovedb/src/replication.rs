@@ -28,6 +28,9 @@
 
 //! Replication
 
+pub mod diff;
+pub mod messages;
+
 use std::{
     collections::VecDeque,
     iter::{empty, once},
@@ -221,9 +224,12 @@ impl<'db> Restorer<'db> {
                     value_hash,
                     feature_type,
                 )) => {
-                    if let Element::Tree(root_key, _) | Element::SumTree(root_key, ..) =
-                        Element::deserialize(value_bytes)
-                            .map_err(|e| RestorerError(e.to_string()))?
+                    if let Element::Tree(root_key, _)
+                    | Element::SumTree(root_key, ..)
+                    | Element::CountTree(root_key, ..)
+                    | Element::BigSumTree(root_key, ..)
+                    | Element::AggregateTree(root_key, ..) = Element::deserialize(value_bytes)
+                        .map_err(|e| RestorerError(e.to_string()))?
                     {
                         if root_key.is_none() || self.current_merk_path.last() == Some(key) {
                             // We add only subtrees of the current subtree to queue, skipping
@@ -356,7 +362,12 @@ impl<'db> SiblingsChunkProducer<'db> {
         }
 
         while let Some(element) = siblings_iter.next_element().unwrap()? {
-            if let (key, Element::Tree(..)) | (key, Element::SumTree(..)) = element {
+            if let (key, Element::Tree(..))
+            | (key, Element::SumTree(..))
+            | (key, Element::CountTree(..))
+            | (key, Element::BigSumTree(..))
+            | (key, Element::AggregateTree(..)) = element
+            {
                 siblings_keys.push_back(key);
             }
         }
@@ -470,7 +481,7 @@ mod test {
         {
             let replica_db = GroveDb::open(replica_tempdir.path()).unwrap();
             let mut chunk_producer = original_db.chunks();
-            let tx = replica_db.start_transaction();
+            let tx = replica_db.start_transaction().unwrap();
 
             let mut restorer = Restorer::new(
                 &replica_db,
@@ -505,7 +516,7 @@ mod test {
         {
             let replica_grove_db = GroveDb::open(replica_tempdir.path()).unwrap();
             let mut chunk_producer = SiblingsChunkProducer::new(original_db.chunks());
-            let tx = replica_grove_db.start_transaction();
+            let tx = replica_grove_db.start_transaction().unwrap();
 
             let mut restorer = BufferedRestorer::new(
                 Restorer::new(
@@ -585,7 +596,7 @@ mod test {
 
         let tmp_dir = TempDir::new().unwrap();
         let restored_db = GroveDb::open(tmp_dir.path()).unwrap();
-        let tx = restored_db.start_transaction();
+        let tx = restored_db.start_transaction().unwrap();
         let mut restorer = Restorer::new(&restored_db, bad_hash, &tx).unwrap();
         let mut chunks = db.chunks();
         assert!(restorer
@@ -619,7 +630,7 @@ mod test {
 
         let tmp_dir = TempDir::new().unwrap();
         let restored_db = GroveDb::open(tmp_dir.path()).unwrap();
-        let tx = restored_db.start_transaction();
+        let tx = restored_db.start_transaction().unwrap();
         let mut restorer = Restorer::new(&restored_db, expected_hash, &tx).unwrap();
         let mut chunks = db.chunks();
 
@@ -0,0 +1,419 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Incremental state sync
+//!
+//! Brings a replica that already holds an older state up to date by only
+//! transferring the subtrees that changed. GroveDB keeps no history, so the
+//! source can't compute the difference from the older root hash alone: the
+//! replica first sends the root hashes of all its subtrees
+//! ([`GroveDb::subtree_hashes`]), then the source sends back the chunks of
+//! every subtree whose root hash differs ([`GroveDb::state_diff`]), and the
+//! replica verifies and applies them ([`GroveDb::apply_diff`]). A subtree
+//! having the same root hash on both sides has the same content, as do all
+//! of its descendants, so only changed subtrees and their ancestors are
+//! transferred.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use grovedb_costs::{cost_return_on_error, CostResult, CostsExt, OperationCost};
+use grovedb_merk::{
+    proofs::{Node, Op},
+    tree::{combine_hash, value_hash},
+    ROOT_KEY_KEY,
+};
+use grovedb_path::SubtreePath;
+use grovedb_storage::{Batch, RawIterator, Storage, StorageContext};
+
+use super::{MerkRestorer, Path};
+use crate::{util::merk_optional_tx, Element, Error, GroveDb, Hash, Transaction, TransactionArg};
+
+/// Root hashes of all subtrees of a GroveDB, keyed by path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubtreeHashes {
+    hashes: BTreeMap<Path, Hash>,
+}
+
+impl SubtreeHashes {
+    /// Root hash of the GroveDB the subtree hashes were taken from
+    pub fn root_hash(&self) -> Hash {
+        self.get(&[])
+            .copied()
+            .expect("the root subtree always exists")
+    }
+
+    /// Root hash of the subtree at `path`, if there is one
+    pub fn get(&self, path: &[Vec<u8>]) -> Option<&Hash> {
+        self.hashes.get(path)
+    }
+
+    /// Iterates over the paths of the subtrees and their root hashes
+    pub fn iter(&self) -> impl Iterator<Item = (&Path, &Hash)> {
+        self.hashes.iter()
+    }
+}
+
+/// Chunks of a subtree that changed between two states
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubtreeDiff {
+    /// Path of the subtree
+    pub path: Path,
+    /// Chunks of the subtree in the newer state, in order, none if it is
+    /// empty
+    pub chunks: Vec<Vec<Op>>,
+}
+
+/// Subtrees that changed between two states of a GroveDB
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateDiff {
+    /// Root hash of the older state
+    pub from_root: Hash,
+    /// Root hash of the newer state
+    pub to_root: Hash,
+    /// Changed subtrees, a subtree always coming after its parent
+    pub subtrees: Vec<SubtreeDiff>,
+}
+
+impl GroveDb {
+    /// Returns the root hashes of all subtrees, for a source to compute a
+    /// [`StateDiff`] against
+    pub fn subtree_hashes(&self, transaction: TransactionArg) -> CostResult<SubtreeHashes, Error> {
+        let mut cost = OperationCost::default();
+
+        let paths = cost_return_on_error!(
+            &mut cost,
+            self.find_subtrees(&SubtreePath::empty(), transaction)
+        );
+        let mut hashes = BTreeMap::new();
+        for path in paths {
+            let subtree_path: SubtreePath<Vec<u8>> = path.as_slice().into();
+            let root_hash = merk_optional_tx!(
+                &mut cost,
+                self.db,
+                subtree_path,
+                None,
+                transaction,
+                subtree,
+                { subtree.root_hash().unwrap_add_cost(&mut cost) }
+            );
+            hashes.insert(path, root_hash);
+        }
+        Ok(SubtreeHashes { hashes }).wrap_with_cost(cost)
+    }
+
+    /// Computes the subtrees that changed between the state described by
+    /// `from`, usually taken on a replica, and the current state, which must
+    /// have the root hash `to_root`
+    pub fn state_diff(&self, from: &SubtreeHashes, to_root: Hash) -> Result<StateDiff, Error> {
+        let to = self.subtree_hashes(None).unwrap()?;
+        if to.root_hash() != to_root {
+            return Err(Error::InvalidInput(
+                "the state to diff to is not the current state",
+            ));
+        }
+
+        let mut changed: Vec<&Path> = to
+            .iter()
+            .filter(|(path, hash)| from.get(path) != Some(*hash))
+            .map(|(path, _)| path)
+            .collect();
+        changed.sort_by_key(|path| path.len());
+
+        let mut chunk_producer = self.chunks();
+        let mut subtrees = Vec::with_capacity(changed.len());
+        for path in changed {
+            let segments = || path.iter().map(|segment| segment.as_slice());
+            let mut chunks = Vec::new();
+            let first = chunk_producer.get_chunk(segments(), 0)?;
+            if !first.is_empty() {
+                chunks.push(first);
+                for index in 1..chunk_producer.chunks_in_current_producer() {
+                    chunks.push(chunk_producer.get_chunk(segments(), index)?);
+                }
+            }
+            subtrees.push(SubtreeDiff {
+                path: path.clone(),
+                chunks,
+            });
+        }
+
+        Ok(StateDiff {
+            from_root: from.root_hash(),
+            to_root,
+            subtrees,
+        })
+    }
+
+    /// Verifies `diff` and applies it within `tx`. The state must be the one
+    /// the diff was computed from, and ends up being the one it was computed
+    /// to; on error the transaction should be rolled back.
+    pub fn apply_diff(&self, diff: StateDiff, tx: &Transaction) -> Result<(), Error> {
+        if self.root_hash(Some(tx)).unwrap()? != diff.from_root {
+            return Err(Error::InvalidInput(
+                "the diff doesn't apply to the current state",
+            ));
+        }
+        let previous_paths = self
+            .find_subtrees(&SubtreePath::empty(), Some(tx))
+            .unwrap()?;
+
+        // Value and value hash of the elements of the subtrees announced by the
+        // parent subtrees restored so far
+        let mut announced: HashMap<Path, (Vec<u8>, Hash)> = HashMap::new();
+        let mut restored_from_chunks: HashSet<Path> = HashSet::new();
+        for SubtreeDiff { path, chunks } in diff.subtrees {
+            let (combining_value, expected_hash) = if path.is_empty() {
+                (None, diff.to_root)
+            } else {
+                let (value, hash) = announced.get(&path).ok_or(Error::InvalidInput(
+                    "diff has a subtree its parent doesn't hold",
+                ))?;
+                (Some(value.clone()), *hash)
+            };
+            self.clear_subtree_storage(&path, tx)?;
+            if chunks.is_empty() {
+                // Checked against its parent along with the unchanged subtrees
+                continue;
+            }
+
+            let merk = self.open_merk_for_replication(path.as_slice().into(), tx)?;
+            let mut restorer = MerkRestorer::new(merk, combining_value, expected_hash);
+            let mut remaining = usize::MAX;
+            for ops in chunks {
+                for op in ops.iter() {
+                    if let Op::Push(Node::KVValueHashFeatureType(key, value, hash, _))
+                    | Op::PushInverted(Node::KVValueHashFeatureType(key, value, hash, _)) = op
+                    {
+                        if Element::deserialize(value)?.is_tree() {
+                            let mut child_path = path.clone();
+                            child_path.push(key.clone());
+                            announced.insert(child_path, (value.clone(), *hash));
+                        }
+                    }
+                }
+                remaining = restorer.process_chunk(ops).map_err(Error::MerkError)?;
+            }
+            if remaining != 0 {
+                return Err(Error::InvalidInput("diff misses chunks of a subtree"));
+            }
+            restorer.finalize().map_err(Error::MerkError)?;
+            restored_from_chunks.insert(path);
+        }
+
+        // Subtrees restored from chunks were verified by the Merk restorer, the
+        // others must match the value hash their parent holds
+        for (path, (value, hash)) in announced {
+            if restored_from_chunks.contains(&path) {
+                continue;
+            }
+            let merk = self.open_merk_for_replication(path.as_slice().into(), tx)?;
+            let combined_hash =
+                combine_hash(&value_hash(&value).unwrap(), &merk.root_hash().unwrap()).unwrap();
+            if combined_hash != hash {
+                return Err(Error::CorruptedData(format!(
+                    "subtree {:?} doesn't match the hash held by its parent",
+                    path
+                )));
+            }
+        }
+
+        let current_paths: HashSet<Path> = self
+            .find_subtrees(&SubtreePath::empty(), Some(tx))
+            .unwrap()?
+            .into_iter()
+            .collect();
+        for path in previous_paths {
+            if !current_paths.contains(&path) {
+                self.clear_subtree_storage(&path, tx)?;
+            }
+        }
+
+        let root_hash = self.root_hash(Some(tx)).unwrap()?;
+        if root_hash != diff.to_root {
+            return Err(Error::CorruptedData(format!(
+                "root hash {} after applying the diff doesn't match the expected {}",
+                hex::encode(root_hash),
+                hex::encode(diff.to_root)
+            )));
+        }
+        Ok(())
+    }
+
+    /// Deletes the nodes and root key of the subtree at `path`
+    fn clear_subtree_storage(&self, path: &[Vec<u8>], tx: &Transaction) -> Result<(), Error> {
+        let storage = self
+            .db
+            .get_immediate_storage_context(SubtreePath::from(path), tx)
+            .unwrap();
+        let mut batch = storage.new_batch();
+        let mut iter = storage.raw_iter();
+        iter.seek_to_first().unwrap();
+        while iter.valid().unwrap() {
+            if let Some(key) = iter.key().unwrap() {
+                batch.delete(key, None);
+            }
+            iter.next().unwrap();
+        }
+        drop(iter);
+        batch.delete_root(ROOT_KEY_KEY, None);
+        storage.commit_batch(batch).unwrap()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::{
+        operations::delete::DeleteOptions,
+        tests::{common::EMPTY_PATH, make_deep_tree, ANOTHER_TEST_LEAF, TEST_LEAF},
+    };
+
+    fn sync(source: &GroveDb, replica: &GroveDb) -> StateDiff {
+        let from = replica.subtree_hashes(None).unwrap().unwrap();
+        let to_root = source.root_hash(None).unwrap().unwrap();
+        let diff = source.state_diff(&from, to_root).unwrap();
+        let tx = replica.start_transaction().unwrap();
+        replica.apply_diff(diff.clone(), &tx).unwrap();
+        replica.commit_transaction(tx).unwrap().unwrap();
+        assert_eq!(replica.root_hash(None).unwrap().unwrap(), to_root);
+        diff
+    }
+
+    #[test]
+    fn test_incremental_sync() {
+        let source = make_deep_tree();
+        let replica_dir = TempDir::new().unwrap();
+        let replica = GroveDb::open(replica_dir.path()).unwrap();
+
+        // Syncing an empty replica transfers every subtree
+        let diff = sync(&source, &replica);
+        assert_eq!(
+            diff.subtrees.len(),
+            source.subtree_hashes(None).unwrap().unwrap().iter().count()
+        );
+
+        source
+            .insert(
+                [TEST_LEAF, b"innertree"].as_ref(),
+                b"key4",
+                Element::new_item(b"value4".to_vec()),
+                None,
+                None,
+            )
+            .unwrap()
+            .unwrap();
+        source
+            .delete(
+                [ANOTHER_TEST_LEAF].as_ref(),
+                b"innertree3",
+                Some(DeleteOptions {
+                    allow_deleting_non_empty_trees: true,
+                    deleting_non_empty_trees_returns_error: false,
+                    ..Default::default()
+                }),
+                None,
+            )
+            .unwrap()
+            .unwrap();
+        source
+            .insert(
+                [ANOTHER_TEST_LEAF].as_ref(),
+                b"innertree5",
+                Element::empty_tree(),
+                None,
+                None,
+            )
+            .unwrap()
+            .unwrap();
+
+        // Only the changed subtrees and their ancestors are transferred
+        let diff = sync(&source, &replica);
+        let paths: Vec<Path> = diff.subtrees.iter().map(|s| s.path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                vec![],
+                vec![TEST_LEAF.to_vec()],
+                vec![ANOTHER_TEST_LEAF.to_vec()],
+                vec![TEST_LEAF.to_vec(), b"innertree".to_vec()],
+                vec![ANOTHER_TEST_LEAF.to_vec(), b"innertree5".to_vec()],
+            ]
+        );
+        assert_eq!(
+            replica
+                .get([TEST_LEAF, b"innertree"].as_ref(), b"key4", None)
+                .unwrap()
+                .unwrap(),
+            Element::new_item(b"value4".to_vec())
+        );
+        assert!(replica
+            .get([ANOTHER_TEST_LEAF].as_ref(), b"innertree3", None)
+            .unwrap()
+            .is_err());
+        assert_eq!(
+            replica.subtree_hashes(None).unwrap().unwrap(),
+            source.subtree_hashes(None).unwrap().unwrap()
+        );
+
+        // A diff only applies to the state it was computed from
+        let tx = replica.start_transaction().unwrap();
+        assert!(matches!(
+            replica.apply_diff(diff, &tx),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_apply_tampered_diff() {
+        let source = make_deep_tree();
+        let replica_dir = TempDir::new().unwrap();
+        let replica = GroveDb::open(replica_dir.path()).unwrap();
+        sync(&source, &replica);
+
+        source
+            .insert(
+                EMPTY_PATH,
+                b"key",
+                Element::new_item(b"value".to_vec()),
+                None,
+                None,
+            )
+            .unwrap()
+            .unwrap();
+        let from = replica.subtree_hashes(None).unwrap().unwrap();
+        let to_root = source.root_hash(None).unwrap().unwrap();
+        let mut diff = source.state_diff(&from, to_root).unwrap();
+        diff.to_root = [0; 32];
+
+        let tx = replica.start_transaction().unwrap();
+        assert!(replica.apply_diff(diff, &tx).is_err());
+    }
+}
@@ -0,0 +1,505 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! State sync messages
+//!
+//! Request/response types exchanged between a node serving its state
+//! ([`SourceSession`], backed by a [`SubtreeChunkProducer`]) and a node
+//! restoring it ([`RestoreSession`], backed by a [`Restorer`]). Both message
+//! enums can be encoded with `ed` or serialized with `serde`, so integrators
+//! only need to move bytes between peers.
+//!
+//! A session goes as follows:
+//! 1. the source sends a [`SyncResponse::Manifest`] with the root hash to
+//!    restore;
+//! 2. the restorer answers with [`SyncRequest::GetChunk`] requests which the
+//!    source answers with [`SyncResponse::ChunkProof`]s;
+//! 3. once the whole state is restored the restorer sends a
+//!    [`SyncRequest::Ack`] with the resulting root hash which finishes the
+//!    session on both sides.
+
+use std::io::{Read, Write};
+
+use grovedb_merk::{
+    ed::{self, Decode, Encode, Terminated},
+    proofs::{encode_into, Decoder, Op},
+};
+use integer_encoding::{VarInt, VarIntReader, VarIntWriter};
+use serde::{Deserialize, Serialize};
+
+use super::{Path, Restorer, RestorerResponse, SubtreeChunkProducer};
+use crate::{Error, GroveDb, Hash, Transaction};
+
+/// Version of the state sync protocol, announced in the manifest.
+pub const SYNC_PROTOCOL_VERSION: u32 = 1;
+
+/// Message sent by the restoring side.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyncRequest {
+    /// Request a chunk of the subtree at `path`
+    GetChunk {
+        /// Path of the subtree
+        path: Path,
+        /// Index of the chunk within the subtree
+        index: usize,
+    },
+    /// Acknowledge that restoration is complete
+    Ack {
+        /// Root hash of the restored state
+        root_hash: Hash,
+    },
+}
+
+/// Message sent by the source side.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyncResponse {
+    /// Describes the state to be restored
+    Manifest {
+        /// Root hash of the state
+        root_hash: Hash,
+        /// Protocol version used by the source
+        version: u32,
+    },
+    /// Chunk of a subtree
+    ChunkProof {
+        /// Path of the subtree
+        path: Path,
+        /// Index of the chunk within the subtree
+        index: usize,
+        /// Encoded chunk proof operations
+        chunk: Vec<u8>,
+    },
+}
+
+impl SyncResponse {
+    /// Builds a chunk proof message from chunk operations.
+    pub fn chunk_proof(path: Path, index: usize, ops: &[Op]) -> Self {
+        let mut chunk = Vec::new();
+        encode_into(ops.iter(), &mut chunk);
+        SyncResponse::ChunkProof { path, index, chunk }
+    }
+}
+
+fn encode_bytes<W: Write>(bytes: &[u8], dest: &mut W) -> ed::Result<()> {
+    dest.write_varint(bytes.len())?;
+    dest.write_all(bytes)?;
+    Ok(())
+}
+
+fn bytes_encoding_length(bytes: &[u8]) -> usize {
+    bytes.len().required_space() + bytes.len()
+}
+
+fn decode_bytes<R: Read>(input: &mut R) -> ed::Result<Vec<u8>> {
+    let len: usize = input.read_varint()?;
+    let mut bytes = vec![0; len];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn encode_path<W: Write>(path: &Path, dest: &mut W) -> ed::Result<()> {
+    dest.write_varint(path.len())?;
+    for segment in path {
+        encode_bytes(segment, dest)?;
+    }
+    Ok(())
+}
+
+fn path_encoding_length(path: &Path) -> usize {
+    path.len().required_space()
+        + path
+            .iter()
+            .map(|segment| bytes_encoding_length(segment))
+            .sum::<usize>()
+}
+
+fn decode_path<R: Read>(input: &mut R) -> ed::Result<Path> {
+    let len: usize = input.read_varint()?;
+    (0..len).map(|_| decode_bytes(input)).collect()
+}
+
+impl Terminated for SyncRequest {}
+
+impl Encode for SyncRequest {
+    fn encode_into<W: Write>(&self, dest: &mut W) -> ed::Result<()> {
+        match self {
+            SyncRequest::GetChunk { path, index } => {
+                dest.write_all(&[0])?;
+                encode_path(path, dest)?;
+                dest.write_varint(*index)?;
+            }
+            SyncRequest::Ack { root_hash } => {
+                dest.write_all(&[1])?;
+                dest.write_all(root_hash)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn encoding_length(&self) -> ed::Result<usize> {
+        Ok(match self {
+            SyncRequest::GetChunk { path, index } => {
+                1 + path_encoding_length(path) + index.required_space()
+            }
+            SyncRequest::Ack { root_hash } => 1 + root_hash.len(),
+        })
+    }
+}
+
+impl Decode for SyncRequest {
+    fn decode<R: Read>(mut input: R) -> ed::Result<Self> {
+        let mut variant = [0u8];
+        input.read_exact(&mut variant)?;
+        match variant[0] {
+            0 => {
+                let path = decode_path(&mut input)?;
+                let index = input.read_varint()?;
+                Ok(SyncRequest::GetChunk { path, index })
+            }
+            1 => {
+                let mut root_hash = Hash::default();
+                input.read_exact(&mut root_hash)?;
+                Ok(SyncRequest::Ack { root_hash })
+            }
+            byte => Err(ed::Error::UnexpectedByte(byte)),
+        }
+    }
+}
+
+impl Terminated for SyncResponse {}
+
+impl Encode for SyncResponse {
+    fn encode_into<W: Write>(&self, dest: &mut W) -> ed::Result<()> {
+        match self {
+            SyncResponse::Manifest { root_hash, version } => {
+                dest.write_all(&[0])?;
+                dest.write_all(root_hash)?;
+                dest.write_varint(*version)?;
+            }
+            SyncResponse::ChunkProof { path, index, chunk } => {
+                dest.write_all(&[1])?;
+                encode_path(path, dest)?;
+                dest.write_varint(*index)?;
+                encode_bytes(chunk, dest)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn encoding_length(&self) -> ed::Result<usize> {
+        Ok(match self {
+            SyncResponse::Manifest { root_hash, version } => {
+                1 + root_hash.len() + version.required_space()
+            }
+            SyncResponse::ChunkProof { path, index, chunk } => {
+                1 + path_encoding_length(path)
+                    + index.required_space()
+                    + bytes_encoding_length(chunk)
+            }
+        })
+    }
+}
+
+impl Decode for SyncResponse {
+    fn decode<R: Read>(mut input: R) -> ed::Result<Self> {
+        let mut variant = [0u8];
+        input.read_exact(&mut variant)?;
+        match variant[0] {
+            0 => {
+                let mut root_hash = Hash::default();
+                input.read_exact(&mut root_hash)?;
+                let version = input.read_varint()?;
+                Ok(SyncResponse::Manifest { root_hash, version })
+            }
+            1 => {
+                let path = decode_path(&mut input)?;
+                let index = input.read_varint()?;
+                let chunk = decode_bytes(&mut input)?;
+                Ok(SyncResponse::ChunkProof { path, index, chunk })
+            }
+            byte => Err(ed::Error::UnexpectedByte(byte)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SourceState {
+    Created,
+    Serving,
+    Finished,
+}
+
+/// State machine of the side serving its state.
+pub struct SourceSession<'db> {
+    chunk_producer: SubtreeChunkProducer<'db>,
+    root_hash: Hash,
+    state: SourceState,
+}
+
+impl<'db> SourceSession<'db> {
+    /// Creates a session serving the current state of `grove_db`.
+    pub fn new(grove_db: &'db GroveDb) -> Result<Self, Error> {
+        Ok(SourceSession {
+            root_hash: grove_db.root_hash(None).unwrap()?,
+            chunk_producer: grove_db.chunks(),
+            state: SourceState::Created,
+        })
+    }
+
+    /// Returns the manifest that opens the session.
+    pub fn manifest(&mut self) -> SyncResponse {
+        if self.state == SourceState::Created {
+            self.state = SourceState::Serving;
+        }
+        SyncResponse::Manifest {
+            root_hash: self.root_hash,
+            version: SYNC_PROTOCOL_VERSION,
+        }
+    }
+
+    /// Handles a request of the restoring side, returning a response to send
+    /// back or `None` once the session is finished.
+    pub fn handle_request(&mut self, request: SyncRequest) -> Result<Option<SyncResponse>, Error> {
+        if self.state != SourceState::Serving {
+            return Err(Error::InvalidInput(
+                "sync requests are only accepted after the manifest and before the ack",
+            ));
+        }
+        match request {
+            SyncRequest::GetChunk { path, index } => {
+                let ops = self
+                    .chunk_producer
+                    .get_chunk(path.iter().map(|segment| segment.as_slice()), index)?;
+                Ok(Some(SyncResponse::chunk_proof(path, index, &ops)))
+            }
+            SyncRequest::Ack { root_hash } => {
+                if root_hash != self.root_hash {
+                    return Err(Error::CorruptedData(format!(
+                        "restored root hash {} doesn't match served root hash {}",
+                        hex::encode(root_hash),
+                        hex::encode(self.root_hash)
+                    )));
+                }
+                self.state = SourceState::Finished;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Returns true once the restoring side acknowledged the restoration.
+    pub fn is_finished(&self) -> bool {
+        self.state == SourceState::Finished
+    }
+}
+
+enum RestoreState<'db> {
+    AwaitManifest,
+    AwaitChunk {
+        restorer: Box<Restorer<'db>>,
+        path: Path,
+        index: usize,
+    },
+    Finished,
+}
+
+/// State machine of the side restoring the state.
+pub struct RestoreSession<'db> {
+    grove_db: &'db GroveDb,
+    tx: &'db Transaction<'db>,
+    state: RestoreState<'db>,
+}
+
+impl<'db> RestoreSession<'db> {
+    /// Creates a session restoring state into `grove_db` within `tx`.
+    pub fn new(grove_db: &'db GroveDb, tx: &'db Transaction<'db>) -> Self {
+        RestoreSession {
+            grove_db,
+            tx,
+            state: RestoreState::AwaitManifest,
+        }
+    }
+
+    /// Handles a response of the source side, returning the next request to
+    /// send.
+    pub fn handle_response(&mut self, response: SyncResponse) -> Result<SyncRequest, Error> {
+        match (&mut self.state, response) {
+            (RestoreState::AwaitManifest, SyncResponse::Manifest { root_hash, version }) => {
+                if version != SYNC_PROTOCOL_VERSION {
+                    return Err(Error::NotSupported("unsupported sync protocol version"));
+                }
+                let restorer = Restorer::new(self.grove_db, root_hash, self.tx)
+                    .map_err(|e| Error::CorruptedData(e.0))?;
+                self.state = RestoreState::AwaitChunk {
+                    restorer: Box::new(restorer),
+                    path: vec![],
+                    index: 0,
+                };
+                Ok(SyncRequest::GetChunk {
+                    path: vec![],
+                    index: 0,
+                })
+            }
+            (
+                RestoreState::AwaitChunk {
+                    restorer,
+                    path: expected_path,
+                    index: expected_index,
+                },
+                SyncResponse::ChunkProof { path, index, chunk },
+            ) => {
+                if path != *expected_path || index != *expected_index {
+                    return Err(Error::InvalidInput("received chunk was not requested"));
+                }
+                let ops = Decoder::new(&chunk)
+                    .collect::<Result<Vec<Op>, _>>()
+                    .map_err(Error::MerkError)?;
+                match restorer
+                    .process_chunk(ops)
+                    .map_err(|e| Error::CorruptedData(e.0))?
+                {
+                    RestorerResponse::AwaitNextChunk { path, index } => {
+                        *expected_path = path.clone();
+                        *expected_index = index;
+                        Ok(SyncRequest::GetChunk { path, index })
+                    }
+                    RestorerResponse::Ready => {
+                        self.state = RestoreState::Finished;
+                        let root_hash = self.grove_db.root_hash(Some(self.tx)).unwrap()?;
+                        Ok(SyncRequest::Ack { root_hash })
+                    }
+                }
+            }
+            _ => Err(Error::InvalidInput("unexpected sync response")),
+        }
+    }
+
+    /// Returns true once the whole state was restored.
+    pub fn is_finished(&self) -> bool {
+        matches!(self.state, RestoreState::Finished)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::tests::{make_test_grovedb, TEST_LEAF};
+
+    fn ed_round_trip<T: Encode + Decode>(message: T) -> T {
+        T::decode(message.encode().unwrap().as_slice()).unwrap()
+    }
+
+    fn serde_round_trip<T: Serialize + for<'de> Deserialize<'de>>(message: T) -> T {
+        bincode::deserialize(&bincode::serialize(&message).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_sync_sessions() {
+        let db = make_test_grovedb();
+        for i in 0u8..50 {
+            db.insert(
+                [TEST_LEAF].as_ref(),
+                &[i],
+                crate::Element::new_item(vec![i; 10]),
+                None,
+                None,
+            )
+            .unwrap()
+            .unwrap();
+        }
+        let expected_root_hash = db.root_hash(None).unwrap().unwrap();
+
+        let replica_dir = TempDir::new().unwrap();
+        let replica = GroveDb::open(replica_dir.path()).unwrap();
+        let tx = replica.start_transaction().unwrap();
+
+        let mut source = SourceSession::new(&db).unwrap();
+        let mut restore = RestoreSession::new(&replica, &tx);
+
+        let mut response = Some(source.manifest());
+        let mut round = 0;
+        while let Some(message) = response {
+            let message = if round % 2 == 0 {
+                ed_round_trip(message)
+            } else {
+                serde_round_trip(message)
+            };
+            let request = restore.handle_response(message).unwrap();
+            let request = if round % 2 == 0 {
+                serde_round_trip(request)
+            } else {
+                ed_round_trip(request)
+            };
+            response = source.handle_request(request).unwrap();
+            round += 1;
+        }
+
+        assert!(source.is_finished());
+        assert!(restore.is_finished());
+        assert!(restore
+            .handle_response(SyncResponse::Manifest {
+                root_hash: expected_root_hash,
+                version: SYNC_PROTOCOL_VERSION,
+            })
+            .is_err());
+
+        replica.commit_transaction(tx).unwrap().unwrap();
+        assert_eq!(
+            replica.root_hash(None).unwrap().unwrap(),
+            expected_root_hash
+        );
+    }
+
+    #[test]
+    fn test_sync_rejects_unrequested_chunk() {
+        let db = make_test_grovedb();
+        let replica_dir = TempDir::new().unwrap();
+        let replica = GroveDb::open(replica_dir.path()).unwrap();
+        let tx = replica.start_transaction().unwrap();
+
+        let mut source = SourceSession::new(&db).unwrap();
+        let mut restore = RestoreSession::new(&replica, &tx);
+
+        assert!(source
+            .handle_request(SyncRequest::GetChunk {
+                path: vec![],
+                index: 0,
+            })
+            .is_err());
+        restore.handle_response(source.manifest()).unwrap();
+        let chunk = source
+            .handle_request(SyncRequest::GetChunk {
+                path: vec![TEST_LEAF.to_vec()],
+                index: 0,
+            })
+            .unwrap()
+            .unwrap();
+        assert!(restore.handle_response(chunk).is_err());
+    }
+}
@@ -0,0 +1,105 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Background refresh of secondary GroveDbs
+//!
+//! A secondary GroveDb sees the state of its primary as of its last catch up.
+//! [`SecondaryRefresh`] catches it up periodically from a thread of its own,
+//! so that a read replica serves near real time reads without its users
+//! having to call [`GroveDb::try_catch_up_with_primary`].
+
+use std::{
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{Error, GroveDb};
+
+impl GroveDb {
+    /// Catches up this secondary GroveDb with its primary every `interval`
+    /// from a background thread, until the returned handle is dropped. Fails
+    /// if the GroveDb isn't a secondary, see [`GroveDb::open_as_secondary`].
+    pub fn refresh_secondary_in_background(
+        self: &Arc<Self>,
+        interval: Duration,
+    ) -> Result<SecondaryRefresh, Error> {
+        if !self.is_secondary() {
+            return Err(Error::NotSupported(
+                "only secondary GroveDbs catch up with their primary",
+            ));
+        }
+        let (stop, stopped) = mpsc::channel::<()>();
+        let last_error = Arc::new(Mutex::new(None));
+        let db = self.clone();
+        let thread_last_error = last_error.clone();
+        let thread = thread::spawn(move || {
+            // Dropping the sender disconnects the channel, which stops the loop
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                if let Err(e) = db.try_catch_up_with_primary() {
+                    *thread_last_error.lock().expect("last error lock") = Some(e);
+                }
+            }
+        });
+        Ok(SecondaryRefresh {
+            stop: Some(stop),
+            thread: Some(thread),
+            last_error,
+        })
+    }
+}
+
+/// Handle of the background refresh of a secondary GroveDb, see
+/// [`GroveDb::refresh_secondary_in_background`]. The refresh stops once the
+/// handle is dropped.
+pub struct SecondaryRefresh {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+    last_error: Arc<Mutex<Option<Error>>>,
+}
+
+impl SecondaryRefresh {
+    /// Takes the error of the last failed catch up since the previous call,
+    /// the refresh going on at the next interval after a failure
+    pub fn take_last_error(&self) -> Option<Error> {
+        self.last_error.lock().expect("last error lock").take()
+    }
+}
+
+impl Drop for SecondaryRefresh {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            // a panicking catch up has nothing left to clean up
+            let _ = thread.join();
+        }
+    }
+}
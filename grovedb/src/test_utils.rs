@@ -0,0 +1,319 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Cost assertion helpers
+//!
+//! Helpers for pinning the costs of operations in test suites. The expected
+//! cost is built field by field with `ExpectedCost`, storage bytes can be
+//! given as `NodeBytes` split into the key, value and parent hook parts of a
+//! merk node, and `assert_cost` panics with every mismatching component and
+//! the breakdown of the expected bytes.
+
+use std::fmt::Write;
+
+use grovedb_costs::{
+    storage_cost::{removal::StorageRemovedBytes, StorageCost},
+    OperationCost,
+};
+use grovedb_merk::tree::{kv::KV, Link};
+
+/// Storage bytes of a merk node, split into the parts paid for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NodeBytes {
+    /// Prefixed key and its length
+    pub key: u32,
+    /// Value with its feature type, hashes and length
+    pub value: u32,
+    /// Key, hash, child heights and sum the parent stores to link the node
+    pub parent_hook: u32,
+}
+
+impl NodeBytes {
+    /// Bytes of a node holding an item, a sum item or a reference, where
+    /// `value_len` is the length of the serialized element
+    pub fn item(key_len: u32, value_len: u32, in_sum_tree: bool) -> Self {
+        let parent_hook = Link::encoded_link_size(key_len, in_sum_tree);
+        Self {
+            key: KV::node_key_byte_cost_size(key_len),
+            value: KV::node_value_byte_cost_size(key_len, value_len, in_sum_tree) - parent_hook,
+            parent_hook,
+        }
+    }
+
+    /// Bytes of a node holding a subtree, where `value_len` is the length of
+    /// the serialized tree element
+    pub fn tree(key_len: u32, value_len: u32, in_sum_tree: bool) -> Self {
+        let parent_hook = Link::encoded_link_size(key_len, in_sum_tree);
+        Self {
+            key: KV::node_key_byte_cost_size(key_len),
+            value: KV::layered_value_byte_cost_size_for_key_and_value_lengths(
+                key_len,
+                value_len,
+                in_sum_tree,
+            ) - parent_hook,
+            parent_hook,
+        }
+    }
+
+    /// Total bytes of the node
+    pub fn total(&self) -> u32 {
+        self.key + self.value + self.parent_hook
+    }
+}
+
+/// Builder of the cost an operation is expected to have
+#[derive(Debug, Clone, Default)]
+pub struct ExpectedCost {
+    cost: OperationCost,
+    added_nodes: Vec<NodeBytes>,
+    replaced_nodes: Vec<NodeBytes>,
+}
+
+impl ExpectedCost {
+    /// Expects no cost at all
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the expected seek count
+    pub fn seek_count(mut self, seek_count: u16) -> Self {
+        self.cost.seek_count = seek_count;
+        self
+    }
+
+    /// Adds `bytes` to the expected added bytes
+    pub fn added_bytes(mut self, bytes: u32) -> Self {
+        self.cost.storage_cost.added_bytes += bytes;
+        self
+    }
+
+    /// Adds the bytes of a new node to the expected added bytes
+    pub fn added_node(mut self, node: NodeBytes) -> Self {
+        self.cost.storage_cost.added_bytes += node.total();
+        self.added_nodes.push(node);
+        self
+    }
+
+    /// Adds `bytes` to the expected replaced bytes
+    pub fn replaced_bytes(mut self, bytes: u32) -> Self {
+        self.cost.storage_cost.replaced_bytes += bytes;
+        self
+    }
+
+    /// Adds the bytes of a rewritten node to the expected replaced bytes
+    pub fn replaced_node(mut self, node: NodeBytes) -> Self {
+        self.cost.storage_cost.replaced_bytes += node.total();
+        self.replaced_nodes.push(node);
+        self
+    }
+
+    /// Sets the expected removed bytes
+    pub fn removed_bytes(mut self, removed_bytes: StorageRemovedBytes) -> Self {
+        self.cost.storage_cost.removed_bytes = removed_bytes;
+        self
+    }
+
+    /// Sets the expected bytes loaded from storage
+    pub fn loaded_bytes(mut self, bytes: u32) -> Self {
+        self.cost.storage_loaded_bytes = bytes;
+        self
+    }
+
+    /// Sets the expected hash node calls
+    pub fn hash_node_calls(mut self, hash_node_calls: u32) -> Self {
+        self.cost.hash_node_calls = hash_node_calls;
+        self
+    }
+
+    /// The expected cost
+    pub fn build(self) -> OperationCost {
+        self.cost
+    }
+}
+
+impl From<ExpectedCost> for OperationCost {
+    fn from(expected: ExpectedCost) -> Self {
+        expected.build()
+    }
+}
+
+fn breakdown(nodes: &[NodeBytes]) -> String {
+    nodes
+        .iter()
+        .map(|node| {
+            format!(
+                "key {} + value {} + parent hook {}",
+                node.key, node.value, node.parent_hook
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn describe_bytes(
+    message: &mut String,
+    name: &str,
+    expected: u32,
+    actual: u32,
+    nodes: &[NodeBytes],
+) {
+    if expected == actual {
+        return;
+    }
+    let _ = write!(
+        message,
+        "\n  {name}: expected {expected}, got {actual} ({:+})",
+        i64::from(actual) - i64::from(expected)
+    );
+    if !nodes.is_empty() {
+        let _ = write!(message, "\n    expected nodes: {}", breakdown(nodes));
+    }
+}
+
+/// Asserts that `actual` is the expected cost. On mismatch, panics with
+/// every differing component and the byte breakdown of the expected nodes.
+#[track_caller]
+pub fn assert_cost(actual: &OperationCost, expected: &ExpectedCost) {
+    let expected_cost = &expected.cost;
+    if actual == expected_cost {
+        return;
+    }
+    let mut message = String::from("operation cost mismatch:");
+    if actual.seek_count != expected_cost.seek_count {
+        let _ = write!(
+            message,
+            "\n  seek_count: expected {}, got {}",
+            expected_cost.seek_count, actual.seek_count
+        );
+    }
+    let StorageCost {
+        added_bytes,
+        replaced_bytes,
+        removed_bytes,
+    } = &actual.storage_cost;
+    describe_bytes(
+        &mut message,
+        "added_bytes",
+        expected_cost.storage_cost.added_bytes,
+        *added_bytes,
+        &expected.added_nodes,
+    );
+    describe_bytes(
+        &mut message,
+        "replaced_bytes",
+        expected_cost.storage_cost.replaced_bytes,
+        *replaced_bytes,
+        &expected.replaced_nodes,
+    );
+    if removed_bytes != &expected_cost.storage_cost.removed_bytes {
+        let _ = write!(
+            message,
+            "\n  removed_bytes: expected {:?}, got {:?}",
+            expected_cost.storage_cost.removed_bytes, removed_bytes
+        );
+    }
+    describe_bytes(
+        &mut message,
+        "storage_loaded_bytes",
+        expected_cost.storage_loaded_bytes,
+        actual.storage_loaded_bytes,
+        &[],
+    );
+    if actual.hash_node_calls != expected_cost.hash_node_calls {
+        let _ = write!(
+            message,
+            "\n  hash_node_calls: expected {}, got {}",
+            expected_cost.hash_node_calls, actual.hash_node_calls
+        );
+    }
+    panic!("{message}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{batch::GroveDbOp, tests::make_empty_grovedb, Element};
+
+    #[test]
+    fn test_assert_cost_of_root_tree_insert() {
+        let db = make_empty_grovedb();
+        let cost = db
+            .apply_batch(
+                vec![GroveDbOp::insert_op(
+                    vec![],
+                    b"key1".to_vec(),
+                    Element::empty_tree(),
+                )],
+                None,
+                None,
+            )
+            .cost;
+
+        let tree_node = NodeBytes::tree(4, 3, false);
+        assert_eq!(
+            tree_node,
+            NodeBytes {
+                key: 37,
+                value: 38,
+                parent_hook: 40,
+            }
+        );
+        assert_cost(
+            &cost,
+            &ExpectedCost::new()
+                .seek_count(3)
+                .added_node(tree_node)
+                .hash_node_calls(6),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "added_bytes: expected 114, got 115 (+1)")]
+    fn test_assert_cost_reports_mismatches() {
+        let db = make_empty_grovedb();
+        let cost = db
+            .apply_batch(
+                vec![GroveDbOp::insert_op(
+                    vec![],
+                    b"key1".to_vec(),
+                    Element::empty_tree(),
+                )],
+                None,
+                None,
+            )
+            .cost;
+
+        assert_cost(
+            &cost,
+            &ExpectedCost::new()
+                .seek_count(3)
+                .added_bytes(114)
+                .hash_node_calls(6),
+        );
+    }
+}
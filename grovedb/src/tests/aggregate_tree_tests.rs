@@ -0,0 +1,234 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Aggregate tree tests
+
+use std::sync::Arc;
+
+use tempfile::TempDir;
+
+use crate::{
+    batch::GroveDbOp,
+    tests::{add_test_leaves, make_test_grovedb, TempGroveDb, TEST_LEAF},
+    Element, Error, GroveDb, OpenOptions, TreeAggregate, TreeAggregates,
+};
+
+/// Id the maximum aggregate is registered with
+const MAX: u8 = 1;
+
+struct Max;
+
+impl TreeAggregate for Max {
+    fn combine(&self, left: i128, right: i128) -> Option<i128> {
+        Some(left.max(right))
+    }
+}
+
+fn make_test_grovedb_with_max_aggregate() -> TempGroveDb {
+    let tmp_dir = TempDir::new().unwrap();
+    let mut tree_aggregates = TreeAggregates::default();
+    tree_aggregates
+        .register(MAX, Arc::new(Max))
+        .expect("should register aggregate");
+    let mut db = GroveDb::open_with_options(
+        tmp_dir.path(),
+        OpenOptions {
+            tree_aggregates,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    add_test_leaves(&mut db);
+    TempGroveDb {
+        _tmp_dir: tmp_dir,
+        grove_db: db,
+    }
+}
+
+#[test]
+fn test_aggregate_tree_maintains_aggregate() {
+    let db = make_test_grovedb_with_max_aggregate();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key",
+        Element::empty_aggregate_tree(MAX),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert tree");
+    assert_eq!(
+        db.aggregate_of_subtree([TEST_LEAF, b"key"].as_ref(), None)
+            .unwrap()
+            .expect("should get aggregate"),
+        None
+    );
+
+    for (key, value) in [(b"item1", 7), (b"item2", -3), (b"item3", 12)] {
+        db.insert(
+            [TEST_LEAF, b"key"].as_ref(),
+            key,
+            Element::new_sum_item(value),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("should insert item");
+    }
+    // items have no value and are left out of the aggregate
+    db.insert(
+        [TEST_LEAF, b"key"].as_ref(),
+        b"item4",
+        Element::new_item(vec![1]),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+    assert_eq!(
+        db.aggregate_of_subtree([TEST_LEAF, b"key"].as_ref(), None)
+            .unwrap()
+            .expect("should get aggregate"),
+        Some(12)
+    );
+
+    db.delete([TEST_LEAF, b"key"].as_ref(), b"item3", None, None)
+        .unwrap()
+        .expect("should delete item");
+    assert_eq!(
+        db.aggregate_of_subtree([TEST_LEAF, b"key"].as_ref(), None)
+            .unwrap()
+            .expect("should get aggregate"),
+        Some(7)
+    );
+
+    // The aggregate is committed to by the tree element in the parent, so it
+    // can be verified from a proof alone
+    let path = vec![TEST_LEAF.to_vec(), b"key".to_vec()];
+    let proof = db
+        .prove_aggregate_of_subtree(path.clone())
+        .unwrap()
+        .expect("should generate proof");
+    let (root_hash, aggregate_id, proved_aggregate) =
+        GroveDb::verify_aggregate_of_subtree(&proof, path).expect("should verify proof");
+    assert_eq!(root_hash, db.grove_db.root_hash(None).unwrap().unwrap());
+    assert_eq!(aggregate_id, MAX);
+    assert_eq!(proved_aggregate, Some(7));
+}
+
+#[test]
+fn test_aggregate_tree_batch_matches_inserts() {
+    let db = make_test_grovedb_with_max_aggregate();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key",
+        Element::empty_aggregate_tree(MAX),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert tree");
+    // Inserted in reverse order so that item2 ends up at the root, as in the
+    // tree built by the batch
+    for (key, value) in [(b"item2", 12), (b"item1", 7)] {
+        db.insert(
+            [TEST_LEAF, b"key"].as_ref(),
+            key,
+            Element::new_sum_item(value),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("should insert item");
+    }
+
+    let batch_db = make_test_grovedb_with_max_aggregate();
+    let ops = vec![
+        GroveDbOp::insert_op(
+            vec![TEST_LEAF.to_vec()],
+            b"key".to_vec(),
+            Element::empty_aggregate_tree(MAX),
+        ),
+        GroveDbOp::insert_op(
+            vec![TEST_LEAF.to_vec(), b"key".to_vec()],
+            b"item1".to_vec(),
+            Element::new_sum_item(7),
+        ),
+        GroveDbOp::insert_op(
+            vec![TEST_LEAF.to_vec(), b"key".to_vec()],
+            b"item2".to_vec(),
+            Element::new_sum_item(12),
+        ),
+    ];
+    batch_db
+        .apply_batch(ops, None, None)
+        .unwrap()
+        .expect("should apply batch");
+
+    assert_eq!(
+        batch_db
+            .aggregate_of_subtree([TEST_LEAF, b"key"].as_ref(), None)
+            .unwrap()
+            .expect("should get aggregate"),
+        Some(12)
+    );
+    assert_eq!(
+        batch_db.grove_db.root_hash(None).unwrap().unwrap(),
+        db.grove_db.root_hash(None).unwrap().unwrap()
+    );
+}
+
+#[test]
+fn test_aggregate_tree_with_unregistered_aggregate_is_rejected() {
+    // Aggregates are registered per GroveDB, this one has none
+    let db = make_test_grovedb();
+    assert!(matches!(
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"key",
+            Element::empty_aggregate_tree(MAX),
+            None,
+            None,
+        )
+        .unwrap(),
+        Err(Error::MerkError(_))
+    ));
+    assert!(matches!(
+        db.apply_batch(
+            vec![GroveDbOp::insert_op(
+                vec![TEST_LEAF.to_vec()],
+                b"key".to_vec(),
+                Element::empty_aggregate_tree(MAX),
+            )],
+            None,
+            None
+        )
+        .unwrap(),
+        Err(Error::MerkError(_))
+    ));
+}
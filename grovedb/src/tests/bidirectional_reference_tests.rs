@@ -0,0 +1,299 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Bidirectional reference tests
+
+use grovedb_merk::proofs::query::QueryItem;
+
+use crate::{
+    query_result_type::QueryResultType,
+    reference_path::ReferencePathType,
+    tests::{make_test_grovedb, TempGroveDb, ANOTHER_TEST_LEAF, TEST_LEAF},
+    Element, GroveDb, PathQuery,
+};
+
+fn make_grovedb_with_bidirectional_reference() -> TempGroveDb {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key",
+        Element::new_item(b"value".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+    db.insert(
+        [ANOTHER_TEST_LEAF].as_ref(),
+        b"ref",
+        Element::new_bidirectional_reference(ReferencePathType::AbsolutePathReference(vec![
+            TEST_LEAF.to_vec(),
+            b"key".to_vec(),
+        ])),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert bidirectional reference");
+    db
+}
+
+#[test]
+fn test_bidirectional_reference_maintains_backlink() {
+    let db = make_grovedb_with_bidirectional_reference();
+
+    let element = db
+        .get([ANOTHER_TEST_LEAF].as_ref(), b"ref", None)
+        .unwrap()
+        .expect("should follow reference");
+    assert_eq!(element, Element::new_item(b"value".to_vec()));
+
+    let backlinks = db
+        .get_backlinks(vec![TEST_LEAF.to_vec()], b"key", None)
+        .unwrap()
+        .expect("should get backlinks");
+    assert_eq!(
+        backlinks,
+        vec![vec![ANOTHER_TEST_LEAF.to_vec(), b"ref".to_vec()]]
+    );
+}
+
+#[test]
+fn test_replacing_bidirectional_reference_removes_backlink() {
+    let db = make_grovedb_with_bidirectional_reference();
+    db.insert(
+        [ANOTHER_TEST_LEAF].as_ref(),
+        b"ref",
+        Element::new_item(b"other".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should replace reference");
+
+    let backlinks = db
+        .get_backlinks(vec![TEST_LEAF.to_vec()], b"key", None)
+        .unwrap()
+        .expect("should get backlinks");
+    assert!(backlinks.is_empty());
+}
+
+#[test]
+fn test_deleting_bidirectional_reference_removes_backlink() {
+    let db = make_grovedb_with_bidirectional_reference();
+    db.delete([ANOTHER_TEST_LEAF].as_ref(), b"ref", None, None)
+        .unwrap()
+        .expect("should delete reference");
+
+    let backlinks = db
+        .get_backlinks(vec![TEST_LEAF.to_vec()], b"key", None)
+        .unwrap()
+        .expect("should get backlinks");
+    assert!(backlinks.is_empty());
+}
+
+#[test]
+fn test_prove_and_verify_backlinks() {
+    let db = make_grovedb_with_bidirectional_reference();
+
+    let proof = db
+        .prove_backlinks(vec![TEST_LEAF.to_vec()], b"key")
+        .unwrap()
+        .expect("should generate proof");
+    let (root_hash, referrers) =
+        GroveDb::verify_backlinks(&proof, vec![TEST_LEAF.to_vec()], b"key")
+            .expect("should verify proof");
+    assert_eq!(
+        root_hash,
+        db.root_hash(None).unwrap().expect("should get root hash")
+    );
+    assert_eq!(
+        referrers,
+        vec![vec![ANOTHER_TEST_LEAF.to_vec(), b"ref".to_vec()]]
+    );
+}
+
+#[test]
+fn test_backlinks_are_not_elements() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key",
+        Element::new_item(b"value".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+    db.insert(
+        [ANOTHER_TEST_LEAF].as_ref(),
+        b"ref",
+        Element::new_bidirectional_reference(ReferencePathType::AbsolutePathReference(vec![
+            TEST_LEAF.to_vec(),
+            b"key".to_vec(),
+        ])),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert bidirectional reference");
+
+    let query =
+        PathQuery::new_single_query_item(vec![TEST_LEAF.to_vec()], QueryItem::RangeFull(..));
+    let (elements, _) = db
+        .query_raw(
+            &query,
+            true,
+            QueryResultType::QueryKeyElementPairResultType,
+            None,
+        )
+        .unwrap()
+        .expect("should query");
+    assert_eq!(
+        elements.to_key_elements(),
+        vec![(b"key".to_vec(), Element::new_item(b"value".to_vec()))]
+    );
+}
+
+#[test]
+fn test_clearing_subtree_of_reference_removes_backlink() {
+    let db = make_grovedb_with_bidirectional_reference();
+    db.clear_subtree([ANOTHER_TEST_LEAF].as_ref(), None, None)
+        .unwrap()
+        .expect("should clear subtree");
+
+    let backlinks = db
+        .get_backlinks(vec![TEST_LEAF.to_vec()], b"key", None)
+        .unwrap()
+        .expect("should get backlinks");
+    assert!(backlinks.is_empty());
+}
+
+#[test]
+fn test_clearing_subtree_of_target_removes_backlinks() {
+    let db = make_grovedb_with_bidirectional_reference();
+    db.clear_subtree([TEST_LEAF].as_ref(), None, None)
+        .unwrap()
+        .expect("should clear subtree");
+
+    let backlinks = db
+        .get_backlinks(vec![TEST_LEAF.to_vec()], b"key", None)
+        .unwrap()
+        .expect("should get backlinks");
+    assert!(backlinks.is_empty());
+}
+
+#[test]
+fn test_verify_backlinks_rejects_proof_of_other_element() {
+    let db = make_grovedb_with_bidirectional_reference();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"other",
+        Element::new_item(b"value".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+
+    // A proof of the backlinks of `key` doesn't prove backlinks of `other`
+    let proof = db
+        .prove_backlinks(vec![TEST_LEAF.to_vec()], b"key")
+        .unwrap()
+        .expect("should generate proof");
+    assert!(GroveDb::verify_backlinks(&proof, vec![TEST_LEAF.to_vec()], b"other").is_err());
+}
+
+#[test]
+fn test_renaming_bidirectional_reference_moves_backlink() {
+    let db = make_grovedb_with_bidirectional_reference();
+    db.rename_key([ANOTHER_TEST_LEAF].as_ref(), b"ref", b"renamed", None)
+        .unwrap()
+        .expect("should rename reference");
+
+    let backlinks = db
+        .get_backlinks(vec![TEST_LEAF.to_vec()], b"key", None)
+        .unwrap()
+        .expect("should get backlinks");
+    assert_eq!(
+        backlinks,
+        vec![vec![ANOTHER_TEST_LEAF.to_vec(), b"renamed".to_vec()]]
+    );
+}
+
+#[test]
+fn test_renaming_subtree_of_reference_moves_backlink() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key",
+        Element::new_item(b"value".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+    db.insert(
+        [ANOTHER_TEST_LEAF].as_ref(),
+        b"old",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert tree");
+    db.insert(
+        [ANOTHER_TEST_LEAF, b"old"].as_ref(),
+        b"ref",
+        Element::new_bidirectional_reference(ReferencePathType::AbsolutePathReference(vec![
+            TEST_LEAF.to_vec(),
+            b"key".to_vec(),
+        ])),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert bidirectional reference");
+
+    db.rename_key([ANOTHER_TEST_LEAF].as_ref(), b"old", b"new", None)
+        .unwrap()
+        .expect("should rename subtree");
+
+    let backlinks = db
+        .get_backlinks(vec![TEST_LEAF.to_vec()], b"key", None)
+        .unwrap()
+        .expect("should get backlinks");
+    assert_eq!(
+        backlinks,
+        vec![vec![
+            ANOTHER_TEST_LEAF.to_vec(),
+            b"new".to_vec(),
+            b"ref".to_vec()
+        ]]
+    );
+}
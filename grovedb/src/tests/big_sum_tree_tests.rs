@@ -0,0 +1,239 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Big sum tree tests
+
+use crate::{
+    batch::GroveDbOp,
+    tests::{make_test_grovedb, TEST_LEAF},
+    Element, Error,
+};
+
+#[test]
+fn test_big_sum_tree_sums_beyond_i64() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key",
+        Element::empty_big_sum_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert tree");
+    db.insert(
+        [TEST_LEAF, b"key"].as_ref(),
+        b"item1",
+        Element::new_sum_item(i64::MAX),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+    db.insert(
+        [TEST_LEAF, b"key"].as_ref(),
+        b"item2",
+        Element::new_sum_item(i64::MAX),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+    db.insert(
+        [TEST_LEAF, b"key"].as_ref(),
+        b"item3",
+        Element::new_item(vec![1]),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+
+    let sum = db
+        .big_sum_of_subtree([TEST_LEAF, b"key"].as_ref(), None)
+        .unwrap()
+        .expect("should get sum");
+    assert_eq!(sum, 2 * i64::MAX as i128);
+
+    // Sum trees nested in a big sum tree add their sum to it
+    db.insert(
+        [TEST_LEAF, b"key"].as_ref(),
+        b"nested",
+        Element::empty_sum_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert tree");
+    db.insert(
+        [TEST_LEAF, b"key", b"nested"].as_ref(),
+        b"item",
+        Element::new_sum_item(5),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+
+    let element = db
+        .get_raw([TEST_LEAF].as_ref().into(), b"key", None)
+        .unwrap()
+        .expect("should get tree");
+    assert!(element.is_big_sum_tree());
+    assert_eq!(element.big_sum_value_or_default(), 2 * i64::MAX as i128 + 5);
+
+    // A big sum tree has no i64 sum
+    assert!(matches!(
+        db.sum_of_subtree([TEST_LEAF, b"key"].as_ref(), None)
+            .unwrap(),
+        Err(Error::WrongElementType(_))
+    ));
+}
+
+#[test]
+fn test_big_sum_tree_batch_matches_inserts() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key",
+        Element::empty_big_sum_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert tree");
+    // Inserted in reverse order so that item2 ends up at the root, as in the
+    // tree built by the batch
+    db.insert(
+        [TEST_LEAF, b"key"].as_ref(),
+        b"item2",
+        Element::new_sum_item(i64::MAX),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+    db.insert(
+        [TEST_LEAF, b"key"].as_ref(),
+        b"item1",
+        Element::new_sum_item(i64::MAX),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+
+    let batch_db = make_test_grovedb();
+    let ops = vec![
+        GroveDbOp::insert_op(
+            vec![TEST_LEAF.to_vec()],
+            b"key".to_vec(),
+            Element::empty_big_sum_tree(),
+        ),
+        GroveDbOp::insert_op(
+            vec![TEST_LEAF.to_vec(), b"key".to_vec()],
+            b"item1".to_vec(),
+            Element::new_sum_item(i64::MAX),
+        ),
+        GroveDbOp::insert_op(
+            vec![TEST_LEAF.to_vec(), b"key".to_vec()],
+            b"item2".to_vec(),
+            Element::new_sum_item(i64::MAX),
+        ),
+    ];
+    batch_db
+        .apply_batch(ops, None, None)
+        .unwrap()
+        .expect("should apply batch");
+
+    assert_eq!(
+        batch_db
+            .big_sum_of_subtree([TEST_LEAF, b"key"].as_ref(), None)
+            .unwrap()
+            .expect("should get sum"),
+        2 * i64::MAX as i128
+    );
+    assert_eq!(
+        batch_db.grove_db.root_hash(None).unwrap().unwrap(),
+        db.grove_db.root_hash(None).unwrap().unwrap()
+    );
+}
+
+#[test]
+fn test_big_sum_tree_in_sum_tree_overflow() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"sums",
+        Element::empty_sum_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert tree");
+    db.insert(
+        [TEST_LEAF, b"sums"].as_ref(),
+        b"big",
+        Element::empty_big_sum_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert tree");
+    db.insert(
+        [TEST_LEAF, b"sums", b"big"].as_ref(),
+        b"item1",
+        Element::new_sum_item(i64::MAX),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+
+    // The big sum tree can hold the total but its parent sum tree can't
+    assert!(matches!(
+        db.insert(
+            [TEST_LEAF, b"sums", b"big"].as_ref(),
+            b"item2",
+            Element::new_sum_item(1),
+            None,
+            None,
+        )
+        .unwrap(),
+        Err(Error::SumOverflow(_))
+    ));
+    let ops = vec![GroveDbOp::insert_op(
+        vec![TEST_LEAF.to_vec(), b"sums".to_vec(), b"big".to_vec()],
+        b"item2".to_vec(),
+        Element::new_sum_item(1),
+    )];
+    assert!(matches!(
+        db.apply_batch(ops, None, None).unwrap(),
+        Err(Error::SumOverflow(_))
+    ));
+}
@@ -0,0 +1,371 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Count tree tests
+
+use grovedb_merk::{proofs::Query, TreeFeatureType::CountedMerk};
+use grovedb_storage::StorageBatch;
+
+use crate::{
+    batch::GroveDbOp,
+    tests::{make_test_grovedb, TEST_LEAF},
+    Aggregate, Element, Error, GroveDb, PathQuery,
+};
+
+#[test]
+fn test_count_tree_counts_inserted_elements() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key",
+        Element::empty_count_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert tree");
+
+    let count = db
+        .count_in_subtree([TEST_LEAF, b"key"].as_ref(), None)
+        .unwrap()
+        .expect("should get count");
+    assert_eq!(count, 0);
+
+    db.insert(
+        [TEST_LEAF, b"key"].as_ref(),
+        b"item1",
+        Element::new_item(vec![1]),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+    db.insert(
+        [TEST_LEAF, b"key"].as_ref(),
+        b"item2",
+        Element::new_item(vec![]),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+    db.insert(
+        [TEST_LEAF, b"key"].as_ref(),
+        b"item3",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert tree");
+
+    // Sum items only make sense in sum trees
+    assert!(matches!(
+        db.insert(
+            [TEST_LEAF, b"key"].as_ref(),
+            b"sumitem",
+            Element::new_sum_item(30),
+            None,
+            None,
+        )
+        .unwrap(),
+        Err(Error::InvalidInput(_))
+    ));
+
+    // Every element counts as one, whatever its type or value
+    let batch = StorageBatch::new();
+    let merk = db
+        .open_non_transactional_merk_at_path([TEST_LEAF, b"key"].as_ref().into(), Some(&batch))
+        .unwrap()
+        .expect("should open tree");
+    assert!(merk.is_count_tree);
+    for key in [b"item1", b"item2", b"item3"] {
+        assert!(matches!(
+            merk.get_feature_type(key, true)
+                .unwrap()
+                .expect("node should exist"),
+            Some(CountedMerk(1))
+        ));
+    }
+
+    let count = db
+        .count_in_subtree([TEST_LEAF, b"key"].as_ref(), None)
+        .unwrap()
+        .expect("should get count");
+    assert_eq!(count, 3);
+
+    // Replacing an element doesn't change the count
+    db.insert(
+        [TEST_LEAF, b"key"].as_ref(),
+        b"item1",
+        Element::new_item(vec![2]),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should replace item");
+    let count = db
+        .count_in_subtree([TEST_LEAF, b"key"].as_ref(), None)
+        .unwrap()
+        .expect("should get count");
+    assert_eq!(count, 3);
+
+    // Deleting an element decrements it
+    db.delete([TEST_LEAF, b"key"].as_ref(), b"item2", None, None)
+        .unwrap()
+        .expect("should delete item");
+    let count = db
+        .count_in_subtree([TEST_LEAF, b"key"].as_ref(), None)
+        .unwrap()
+        .expect("should get count");
+    assert_eq!(count, 2);
+}
+
+#[test]
+fn test_count_tree_only_counts_direct_children() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key",
+        Element::empty_count_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert tree");
+    db.insert(
+        [TEST_LEAF, b"key"].as_ref(),
+        b"inner",
+        Element::empty_count_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert tree");
+    for key in [b"a", b"b", b"c"] {
+        db.insert(
+            [TEST_LEAF, b"key", b"inner"].as_ref(),
+            key,
+            Element::new_item(vec![1]),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("should insert item");
+    }
+
+    let count = db
+        .count_in_subtree([TEST_LEAF, b"key", b"inner"].as_ref(), None)
+        .unwrap()
+        .expect("should get count");
+    assert_eq!(count, 3);
+    let count = db
+        .count_in_subtree([TEST_LEAF, b"key"].as_ref(), None)
+        .unwrap()
+        .expect("should get count");
+    assert_eq!(count, 1);
+
+    // Regular trees and the root tree have no count
+    assert!(matches!(
+        db.count_in_subtree([TEST_LEAF].as_ref(), None).unwrap(),
+        Err(Error::WrongElementType(_))
+    ));
+    assert!(matches!(
+        db.count_in_subtree::<&[u8], _>(&[] as &[&[u8]], None)
+            .unwrap(),
+        Err(Error::InvalidPath(_))
+    ));
+}
+
+#[test]
+fn test_count_tree_is_maintained_by_batches() {
+    let db = make_test_grovedb();
+    let ops = vec![
+        GroveDbOp::insert_op(
+            vec![TEST_LEAF.to_vec()],
+            b"key".to_vec(),
+            Element::empty_count_tree(),
+        ),
+        GroveDbOp::insert_op(
+            vec![TEST_LEAF.to_vec(), b"key".to_vec()],
+            b"item1".to_vec(),
+            Element::new_item(vec![1]),
+        ),
+        GroveDbOp::insert_op(
+            vec![TEST_LEAF.to_vec(), b"key".to_vec()],
+            b"item2".to_vec(),
+            Element::new_item(vec![2]),
+        ),
+        GroveDbOp::insert_op(
+            vec![TEST_LEAF.to_vec(), b"key".to_vec()],
+            b"item3".to_vec(),
+            Element::empty_tree(),
+        ),
+    ];
+    db.apply_batch(ops, None, None)
+        .unwrap()
+        .expect("should apply batch");
+
+    let count = db
+        .count_in_subtree([TEST_LEAF, b"key"].as_ref(), None)
+        .unwrap()
+        .expect("should get count");
+    assert_eq!(count, 3);
+
+    let ops = vec![
+        GroveDbOp::delete_op(vec![TEST_LEAF.to_vec(), b"key".to_vec()], b"item1".to_vec()),
+        GroveDbOp::insert_op(
+            vec![TEST_LEAF.to_vec(), b"key".to_vec()],
+            b"item4".to_vec(),
+            Element::new_item(vec![4]),
+        ),
+        GroveDbOp::insert_op(
+            vec![TEST_LEAF.to_vec(), b"key".to_vec()],
+            b"item5".to_vec(),
+            Element::new_item(vec![5]),
+        ),
+    ];
+    db.apply_batch(ops, None, None)
+        .unwrap()
+        .expect("should apply batch");
+
+    let count = db
+        .count_in_subtree([TEST_LEAF, b"key"].as_ref(), None)
+        .unwrap()
+        .expect("should get count");
+    assert_eq!(count, 4);
+}
+
+#[test]
+fn test_count_in_subtree_with_proof() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key",
+        Element::empty_count_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert tree");
+    for key in [b"item1", b"item2", b"item3"] {
+        db.insert(
+            [TEST_LEAF, b"key"].as_ref(),
+            key,
+            Element::new_item(key.to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("should insert item");
+    }
+
+    // The count can be verified from the proof alone
+    let path = vec![TEST_LEAF.to_vec(), b"key".to_vec()];
+    let proof = db
+        .prove_count_in_subtree(path.clone())
+        .unwrap()
+        .expect("should generate proof");
+    let (root_hash, proved_count) =
+        GroveDb::verify_count_in_subtree(&proof, path).expect("should verify proof");
+    assert_eq!(root_hash, db.grove_db.root_hash(None).unwrap().unwrap());
+    assert_eq!(proved_count, 3);
+
+    // A proof for a regular tree doesn't verify as a count
+    let path = vec![TEST_LEAF.to_vec()];
+    let proof = db
+        .prove_count_in_subtree(path.clone())
+        .unwrap()
+        .expect("should generate proof");
+    assert!(matches!(
+        GroveDb::verify_count_in_subtree(&proof, path),
+        Err(Error::WrongElementType(_))
+    ));
+}
+
+#[test]
+fn test_count_aggregate_of_whole_count_tree_with_proof() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key",
+        Element::empty_count_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert tree");
+    for i in 0u8..50 {
+        db.insert(
+            [TEST_LEAF, b"key"].as_ref(),
+            &[i],
+            Element::new_item(vec![i]),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("should insert item");
+    }
+    let path = vec![TEST_LEAF.to_vec(), b"key".to_vec()];
+    let root_hash = db.grove_db.root_hash(None).unwrap().unwrap();
+
+    let mut query = Query::new();
+    query.insert_all();
+    let whole_tree = PathQuery::new_unsized(path.clone(), query);
+    let mut query = Query::new();
+    query.insert_range_from(vec![0]..);
+    let range = PathQuery::new_unsized(path.clone(), query);
+
+    let mut proof_sizes = vec![];
+    for path_query in [&whole_tree, &range] {
+        let count = db
+            .query_aggregate(path_query, Aggregate::Count, None)
+            .unwrap()
+            .expect("should compute count");
+        assert_eq!(count, 50);
+
+        let proof = db
+            .prove_aggregate(path_query, Aggregate::Count)
+            .unwrap()
+            .expect("should prove count");
+        let (hash, proven_count) = GroveDb::verify_aggregate(&proof, path_query, Aggregate::Count)
+            .expect("should verify count");
+        assert_eq!(hash, root_hash);
+        assert_eq!(proven_count, 50);
+        proof_sizes.push(proof.len());
+    }
+
+    // The whole tree is proven with the count tree element alone, while the
+    // range proof ships every matched key
+    let count_tree_proof = db
+        .prove_count_in_subtree(path)
+        .unwrap()
+        .expect("should generate proof");
+    assert_eq!(proof_sizes[0], count_tree_proof.len());
+    assert!(proof_sizes[0] < proof_sizes[1]);
+}
@@ -30,8 +30,20 @@
 
 pub mod common;
 
+mod aggregate_tree_tests;
+
+mod bidirectional_reference_tests;
+
+mod big_sum_tree_tests;
+
+mod count_tree_tests;
+
+mod path_query_tests;
+
 mod query_tests;
 
+mod reference_path_tests;
+
 mod sum_tree_tests;
 
 mod tree_hashes_tests;
@@ -47,8 +59,23 @@ use tempfile::TempDir;
 use self::common::EMPTY_PATH;
 use super::*;
 use crate::{
-    query_result_type::QueryResultType::QueryKeyElementPairResultType,
-    reference_path::ReferencePathType, tests::common::compare_result_tuples,
+    operations::{
+        insert::InsertOptions,
+        proof::{
+            display::{
+                display_proof, display_root_hash, parse_proof, parse_root_hash, ProofSummary,
+                TextEncoding,
+            },
+            util::ProofTokenType,
+            verify::RemainingLimitOffset,
+        },
+    },
+    query_result_type::QueryResultType::{
+        QueryElementResultType, QueryKeyElementPairResultType, QueryPathKeyElementTrioResultType,
+        QueryPathKeyResultType, QueryPathKeyValueHashResultType,
+    },
+    reference_path::ReferencePathType,
+    tests::common::compare_result_tuples,
 };
 
 pub const TEST_LEAF: &[u8] = b"test_leaf";
@@ -1011,6 +1038,164 @@ fn test_tree_structure_is_persistent() {
     assert_eq!(prev_root_hash, db.root_hash(None).unwrap().unwrap());
 }
 
+#[test]
+fn test_secondary_follows_primary() {
+    let primary_dir = TempDir::new().unwrap();
+    let secondary_dir = TempDir::new().unwrap();
+    let mut db = GroveDb::open(primary_dir.path()).unwrap();
+    add_test_leaves(&mut db);
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key1",
+        Element::new_item(b"ayy".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+
+    let secondary = GroveDb::open_as_secondary(primary_dir.path(), secondary_dir.path())
+        .expect("successful secondary open");
+    assert!(secondary.is_secondary());
+    assert!(!db.is_secondary());
+    assert_eq!(
+        secondary
+            .get([TEST_LEAF].as_ref(), b"key1", None)
+            .unwrap()
+            .expect("successful get"),
+        Element::new_item(b"ayy".to_vec())
+    );
+    assert_eq!(
+        secondary.root_hash(None).unwrap().unwrap(),
+        db.root_hash(None).unwrap().unwrap()
+    );
+
+    // Writes of the primary are seen once the secondary caught up
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key2",
+        Element::new_item(b"ayy2".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    assert!(secondary
+        .get([TEST_LEAF].as_ref(), b"key2", None)
+        .unwrap()
+        .is_err());
+    secondary
+        .try_catch_up_with_primary()
+        .expect("successful catch up");
+    assert_eq!(
+        secondary
+            .get([TEST_LEAF].as_ref(), b"key2", None)
+            .unwrap()
+            .expect("successful get"),
+        Element::new_item(b"ayy2".to_vec())
+    );
+    assert_eq!(
+        secondary.root_hash(None).unwrap().unwrap(),
+        db.root_hash(None).unwrap().unwrap()
+    );
+
+    // The secondary is read only and generates no proofs
+    assert!(secondary
+        .insert(
+            [TEST_LEAF].as_ref(),
+            b"key3",
+            Element::new_item(b"ayy3".to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .is_err());
+    assert!(db
+        .get([TEST_LEAF].as_ref(), b"key3", None)
+        .unwrap()
+        .is_err());
+    let mut query = Query::new();
+    query.insert_all();
+    let path_query = PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], query);
+    assert!(matches!(
+        secondary.prove_query(&path_query).unwrap(),
+        Err(Error::NotSupported(_))
+    ));
+    assert!(matches!(
+        secondary.start_transaction(),
+        Err(Error::StorageError(grovedb_storage::Error::ReadOnly(_)))
+    ));
+    assert!(matches!(
+        secondary.snapshot(),
+        Err(Error::StorageError(grovedb_storage::Error::ReadOnly(_)))
+    ));
+}
+
+#[test]
+fn test_secondary_refresh_in_background() {
+    use std::time::{Duration, Instant};
+
+    let primary_dir = TempDir::new().unwrap();
+    let secondary_dir = TempDir::new().unwrap();
+    let mut db = GroveDb::open(primary_dir.path()).unwrap();
+    add_test_leaves(&mut db);
+    assert!(matches!(
+        Arc::new(GroveDb::open(TempDir::new().unwrap().path()).unwrap())
+            .refresh_secondary_in_background(Duration::from_millis(10)),
+        Err(Error::NotSupported(_))
+    ));
+
+    let secondary = Arc::new(
+        GroveDb::open_as_secondary(primary_dir.path(), secondary_dir.path())
+            .expect("successful secondary open"),
+    );
+    let refresh = secondary
+        .refresh_secondary_in_background(Duration::from_millis(10))
+        .expect("successful refresh start");
+
+    // The write of the primary is picked up without an explicit catch up
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key1",
+        Element::new_item(b"ayy".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while secondary
+        .get([TEST_LEAF].as_ref(), b"key1", None)
+        .unwrap()
+        .is_err()
+    {
+        assert!(Instant::now() < deadline, "secondary never caught up");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(
+        secondary.root_hash(None).unwrap().unwrap(),
+        db.root_hash(None).unwrap().unwrap()
+    );
+    assert!(refresh.take_last_error().is_none());
+
+    // Once the handle is dropped the secondary isn't caught up anymore
+    drop(refresh);
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key2",
+        Element::new_item(b"ayy2".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(secondary
+        .get([TEST_LEAF].as_ref(), b"key2", None)
+        .unwrap()
+        .is_err());
+}
+
 #[test]
 fn test_root_tree_leaves_are_noted() {
     let db = make_test_grovedb();
@@ -1124,6 +1309,56 @@ fn test_proof_for_non_existent_data() {
     assert_eq!(result_set.len(), 0);
 }
 
+#[test]
+fn test_root_leaf_proof() {
+    let db = make_test_grovedb();
+    let root_hash = db.root_hash(None).unwrap().unwrap();
+
+    let proof = db.prove_root_leaf(TEST_LEAF).unwrap().unwrap();
+    let (hash, element) =
+        GroveDb::verify_root_leaf(proof.as_slice(), TEST_LEAF).expect("should verify proof");
+    assert_eq!(hash, root_hash);
+    assert!(matches!(element, Some(Element::Tree(..))));
+
+    let absent_proof = db.prove_root_leaf(b"absent_leaf").unwrap().unwrap();
+    let (hash, element) = GroveDb::verify_root_leaf(absent_proof.as_slice(), b"absent_leaf")
+        .expect("should verify proof");
+    assert_eq!(hash, root_hash);
+    assert_eq!(element, None);
+
+    // the root leaf proof is smaller than the equivalent path query proof
+    let path_query = PathQuery::new_single_key(vec![], TEST_LEAF.to_vec());
+    let query_proof = db.prove_query(&path_query).unwrap().unwrap();
+    assert!(proof.len() < query_proof.len());
+}
+
+#[test]
+fn test_proof_generation_cost() {
+    let db = make_test_grovedb();
+    for i in 0u8..20 {
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            &[i],
+            Element::new_item(vec![i; 10]),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful item insert");
+    }
+
+    let key_query = PathQuery::new_single_key(vec![TEST_LEAF.to_vec()], vec![3]);
+    let key_proof_cost = db.prove_query(&key_query).cost;
+    assert!(key_proof_cost.seek_count > 0);
+    assert!(key_proof_cost.storage_loaded_bytes > 0);
+
+    let range_query =
+        PathQuery::new_single_query_item(vec![TEST_LEAF.to_vec()], QueryItem::RangeFull(..));
+    let range_proof_cost = db.prove_query(&range_query).cost;
+    assert!(range_proof_cost.seek_count > key_proof_cost.seek_count);
+    assert!(range_proof_cost.storage_loaded_bytes > key_proof_cost.storage_loaded_bytes);
+}
+
 #[test]
 fn test_path_query_proofs_without_subquery_with_reference() {
     // Tree Structure
@@ -1266,7 +1501,7 @@ fn test_path_query_proofs_without_subquery_with_reference() {
     let proof = temp_db.prove_query(&path_query).unwrap().unwrap();
     assert_eq!(
         hex::encode(&proof),
-        "010285010198ebd6dc7e1c82951c41fcfa6487711cac6a399ebb01bb979cb\
+        "030285010198ebd6dc7e1c82951c41fcfa6487711cac6a399ebb01bb979cb\
         e4a51e0b2f08d06046b6579340009000676616c75653100bf2f052b01c2b\
         b83ff3a40504d42b5b9141c582a3e0c98679189b33a24478a6f1006046b6\
         579350009000676616c75653400f084ffdbc429a89c9b6620e7224d73c2e\
@@ -1400,7 +1635,7 @@ fn test_path_query_proofs_without_subquery() {
     let proof = temp_db.prove_query(&path_query).unwrap().unwrap();
     assert_eq!(
         hex::encode(proof.as_slice()),
-        "01025503046b6579310009000676616c7565310002018655e18e4555b0b65\
+        "03025503046b6579310009000676616c7565310002018655e18e4555b0b65\
         bbcec64c749db6b9ad84231969fb4fbe769a3093d10f2100198ebd6dc7e1\
         c82951c41fcfa6487711cac6a399ebb01bb979cbe4a51e0b2f08d1101350\
         409696e6e65727472656500080201046b657932004910536da659a3dbdbc\
@@ -1887,6 +2122,62 @@ fn test_path_query_proofs_with_sized_query() {
     compare_result_tuples(result_set, expected_result_set);
 }
 
+#[test]
+fn test_proof_verification_returns_remaining_limit_offset() {
+    let temp_db = make_test_grovedb();
+    for i in 0u8..10 {
+        temp_db
+            .insert(
+                [TEST_LEAF].as_ref(),
+                &[i],
+                Element::new_item(vec![i]),
+                None,
+                None,
+            )
+            .unwrap()
+            .expect("successful item insert");
+    }
+    let root_hash = temp_db.root_hash(None).unwrap().unwrap();
+
+    let mut query = Query::new();
+    query.insert_all();
+
+    let verify = |limit, offset| {
+        let path_query = PathQuery::new(
+            vec![TEST_LEAF.to_vec()],
+            SizedQuery::new(query.clone(), limit, offset),
+        );
+        let proof = temp_db.prove_query(&path_query).unwrap().unwrap();
+        let (hash, result_set, remaining) =
+            GroveDb::verify_query_with_remaining_limit_offset(proof.as_slice(), &path_query)
+                .expect("should execute proof");
+        assert_eq!(hash, root_hash);
+        (result_set.len(), remaining)
+    };
+
+    // the page is full
+    let (len, remaining) = verify(Some(4), Some(2));
+    assert_eq!(len, 4);
+    assert_eq!(remaining.limit, Some(0));
+    assert_eq!(remaining.offset, Some(0));
+
+    // the last page
+    let (len, remaining) = verify(Some(20), Some(2));
+    assert_eq!(len, 8);
+    assert_eq!(remaining.limit, Some(12));
+    assert_eq!(remaining.offset, Some(0));
+
+    // the offset skips past all results
+    let (len, remaining) = verify(Some(5), Some(15));
+    assert_eq!(len, 0);
+    assert_eq!(remaining.limit, Some(5));
+    assert_eq!(remaining.offset, Some(5));
+
+    let (len, remaining) = verify(None, None);
+    assert_eq!(len, 10);
+    assert_eq!(remaining, RemainingLimitOffset::default());
+}
+
 #[test]
 fn test_path_query_proofs_with_direction() {
     let temp_db = make_deep_tree();
@@ -2142,173 +2433,3654 @@ fn test_is_empty_tree() {
 }
 
 #[test]
-fn transaction_should_be_aborted_when_rollback_is_called() {
-    let item_key = b"key3";
-
+fn test_is_empty_tree_with_limit() {
     let db = make_test_grovedb();
-    let transaction = db.start_transaction();
-
-    let element1 = Element::new_item(b"ayy".to_vec());
-
-    let result = db
-        .insert(
-            [TEST_LEAF].as_ref(),
-            item_key,
-            element1,
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"innertree",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .unwrap();
+    for key in [b"key1", b"key2", b"key3"] {
+        db.insert(
+            [TEST_LEAF, b"innertree"].as_ref(),
+            key,
+            Element::new_item(b"hello".to_vec()),
+            None,
             None,
-            Some(&transaction),
         )
+        .unwrap()
         .unwrap();
+    }
 
-    assert!(matches!(result, Ok(())));
-
-    db.rollback_transaction(&transaction).unwrap();
+    let has_fewer_than = |limit| {
+        db.is_empty_tree_with_limit([TEST_LEAF, b"innertree"].as_ref(), limit, None)
+            .unwrap()
+            .expect("path is valid tree")
+    };
+    assert!(!has_fewer_than(1));
+    assert!(!has_fewer_than(3));
+    assert!(has_fewer_than(4));
+    assert!(has_fewer_than(100));
+
+    // Only as many elements as the limit are looked at
+    let few = db
+        .is_empty_tree_with_limit([TEST_LEAF, b"innertree"].as_ref(), 1, None)
+        .cost;
+    let many = db
+        .is_empty_tree_with_limit([TEST_LEAF, b"innertree"].as_ref(), 100, None)
+        .cost;
+    assert!(few.seek_count < many.seek_count);
 
-    let result = db
-        .get([TEST_LEAF].as_ref(), item_key, Some(&transaction))
-        .unwrap();
-    assert!(matches!(result, Err(Error::PathKeyNotFound(_))));
+    assert!(db
+        .is_empty_tree_with_limit([TEST_LEAF, b"key1"].as_ref(), 1, None)
+        .unwrap()
+        .is_err());
 }
 
 #[test]
-fn transaction_should_be_aborted() {
+fn test_query_and_count_keys() {
     let db = make_test_grovedb();
-    let transaction = db.start_transaction();
+    for key in [b"key1", b"key2", b"key3", b"key4"] {
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            key,
+            Element::new_item(vec![0; 1000]),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful item insert");
+    }
 
-    let item_key = b"key3";
-    let element = Element::new_item(b"ayy".to_vec());
+    let mut query = Query::new();
+    query.insert_range(b"key2".to_vec()..b"key4".to_vec());
+    assert_eq!(
+        db.query_keys([TEST_LEAF].as_ref(), &query, None)
+            .unwrap()
+            .expect("cannot query keys"),
+        vec![b"key2".to_vec(), b"key3".to_vec()]
+    );
 
-    db.insert(
-        [TEST_LEAF].as_ref(),
-        item_key,
-        element,
-        None,
-        Some(&transaction),
-    )
-    .unwrap()
-    .unwrap();
+    let mut all_query = Query::new();
+    all_query.insert_all();
+    let count = db.count_keys([TEST_LEAF].as_ref(), &all_query, None);
+    assert_eq!(count.value.expect("cannot count keys"), 4);
 
-    drop(transaction);
+    // only the keys are read, not the 4000 bytes of values
+    let path_query = PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], all_query);
+    let elements_cost = db
+        .query_raw(&path_query, true, QueryElementResultType, None)
+        .cost;
+    assert!(count.cost.storage_loaded_bytes < 4000);
+    assert!(elements_cost.storage_loaded_bytes > 4000);
 
-    // Transactional data shouldn't be committed to the main database
-    let result = db.get([TEST_LEAF].as_ref(), item_key, None).unwrap();
-    assert!(matches!(result, Err(Error::PathKeyNotFound(_))));
+    assert!(db
+        .count_keys([TEST_LEAF, b"key1"].as_ref(), &query, None)
+        .unwrap()
+        .is_err());
 }
 
 #[test]
-fn test_subtree_pairs_iterator() {
+fn test_query_many() {
     let db = make_test_grovedb();
-    let element = Element::new_item(b"ayy".to_vec());
-    let element2 = Element::new_item(b"lmao".to_vec());
-
-    // Insert some nested subtrees
-    db.insert(
-        [TEST_LEAF].as_ref(),
-        b"subtree1",
-        Element::empty_tree(),
-        None,
-        None,
-    )
-    .unwrap()
-    .expect("successful subtree 1 insert");
-    db.insert(
-        [TEST_LEAF, b"subtree1"].as_ref(),
-        b"subtree11",
-        Element::empty_tree(),
-        None,
-        None,
-    )
-    .unwrap()
-    .expect("successful subtree 2 insert");
-    // Insert an element into subtree
-    db.insert(
-        [TEST_LEAF, b"subtree1", b"subtree11"].as_ref(),
-        b"key1",
-        element.clone(),
-        None,
-        None,
-    )
-    .unwrap()
-    .expect("successful value insert");
-    assert_eq!(
-        db.get(
-            [TEST_LEAF, b"subtree1", b"subtree11"].as_ref(),
-            b"key1",
-            None
+    for key in [b"key1", b"key2", b"key3", b"key4"] {
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            key,
+            Element::new_item(key.to_vec()),
+            None,
+            None,
         )
         .unwrap()
-        .expect("successful get 1"),
-        element
-    );
+        .expect("successful item insert");
+    }
     db.insert(
-        [TEST_LEAF, b"subtree1", b"subtree11"].as_ref(),
-        b"key0",
-        element.clone(),
+        [ANOTHER_TEST_LEAF].as_ref(),
+        b"key5",
+        Element::new_item(b"value5".to_vec()),
         None,
         None,
     )
     .unwrap()
-    .expect("successful value insert");
-    db.insert(
-        [TEST_LEAF, b"subtree1"].as_ref(),
-        b"subtree12",
-        Element::empty_tree(),
-        None,
-        None,
+    .expect("successful item insert");
+
+    let mut range_query = Query::new();
+    range_query.insert_range(b"key2".to_vec()..b"key4".to_vec());
+    let mut overlapping_query = Query::new();
+    overlapping_query.insert_key(b"key1".to_vec());
+    overlapping_query.insert_key(b"key3".to_vec());
+    let mut all_query = Query::new();
+    all_query.insert_all();
+    let path_queries = [
+        PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], range_query),
+        PathQuery::new_unsized(vec![ANOTHER_TEST_LEAF.to_vec()], all_query.clone()),
+        PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], overlapping_query),
+        PathQuery::new(
+            vec![TEST_LEAF.to_vec()],
+            SizedQuery::new(all_query, Some(1), Some(3)),
+        ),
+    ];
+
+    let results = db
+        .query_many(&path_queries, true, QueryKeyElementPairResultType, None)
+        .unwrap()
+        .expect("cannot query many");
+    let keys: Vec<Vec<Vec<u8>>> = results
+        .into_iter()
+        .map(|elements| elements.to_keys())
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            vec![b"key2".to_vec(), b"key3".to_vec()],
+            vec![b"key5".to_vec()],
+            vec![b"key1".to_vec(), b"key3".to_vec()],
+            vec![b"key4".to_vec()],
+        ]
+    );
+
+    // every query still gets the same results as when run on its own
+    for (path_query, elements) in path_queries.iter().zip(
+        db.query_many(&path_queries, true, QueryElementResultType, None)
+            .unwrap()
+            .expect("cannot query many"),
+    ) {
+        let (expected, _) = db
+            .query(path_query, true, QueryElementResultType, None)
+            .unwrap()
+            .expect("cannot query");
+        assert_eq!(elements.to_elements(), expected.to_elements());
+    }
+}
+
+#[test]
+fn test_query_many_spanning_subtrees() {
+    let db = make_deep_tree();
+
+    let mut subquery = Query::new();
+    subquery.insert_all();
+    let mut query = Query::new();
+    query.insert_all();
+    query.set_subquery(subquery.clone());
+    let mut reversed_query = Query::new_with_direction(false);
+    reversed_query.insert_all();
+    reversed_query.set_subquery(subquery.clone());
+    let mut key_query = Query::new();
+    key_query.insert_key(b"key1".to_vec());
+    let mut reversed_keys_query = Query::new_with_direction(false);
+    reversed_keys_query.insert_key(b"innertree".to_vec());
+    reversed_keys_query.insert_key(b"innertree4".to_vec());
+    reversed_keys_query.set_subquery(subquery);
+    let path_queries = [
+        PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], query),
+        PathQuery::new_unsized(vec![ANOTHER_TEST_LEAF.to_vec()], reversed_query),
+        PathQuery::new_unsized(vec![TEST_LEAF.to_vec(), b"innertree".to_vec()], key_query),
+        PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], reversed_keys_query),
+    ];
+
+    // queries spanning several subtrees get the same results as when run on
+    // their own, whether their subtrees are queried concurrently or not
+    let result = db.query_many(&path_queries, true, QueryPathKeyElementTrioResultType, None);
+    let results = result.value.expect("cannot query many");
+    // running the queries in a transaction doesn't spread them over workers,
+    // and costs the same
+    let transaction = db.start_transaction().unwrap();
+    assert_eq!(
+        db.query_many(
+            &path_queries,
+            true,
+            QueryPathKeyElementTrioResultType,
+            Some(&transaction)
+        )
+        .cost,
+        result.cost
+    );
+    for (path_query, elements) in path_queries.iter().zip(results) {
+        let (expected, _) = db
+            .query(path_query, true, QueryPathKeyElementTrioResultType, None)
+            .unwrap()
+            .expect("cannot query");
+        assert!(!expected.is_empty());
+        assert_eq!(
+            elements.to_path_key_elements(),
+            expected.to_path_key_elements()
+        );
+    }
+
+    // the error of the first failing query is returned: querying trees fails
+    // as they can't be followed to items
+    let mut query = Query::new();
+    query.insert_all();
+    let trees_query = PathQuery::new_unsized(vec![], query);
+    assert!(matches!(
+        db.query_many(
+            &[
+                path_queries[0].clone(),
+                trees_query,
+                path_queries[1].clone()
+            ],
+            true,
+            QueryElementResultType,
+            None
+        )
+        .unwrap(),
+        Err(Error::InvalidQuery(
+            "path_queries can only refer to items and references"
+        ))
+    ));
+}
+
+#[test]
+fn test_keys_only_path_query() {
+    let db = make_test_grovedb();
+    for key in [b"key1", b"key2", b"key3", b"key4"] {
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            key,
+            Element::new_item(vec![key[3]; 1000]),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful item insert");
+    }
+
+    let mut query = Query::new();
+    query.insert_all();
+    let elements_path_query = PathQuery::new(
+        vec![TEST_LEAF.to_vec()],
+        SizedQuery::new(query, Some(2), Some(1)),
+    );
+    let path_query = elements_path_query.clone().with_keys_only();
+
+    assert_eq!(
+        db.query_path_keys(&path_query, None)
+            .unwrap()
+            .expect("cannot query keys"),
+        vec![b"key2".to_vec(), b"key3".to_vec()]
+    );
+
+    // the proof carries value hashes instead of the elements
+    let proof = db
+        .prove_query(&path_query)
+        .unwrap()
+        .expect("cannot prove keys");
+    let elements_proof = db
+        .prove_query(&elements_path_query)
+        .unwrap()
+        .expect("cannot prove elements");
+    assert!(proof.len() + 1500 < elements_proof.len());
+
+    let (root_hash, keys) = GroveDb::verify_keys_only_query(&proof, &path_query)
+        .expect("cannot verify keys only proof");
+    assert_eq!(
+        root_hash,
+        db.root_hash(None).unwrap().expect("cannot get root hash")
+    );
+    let (_, elements) = GroveDb::verify_query_raw(&elements_proof, &elements_path_query)
+        .expect("cannot verify elements proof");
+    assert_eq!(
+        keys,
+        elements
+            .into_iter()
+            .map(|proved| (proved.key, proved.proof))
+            .collect::<Vec<_>>()
+    );
+    assert!(GroveDb::verify_keys_only_query(&proof, &elements_path_query).is_err());
+
+    // queries return the keys but no elements
+    let (keys, skipped) = db
+        .query_raw(&path_query, true, QueryPathKeyResultType, None)
+        .unwrap()
+        .expect("cannot query keys");
+    assert_eq!(keys.to_keys(), vec![b"key2".to_vec(), b"key3".to_vec()]);
+    assert_eq!(skipped, 1);
+    assert!(matches!(
+        db.query_raw(&path_query, true, QueryElementResultType, None)
+            .unwrap(),
+        Err(Error::NotSupported(_))
+    ));
+    assert!(matches!(
+        db.query(&path_query, true, QueryKeyElementPairResultType, None)
+            .unwrap(),
+        Err(Error::NotSupported(_))
+    ));
+    assert!(matches!(
+        db.query_item_value(&path_query, true, None).unwrap(),
+        Err(Error::NotSupported(_))
+    ));
+    assert!(matches!(
+        db.query_many(&[path_query.clone()], true, QueryElementResultType, None)
+            .unwrap(),
+        Err(Error::NotSupported(_))
+    ));
+
+    // keys only path queries only merge with each other
+    let mut query = Query::new();
+    query.insert_key(b"key1".to_vec());
+    let other_path_query = PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], query);
+    assert!(matches!(
+        PathQuery::merge(vec![
+            &other_path_query,
+            &other_path_query.clone().with_keys_only()
+        ]),
+        Err(Error::NotSupported(_))
+    ));
+    assert!(PathQuery::merge(vec![
+        &other_path_query.clone().with_keys_only(),
+        &other_path_query.clone().with_keys_only()
+    ])
+    .expect("cannot merge keys only path queries")
+    .is_keys_only());
+
+    // subqueries need the elements
+    let mut query = Query::new();
+    query.insert_all();
+    query.set_subquery(Query::new());
+    let path_query = PathQuery::new_unsized(vec![], query).with_keys_only();
+    assert!(matches!(
+        db.query_path_keys(&path_query, None).unwrap(),
+        Err(Error::NotSupported(_))
+    ));
+    assert!(matches!(
+        db.prove_query(&path_query).unwrap(),
+        Err(Error::NotSupported(_))
+    ));
+}
+
+#[test]
+fn test_keys_and_value_hashes_query_result_types() {
+    let db = make_deep_tree();
+
+    let mut subsubquery = Query::new();
+    subsubquery.insert_all();
+    let mut subquery = Query::new();
+    subquery.insert_all();
+    subquery.set_subquery(subsubquery);
+    let mut query = Query::new();
+    query.insert_all();
+    query.set_subquery(subquery);
+    let path_query = PathQuery::new_unsized(vec![DEEP_LEAF.to_vec()], query);
+
+    let (elements, _) = db
+        .query_raw(&path_query, true, QueryPathKeyElementTrioResultType, None)
+        .unwrap()
+        .expect("cannot query elements");
+    let (keys, _) = db
+        .query_raw(&path_query, true, QueryPathKeyResultType, None)
+        .unwrap()
+        .expect("cannot query keys");
+    assert_eq!(keys.to_path_keys(), elements.to_path_keys());
+
+    // the value hashes are the ones the proofs carry
+    let (value_hashes, _) = db
+        .query_raw(&path_query, true, QueryPathKeyValueHashResultType, None)
+        .unwrap()
+        .expect("cannot query value hashes");
+    let proof = db.prove_query(&path_query).unwrap().unwrap();
+    let (_, proved) = GroveDb::verify_query_raw(&proof, &path_query).unwrap();
+    let proved_value_hashes: Vec<_> = proved
+        .into_iter()
+        .map(|proved| (proved.path, proved.key, proved.proof))
+        .collect();
+    assert_eq!(proved_value_hashes.len(), 11);
+    assert_eq!(value_hashes.to_path_key_value_hashes(), proved_value_hashes);
+
+    // queried keys look their value hash up
+    let mut query = Query::new();
+    query.insert_key(b"key2".to_vec());
+    query.insert_key(b"key3".to_vec());
+    let path_query = PathQuery::new_unsized(
+        vec![
+            DEEP_LEAF.to_vec(),
+            b"deep_node_1".to_vec(),
+            b"deeper_1".to_vec(),
+        ],
+        query,
+    )
+    .with_keys_only();
+    let (value_hashes, _) = db
+        .query_raw(&path_query, true, QueryPathKeyValueHashResultType, None)
+        .unwrap()
+        .expect("cannot query value hashes");
+
+    // and keys only proofs, which hold no values, verify into the same results
+    let proof = db.prove_query(&path_query).unwrap().unwrap();
+    let (root_hash, proved) = GroveDb::verify_keys_only_query_with_result_type(
+        &proof,
+        &path_query,
+        QueryPathKeyValueHashResultType,
+    )
+    .expect("cannot verify keys only proof");
+    assert_eq!(root_hash, db.root_hash(None).unwrap().unwrap());
+    assert_eq!(
+        proved.to_path_key_value_hashes(),
+        value_hashes.to_path_key_value_hashes()
+    );
+    let (_, proved) = GroveDb::verify_keys_only_query_with_result_type(
+        &proof,
+        &path_query,
+        QueryPathKeyResultType,
+    )
+    .expect("cannot verify keys only proof");
+    assert_eq!(proved.to_keys(), vec![b"key2".to_vec(), b"key3".to_vec()]);
+    assert!(GroveDb::verify_keys_only_query_with_result_type(
+        &proof,
+        &path_query,
+        QueryElementResultType
+    )
+    .is_err());
+}
+
+#[test]
+fn test_check_subtree_exists() {
+    let db = make_deep_tree();
+    let transaction = db.start_transaction().unwrap();
+
+    assert!(db
+        .check_subtree_exists([TEST_LEAF, b"innertree"].as_ref(), None)
+        .unwrap()
+        .expect("cannot check subtree"));
+    assert!(db
+        .check_subtree_exists(EMPTY_PATH, None)
+        .unwrap()
+        .expect("cannot check subtree"));
+    // missing segment
+    assert!(!db
+        .check_subtree_exists([TEST_LEAF, b"missing"].as_ref(), None)
+        .unwrap()
+        .expect("cannot check subtree"));
+    // the last segment is an item
+    assert!(!db
+        .check_subtree_exists([TEST_LEAF, b"innertree", b"key1"].as_ref(), None)
+        .unwrap()
+        .expect("cannot check subtree"));
+
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"newtree",
+        Element::empty_tree(),
+        None,
+        Some(&transaction),
+    )
+    .unwrap()
+    .expect("successful subtree insert");
+    assert!(!db
+        .check_subtree_exists([TEST_LEAF, b"newtree"].as_ref(), None)
+        .unwrap()
+        .expect("cannot check subtree"));
+    assert!(db
+        .check_subtree_exists([TEST_LEAF, b"newtree"].as_ref(), Some(&transaction))
+        .unwrap()
+        .expect("cannot check subtree"));
+}
+
+#[test]
+fn test_insert_create_path() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"item",
+        Element::new_item(b"value".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful item insert");
+
+    db.insert_create_path(
+        [TEST_LEAF, b"a", b"b"].as_ref(),
+        b"key",
+        Element::new_item(b"deep".to_vec()),
+        None,
+    )
+    .unwrap()
+    .expect("successful insert creating path");
+    assert_eq!(
+        db.get([TEST_LEAF, b"a", b"b"].as_ref(), b"key", None)
+            .unwrap()
+            .expect("cannot get item"),
+        Element::new_item(b"deep".to_vec())
+    );
+
+    // existing trees are kept
+    db.insert_create_path(
+        [TEST_LEAF, b"a", b"c"].as_ref(),
+        b"key",
+        Element::new_item(b"other".to_vec()),
+        None,
+    )
+    .unwrap()
+    .expect("successful insert creating path");
+    assert!(db
+        .get([TEST_LEAF, b"a", b"b"].as_ref(), b"key", None)
+        .unwrap()
+        .is_ok());
+
+    // a segment that is not a tree fails without writing anything
+    assert!(matches!(
+        db.insert_create_path(
+            [TEST_LEAF, b"item", b"x"].as_ref(),
+            b"key",
+            Element::new_item(b"value".to_vec()),
+            None,
+        )
+        .unwrap(),
+        Err(Error::InvalidPath(_))
+    ));
+
+    // paths sharing a prefix are created once in the same batch
+    let mut ops = db
+        .create_path_ops(
+            &[ANOTHER_TEST_LEAF.to_vec(), b"x".to_vec(), b"y".to_vec()],
+            &[],
+            None,
+        )
+        .unwrap()
+        .expect("cannot create path ops");
+    assert_eq!(ops.len(), 2);
+    let more_ops = db
+        .create_path_ops(
+            &[ANOTHER_TEST_LEAF.to_vec(), b"x".to_vec(), b"z".to_vec()],
+            &ops,
+            None,
+        )
+        .unwrap()
+        .expect("cannot create path ops");
+    assert_eq!(more_ops.len(), 1);
+    ops.extend(more_ops);
+    ops.push(batch::GroveDbOp::insert_op(
+        vec![ANOTHER_TEST_LEAF.to_vec(), b"x".to_vec(), b"z".to_vec()],
+        b"key".to_vec(),
+        Element::new_item(b"value".to_vec()),
+    ));
+    db.apply_batch(ops, None, None)
+        .unwrap()
+        .expect("cannot apply batch");
+    assert!(db
+        .check_subtree_exists([ANOTHER_TEST_LEAF, b"x", b"y"].as_ref(), None)
+        .unwrap()
+        .expect("cannot check subtree"));
+
+    // a tree created after the ops were made is kept when they are applied
+    let mut ops = db
+        .create_path_ops(&[TEST_LEAF.to_vec(), b"late".to_vec()], &[], None)
+        .unwrap()
+        .expect("cannot create path ops");
+    assert_eq!(ops.len(), 1);
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"late",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful tree insert");
+    db.insert(
+        [TEST_LEAF, b"late"].as_ref(),
+        b"first",
+        Element::new_item(b"value".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful item insert");
+    ops.push(batch::GroveDbOp::insert_op(
+        vec![TEST_LEAF.to_vec(), b"late".to_vec()],
+        b"second".to_vec(),
+        Element::new_item(b"value".to_vec()),
+    ));
+    db.apply_batch(ops, None, None)
+        .unwrap()
+        .expect("cannot apply batch");
+    for key in [b"first".as_slice(), b"second".as_slice()] {
+        assert!(db
+            .get([TEST_LEAF, b"late"].as_ref(), key, None)
+            .unwrap()
+            .is_ok());
+    }
+}
+
+#[test]
+fn test_memory_usage() {
+    let db = make_test_grovedb();
+    let before = db.memory_usage().expect("cannot get memory usage");
+    for i in 0u8..100 {
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            &[i],
+            Element::new_item(vec![i; 100]),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful item insert");
+    }
+    let after = db.memory_usage().expect("cannot get memory usage");
+    assert!(after.memtables > before.memtables);
+}
+
+#[test]
+fn test_changed_since() {
+    let db = make_test_grovedb();
+    db.configure_last_modified_tracking(EMPTY_PATH, true, None)
+        .unwrap()
+        .expect("successful configuration");
+    db.configure_last_modified_tracking([TEST_LEAF].as_ref(), true, None)
+        .unwrap()
+        .expect("successful configuration");
+
+    let options_at = |block_height, timestamp| {
+        Some(InsertOptions {
+            insertion_metadata: Some(InsertionMetadata {
+                block_height,
+                timestamp,
+            }),
+            ..Default::default()
+        })
+    };
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"inner",
+        Element::empty_tree(),
+        options_at(1, 1000),
+        None,
+    )
+    .unwrap()
+    .expect("successful subtree insert");
+    db.configure_last_modified_tracking([TEST_LEAF, b"inner"].as_ref(), true, None)
+        .unwrap()
+        .expect("successful configuration");
+    for (path, key) in [
+        ([TEST_LEAF, b"inner"].as_ref(), b"a"),
+        ([TEST_LEAF].as_ref(), b"b"),
+    ] {
+        db.insert(
+            path,
+            key,
+            Element::new_item(b"value".to_vec()),
+            options_at(1, 1000),
+            None,
+        )
+        .unwrap()
+        .expect("successful item insert");
+    }
+
+    db.insert(
+        [TEST_LEAF, b"inner"].as_ref(),
+        b"c",
+        Element::new_item(b"value".to_vec()),
+        options_at(5, 2000),
+        None,
+    )
+    .unwrap()
+    .expect("successful item insert");
+
+    assert_eq!(
+        db.changed_since(EMPTY_PATH, 5, None)
+            .unwrap()
+            .expect("cannot scan changes"),
+        vec![
+            (vec![], TEST_LEAF.to_vec()),
+            (vec![TEST_LEAF.to_vec()], b"inner".to_vec()),
+            (vec![TEST_LEAF.to_vec(), b"inner".to_vec()], b"c".to_vec()),
+        ]
+    );
+    assert_eq!(
+        db.changed_since([TEST_LEAF].as_ref(), 1, None)
+            .unwrap()
+            .expect("cannot scan changes")
+            .len(),
+        4
+    );
+
+    let metadata_at_7 = Some(InsertionMetadata {
+        block_height: 7,
+        timestamp: 3000,
+    });
+    db.apply_batch(
+        vec![batch::GroveDbOp::update_flags_op(
+            vec![TEST_LEAF.to_vec(), b"inner".to_vec()],
+            b"a".to_vec(),
+            Some(vec![1]),
+        )],
+        Some(batch::BatchApplyOptions {
+            insertion_metadata: metadata_at_7,
+            ..Default::default()
+        }),
+        None,
+    )
+    .unwrap()
+    .expect("successful flags update");
+    db.delete(
+        [TEST_LEAF].as_ref(),
+        b"b",
+        Some(operations::delete::DeleteOptions {
+            insertion_metadata: metadata_at_7,
+            ..Default::default()
+        }),
+        None,
+    )
+    .unwrap()
+    .expect("successful delete");
+    assert_eq!(
+        db.changed_since(EMPTY_PATH, 7, None)
+            .unwrap()
+            .expect("cannot scan changes"),
+        vec![
+            (vec![], TEST_LEAF.to_vec()),
+            (vec![TEST_LEAF.to_vec()], b"b".to_vec()),
+            (vec![TEST_LEAF.to_vec()], b"inner".to_vec()),
+            (vec![TEST_LEAF.to_vec(), b"inner".to_vec()], b"a".to_vec()),
+        ]
+    );
+
+    // The tracking data stays out of the auxiliary storage of users
+    assert_eq!(
+        db.get_aux(b"last_modified_enabled", None)
+            .unwrap()
+            .expect("cannot get aux"),
+        None
+    );
+}
+
+#[cfg(feature = "audit_log")]
+#[test]
+fn test_audit_log() {
+    use crate::operations::audit_log::AuditOpKind;
+
+    let db = make_test_grovedb();
+    let start = db
+        .audit_log_bounds(None)
+        .unwrap()
+        .expect("successful bounds")
+        .next;
+
+    let root_hash_before = db.root_hash(None).unwrap().expect("successful root hash");
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key",
+        Element::new_item(b"value".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful item insert");
+    db.delete([TEST_LEAF].as_ref(), b"key", None, None)
+        .unwrap()
+        .expect("successful delete");
+    db.apply_batch(
+        vec![
+            batch::GroveDbOp::insert_op(
+                vec![TEST_LEAF.to_vec()],
+                b"a".to_vec(),
+                Element::new_item(b"a".to_vec()),
+            ),
+            batch::GroveDbOp::insert_op(
+                vec![ANOTHER_TEST_LEAF.to_vec()],
+                b"b".to_vec(),
+                Element::new_item(b"b".to_vec()),
+            ),
+        ],
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful batch");
+
+    let records = db
+        .read_audit_log(start, 10, None)
+        .unwrap()
+        .expect("successful read");
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0].root_hash_before, root_hash_before);
+    assert_eq!(records[0].entries[0].key, b"key".to_vec());
+    assert_eq!(records[0].entries[0].op, AuditOpKind::Insert);
+    assert!(records[0].entries[0].value_hash.is_some());
+    assert_eq!(records[1].entries[0].op, AuditOpKind::Delete);
+    assert_eq!(records[1].previous_hash, records[0].hash());
+    assert_eq!(records[2].entries.len(), 2);
+    assert!(db
+        .verify_audit_log(None)
+        .unwrap()
+        .expect("successful verify"));
+
+    db.rotate_audit_log(2, None)
+        .unwrap()
+        .expect("successful rotation");
+    let bounds = db
+        .audit_log_bounds(None)
+        .unwrap()
+        .expect("successful bounds");
+    assert_eq!(bounds.len(), 2);
+    assert!(db
+        .verify_audit_log(None)
+        .unwrap()
+        .expect("successful verify"));
+
+    // The log is out of the auxiliary storage of users
+    let mut meta_key = b"audit_log/record/".to_vec();
+    meta_key.extend_from_slice(&bounds.first.to_be_bytes());
+    assert_eq!(
+        db.get_aux(&meta_key, None)
+            .unwrap()
+            .expect("successful get"),
+        None
+    );
+
+    // Altering the root hash recorded in the oldest record breaks the chain
+    let batch = StorageBatch::new();
+    let storage = db
+        .db
+        .get_storage_context(SubtreePath::empty(), Some(&batch))
+        .unwrap();
+    let mut record = storage
+        .get_meta(&meta_key)
+        .unwrap()
+        .expect("successful get")
+        .expect("record exists");
+    record[40] ^= 1;
+    storage
+        .put_meta(&meta_key, &record, None)
+        .unwrap()
+        .expect("successful put");
+    drop(storage);
+    db.db
+        .commit_multi_context_batch(batch, None)
+        .unwrap()
+        .expect("successful commit");
+    assert!(!db
+        .verify_audit_log(None)
+        .unwrap()
+        .expect("successful verify"));
+}
+
+#[cfg(feature = "audit_log")]
+#[test]
+fn test_audit_log_concurrent_transactions() {
+    let db = make_test_grovedb();
+    let start = db
+        .audit_log_bounds(None)
+        .unwrap()
+        .expect("successful bounds")
+        .next;
+
+    let tx_a = db.start_transaction().unwrap();
+    let tx_b = db.start_transaction().unwrap();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"a",
+        Element::new_item(b"a".to_vec()),
+        None,
+        Some(&tx_a),
+    )
+    .unwrap()
+    .expect("successful insert");
+    db.insert(
+        [ANOTHER_TEST_LEAF].as_ref(),
+        b"b",
+        Element::new_item(b"b".to_vec()),
+        None,
+        Some(&tx_b),
+    )
+    .unwrap()
+    .expect("successful insert");
+    db.commit_transaction(tx_a)
+        .unwrap()
+        .expect("successful commit");
+    // Both transactions update the root tree, so the second one conflicts on
+    // the grove itself. Its record, staged under a key of its own, is
+    // dropped with it.
+    assert!(db.commit_transaction(tx_b).unwrap().is_err());
+    let records = db
+        .read_audit_log(start, 10, None)
+        .unwrap()
+        .expect("successful read");
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].entries[0].key, b"a".to_vec());
+
+    // Retried, the mutation is appended after the committed one
+    let tx_b = db.start_transaction().unwrap();
+    db.insert(
+        [ANOTHER_TEST_LEAF].as_ref(),
+        b"b",
+        Element::new_item(b"b".to_vec()),
+        None,
+        Some(&tx_b),
+    )
+    .unwrap()
+    .expect("successful insert");
+    db.commit_transaction(tx_b)
+        .unwrap()
+        .expect("successful commit");
+
+    let records = db
+        .read_audit_log(start, 10, None)
+        .unwrap()
+        .expect("successful read");
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[1].previous_hash, records[0].hash());
+    let keys: Vec<_> = records.iter().map(|r| r.entries[0].key.clone()).collect();
+    assert_eq!(keys, vec![b"a".to_vec(), b"b".to_vec()]);
+    assert!(db
+        .verify_audit_log(None)
+        .unwrap()
+        .expect("successful verify"));
+}
+
+#[cfg(feature = "audit_log")]
+#[test]
+fn test_audit_log_rename_clear_and_counter() {
+    use crate::operations::audit_log::AuditOpKind;
+
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"old",
+        Element::new_item(b"value".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    let start = db
+        .audit_log_bounds(None)
+        .unwrap()
+        .expect("successful bounds")
+        .next;
+
+    db.rename_key([TEST_LEAF].as_ref(), b"old", b"new", None)
+        .unwrap()
+        .expect("successful rename");
+    db.increment_counter([TEST_LEAF].as_ref(), b"counter", 3, None)
+        .unwrap()
+        .expect("successful increment");
+    db.clear_subtree([TEST_LEAF].as_ref(), None, None)
+        .unwrap()
+        .expect("successful clear");
+
+    let records = db
+        .read_audit_log(start, 10, None)
+        .unwrap()
+        .expect("successful read");
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0].entries.len(), 2);
+    assert_eq!(records[0].entries[0].op, AuditOpKind::Delete);
+    assert_eq!(records[0].entries[0].key, b"old".to_vec());
+    assert_eq!(records[0].entries[1].op, AuditOpKind::Insert);
+    assert_eq!(records[0].entries[1].key, b"new".to_vec());
+    assert_eq!(records[1].entries[0].op, AuditOpKind::IncrementCounter);
+    assert_eq!(records[1].entries[0].key, b"counter".to_vec());
+    assert_eq!(records[2].entries[0].op, AuditOpKind::Clear);
+    assert_eq!(records[2].entries[0].path, Vec::<Vec<u8>>::new());
+    assert_eq!(records[2].entries[0].key, TEST_LEAF.to_vec());
+}
+
+#[cfg(feature = "audit_log")]
+#[test]
+fn test_audit_log_does_not_change_mutation_costs() {
+    use grovedb_costs::storage_cost::{
+        removal::StorageRemovedBytes::NoStorageRemoval, StorageCost,
+    };
+
+    // Same cost as without the audit log, see `test_one_insert_item_cost`
+    let db = make_empty_grovedb();
+    let tx = db.start_transaction().unwrap();
+    let cost = db
+        .insert(
+            EMPTY_PATH,
+            b"key1",
+            Element::new_item(b"cat".to_vec()),
+            None,
+            Some(&tx),
+        )
+        .cost_as_result()
+        .expect("should insert");
+    assert_eq!(
+        cost,
+        OperationCost {
+            seek_count: 3,
+            storage_cost: StorageCost {
+                added_bytes: 149,
+                replaced_bytes: 0,
+                removed_bytes: NoStorageRemoval
+            },
+            storage_loaded_bytes: 0,
+            hash_node_calls: 2,
+        }
+    );
+}
+
+#[test]
+fn test_prefix_registry() {
+    use grovedb_storage::rocksdb_storage::RocksDbStorage;
+
+    use crate::operations::delete::DeleteOptions;
+
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"inner",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful subtree insert");
+    let prefix_of = |path: &[&[u8]]| RocksDbStorage::build_prefix(path.into()).unwrap();
+
+    assert!(db
+        .path_of_prefix(&prefix_of(&[TEST_LEAF]), None)
+        .unwrap()
+        .is_err());
+    db.enable_prefix_registry()
+        .unwrap()
+        .expect("successful enabling");
+    assert_eq!(
+        db.path_of_prefix(&prefix_of(&[TEST_LEAF, b"inner"]), None)
+            .unwrap()
+            .expect("successful lookup"),
+        Some(vec![TEST_LEAF.to_vec(), b"inner".to_vec()])
+    );
+
+    // Subtrees created by insertions and batches are registered
+    db.insert(
+        [TEST_LEAF, b"inner"].as_ref(),
+        b"deeper",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful subtree insert");
+    db.apply_batch(
+        vec![batch::GroveDbOp::insert_op(
+            vec![ANOTHER_TEST_LEAF.to_vec()],
+            b"other".to_vec(),
+            Element::empty_tree(),
+        )],
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful batch");
+    assert_eq!(
+        db.path_of_prefix(&prefix_of(&[TEST_LEAF, b"inner", b"deeper"]), None)
+            .unwrap()
+            .expect("successful lookup"),
+        Some(vec![
+            TEST_LEAF.to_vec(),
+            b"inner".to_vec(),
+            b"deeper".to_vec()
+        ])
+    );
+    assert_eq!(
+        db.path_of_prefix(&prefix_of(&[ANOTHER_TEST_LEAF, b"other"]), None)
+            .unwrap()
+            .expect("successful lookup"),
+        Some(vec![ANOTHER_TEST_LEAF.to_vec(), b"other".to_vec()])
+    );
+
+    // Deleting a subtree unregisters it with the subtrees below it
+    db.delete(
+        [TEST_LEAF].as_ref(),
+        b"inner",
+        Some(DeleteOptions {
+            allow_deleting_non_empty_trees: true,
+            deleting_non_empty_trees_returns_error: false,
+            ..Default::default()
+        }),
+        None,
+    )
+    .unwrap()
+    .expect("successful delete");
+    for path in [
+        [TEST_LEAF, b"inner"].as_ref(),
+        [TEST_LEAF, b"inner", b"deeper"].as_ref(),
+    ] {
+        assert_eq!(
+            db.path_of_prefix(&prefix_of(path), None)
+                .unwrap()
+                .expect("successful lookup"),
+            None
+        );
+    }
+    let registered: Vec<_> = db
+        .registered_prefixes(None)
+        .unwrap()
+        .expect("successful listing")
+        .into_iter()
+        .map(|(_, path)| path)
+        .collect();
+    assert_eq!(registered.len(), 4);
+    assert!(registered.contains(&vec![]));
+    assert!(registered.contains(&vec![TEST_LEAF.to_vec()]));
+}
+
+#[test]
+fn test_subtree_stats() {
+    let db = make_test_grovedb();
+
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"innertree",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .unwrap();
+
+    let stats = db
+        .subtree_stats([TEST_LEAF, b"innertree"].as_ref(), None)
+        .unwrap()
+        .expect("path is valid tree");
+    assert_eq!(stats.height, 0);
+    assert_eq!(stats.node_count, 0);
+
+    for i in 0u8..20 {
+        db.insert(
+            [TEST_LEAF, b"innertree"].as_ref(),
+            &[i],
+            Element::new_item(vec![i; i as usize + 1]),
+            None,
+            None,
+        )
+        .unwrap()
+        .unwrap();
+    }
+
+    let stats = db
+        .subtree_stats([TEST_LEAF, b"innertree"].as_ref(), None)
+        .unwrap()
+        .expect("path is valid tree");
+    assert_eq!(stats.node_count, 20);
+    assert_eq!(stats.max_key_size, 1);
+    assert!(stats.height >= 5);
+    assert_eq!(stats.balance_factors.values().sum::<u64>(), 20);
+
+    assert!(matches!(
+        db.subtree_stats([TEST_LEAF, b"missing"].as_ref(), None)
+            .unwrap(),
+        Err(Error::PathNotFound(_))
+    ));
+}
+
+#[test]
+fn test_prefetch_subtree() {
+    let db = make_test_grovedb();
+
+    for i in 0u8..31 {
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            &[i],
+            Element::new_item(vec![i]),
+            None,
+            None,
+        )
+        .unwrap()
+        .unwrap();
+    }
+
+    assert_eq!(
+        db.prefetch_subtree([TEST_LEAF].as_ref(), 0, false, None)
+            .unwrap()
+            .expect("path is valid tree"),
+        0
+    );
+    assert_eq!(
+        db.prefetch_subtree([TEST_LEAF].as_ref(), 2, false, None)
+            .unwrap()
+            .expect("path is valid tree"),
+        3
+    );
+
+    let shallow = db.prefetch_subtree([TEST_LEAF].as_ref(), 2, false, None);
+    let read_ahead = db.prefetch_subtree([TEST_LEAF].as_ref(), 2, true, None);
+    assert!(read_ahead.cost.seek_count > shallow.cost.seek_count);
+
+    let tx = db.start_transaction().unwrap();
+    assert_eq!(
+        db.prefetch_subtree([TEST_LEAF].as_ref(), 64, true, Some(&tx))
+            .unwrap()
+            .expect("path is valid tree"),
+        31
+    );
+
+    assert!(matches!(
+        db.prefetch_subtree([TEST_LEAF, b"missing"].as_ref(), 1, false, None)
+            .unwrap(),
+        Err(Error::PathNotFound(_))
+    ));
+}
+
+#[test]
+fn test_metrics_by_path_tag() {
+    use crate::metrics::{PathTagMode, PathTagging};
+
+    let db = make_test_grovedb();
+    let metrics = db.metrics();
+    metrics.reset();
+    metrics.set_path_tagging(Some(PathTagging {
+        mode: PathTagMode::Truncated(10),
+        max_tags: 1,
+    }));
+
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key",
+        Element::new_item(b"value".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    db.insert(
+        [ANOTHER_TEST_LEAF].as_ref(),
+        b"key",
+        Element::new_item(b"value".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    let mut query = Query::new();
+    query.insert_all();
+    db.query_raw(
+        &PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], query),
+        true,
+        QueryKeyElementPairResultType,
+        None,
+    )
+    .unwrap()
+    .expect("successful query");
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.total.writes, 2);
+    assert_eq!(snapshot.total.queries, 1);
+    assert_eq!(snapshot.by_path_tag.len(), 1);
+    let test_leaf_counters = snapshot.by_path_tag[&hex::encode(TEST_LEAF)];
+    assert_eq!(test_leaf_counters.writes, 1);
+    assert_eq!(test_leaf_counters.queries, 1);
+    // the second top-level tree is over the cardinality limit
+    assert_eq!(snapshot.over_cardinality_limit.writes, 1);
+
+    // rejected writes aren't counted
+    assert!(db
+        .insert(
+            [TEST_LEAF, b"missing"].as_ref(),
+            b"key",
+            Element::new_item(b"value".to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .is_err());
+    assert!(db
+        .delete([TEST_LEAF].as_ref(), b"missing", None, None)
+        .unwrap()
+        .is_err());
+    assert_eq!(metrics.snapshot().total.writes, 2);
+
+    db.get([TEST_LEAF].as_ref(), b"key", None)
+        .unwrap()
+        .expect("successful get");
+    let reads = metrics.snapshot().by_path_tag[&hex::encode(TEST_LEAF)].reads;
+    assert!(reads > test_leaf_counters.reads);
+
+    metrics.set_path_tagging(Some(PathTagging {
+        mode: PathTagMode::Hashed,
+        max_tags: 10,
+    }));
+    db.delete([ANOTHER_TEST_LEAF].as_ref(), b"key", None, None)
+        .unwrap()
+        .expect("successful delete");
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.total.writes, 3);
+    assert_eq!(snapshot.over_cardinality_limit.writes, 0);
+    let (tag, counters) = snapshot.by_path_tag.iter().next().expect("expected a tag");
+    assert_eq!(tag.len(), 16);
+    assert_eq!(counters.writes, 1);
+}
+
+#[test]
+fn test_update_element_flags() {
+    let populate = |db: &TempGroveDb,
+                    item_flags: Option<ElementFlags>,
+                    tree_flags: Option<ElementFlags>,
+                    reference_flags: Option<ElementFlags>| {
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"item",
+            Element::new_item_with_flags(b"value".to_vec(), item_flags),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful item insert");
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"tree",
+            Element::new_tree_with_flags(None, tree_flags),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful tree insert");
+        db.insert(
+            [TEST_LEAF, b"tree"].as_ref(),
+            b"inner",
+            Element::new_item(b"inner".to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful inner item insert");
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"ref",
+            Element::new_reference_with_flags(
+                ReferencePathType::AbsolutePathReference(vec![
+                    TEST_LEAF.to_vec(),
+                    b"item".to_vec(),
+                ]),
+                reference_flags,
+            ),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful reference insert");
+    };
+
+    let db = make_test_grovedb();
+    populate(&db, None, None, None);
+
+    db.update_element_flags([TEST_LEAF].as_ref(), b"tree", Some(vec![2]), None)
+        .unwrap()
+        .expect("successful tree flags update");
+    assert_eq!(
+        db.get_raw([TEST_LEAF].as_ref().into(), b"tree", None)
+            .unwrap()
+            .expect("successful get"),
+        Element::new_tree_with_flags(Some(b"inner".to_vec()), Some(vec![2]))
+    );
+
+    // the reference follows the new flags of the item updated in the same batch
+    db.apply_batch(
+        vec![
+            batch::GroveDbOp::update_flags_op(
+                vec![TEST_LEAF.to_vec()],
+                b"item".to_vec(),
+                Some(vec![1]),
+            ),
+            batch::GroveDbOp::update_flags_op(
+                vec![TEST_LEAF.to_vec()],
+                b"ref".to_vec(),
+                Some(vec![3]),
+            ),
+        ],
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful flags update batch");
+    assert_eq!(
+        db.get([TEST_LEAF].as_ref(), b"ref", None)
+            .unwrap()
+            .expect("successful get"),
+        Element::new_item_with_flags(b"value".to_vec(), Some(vec![1]))
+    );
+
+    let expected_db = make_test_grovedb();
+    populate(&expected_db, Some(vec![1]), Some(vec![2]), Some(vec![3]));
+    assert_eq!(
+        db.root_hash(None).unwrap().unwrap(),
+        expected_db.root_hash(None).unwrap().unwrap()
+    );
+
+    assert!(matches!(
+        db.update_element_flags([TEST_LEAF].as_ref(), b"missing", None, None)
+            .unwrap(),
+        Err(Error::PathKeyNotFound(_))
+    ));
+}
+
+#[test]
+fn test_rename_key_of_item() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"old",
+        Element::new_item(b"value".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    db.rename_key([TEST_LEAF].as_ref(), b"old", b"new", None)
+        .unwrap()
+        .expect("successful rename");
+
+    assert!(matches!(
+        db.get([TEST_LEAF].as_ref(), b"old", None).unwrap(),
+        Err(Error::PathKeyNotFound(_))
+    ));
+    assert_eq!(
+        db.get([TEST_LEAF].as_ref(), b"new", None)
+            .unwrap()
+            .expect("successful get"),
+        Element::new_item(b"value".to_vec())
+    );
+
+    let expected_db = make_test_grovedb();
+    expected_db
+        .insert(
+            [TEST_LEAF].as_ref(),
+            b"new",
+            Element::new_item(b"value".to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+    assert_eq!(
+        db.root_hash(None).unwrap().unwrap(),
+        expected_db.root_hash(None).unwrap().unwrap()
+    );
+
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"other",
+        Element::new_item(b"other".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    assert!(matches!(
+        db.rename_key([TEST_LEAF].as_ref(), b"other", b"new", None)
+            .unwrap(),
+        Err(Error::OverrideNotAllowed(_))
+    ));
+}
+
+#[test]
+fn test_rename_key_of_tree_moves_subtrees() {
+    let populate = |db: &TempGroveDb, tree_key: &[u8]| {
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            tree_key,
+            Element::empty_tree(),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+        db.insert(
+            [TEST_LEAF, tree_key].as_ref(),
+            b"inner",
+            Element::empty_tree(),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+        db.insert(
+            [TEST_LEAF, tree_key, b"inner"].as_ref(),
+            b"item",
+            Element::new_item(b"value".to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+    };
+
+    let db = make_test_grovedb();
+    populate(&db, b"old");
+    let expected_db = make_test_grovedb();
+    populate(&expected_db, b"new");
+
+    let transaction = db.start_transaction().unwrap();
+    db.rename_key([TEST_LEAF].as_ref(), b"old", b"new", Some(&transaction))
+        .unwrap()
+        .expect("successful rename");
+    // the rename is not visible outside of the transaction before commit
+    assert_eq!(
+        db.get([TEST_LEAF, b"old", b"inner"].as_ref(), b"item", None)
+            .unwrap()
+            .expect("successful get"),
+        Element::new_item(b"value".to_vec())
+    );
+    db.commit_transaction(transaction)
+        .unwrap()
+        .expect("successful commit");
+
+    assert!(db
+        .get([TEST_LEAF, b"old", b"inner"].as_ref(), b"item", None)
+        .unwrap()
+        .is_err());
+    assert_eq!(
+        db.get([TEST_LEAF, b"new", b"inner"].as_ref(), b"item", None)
+            .unwrap()
+            .expect("successful get"),
+        Element::new_item(b"value".to_vec())
+    );
+    assert_eq!(
+        db.root_hash(None).unwrap().unwrap(),
+        expected_db.root_hash(None).unwrap().unwrap()
+    );
+
+    db.rename_key([TEST_LEAF].as_ref(), b"new", b"old", None)
+        .unwrap()
+        .expect("successful rename");
+    assert_eq!(
+        db.get([TEST_LEAF, b"old", b"inner"].as_ref(), b"item", None)
+            .unwrap()
+            .expect("successful get"),
+        Element::new_item(b"value".to_vec())
+    );
+}
+
+#[test]
+fn test_rename_key_of_tree_moves_auxiliary_data() {
+    let db = make_test_grovedb();
+    db.enable_prefix_registry()
+        .unwrap()
+        .expect("successful enable");
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"old",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    db.insert(
+        [TEST_LEAF, b"old"].as_ref(),
+        b"inner",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    let first_key = db
+        .insert_auto_key(
+            [TEST_LEAF, b"old", b"inner"].as_ref(),
+            Element::new_item(b"first".to_vec()),
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+    db.increment_counter([TEST_LEAF, b"old"].as_ref(), b"counter", 5, None)
+        .unwrap()
+        .expect("successful increment");
+
+    db.rename_key([TEST_LEAF].as_ref(), b"old", b"new", None)
+        .unwrap()
+        .expect("successful rename");
+
+    // the pending counter and the auto-key cursor moved with the subtrees
+    assert_eq!(
+        db.get_counter([TEST_LEAF, b"new"].as_ref(), b"counter", None)
+            .unwrap()
+            .expect("successful get"),
+        5
+    );
+    let second_key = db
+        .insert_auto_key(
+            [TEST_LEAF, b"new", b"inner"].as_ref(),
+            Element::new_item(b"second".to_vec()),
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+    assert_ne!(first_key, second_key);
+    assert_eq!(
+        db.get([TEST_LEAF, b"new", b"inner"].as_ref(), &first_key, None)
+            .unwrap()
+            .expect("successful get"),
+        Element::new_item(b"first".to_vec())
+    );
+
+    // the prefix registry only knows the new paths
+    let registered: Vec<Vec<Vec<u8>>> = db
+        .registered_prefixes(None)
+        .unwrap()
+        .expect("successful listing")
+        .into_iter()
+        .map(|(_, path)| path)
+        .collect();
+    assert!(registered.contains(&vec![TEST_LEAF.to_vec(), b"new".to_vec()]));
+    assert!(registered.contains(&vec![
+        TEST_LEAF.to_vec(),
+        b"new".to_vec(),
+        b"inner".to_vec()
+    ]));
+    assert!(!registered
+        .iter()
+        .any(|path| path.get(1) == Some(&b"old".to_vec())));
+}
+
+#[test]
+fn test_copy_subtree() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"src",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    db.insert(
+        [TEST_LEAF, b"src"].as_ref(),
+        b"item",
+        Element::new_item(b"value".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    db.insert(
+        [TEST_LEAF, b"src"].as_ref(),
+        b"ref",
+        Element::new_reference(ReferencePathType::SiblingReference(b"item".to_vec())),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    db.insert(
+        [TEST_LEAF, b"src"].as_ref(),
+        b"sums",
+        Element::empty_sum_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    for (key, value) in [(b"a", 3), (b"b", 4)] {
+        db.insert(
+            [TEST_LEAF, b"src", b"sums"].as_ref(),
+            key,
+            Element::new_sum_item(value),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+    }
+
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"outside",
+        Element::new_item(b"outside".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    db.insert(
+        [TEST_LEAF, b"src"].as_ref(),
+        b"up_ref",
+        Element::new_reference(ReferencePathType::UpstreamRootHeightReference(
+            1,
+            vec![b"outside".to_vec()],
+        )),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    db.insert(
+        [TEST_LEAF, b"src"].as_ref(),
+        b"zz",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    db.insert(
+        [TEST_LEAF, b"src", b"zz"].as_ref(),
+        b"chained",
+        Element::new_reference(ReferencePathType::AbsolutePathReference(vec![
+            TEST_LEAF.to_vec(),
+            b"src".to_vec(),
+            b"item".to_vec(),
+        ])),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    // copied before the reference it points to, which is in a later subtree
+    db.insert(
+        [TEST_LEAF, b"src"].as_ref(),
+        b"abs_ref",
+        Element::new_reference(ReferencePathType::AbsolutePathReference(vec![
+            TEST_LEAF.to_vec(),
+            b"src".to_vec(),
+            b"zz".to_vec(),
+            b"chained".to_vec(),
+        ])),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+
+    let cost = db
+        .copy_subtree(
+            [TEST_LEAF, b"src"].as_ref(),
+            [ANOTHER_TEST_LEAF].as_ref(),
+            b"copy",
+            None,
+        )
+        .cost_as_result()
+        .expect("successful copy");
+    assert!(cost.storage_cost.added_bytes > 0);
+
+    assert_eq!(
+        db.get([ANOTHER_TEST_LEAF, b"copy"].as_ref(), b"item", None)
+            .unwrap()
+            .expect("successful get"),
+        Element::new_item(b"value".to_vec())
+    );
+    // the relative reference resolves within the copy
+    assert_eq!(
+        db.get([ANOTHER_TEST_LEAF, b"copy"].as_ref(), b"ref", None)
+            .unwrap()
+            .expect("successful get"),
+        Element::new_item(b"value".to_vec())
+    );
+    // references into the copied subtree point to the copy
+    assert_eq!(
+        db.get_raw(
+            [ANOTHER_TEST_LEAF, b"copy"].as_ref().into(),
+            b"abs_ref",
+            None
+        )
+        .unwrap()
+        .expect("successful get"),
+        Element::new_reference(ReferencePathType::AbsolutePathReference(vec![
+            ANOTHER_TEST_LEAF.to_vec(),
+            b"copy".to_vec(),
+            b"zz".to_vec(),
+            b"chained".to_vec(),
+        ]))
+    );
+    assert_eq!(
+        db.get([ANOTHER_TEST_LEAF, b"copy"].as_ref(), b"abs_ref", None)
+            .unwrap()
+            .expect("successful get"),
+        Element::new_item(b"value".to_vec())
+    );
+    // other references keep their target
+    assert_eq!(
+        db.get([ANOTHER_TEST_LEAF, b"copy"].as_ref(), b"up_ref", None)
+            .unwrap()
+            .expect("successful get"),
+        Element::new_item(b"outside".to_vec())
+    );
+    assert_eq!(
+        db.sum_of_subtree([ANOTHER_TEST_LEAF, b"copy", b"sums"].as_ref(), None)
+            .unwrap()
+            .expect("successful sum"),
+        7
+    );
+    // the source is left as it is
+    assert_eq!(
+        db.get([TEST_LEAF, b"src"].as_ref(), b"item", None)
+            .unwrap()
+            .expect("successful get"),
+        Element::new_item(b"value".to_vec())
+    );
+
+    assert!(matches!(
+        db.copy_subtree(
+            [TEST_LEAF, b"src"].as_ref(),
+            [ANOTHER_TEST_LEAF].as_ref(),
+            b"copy",
+            None
+        )
+        .unwrap(),
+        Err(Error::OverrideNotAllowed(_))
+    ));
+    assert!(matches!(
+        db.copy_subtree(
+            [TEST_LEAF, b"src", b"item"].as_ref(),
+            [ANOTHER_TEST_LEAF].as_ref(),
+            b"other",
+            None
+        )
+        .unwrap(),
+        Err(Error::WrongElementType(_))
+    ));
+}
+
+#[test]
+fn test_merge_counters() {
+    let db = make_test_grovedb();
+    let root_hash = db.root_hash(None).unwrap().unwrap();
+
+    for delta in [5, 3] {
+        db.increment_counter([TEST_LEAF].as_ref(), b"counter", delta, None)
+            .unwrap()
+            .expect("successful increment");
+    }
+    assert_eq!(
+        db.get_counter([TEST_LEAF].as_ref(), b"counter", None)
+            .unwrap()
+            .expect("successful get"),
+        8
+    );
+    // pending increments don't change the tree hash
+    assert_eq!(db.root_hash(None).unwrap().unwrap(), root_hash);
+
+    assert_eq!(
+        db.sync_counter([TEST_LEAF].as_ref(), b"counter", None)
+            .unwrap()
+            .expect("successful sync"),
+        8
+    );
+    assert_ne!(db.root_hash(None).unwrap().unwrap(), root_hash);
+    assert_eq!(
+        db.get_raw([TEST_LEAF].as_ref().into(), b"counter", None)
+            .unwrap()
+            .expect("successful get"),
+        Element::new_sum_item(8)
+    );
+
+    let transaction = db.start_transaction().unwrap();
+    for delta in [2, 4] {
+        db.increment_counter([TEST_LEAF].as_ref(), b"counter", delta, Some(&transaction))
+            .unwrap()
+            .expect("successful increment");
+    }
+    assert_eq!(
+        db.get_counter([TEST_LEAF].as_ref(), b"counter", Some(&transaction))
+            .unwrap()
+            .expect("successful get"),
+        14
+    );
+    assert_eq!(
+        db.get_counter([TEST_LEAF].as_ref(), b"counter", None)
+            .unwrap()
+            .expect("successful get"),
+        8
+    );
+    db.commit_transaction(transaction)
+        .unwrap()
+        .expect("successful commit");
+
+    assert_eq!(
+        db.sync_counters([TEST_LEAF].as_ref(), None)
+            .unwrap()
+            .expect("successful sync"),
+        vec![(b"counter".to_vec(), 14)]
+    );
+    assert!(db
+        .sync_counters([TEST_LEAF].as_ref(), None)
+        .unwrap()
+        .expect("successful sync")
+        .is_empty());
+    assert_eq!(
+        db.get_counter([TEST_LEAF].as_ref(), b"counter", None)
+            .unwrap()
+            .expect("successful get"),
+        14
+    );
+
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"item",
+        Element::new_item(b"value".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    db.increment_counter([TEST_LEAF].as_ref(), b"item", 1, None)
+        .unwrap()
+        .expect("successful increment");
+    assert!(matches!(
+        db.sync_counter([TEST_LEAF].as_ref(), b"item", None)
+            .unwrap(),
+        Err(Error::WrongElementType(_))
+    ));
+}
+
+#[test]
+fn test_insert_auto_key() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"queue",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    let queue_path = [TEST_LEAF, b"queue"];
+
+    for expected_key in 0u64..2 {
+        let key = db
+            .insert_auto_key(queue_path.as_ref(), Element::new_item(b"v".to_vec()), None)
+            .unwrap()
+            .expect("successful insert");
+        assert_eq!(key, expected_key.to_be_bytes().to_vec());
+    }
+    assert_eq!(
+        db.get(queue_path.as_ref(), &1u64.to_be_bytes(), None)
+            .unwrap()
+            .expect("successful get"),
+        Element::new_item(b"v".to_vec())
+    );
+
+    // concurrent allocations of the same key conflict
+    let tx1 = db.start_transaction().unwrap();
+    let tx2 = db.start_transaction().unwrap();
+    let key1 = db
+        .insert_auto_key(
+            queue_path.as_ref(),
+            Element::new_item(b"v1".to_vec()),
+            Some(&tx1),
+        )
+        .unwrap()
+        .expect("successful insert");
+    let key2 = db
+        .insert_auto_key(
+            queue_path.as_ref(),
+            Element::new_item(b"v2".to_vec()),
+            Some(&tx2),
+        )
+        .unwrap()
+        .expect("successful insert");
+    assert_eq!(key1, key2);
+    db.commit_transaction(tx1)
+        .unwrap()
+        .expect("successful commit");
+    assert!(db.commit_transaction(tx2).unwrap().is_err());
+
+    assert_eq!(
+        db.insert_auto_key(queue_path.as_ref(), Element::empty_tree(), None)
+            .unwrap()
+            .expect("successful insert"),
+        3u64.to_be_bytes().to_vec()
+    );
+}
+
+#[test]
+fn test_append_log() {
+    use crate::operations::append_log::AppendLogBounds;
+
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"log",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    let log_path = [TEST_LEAF, b"log"];
+
+    for (expected_seq, value) in [b"a", b"b", b"c"].into_iter().enumerate() {
+        let seq = db
+            .append(log_path.as_ref(), Element::new_item(value.to_vec()), None)
+            .unwrap()
+            .expect("successful append");
+        assert_eq!(seq, expected_seq as u64);
+    }
+    assert_eq!(
+        db.read_range(log_path.as_ref(), 1, 10, None)
+            .unwrap()
+            .expect("successful read"),
+        vec![
+            (1, Element::new_item(b"b".to_vec())),
+            (2, Element::new_item(b"c".to_vec())),
+        ]
+    );
+    assert_eq!(
+        db.read_range(log_path.as_ref(), 0, 1, None)
+            .unwrap()
+            .expect("successful read"),
+        vec![(0, Element::new_item(b"a".to_vec()))]
+    );
+
+    assert_eq!(
+        db.truncate_front(log_path.as_ref(), 2, None)
+            .unwrap()
+            .expect("successful truncate"),
+        2
+    );
+    assert_eq!(
+        db.append_log_bounds(log_path.as_ref(), None)
+            .unwrap()
+            .expect("successful bounds"),
+        AppendLogBounds { first: 2, next: 3 }
+    );
+    assert_eq!(
+        db.read_range(log_path.as_ref(), 0, 10, None)
+            .unwrap()
+            .expect("successful read"),
+        vec![(2, Element::new_item(b"c".to_vec()))]
+    );
+
+    // sequence numbers are not reused once the log is empty
+    assert_eq!(
+        db.truncate_front(log_path.as_ref(), 10, None)
+            .unwrap()
+            .expect("successful truncate"),
+        1
+    );
+    let transaction = db.start_transaction().unwrap();
+    assert_eq!(
+        db.append(
+            log_path.as_ref(),
+            Element::new_item(b"d".to_vec()),
+            Some(&transaction)
+        )
+        .unwrap()
+        .expect("successful append"),
+        3
+    );
+    assert!(db
+        .read_range(log_path.as_ref(), 0, 10, None)
+        .unwrap()
+        .expect("successful read")
+        .is_empty());
+    db.commit_transaction(transaction)
+        .unwrap()
+        .expect("successful commit");
+    assert_eq!(
+        db.read_range(log_path.as_ref(), 0, 10, None)
+            .unwrap()
+            .expect("successful read"),
+        vec![(3, Element::new_item(b"d".to_vec()))]
+    );
+
+    assert!(matches!(
+        db.append(log_path.as_ref(), Element::empty_tree(), None)
+            .unwrap(),
+        Err(Error::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn test_append_log_truncate_all_matches_deleting_each_entry() {
+    let truncated_db = make_test_grovedb();
+    let deleted_db = make_test_grovedb();
+    let log_path = [TEST_LEAF, b"log"];
+    for db in [&truncated_db, &deleted_db] {
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"log",
+            Element::empty_tree(),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+        for i in 0u8..20 {
+            db.append(log_path.as_ref(), Element::new_item(vec![i]), None)
+                .unwrap()
+                .expect("successful append");
+        }
+    }
+
+    // Truncating every entry clears the log over its prefix
+    assert_eq!(
+        truncated_db
+            .truncate_front(log_path.as_ref(), 20, None)
+            .unwrap()
+            .expect("successful truncate"),
+        20
+    );
+    for seq in 0u64..20 {
+        deleted_db
+            .delete(log_path.as_ref(), &seq.to_be_bytes(), None, None)
+            .unwrap()
+            .expect("successful delete");
+    }
+
+    assert_eq!(
+        truncated_db.root_hash(None).unwrap().unwrap(),
+        deleted_db.root_hash(None).unwrap().unwrap()
+    );
+    assert!(truncated_db
+        .read_range(log_path.as_ref(), 0, 100, None)
+        .unwrap()
+        .expect("successful read")
+        .is_empty());
+    assert_eq!(
+        truncated_db
+            .append(log_path.as_ref(), Element::new_item(b"a".to_vec()), None)
+            .unwrap()
+            .expect("successful append"),
+        20
+    );
+}
+
+#[test]
+fn test_insertion_metadata() {
+    let db = make_test_grovedb();
+    db.configure_insertion_metadata([TEST_LEAF].as_ref(), true, None)
+        .unwrap()
+        .expect("successful configuration");
+
+    let first = InsertionMetadata {
+        block_height: 10,
+        timestamp: 1000,
+    };
+    let insert_options = InsertOptions {
+        insertion_metadata: Some(first),
+        ..Default::default()
+    };
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key1",
+        Element::new_item(b"a".to_vec()),
+        Some(insert_options.clone()),
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    db.insert(
+        [ANOTHER_TEST_LEAF].as_ref(),
+        b"key1",
+        Element::new_item(b"a".to_vec()),
+        Some(insert_options),
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+
+    let second = InsertionMetadata {
+        block_height: 11,
+        timestamp: 2000,
+    };
+    db.apply_batch(
+        vec![batch::GroveDbOp::insert_op(
+            vec![TEST_LEAF.to_vec()],
+            b"key2".to_vec(),
+            Element::new_item(b"b".to_vec()),
+        )],
+        Some(batch::BatchApplyOptions {
+            insertion_metadata: Some(second),
+            ..Default::default()
+        }),
+        None,
+    )
+    .unwrap()
+    .expect("successful batch apply");
+
+    // writes without metadata don't record anything
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key3",
+        Element::new_item(b"c".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+
+    assert_eq!(
+        db.get_with_insertion_metadata([TEST_LEAF].as_ref(), b"key1", None)
+            .unwrap()
+            .expect("successful get"),
+        (Element::new_item(b"a".to_vec()), Some(first))
+    );
+    assert_eq!(
+        db.get_with_insertion_metadata([ANOTHER_TEST_LEAF].as_ref(), b"key1", None)
+            .unwrap()
+            .expect("successful get"),
+        (Element::new_item(b"a".to_vec()), None)
+    );
+
+    let mut query = Query::new();
+    query.insert_all();
+    let path_query = PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], query);
+    let metadata: Vec<_> = db
+        .query_with_insertion_metadata(&path_query, None)
+        .unwrap()
+        .expect("successful query")
+        .into_iter()
+        .map(|(_, key, _, metadata)| (key, metadata))
+        .collect();
+    assert_eq!(
+        metadata,
+        vec![
+            (b"key1".to_vec(), Some(first)),
+            (b"key2".to_vec(), Some(second)),
+            (b"key3".to_vec(), None),
+        ]
+    );
+}
+
+#[test]
+fn test_scan_resumes_from_cursor() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"a",
+        Element::new_item(b"1".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"b",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    db.insert(
+        [TEST_LEAF, b"b"].as_ref(),
+        b"c",
+        Element::new_item(b"2".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"d",
+        Element::new_item(b"3".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+
+    let full_scan = db
+        .scan(None, u16::MAX, None)
+        .unwrap()
+        .expect("successful scan");
+    assert!(full_scan.cursor.is_none());
+    let positions: Vec<_> = full_scan
+        .elements
+        .iter()
+        .map(|(path, key, _)| (path.clone(), key.clone()))
+        .collect();
+    assert_eq!(
+        positions,
+        vec![
+            (vec![], TEST_LEAF.to_vec()),
+            (vec![TEST_LEAF.to_vec()], b"a".to_vec()),
+            (vec![TEST_LEAF.to_vec()], b"b".to_vec()),
+            (vec![TEST_LEAF.to_vec(), b"b".to_vec()], b"c".to_vec()),
+            (vec![TEST_LEAF.to_vec()], b"d".to_vec()),
+            (vec![], ANOTHER_TEST_LEAF.to_vec()),
+        ]
+    );
+
+    // paging through the grove returns the same elements
+    let mut paged = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = db
+            .scan(cursor.as_ref(), 2, None)
+            .unwrap()
+            .expect("successful scan");
+        paged.extend(page.elements);
+        cursor = page.cursor;
+        if cursor.is_none() {
+            break;
+        }
+    }
+    assert_eq!(paged, full_scan.elements);
+}
+
+#[test]
+fn test_find_paths_of_key() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"id",
+        Element::new_item(b"a".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful insert");
+    assert!(db.find_paths_of_key(b"id", None).unwrap().is_err());
+
+    // elements written before the index was enabled are backfilled
+    db.enable_reverse_key_index()
+        .unwrap()
+        .expect("successful enable");
+    assert_eq!(
+        db.find_paths_of_key(b"id", None)
+            .unwrap()
+            .expect("successful lookup"),
+        vec![vec![TEST_LEAF.to_vec()]]
+    );
+
+    db.apply_batch(
+        vec![batch::GroveDbOp::insert_op(
+            vec![ANOTHER_TEST_LEAF.to_vec()],
+            b"id".to_vec(),
+            Element::new_item(b"b".to_vec()),
+        )],
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful batch apply");
+    assert_eq!(
+        db.find_paths_of_key(b"id", None)
+            .unwrap()
+            .expect("successful lookup"),
+        vec![vec![TEST_LEAF.to_vec()], vec![ANOTHER_TEST_LEAF.to_vec()]]
+    );
+
+    // removed elements aren't returned
+    db.delete([TEST_LEAF].as_ref(), b"id", None, None)
+        .unwrap()
+        .expect("successful delete");
+    assert_eq!(
+        db.find_paths_of_key(b"id", None)
+            .unwrap()
+            .expect("successful lookup"),
+        vec![vec![ANOTHER_TEST_LEAF.to_vec()]]
+    );
+}
+
+#[test]
+fn test_value_size_report() {
+    let db = make_test_grovedb();
+    for (key, size) in [
+        (b"small".as_slice(), 1),
+        (b"large".as_slice(), 300),
+        (b"medium".as_slice(), 40),
+    ] {
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            key,
+            Element::new_item(vec![0; size]),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+    }
+
+    let report = db
+        .value_size_report(2, None)
+        .unwrap()
+        .expect("successful report");
+    let histogram = &report.histograms[&vec![TEST_LEAF.to_vec()]];
+    assert_eq!(histogram.count, 3);
+    assert_eq!(
+        histogram.total_bytes,
+        [1, 300, 40]
+            .into_iter()
+            .map(|size| Element::new_item(vec![0; size]).serialized_size() as u64)
+            .sum::<u64>()
+    );
+    assert_eq!(
+        report.histograms[&Vec::<Vec<u8>>::new()].count,
+        2,
+        "the root holds the two test leaves"
+    );
+    let largest: Vec<_> = report
+        .largest
+        .iter()
+        .map(|(path, key, _)| (path.clone(), key.clone()))
+        .collect();
+    assert_eq!(
+        largest,
+        vec![
+            (vec![TEST_LEAF.to_vec()], b"large".to_vec()),
+            (vec![TEST_LEAF.to_vec()], b"medium".to_vec()),
+        ]
+    );
+}
+
+#[test]
+fn test_apply_batch_with_cost_breakdown() {
+    let db = make_test_grovedb();
+    let ops = vec![
+        batch::GroveDbOp::insert_op(
+            vec![TEST_LEAF.to_vec()],
+            b"key1".to_vec(),
+            Element::new_item(b"a".to_vec()),
+        ),
+        batch::GroveDbOp::insert_op(
+            vec![ANOTHER_TEST_LEAF.to_vec()],
+            b"key1".to_vec(),
+            Element::new_item(vec![0; 100]),
+        ),
+    ];
+    let result = db.apply_batch_with_cost_breakdown(ops, None, None);
+    let breakdown = result.value.expect("successful batch apply");
+
+    assert_eq!(breakdown.len(), 2);
+    assert!(breakdown.iter().all(|cost| cost.hash_node_calls > 0));
+    assert!(
+        breakdown[1].storage_cost.added_bytes > breakdown[0].storage_cost.added_bytes,
+        "the larger item pays for its bytes"
+    );
+    assert_eq!(
+        breakdown
+            .into_iter()
+            .fold(OperationCost::default(), |total, cost| total + cost),
+        result.cost
+    );
+}
+
+#[test]
+fn test_cost_breakdown_shares_sectioned_removals() {
+    use grovedb_costs::storage_cost::removal::StorageRemovedBytes::SectionedStorageRemoval;
+
+    let db = make_test_grovedb();
+    for (key, value) in [(b"small", vec![1; 5]), (b"large", vec![2; 200])] {
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            key,
+            Element::new_item_with_flags(
+                value,
+                Some(StorageFlags::new_single_epoch(0, None).serialize()),
+            ),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful item insert");
+    }
+
+    let ops = vec![
+        batch::GroveDbOp::delete_op(vec![TEST_LEAF.to_vec()], b"small".to_vec()),
+        batch::GroveDbOp::delete_op(vec![TEST_LEAF.to_vec()], b"large".to_vec()),
+    ];
+    let result = db.apply_batch_with_element_flags_update_and_cost_breakdown(
+        ops,
+        None,
+        |_, _, _| Ok(false),
+        |flags, removed_key_bytes, removed_value_bytes| {
+            StorageFlags::split_removal_bytes(
+                RemovalStrategy::OldestFirst,
+                flags,
+                removed_key_bytes,
+                removed_value_bytes,
+            )
+        },
+        None,
+    );
+    let breakdown = result.value.expect("successful batch apply");
+
+    let removed: Vec<u32> = breakdown
+        .iter()
+        .map(|cost| {
+            assert!(matches!(
+                cost.storage_cost.removed_bytes,
+                SectionedStorageRemoval(_)
+            ));
+            cost.storage_cost.removed_bytes.total_removed_bytes()
+        })
+        .collect();
+    assert!(
+        removed[1] > removed[0],
+        "the larger item is refunded its bytes"
+    );
+    assert_eq!(
+        breakdown
+            .into_iter()
+            .fold(OperationCost::default(), |total, cost| total + cost),
+        result.cost
+    );
+}
+
+#[test]
+fn test_batch_builder() {
+    let db = make_test_grovedb();
+    let ops = batch::BatchBuilder::new()
+        .insert(vec![TEST_LEAF.to_vec()])
+        .key(b"key1".to_vec())
+        .element(Element::new_item(b"value".to_vec()))
+        .insert(vec![ANOTHER_TEST_LEAF.to_vec()])
+        .key(b"ref".to_vec())
+        .ref_to(vec![TEST_LEAF.to_vec(), b"key1".to_vec()])
+        .build()
+        .expect("expected valid operations");
+    assert_eq!(ops.len(), 2);
+    db.apply_batch(ops, None, None)
+        .unwrap()
+        .expect("expected to apply batch");
+    assert_eq!(
+        db.get([ANOTHER_TEST_LEAF].as_ref(), b"ref", None)
+            .unwrap()
+            .expect("expected to get referenced item"),
+        Element::new_item(b"value".to_vec())
+    );
+
+    let too_long_key = batch::BatchBuilder::new()
+        .insert(vec![TEST_LEAF.to_vec()])
+        .key(vec![0; 256])
+        .element(Element::new_item(b"value".to_vec()))
+        .delete(vec![TEST_LEAF.to_vec()])
+        .key(b"key1".to_vec())
+        .build();
+    assert!(matches!(too_long_key, Err(Error::InvalidInput(_))));
+
+    let empty_reference = batch::BatchBuilder::new()
+        .insert(vec![TEST_LEAF.to_vec()])
+        .key(b"ref".to_vec())
+        .ref_to(vec![])
+        .build();
+    assert!(matches!(empty_reference, Err(Error::InvalidInput(_))));
+
+    let inconsistent = batch::BatchBuilder::new()
+        .insert(vec![TEST_LEAF.to_vec()])
+        .key(b"key1".to_vec())
+        .element(Element::new_item(b"value".to_vec()))
+        .delete(vec![TEST_LEAF.to_vec()])
+        .key(b"key1".to_vec())
+        .build();
+    assert!(matches!(inconsistent, Err(Error::InvalidBatchOperation(_))));
+}
+
+#[test]
+fn test_prove_is_empty_tree() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"empty",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful subtree insert");
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"full",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful subtree insert");
+    db.insert(
+        [TEST_LEAF, b"full"].as_ref(),
+        b"key",
+        Element::new_item(b"value".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful item insert");
+    let root_hash = db.root_hash(None).unwrap().unwrap();
+
+    let path = vec![TEST_LEAF.to_vec(), b"empty".to_vec()];
+    let proof = db.prove_is_empty_tree(path.clone()).unwrap().unwrap();
+    let (hash, is_empty) = GroveDb::verify_is_empty_tree(&proof, path).expect("should verify");
+    assert_eq!(hash, root_hash);
+    assert!(is_empty);
+
+    let path = vec![TEST_LEAF.to_vec(), b"full".to_vec()];
+    let proof = db.prove_is_empty_tree(path.clone()).unwrap().unwrap();
+    let (hash, is_empty) = GroveDb::verify_is_empty_tree(&proof, path).expect("should verify");
+    assert_eq!(hash, root_hash);
+    assert!(!is_empty);
+
+    let path = vec![TEST_LEAF.to_vec(), b"full".to_vec(), b"key".to_vec()];
+    let proof = db.prove_is_empty_tree(path.clone()).unwrap().unwrap();
+    assert!(matches!(
+        GroveDb::verify_is_empty_tree(&proof, path),
+        Err(Error::WrongElementType(_))
+    ));
+}
+
+#[test]
+fn test_prove_path_absence() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"innertree",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful subtree insert");
+    let root_hash = db.root_hash(None).unwrap().unwrap();
+
+    // Absent at an intermediate level
+    let path = vec![
+        TEST_LEAF.to_vec(),
+        b"innertree".to_vec(),
+        b"missing".to_vec(),
+        b"deeper".to_vec(),
+    ];
+    let proof = db.prove_path_absence(path.clone()).unwrap().unwrap();
+    let (hash, existing_depth) =
+        GroveDb::verify_path_absence(&proof, path).expect("should verify absence");
+    assert_eq!(hash, root_hash);
+    assert_eq!(existing_depth, 2);
+
+    // Absent at the last segment
+    let path = vec![
+        TEST_LEAF.to_vec(),
+        b"innertree".to_vec(),
+        b"missing".to_vec(),
+    ];
+    let proof = db.prove_path_absence(path.clone()).unwrap().unwrap();
+    let (hash, existing_depth) =
+        GroveDb::verify_path_absence(&proof, path).expect("should verify absence");
+    assert_eq!(hash, root_hash);
+    assert_eq!(existing_depth, 2);
+
+    // Absent right under the root
+    let path = vec![b"missing".to_vec(), b"deeper".to_vec()];
+    let proof = db.prove_path_absence(path.clone()).unwrap().unwrap();
+    let (hash, existing_depth) =
+        GroveDb::verify_path_absence(&proof, path).expect("should verify absence");
+    assert_eq!(hash, root_hash);
+    assert_eq!(existing_depth, 0);
+
+    // An existing path can't be proven absent
+    let path = vec![TEST_LEAF.to_vec(), b"innertree".to_vec()];
+    let proof = db.prove_path_absence(path.clone()).unwrap().unwrap();
+    assert!(matches!(
+        GroveDb::verify_path_absence(&proof, path),
+        Err(Error::InvalidProof(_))
+    ));
+}
+
+#[test]
+fn test_prove_path_key_absence() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key1",
+        Element::new_item(b"value1".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful item insert");
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key3",
+        Element::new_item(b"value3".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful item insert");
+    let root_hash = db.root_hash(None).unwrap().unwrap();
+
+    // Absent between two existing keys, before them and after them
+    for key in [b"key2".as_ref(), b"key0", b"key4"] {
+        let proof = db
+            .prove_path_key_absence(vec![TEST_LEAF.to_vec()], key)
+            .unwrap()
+            .unwrap();
+        let hash = GroveDb::verify_path_key_absence(&proof, vec![TEST_LEAF.to_vec()], key)
+            .expect("should verify absence");
+        assert_eq!(hash, root_hash);
+    }
+
+    // Absent because the path itself is absent
+    let path = vec![TEST_LEAF.to_vec(), b"missing".to_vec()];
+    let proof = db
+        .prove_path_key_absence(path.clone(), b"key1")
+        .unwrap()
+        .unwrap();
+    let hash =
+        GroveDb::verify_path_key_absence(&proof, path, b"key1").expect("should verify absence");
+    assert_eq!(hash, root_hash);
+
+    // An existing key can't be proven absent
+    let proof = db
+        .prove_path_key_absence(vec![TEST_LEAF.to_vec()], b"key1")
+        .unwrap()
+        .unwrap();
+    assert!(matches!(
+        GroveDb::verify_path_key_absence(&proof, vec![TEST_LEAF.to_vec()], b"key1"),
+        Err(Error::InvalidProof(_))
+    ));
+
+    // An absence proof doesn't verify for another key
+    let proof = db
+        .prove_path_key_absence(vec![TEST_LEAF.to_vec()], b"key2")
+        .unwrap()
+        .unwrap();
+    assert!(GroveDb::verify_path_key_absence(&proof, vec![TEST_LEAF.to_vec()], b"key1").is_err());
+}
+
+#[test]
+fn test_prove_query_many_shares_upper_layers() {
+    let db = make_deep_tree();
+    let root_hash = db.root_hash(None).unwrap().unwrap();
+
+    let first = PathQuery::new_single_key(
+        vec![TEST_LEAF.to_vec(), b"innertree".to_vec()],
+        b"key1".to_vec(),
+    );
+    let second = PathQuery::new_single_key(
+        vec![TEST_LEAF.to_vec(), b"innertree4".to_vec()],
+        b"key4".to_vec(),
+    );
+
+    let proof = db
+        .prove_query_many(vec![&first, &second])
+        .unwrap()
+        .expect("should generate proof");
+    let (hash, result_set) =
+        GroveDb::verify_query_many(&proof, vec![&first, &second]).expect("should verify proof");
+    assert_eq!(hash, root_hash);
+    let results: Vec<(Vec<Vec<u8>>, Vec<u8>, Element)> = result_set
+        .into_iter()
+        .map(|proved| {
+            (
+                proved.path,
+                proved.key,
+                Element::deserialize(&proved.value).unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        results,
+        vec![
+            (
+                vec![TEST_LEAF.to_vec(), b"innertree".to_vec()],
+                b"key1".to_vec(),
+                Element::new_item(b"value1".to_vec())
+            ),
+            (
+                vec![TEST_LEAF.to_vec(), b"innertree4".to_vec()],
+                b"key4".to_vec(),
+                Element::new_item(b"value4".to_vec())
+            ),
+        ]
+    );
+
+    // The root and test leaf layers are only proven once
+    let first_proof = db.prove_query(&first).unwrap().unwrap();
+    let second_proof = db.prove_query(&second).unwrap().unwrap();
+    assert!(proof.len() < first_proof.len() + second_proof.len());
+}
+
+#[test]
+fn test_prove_query_with_compact_version() {
+    let db = make_deep_tree();
+    let root_hash = db.root_hash(None).unwrap().unwrap();
+
+    let mut query = Query::new();
+    query.insert_all();
+    let mut subquery = Query::new();
+    subquery.insert_all();
+    query.set_subquery(subquery);
+    let path_query = PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], query);
+
+    let proof_v1 = db.prove_query(&path_query).unwrap().unwrap();
+    assert_eq!(
+        db.prove_query_with_version(&path_query, ProofVersion::V1)
+            .unwrap()
+            .unwrap(),
+        proof_v1
+    );
+    let proof_v2 = db
+        .prove_query_with_version(&path_query, ProofVersion::V2)
+        .unwrap()
+        .expect("should generate compact proof");
+    assert!(proof_v2.len() < proof_v1.len());
+
+    // Both versions prove the same elements
+    let (hash_v1, result_set_v1) =
+        GroveDb::verify_query(&proof_v1, &path_query).expect("should verify proof");
+    let (hash_v2, result_set_v2) =
+        GroveDb::verify_query(&proof_v2, &path_query).expect("should verify compact proof");
+    assert_eq!(hash_v1, root_hash);
+    assert_eq!(hash_v2, root_hash);
+    assert_eq!(result_set_v2, result_set_v1);
+    assert_eq!(result_set_v2.len(), 5);
+
+    let summary_v1 = ProofSummary::from_proof(&proof_v1).unwrap();
+    let summary_v2 = ProofSummary::from_proof(&proof_v2).unwrap();
+    assert_eq!(summary_v2.version, 2);
+    assert_eq!(summary_v2.layers, summary_v1.layers);
+
+    // A truncated compact proof doesn't verify
+    assert!(GroveDb::verify_query(&proof_v2[..proof_v2.len() - 1], &path_query).is_err());
+
+    // Proofs of unknown versions are rejected instead of being misparsed
+    let mut unknown_version_proof = proof_v1.clone();
+    unknown_version_proof[0] = 4;
+    assert!(matches!(
+        GroveDb::verify_query(&unknown_version_proof, &path_query),
+        Err(Error::InvalidProof(_))
+    ));
+    assert!(ProofSummary::from_proof(&unknown_version_proof).is_err());
+}
+
+#[test]
+fn test_compact_proof_of_large_values_is_larger() {
+    let db = make_test_grovedb();
+    for i in 0u8..10 {
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            &[i],
+            Element::new_item(vec![i; 20000]),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+    }
+
+    let mut query = Query::new();
+    query.insert_all();
+    let path_query = PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], query);
+
+    // Value lengths of 16384 bytes or more take a byte more as varints than
+    // in version 1
+    let proof_v1 = db.prove_query(&path_query).unwrap().unwrap();
+    let proof_v2 = db
+        .prove_query_with_version(&path_query, ProofVersion::V2)
+        .unwrap()
+        .expect("should generate compact proof");
+    assert!(proof_v2.len() > proof_v1.len());
+
+    let (_, result_set_v1) =
+        GroveDb::verify_query(&proof_v1, &path_query).expect("should verify proof");
+    let (_, result_set_v2) =
+        GroveDb::verify_query(&proof_v2, &path_query).expect("should verify compact proof");
+    assert_eq!(result_set_v2, result_set_v1);
+    assert_eq!(result_set_v2.len(), 10);
+}
+
+#[test]
+fn test_proof_display_and_summary() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"a",
+        Element::new_item(b"1".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful item insert");
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"b",
+        Element::new_item(b"2".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful item insert");
+
+    let root_hash = db.root_hash(None).unwrap().unwrap();
+    for encoding in [TextEncoding::Hex, TextEncoding::Base58] {
+        let text = display_root_hash(&root_hash, encoding);
+        assert_eq!(parse_root_hash(&text, encoding).unwrap(), root_hash);
+    }
+
+    let mut query = Query::new();
+    query.insert_key(b"a".to_vec());
+    let path_query = PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], query);
+    let proof = db.prove_query(&path_query).unwrap().unwrap();
+    for encoding in [TextEncoding::Hex, TextEncoding::Base58] {
+        let text = display_proof(&proof, encoding);
+        assert_eq!(parse_proof(&text, encoding).unwrap(), proof);
+    }
+    assert!(parse_proof("", TextEncoding::Hex).is_err());
+
+    let summary = ProofSummary::from_proof(&proof).expect("should summarize proof");
+    assert!(!summary.is_verbose);
+    assert_eq!(summary.byte_length, proof.len());
+    // the queried layer comes first, followed by the proof of its path
+    let layer_types: Vec<_> = summary
+        .layers
+        .iter()
+        .map(|layer| layer.proof_token_type)
+        .collect();
+    assert_eq!(
+        layer_types,
+        vec![ProofTokenType::SizedMerk, ProofTokenType::Merk]
+    );
+    assert!(summary
+        .layers
+        .iter()
+        .all(|layer| !layer.is_merk_ref && layer.op_count > 0 && layer.value_count == 1));
+    assert!(summary.to_string().starts_with("proof v"));
+
+    assert!(ProofSummary::from_proof(&proof[..proof.len() - 1]).is_err());
+
+    // Identical subtrees have identical merk proofs, the repeated one being
+    // encoded as a reference and summarized as the layer it points to
+    for tree in [b"x", b"y"] {
+        db.insert(
+            [ANOTHER_TEST_LEAF].as_ref(),
+            tree,
+            Element::empty_tree(),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful subtree insert");
+        db.insert(
+            [ANOTHER_TEST_LEAF, tree.as_slice()].as_ref(),
+            b"k",
+            Element::new_item(b"v".to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful item insert");
+    }
+    let mut subquery = Query::new();
+    subquery.insert_all();
+    let mut query = Query::new();
+    query.insert_all();
+    query.set_subquery(subquery);
+    let path_query = PathQuery::new_unsized(vec![ANOTHER_TEST_LEAF.to_vec()], query);
+    let proof = db.prove_query(&path_query).unwrap().unwrap();
+    let summary = ProofSummary::from_proof(&proof).expect("should summarize proof");
+    let subtree_layers: Vec<_> = summary
+        .layers
+        .iter()
+        .filter(|layer| layer.proof_token_type == ProofTokenType::SizedMerk)
+        .collect();
+    assert_eq!(subtree_layers.len(), 2);
+    assert!(!subtree_layers[0].is_merk_ref);
+    assert!(subtree_layers[1].is_merk_ref);
+    assert_eq!(subtree_layers[0].byte_length, subtree_layers[1].byte_length);
+    assert_eq!(subtree_layers[0].value_count, subtree_layers[1].value_count);
+    assert!(summary.to_string().contains(" ref:sized_merk("));
+}
+
+#[test]
+fn test_flags_codec_used_by_inserts_deletes_and_batches() {
+    use grovedb_costs::storage_cost::removal::{
+        Identifier, StorageRemovedBytes::SectionedStorageRemoval,
+    };
+
+    let tmp_dir = TempDir::new().unwrap();
+    let codec = Arc::new(StorageFlagsCodec::new(RemovalStrategy::OldestFirst));
+    let db = GroveDb::open_with_flags_codec(tmp_dir.path(), codec).unwrap();
+    db.insert(EMPTY_PATH, TEST_LEAF, Element::empty_tree(), None, None)
+        .unwrap()
+        .expect("successful root tree leaf insert");
+
+    // inserts are encoded by the codec
+    let result = db
+        .insert(
+            [TEST_LEAF].as_ref(),
+            b"key",
+            Element::new_item_with_flags(b"value".to_vec(), Some(vec![9])),
+            None,
+            None,
+        )
+        .unwrap();
+    assert!(matches!(result, Err(Error::InvalidElementFlags(_))));
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key",
+        Element::new_item_with_flags(
+            b"value".to_vec(),
+            Some(StorageFlags::new_single_epoch(0, None).serialize()),
+        ),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful item insert");
+
+    // batch updates merge flags in the epoch of the batch
+    db.apply_batch(
+        vec![batch::GroveDbOp::insert_op(
+            vec![TEST_LEAF.to_vec()],
+            b"key".to_vec(),
+            Element::new_item_with_flags(
+                b"a much bigger value".to_vec(),
+                Some(StorageFlags::new_single_epoch(2, None).serialize()),
+            ),
+        )],
+        Some(batch::BatchApplyOptions {
+            epoch: 2,
+            ..Default::default()
+        }),
+        None,
+    )
+    .unwrap()
+    .expect("successful batch apply");
+    let element = db
+        .get([TEST_LEAF].as_ref(), b"key", None)
+        .unwrap()
+        .expect("successful get");
+    let storage_flags =
+        StorageFlags::deserialize(element.get_flags().as_ref().expect("expected flags"))
+            .expect("expected storage flags");
+    assert_eq!(storage_flags.base_epoch(), 0);
+    let epoch_2_bytes = storage_flags.bytes_per_epoch()[&2];
+
+    // deletes refund per epoch
+    let cost = db.delete([TEST_LEAF].as_ref(), b"key", None, None).cost;
+    let SectionedStorageRemoval(removed_bytes) = cost.storage_cost.removed_bytes else {
+        panic!("expected removal sectioned by epoch");
+    };
+    assert_eq!(
+        removed_bytes[&Identifier::default()].get(2),
+        Some(&epoch_2_bytes)
+    );
+}
+
+#[test]
+fn test_open_with_verification_reports_corruption() {
+    let tmp_dir = TempDir::new().unwrap();
+    let verification = OpenVerification {
+        paranoid_checks: true,
+        check_root_hash: true,
+    };
+    {
+        let mut db = GroveDb::open(tmp_dir.path()).unwrap();
+        add_test_leaves(&mut db);
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"key",
+            Element::new_item(b"value".to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful item insert");
+    }
+    drop(
+        GroveDb::open_with_verification(tmp_dir.path(), Arc::new(RawFlagsCodec), verification)
+            .expect("healthy database should open"),
+    );
+
+    // a CURRENT file without a trailing newline is reported as corrupted
+    std::fs::write(tmp_dir.path().join("CURRENT"), b"MANIFEST").unwrap();
+    assert!(matches!(
+        GroveDb::open_with_verification(tmp_dir.path(), Arc::new(RawFlagsCodec), verification),
+        Err(Error::StorageCorruption { .. })
+    ));
+}
+
+#[test]
+fn test_open_with_verification_reports_root_hash_mismatch() {
+    let tmp_dir = TempDir::new().unwrap();
+    let verification = OpenVerification {
+        paranoid_checks: false,
+        check_root_hash: true,
+    };
+    {
+        let mut db = GroveDb::open(tmp_dir.path()).unwrap();
+        add_test_leaves(&mut db);
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"key",
+            Element::new_item(b"value".to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful item insert");
+
+        // the item is replaced without propagating the new root hash, so the
+        // one of the root tree is stale
+        let batch = StorageBatch::new();
+        let mut merk = db
+            .open_non_transactional_merk_at_path([TEST_LEAF].as_ref().into(), Some(&batch))
+            .unwrap()
+            .expect("should open tree");
+        Element::new_item(b"tampered".to_vec())
+            .insert(&mut merk, b"key", None)
+            .unwrap()
+            .expect("successful item insert");
+        drop(merk);
+        db.db
+            .commit_multi_context_batch(batch, None)
+            .unwrap()
+            .expect("successful commit");
+    }
+    assert!(matches!(
+        GroveDb::open_with_verification(tmp_dir.path(), Arc::new(RawFlagsCodec), verification),
+        Err(Error::StorageCorruption { details }) if details.contains("root hash mismatch")
+    ));
+}
+
+#[test]
+fn test_open_with_prefix_salt() {
+    let salted_dirs = [TempDir::new().unwrap(), TempDir::new().unwrap()];
+    let mut root_hashes = Vec::new();
+    for (salt, tmp_dir) in [[1; 32], [2; 32]].into_iter().zip(salted_dirs.iter()) {
+        let mut db = GroveDb::open_with_prefix_salt(tmp_dir.path(), salt).unwrap();
+        add_test_leaves(&mut db);
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"key",
+            Element::new_item(b"value".to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful item insert");
+        root_hashes.push(db.root_hash(None).unwrap().unwrap());
+    }
+    // Salts change the storage keys, not the state
+    assert_eq!(root_hashes[0], root_hashes[1]);
+
+    let db = GroveDb::open(salted_dirs[0].path()).unwrap();
+    assert_eq!(
+        db.get([TEST_LEAF].as_ref(), b"key", None)
+            .unwrap()
+            .expect("successful get"),
+        Element::new_item(b"value".to_vec())
+    );
+    drop(db);
+    assert!(GroveDb::open_with_prefix_salt(salted_dirs[0].path(), [2; 32]).is_err());
+}
+
+#[test]
+fn test_hierarchical_prefix_scheme() {
+    let populate = |db: &GroveDb| {
+        let mut cost = OperationCost::default();
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            b"inner",
+            Element::empty_tree(),
+            None,
+            None,
+        )
+        .unwrap_add_cost(&mut cost)
+        .expect("successful subtree insert");
+        db.insert(
+            [TEST_LEAF, b"inner"].as_ref(),
+            b"key",
+            Element::new_item(b"value".to_vec()),
+            None,
+            None,
+        )
+        .unwrap_add_cost(&mut cost)
+        .expect("successful item insert");
+        cost
+    };
+
+    let tmp_dir = TempDir::new().unwrap();
+    let mut db = GroveDb::open_with_storage_builder(
+        tmp_dir.path(),
+        RocksDbStorageBuilder::new().prefix_scheme(PrefixScheme::Hierarchical),
+        Arc::new(RawFlagsCodec),
+        OpenVerification::default(),
+    )
+    .unwrap();
+    add_test_leaves(&mut db);
+    let cost = populate(&db);
+    let hashed_db = make_test_grovedb();
+    let hashed_cost = populate(&hashed_db);
+
+    // Prefixes are paid for as hashes whatever their length
+    assert_eq!(cost.storage_cost, hashed_cost.storage_cost);
+    // The prefix scheme changes the storage keys, not the state
+    assert_eq!(
+        db.root_hash(None).unwrap().unwrap(),
+        hashed_db.root_hash(None).unwrap().unwrap()
+    );
+    let leaf_usage = db.hierarchy_usage([TEST_LEAF].as_ref()).unwrap();
+    let inner_usage = db.hierarchy_usage([TEST_LEAF, b"inner"].as_ref()).unwrap();
+    assert!(inner_usage.keys > 0);
+    assert!(leaf_usage.keys > inner_usage.keys);
+    assert!(hashed_db.hierarchy_usage([TEST_LEAF].as_ref()).is_err());
+}
+
+#[test]
+fn test_clone_compacted() {
+    let db = make_test_grovedb();
+    for i in 0u8..100 {
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            &[i],
+            Element::new_item(vec![i; 64]),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful item insert");
+    }
+    for i in 0u8..50 {
+        db.delete([TEST_LEAF].as_ref(), &[i], None, None)
+            .unwrap()
+            .expect("successful delete");
+    }
+
+    let clone_dir = TempDir::new().unwrap();
+    let clone_path = clone_dir.path().join("clone");
+    let root_hash = db
+        .clone_compacted(&clone_path)
+        .expect("cannot clone database");
+    assert_eq!(
+        root_hash,
+        db.root_hash(None).unwrap().expect("cannot get root hash")
+    );
+
+    let clone = GroveDb::open(&clone_path).unwrap();
+    assert_eq!(
+        clone
+            .root_hash(None)
+            .unwrap()
+            .expect("cannot get root hash"),
+        root_hash
+    );
+    assert_eq!(
+        clone
+            .get([TEST_LEAF].as_ref(), &[99], None)
+            .unwrap()
+            .expect("cannot get item"),
+        Element::new_item(vec![99; 64])
+    );
+    assert!(clone
+        .get([TEST_LEAF].as_ref(), &[0], None)
+        .unwrap()
+        .is_err());
+}
+
+#[test]
+fn transaction_should_be_aborted_when_rollback_is_called() {
+    let item_key = b"key3";
+
+    let db = make_test_grovedb();
+    let transaction = db.start_transaction().unwrap();
+
+    let element1 = Element::new_item(b"ayy".to_vec());
+
+    let result = db
+        .insert(
+            [TEST_LEAF].as_ref(),
+            item_key,
+            element1,
+            None,
+            Some(&transaction),
+        )
+        .unwrap();
+
+    assert!(matches!(result, Ok(())));
+
+    db.rollback_transaction(&transaction).unwrap();
+
+    let result = db
+        .get([TEST_LEAF].as_ref(), item_key, Some(&transaction))
+        .unwrap();
+    assert!(matches!(result, Err(Error::PathKeyNotFound(_))));
+}
+
+#[test]
+fn transaction_should_be_aborted() {
+    let db = make_test_grovedb();
+    let transaction = db.start_transaction().unwrap();
+
+    let item_key = b"key3";
+    let element = Element::new_item(b"ayy".to_vec());
+
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        item_key,
+        element,
+        None,
+        Some(&transaction),
+    )
+    .unwrap()
+    .unwrap();
+
+    drop(transaction);
+
+    // Transactional data shouldn't be committed to the main database
+    let result = db.get([TEST_LEAF].as_ref(), item_key, None).unwrap();
+    assert!(matches!(result, Err(Error::PathKeyNotFound(_))));
+}
+
+#[test]
+fn test_subtree_pairs_iterator() {
+    let db = make_test_grovedb();
+    let element = Element::new_item(b"ayy".to_vec());
+    let element2 = Element::new_item(b"lmao".to_vec());
+
+    // Insert some nested subtrees
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"subtree1",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful subtree 1 insert");
+    db.insert(
+        [TEST_LEAF, b"subtree1"].as_ref(),
+        b"subtree11",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful subtree 2 insert");
+    // Insert an element into subtree
+    db.insert(
+        [TEST_LEAF, b"subtree1", b"subtree11"].as_ref(),
+        b"key1",
+        element.clone(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful value insert");
+    assert_eq!(
+        db.get(
+            [TEST_LEAF, b"subtree1", b"subtree11"].as_ref(),
+            b"key1",
+            None
+        )
+        .unwrap()
+        .expect("successful get 1"),
+        element
+    );
+    db.insert(
+        [TEST_LEAF, b"subtree1", b"subtree11"].as_ref(),
+        b"key0",
+        element.clone(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful value insert");
+    db.insert(
+        [TEST_LEAF, b"subtree1"].as_ref(),
+        b"subtree12",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful subtree 3 insert");
+    db.insert(
+        [TEST_LEAF, b"subtree1"].as_ref(),
+        b"key1",
+        element.clone(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful value insert");
+    db.insert(
+        [TEST_LEAF, b"subtree1"].as_ref(),
+        b"key2",
+        element2.clone(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful value insert");
+
+    // Iterate over subtree1 to see if keys of other subtrees messed up
+    // let mut iter = db
+    //     .elements_iterator([TEST_LEAF, b"subtree1"].as_ref(), None)
+    //     .expect("cannot create iterator");
+    let storage_context = db
+        .grove_db
+        .db
+        .get_storage_context([TEST_LEAF, b"subtree1"].as_ref().into(), None)
+        .unwrap();
+    let mut iter = Element::iterator(storage_context.raw_iter()).unwrap();
+    assert_eq!(
+        iter.next_element().unwrap().unwrap(),
+        Some((b"key1".to_vec(), element))
+    );
+    assert_eq!(
+        iter.next_element().unwrap().unwrap(),
+        Some((b"key2".to_vec(), element2))
+    );
+    let subtree_element = iter.next_element().unwrap().unwrap().unwrap();
+    assert_eq!(subtree_element.0, b"subtree11".to_vec());
+    assert!(matches!(subtree_element.1, Element::Tree(..)));
+    let subtree_element = iter.next_element().unwrap().unwrap().unwrap();
+    assert_eq!(subtree_element.0, b"subtree12".to_vec());
+    assert!(matches!(subtree_element.1, Element::Tree(..)));
+    assert!(matches!(iter.next_element().unwrap(), Ok(None)));
+}
+
+#[test]
+fn test_find_subtrees() {
+    let element = Element::new_item(b"ayy".to_vec());
+    let db = make_test_grovedb();
+    // Insert some nested subtrees
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key1",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful subtree 1 insert");
+    db.insert(
+        [TEST_LEAF, b"key1"].as_ref(),
+        b"key2",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful subtree 2 insert");
+    // Insert an element into subtree
+    db.insert(
+        [TEST_LEAF, b"key1", b"key2"].as_ref(),
+        b"key3",
+        element,
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful value insert");
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key4",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful subtree 3 insert");
+    let subtrees = db
+        .find_subtrees(&[TEST_LEAF].as_ref().into(), None)
+        .unwrap()
+        .expect("cannot get subtrees");
+    assert_eq!(
+        vec![
+            vec![TEST_LEAF],
+            vec![TEST_LEAF, b"key1"],
+            vec![TEST_LEAF, b"key4"],
+            vec![TEST_LEAF, b"key1", b"key2"],
+        ],
+        subtrees
+    );
+}
+
+#[test]
+fn test_root_subtree_has_root_key() {
+    let db = make_test_grovedb();
+    let storage = db.db.get_storage_context(EMPTY_PATH, None).unwrap();
+    let root_merk = Merk::open_base(storage, false)
+        .unwrap()
+        .expect("expected to get root merk");
+    let (_, root_key, _) = root_merk
+        .root_hash_key_and_sum()
+        .unwrap()
+        .expect("expected to get root hash, key and sum");
+    assert!(root_key.is_some())
+}
+
+#[test]
+fn test_get_subtree() {
+    let db = make_test_grovedb();
+    let element = Element::new_item(b"ayy".to_vec());
+
+    // Returns error is subtree is not valid
+    {
+        let subtree = db.get([TEST_LEAF].as_ref(), b"invalid_tree", None).unwrap();
+        assert!(subtree.is_err());
+
+        // Doesn't return an error for subtree that exists but empty
+        let subtree = db.get(EMPTY_PATH, TEST_LEAF, None).unwrap();
+        assert!(subtree.is_ok());
+    }
+
+    // Insert some nested subtrees
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key1",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful subtree 1 insert");
+
+    let key1_tree = db
+        .get(EMPTY_PATH, TEST_LEAF, None)
+        .unwrap()
+        .expect("expected to get a root tree");
+
+    assert!(
+        matches!(key1_tree, Element::Tree(Some(_), _)),
+        "{}",
+        format!(
+            "expected tree with root key, got {:?}",
+            if let Element::Tree(tree, ..) = key1_tree {
+                format!("{:?}", tree)
+            } else {
+                "not a tree".to_string()
+            }
+        )
+    );
+
+    db.insert(
+        [TEST_LEAF, b"key1"].as_ref(),
+        b"key2",
+        Element::empty_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful subtree 2 insert");
+
+    // Insert an element into subtree
+    db.insert(
+        [TEST_LEAF, b"key1", b"key2"].as_ref(),
+        b"key3",
+        element.clone(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful value insert");
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key4",
+        Element::empty_tree(),
+        None,
+        None,
     )
     .unwrap()
     .expect("successful subtree 3 insert");
+
+    // Retrieve subtree instance
+    // Check if it returns the same instance that was inserted
+    {
+        let subtree_storage = db
+            .grove_db
+            .db
+            .get_storage_context([TEST_LEAF, b"key1", b"key2"].as_ref().into(), None)
+            .unwrap();
+        let subtree =
+            Merk::open_layered_with_root_key(subtree_storage, Some(b"key3".to_vec()), false)
+                .unwrap()
+                .expect("cannot open merk");
+        let result_element = Element::get(&subtree, b"key3", true).unwrap().unwrap();
+        assert_eq!(result_element, Element::new_item(b"ayy".to_vec()));
+    }
+    // Insert a new tree with transaction
+    let transaction = db.start_transaction().unwrap();
+
     db.insert(
-        [TEST_LEAF, b"subtree1"].as_ref(),
-        b"key1",
-        element.clone(),
-        None,
+        [TEST_LEAF, b"key1"].as_ref(),
+        b"innertree",
+        Element::empty_tree(),
         None,
+        Some(&transaction),
     )
     .unwrap()
-    .expect("successful value insert");
+    .expect("successful subtree insert");
+
     db.insert(
-        [TEST_LEAF, b"subtree1"].as_ref(),
-        b"key2",
-        element2.clone(),
-        None,
+        [TEST_LEAF, b"key1", b"innertree"].as_ref(),
+        b"key4",
+        element,
         None,
+        Some(&transaction),
     )
     .unwrap()
     .expect("successful value insert");
 
-    // Iterate over subtree1 to see if keys of other subtrees messed up
-    // let mut iter = db
-    //     .elements_iterator([TEST_LEAF, b"subtree1"].as_ref(), None)
-    //     .expect("cannot create iterator");
-    let storage_context = db
+    // Retrieve subtree instance with transaction
+    let subtree_storage = db
         .grove_db
         .db
-        .get_storage_context([TEST_LEAF, b"subtree1"].as_ref().into(), None)
+        .get_transactional_storage_context(
+            [TEST_LEAF, b"key1", b"innertree"].as_ref().into(),
+            None,
+            &transaction,
+        )
         .unwrap();
-    let mut iter = Element::iterator(storage_context.raw_iter()).unwrap();
-    assert_eq!(
-        iter.next_element().unwrap().unwrap(),
-        Some((b"key1".to_vec(), element))
-    );
-    assert_eq!(
-        iter.next_element().unwrap().unwrap(),
-        Some((b"key2".to_vec(), element2))
-    );
-    let subtree_element = iter.next_element().unwrap().unwrap().unwrap();
-    assert_eq!(subtree_element.0, b"subtree11".to_vec());
-    assert!(matches!(subtree_element.1, Element::Tree(..)));
-    let subtree_element = iter.next_element().unwrap().unwrap().unwrap();
-    assert_eq!(subtree_element.0, b"subtree12".to_vec());
-    assert!(matches!(subtree_element.1, Element::Tree(..)));
-    assert!(matches!(iter.next_element().unwrap(), Ok(None)));
+    let subtree = Merk::open_layered_with_root_key(subtree_storage, Some(b"key4".to_vec()), false)
+        .unwrap()
+        .expect("cannot open merk");
+    let result_element = Element::get(&subtree, b"key4", true).unwrap().unwrap();
+    assert_eq!(result_element, Element::new_item(b"ayy".to_vec()));
+
+    // Should be able to retrieve instances created before transaction
+    let subtree_storage = db
+        .grove_db
+        .db
+        .get_storage_context([TEST_LEAF, b"key1", b"key2"].as_ref().into(), None)
+        .unwrap();
+    let subtree = Merk::open_layered_with_root_key(subtree_storage, Some(b"key3".to_vec()), false)
+        .unwrap()
+        .expect("cannot open merk");
+    let result_element = Element::get(&subtree, b"key3", true).unwrap().unwrap();
+    assert_eq!(result_element, Element::new_item(b"ayy".to_vec()));
 }
 
 #[test]
-fn test_find_subtrees() {
-    let element = Element::new_item(b"ayy".to_vec());
+fn test_get_full_query() {
     let db = make_test_grovedb();
-    // Insert some nested subtrees
+
+    // Insert a couple of subtrees first
     db.insert(
         [TEST_LEAF].as_ref(),
         b"key1",
@@ -2317,79 +6089,99 @@ fn test_find_subtrees() {
         None,
     )
     .unwrap()
-    .expect("successful subtree 1 insert");
+    .expect("successful subtree insert");
     db.insert(
-        [TEST_LEAF, b"key1"].as_ref(),
+        [TEST_LEAF].as_ref(),
         b"key2",
         Element::empty_tree(),
         None,
         None,
     )
     .unwrap()
-    .expect("successful subtree 2 insert");
-    // Insert an element into subtree
+    .expect("successful subtree insert");
+    // Insert some elements into subtree
     db.insert(
-        [TEST_LEAF, b"key1", b"key2"].as_ref(),
+        [TEST_LEAF, b"key1"].as_ref(),
         b"key3",
-        element,
+        Element::new_item(b"ayya".to_vec()),
         None,
         None,
     )
     .unwrap()
     .expect("successful value insert");
     db.insert(
-        [TEST_LEAF].as_ref(),
+        [TEST_LEAF, b"key1"].as_ref(),
         b"key4",
-        Element::empty_tree(),
+        Element::new_item(b"ayyb".to_vec()),
         None,
         None,
     )
     .unwrap()
-    .expect("successful subtree 3 insert");
-    let subtrees = db
-        .find_subtrees(&[TEST_LEAF].as_ref().into(), None)
-        .unwrap()
-        .expect("cannot get subtrees");
+    .expect("successful value insert");
+    db.insert(
+        [TEST_LEAF, b"key1"].as_ref(),
+        b"key5",
+        Element::new_item(b"ayyc".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful value insert");
+    db.insert(
+        [TEST_LEAF, b"key2"].as_ref(),
+        b"key6",
+        Element::new_item(b"ayyd".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful value insert");
+
+    //          Test_Leaf
+    // ___________________________
+    //         /        \
+    //     key1           key2
+    // ___________________________
+    //      |              |
+    //     key4          key6
+    //     / \
+    //   key3 key5
+    //
+
+    let path1 = vec![TEST_LEAF.to_vec(), b"key1".to_vec()];
+    let path2 = vec![TEST_LEAF.to_vec(), b"key2".to_vec()];
+    let mut query1 = Query::new();
+    let mut query2 = Query::new();
+    query1.insert_range_inclusive(b"key3".to_vec()..=b"key4".to_vec());
+    query2.insert_key(b"key6".to_vec());
+
+    let path_query1 = PathQuery::new_unsized(path1, query1);
+    // should get back key3, key4
+    let path_query2 = PathQuery::new_unsized(path2, query2);
+    // should get back key6
+
     assert_eq!(
+        db.query_many_raw(
+            &[&path_query1, &path_query2],
+            true,
+            QueryKeyElementPairResultType,
+            None
+        )
+        .unwrap()
+        .expect("expected successful get_query")
+        .to_key_elements(),
         vec![
-            vec![TEST_LEAF],
-            vec![TEST_LEAF, b"key1"],
-            vec![TEST_LEAF, b"key4"],
-            vec![TEST_LEAF, b"key1", b"key2"],
-        ],
-        subtrees
+            (b"key3".to_vec(), Element::new_item(b"ayya".to_vec())),
+            (b"key4".to_vec(), Element::new_item(b"ayyb".to_vec())),
+            (b"key6".to_vec(), Element::new_item(b"ayyd".to_vec())),
+        ]
     );
 }
 
 #[test]
-fn test_root_subtree_has_root_key() {
-    let db = make_test_grovedb();
-    let storage = db.db.get_storage_context(EMPTY_PATH, None).unwrap();
-    let root_merk = Merk::open_base(storage, false)
-        .unwrap()
-        .expect("expected to get root merk");
-    let (_, root_key, _) = root_merk
-        .root_hash_key_and_sum()
-        .unwrap()
-        .expect("expected to get root hash, key and sum");
-    assert!(root_key.is_some())
-}
-
-#[test]
-fn test_get_subtree() {
-    let db = make_test_grovedb();
+fn test_aux_uses_separate_cf() {
     let element = Element::new_item(b"ayy".to_vec());
-
-    // Returns error is subtree is not valid
-    {
-        let subtree = db.get([TEST_LEAF].as_ref(), b"invalid_tree", None).unwrap();
-        assert!(subtree.is_err());
-
-        // Doesn't return an error for subtree that exists but empty
-        let subtree = db.get(EMPTY_PATH, TEST_LEAF, None).unwrap();
-        assert!(subtree.is_ok());
-    }
-
+    let db = make_test_grovedb();
     // Insert some nested subtrees
     db.insert(
         [TEST_LEAF].as_ref(),
@@ -2400,25 +6192,6 @@ fn test_get_subtree() {
     )
     .unwrap()
     .expect("successful subtree 1 insert");
-
-    let key1_tree = db
-        .get(EMPTY_PATH, TEST_LEAF, None)
-        .unwrap()
-        .expect("expected to get a root tree");
-
-    assert!(
-        matches!(key1_tree, Element::Tree(Some(_), _)),
-        "{}",
-        format!(
-            "expected tree with root key, got {:?}",
-            if let Element::Tree(tree, ..) = key1_tree {
-                format!("{:?}", tree)
-            } else {
-                "not a tree".to_string()
-            }
-        )
-    );
-
     db.insert(
         [TEST_LEAF, b"key1"].as_ref(),
         b"key2",
@@ -2428,364 +6201,827 @@ fn test_get_subtree() {
     )
     .unwrap()
     .expect("successful subtree 2 insert");
+    // Insert an element into subtree
+    db.insert(
+        [TEST_LEAF, b"key1", b"key2"].as_ref(),
+        b"key3",
+        element.clone(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful value insert");
+
+    db.put_aux(b"key1", b"a", None, None)
+        .unwrap()
+        .expect("cannot put aux");
+    db.put_aux(b"key2", b"b", None, None)
+        .unwrap()
+        .expect("cannot put aux");
+    db.put_aux(b"key3", b"c", None, None)
+        .unwrap()
+        .expect("cannot put aux");
+    db.delete_aux(b"key3", None, None)
+        .unwrap()
+        .expect("cannot delete from aux");
+
+    assert_eq!(
+        db.get([TEST_LEAF, b"key1", b"key2"].as_ref(), b"key3", None)
+            .unwrap()
+            .expect("cannot get element"),
+        element
+    );
+    assert_eq!(
+        db.get_aux(b"key1", None)
+            .unwrap()
+            .expect("cannot get from aux"),
+        Some(b"a".to_vec())
+    );
+    assert_eq!(
+        db.get_aux(b"key2", None)
+            .unwrap()
+            .expect("cannot get from aux"),
+        Some(b"b".to_vec())
+    );
+    assert_eq!(
+        db.get_aux(b"key3", None)
+            .unwrap()
+            .expect("cannot get from aux"),
+        None
+    );
+    assert_eq!(
+        db.get_aux(b"key4", None)
+            .unwrap()
+            .expect("cannot get from aux"),
+        None
+    );
+}
 
-    // Insert an element into subtree
-    db.insert(
-        [TEST_LEAF, b"key1", b"key2"].as_ref(),
-        b"key3",
-        element.clone(),
-        None,
-        None,
-    )
-    .unwrap()
-    .expect("successful value insert");
+#[test]
+fn test_aux_with_transaction() {
+    let element = Element::new_item(b"ayy".to_vec());
+    let aux_value = b"ayylmao".to_vec();
+    let key = b"key".to_vec();
+    let db = make_test_grovedb();
+    let transaction = db.start_transaction().unwrap();
+
+    // Insert a regular data with aux data in the same transaction
     db.insert(
         [TEST_LEAF].as_ref(),
-        b"key4",
-        Element::empty_tree(),
-        None,
+        &key,
+        element,
         None,
+        Some(&transaction),
     )
     .unwrap()
-    .expect("successful subtree 3 insert");
+    .expect("unable to insert");
+    db.put_aux(&key, &aux_value, None, Some(&transaction))
+        .unwrap()
+        .expect("unable to insert aux value");
+    assert_eq!(
+        db.get_aux(&key, Some(&transaction))
+            .unwrap()
+            .expect("unable to get aux value"),
+        Some(aux_value.clone())
+    );
+    // Cannot reach the data outside of transaction
+    assert_eq!(
+        db.get_aux(&key, None)
+            .unwrap()
+            .expect("unable to get aux value"),
+        None
+    );
+    // And should be able to get data when committed
+    db.commit_transaction(transaction)
+        .unwrap()
+        .expect("unable to commit transaction");
+    assert_eq!(
+        db.get_aux(&key, None)
+            .unwrap()
+            .expect("unable to get committed aux value"),
+        Some(aux_value)
+    );
+}
 
-    // Retrieve subtree instance
-    // Check if it returns the same instance that was inserted
-    {
-        let subtree_storage = db
-            .grove_db
-            .db
-            .get_storage_context([TEST_LEAF, b"key1", b"key2"].as_ref().into(), None)
-            .unwrap();
-        let subtree =
-            Merk::open_layered_with_root_key(subtree_storage, Some(b"key3".to_vec()), false)
-                .unwrap()
-                .expect("cannot open merk");
-        let result_element = Element::get(&subtree, b"key3", true).unwrap().unwrap();
-        assert_eq!(result_element, Element::new_item(b"ayy".to_vec()));
-    }
-    // Insert a new tree with transaction
-    let transaction = db.start_transaction();
+#[test]
+fn test_savepoints() {
+    let db = make_test_grovedb();
+    let transaction = db.start_transaction().unwrap();
 
     db.insert(
-        [TEST_LEAF, b"key1"].as_ref(),
-        b"innertree",
-        Element::empty_tree(),
+        [TEST_LEAF].as_ref(),
+        b"kept",
+        Element::new_item(b"a".to_vec()),
         None,
         Some(&transaction),
     )
     .unwrap()
-    .expect("successful subtree insert");
+    .expect("unable to insert");
 
+    db.set_savepoint(&transaction);
     db.insert(
-        [TEST_LEAF, b"key1", b"innertree"].as_ref(),
-        b"key4",
-        element,
+        [TEST_LEAF].as_ref(),
+        b"discarded",
+        Element::new_item(b"b".to_vec()),
         None,
         Some(&transaction),
     )
     .unwrap()
-    .expect("successful value insert");
+    .expect("unable to insert");
+    // Savepoints are kept with their transaction when it is moved
+    let transaction = Box::new(transaction);
+    db.rollback_to_savepoint(&transaction)
+        .expect("unable to roll back to savepoint");
 
-    // Retrieve subtree instance with transaction
-    let subtree_storage = db
-        .grove_db
-        .db
-        .get_transactional_storage_context(
-            [TEST_LEAF, b"key1", b"innertree"].as_ref().into(),
+    // Only changes made after the savepoint are undone
+    assert_eq!(
+        db.get([TEST_LEAF].as_ref(), b"kept", Some(&transaction))
+            .unwrap()
+            .expect("expected element"),
+        Element::new_item(b"a".to_vec())
+    );
+    assert!(matches!(
+        db.get([TEST_LEAF].as_ref(), b"discarded", Some(&transaction))
+            .unwrap(),
+        Err(Error::PathKeyNotFound(_))
+    ));
+
+    // The savepoint was consumed by the rollback
+    assert!(db.rollback_to_savepoint(&transaction).is_err());
+
+    db.commit_transaction(*transaction)
+        .unwrap()
+        .expect("unable to commit transaction");
+    assert!(db.get([TEST_LEAF].as_ref(), b"kept", None).unwrap().is_ok());
+}
+
+#[test]
+fn test_nested_transactions() {
+    let db = make_test_grovedb();
+    let transaction = db.start_transaction().unwrap();
+    let insert = |key: &[u8], tx: &Transaction| {
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            key,
+            Element::new_item(key.to_vec()),
             None,
-            &transaction,
+            Some(tx),
         )
-        .unwrap();
-    let subtree = Merk::open_layered_with_root_key(subtree_storage, Some(b"key4".to_vec()), false)
         .unwrap()
-        .expect("cannot open merk");
-    let result_element = Element::get(&subtree, b"key4", true).unwrap().unwrap();
-    assert_eq!(result_element, Element::new_item(b"ayy".to_vec()));
+        .expect("unable to insert");
+    };
+    let exists =
+        |key: &[u8], tx: &Transaction| db.get([TEST_LEAF].as_ref(), key, Some(tx)).unwrap().is_ok();
+
+    insert(b"outer", &transaction);
+
+    let child = db.start_nested_transaction(&transaction);
+    insert(b"committed", child.transaction());
+    let grandchild = child.start_nested_transaction();
+    insert(b"rolled back", grandchild.transaction());
+    grandchild.rollback().expect("unable to roll back");
+    assert!(!exists(b"rolled back", &transaction));
+    child.commit().expect("unable to commit");
+    assert!(exists(b"committed", &transaction));
+
+    // Rolling back a child also discards the changes of its committed children
+    let child = db.start_nested_transaction(&transaction);
+    insert(b"child", child.transaction());
+    let grandchild = child.start_nested_transaction();
+    insert(b"grandchild", grandchild.transaction());
+    grandchild.commit().expect("unable to commit");
+    child.rollback().expect("unable to roll back");
+    assert!(!exists(b"child", &transaction));
+    assert!(!exists(b"grandchild", &transaction));
+
+    // Dropping a child without committing it rolls it back
+    {
+        let child = db.start_nested_transaction(&transaction);
+        insert(b"dropped", child.transaction());
+    }
+    assert!(!exists(b"dropped", &transaction));
 
-    // Should be able to retrieve instances created before transaction
-    let subtree_storage = db
-        .grove_db
-        .db
-        .get_storage_context([TEST_LEAF, b"key1", b"key2"].as_ref().into(), None)
-        .unwrap();
-    let subtree = Merk::open_layered_with_root_key(subtree_storage, Some(b"key3".to_vec()), false)
+    assert!(exists(b"outer", &transaction));
+    assert!(exists(b"committed", &transaction));
+    db.commit_transaction(transaction)
         .unwrap()
-        .expect("cannot open merk");
-    let result_element = Element::get(&subtree, b"key3", true).unwrap().unwrap();
-    assert_eq!(result_element, Element::new_item(b"ayy".to_vec()));
+        .expect("unable to commit transaction");
+    assert!(exists(b"committed", &db.start_transaction().unwrap()));
 }
 
 #[test]
-fn test_get_full_query() {
+fn test_nested_transactions_release_their_savepoints() {
     let db = make_test_grovedb();
+    let transaction = db.start_transaction().unwrap();
+    let exists = |key: &[u8]| {
+        db.get([TEST_LEAF].as_ref(), key, Some(&transaction))
+            .unwrap()
+            .is_ok()
+    };
+    let insert_op = |key: &[u8]| {
+        batch::GroveDbOp::insert_op(
+            vec![TEST_LEAF.to_vec()],
+            key.to_vec(),
+            Element::new_item(key.to_vec()),
+        )
+    };
 
-    // Insert a couple of subtrees first
+    // A committed nested transaction doesn't take the place of the savepoint
+    // of the caller
+    db.set_savepoint(&transaction);
+    let child = db.start_nested_transaction(&transaction);
+    db.apply_batch(vec![insert_op(b"child")], None, Some(child.transaction()))
+        .unwrap()
+        .expect("unable to apply batch");
+    child.commit().expect("unable to commit");
+    assert!(exists(b"child"));
+    db.rollback_to_savepoint(&transaction)
+        .expect("unable to roll back");
+    assert!(!exists(b"child"));
+    assert!(db.rollback_to_savepoint(&transaction).is_err());
+
+    // Batches applied within a child are rolled back with it
+    let child = db.start_nested_transaction(&transaction);
+    let results = db
+        .apply_batches(
+            vec![
+                vec![insert_op(b"first")],
+                vec![batch::GroveDbOp::insert_op(
+                    vec![b"missing".to_vec()],
+                    b"key".to_vec(),
+                    Element::new_item(b"value".to_vec()),
+                )],
+                vec![insert_op(b"last")],
+            ],
+            None,
+            child.transaction(),
+        )
+        .unwrap()
+        .expect("unable to apply batches");
+    assert!(results[1].value.is_err());
+    assert!(exists(b"first"));
+    assert!(exists(b"last"));
+    child.rollback().expect("unable to roll back");
+    assert!(!exists(b"first"));
+    assert!(!exists(b"last"));
+    assert!(db.rollback_to_savepoint(&transaction).is_err());
+}
+
+#[test]
+fn test_read_snapshot() {
+    let db = make_test_grovedb();
     db.insert(
         [TEST_LEAF].as_ref(),
-        b"key1",
-        Element::empty_tree(),
+        b"a",
+        Element::new_item(b"before".to_vec()),
         None,
         None,
     )
     .unwrap()
-    .expect("successful subtree insert");
+    .expect("unable to insert");
+
+    let snapshot = db.snapshot().unwrap();
+
     db.insert(
         [TEST_LEAF].as_ref(),
-        b"key2",
-        Element::empty_tree(),
+        b"a",
+        Element::new_item(b"after".to_vec()),
         None,
         None,
     )
     .unwrap()
-    .expect("successful subtree insert");
-    // Insert some elements into subtree
+    .expect("unable to insert");
     db.insert(
-        [TEST_LEAF, b"key1"].as_ref(),
-        b"key3",
-        Element::new_item(b"ayya".to_vec()),
+        [TEST_LEAF].as_ref(),
+        b"b",
+        Element::new_item(b"after".to_vec()),
         None,
         None,
     )
     .unwrap()
-    .expect("successful value insert");
+    .expect("unable to insert");
+
+    // The snapshot doesn't see writes made after it was taken
+    assert_eq!(
+        db.get([TEST_LEAF].as_ref(), b"a", Some(snapshot.transaction()))
+            .unwrap()
+            .expect("expected element"),
+        Element::new_item(b"before".to_vec())
+    );
+    let mut query = Query::new();
+    query.insert_all();
+    let path_query = PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], query);
+    let (values, _) = db
+        .query_item_value(&path_query, true, Some(snapshot.transaction()))
+        .unwrap()
+        .expect("expected successful query");
+    assert_eq!(values, vec![b"before".to_vec()]);
+
+    // While reads without the snapshot do
+    let (values, _) = db
+        .query_item_value(&path_query, true, None)
+        .unwrap()
+        .expect("expected successful query");
+    assert_eq!(values, vec![b"after".to_vec(), b"after".to_vec()]);
+}
+
+#[test]
+fn test_prove_query_in_snapshot() {
+    let db = make_test_grovedb();
     db.insert(
-        [TEST_LEAF, b"key1"].as_ref(),
-        b"key4",
-        Element::new_item(b"ayyb".to_vec()),
+        [TEST_LEAF].as_ref(),
+        b"a",
+        Element::new_item(b"before".to_vec()),
         None,
         None,
     )
     .unwrap()
-    .expect("successful value insert");
+    .expect("unable to insert");
+
+    let snapshot = db.snapshot().unwrap();
+    let snapshot_root_hash = db
+        .root_hash(Some(snapshot.transaction()))
+        .unwrap()
+        .expect("expected root hash");
+
     db.insert(
-        [TEST_LEAF, b"key1"].as_ref(),
-        b"key5",
-        Element::new_item(b"ayyc".to_vec()),
+        [TEST_LEAF].as_ref(),
+        b"a",
+        Element::new_item(b"after".to_vec()),
         None,
         None,
     )
     .unwrap()
-    .expect("successful value insert");
+    .expect("unable to insert");
     db.insert(
-        [TEST_LEAF, b"key2"].as_ref(),
-        b"key6",
-        Element::new_item(b"ayyd".to_vec()),
+        [ANOTHER_TEST_LEAF].as_ref(),
+        b"b",
+        Element::new_item(b"after".to_vec()),
         None,
         None,
     )
     .unwrap()
-    .expect("successful value insert");
+    .expect("unable to insert");
+    assert_ne!(db.root_hash(None).unwrap().unwrap(), snapshot_root_hash);
 
-    //          Test_Leaf
-    // ___________________________
-    //         /        \
-    //     key1           key2
-    // ___________________________
-    //      |              |
-    //     key4          key6
-    //     / \
-    //   key3 key5
-    //
+    let mut query = Query::new();
+    query.insert_all();
+    let path_query = PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], query);
 
-    let path1 = vec![TEST_LEAF.to_vec(), b"key1".to_vec()];
-    let path2 = vec![TEST_LEAF.to_vec(), b"key2".to_vec()];
-    let mut query1 = Query::new();
-    let mut query2 = Query::new();
-    query1.insert_range_inclusive(b"key3".to_vec()..=b"key4".to_vec());
-    query2.insert_key(b"key6".to_vec());
+    // The proof is of the pinned state, later writes aren't part of it
+    let proof = db
+        .prove_query_in_snapshot(&path_query, &snapshot)
+        .unwrap()
+        .expect("expected successful proving");
+    let (hash, result_set) =
+        GroveDb::verify_query(proof.as_slice(), &path_query).expect("expected valid proof");
+    assert_eq!(hash, snapshot_root_hash);
+    assert_eq!(result_set.len(), 1);
+    assert_eq!(result_set[0].2, Some(Element::new_item(b"before".to_vec())));
 
-    let path_query1 = PathQuery::new_unsized(path1, query1);
-    // should get back key3, key4
-    let path_query2 = PathQuery::new_unsized(path2, query2);
-    // should get back key6
+    let proof = db
+        .prove_verbose_in_snapshot(&path_query, &snapshot)
+        .unwrap()
+        .expect("expected successful proving");
+    let (hash, _) =
+        GroveDb::verify_subset_query(proof.as_slice(), &path_query).expect("expected valid proof");
+    assert_eq!(hash, snapshot_root_hash);
+}
+
+#[test]
+fn test_concurrent_readers_and_writer() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<GroveDb>();
+
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key",
+        Element::new_item(b"committed".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("unable to insert");
+
+    let db = &db;
+    let transaction = db.start_transaction().unwrap();
+    // Transactions are `Send` but not `Sync`, so the writer owns it
+    let transaction = std::thread::scope(|scope| {
+        let writer = scope.spawn(move || {
+            for i in 0u8..32 {
+                db.insert(
+                    [TEST_LEAF].as_ref(),
+                    &[i],
+                    Element::new_item(vec![i]),
+                    None,
+                    Some(&transaction),
+                )
+                .unwrap()
+                .expect("unable to insert");
+            }
+            transaction
+        });
+        for _ in 0..4 {
+            scope.spawn(move || {
+                for _ in 0..32 {
+                    // Readers don't see the uncommitted writes of the writer
+                    assert_eq!(
+                        db.get([TEST_LEAF].as_ref(), b"key", None)
+                            .unwrap()
+                            .expect("expected element"),
+                        Element::new_item(b"committed".to_vec())
+                    );
+                    assert!(db.get([TEST_LEAF].as_ref(), &[0], None).unwrap().is_err());
+                }
+            });
+        }
+        writer.join().expect("writer panicked")
+    });
+    db.commit_transaction(transaction)
+        .unwrap()
+        .expect("unable to commit transaction");
+    assert!(db.get([TEST_LEAF].as_ref(), &[31], None).unwrap().is_ok());
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_async_grovedb() {
+    use std::{
+        future::{poll_fn, Future},
+        pin::{pin, Pin},
+        sync::Arc,
+        task::{Context, Poll, Wake},
+        thread::{self, Thread},
+    };
+
+    use futures_core::Stream;
+
+    use crate::query_result_type::QueryResultElements;
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let waker = Arc::new(ThreadWaker(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    let TempGroveDb { _tmp_dir, grove_db } = make_test_grovedb();
+    let db = AsyncGroveDb::new(Arc::new(grove_db), 2);
+
+    let insert_cost = block_on(db.insert(
+        vec![TEST_LEAF.to_vec()],
+        b"key".to_vec(),
+        Element::new_item(b"value".to_vec()),
+        None,
+    ))
+    .cost_as_result()
+    .expect("unable to insert");
+    assert!(insert_cost.seek_count > 0);
+
+    block_on(db.apply_batch(
+        vec![batch::GroveDbOp::insert_op(
+            vec![TEST_LEAF.to_vec()],
+            b"batched".to_vec(),
+            Element::new_item(b"value".to_vec()),
+        )],
+        None,
+    ))
+    .unwrap()
+    .expect("unable to apply batch");
 
+    let result = block_on(db.get(vec![TEST_LEAF.to_vec()], b"batched".to_vec()));
+    assert!(result.cost.seek_count > 0);
     assert_eq!(
-        db.query_many_raw(
-            &[&path_query1, &path_query2],
-            true,
-            QueryKeyElementPairResultType,
-            None
-        )
+        result.value.expect("expected element"),
+        Element::new_item(b"value".to_vec())
+    );
+
+    let mut query = Query::new();
+    query.insert_key(b"key".to_vec());
+    let path_query = PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], query);
+    let proof = block_on(db.prove_query(path_query.clone()))
         .unwrap()
-        .expect("expected successful get_query")
-        .to_key_elements(),
-        vec![
-            (b"key3".to_vec(), Element::new_item(b"ayya".to_vec())),
-            (b"key4".to_vec(), Element::new_item(b"ayyb".to_vec())),
-            (b"key6".to_vec(), Element::new_item(b"ayyd".to_vec())),
-        ]
+        .expect("unable to prove query");
+    let (root_hash, _) =
+        GroveDb::verify_query_raw(&proof, &path_query).expect("unable to verify proof");
+    assert_eq!(
+        root_hash,
+        db.db()
+            .root_hash(None)
+            .unwrap()
+            .expect("expected root hash")
+    );
+
+    // Transactional work is run as a whole on a worker thread
+    let committed = block_on(db.run(|db| {
+        let transaction = db.start_transaction().unwrap();
+        db.delete([TEST_LEAF].as_ref(), b"key", None, Some(&transaction))
+            .unwrap()?;
+        db.commit_transaction(transaction).unwrap()
+    }));
+    assert!(committed.is_ok());
+    assert!(block_on(db.get(vec![TEST_LEAF.to_vec()], b"key".to_vec()))
+        .unwrap()
+        .is_err());
+
+    // Query results are read on a thread of their own
+    let mut query = Query::new();
+    query.insert_all();
+    let mut stream = db.query_stream(
+        PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], query),
+        QueryKeyElementPairResultType,
+    );
+    let mut streamed = Vec::new();
+    while let Some(result) = block_on(poll_fn(|cx| Pin::new(&mut stream).poll_next(cx))) {
+        streamed.push(result.expect("expected successful stream item"));
+    }
+    assert!(stream.cost().seek_count > 0);
+    assert_eq!(
+        QueryResultElements::from_elements(streamed).to_key_elements(),
+        vec![(b"batched".to_vec(), Element::new_item(b"value".to_vec()))]
     );
 }
 
 #[test]
-fn test_aux_uses_separate_cf() {
-    let element = Element::new_item(b"ayy".to_vec());
+fn test_root_hash() {
     let db = make_test_grovedb();
-    // Insert some nested subtrees
+    // Check hashes are different if tree is edited
+    let old_root_hash = db.root_hash(None).unwrap();
     db.insert(
         [TEST_LEAF].as_ref(),
         b"key1",
-        Element::empty_tree(),
+        Element::new_item(b"ayy".to_vec()),
         None,
         None,
     )
     .unwrap()
-    .expect("successful subtree 1 insert");
+    .expect("unable to insert an item");
+    assert_ne!(old_root_hash.unwrap(), db.root_hash(None).unwrap().unwrap());
+
+    // Check isolation
+    let transaction = db.start_transaction().unwrap();
+
     db.insert(
-        [TEST_LEAF, b"key1"].as_ref(),
+        [TEST_LEAF].as_ref(),
         b"key2",
-        Element::empty_tree(),
+        Element::new_item(b"ayy".to_vec()),
+        None,
+        Some(&transaction),
+    )
+    .unwrap()
+    .expect("unable to insert an item");
+    let root_hash_outside = db.root_hash(None).unwrap().unwrap();
+    assert_ne!(
+        db.root_hash(Some(&transaction)).unwrap().unwrap(),
+        root_hash_outside
+    );
+
+    assert_eq!(db.root_hash(None).unwrap().unwrap(), root_hash_outside);
+    db.commit_transaction(transaction).unwrap().unwrap();
+    assert_ne!(db.root_hash(None).unwrap().unwrap(), root_hash_outside);
+}
+
+#[test]
+fn test_get_non_existing_root_leaf() {
+    let db = make_test_grovedb();
+    assert!(matches!(db.get(EMPTY_PATH, b"ayy", None).unwrap(), Err(_)));
+}
+
+#[test]
+fn test_get_many() {
+    let db = make_test_grovedb();
+    for (key, value) in [(b"key1", b"value1"), (b"key2", b"value2")] {
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            key,
+            Element::new_item(value.to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful insert");
+    }
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"ref",
+        Element::new_reference(ReferencePathType::SiblingReference(b"key1".to_vec())),
         None,
         None,
     )
     .unwrap()
-    .expect("successful subtree 2 insert");
-    // Insert an element into subtree
+    .expect("successful insert");
+    let transaction = db.start_transaction().unwrap();
     db.insert(
-        [TEST_LEAF, b"key1", b"key2"].as_ref(),
+        [TEST_LEAF].as_ref(),
         b"key3",
-        element.clone(),
-        None,
+        Element::new_item(b"value3".to_vec()),
         None,
+        Some(&transaction),
     )
     .unwrap()
-    .expect("successful value insert");
+    .expect("successful insert");
 
-    db.put_aux(b"key1", b"a", None, None)
-        .unwrap()
-        .expect("cannot put aux");
-    db.put_aux(b"key2", b"b", None, None)
-        .unwrap()
-        .expect("cannot put aux");
-    db.put_aux(b"key3", b"c", None, None)
-        .unwrap()
-        .expect("cannot put aux");
-    db.delete_aux(b"key3", None, None)
+    let keys: [&[u8]; 4] = [b"key2", b"missing", b"ref", b"key3"];
+    let elements = db
+        .get_many([TEST_LEAF].as_ref(), &keys, Some(&transaction))
         .unwrap()
-        .expect("cannot delete from aux");
-
-    assert_eq!(
-        db.get([TEST_LEAF, b"key1", b"key2"].as_ref(), b"key3", None)
-            .unwrap()
-            .expect("cannot get element"),
-        element
-    );
-    assert_eq!(
-        db.get_aux(b"key1", None)
-            .unwrap()
-            .expect("cannot get from aux"),
-        Some(b"a".to_vec())
-    );
+        .expect("successful get many");
     assert_eq!(
-        db.get_aux(b"key2", None)
-            .unwrap()
-            .expect("cannot get from aux"),
-        Some(b"b".to_vec())
+        elements,
+        vec![
+            Some(Element::new_item(b"value2".to_vec())),
+            None,
+            Some(Element::new_item(b"value1".to_vec())),
+            Some(Element::new_item(b"value3".to_vec())),
+        ]
     );
+    // the insertion is not visible outside of the transaction
     assert_eq!(
-        db.get_aux(b"key3", None)
+        db.get_many([TEST_LEAF].as_ref(), &[b"key3"], None)
             .unwrap()
-            .expect("cannot get from aux"),
-        None
+            .expect("successful get many"),
+        vec![None]
     );
+
+    let raw_elements = db
+        .get_many_raw([TEST_LEAF].as_ref().into(), &[b"ref"], None)
+        .unwrap()
+        .expect("successful get many");
     assert_eq!(
-        db.get_aux(b"key4", None)
-            .unwrap()
-            .expect("cannot get from aux"),
-        None
+        raw_elements,
+        vec![Some(Element::new_reference(
+            ReferencePathType::SiblingReference(b"key1".to_vec())
+        ))]
     );
+
+    assert!(matches!(
+        db.get_many([TEST_LEAF, b"missing"].as_ref(), &[b"key1"], None)
+            .unwrap(),
+        Err(Error::PathNotFound(_))
+    ));
 }
 
 #[test]
-fn test_aux_with_transaction() {
-    let element = Element::new_item(b"ayy".to_vec());
-    let aux_value = b"ayylmao".to_vec();
-    let key = b"key".to_vec();
+fn test_update() {
     let db = make_test_grovedb();
-    let transaction = db.start_transaction();
-
-    // Insert a regular data with aux data in the same transaction
     db.insert(
         [TEST_LEAF].as_ref(),
-        &key,
-        element,
+        b"sums",
+        Element::empty_sum_tree(),
+        None,
         None,
-        Some(&transaction),
     )
     .unwrap()
-    .expect("unable to insert");
-    db.put_aux(&key, &aux_value, None, Some(&transaction))
+    .expect("successful sum tree insert");
+    let increment = |element: Option<Element>| match element {
+        Some(Element::SumItem(value, flags)) => Some(Element::SumItem(value + 1, flags)),
+        _ => Some(Element::new_sum_item(1)),
+    };
+
+    let updated = db
+        .update([TEST_LEAF, b"sums"].as_ref(), b"counter", increment, None)
         .unwrap()
-        .expect("unable to insert aux value");
+        .expect("successful update");
+    assert_eq!(updated, Some(Element::new_sum_item(1)));
+    db.update([TEST_LEAF, b"sums"].as_ref(), b"counter", increment, None)
+        .unwrap()
+        .expect("successful update");
     assert_eq!(
-        db.get_aux(&key, Some(&transaction))
+        db.get([TEST_LEAF, b"sums"].as_ref(), b"counter", None)
             .unwrap()
-            .expect("unable to get aux value"),
-        Some(aux_value.clone())
+            .expect("successful get"),
+        Element::new_sum_item(2)
     );
-    // Cannot reach the data outside of transaction
+
+    let transaction = db.start_transaction().unwrap();
+    db.update(
+        [TEST_LEAF, b"sums"].as_ref(),
+        b"counter",
+        increment,
+        Some(&transaction),
+    )
+    .unwrap()
+    .expect("successful update");
+    // the update is not visible outside of the transaction
     assert_eq!(
-        db.get_aux(&key, None)
+        db.get([TEST_LEAF, b"sums"].as_ref(), b"counter", None)
             .unwrap()
-            .expect("unable to get aux value"),
-        None
+            .expect("successful get"),
+        Element::new_sum_item(2)
     );
-    // And should be able to get data when committed
     db.commit_transaction(transaction)
         .unwrap()
-        .expect("unable to commit transaction");
+        .expect("successful commit");
     assert_eq!(
-        db.get_aux(&key, None)
+        db.get([TEST_LEAF, b"sums"].as_ref(), b"counter", None)
             .unwrap()
-            .expect("unable to get committed aux value"),
-        Some(aux_value)
+            .expect("successful get"),
+        Element::new_sum_item(3)
+    );
+    assert_eq!(
+        db.sum_of_subtree([TEST_LEAF, b"sums"].as_ref(), None)
+            .unwrap()
+            .expect("successful sum"),
+        3
     );
+
+    // returning nothing deletes the element
+    let updated = db
+        .update([TEST_LEAF, b"sums"].as_ref(), b"counter", |_| None, None)
+        .unwrap()
+        .expect("successful update");
+    assert_eq!(updated, None);
+    assert!(matches!(
+        db.get([TEST_LEAF, b"sums"].as_ref(), b"counter", None)
+            .unwrap(),
+        Err(Error::PathKeyNotFound(_))
+    ));
+    db.update(
+        [TEST_LEAF, b"sums"].as_ref(),
+        b"missing",
+        |element| {
+            assert_eq!(element, None);
+            None
+        },
+        None,
+    )
+    .unwrap()
+    .expect("successful update");
 }
 
 #[test]
-fn test_root_hash() {
+fn test_update_does_not_lose_concurrent_writes() {
     let db = make_test_grovedb();
-    // Check hashes are different if tree is edited
-    let old_root_hash = db.root_hash(None).unwrap();
     db.insert(
         [TEST_LEAF].as_ref(),
-        b"key1",
-        Element::new_item(b"ayy".to_vec()),
+        b"sums",
+        Element::empty_sum_tree(),
         None,
         None,
     )
     .unwrap()
-    .expect("unable to insert an item");
-    assert_ne!(old_root_hash.unwrap(), db.root_hash(None).unwrap().unwrap());
-
-    // Check isolation
-    let transaction = db.start_transaction();
-
+    .expect("successful sum tree insert");
     db.insert(
-        [TEST_LEAF].as_ref(),
-        b"key2",
-        Element::new_item(b"ayy".to_vec()),
+        [TEST_LEAF, b"sums"].as_ref(),
+        b"counter",
+        Element::new_sum_item(1),
+        None,
         None,
-        Some(&transaction),
     )
     .unwrap()
-    .expect("unable to insert an item");
-    let root_hash_outside = db.root_hash(None).unwrap().unwrap();
-    assert_ne!(
-        db.root_hash(Some(&transaction)).unwrap().unwrap(),
-        root_hash_outside
+    .expect("successful insert");
+
+    // Another transaction changes the element between the read and the write
+    // of the update
+    let updated = db
+        .update(
+            [TEST_LEAF, b"sums"].as_ref(),
+            b"counter",
+            |element| {
+                let transaction = db.start_transaction().unwrap();
+                db.insert(
+                    [TEST_LEAF, b"sums"].as_ref(),
+                    b"counter",
+                    Element::new_sum_item(10),
+                    None,
+                    Some(&transaction),
+                )
+                .unwrap()
+                .expect("successful insert");
+                db.commit_transaction(transaction)
+                    .unwrap()
+                    .expect("successful commit");
+                match element {
+                    Some(Element::SumItem(value, flags)) => {
+                        Some(Element::SumItem(value + 1, flags))
+                    }
+                    _ => None,
+                }
+            },
+            None,
+        )
+        .unwrap();
+    assert!(updated.is_err());
+    assert_eq!(
+        db.get([TEST_LEAF, b"sums"].as_ref(), b"counter", None)
+            .unwrap()
+            .expect("successful get"),
+        Element::new_sum_item(10)
     );
-
-    assert_eq!(db.root_hash(None).unwrap().unwrap(), root_hash_outside);
-    db.commit_transaction(transaction).unwrap().unwrap();
-    assert_ne!(db.root_hash(None).unwrap().unwrap(), root_hash_outside);
-}
-
-#[test]
-fn test_get_non_existing_root_leaf() {
-    let db = make_test_grovedb();
-    assert!(matches!(db.get(EMPTY_PATH, b"ayy", None).unwrap(), Err(_)));
 }
 
 #[test]
@@ -2871,7 +7107,7 @@ fn test_tree_value_exists_method_no_tx() {
 #[test]
 fn test_tree_value_exists_method_tx() {
     let db = make_test_grovedb();
-    let tx = db.start_transaction();
+    let tx = db.start_transaction().unwrap();
     // Test keys in non-root tree
     db.insert(
         [TEST_LEAF].as_ref(),
@@ -2907,3 +7143,79 @@ fn test_tree_value_exists_method_tx() {
         .unwrap());
     assert!(db.has_raw(EMPTY_PATH, b"leaf", None).unwrap().unwrap());
 }
+
+#[test]
+fn test_export_and_import_snapshot() {
+    let db = make_deep_tree();
+    db.put_aux(b"aux_key", b"aux_value", None, None)
+        .unwrap()
+        .expect("cannot put aux");
+
+    let mut snapshot = Vec::new();
+    db.export_snapshot(&mut snapshot)
+        .expect("cannot export snapshot");
+
+    let imported = make_empty_grovedb();
+    imported
+        .import_snapshot(snapshot.as_slice())
+        .expect("cannot import snapshot");
+    assert_eq!(
+        imported.root_hash(None).unwrap().unwrap(),
+        db.root_hash(None).unwrap().unwrap()
+    );
+    assert_eq!(
+        imported
+            .get([TEST_LEAF, b"innertree"].as_ref(), b"key1", None)
+            .unwrap()
+            .expect("cannot get imported element"),
+        Element::new_item(b"value1".to_vec())
+    );
+    assert_eq!(
+        imported.get_aux(b"aux_key", None).unwrap().unwrap(),
+        Some(b"aux_value".to_vec())
+    );
+
+    // Importing into a GroveDB that is not empty fails
+    assert!(imported.import_snapshot(snapshot.as_slice()).is_err());
+
+    // A truncated snapshot leaves the GroveDB empty
+    let truncated = make_empty_grovedb();
+    assert!(truncated
+        .import_snapshot(&snapshot[..snapshot.len() - 1])
+        .is_err());
+    assert!(truncated.get(EMPTY_PATH, TEST_LEAF, None).unwrap().is_err());
+}
+
+#[test]
+fn test_import_snapshot_spanning_several_chunks() {
+    let db = make_test_grovedb();
+    for i in 0u8..6 {
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            &[i],
+            Element::new_item(vec![i; 1024 * 1024]),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("cannot insert item");
+    }
+    let mut snapshot = Vec::new();
+    db.export_snapshot(&mut snapshot)
+        .expect("cannot export snapshot");
+
+    // Chunks already written are removed when the snapshot turns out truncated
+    let imported = make_empty_grovedb();
+    assert!(imported
+        .import_snapshot(&snapshot[..snapshot.len() - 1])
+        .is_err());
+    assert!(imported.get(EMPTY_PATH, TEST_LEAF, None).unwrap().is_err());
+
+    imported
+        .import_snapshot(snapshot.as_slice())
+        .expect("cannot import snapshot");
+    assert_eq!(
+        imported.root_hash(None).unwrap().unwrap(),
+        db.root_hash(None).unwrap().unwrap()
+    );
+}
@@ -28,7 +28,10 @@
 
 //! Query tests
 
-use grovedb_merk::proofs::{query::QueryItem, Query};
+use grovedb_merk::proofs::{
+    query::{ElementKind, ElementKindAction, QueryItem, SubqueryBranch},
+    Query,
+};
 use rand::Rng;
 use tempfile::TempDir;
 
@@ -38,9 +41,9 @@ use crate::{
     reference_path::ReferencePathType,
     tests::{
         common::compare_result_sets, make_deep_tree, make_test_grovedb, TempGroveDb,
-        ANOTHER_TEST_LEAF, TEST_LEAF,
+        ANOTHER_TEST_LEAF, DEEP_LEAF, TEST_LEAF,
     },
-    Element, GroveDb, PathQuery, SizedQuery,
+    Element, Error, GroveDb, PathQuery, QueryCursor, RawIteratorOptions, SizedQuery,
 };
 
 fn populate_tree_for_non_unique_range_subquery(db: &TempGroveDb) {
@@ -386,6 +389,60 @@ fn test_get_range_query_with_non_unique_subquery() {
     compare_result_sets(&elements, &result_set);
 }
 
+#[test]
+fn test_get_range_query_with_iterator_options() {
+    let db = make_test_grovedb();
+    populate_tree_for_non_unique_range_subquery(&db);
+
+    let iterator_options = RawIteratorOptions {
+        readahead_size: Some(4 * 1024 * 1024),
+        fill_cache: false,
+        iterate_upper_bound: Some(1990_u32.to_be_bytes().to_vec()),
+    };
+
+    for left_to_right in [true, false] {
+        let mut query = Query::new_with_direction(left_to_right);
+        query.insert_all();
+        let mut subquery = Query::new_with_direction(left_to_right);
+        subquery.insert_all();
+        query.set_subquery_key(b"\0".to_vec());
+        query.set_subquery(subquery.clone());
+        let path_query = PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], query);
+
+        let mut bounded_query = Query::new_with_direction(left_to_right);
+        bounded_query.insert_range_to(..1990_u32.to_be_bytes().to_vec());
+        bounded_query.set_subquery_key(b"\0".to_vec());
+        bounded_query.set_subquery(subquery);
+        let bounded_path_query = PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], bounded_query);
+
+        let (elements, _) = db
+            .query_raw_with_iterator_options(
+                &path_query,
+                true,
+                &iterator_options,
+                QueryResultType::QueryPathKeyElementTrioResultType,
+                None,
+            )
+            .unwrap()
+            .expect("expected successful get_path_query");
+        assert_eq!(elements.len(), 250);
+
+        let (expected, _) = db
+            .query_raw(
+                &bounded_path_query,
+                true,
+                QueryResultType::QueryPathKeyElementTrioResultType,
+                None,
+            )
+            .unwrap()
+            .expect("expected successful get_path_query");
+        assert_eq!(
+            elements.to_path_key_elements(),
+            expected.to_path_key_elements()
+        );
+    }
+}
+
 #[test]
 fn test_get_range_query_with_unique_subquery() {
     let mut db = make_test_grovedb();
@@ -2042,6 +2099,52 @@ fn test_proof_with_limit_zero() {
     assert_eq!(result_set.len(), 0);
 }
 
+#[test]
+fn test_query_limit_and_offset_span_subtrees() {
+    let db = make_deep_tree();
+    let mut subquery = Query::new();
+    subquery.insert_all();
+    let mut query = Query::new();
+    query.insert_all();
+    query.set_subquery(subquery);
+    let path_query = PathQuery::new(
+        vec![TEST_LEAF.to_vec()],
+        SizedQuery::new(query, Some(3), Some(2)),
+    );
+
+    // innertree holds key1 to key3 and innertree4 holds key4 and key5, the
+    // offset and limit are spent across both of them
+    let (elements, skipped) = db
+        .query_raw(
+            &path_query,
+            true,
+            QueryResultType::QueryKeyElementPairResultType,
+            None,
+        )
+        .unwrap()
+        .expect("expected successful query");
+    assert_eq!(
+        elements.to_keys(),
+        vec![b"key3".to_vec(), b"key4".to_vec(), b"key5".to_vec()]
+    );
+    assert_eq!(skipped, 2);
+
+    let path_query = PathQuery::new(
+        vec![TEST_LEAF.to_vec(), b"innertree".to_vec()],
+        SizedQuery::new(Query::new_single_key(b"key1".to_vec()), Some(0), None),
+    );
+    let (elements, _) = db
+        .query_raw(
+            &path_query,
+            true,
+            QueryResultType::QueryKeyElementPairResultType,
+            None,
+        )
+        .unwrap()
+        .expect("expected successful query");
+    assert!(elements.to_keys().is_empty());
+}
+
 #[test]
 fn test_result_set_path_after_verification() {
     let db = make_deep_tree();
@@ -2658,3 +2761,313 @@ fn test_query_b_depends_on_query_a() {
     assert_eq!(age_result[0].2, Some(Element::new_item(vec![12])));
     assert_eq!(age_result[1].2, Some(Element::new_item(vec![46])));
 }
+
+#[cfg(feature = "async")]
+#[test]
+fn test_query_stream_matches_query_raw() {
+    use crate::query_result_type::QueryResultElements;
+
+    let db = make_test_grovedb();
+    populate_tree_for_non_unique_range_subquery(&db);
+
+    let mut query = Query::new_with_direction(false);
+    query.insert_range(1988_u32.to_be_bytes().to_vec()..1992_u32.to_be_bytes().to_vec());
+    let mut subquery = Query::new();
+    subquery.insert_all();
+    query.set_subquery_key(b"\0".to_vec());
+    query.set_subquery(subquery);
+
+    let path_query = PathQuery::new(
+        vec![TEST_LEAF.to_vec()],
+        SizedQuery::new(query, Some(70), Some(20)),
+    );
+
+    let (expected, _) = db
+        .query_raw(
+            &path_query,
+            true,
+            QueryResultType::QueryPathKeyElementTrioResultType,
+            None,
+        )
+        .unwrap()
+        .expect("expected successful query");
+    assert_eq!(expected.len(), 70);
+
+    let mut stream = db.query_stream(
+        &path_query,
+        QueryResultType::QueryPathKeyElementTrioResultType,
+        None,
+    );
+    let streamed = stream
+        .by_ref()
+        .collect::<Result<Vec<_>, _>>()
+        .expect("expected successful stream items");
+    assert!(stream.cost().seek_count > 0);
+    assert_eq!(
+        QueryResultElements::from_elements(streamed).to_path_key_elements(),
+        expected.to_path_key_elements()
+    );
+}
+
+#[test]
+fn test_verify_query_detects_limit_and_offset_mismatch() {
+    let db = make_test_grovedb();
+    for i in 0u8..5 {
+        db.insert(
+            [TEST_LEAF].as_ref(),
+            &[i],
+            Element::new_item(vec![i]),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful item insert");
+    }
+
+    let mut query = Query::new();
+    query.insert_all();
+    let path_query = PathQuery::new(
+        vec![TEST_LEAF.to_vec()],
+        SizedQuery::new(query.clone(), Some(3), None),
+    );
+    let proof = db.prove_query(&path_query).unwrap().unwrap();
+    let (_, result_set) = GroveDb::verify_query(&proof, &path_query).expect("should verify proof");
+    assert_eq!(result_set.len(), 3);
+
+    let smaller_limit = PathQuery::new(
+        vec![TEST_LEAF.to_vec()],
+        SizedQuery::new(query.clone(), Some(2), None),
+    );
+    assert!(matches!(
+        GroveDb::verify_query(&proof, &smaller_limit),
+        Err(Error::ProofLimitOffsetMismatch(_))
+    ));
+
+    let with_offset = PathQuery::new(
+        vec![TEST_LEAF.to_vec()],
+        SizedQuery::new(query, Some(3), Some(1)),
+    );
+    assert!(matches!(
+        GroveDb::verify_query(&proof, &with_offset),
+        Err(Error::ProofLimitOffsetMismatch(_))
+    ));
+}
+
+#[test]
+fn test_paging_query_with_cursors() {
+    let db = make_deep_tree();
+    let root_hash = db.root_hash(None).unwrap().unwrap();
+
+    for left_to_right in [true, false] {
+        let mut subsubquery = Query::new_with_direction(left_to_right);
+        subsubquery.insert_all();
+        let mut subquery = Query::new_with_direction(left_to_right);
+        subquery.insert_all();
+        subquery.set_subquery(subsubquery);
+        let mut query = Query::new_with_direction(left_to_right);
+        query.insert_all();
+        query.set_subquery(subquery);
+        let path_query = PathQuery::new_unsized(vec![DEEP_LEAF.to_vec()], query);
+
+        let proof = db.prove_query(&path_query).unwrap().unwrap();
+        let (_, all_elements) = GroveDb::verify_query(&proof, &path_query).unwrap();
+        assert_eq!(all_elements.len(), 11);
+
+        // Pages of 4 elements, each proven and resumed from the cursor of the
+        // last element of the previous page, which crosses subtrees
+        let mut paged_elements = vec![];
+        let mut page_query = PathQuery::new(
+            path_query.path.clone(),
+            SizedQuery::new(path_query.query.query.clone(), Some(4), None),
+        );
+        loop {
+            let proof = db.prove_query(&page_query).unwrap().unwrap();
+            let (hash, page) = GroveDb::verify_query(&proof, &page_query).unwrap();
+            assert_eq!(hash, root_hash);
+            let Some((path, key, _)) = page.last() else {
+                break;
+            };
+            let cursor =
+                QueryCursor::from_bytes(&QueryCursor::new(path.clone(), key.clone()).to_bytes())
+                    .unwrap();
+            let page_len = page.len();
+            paged_elements.extend(page);
+            if page_len < 4 {
+                break;
+            }
+            page_query = page_query.resume_after(&cursor).unwrap();
+        }
+        assert_eq!(paged_elements, all_elements);
+    }
+
+    // An element inserted before the cursor doesn't shift the next page
+    let mut subquery = Query::new();
+    subquery.insert_all();
+    let mut query = Query::new();
+    query.insert_all();
+    query.set_subquery(subquery);
+    let path_query = PathQuery::new(
+        vec![DEEP_LEAF.to_vec(), b"deep_node_1".to_vec()],
+        SizedQuery::new(query, Some(2), None),
+    );
+    let cursor = QueryCursor::new(
+        vec![
+            DEEP_LEAF.to_vec(),
+            b"deep_node_1".to_vec(),
+            b"deeper_1".to_vec(),
+        ],
+        b"key2".to_vec(),
+    );
+    db.insert(
+        [DEEP_LEAF, b"deep_node_1", b"deeper_1"].as_ref(),
+        b"key0",
+        Element::new_item(b"value0".to_vec()),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("successful item insert");
+    let (elements, _) = db
+        .query_raw(
+            &path_query.resume_after(&cursor).unwrap(),
+            true,
+            QueryResultType::QueryPathKeyElementTrioResultType,
+            None,
+        )
+        .unwrap()
+        .unwrap();
+    let keys: Vec<Vec<u8>> = elements
+        .to_path_key_elements()
+        .into_iter()
+        .map(|(_, key, _)| key)
+        .collect();
+    assert_eq!(keys, vec![b"key3".to_vec(), b"key4".to_vec()]);
+
+    // A cursor outside of the query path can't resume it
+    let cursor = QueryCursor::new(vec![TEST_LEAF.to_vec()], b"key1".to_vec());
+    assert!(path_query.resume_after(&cursor).is_err());
+}
+
+#[test]
+fn test_query_with_element_kind_actions() {
+    let db = make_test_grovedb();
+    // A subtree mixing items, trees and a reference
+    for (key, element) in [
+        (b"a".to_vec(), Element::new_item(b"item_a".to_vec())),
+        (b"b".to_vec(), Element::empty_tree()),
+        (b"c".to_vec(), Element::new_item(b"item_c".to_vec())),
+        (b"d".to_vec(), Element::empty_tree()),
+        (
+            b"e".to_vec(),
+            Element::new_reference(ReferencePathType::AbsolutePathReference(vec![
+                TEST_LEAF.to_vec(),
+                b"a".to_vec(),
+            ])),
+        ),
+    ] {
+        db.insert([TEST_LEAF].as_ref(), &key, element, None, None)
+            .unwrap()
+            .expect("successful insert");
+    }
+    for (tree, key) in [(b"b", b"k1"), (b"b", b"k2"), (b"d", b"k3")] {
+        db.insert(
+            [TEST_LEAF, tree.as_slice()].as_ref(),
+            key,
+            Element::new_item(key.to_vec()),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("successful item insert");
+    }
+
+    // Items are skipped, trees are descended into and the reference is kept
+    let mut subquery = Query::new();
+    subquery.insert_all();
+    let mut query = Query::new();
+    query.insert_all();
+    query.set_subquery(subquery);
+    query.add_element_kind_action(
+        QueryItem::RangeFull(..),
+        ElementKind::Item,
+        ElementKindAction::Skip,
+    );
+    let path_query = PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], query);
+    let expected = vec![
+        (vec![TEST_LEAF.to_vec(), b"b".to_vec()], b"k1".to_vec()),
+        (vec![TEST_LEAF.to_vec(), b"b".to_vec()], b"k2".to_vec()),
+        (vec![TEST_LEAF.to_vec(), b"d".to_vec()], b"k3".to_vec()),
+        (vec![TEST_LEAF.to_vec()], b"e".to_vec()),
+    ];
+
+    let (elements, _) = db
+        .query_raw(
+            &path_query,
+            true,
+            QueryResultType::QueryPathKeyElementTrioResultType,
+            None,
+        )
+        .unwrap()
+        .expect("expected successful query");
+    let path_keys: Vec<_> = elements
+        .to_path_key_elements()
+        .into_iter()
+        .map(|(path, key, _)| (path, key))
+        .collect();
+    assert_eq!(path_keys, expected);
+
+    let proof = db.prove_query(&path_query).unwrap().unwrap();
+    let (hash, proved) = GroveDb::verify_query(&proof, &path_query).unwrap();
+    assert_eq!(hash, db.root_hash(None).unwrap().unwrap());
+    let proved_path_keys: Vec<_> = proved
+        .into_iter()
+        .map(|(path, key, _)| (path, key))
+        .collect();
+    assert_eq!(proved_path_keys, expected);
+
+    // The tree at "d" is skipped and the one at "b" uses its own subquery
+    let mut subquery = Query::new();
+    subquery.insert_all();
+    let mut query = Query::new();
+    query.insert_all();
+    query.set_subquery(subquery);
+    query.add_element_kind_action(
+        QueryItem::Key(b"b".to_vec()),
+        ElementKind::Tree,
+        ElementKindAction::Subquery(SubqueryBranch {
+            subquery_path: Some(vec![b"k2".to_vec()]),
+            subquery: None,
+        }),
+    );
+    query.add_element_kind_action(
+        QueryItem::RangeFull(..),
+        ElementKind::Tree,
+        ElementKindAction::Skip,
+    );
+    let path_query = PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], query);
+    let expected = vec![
+        (vec![TEST_LEAF.to_vec()], b"a".to_vec()),
+        (vec![TEST_LEAF.to_vec(), b"b".to_vec()], b"k2".to_vec()),
+        (vec![TEST_LEAF.to_vec()], b"c".to_vec()),
+        (vec![TEST_LEAF.to_vec()], b"e".to_vec()),
+    ];
+
+    let proof = db.prove_query(&path_query).unwrap().unwrap();
+    let (hash, proved) = GroveDb::verify_query(&proof, &path_query).unwrap();
+    assert_eq!(hash, db.root_hash(None).unwrap().unwrap());
+    let proved_path_keys: Vec<_> = proved
+        .into_iter()
+        .map(|(path, key, _)| (path, key))
+        .collect();
+    assert_eq!(proved_path_keys, expected);
+
+    // Leaf proofs apply limits to every element, so limits aren't supported
+    let limited_path_query = PathQuery::new(
+        path_query.path.clone(),
+        SizedQuery::new(path_query.query.query.clone(), Some(2), None),
+    );
+    assert!(matches!(
+        db.prove_query(&limited_path_query).unwrap(),
+        Err(Error::NotSupported(_))
+    ));
+}
@@ -0,0 +1,242 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Reference path tests
+
+use grovedb_merk::proofs::Query;
+
+use crate::{
+    reference_path::{path_from_reference_path_type, ReferencePathType},
+    tests::{make_deep_tree, TEST_LEAF},
+    Element, GroveDb, PathQuery,
+};
+
+#[test]
+fn test_upstream_root_height_reference() {
+    let stored_path = vec![b"a".as_ref(), b"b".as_ref(), b"m".as_ref()];
+    // selects the first 2 elements from the stored path and appends the new path.
+    let ref1 =
+        ReferencePathType::UpstreamRootHeightReference(2, vec![b"c".to_vec(), b"d".to_vec()]);
+    let final_path = path_from_reference_path_type(ref1, &stored_path, None).unwrap();
+    assert_eq!(
+        final_path,
+        vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec(), b"d".to_vec()]
+    );
+}
+
+#[test]
+fn test_upstream_from_element_height_reference() {
+    let stored_path = vec![b"a".as_ref(), b"b".as_ref(), b"m".as_ref()];
+    // discards the last element from the stored_path
+    let ref1 = ReferencePathType::UpstreamFromElementHeightReference(
+        1,
+        vec![b"c".to_vec(), b"d".to_vec()],
+    );
+    let final_path = path_from_reference_path_type(ref1, &stored_path, None).unwrap();
+    assert_eq!(
+        final_path,
+        vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec(), b"d".to_vec()]
+    );
+}
+
+#[test]
+fn test_cousin_reference_no_key() {
+    let stored_path = vec![b"a".as_ref(), b"b".as_ref(), b"m".as_ref()];
+    // Replaces the immediate parent (in this case b) with the given key (c)
+    let ref1 = ReferencePathType::CousinReference(b"c".to_vec());
+    let final_path = path_from_reference_path_type(ref1, &stored_path, None);
+    assert!(final_path.is_err());
+}
+
+#[test]
+fn test_cousin_reference() {
+    let stored_path = vec![b"a".as_ref(), b"b".as_ref()];
+    let key = b"m".as_ref();
+    // Replaces the immediate parent (in this case b) with the given key (c)
+    let ref1 = ReferencePathType::CousinReference(b"c".to_vec());
+    let final_path = path_from_reference_path_type(ref1, &stored_path, Some(key)).unwrap();
+    assert_eq!(
+        final_path,
+        vec![b"a".to_vec(), b"c".to_vec(), b"m".to_vec()]
+    );
+}
+
+#[test]
+fn test_removed_cousin_reference_no_key() {
+    let stored_path = vec![b"a".as_ref(), b"b".as_ref(), b"m".as_ref()];
+    // Replaces the immediate parent (in this case b) with the given key (c)
+    let ref1 = ReferencePathType::RemovedCousinReference(vec![b"c".to_vec(), b"d".to_vec()]);
+    let final_path = path_from_reference_path_type(ref1, &stored_path, None);
+    assert!(final_path.is_err());
+}
+
+#[test]
+fn test_removed_cousin_reference() {
+    let stored_path = vec![b"a".as_ref(), b"b".as_ref()];
+    let key = b"m".as_ref();
+    // Replaces the immediate parent (in this case b) with the given key (c)
+    let ref1 = ReferencePathType::RemovedCousinReference(vec![b"c".to_vec(), b"d".to_vec()]);
+    let final_path = path_from_reference_path_type(ref1, &stored_path, Some(key)).unwrap();
+    assert_eq!(
+        final_path,
+        vec![b"a".to_vec(), b"c".to_vec(), b"d".to_vec(), b"m".to_vec()]
+    );
+}
+
+#[test]
+fn test_sibling_reference() {
+    let stored_path = vec![b"a".as_ref(), b"b".as_ref()];
+    let key = b"m".as_ref();
+    let ref1 = ReferencePathType::SiblingReference(b"c".to_vec());
+    let final_path = path_from_reference_path_type(ref1, &stored_path, Some(key)).unwrap();
+    assert_eq!(
+        final_path,
+        vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]
+    );
+}
+
+#[test]
+fn test_cousin_with_key_reference() {
+    let stored_path = vec![b"a".as_ref(), b"b".as_ref(), b"c".as_ref()];
+    let key = b"m".as_ref();
+    // discards the last 2 elements and descends into s to point to k
+    let ref1 = ReferencePathType::CousinWithKeyReference(2, b"s".to_vec(), b"k".to_vec());
+    let final_path = path_from_reference_path_type(ref1, &stored_path, Some(key)).unwrap();
+    assert_eq!(
+        final_path,
+        vec![b"a".to_vec(), b"s".to_vec(), b"k".to_vec()]
+    );
+}
+
+#[test]
+fn test_cousin_with_key_reference_too_high() {
+    let stored_path = vec![b"a".as_ref(), b"b".as_ref()];
+    let ref1 = ReferencePathType::CousinWithKeyReference(3, b"s".to_vec(), b"k".to_vec());
+    let final_path = path_from_reference_path_type(ref1, &stored_path, None);
+    assert!(final_path.is_err());
+}
+
+#[test]
+fn test_follow_cousin_with_key_reference() {
+    let db = make_deep_tree();
+
+    db.insert(
+        [TEST_LEAF, b"innertree4"].as_ref(),
+        b"ref",
+        Element::new_reference(ReferencePathType::CousinWithKeyReference(
+            1,
+            b"innertree".to_vec(),
+            b"key1".to_vec(),
+        )),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert successfully");
+
+    let element = db
+        .get([TEST_LEAF, b"innertree4"].as_ref(), b"ref", None)
+        .unwrap()
+        .expect("should follow reference");
+    assert_eq!(element, Element::new_item(b"value1".to_vec()));
+}
+
+#[test]
+fn test_query_many_with_different_reference_types() {
+    let db = make_deep_tree();
+
+    db.insert(
+        [TEST_LEAF, b"innertree4"].as_ref(),
+        b"ref1",
+        Element::new_reference(ReferencePathType::AbsolutePathReference(vec![
+            TEST_LEAF.to_vec(),
+            b"innertree".to_vec(),
+            b"key1".to_vec(),
+        ])),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert successfully");
+
+    db.insert(
+        [TEST_LEAF, b"innertree4"].as_ref(),
+        b"ref2",
+        Element::new_reference(ReferencePathType::UpstreamRootHeightReference(
+            1,
+            vec![b"innertree".to_vec(), b"key1".to_vec()],
+        )),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert successfully");
+
+    db.insert(
+        [TEST_LEAF, b"innertree4"].as_ref(),
+        b"ref3",
+        Element::new_reference(ReferencePathType::UpstreamFromElementHeightReference(
+            1,
+            vec![b"innertree".to_vec(), b"key1".to_vec()],
+        )),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert successfully");
+
+    // Query all the elements in Test Leaf
+    let mut query = Query::new();
+    query.insert_all();
+    let path_query =
+        PathQuery::new_unsized(vec![TEST_LEAF.to_vec(), b"innertree4".to_vec()], query);
+    let result = db
+        .query_item_value(&path_query, true, None)
+        .unwrap()
+        .expect("should query items");
+    assert_eq!(result.0.len(), 5);
+    assert_eq!(
+        result.0,
+        vec![
+            b"value4".to_vec(),
+            b"value5".to_vec(),
+            b"value1".to_vec(),
+            b"value1".to_vec(),
+            b"value1".to_vec()
+        ]
+    );
+
+    let proof = db
+        .prove_query(&path_query)
+        .unwrap()
+        .expect("should generate proof");
+    let (hash, result) =
+        GroveDb::verify_query_raw(&proof, &path_query).expect("should verify proof");
+    assert_eq!(hash, db.root_hash(None).unwrap().unwrap());
+    assert_eq!(result.len(), 5);
+}
@@ -38,7 +38,7 @@ use crate::{
     batch::GroveDbOp,
     reference_path::ReferencePathType,
     tests::{make_test_grovedb, TEST_LEAF},
-    Element, Error, GroveDb, PathQuery,
+    Aggregate, Element, Error, GroveDb, PathQuery,
 };
 
 #[test]
@@ -484,8 +484,72 @@ fn test_sum_tree_feature() {
                                                                          // 10 -
                                                                          // 100 +
                                                                          // 10000000
+}
+
+#[test]
+fn test_sum_tree_overflow() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key",
+        Element::empty_sum_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert tree");
+    db.insert(
+        [TEST_LEAF, b"key"].as_ref(),
+        b"item1",
+        Element::new_sum_item(i64::MAX),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+    // negative values take the total back below the maximum
+    db.insert(
+        [TEST_LEAF, b"key"].as_ref(),
+        b"item2",
+        Element::new_sum_item(-10),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+    assert_eq!(
+        db.sum_of_subtree([TEST_LEAF, b"key"].as_ref(), None)
+            .unwrap()
+            .expect("should get sum"),
+        i64::MAX - 10
+    );
 
-    // TODO: Test out overflows
+    let ops = vec![GroveDbOp::insert_op(
+        vec![TEST_LEAF.to_vec(), b"key".to_vec()],
+        b"item3".to_vec(),
+        Element::new_sum_item(11),
+    )];
+    assert!(matches!(
+        db.apply_batch(ops, None, None).unwrap(),
+        Err(Error::SumOverflow(_))
+    ));
+    assert!(matches!(
+        db.insert(
+            [TEST_LEAF, b"key"].as_ref(),
+            b"item3",
+            Element::new_sum_item(11),
+            None,
+            None,
+        )
+        .unwrap(),
+        Err(Error::SumOverflow(_))
+    ));
+    assert_eq!(
+        db.sum_of_subtree([TEST_LEAF, b"key"].as_ref(), None)
+            .unwrap()
+            .expect("should get sum"),
+        i64::MAX - 10
+    );
 }
 
 #[test]
@@ -781,3 +845,220 @@ fn test_sum_tree_with_batches() {
         .expect("should open tree");
     assert_eq!(sum_tree.sum().expect("expected to get sum"), Some(41));
 }
+
+#[test]
+fn test_sum_of_subtree_with_proof() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key",
+        Element::empty_sum_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert tree");
+    db.insert(
+        [TEST_LEAF, b"key"].as_ref(),
+        b"sumitem1",
+        Element::new_sum_item(30),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+    db.insert(
+        [TEST_LEAF, b"key"].as_ref(),
+        b"sumitem2",
+        Element::new_sum_item(-10),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+    db.insert(
+        [TEST_LEAF, b"key"].as_ref(),
+        b"item",
+        Element::new_item(vec![1]),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+
+    let sum = db
+        .sum_of_subtree([TEST_LEAF, b"key"].as_ref(), None)
+        .unwrap()
+        .expect("should get sum");
+    assert_eq!(sum, 20);
+
+    // Regular trees and the root tree have no sum
+    assert!(matches!(
+        db.sum_of_subtree([TEST_LEAF].as_ref(), None).unwrap(),
+        Err(Error::WrongElementType(_))
+    ));
+    assert!(matches!(
+        db.sum_of_subtree::<&[u8], _>(&[] as &[&[u8]], None)
+            .unwrap(),
+        Err(Error::InvalidPath(_))
+    ));
+
+    // The sum can be verified from the proof alone
+    let path = vec![TEST_LEAF.to_vec(), b"key".to_vec()];
+    let proof = db
+        .prove_sum_of_subtree(path.clone())
+        .unwrap()
+        .expect("should generate proof");
+    let (root_hash, proved_sum) =
+        GroveDb::verify_sum_of_subtree(&proof, path).expect("should verify proof");
+    assert_eq!(root_hash, db.grove_db.root_hash(None).unwrap().unwrap());
+    assert_eq!(proved_sum, 20);
+
+    // A proof for a regular tree doesn't verify as a sum
+    let path = vec![TEST_LEAF.to_vec()];
+    let proof = db
+        .prove_sum_of_subtree(path.clone())
+        .unwrap()
+        .expect("should generate proof");
+    assert!(matches!(
+        GroveDb::verify_sum_of_subtree(&proof, path),
+        Err(Error::WrongElementType(_))
+    ));
+}
+
+#[test]
+fn test_verify_query_with_subtree_sums() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"balances",
+        Element::empty_sum_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert tree");
+    for (key, value) in [(b"a", 5), (b"b", 10), (b"c", -3)] {
+        db.insert(
+            [TEST_LEAF, b"balances"].as_ref(),
+            key,
+            Element::new_sum_item(value),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("should insert sum item");
+    }
+    db.insert(
+        [TEST_LEAF, b"balances"].as_ref(),
+        b"d",
+        Element::new_item(vec![1]),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+    let balances_path = vec![TEST_LEAF.to_vec(), b"balances".to_vec()];
+
+    // Query the sum tree itself
+    let mut all_items = Query::new();
+    all_items.insert_all();
+    let path_query = PathQuery::new_unsized(balances_path.clone(), all_items.clone());
+    let proof = db.prove_query(&path_query).unwrap().unwrap();
+    let (root_hash, result_set, sums) =
+        GroveDb::verify_query_with_subtree_sums(&proof, &path_query).expect("should verify proof");
+    assert_eq!(root_hash, db.grove_db.root_hash(None).unwrap().unwrap());
+    assert_eq!(result_set.len(), 4);
+    assert_eq!(sums.get(&balances_path), Some(&12));
+
+    // Reach the sum tree through a subquery
+    let mut query = Query::new();
+    query.insert_key(b"balances".to_vec());
+    query.set_subquery(all_items);
+    let path_query = PathQuery::new_unsized(vec![TEST_LEAF.to_vec()], query);
+    let proof = db.prove_query(&path_query).unwrap().unwrap();
+    let (_, result_set, sums) =
+        GroveDb::verify_query_with_subtree_sums(&proof, &path_query).expect("should verify proof");
+    assert_eq!(result_set.len(), 4);
+    assert_eq!(sums.get(&balances_path), Some(&12));
+
+    // A partial query still carries the committed sum
+    let mut query = Query::new();
+    query.insert_key(b"a".to_vec());
+    let path_query = PathQuery::new_unsized(balances_path.clone(), query);
+    let proof = db.prove_query(&path_query).unwrap().unwrap();
+    let (_, result_set, sums) =
+        GroveDb::verify_query_with_subtree_sums(&proof, &path_query).expect("should verify proof");
+    assert_eq!(result_set.len(), 1);
+    assert_eq!(sums.get(&balances_path), Some(&12));
+}
+
+#[test]
+fn test_aggregate_queries_with_proofs() {
+    let db = make_test_grovedb();
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"key",
+        Element::empty_sum_tree(),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert tree");
+    for (key, value) in [(b"a", 5), (b"b", 7), (b"c", -3), (b"d", 11), (b"e", 2)] {
+        db.insert(
+            [TEST_LEAF, b"key"].as_ref(),
+            key,
+            Element::new_sum_item(value),
+            None,
+            None,
+        )
+        .unwrap()
+        .expect("should insert item");
+    }
+    let root_hash = db.root_hash(None).unwrap().unwrap();
+
+    let mut query = Query::new();
+    query.insert_range_inclusive(b"b".to_vec()..=b"d".to_vec());
+    let path_query = PathQuery::new_unsized(vec![TEST_LEAF.to_vec(), b"key".to_vec()], query);
+
+    for (aggregate, expected) in [(Aggregate::Count, 3), (Aggregate::Sum, 15)] {
+        let value = db
+            .query_aggregate(&path_query, aggregate, None)
+            .unwrap()
+            .expect("should compute aggregate");
+        assert_eq!(value, expected);
+
+        let proof = db
+            .prove_aggregate(&path_query, aggregate)
+            .unwrap()
+            .expect("should prove aggregate");
+        let (hash, proven_value) = GroveDb::verify_aggregate(&proof, &path_query, aggregate)
+            .expect("should verify aggregate");
+        assert_eq!(hash, root_hash);
+        assert_eq!(proven_value, expected);
+    }
+
+    // Sums only cover sum items
+    db.insert(
+        [TEST_LEAF, b"key"].as_ref(),
+        b"c2",
+        Element::new_item(vec![1]),
+        None,
+        None,
+    )
+    .unwrap()
+    .expect("should insert item");
+    assert!(db
+        .query_aggregate(&path_query, Aggregate::Sum, None)
+        .unwrap()
+        .is_err());
+    let proof = db
+        .prove_aggregate(&path_query, Aggregate::Sum)
+        .unwrap()
+        .expect("should prove query");
+    assert!(matches!(
+        GroveDb::verify_aggregate(&proof, &path_query, Aggregate::Sum),
+        Err(Error::WrongElementType(_))
+    ));
+}
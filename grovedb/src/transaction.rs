@@ -0,0 +1,76 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Transactions
+
+use std::{ops::Deref, sync::Mutex};
+
+use grovedb_storage::rocksdb_storage::Tx;
+
+use crate::TransactionArg;
+
+/// Transaction of the storage of a GroveDB
+pub type StorageTransaction<'db> = Tx<'db>;
+
+/// A transaction of a GroveDB, with the stack of the savepoints set on it
+/// through [`crate::GroveDb::set_savepoint`]
+pub struct Transaction<'db> {
+    inner: StorageTransaction<'db>,
+    /// Savepoints set on the transaction, released ones being `false`
+    pub(crate) savepoints: Mutex<Vec<bool>>,
+}
+
+impl<'db> Transaction<'db> {
+    /// Wraps a storage transaction having no savepoint set
+    pub(crate) fn new(inner: StorageTransaction<'db>) -> Self {
+        Transaction {
+            inner,
+            savepoints: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Unwraps the storage transaction, to commit it
+    pub(crate) fn into_inner(self) -> StorageTransaction<'db> {
+        self.inner
+    }
+}
+
+impl<'db> Deref for Transaction<'db> {
+    type Target = StorageTransaction<'db>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+/// Storage transaction of an optional transaction
+pub(crate) fn storage_transaction<'db, 'a>(
+    transaction: TransactionArg<'db, 'a>,
+) -> Option<&'a StorageTransaction<'db>> {
+    transaction.map(|transaction| &transaction.inner)
+}
@@ -61,6 +61,7 @@ macro_rules! storage_context_with_parent_optional_tx {
     ) => {
         {
             use ::grovedb_storage::Storage;
+            use $crate::element::ElementGetExt;
             if let Some(tx) = $transaction {
                 let $storage = $db
                     .get_transactional_storage_context($path.clone(), $batch, tx)
@@ -86,7 +87,10 @@ macro_rules! storage_context_with_parent_optional_tx {
                             let $is_sum_tree = false;
                             $($body)*
                         }
-                        Element::SumTree(root_key, ..) => {
+                        Element::SumTree(root_key, ..)
+                        | Element::CountTree(root_key, ..)
+                        | Element::BigSumTree(root_key, ..)
+                        | Element::AggregateTree(root_key, ..) => {
                             let $root_key = root_key;
                             let $is_sum_tree = true;
                             $($body)*
@@ -127,7 +131,10 @@ macro_rules! storage_context_with_parent_optional_tx {
                             let $is_sum_tree = false;
                             $($body)*
                         }
-                        Element::SumTree(root_key, ..) => {
+                        Element::SumTree(root_key, ..)
+                        | Element::CountTree(root_key, ..)
+                        | Element::BigSumTree(root_key, ..)
+                        | Element::AggregateTree(root_key, ..) => {
                             let $root_key = root_key;
                             let $is_sum_tree = true;
                             $($body)*
@@ -77,6 +77,10 @@ pub enum Error {
     #[error("invalid proof error {0}")]
     InvalidProofError(String),
 
+    /// Proof limit or offset mismatch error
+    #[error("proof limit offset error {0}")]
+    ProofLimitOffsetError(&'static str),
+
     /// Proof creation error
     #[error("proof creation error {0}")]
     ProofCreationError(String),
@@ -118,5 +122,5 @@ pub enum Error {
     // Costs errors
     /// Costs errors
     #[error("costs error: {0}")]
-    CostsError(grovedb_costs::error::Error),
+    CostsError(#[from] grovedb_costs::error::Error),
 }
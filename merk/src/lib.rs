@@ -38,7 +38,10 @@ extern crate core;
 mod merk;
 
 #[cfg(feature = "full")]
-pub use crate::merk::{chunks::ChunkProducer, options::MerkOptions, restore::Restorer};
+pub use crate::merk::{
+    chunks::ChunkProducer, memory_budget::MemoryBudget, options::MerkOptions, restore::Restorer,
+    stats::TreeStats,
+};
 
 /// Provides a container type that allows temporarily taking ownership of a
 /// value.
@@ -68,9 +71,9 @@ pub mod estimated_costs;
 #[cfg(feature = "full")]
 mod visualize;
 
-#[cfg(feature = "full")]
+#[cfg(any(feature = "full", feature = "verify"))]
 pub use ed;
-#[cfg(feature = "full")]
+#[cfg(any(feature = "full", feature = "verify"))]
 pub use error::Error;
 #[cfg(any(feature = "full", feature = "verify"))]
 pub use proofs::query::execute_proof;
@@ -78,8 +81,8 @@ pub use proofs::query::execute_proof;
 pub use proofs::query::verify_query;
 #[cfg(feature = "full")]
 pub use tree::{
-    BatchEntry, Link, MerkBatch, Op, PanicSource, HASH_BLOCK_SIZE, HASH_BLOCK_SIZE_U32,
-    HASH_LENGTH, HASH_LENGTH_U32, HASH_LENGTH_U32_X2,
+    BatchEntry, Link, MerkBatch, Op, PanicSource, TreeAggregate, TreeAggregates, HASH_BLOCK_SIZE,
+    HASH_BLOCK_SIZE_U32, HASH_LENGTH, HASH_LENGTH_U32, HASH_LENGTH_U32_X2,
 };
 #[cfg(any(feature = "full", feature = "verify"))]
 pub use tree::{CryptoHash, TreeFeatureType};
@@ -0,0 +1,374 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Memory budget for tree nodes retained in memory
+
+#[cfg(feature = "full")]
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+#[cfg(feature = "full")]
+use grovedb_storage::StorageContext;
+
+#[cfg(feature = "full")]
+use super::Merk;
+#[cfg(feature = "full")]
+use crate::tree::{Link, Tree};
+
+#[cfg(feature = "full")]
+/// Limits the amount of decoded tree nodes retained in memory across all the
+/// Merks sharing it. When a commit brings the retained bytes over the limit,
+/// the least recently used Merks are pruned back to their root node, their
+/// children being replaced with `Link::Reference`s.
+#[derive(Debug)]
+pub struct MemoryBudget {
+    limit: usize,
+    state: Mutex<MemoryBudgetState>,
+}
+
+#[cfg(feature = "full")]
+#[derive(Debug, Default)]
+struct MemoryBudgetState {
+    next_id: u64,
+    clock: u64,
+    retained_bytes: usize,
+    merks: HashMap<u64, RetainedMerk>,
+}
+
+#[cfg(feature = "full")]
+#[derive(Debug, Default)]
+struct RetainedMerk {
+    bytes: usize,
+    last_used: u64,
+    prune_requested: bool,
+}
+
+#[cfg(feature = "full")]
+impl MemoryBudget {
+    /// New memory budget allowing `limit` bytes of retained tree nodes
+    pub fn new(limit: usize) -> Arc<Self> {
+        Arc::new(Self {
+            limit,
+            state: Mutex::new(MemoryBudgetState::default()),
+        })
+    }
+
+    /// Maximum amount of bytes of retained tree nodes
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Amount of bytes of tree nodes currently accounted as retained
+    pub fn retained_bytes(&self) -> usize {
+        self.state
+            .lock()
+            .expect("memory budget lock")
+            .retained_bytes
+    }
+
+    fn register(self: &Arc<Self>) -> MemoryBudgetHandle {
+        let mut state = self.state.lock().expect("memory budget lock");
+        let id = state.next_id;
+        state.next_id += 1;
+        state.merks.insert(id, RetainedMerk::default());
+        MemoryBudgetHandle {
+            budget: self.clone(),
+            id,
+        }
+    }
+}
+
+#[cfg(feature = "full")]
+/// Registration of a Merk within a `MemoryBudget`, released on drop
+#[derive(Debug)]
+pub(crate) struct MemoryBudgetHandle {
+    budget: Arc<MemoryBudget>,
+    id: u64,
+}
+
+#[cfg(feature = "full")]
+impl MemoryBudgetHandle {
+    /// Returns `true` once if the Merk was chosen to be pruned since it was
+    /// last used
+    fn take_prune_request(&self) -> bool {
+        let mut state = self.budget.state.lock().expect("memory budget lock");
+        state
+            .merks
+            .get_mut(&self.id)
+            .map(|merk| std::mem::take(&mut merk.prune_requested))
+            .unwrap_or_default()
+    }
+
+    /// Records the bytes retained by the Merk and, if the budget is exceeded,
+    /// requests the least recently used Merks to be pruned until it is not.
+    /// Pruning requests are served at the next use of the requested Merk,
+    /// whose bytes are no longer accounted meanwhile. Returns `true` if the
+    /// Merk itself has to be pruned.
+    fn record(&self, bytes: usize) -> bool {
+        let mut state = self.budget.state.lock().expect("memory budget lock");
+        state.clock += 1;
+        let clock = state.clock;
+        let previous_bytes = state
+            .merks
+            .insert(
+                self.id,
+                RetainedMerk {
+                    bytes,
+                    last_used: clock,
+                    prune_requested: false,
+                },
+            )
+            .map(|merk| merk.bytes)
+            .unwrap_or_default();
+        state.retained_bytes = state.retained_bytes - previous_bytes + bytes;
+
+        let mut prune_self = false;
+        while state.retained_bytes > self.budget.limit {
+            let Some((&id, _)) = state
+                .merks
+                .iter()
+                .filter(|(_, merk)| merk.bytes > 0)
+                .min_by_key(|(_, merk)| merk.last_used)
+            else {
+                break;
+            };
+            let merk = state.merks.get_mut(&id).expect("merk is registered");
+            let freed_bytes = std::mem::take(&mut merk.bytes);
+            if id == self.id {
+                prune_self = true;
+            } else {
+                merk.prune_requested = true;
+            }
+            state.retained_bytes -= freed_bytes;
+        }
+        prune_self
+    }
+
+    /// Records the bytes retained by the Merk after pruning, without
+    /// triggering pruning of other Merks
+    fn record_pruned(&self, bytes: usize) {
+        let mut state = self.budget.state.lock().expect("memory budget lock");
+        if let Some(merk) = state.merks.get_mut(&self.id) {
+            let previous_bytes = std::mem::replace(&mut merk.bytes, bytes);
+            state.retained_bytes = state.retained_bytes - previous_bytes + bytes;
+        }
+    }
+}
+
+#[cfg(feature = "full")]
+impl Drop for MemoryBudgetHandle {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.budget.state.lock() {
+            if let Some(merk) = state.merks.remove(&self.id) {
+                state.retained_bytes -= merk.bytes;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "full")]
+impl<'db, S> Merk<S>
+where
+    S: StorageContext<'db>,
+{
+    /// Makes the Merk account its retained tree nodes within the given memory
+    /// budget. Nothing changes if the Merk already uses this budget.
+    pub fn set_memory_budget(&mut self, budget: Arc<MemoryBudget>) {
+        if let Some(handle) = &self.memory_budget {
+            if Arc::ptr_eq(&handle.budget, &budget) {
+                return;
+            }
+        }
+        self.memory_budget = Some(budget.register());
+    }
+
+    /// Accounts the tree nodes retained after a commit within the memory
+    /// budget, pruning the tree if the budget chose this Merk to be pruned
+    pub(crate) fn enforce_memory_budget(&self) {
+        let Some(handle) = &self.memory_budget else {
+            return;
+        };
+        self.use_tree_mut(|maybe_tree| {
+            let bytes = maybe_tree
+                .as_deref()
+                .map(retained_bytes)
+                .unwrap_or_default();
+            if handle.record(bytes) {
+                if let Some(tree) = maybe_tree {
+                    prune_loaded(tree);
+                    handle.record_pruned(retained_bytes(tree));
+                }
+            }
+        });
+    }
+
+    /// Takes the tree out of its cell, pruning it first if the memory budget
+    /// requested so
+    pub(super) fn take_tree(&self) -> Option<Tree> {
+        let mut tree = self.tree.take();
+        if let (Some(tree), Some(handle)) = (tree.as_mut(), &self.memory_budget) {
+            if handle.take_prune_request() {
+                prune_loaded(tree);
+                handle.record_pruned(retained_bytes(tree));
+            }
+        }
+        tree
+    }
+}
+
+#[cfg(feature = "full")]
+/// Sum of the encoding lengths of the tree nodes held in memory
+fn retained_bytes(tree: &Tree) -> usize {
+    let children_bytes: usize = [true, false]
+        .into_iter()
+        .filter_map(|left| tree.link(left).and_then(Link::tree))
+        .map(retained_bytes)
+        .sum();
+    tree.encoding_length() + children_bytes
+}
+
+#[cfg(feature = "full")]
+/// Replaces every loaded child link with a reference, keeping the nodes that
+/// have pending changes
+fn prune_loaded(tree: &mut Tree) {
+    for left in [true, false] {
+        let slot = tree.slot_mut(left);
+        if matches!(slot, Some(Link::Loaded { .. })) {
+            *slot = slot.take().map(Link::into_reference);
+        } else if let Some(Link::Modified { tree, .. } | Link::Uncommitted { tree, .. }) = slot {
+            prune_loaded(tree);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use grovedb_path::SubtreePath;
+    use grovedb_storage::{
+        rocksdb_storage::{test_utils::TempStorage, PrefixedRocksDbImmediateStorageContext},
+        Storage,
+    };
+
+    use super::*;
+    use crate::test_utils::*;
+
+    fn is_pruned(merk: &Merk<PrefixedRocksDbImmediateStorageContext>) -> bool {
+        merk.use_tree(|maybe_tree| {
+            let tree = maybe_tree.expect("tree should exist");
+            [true, false]
+                .into_iter()
+                .filter_map(|left| tree.link(left))
+                .all(|link| matches!(link, Link::Reference { .. }))
+        })
+    }
+
+    #[test]
+    fn test_memory_budget_prunes_least_recently_used() {
+        let storage = TempStorage::new();
+        let tx = storage.start_transaction().unwrap();
+        let budget = MemoryBudget::new(12_000);
+
+        let mut first = Merk::open_base(
+            storage
+                .get_immediate_storage_context(SubtreePath::from([b"first"].as_ref()), &tx)
+                .unwrap(),
+            false,
+        )
+        .unwrap()
+        .unwrap();
+        first.set_memory_budget(budget.clone());
+        first
+            .apply::<_, Vec<_>>(&make_batch_seq(0..50), &[], None)
+            .unwrap()
+            .expect("apply failed");
+        let first_bytes = budget.retained_bytes();
+        assert!(first_bytes > 0 && first_bytes <= budget.limit());
+
+        let mut second = Merk::open_base(
+            storage
+                .get_immediate_storage_context(SubtreePath::from([b"second"].as_ref()), &tx)
+                .unwrap(),
+            false,
+        )
+        .unwrap()
+        .unwrap();
+        second.set_memory_budget(budget.clone());
+        second
+            .apply::<_, Vec<_>>(&make_batch_seq(0..50), &[], None)
+            .unwrap()
+            .expect("apply failed");
+        assert!(budget.retained_bytes() <= budget.limit());
+        assert!(!is_pruned(&second));
+
+        // The first Merk was the least recently used, so it is pruned on its next use
+        assert!(is_pruned(&first));
+        assert!(first
+            .get(&seq_key(10), true)
+            .unwrap()
+            .expect("get failed")
+            .is_some());
+
+        drop(second);
+        assert!(budget.retained_bytes() < first_bytes);
+    }
+
+    #[test]
+    fn test_memory_budget_prunes_committing_merk() {
+        let storage = TempStorage::new();
+        let tx = storage.start_transaction().unwrap();
+        let budget = MemoryBudget::new(1);
+
+        let mut merk = Merk::open_base(
+            storage
+                .get_immediate_storage_context(SubtreePath::empty(), &tx)
+                .unwrap(),
+            false,
+        )
+        .unwrap()
+        .unwrap();
+        merk.set_memory_budget(budget.clone());
+        merk.apply::<_, Vec<_>>(&make_batch_seq(0..20), &[], None)
+            .unwrap()
+            .expect("apply failed");
+        assert!(is_pruned(&merk));
+        let root_hash = merk.root_hash().unwrap();
+
+        merk.apply::<_, Vec<_>>(&make_batch_seq(20..40), &[], None)
+            .unwrap()
+            .expect("apply failed");
+        assert!(is_pruned(&merk));
+        assert_ne!(merk.root_hash().unwrap(), root_hash);
+        assert!(merk
+            .get(&seq_key(5), true)
+            .unwrap()
+            .expect("get failed")
+            .is_some());
+    }
+}
@@ -32,15 +32,22 @@ pub mod chunks;
 
 pub(crate) mod defaults;
 
+pub mod memory_budget;
+
 pub mod options;
 
+pub mod prefetch;
+
 pub mod restore;
 
+pub mod stats;
+
 use std::{
     cell::Cell,
     cmp::Ordering,
     collections::{BTreeSet, LinkedList},
     fmt,
+    sync::Arc,
 };
 
 use grovedb_costs::{
@@ -58,13 +65,14 @@ use crate::{
     error::Error,
     merk::{
         defaults::{MAX_UPDATE_VALUE_BASED_ON_COSTS_TIMES, ROOT_KEY_KEY},
+        memory_budget::MemoryBudgetHandle,
         options::MerkOptions,
     },
     proofs::{encode_into, query::query_item::QueryItem, Op as ProofOp, Query},
     tree::{
         kv::{ValueDefinedCostType, KV},
-        AuxMerkBatch, Commit, CryptoHash, Fetch, Link, MerkBatch, Op, RefWalker, Tree, Walker,
-        NULL_HASH,
+        AuxMerkBatch, Commit, CryptoHash, Fetch, Link, MerkBatch, Op, RefWalker, Tree,
+        TreeAggregates, Walker, NULL_HASH,
     },
     Error::{CostsError, EdError, StorageError},
     MerkType::{BaseMerk, LayeredMerk, StandaloneMerk},
@@ -152,7 +160,7 @@ pub type BatchValue = (
 pub type IsSumTree = bool;
 
 /// Root hash key and sum
-pub type RootHashKeyAndSum = (CryptoHash, Option<Vec<u8>>, Option<i64>);
+pub type RootHashKeyAndSum = (CryptoHash, Option<Vec<u8>>, Option<i128>);
 
 /// KVIterator allows you to lazily iterate over each kv pair of a subtree
 pub struct KVIterator<'a, I: RawIterator> {
@@ -179,32 +187,38 @@ impl<'a, I: RawIterator> KVIterator<'a, I> {
     }
 
     /// Returns the current node the iter points to if it's valid for the given
-    /// query item returns None otherwise
-    fn get_kv(&mut self, query_item: &QueryItem) -> CostContext<Option<(Vec<u8>, Vec<u8>)>> {
+    /// query item returns None otherwise. The value is only read from
+    /// storage, and charged, if `load_value` is set.
+    fn get_entry(
+        &mut self,
+        query_item: &QueryItem,
+        load_value: bool,
+    ) -> CostContext<Option<(Vec<u8>, Option<Vec<u8>>)>> {
         let mut cost = OperationCost::default();
 
         if query_item
             .iter_is_valid_for_type(&self.raw_iter, None, self.left_to_right)
             .unwrap_add_cost(&mut cost)
         {
-            let kv = (
-                self.raw_iter
-                    .key()
-                    .unwrap_add_cost(&mut cost)
-                    .expect("key must exist as iter is valid")
-                    .to_vec(),
+            let key = self
+                .raw_iter
+                .key()
+                .unwrap_add_cost(&mut cost)
+                .expect("key must exist as iter is valid")
+                .to_vec();
+            let value = load_value.then(|| {
                 self.raw_iter
                     .value()
                     .unwrap_add_cost(&mut cost)
                     .expect("value must exists as iter is valid")
-                    .to_vec(),
-            );
+                    .to_vec()
+            });
             if self.left_to_right {
                 self.raw_iter.next().unwrap_add_cost(&mut cost)
             } else {
                 self.raw_iter.prev().unwrap_add_cost(&mut cost)
             }
-            Some(kv).wrap_with_cost(cost)
+            Some((key, value)).wrap_with_cost(cost)
         } else {
             None.wrap_with_cost(cost)
         }
@@ -229,16 +243,30 @@ impl<'a, I: RawIterator> KVIterator<'a, I> {
 impl<'a, I: RawIterator> KVIterator<'a, I> {
     /// Next key-value
     pub fn next_kv(&mut self) -> CostContext<Option<(Vec<u8>, Vec<u8>)>> {
+        self.next_entry(true).map(|entry| {
+            entry.map(|(key, value)| (key, value.expect("value is loaded when requested")))
+        })
+    }
+
+    /// Next key, walking the keys without reading the values
+    pub fn next_key(&mut self) -> CostContext<Option<Vec<u8>>> {
+        self.next_entry(false)
+            .map(|entry| entry.map(|(key, _)| key))
+    }
+
+    fn next_entry(&mut self, load_value: bool) -> CostContext<Option<(Vec<u8>, Option<Vec<u8>>)>> {
         let mut cost = OperationCost::default();
 
         if let Some(query_item) = self.current_query_item {
-            let kv_pair = self.get_kv(query_item).unwrap_add_cost(&mut cost);
+            let entry = self
+                .get_entry(query_item, load_value)
+                .unwrap_add_cost(&mut cost);
 
-            if kv_pair.is_some() {
-                kv_pair.wrap_with_cost(cost)
+            if entry.is_some() {
+                entry.wrap_with_cost(cost)
             } else {
                 self.seek().unwrap_add_cost(&mut cost);
-                self.next_kv().add_cost(cost)
+                self.next_entry(load_value).add_cost(cost)
             }
         } else {
             None.wrap_with_cost(cost)
@@ -280,6 +308,18 @@ pub struct Merk<S> {
     pub merk_type: MerkType,
     /// Is sum tree?
     pub is_sum_tree: bool,
+    /// Is count tree? A count tree is a sum tree in which every element
+    /// counts as one, its sum being the number of elements it holds
+    pub is_count_tree: bool,
+    /// Is big sum tree? A big sum tree is a sum tree whose nodes carry 128 bit
+    /// sums
+    pub is_big_sum_tree: bool,
+    /// Id of the aggregate maintained by the nodes of an aggregated Merk
+    pub aggregate_id: Option<u8>,
+    /// Aggregates the nodes of an aggregated Merk are folded with
+    pub tree_aggregates: Option<Arc<TreeAggregates>>,
+    /// Memory budget accounting the tree nodes retained by this Merk
+    pub(crate) memory_budget: Option<MemoryBudgetHandle>,
 }
 
 impl<S> fmt::Debug for Merk<S> {
@@ -311,6 +351,11 @@ where
             storage,
             merk_type,
             is_sum_tree,
+            is_count_tree: false,
+            is_big_sum_tree: false,
+            aggregate_id: None,
+            tree_aggregates: None,
+            memory_budget: None,
         }
     }
 
@@ -322,6 +367,11 @@ where
             storage,
             merk_type: StandaloneMerk,
             is_sum_tree,
+            is_count_tree: false,
+            is_big_sum_tree: false,
+            aggregate_id: None,
+            tree_aggregates: None,
+            memory_budget: None,
         };
 
         merk.load_base_root().map_ok(|_| merk)
@@ -335,6 +385,11 @@ where
             storage,
             merk_type: BaseMerk,
             is_sum_tree,
+            is_count_tree: false,
+            is_big_sum_tree: false,
+            aggregate_id: None,
+            tree_aggregates: None,
+            memory_budget: None,
         };
 
         merk.load_base_root().map_ok(|_| merk)
@@ -352,6 +407,11 @@ where
             storage,
             merk_type: LayeredMerk,
             is_sum_tree,
+            is_count_tree: false,
+            is_big_sum_tree: false,
+            aggregate_id: None,
+            tree_aggregates: None,
+            memory_budget: None,
         };
 
         merk.load_root().map_ok(|_| merk)
@@ -424,6 +484,18 @@ where
         }
     }
 
+    /// Gets the values for the given keys, in the order of the keys with
+    /// `None` for the keys not found. The nodes are looked up in storage all at
+    /// once, bypassing the tree.
+    pub fn get_many<K: AsRef<[u8]>>(&self, keys: &[K]) -> CostResult<Vec<Option<Vec<u8>>>, Error> {
+        Tree::get_many(&self.storage, keys).map_ok(|nodes| {
+            nodes
+                .into_iter()
+                .map(|node| node.map(|node| node.value_as_slice().to_vec()))
+                .collect()
+        })
+    }
+
     /// Returns the feature type for the node at the given key.
     pub fn get_feature_type(
         &self,
@@ -534,8 +606,9 @@ where
                 let maybe_child = link.tree();
                 match maybe_child {
                     None => {
-                        // fetch from RocksDB
-                        break self.has_node_direct(key);
+                        // fetch from RocksDB, seeing the batch as the node may have been pruned
+                        // before the batch was committed
+                        break Tree::get_staged(&self.storage, key).map_ok(|x| x.is_some());
                     }
                     Some(child) => cursor = child, // traverse to child
                 }
@@ -554,6 +627,17 @@ where
         })
     }
 
+    /// Generic way to get a node's field, seeing writes deferred in the batch
+    fn get_node_staged_fn<T, F>(&self, key: &[u8], f: F) -> CostResult<Option<T>, Error>
+    where
+        F: FnOnce(&Tree) -> CostContext<T>,
+    {
+        Tree::get_staged(&self.storage, key).flat_map_ok(|maybe_node| {
+            let mut cost = OperationCost::default();
+            Ok(maybe_node.map(|node| f(&node).unwrap_add_cost(&mut cost))).wrap_with_cost(cost)
+        })
+    }
+
     /// Generic way to get a node's field
     fn get_node_fn<T, F>(&self, key: &[u8], f: F) -> CostResult<Option<T>, Error>
     where
@@ -579,8 +663,9 @@ where
                 let maybe_child = link.tree();
                 match maybe_child {
                     None => {
-                        // fetch from RocksDB
-                        break self.get_node_direct_fn(key, f);
+                        // fetch from RocksDB, seeing the batch as the node may have been pruned
+                        // before the batch was committed
+                        break self.get_node_staged_fn(key, f);
                     }
                     Some(child) => cursor = child, // traverse to child
                 }
@@ -599,11 +684,12 @@ where
         })
     }
 
-    /// Returns the total sum value in the Merk tree
-    pub fn sum(&self) -> Result<Option<i64>, Error> {
+    /// Returns the total sum value in the Merk tree, or its aggregate for
+    /// aggregated Merks
+    pub fn sum(&self) -> Result<Option<i128>, Error> {
         self.use_tree(|tree| match tree {
             None => Ok(None),
-            Some(tree) => tree.sum(),
+            Some(tree) => tree.sum_with_aggregates(self.tree_aggregates.as_deref()),
         })
     }
 
@@ -618,7 +704,9 @@ where
         self.use_tree(|tree| match tree {
             None => Ok((NULL_HASH, None, None)).wrap_with_cost(Default::default()),
             Some(tree) => {
-                let sum = cost_return_on_error_default!(tree.sum());
+                let sum = cost_return_on_error_default!(
+                    tree.sum_with_aggregates(self.tree_aggregates.as_deref())
+                );
                 tree.hash()
                     .map(|hash| Ok((hash, Some(tree.key().to_vec()), sum)))
             }
@@ -888,9 +976,7 @@ where
         R: FnMut(&Vec<u8>, u32, u32) -> Result<(StorageRemovedBytes, StorageRemovedBytes), Error>,
     {
         let maybe_walker = self
-            .tree
-            .take()
-            .take()
+            .take_tree()
             .map(|tree| Walker::new(tree, self.source()));
 
         Walker::apply_to(
@@ -915,6 +1001,65 @@ where
         })
     }
 
+    /// Applies `f` to the value stored at `key` in place and commits the
+    /// result. The node's value hash and kv hash are recomputed and every
+    /// ancestor on the path to the root is marked as `Link::Modified`, so
+    /// small in-place updates such as flag changes don't require re-inserting
+    /// the whole value. The node's feature type is kept as is.
+    ///
+    /// Returns `false` without touching the tree if the key doesn't exist.
+    pub fn value_mut_with_invalidation<F>(
+        &mut self,
+        key: &[u8],
+        f: F,
+        options: Option<MerkOptions>,
+    ) -> CostResult<bool, Error>
+    where
+        F: FnOnce(&mut Vec<u8>),
+    {
+        let mut cost = OperationCost::default();
+
+        let walker = match self.take_tree() {
+            Some(tree) => Walker::new(tree, self.source()),
+            None => return Ok(false).wrap_with_cost(cost),
+        };
+        let (walker, found) =
+            cost_return_on_error!(&mut cost, walker.value_mut_at_key_with_invalidation(key, f));
+        self.tree.set(Some(walker.into_inner()));
+        if !found {
+            return Ok(false).wrap_with_cost(cost);
+        }
+
+        let use_sum_nodes = self.is_sum_tree;
+        let aux: &AuxMerkBatch<Vec<u8>> = &[];
+        self.commit(
+            KeyUpdates::new(
+                BTreeSet::default(),
+                BTreeSet::from([key.to_vec()]),
+                LinkedList::default(),
+                None,
+            ),
+            aux,
+            options,
+            &|key, value| {
+                Ok(KV::layered_value_byte_cost_size_for_key_and_value_lengths(
+                    key.len() as u32,
+                    value.len() as u32,
+                    use_sum_nodes,
+                ))
+            },
+            &mut |_costs, _old_value, _value| Ok((false, None)),
+            &mut |_a, key_bytes_to_remove, value_bytes_to_remove| {
+                Ok((
+                    BasicStorageRemoval(key_bytes_to_remove),
+                    BasicStorageRemoval(value_bytes_to_remove),
+                ))
+            },
+        )
+        .map_ok(|_| found)
+        .add_cost(cost)
+    }
+
     /// Creates a Merkle proof for the list of queried keys. For each key in the
     /// query, if the key is found in the store then the value will be proven to
     /// be in the tree. For each key in the query that does not exist in the
@@ -1039,7 +1184,8 @@ where
 
             if let Some(tree) = maybe_tree {
                 // TODO: configurable committer
-                let mut committer = MerkCommitter::new(tree.height(), 100);
+                let mut committer =
+                    MerkCommitter::new(tree.height(), 100, self.tree_aggregates.clone());
                 cost_return_on_error!(
                     &mut inner_cost,
                     tree.commit(
@@ -1147,6 +1293,8 @@ where
             };
         }
 
+        self.enforce_memory_budget();
+
         // write to db
         self.storage
             .commit_batch(batch)
@@ -1156,7 +1304,7 @@ where
 
     /// Walk
     pub fn walk<'s, T>(&'s self, f: impl FnOnce(Option<RefWalker<MerkSource<'s, S>>>) -> T) -> T {
-        let mut tree = self.tree.take();
+        let mut tree = self.take_tree();
         let maybe_walker = tree
             .as_mut()
             .map(|tree| RefWalker::new(tree, self.source()));
@@ -1171,6 +1319,27 @@ where
         iter.seek_to_first().flat_map(|_| iter.valid().map(|x| !x))
     }
 
+    /// Checks if the tree has fewer than `limit` elements, looking at no more
+    /// than `limit` of them
+    pub fn has_fewer_elements_than(&self, limit: usize) -> CostContext<bool> {
+        let mut cost = OperationCost::default();
+        if limit == 0 {
+            return false.wrap_with_cost(cost);
+        }
+
+        let mut iter = self.storage.raw_iter();
+        iter.seek_to_first().unwrap_add_cost(&mut cost);
+        let mut count = 0;
+        while iter.valid().unwrap_add_cost(&mut cost) {
+            count += 1;
+            if count >= limit {
+                return false.wrap_with_cost(cost);
+            }
+            iter.next().unwrap_add_cost(&mut cost);
+        }
+        true.wrap_with_cost(cost)
+    }
+
     /// Checks if it's an empty tree excluding exceptions
     pub fn is_empty_tree_except(&self, mut except_keys: BTreeSet<&[u8]>) -> CostContext<bool> {
         let mut cost = OperationCost::default();
@@ -1195,14 +1364,14 @@ where
 
     /// Use tree
     pub(crate) fn use_tree<T>(&self, f: impl FnOnce(Option<&Tree>) -> T) -> T {
-        let tree = self.tree.take();
+        let tree = self.take_tree();
         let res = f(tree.as_ref());
         self.tree.set(tree);
         res
     }
 
     fn use_tree_mut<T>(&self, mut f: impl FnMut(Option<&mut Tree>) -> T) -> T {
-        let mut tree = self.tree.take();
+        let mut tree = self.take_tree();
         let res = f(tree.as_mut());
         self.tree.set(tree);
         res
@@ -1315,7 +1484,7 @@ where
     S: StorageContext<'db>,
 {
     fn fetch(&self, link: &Link) -> CostResult<Tree, Error> {
-        Tree::get(self.storage, link.key())
+        Tree::get_staged(self.storage, link.key())
             .map_ok(|x| x.ok_or(Error::KeyNotFoundError("Key not found for fetch")))
             .flatten()
     }
@@ -1328,14 +1497,16 @@ struct MerkCommitter {
     batch: Vec<BatchValue>,
     height: u8,
     levels: u8,
+    tree_aggregates: Option<Arc<TreeAggregates>>,
 }
 
 impl MerkCommitter {
-    fn new(height: u8, levels: u8) -> Self {
+    fn new(height: u8, levels: u8, tree_aggregates: Option<Arc<TreeAggregates>>) -> Self {
         Self {
             batch: Vec::with_capacity(10000),
             height,
             levels,
+            tree_aggregates,
         }
     }
 }
@@ -1429,6 +1600,10 @@ impl Commit for MerkCommitter {
         let prune = (self.height - tree.height()) >= self.levels;
         (prune, prune)
     }
+
+    fn tree_aggregates(&self) -> Option<&TreeAggregates> {
+        self.tree_aggregates.as_deref()
+    }
 }
 
 #[cfg(test)]
@@ -1563,6 +1738,53 @@ mod test {
         );
     }
 
+    #[test]
+    fn value_mut_with_invalidation() {
+        let batch = make_batch_seq(0..20);
+        let mut merk = TempMerk::new();
+        merk.apply::<_, Vec<_>>(&batch, &[], None)
+            .unwrap()
+            .expect("apply failed");
+
+        let root_hash = merk.root_hash().unwrap();
+        let found = merk
+            .value_mut_with_invalidation(&seq_key(100), |value| value.push(0), None)
+            .unwrap()
+            .expect("should not error on a missing key");
+        assert!(!found);
+        assert_eq!(merk.root_hash().unwrap(), root_hash);
+
+        let found = merk
+            .value_mut_with_invalidation(&seq_key(3), |value| value[0] = 7, None)
+            .unwrap()
+            .expect("should update value in place");
+        assert!(found);
+        assert_invariants(&merk);
+
+        let mut expected_value = vec![123; 60];
+        expected_value[0] = 7;
+        assert_eq!(
+            merk.get(&seq_key(3), true).unwrap().expect("should get"),
+            Some(expected_value.clone())
+        );
+
+        let mut reference = TempMerk::new();
+        reference
+            .apply::<_, Vec<_>>(&batch, &[], None)
+            .unwrap()
+            .expect("apply failed");
+        reference
+            .apply::<_, Vec<_>>(
+                &[(seq_key(3).to_vec(), Op::Put(expected_value, BasicMerk))],
+                &[],
+                None,
+            )
+            .unwrap()
+            .expect("apply failed");
+        assert_ne!(merk.root_hash().unwrap(), root_hash);
+        assert_eq!(merk.root_hash().unwrap(), reference.root_hash().unwrap());
+    }
+
     #[test]
     fn insert_uncached() {
         let batch_size = 20;
@@ -0,0 +1,121 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Merk tree prefetching
+
+#[cfg(feature = "full")]
+use grovedb_costs::{cost_return_on_error, CostResult, CostsExt, OperationCost};
+#[cfg(feature = "full")]
+use grovedb_storage::{RawIterator, StorageContext};
+
+#[cfg(feature = "full")]
+use super::Merk;
+#[cfg(feature = "full")]
+use crate::{
+    tree::{Fetch, Tree},
+    Error,
+};
+
+#[cfg(feature = "full")]
+impl<'db, S> Merk<S>
+where
+    S: StorageContext<'db>,
+{
+    /// Touches the top `levels` levels of the tree, fetching from storage
+    /// every node that is not loaded in memory, so that subsequent reads of
+    /// those nodes are served by the storage caches. Returns the number of
+    /// nodes visited.
+    pub fn prefetch(&self, levels: usize) -> CostResult<usize, Error> {
+        self.use_tree(|maybe_tree| match maybe_tree {
+            Some(tree) if levels > 0 => self.prefetch_subtree(tree, levels - 1),
+            _ => Ok(0).wrap_with_cost(OperationCost::default()),
+        })
+    }
+
+    fn prefetch_subtree(&self, tree: &Tree, levels: usize) -> CostResult<usize, Error> {
+        let mut cost = OperationCost::default();
+        let mut visited = 1;
+        if levels == 0 {
+            return Ok(visited).wrap_with_cost(cost);
+        }
+        for left in [true, false] {
+            let Some(link) = tree.link(left) else {
+                continue;
+            };
+            visited += match link.tree() {
+                Some(child) => {
+                    cost_return_on_error!(&mut cost, self.prefetch_subtree(child, levels - 1))
+                }
+                None => {
+                    let child = cost_return_on_error!(&mut cost, self.source().fetch(link));
+                    cost_return_on_error!(&mut cost, self.prefetch_subtree(&child, levels - 1))
+                }
+            };
+        }
+        Ok(visited).wrap_with_cost(cost)
+    }
+
+    /// Reads every entry of the Merk's storage in key order, letting the
+    /// storage read ahead the blocks of the whole tree. Returns the number of
+    /// entries read.
+    pub fn read_ahead(&self) -> CostResult<usize, Error> {
+        let mut cost = OperationCost::default();
+        let mut entries = 0;
+        let mut iter = self.storage.raw_iter();
+        iter.seek_to_first().unwrap_add_cost(&mut cost);
+        while iter.valid().unwrap_add_cost(&mut cost) {
+            iter.value().unwrap_add_cost(&mut cost);
+            entries += 1;
+            iter.next().unwrap_add_cost(&mut cost);
+        }
+        Ok(entries).wrap_with_cost(cost)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_prefetch_levels() {
+        let mut merk = TempMerk::new();
+        assert_eq!(merk.prefetch(3).unwrap().expect("should prefetch"), 0);
+
+        let batch = make_batch_seq(0..100);
+        merk.apply::<_, Vec<_>>(&batch, &[], None)
+            .unwrap()
+            .expect("apply failed");
+        merk.commit();
+
+        assert_eq!(merk.prefetch(0).unwrap().expect("should prefetch"), 0);
+        assert_eq!(merk.prefetch(1).unwrap().expect("should prefetch"), 1);
+        assert_eq!(merk.prefetch(3).unwrap().expect("should prefetch"), 7);
+        assert_eq!(merk.prefetch(64).unwrap().expect("should prefetch"), 100);
+        assert_eq!(merk.read_ahead().unwrap().expect("should read ahead"), 100);
+    }
+}
@@ -403,7 +403,7 @@ mod tests {
 
     fn restore_test(batches: &[&MerkBatch<Vec<u8>>], expected_nodes: usize) {
         let storage = TempStorage::new();
-        let tx = storage.start_transaction();
+        let tx = storage.start_transaction().unwrap();
         let mut original = Merk::open_base(
             storage
                 .get_immediate_storage_context(SubtreePath::empty(), &tx)
@@ -422,7 +422,7 @@ mod tests {
         let chunks = original.chunks().unwrap();
 
         let storage = TempStorage::default();
-        let _tx2 = storage.start_transaction();
+        let _tx2 = storage.start_transaction().unwrap();
         let ctx = storage
             .get_immediate_storage_context(SubtreePath::empty(), &tx)
             .unwrap();
@@ -0,0 +1,183 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Merk tree shape statistics
+
+#[cfg(feature = "full")]
+use std::collections::BTreeMap;
+
+#[cfg(feature = "full")]
+use grovedb_costs::{cost_return_on_error, CostResult, CostsExt, OperationCost};
+#[cfg(feature = "full")]
+use grovedb_storage::StorageContext;
+
+#[cfg(feature = "full")]
+use super::Merk;
+#[cfg(feature = "full")]
+use crate::{
+    tree::{Fetch, Tree},
+    Error,
+};
+
+#[cfg(feature = "full")]
+/// Shape statistics of a Merk tree, gathered by walking every node
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TreeStats {
+    /// Height of the tree, 0 for an empty tree
+    pub height: u8,
+    /// Number of nodes in the tree
+    pub node_count: u64,
+    /// Sum of the sizes of all keys
+    pub total_key_size: u64,
+    /// Size of the largest key
+    pub max_key_size: u32,
+    /// Sum of the sizes of all values
+    pub total_value_size: u64,
+    /// Size of the largest value
+    pub max_value_size: u32,
+    /// Number of nodes for each balance factor (right child height minus left
+    /// child height)
+    pub balance_factors: BTreeMap<i8, u64>,
+}
+
+#[cfg(feature = "full")]
+impl TreeStats {
+    /// Average key size, 0 for an empty tree
+    pub fn average_key_size(&self) -> f64 {
+        if self.node_count == 0 {
+            0.0
+        } else {
+            self.total_key_size as f64 / self.node_count as f64
+        }
+    }
+
+    /// Average value size, 0 for an empty tree
+    pub fn average_value_size(&self) -> f64 {
+        if self.node_count == 0 {
+            0.0
+        } else {
+            self.total_value_size as f64 / self.node_count as f64
+        }
+    }
+
+    fn add_node(&mut self, tree: &Tree) {
+        let key_size = tree.key().len() as u32;
+        let value_size = tree.value_as_slice().len() as u32;
+        self.node_count += 1;
+        self.total_key_size += key_size as u64;
+        self.max_key_size = self.max_key_size.max(key_size);
+        self.total_value_size += value_size as u64;
+        self.max_value_size = self.max_value_size.max(value_size);
+        *self
+            .balance_factors
+            .entry(tree.balance_factor())
+            .or_default() += 1;
+    }
+}
+
+#[cfg(feature = "full")]
+impl<'db, S> Merk<S>
+where
+    S: StorageContext<'db>,
+{
+    /// Walks the whole tree and returns its shape statistics. Every node is
+    /// visited, so the cost grows linearly with the number of nodes. Nodes
+    /// that are not loaded in memory are fetched from storage for the walk
+    /// only, they stay unloaded afterwards.
+    pub fn stats(&self) -> CostResult<TreeStats, Error> {
+        self.use_tree(|maybe_tree| {
+            let mut cost = OperationCost::default();
+            let mut stats = TreeStats::default();
+            if let Some(tree) = maybe_tree {
+                stats.height = tree.height();
+                cost_return_on_error!(&mut cost, self.add_subtree_stats(tree, &mut stats));
+            }
+            Ok(stats).wrap_with_cost(cost)
+        })
+    }
+
+    fn add_subtree_stats(&self, tree: &Tree, stats: &mut TreeStats) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+        stats.add_node(tree);
+        for left in [true, false] {
+            let Some(link) = tree.link(left) else {
+                continue;
+            };
+            match link.tree() {
+                Some(child) => {
+                    cost_return_on_error!(&mut cost, self.add_subtree_stats(child, stats))
+                }
+                None => {
+                    let child = cost_return_on_error!(&mut cost, self.source().fetch(link));
+                    cost_return_on_error!(&mut cost, self.add_subtree_stats(&child, stats))
+                }
+            }
+        }
+        Ok(()).wrap_with_cost(cost)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_stats_of_empty_tree() {
+        let merk = TempMerk::new();
+        let stats = merk.stats().unwrap().expect("should get stats");
+        assert_eq!(stats.height, 0);
+        assert_eq!(stats.node_count, 0);
+        assert_eq!(stats.average_key_size(), 0.0);
+        assert!(stats.balance_factors.is_empty());
+    }
+
+    #[test]
+    fn test_stats_walk_committed_and_loaded_nodes() {
+        let mut merk = TempMerk::new();
+        let batch = make_batch_seq(0..100);
+        merk.apply::<_, Vec<_>>(&batch, &[], None)
+            .unwrap()
+            .expect("apply failed");
+
+        let stats = merk.stats().unwrap().expect("should get stats");
+        assert_eq!(stats.node_count, 100);
+        assert_eq!(stats.max_key_size, 8);
+        assert_eq!(stats.average_key_size(), 8.0);
+        assert_eq!(stats.max_value_size, 60);
+        assert_eq!(stats.average_value_size(), 60.0);
+        assert_eq!(stats.balance_factors.values().sum::<u64>(), 100);
+        assert!(stats.balance_factors.keys().all(|factor| factor.abs() <= 1));
+        assert!(stats.height >= 7 && stats.height <= 10);
+
+        // Committing reopens the Merk with only its root loaded, so the other
+        // nodes are fetched from storage
+        merk.commit();
+        let reopened_stats = merk.stats().unwrap().expect("should get stats");
+        assert_eq!(reopened_stats, stats);
+    }
+}
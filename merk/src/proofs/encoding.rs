@@ -413,6 +413,27 @@ impl Op {
             )),
         })
     }
+
+    #[cfg(any(feature = "full", feature = "verify"))]
+    /// Decodes the operator at the start of `bytes` along with its encoding
+    /// length, or returns `None` if `bytes` end before the operator does
+    pub(crate) fn decode_partial(bytes: &[u8]) -> Result<Option<(Self, usize)>, Error> {
+        match <Op as Decode>::decode(bytes) {
+            Ok(op) => {
+                let length = op.encoding_length();
+                Ok(Some((op, length)))
+            }
+            Err(EdError::IOError(error)) if error.kind() == std::io::ErrorKind::UnexpectedEof => {
+                Ok(None)
+            }
+            Err(EdError::UnexpectedByte(byte)) => Err(Error::ProofCreationError(format!(
+                "failed to decode an proofs::Op structure (UnexpectedByte: {byte})"
+            ))),
+            Err(EdError::IOError(error)) => Err(Error::ProofCreationError(format!(
+                "failed to decode an proofs::Op structure ({error})"
+            ))),
+        }
+    }
 }
 
 #[cfg(feature = "full")]
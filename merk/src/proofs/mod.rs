@@ -27,6 +27,10 @@
 // DEALINGS IN THE SOFTWARE.
 
 //! Merk proofs
+//!
+//! Proofs require the standard library: their operators are encoded with
+//! `ed` and varints from `integer-encoding`, both built on `std::io`, and
+//! their errors implement `std::error::Error`.
 
 #[cfg(feature = "full")]
 pub mod chunk;
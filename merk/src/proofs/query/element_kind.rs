@@ -0,0 +1,136 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Element kind rules
+//!
+//! A query can decide per query item what happens to the elements of a given
+//! kind it matches, for example descending only into trees of a subtree that
+//! also holds items.
+
+use std::collections::BTreeMap;
+
+use indexmap::IndexMap;
+
+use crate::proofs::{
+    query::{query_item::QueryItem, SubqueryBranch},
+    Query,
+};
+
+/// The kinds of elements a query can match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ElementKind {
+    /// An item
+    Item,
+    /// A reference
+    Reference,
+    /// A tree
+    Tree,
+    /// A sum item
+    SumItem,
+    /// A sum tree
+    SumTree,
+    /// A count tree
+    CountTree,
+    /// A big sum tree
+    BigSumTree,
+    /// An aggregate tree
+    AggregateTree,
+}
+
+/// What a query does with the elements of a kind it matches
+#[derive(Debug, Clone, PartialEq)]
+pub enum ElementKindAction {
+    /// The elements aren't returned, trees aren't descended into
+    Skip,
+    /// Trees are subqueried with this branch instead of the conditional or
+    /// default subquery branch, other elements are returned as they are
+    Subquery(SubqueryBranch),
+}
+
+impl Query {
+    /// Sets the action applied to the elements of `kind` matched by `item`.
+    /// If the elements of a key are given an action by several items, the
+    /// first one that was added is used. Elements without an action are
+    /// returned and subqueried as usual.
+    pub fn add_element_kind_action(
+        &mut self,
+        item: QueryItem,
+        kind: ElementKind,
+        action: ElementKindAction,
+    ) {
+        self.element_kind_actions
+            .get_or_insert_with(IndexMap::new)
+            .entry(item)
+            .or_default()
+            .insert(kind, action);
+    }
+
+    /// Returns the action applied to the element of `kind` at `key`, if any
+    pub fn element_kind_action(&self, key: &[u8], kind: ElementKind) -> Option<&ElementKindAction> {
+        self.element_kind_actions
+            .iter()
+            .flatten()
+            .filter(|(query_item, _)| query_item.contains(key))
+            .find_map(|(_, actions)| actions.get(&kind))
+    }
+
+    /// Adds the element kind actions of a query merged into this one, the
+    /// actions already set here come first
+    pub(super) fn merge_element_kind_actions(
+        &mut self,
+        element_kind_actions: Option<IndexMap<QueryItem, BTreeMap<ElementKind, ElementKindAction>>>,
+    ) {
+        for (item, actions) in element_kind_actions.into_iter().flatten() {
+            for (kind, action) in actions {
+                self.element_kind_actions
+                    .get_or_insert_with(IndexMap::new)
+                    .entry(item.clone())
+                    .or_default()
+                    .entry(kind)
+                    .or_insert(action);
+            }
+        }
+    }
+
+    /// Check if this query or any of its subqueries sets element kind actions
+    pub fn has_element_kind_actions(&self) -> bool {
+        let branch_has_actions = |branch: &SubqueryBranch| {
+            branch
+                .subquery
+                .as_ref()
+                .is_some_and(|subquery| subquery.has_element_kind_actions())
+        };
+        self.element_kind_actions.is_some()
+            || branch_has_actions(&self.default_subquery_branch)
+            || self
+                .conditional_subquery_branches
+                .iter()
+                .flatten()
+                .any(|(_, branch)| branch_has_actions(branch))
+    }
+}
@@ -443,10 +443,12 @@ impl Query {
                 mut items,
                 default_subquery_branch,
                 conditional_subquery_branches,
+                element_kind_actions,
                 ..
             } = query;
             // the searched for items are the union of all items
             merged_query.insert_items(items.clone());
+            merged_query.merge_element_kind_actions(element_kind_actions);
 
             // // We now need to deal with subqueries
             // let QueryItemManyIntersectionResult{ in_both, ours, theirs } =
@@ -502,9 +504,11 @@ impl Query {
             mut items,
             default_subquery_branch,
             conditional_subquery_branches,
+            element_kind_actions,
             ..
         } = other;
         self.insert_items(items.clone());
+        self.merge_element_kind_actions(element_kind_actions);
 
         // let intersection_result = QueryItem::intersect_many_ordered(&mut self.items,
         // items); // merge query items as they point to the same context
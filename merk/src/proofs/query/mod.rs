@@ -34,6 +34,8 @@ mod map;
 #[cfg(any(feature = "full", feature = "verify"))]
 mod common_path;
 #[cfg(any(feature = "full", feature = "verify"))]
+mod element_kind;
+#[cfg(any(feature = "full", feature = "verify"))]
 mod insert;
 #[cfg(any(feature = "full", feature = "verify"))]
 mod merge;
@@ -42,11 +44,13 @@ pub mod query_item;
 #[cfg(any(feature = "full", feature = "verify"))]
 mod verify;
 
-#[cfg(any(feature = "full", feature = "verify"))]
+#[cfg(feature = "full")]
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 
 #[cfg(any(feature = "full", feature = "verify"))]
+pub use element_kind::{ElementKind, ElementKindAction};
+#[cfg(feature = "full")]
 use grovedb_costs::{cost_return_on_error, CostContext, CostResult, CostsExt, OperationCost};
 #[cfg(any(feature = "full", feature = "verify"))]
 use indexmap::IndexMap;
@@ -56,14 +60,17 @@ pub use map::*;
 pub use query_item::intersect::QueryItemIntersectionResult;
 #[cfg(any(feature = "full", feature = "verify"))]
 pub use query_item::QueryItem;
-#[cfg(any(feature = "full", feature = "verify"))]
+#[cfg(feature = "full")]
 use verify::ProofAbsenceLimitOffset;
 #[cfg(any(feature = "full", feature = "verify"))]
-pub use verify::{execute_proof, verify_query, ProofVerificationResult, ProvedKeyValue};
+pub use verify::{
+    execute_keys_only_proof, execute_proof, verify_query, ProofVerificationResult, ProofVerifier,
+    ProvedKeyValue,
+};
 #[cfg(feature = "full")]
 use {super::Op, std::collections::LinkedList};
 
-#[cfg(any(feature = "full", feature = "verify"))]
+#[cfg(feature = "full")]
 use super::Node;
 #[cfg(any(feature = "full", feature = "verify"))]
 use crate::error::Error;
@@ -103,6 +110,8 @@ pub struct Query {
     pub default_subquery_branch: SubqueryBranch,
     /// Conditional subquery branches
     pub conditional_subquery_branches: Option<IndexMap<QueryItem, SubqueryBranch>>,
+    /// Actions applied to the elements of some kinds matched by a query item
+    pub element_kind_actions: Option<IndexMap<QueryItem, BTreeMap<ElementKind, ElementKindAction>>>,
     /// Left to right?
     pub left_to_right: bool,
 }
@@ -309,6 +318,7 @@ impl Query {
     }
 
     /// Get number of query items
+    #[cfg(feature = "full")]
     pub(crate) fn len(&self) -> usize {
         self.items.len()
     }
@@ -392,6 +402,15 @@ impl Query {
         if self.default_subquery_branch.subquery.is_some()
             || self.default_subquery_branch.subquery_path.is_some()
             || self.conditional_subquery_branches.is_some()
+            || self
+                .element_kind_actions
+                .iter()
+                .flatten()
+                .any(|(_, actions)| {
+                    actions
+                        .values()
+                        .any(|action| matches!(action, ElementKindAction::Subquery(_)))
+                })
         {
             return true;
         }
@@ -416,6 +435,7 @@ impl<Q: Into<QueryItem>> From<Vec<Q>> for Query {
                 subquery: None,
             },
             conditional_subquery_branches: None,
+            element_kind_actions: None,
             left_to_right: true,
         }
     }
@@ -5842,4 +5862,52 @@ mod test {
             .unwrap()
             .expect("verify failed");
     }
+
+    #[test]
+    fn streaming_verify() {
+        let mut tree = make_tree_seq(10);
+        let mut walker = RefWalker::new(&mut tree, PanicSource {});
+
+        let queryitems = vec![QueryItem::Range(
+            vec![0, 0, 0, 0, 0, 0, 0, 2]..vec![0, 0, 0, 0, 0, 0, 0, 8],
+        )];
+        let (proof, ..) = walker
+            .create_full_proof(queryitems.as_slice(), Some(4), None, true)
+            .unwrap()
+            .expect("create_proof errored");
+
+        let mut bytes = vec![];
+        encode_into(proof.iter(), &mut bytes);
+        let mut query = Query::new();
+        for item in queryitems {
+            query.insert_item(item);
+        }
+        let (expected_hash, expected) =
+            verify::execute_proof(bytes.as_slice(), &query, Some(4), None, true)
+                .unwrap()
+                .unwrap();
+        assert_eq!(expected_hash, tree.hash().unwrap());
+        assert_eq!(expected.result_set.len(), 4);
+
+        // Whatever the chunks the proof is split into, the result is the same
+        for chunk_size in 1..=bytes.len() {
+            let mut verifier = ProofVerifier::new(&query, Some(4), None, true);
+            let mut result_set = vec![];
+            for chunk in bytes.chunks(chunk_size) {
+                result_set.extend(verifier.feed(chunk).unwrap().unwrap());
+            }
+            assert_eq!(verifier.limit(), expected.limit);
+            assert_eq!(verifier.offset(), expected.offset);
+            assert_eq!(verifier.finish().unwrap().unwrap(), expected_hash);
+            assert_eq!(result_set, expected.result_set);
+        }
+
+        // A truncated proof is rejected once finished
+        let mut verifier = ProofVerifier::new(&query, Some(4), None, true);
+        verifier
+            .feed(&bytes[..bytes.len() - 1])
+            .unwrap()
+            .expect("feed errored");
+        assert!(verifier.finish().unwrap().is_err());
+    }
 }
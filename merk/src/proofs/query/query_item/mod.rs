@@ -3,13 +3,12 @@ pub mod intersect;
 mod merge;
 
 use std::{
-    cmp,
     cmp::Ordering,
     hash::Hash,
     ops::{Range, RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive},
 };
 
-#[cfg(any(feature = "full", feature = "verify"))]
+#[cfg(feature = "full")]
 use grovedb_costs::{CostContext, CostsExt, OperationCost};
 #[cfg(feature = "full")]
 use grovedb_storage::RawIterator;
@@ -378,8 +377,8 @@ impl QueryItem {
         }
     }
 
-    #[cfg(any(feature = "full", feature = "verify"))]
-    fn compare(a: &[u8], b: &[u8]) -> cmp::Ordering {
+    #[cfg(feature = "full")]
+    fn compare(a: &[u8], b: &[u8]) -> Ordering {
         for (ai, bi) in a.iter().zip(b.iter()) {
             match ai.cmp(bi) {
                 Ordering::Equal => continue,
@@ -1,17 +1,24 @@
+#[cfg(feature = "full")]
 use std::collections::LinkedList;
 
-use grovedb_costs::{cost_return_on_error, CostResult, CostsExt, OperationCost};
+use grovedb_costs::{
+    cost_return_on_error, cost_return_on_error_no_add, CostResult, CostsExt, OperationCost,
+};
 
 #[cfg(feature = "full")]
-use crate::proofs::query::{Map, MapBuilder};
+use crate::proofs::{
+    query::{Map, MapBuilder},
+    tree::execute,
+    Decoder,
+};
 use crate::{
     error::Error,
-    proofs::{tree::execute, Decoder, Node, Op, Query},
+    proofs::{query::QueryItem, tree::ProofExecutor, Node, Op, Query},
     tree::value_hash,
     CryptoHash as MerkHash, CryptoHash,
 };
 
-#[cfg(any(feature = "full", feature = "verify"))]
+#[cfg(feature = "full")]
 pub type ProofAbsenceLimitOffset = (LinkedList<Op>, (bool, bool), Option<u16>, Option<u16>);
 
 #[cfg(feature = "full")]
@@ -54,222 +61,407 @@ pub fn execute_proof(
     limit: Option<u16>,
     offset: Option<u16>,
     left_to_right: bool,
+) -> CostResult<(MerkHash, ProofVerificationResult), Error> {
+    execute_proof_internal(bytes, query, limit, offset, left_to_right, false)
+}
+
+#[cfg(any(feature = "full", feature = "verify"))]
+/// Verifies the encoded keys only proof with the given query
+///
+/// A keys only proof carries the key and value hash of the matched nodes but
+/// not their value. The returned proven key/value pairs have empty values, the
+/// value hash of each key being its `proof`.
+pub fn execute_keys_only_proof(
+    bytes: &[u8],
+    query: &Query,
+    limit: Option<u16>,
+    offset: Option<u16>,
+    left_to_right: bool,
+) -> CostResult<(MerkHash, ProofVerificationResult), Error> {
+    execute_proof_internal(bytes, query, limit, offset, left_to_right, true)
+}
+
+#[cfg(any(feature = "full", feature = "verify"))]
+fn execute_proof_internal(
+    bytes: &[u8],
+    query: &Query,
+    limit: Option<u16>,
+    offset: Option<u16>,
+    left_to_right: bool,
+    keys_only: bool,
 ) -> CostResult<(MerkHash, ProofVerificationResult), Error> {
     let mut cost = OperationCost::default();
 
-    let mut output = Vec::with_capacity(query.len());
-    let mut last_push = None;
-    let mut query = query.directional_iter(left_to_right).peekable();
-    let mut in_range = false;
-    let mut current_limit = limit;
-    let mut current_offset = offset;
+    let mut verifier = ProofVerifier::new_internal(query, limit, offset, left_to_right, keys_only);
+    let result_set = cost_return_on_error!(&mut cost, verifier.feed(bytes));
+    let (limit, offset) = (verifier.limit(), verifier.offset());
+    let root_hash = cost_return_on_error!(&mut cost, verifier.finish());
 
-    let ops = Decoder::new(bytes);
+    Ok((
+        root_hash,
+        ProofVerificationResult {
+            result_set,
+            limit,
+            offset,
+        },
+    ))
+    .wrap_with_cost(cost)
+}
 
-    let root_wrapped = execute(ops, true, |node| {
-        let mut execute_node = |key: &Vec<u8>,
-                                value: Option<&Vec<u8>>,
-                                value_hash: CryptoHash|
-         -> Result<_, Error> {
-            while let Some(item) = query.peek() {
-                // get next item in query
-                let query_item = *item;
-                let (lower_bound, start_non_inclusive) = query_item.lower_bound();
-                let (upper_bound, end_inclusive) = query_item.upper_bound();
-
-                // terminate if we encounter a node before the current query item.
-                // this means a node less than the current query item for left to right.
-                // and a node greater than the current query item for right to left.
-                let terminate = if left_to_right {
-                    // if the query item is lower unbounded, then a node cannot be less than it.
-                    // checks that the lower bound of the query item not greater than the key
-                    // if they are equal make sure the start is inclusive
-                    !query_item.lower_unbounded()
-                        && ((lower_bound.expect("confirmed not unbounded") > key.as_slice())
-                            || (start_non_inclusive
-                                && lower_bound.expect("confirmed not unbounded") == key.as_slice()))
-                } else {
-                    !query_item.upper_unbounded()
-                        && ((upper_bound.expect("confirmed not unbounded") < key.as_slice())
-                            || (!end_inclusive
-                                && upper_bound.expect("confirmed not unbounded") == key.as_slice()))
-                };
-                if terminate {
-                    break;
-                }
+#[cfg(any(feature = "full", feature = "verify"))]
+/// Verifies a proof fed in chunks, for instance as they are read from a
+/// socket, with the given query. Only the bytes of the last operator that
+/// wasn't entirely fed are buffered, and pushed nodes are hashed and pruned
+/// as they are attached, so the proof is never held in memory as a whole.
+///
+/// The key/value pairs matching the query are returned as soon as the chunk
+/// holding them is fed, but they are only proven once [`Self::finish`]
+/// returns a root hash that matches the expected one.
+pub struct ProofVerifier {
+    executor: ProofExecutor,
+    state: QueryVerificationState,
+    pending: Vec<u8>,
+}
 
-                if !in_range {
-                    // this is the first data we have encountered for this query item
-                    if left_to_right {
-                        // ensure lower bound of query item is proven
-                        match last_push {
-                            // lower bound is proven - we have an exact match
-                            // ignoring the case when the lower bound is unbounded
-                            // as it's not possible the get an exact key match for
-                            // an unbounded value
-                            _ if Some(key.as_slice()) == query_item.lower_bound().0 => {}
-
-                            // lower bound is proven - this is the leftmost node
-                            // in the tree
-                            None => {}
-
-                            // lower bound is proven - the preceding tree node
-                            // is lower than the bound
-                            Some(Node::KV(..)) => {}
-                            Some(Node::KVDigest(..)) => {}
-                            Some(Node::KVRefValueHash(..)) => {}
-                            Some(Node::KVValueHash(..)) => {}
-
-                            // cannot verify lower bound - we have an abridged
-                            // tree so we cannot tell what the preceding key was
-                            Some(_) => {
-                                return Err(Error::InvalidProofError(
-                                    "Cannot verify lower bound of queried range".to_string(),
-                                ));
-                            }
+#[cfg(any(feature = "full", feature = "verify"))]
+impl ProofVerifier {
+    /// New verifier of a proof of `query`, see [`execute_proof`]
+    pub fn new(
+        query: &Query,
+        limit: Option<u16>,
+        offset: Option<u16>,
+        left_to_right: bool,
+    ) -> Self {
+        Self::new_internal(query, limit, offset, left_to_right, false)
+    }
+
+    /// New verifier of a keys only proof of `query`, see
+    /// [`execute_keys_only_proof`]
+    pub fn new_keys_only(
+        query: &Query,
+        limit: Option<u16>,
+        offset: Option<u16>,
+        left_to_right: bool,
+    ) -> Self {
+        Self::new_internal(query, limit, offset, left_to_right, true)
+    }
+
+    fn new_internal(
+        query: &Query,
+        limit: Option<u16>,
+        offset: Option<u16>,
+        left_to_right: bool,
+        keys_only: bool,
+    ) -> Self {
+        ProofVerifier {
+            executor: ProofExecutor::new(true),
+            state: QueryVerificationState {
+                items: query.directional_iter(left_to_right).cloned().collect(),
+                next_item: 0,
+                in_range: false,
+                last_push: None,
+                limit,
+                offset,
+                left_to_right,
+                keys_only,
+            },
+            pending: Vec::new(),
+        }
+    }
+
+    /// Feeds the next bytes of the proof, returning the key/value pairs
+    /// matching the query among the operators they complete
+    pub fn feed(&mut self, bytes: &[u8]) -> CostResult<Vec<ProvedKeyValue>, Error> {
+        let mut cost = OperationCost::default();
+        let mut output = Vec::new();
+
+        if self.pending.is_empty() {
+            let consumed = cost_return_on_error!(&mut cost, self.execute_ops(bytes, &mut output));
+            self.pending = bytes[consumed..].to_vec();
+        } else {
+            let mut bytes = [std::mem::take(&mut self.pending).as_slice(), bytes].concat();
+            let consumed = cost_return_on_error!(&mut cost, self.execute_ops(&bytes, &mut output));
+            bytes.drain(..consumed);
+            self.pending = bytes;
+        }
+
+        Ok(output).wrap_with_cost(cost)
+    }
+
+    /// Executes the operators entirely held in `bytes`, returning the length
+    /// of their encoding
+    fn execute_ops(
+        &mut self,
+        bytes: &[u8],
+        output: &mut Vec<ProvedKeyValue>,
+    ) -> CostResult<usize, Error> {
+        let mut cost = OperationCost::default();
+
+        let mut consumed = 0;
+        while let Some((op, length)) =
+            cost_return_on_error_no_add!(&cost, Op::decode_partial(&bytes[consumed..]))
+        {
+            consumed += length;
+            let state = &mut self.state;
+            cost_return_on_error!(
+                &mut cost,
+                self.executor
+                    .apply(op, |node| state.visit_node(node, output))
+            );
+        }
+
+        Ok(consumed).wrap_with_cost(cost)
+    }
+
+    /// Limit left after the key/value pairs returned so far
+    pub fn limit(&self) -> Option<u16> {
+        self.state.limit
+    }
+
+    /// Offset left after the key/value pairs returned so far
+    pub fn offset(&self) -> Option<u16> {
+        self.state.offset
+    }
+
+    /// Checks the whole proof was fed and returns its root hash
+    pub fn finish(self) -> CostResult<MerkHash, Error> {
+        let mut cost = OperationCost::default();
+
+        if !self.pending.is_empty() {
+            return Err(Error::InvalidProofError(
+                "Proof ends in the middle of an operator".to_string(),
+            ))
+            .wrap_with_cost(cost);
+        }
+        let root = cost_return_on_error_no_add!(&cost, self.executor.finish());
+        cost_return_on_error_no_add!(&cost, self.state.finish());
+
+        Ok(root.hash().unwrap_add_cost(&mut cost)).wrap_with_cost(cost)
+    }
+}
+
+#[cfg(any(feature = "full", feature = "verify"))]
+/// Progress of a proof verification through the items of the query
+struct QueryVerificationState {
+    items: Vec<QueryItem>,
+    next_item: usize,
+    in_range: bool,
+    last_push: Option<Node>,
+    limit: Option<u16>,
+    offset: Option<u16>,
+    left_to_right: bool,
+    keys_only: bool,
+}
+
+#[cfg(any(feature = "full", feature = "verify"))]
+impl QueryVerificationState {
+    fn visit_node(&mut self, node: &Node, output: &mut Vec<ProvedKeyValue>) -> Result<(), Error> {
+        if let Node::KV(key, value) = node {
+            self.execute_node(key, Some(value), value_hash(value).unwrap(), output)?;
+        } else if let Node::KVValueHash(key, value, value_hash) = node {
+            self.execute_node(key, Some(value), *value_hash, output)?;
+        } else if let Node::KVDigest(key, value_hash) = node {
+            self.execute_node(key, None, *value_hash, output)?;
+        } else if let Node::KVRefValueHash(key, value, value_hash) = node {
+            self.execute_node(key, Some(value), *value_hash, output)?;
+        } else if self.in_range {
+            // we encountered a queried range but the proof was abridged (saw a
+            // non-KV push), we are missing some part of the range
+            return Err(Error::InvalidProofError(
+                "Proof is missing data for query for range".to_string(),
+            ));
+        }
+
+        self.last_push = Some(node.clone());
+
+        Ok(())
+    }
+
+    fn execute_node(
+        &mut self,
+        key: &[u8],
+        value: Option<&Vec<u8>>,
+        value_hash: CryptoHash,
+        output: &mut Vec<ProvedKeyValue>,
+    ) -> Result<(), Error> {
+        let left_to_right = self.left_to_right;
+        while let Some(query_item) = self.items.get(self.next_item) {
+            // get next item in query
+            let (lower_bound, start_non_inclusive) = query_item.lower_bound();
+            let (upper_bound, end_inclusive) = query_item.upper_bound();
+
+            // terminate if we encounter a node before the current query item.
+            // this means a node less than the current query item for left to right.
+            // and a node greater than the current query item for right to left.
+            let terminate = if left_to_right {
+                // if the query item is lower unbounded, then a node cannot be less than it.
+                // checks that the lower bound of the query item not greater than the key
+                // if they are equal make sure the start is inclusive
+                !query_item.lower_unbounded()
+                    && ((lower_bound.expect("confirmed not unbounded") > key)
+                        || (start_non_inclusive
+                            && lower_bound.expect("confirmed not unbounded") == key))
+            } else {
+                !query_item.upper_unbounded()
+                    && ((upper_bound.expect("confirmed not unbounded") < key)
+                        || (!end_inclusive && upper_bound.expect("confirmed not unbounded") == key))
+            };
+            if terminate {
+                break;
+            }
+
+            if !self.in_range {
+                // this is the first data we have encountered for this query item
+                if left_to_right {
+                    // ensure lower bound of query item is proven
+                    match self.last_push {
+                        // lower bound is proven - we have an exact match
+                        // ignoring the case when the lower bound is unbounded
+                        // as it's not possible the get an exact key match for
+                        // an unbounded value
+                        _ if Some(key) == query_item.lower_bound().0 => {}
+
+                        // lower bound is proven - this is the leftmost node
+                        // in the tree
+                        None => {}
+
+                        // lower bound is proven - the preceding tree node
+                        // is lower than the bound
+                        Some(Node::KV(..)) => {}
+                        Some(Node::KVDigest(..)) => {}
+                        Some(Node::KVRefValueHash(..)) => {}
+                        Some(Node::KVValueHash(..)) => {}
+
+                        // cannot verify lower bound - we have an abridged
+                        // tree so we cannot tell what the preceding key was
+                        Some(_) => {
+                            return Err(Error::InvalidProofError(
+                                "Cannot verify lower bound of queried range".to_string(),
+                            ));
                         }
-                    } else {
-                        // ensure upper bound of query item is proven
-                        match last_push {
-                            // upper bound is proven - we have an exact match
-                            // ignoring the case when the upper bound is unbounded
-                            // as it's not possible the get an exact key match for
-                            // an unbounded value
-                            _ if Some(key.as_slice()) == query_item.upper_bound().0 => {}
-
-                            // lower bound is proven - this is the rightmost node
-                            // in the tree
-                            None => {}
-
-                            // upper bound is proven - the preceding tree node
-                            // is greater than the bound
-                            Some(Node::KV(..)) => {}
-                            Some(Node::KVDigest(..)) => {}
-                            Some(Node::KVRefValueHash(..)) => {}
-                            Some(Node::KVValueHash(..)) => {}
-
-                            // cannot verify upper bound - we have an abridged
-                            // tree so we cannot tell what the previous key was
-                            Some(_) => {
-                                return Err(Error::InvalidProofError(
-                                    "Cannot verify upper bound of queried range".to_string(),
-                                ));
-                            }
+                    }
+                } else {
+                    // ensure upper bound of query item is proven
+                    match self.last_push {
+                        // upper bound is proven - we have an exact match
+                        // ignoring the case when the upper bound is unbounded
+                        // as it's not possible the get an exact key match for
+                        // an unbounded value
+                        _ if Some(key) == query_item.upper_bound().0 => {}
+
+                        // lower bound is proven - this is the rightmost node
+                        // in the tree
+                        None => {}
+
+                        // upper bound is proven - the preceding tree node
+                        // is greater than the bound
+                        Some(Node::KV(..)) => {}
+                        Some(Node::KVDigest(..)) => {}
+                        Some(Node::KVRefValueHash(..)) => {}
+                        Some(Node::KVValueHash(..)) => {}
+
+                        // cannot verify upper bound - we have an abridged
+                        // tree so we cannot tell what the previous key was
+                        Some(_) => {
+                            return Err(Error::InvalidProofError(
+                                "Cannot verify upper bound of queried range".to_string(),
+                            ));
                         }
                     }
                 }
+            }
 
-                if left_to_right {
-                    if query_item.upper_bound().0.is_some()
-                        && Some(key.as_slice()) >= query_item.upper_bound().0
-                    {
-                        // at or past upper bound of range (or this was an exact
-                        // match on a single-key queryitem), advance to next query
-                        // item
-                        query.next();
-                        in_range = false;
-                    } else {
-                        // have not reached upper bound, we expect more values
-                        // to be proven in the range (and all pushes should be
-                        // unabridged until we reach end of range)
-                        in_range = true;
-                    }
-                } else if query_item.lower_bound().0.is_some()
-                    && Some(key.as_slice()) <= query_item.lower_bound().0
-                {
-                    // at or before lower bound of range (or this was an exact
+            let contains_key = query_item.contains(key);
+            if left_to_right {
+                if query_item.upper_bound().0.is_some() && Some(key) >= query_item.upper_bound().0 {
+                    // at or past upper bound of range (or this was an exact
                     // match on a single-key queryitem), advance to next query
                     // item
-                    query.next();
-                    in_range = false;
+                    self.next_item += 1;
+                    self.in_range = false;
                 } else {
-                    // have not reached lower bound, we expect more values
+                    // have not reached upper bound, we expect more values
                     // to be proven in the range (and all pushes should be
                     // unabridged until we reach end of range)
-                    in_range = true;
+                    self.in_range = true;
                 }
+            } else if query_item.lower_bound().0.is_some()
+                && Some(key) <= query_item.lower_bound().0
+            {
+                // at or before lower bound of range (or this was an exact
+                // match on a single-key queryitem), advance to next query
+                // item
+                self.next_item += 1;
+                self.in_range = false;
+            } else {
+                // have not reached lower bound, we expect more values
+                // to be proven in the range (and all pushes should be
+                // unabridged until we reach end of range)
+                self.in_range = true;
+            }
 
-                // this push matches the queried item
-                if query_item.contains(key) {
-                    // if there are still offset slots, and node is of type kvdigest
-                    // reduce the offset counter
-                    // also, verify that a kv node was not pushed before offset is exhausted
-                    if let Some(offset) = current_offset {
-                        if offset > 0 && value.is_none() {
-                            current_offset = Some(offset - 1);
-                            break;
-                        } else if offset > 0 && value.is_some() {
-                            // inserting a kv node before exhausting offset
-                            return Err(Error::InvalidProofError(
-                                "Proof returns data before offset is exhausted".to_string(),
-                            ));
-                        }
+            // this push matches the queried item
+            if contains_key {
+                // if there are still offset slots, and node is of type kvdigest
+                // reduce the offset counter
+                // also, verify that a kv node was not pushed before offset is exhausted
+                if let Some(offset) = self.offset {
+                    if offset > 0 && value.is_none() {
+                        self.offset = Some(offset - 1);
+                        break;
+                    } else if offset > 0 && value.is_some() {
+                        // inserting a kv node before exhausting offset
+                        return Err(Error::ProofLimitOffsetError(
+                            "Proof returns data before offset is exhausted",
+                        ));
                     }
+                }
 
-                    // offset is equal to zero or none
-                    if let Some(val) = value {
-                        if let Some(limit) = current_limit {
-                            if limit == 0 {
-                                return Err(Error::InvalidProofError(
-                                    "Proof returns more data than limit".to_string(),
-                                ));
-                            } else {
-                                current_limit = Some(limit - 1);
-                                if current_limit == Some(0) {
-                                    in_range = false;
-                                }
+                // offset is equal to zero or none, the matched nodes of
+                // keys only proofs have no value
+                let value = match value {
+                    Some(value) => Some(value.clone()),
+                    None if self.keys_only => Some(Vec::new()),
+                    None => None,
+                };
+                if let Some(val) = value {
+                    if let Some(limit) = self.limit {
+                        if limit == 0 {
+                            return Err(Error::ProofLimitOffsetError(
+                                "Proof returns more data than limit",
+                            ));
+                        } else {
+                            self.limit = Some(limit - 1);
+                            if self.limit == Some(0) {
+                                self.in_range = false;
                             }
                         }
-                        // add data to output
-                        output.push(ProvedKeyValue {
-                            key: key.clone(),
-                            value: val.clone(),
-                            proof: value_hash,
-                        });
-
-                        // continue to next push
-                        break;
-                    } else {
-                        return Err(Error::InvalidProofError(
-                            "Proof is missing data for query".to_string(),
-                        ));
                     }
+                    // add data to output
+                    output.push(ProvedKeyValue {
+                        key: key.to_vec(),
+                        value: val,
+                        proof: value_hash,
+                    });
+
+                    // continue to next push
+                    break;
+                } else {
+                    return Err(Error::InvalidProofError(
+                        "Proof is missing data for query".to_string(),
+                    ));
                 }
-                {}
-                // continue to next queried item
             }
-            Ok(())
-        };
-
-        if let Node::KV(key, value) = node {
-            execute_node(key, Some(value), value_hash(value).unwrap())?;
-        } else if let Node::KVValueHash(key, value, value_hash) = node {
-            execute_node(key, Some(value), *value_hash)?;
-        } else if let Node::KVDigest(key, value_hash) = node {
-            execute_node(key, None, *value_hash)?;
-        } else if let Node::KVRefValueHash(key, value, value_hash) = node {
-            execute_node(key, Some(value), *value_hash)?;
-        } else if in_range {
-            // we encountered a queried range but the proof was abridged (saw a
-            // non-KV push), we are missing some part of the range
-            return Err(Error::InvalidProofError(
-                "Proof is missing data for query for range".to_string(),
-            ));
+            // continue to next queried item
         }
-
-        last_push = Some(node.clone());
-
         Ok(())
-    });
-
-    let root = cost_return_on_error!(&mut cost, root_wrapped);
+    }
 
-    // we have remaining query items, check absence proof against right edge of
-    // tree
-    if query.peek().is_some() {
-        if current_limit == Some(0) {
-        } else {
-            match last_push {
+    fn finish(self) -> Result<(), Error> {
+        // we have remaining query items, check absence proof against right edge of
+        // tree
+        if self.next_item < self.items.len() && self.limit != Some(0) {
+            match self.last_push {
                 // last node in tree was less than queried item
                 Some(Node::KV(..)) => {}
                 Some(Node::KVDigest(..)) => {}
@@ -282,21 +474,11 @@ pub fn execute_proof(
                     return Err(Error::InvalidProofError(
                         "Proof is missing data for query".to_string(),
                     ))
-                    .wrap_with_cost(cost)
                 }
             }
         }
+        Ok(())
     }
-
-    Ok((
-        root.hash().unwrap_add_cost(&mut cost),
-        ProofVerificationResult {
-            result_set: output,
-            limit: current_limit,
-            offset: current_offset,
-        },
-    ))
-    .wrap_with_cost(cost)
 }
 
 #[cfg(any(feature = "full", feature = "verify"))]
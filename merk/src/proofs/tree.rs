@@ -312,7 +312,7 @@ impl<'a> Iterator for LayerIter<'a> {
     }
 }
 
-#[cfg(any(feature = "full", feature = "verify"))]
+#[cfg(feature = "full")]
 /// Executes a proof by stepping through its operators, modifying the
 /// verification stack as it goes. The resulting stack item is returned.
 ///
@@ -332,146 +332,126 @@ where
 {
     let mut cost = OperationCost::default();
 
-    let mut stack: Vec<Tree> = Vec::with_capacity(32);
-    let mut maybe_last_key = None;
+    let mut executor = ProofExecutor::new(collapse);
+    for op in ops {
+        let op = cost_return_on_error_no_add!(&cost, op);
+        cost_return_on_error!(&mut cost, executor.apply(op, &mut visit_node));
+    }
+
+    executor.finish().wrap_with_cost(cost)
+}
 
-    fn try_pop(stack: &mut Vec<Tree>) -> Result<Tree, Error> {
-        stack
+#[cfg(any(feature = "full", feature = "verify"))]
+/// Verification stack of a proof executed one operator at a time, see
+/// [`execute`]
+pub(crate) struct ProofExecutor {
+    stack: Vec<Tree>,
+    maybe_last_key: Option<Vec<u8>>,
+    collapse: bool,
+}
+
+#[cfg(any(feature = "full", feature = "verify"))]
+impl ProofExecutor {
+    /// New executor, pruning attached nodes from memory if `collapse` is set
+    pub(crate) fn new(collapse: bool) -> Self {
+        ProofExecutor {
+            stack: Vec::with_capacity(32),
+            maybe_last_key: None,
+            collapse,
+        }
+    }
+
+    fn try_pop(&mut self) -> Result<Tree, Error> {
+        self.stack
             .pop()
             .ok_or_else(|| Error::InvalidProofError("Stack underflow".to_string()))
     }
 
-    for op in ops {
-        match cost_return_on_error_no_add!(&cost, op) {
-            Op::Parent => {
-                let (mut parent, child) = (
-                    cost_return_on_error_no_add!(&cost, try_pop(&mut stack)),
-                    cost_return_on_error_no_add!(&cost, try_pop(&mut stack)),
-                );
-                cost_return_on_error!(
-                    &mut cost,
-                    parent.attach(
-                        true,
-                        if collapse {
-                            child.into_hash().unwrap_add_cost(&mut cost)
-                        } else {
-                            child
-                        },
-                    )
-                );
-                stack.push(parent);
-            }
-            Op::Child => {
-                let (child, mut parent) = (
-                    cost_return_on_error_no_add!(&cost, try_pop(&mut stack)),
-                    cost_return_on_error_no_add!(&cost, try_pop(&mut stack)),
-                );
-                cost_return_on_error!(
-                    &mut cost,
-                    parent.attach(
-                        false,
-                        if collapse {
-                            child.into_hash().unwrap_add_cost(&mut cost)
-                        } else {
-                            child
-                        }
-                    )
-                );
-                stack.push(parent);
-            }
-            Op::ParentInverted => {
-                let (mut parent, child) = (
-                    cost_return_on_error_no_add!(&cost, try_pop(&mut stack)),
-                    cost_return_on_error_no_add!(&cost, try_pop(&mut stack)),
-                );
-                cost_return_on_error!(
-                    &mut cost,
-                    parent.attach(
-                        false,
-                        if collapse {
-                            child.into_hash().unwrap_add_cost(&mut cost)
-                        } else {
-                            child
-                        },
-                    )
-                );
-                stack.push(parent);
-            }
-            Op::ChildInverted => {
-                let (child, mut parent) = (
-                    cost_return_on_error_no_add!(&cost, try_pop(&mut stack)),
-                    cost_return_on_error_no_add!(&cost, try_pop(&mut stack)),
-                );
-                cost_return_on_error!(
-                    &mut cost,
-                    parent.attach(
-                        true,
-                        if collapse {
-                            child.into_hash().unwrap_add_cost(&mut cost)
-                        } else {
-                            child
-                        },
-                    )
-                );
-                stack.push(parent);
-            }
-            Op::Push(node) => {
-                if let Node::KV(key, _)
-                | Node::KVValueHashFeatureType(key, ..)
-                | Node::KVRefValueHash(key, ..) = &node
-                {
-                    // keys should always increase
-                    if let Some(last_key) = &maybe_last_key {
-                        if key <= last_key {
-                            return Err(Error::InvalidProofError(
-                                "Incorrect key ordering".to_string(),
-                            ))
-                            .wrap_with_cost(cost);
-                        }
-                    }
-
-                    maybe_last_key = Some(key.clone());
-                }
-
-                cost_return_on_error_no_add!(&cost, visit_node(&node));
+    /// Pops a parent and a child from the stack, in the given order, and
+    /// pushes back the parent with the child attached on the given side
+    fn attach(&mut self, parent_first: bool, left: bool) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
 
-                let tree: Tree = node.into();
-                stack.push(tree);
-            }
-            Op::PushInverted(node) => {
-                if let Node::KV(key, _)
-                | Node::KVValueHashFeatureType(key, ..)
-                | Node::KVRefValueHash(key, ..) = &node
-                {
-                    // keys should always decrease
-                    if let Some(last_key) = &maybe_last_key {
-                        if key >= last_key {
-                            return Err(Error::InvalidProofError(
-                                "Incorrect key ordering inverted".to_string(),
-                            ))
-                            .wrap_with_cost(cost);
-                        }
-                    }
-
-                    maybe_last_key = Some(key.clone());
-                }
+        let (first, second) = (
+            cost_return_on_error_no_add!(&cost, self.try_pop()),
+            cost_return_on_error_no_add!(&cost, self.try_pop()),
+        );
+        let (mut parent, child) = if parent_first {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        let child = if self.collapse {
+            child.into_hash().unwrap_add_cost(&mut cost)
+        } else {
+            child
+        };
+        cost_return_on_error!(&mut cost, parent.attach(left, child));
+        self.stack.push(parent);
+        Ok(()).wrap_with_cost(cost)
+    }
 
-                cost_return_on_error_no_add!(&cost, visit_node(&node));
+    /// Applies an operator, calling `visit_node` if it pushes a node
+    pub(crate) fn apply<F>(&mut self, op: Op, visit_node: F) -> CostResult<(), Error>
+    where
+        F: FnMut(&Node) -> Result<(), Error>,
+    {
+        match op {
+            Op::Parent => self.attach(true, true),
+            Op::Child => self.attach(false, false),
+            Op::ParentInverted => self.attach(true, false),
+            Op::ChildInverted => self.attach(false, true),
+            Op::Push(node) => self.push(node, false, visit_node),
+            Op::PushInverted(node) => self.push(node, true, visit_node),
+        }
+    }
 
-                let tree: Tree = node.into();
-                stack.push(tree);
+    fn push<F>(&mut self, node: Node, inverted: bool, mut visit_node: F) -> CostResult<(), Error>
+    where
+        F: FnMut(&Node) -> Result<(), Error>,
+    {
+        let cost = OperationCost::default();
+
+        if let Node::KV(key, _)
+        | Node::KVValueHashFeatureType(key, ..)
+        | Node::KVRefValueHash(key, ..) = &node
+        {
+            // keys should always increase, or decrease when inverted
+            if let Some(last_key) = &self.maybe_last_key {
+                if !inverted && key <= last_key {
+                    return Err(Error::InvalidProofError(
+                        "Incorrect key ordering".to_string(),
+                    ))
+                    .wrap_with_cost(cost);
+                }
+                if inverted && key >= last_key {
+                    return Err(Error::InvalidProofError(
+                        "Incorrect key ordering inverted".to_string(),
+                    ))
+                    .wrap_with_cost(cost);
+                }
             }
+
+            self.maybe_last_key = Some(key.clone());
         }
-    }
 
-    if stack.len() != 1 {
-        return Err(Error::InvalidProofError(
-            "Expected proof to result in exactly one stack item".to_string(),
-        ))
-        .wrap_with_cost(cost);
+        cost_return_on_error_no_add!(&cost, visit_node(&node));
+
+        let tree: Tree = node.into();
+        self.stack.push(tree);
+        Ok(()).wrap_with_cost(cost)
     }
 
-    Ok(stack.pop().unwrap()).wrap_with_cost(cost)
+    /// Returns the resulting stack item once all operators were applied
+    pub(crate) fn finish(mut self) -> Result<Tree, Error> {
+        if self.stack.len() != 1 {
+            return Err(Error::InvalidProofError(
+                "Expected proof to result in exactly one stack item".to_string(),
+            ));
+        }
+
+        Ok(self.stack.pop().unwrap())
+    }
 }
 
 #[cfg(feature = "full")]
@@ -0,0 +1,244 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+//! Tree aggregates
+//!
+//! Besides sums, Merk nodes can maintain a custom aggregate, like the minimum
+//! or the maximum of the values of a subtree. Every node of an aggregated
+//! Merk carries its own value in its `TreeFeatureType::AggregatedMerk`
+//! feature type, and the aggregate of each subtree is stored in the link
+//! pointing to it, just like sums are. The aggregates are looked up by id in
+//! the `TreeAggregates` the Merk is opened with, so every user of the crate
+//! keeps its own.
+
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+use crate::Error;
+
+/// An aggregate maintained by the nodes of a Merk.
+///
+/// The aggregate of a subtree is the fold of the values of its nodes in key
+/// order, `combine` must therefore be associative so the aggregate doesn't
+/// depend on the shape of the tree.
+pub trait TreeAggregate: Send + Sync {
+    /// Combines the aggregates of two consecutive ranges of nodes, returns
+    /// `None` if the result can't be represented
+    fn combine(&self, left: i128, right: i128) -> Option<i128>;
+}
+
+/// Tree aggregates by id, nodes with the `TreeFeatureType::AggregatedMerk(id,
+/// _)` feature type being aggregated with the aggregate registered under `id`
+#[derive(Clone, Default)]
+pub struct TreeAggregates {
+    aggregates: BTreeMap<u8, Arc<dyn TreeAggregate>>,
+}
+
+impl fmt::Debug for TreeAggregates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.aggregates.keys()).finish()
+    }
+}
+
+impl TreeAggregates {
+    /// Registers `aggregate` under `id`. An id can only be registered once,
+    /// as changing the aggregate of an id would invalidate the aggregates
+    /// already stored.
+    pub fn register(&mut self, id: u8, aggregate: Arc<dyn TreeAggregate>) -> Result<(), Error> {
+        if self.aggregates.contains_key(&id) {
+            return Err(Error::InvalidInputError(
+                "a tree aggregate is already registered with this id",
+            ));
+        }
+        self.aggregates.insert(id, aggregate);
+        Ok(())
+    }
+
+    /// Get the aggregate registered under `id`
+    pub fn get(&self, id: u8) -> Result<&dyn TreeAggregate, Error> {
+        self.aggregates
+            .get(&id)
+            .map(|aggregate| aggregate.as_ref())
+            .ok_or(Error::InvalidInputError(
+                "no tree aggregate is registered with this id",
+            ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use grovedb_costs::storage_cost::{removal::StorageRemovedBytes, StorageCost};
+
+    use super::*;
+    use crate::tree::{
+        kv::ValueDefinedCostType, Commit, NoopCommit, Tree, TreeFeatureType::AggregatedMerk,
+    };
+
+    struct Max;
+
+    impl TreeAggregate for Max {
+        fn combine(&self, left: i128, right: i128) -> Option<i128> {
+            Some(left.max(right))
+        }
+    }
+
+    struct Min;
+
+    impl TreeAggregate for Min {
+        fn combine(&self, left: i128, right: i128) -> Option<i128> {
+            Some(left.min(right))
+        }
+    }
+
+    /// Keeps the tree in memory like `NoopCommit`, with aggregates
+    struct AggregatesCommit(TreeAggregates);
+
+    impl Commit for AggregatesCommit {
+        fn write(
+            &mut self,
+            tree: &mut Tree,
+            old_specialized_cost: &impl Fn(&Vec<u8>, &Vec<u8>) -> Result<u32, Error>,
+            update_tree_value_based_on_costs: &mut impl FnMut(
+                &StorageCost,
+                &Vec<u8>,
+                &mut Vec<u8>,
+            ) -> Result<
+                (bool, Option<ValueDefinedCostType>),
+                Error,
+            >,
+            section_removal_bytes: &mut impl FnMut(
+                &Vec<u8>,
+                u32,
+                u32,
+            ) -> Result<
+                (StorageRemovedBytes, StorageRemovedBytes),
+                Error,
+            >,
+        ) -> Result<(), Error> {
+            NoopCommit {}.write(
+                tree,
+                old_specialized_cost,
+                update_tree_value_based_on_costs,
+                section_removal_bytes,
+            )
+        }
+
+        fn prune(&self, _tree: &Tree) -> (bool, bool) {
+            (false, false)
+        }
+
+        fn tree_aggregates(&self) -> Option<&TreeAggregates> {
+            Some(&self.0)
+        }
+    }
+
+    fn aggregates() -> TreeAggregates {
+        let mut aggregates = TreeAggregates::default();
+        aggregates
+            .register(100, Arc::new(Max))
+            .expect("should register aggregate");
+        aggregates
+            .register(101, Arc::new(Min))
+            .expect("should register aggregate");
+        aggregates
+    }
+
+    fn committed_tree(id: u8, values: [Option<i128>; 3]) -> Tree {
+        let mut tree = Tree::new(vec![1], vec![1], None, AggregatedMerk(id, values[1]))
+            .unwrap()
+            .attach(
+                true,
+                Some(Tree::new(vec![0], vec![0], None, AggregatedMerk(id, values[0])).unwrap()),
+            )
+            .attach(
+                false,
+                Some(Tree::new(vec![2], vec![2], None, AggregatedMerk(id, values[2])).unwrap()),
+            );
+        tree.commit(
+            &mut AggregatesCommit(aggregates()),
+            &|_, _| Ok(0),
+            &mut |_, _, _| Ok((false, None)),
+            &mut |_, _, _| {
+                Ok((
+                    StorageRemovedBytes::NoStorageRemoval,
+                    StorageRemovedBytes::NoStorageRemoval,
+                ))
+            },
+        )
+        .unwrap()
+        .expect("commit failed");
+        tree
+    }
+
+    #[test]
+    fn registered_aggregates_are_maintained() {
+        let aggregates = aggregates();
+        assert!(aggregates.clone().register(100, Arc::new(Min)).is_err());
+
+        let tree = committed_tree(100, [Some(7), Some(-3), Some(12)]);
+        assert_eq!(
+            tree.sum_with_aggregates(Some(&aggregates))
+                .expect("expected to get aggregate"),
+            Some(12)
+        );
+        assert_eq!(tree.link(true).and_then(|link| link.sum()), Some(7));
+
+        let tree = committed_tree(101, [Some(7), Some(-3), Some(12)]);
+        assert_eq!(
+            tree.sum_with_aggregates(Some(&aggregates))
+                .expect("expected to get aggregate"),
+            Some(-3)
+        );
+    }
+
+    #[test]
+    fn nodes_without_value_are_left_out() {
+        let aggregates = aggregates();
+        let tree = committed_tree(101, [Some(7), None, Some(12)]);
+        assert_eq!(
+            tree.sum_with_aggregates(Some(&aggregates))
+                .expect("expected to get aggregate"),
+            Some(7)
+        );
+
+        let tree = committed_tree(101, [None, None, None]);
+        assert_eq!(
+            tree.sum_with_aggregates(Some(&aggregates))
+                .expect("expected to get aggregate"),
+            None
+        );
+    }
+
+    #[test]
+    fn unregistered_aggregate() {
+        let tree = Tree::new(vec![1], vec![1], None, AggregatedMerk(102, Some(5))).unwrap();
+        assert!(matches!(
+            tree.sum_with_aggregates(Some(&aggregates())),
+            Err(Error::InvalidInputError(_))
+        ));
+        assert!(matches!(tree.sum(), Err(Error::InvalidInputError(_))));
+    }
+}
@@ -32,7 +32,7 @@
 use grovedb_costs::storage_cost::{removal::StorageRemovedBytes, StorageCost};
 
 #[cfg(feature = "full")]
-use super::Tree;
+use super::{aggregate::TreeAggregates, Tree};
 #[cfg(feature = "full")]
 use crate::error::Error;
 use crate::tree::kv::ValueDefinedCostType;
@@ -72,6 +72,12 @@ pub trait Commit {
     fn prune(&self, _tree: &Tree) -> (bool, bool) {
         (true, true)
     }
+
+    /// Aggregates the nodes of an aggregated Merk are folded with when their
+    /// links are committed
+    fn tree_aggregates(&self) -> Option<&TreeAggregates> {
+        None
+    }
 }
 
 #[cfg(feature = "full")]
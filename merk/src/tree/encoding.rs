@@ -72,6 +72,57 @@ impl Tree {
 
         Ok(tree_opt).wrap_with_cost(cost)
     }
+
+    /// Get values from storage given keys, looking them all up at once.
+    pub(crate) fn get_many<'db, S, K>(
+        storage: &S,
+        keys: &[K],
+    ) -> CostResult<Vec<Option<Self>>, Error>
+    where
+        S: StorageContext<'db>,
+        K: AsRef<[u8]>,
+    {
+        let mut cost = OperationCost::default();
+        let trees_bytes =
+            cost_return_on_error!(&mut cost, storage.multi_get(keys).map_err(StorageError));
+
+        let trees = cost_return_on_error_no_add!(
+            &cost,
+            trees_bytes
+                .into_iter()
+                .zip(keys)
+                .map(|(tree_bytes, key)| {
+                    tree_bytes
+                        .map(|x| Tree::decode_raw(&x, key.as_ref().to_vec()))
+                        .transpose()
+                })
+                .collect::<Result<Vec<_>, Error>>()
+        );
+
+        Ok(trees).wrap_with_cost(cost)
+    }
+
+    /// Get value from storage given key, seeing the writes deferred in the
+    /// storage batch so that nodes pruned before the batch is committed can be
+    /// fetched back.
+    pub(crate) fn get_staged<'db, S, K>(storage: &S, key: K) -> CostResult<Option<Self>, Error>
+    where
+        S: StorageContext<'db>,
+        K: AsRef<[u8]>,
+    {
+        let mut cost = OperationCost::default();
+        let tree_bytes =
+            cost_return_on_error!(&mut cost, storage.get_staged(&key).map_err(StorageError));
+
+        let tree_opt = cost_return_on_error_no_add!(
+            &cost,
+            tree_bytes
+                .map(|x| Tree::decode_raw(&x, key.as_ref().to_vec()))
+                .transpose()
+        );
+
+        Ok(tree_opt).wrap_with_cost(cost)
+    }
 }
 
 #[cfg(feature = "full")]
@@ -59,7 +59,7 @@ pub enum Link {
         /// Key
         key: Vec<u8>,
         /// Sum
-        sum: Option<i64>,
+        sum: Option<i128>,
     },
 
     /// Represents a tree node which has been modified since the `Tree`'s last
@@ -86,7 +86,7 @@ pub enum Link {
         /// Tree
         tree: Tree,
         /// Sum
-        sum: Option<i64>,
+        sum: Option<i128>,
     },
 
     /// Represents a tree node which has not been modified, has an up-to-date
@@ -99,7 +99,7 @@ pub enum Link {
         /// Tree
         tree: Tree,
         /// Sum
-        sum: Option<i64>,
+        sum: Option<i128>,
     },
 }
 
@@ -188,7 +188,7 @@ impl Link {
     /// of variant `Link::Modified` since we have not yet recomputed the tree's
     /// hash.
     #[inline]
-    pub const fn sum(&self) -> Option<i64> {
+    pub const fn sum(&self) -> Option<i128> {
         match self {
             Link::Modified { .. } => panic!("Cannot get hash from modified link"),
             Link::Reference { sum, .. } => *sum,
@@ -291,7 +291,18 @@ impl Link {
         not_prefixed_key_len + HASH_LENGTH_U32 + 4 + sum_tree_cost
     }
 
-    /// The encoding cost is always 8 bytes for the sum instead of a varint
+    /// Length of the encoded sum, sums that fit in an i64 are encoded as a
+    /// varint and bigger sums as 16 fixed bytes
+    #[inline]
+    pub fn sum_encoding_length(sum: i128) -> usize {
+        match i64::try_from(sum) {
+            Ok(sum) => sum.encode_var_vec().len(),
+            Err(_) => 16,
+        }
+    }
+
+    /// The encoding cost is always 8 bytes for the sum instead of a varint, or
+    /// 16 bytes for sums that don't fit in an i64
     #[inline]
     pub fn encoding_cost(&self) -> Result<usize> {
         debug_assert!(self.key().len() < 256, "Key length must be less than 256");
@@ -299,6 +310,9 @@ impl Link {
         Ok(match self {
             Link::Reference { key, sum, .. } => match sum {
                 None => key.len() + 36, // 1 + HASH_LENGTH + 2 + 1,
+                Some(sum_value) if i64::try_from(*sum_value).is_err() => {
+                    key.len() + 52 // 1 + 32 + 2 + 1 + 16
+                }
                 Some(_sum_value) => {
                     // 1 for key len
                     // key_len for keys
@@ -314,8 +328,10 @@ impl Link {
             Link::Modified { .. } => panic!("No encoding for Link::Modified"),
             Link::Uncommitted { tree, sum, .. } | Link::Loaded { tree, sum, .. } => match sum {
                 None => tree.key().len() + 36, // 1 + 32 + 2 + 1,
-                Some(sum_value) => {
-                    let _encoded_sum_value = sum_value.encode_var_vec();
+                Some(sum_value) if i64::try_from(*sum_value).is_err() => {
+                    tree.key().len() + 52 // 1 + 32 + 2 + 1 + 16
+                }
+                Some(_sum_value) => {
                     tree.key().len() + 44 // 1 + 32 + 2 + 1 + 8
                 }
             },
@@ -363,10 +379,16 @@ impl Encode for Link {
             None => {
                 out.write_all(&[0])?;
             }
-            Some(sum_value) => {
-                out.write_all(&[1])?;
-                out.write_varint(sum_value.to_owned())?;
-            }
+            Some(sum_value) => match i64::try_from(*sum_value) {
+                Ok(sum_value) => {
+                    out.write_all(&[1])?;
+                    out.write_varint(sum_value)?;
+                }
+                Err(_) => {
+                    out.write_all(&[2])?;
+                    out.write_all(&sum_value.to_be_bytes())?;
+                }
+            },
         }
 
         Ok(())
@@ -380,7 +402,7 @@ impl Encode for Link {
             Link::Reference { key, sum, .. } => match sum {
                 None => key.len() + 36, // 1 + 32 + 2 + 1
                 Some(sum_value) => {
-                    let encoded_sum_value = sum_value.encode_var_vec();
+                    let encoded_sum_value_len = Self::sum_encoding_length(*sum_value);
                     // 1 for key len
                     // key_len for keys
                     // 32 for hash
@@ -389,15 +411,15 @@ impl Encode for Link {
                     //    if above is 1, then
                     //    1 for sum len
                     //    sum_len for sum vale
-                    key.len() + encoded_sum_value.len() + 36 // 1 + 32 + 2 + 1
+                    key.len() + encoded_sum_value_len + 36 // 1 + 32 + 2 + 1
                 }
             },
             Link::Modified { .. } => panic!("No encoding for Link::Modified"),
             Link::Uncommitted { tree, sum, .. } | Link::Loaded { tree, sum, .. } => match sum {
                 None => tree.key().len() + 36, // 1 + 32 + 2 + 1
                 Some(sum_value) => {
-                    let encoded_sum_value = sum_value.encode_var_vec();
-                    tree.key().len() + encoded_sum_value.len() + 36 // 1 + 32 + 2 + 1
+                    let encoded_sum_value_len = Self::sum_encoding_length(*sum_value);
+                    tree.key().len() + encoded_sum_value_len + 36 // 1 + 32 + 2 + 1
                 }
             },
         })
@@ -456,7 +478,12 @@ impl Decode for Link {
                 0 => None,
                 1 => {
                     let encoded_sum: i64 = input.read_varint()?;
-                    Some(encoded_sum)
+                    Some(encoded_sum as i128)
+                }
+                2 => {
+                    let mut encoded_sum = [0; 16];
+                    input.read_exact(&mut encoded_sum)?;
+                    Some(i128::from_be_bytes(encoded_sum))
                 }
                 _ => return Err(ed::Error::UnexpectedByte(55)),
             };
@@ -657,6 +684,26 @@ mod test {
         );
     }
 
+    #[test]
+    fn encode_link_with_big_sum() {
+        let big_sum = i64::MAX as i128 + 1;
+        let link = Link::Reference {
+            key: vec![1, 2, 3],
+            sum: Some(big_sum),
+            child_heights: (123, 124),
+            hash: [55; 32],
+        };
+        assert_eq!(link.encoding_length().unwrap(), 55);
+
+        let mut bytes = vec![];
+        link.encode_into(&mut bytes).unwrap();
+        assert_eq!(link.encoding_length().unwrap(), bytes.len());
+        assert_eq!(bytes[38], 2);
+
+        let decoded = Link::decode(bytes.as_slice()).expect("expected to decode a link");
+        assert_eq!(decoded.sum(), Some(big_sum));
+    }
+
     #[test]
     #[should_panic]
     fn encode_link_long_key() {
@@ -28,6 +28,8 @@
 
 //! Merk trees
 
+#[cfg(feature = "full")]
+mod aggregate;
 #[cfg(feature = "full")]
 mod commit;
 #[cfg(feature = "full")]
@@ -54,6 +56,8 @@ mod walk;
 #[cfg(feature = "full")]
 use std::cmp::{max, Ordering};
 
+#[cfg(feature = "full")]
+pub use aggregate::{TreeAggregate, TreeAggregates};
 #[cfg(feature = "full")]
 pub use commit::{Commit, NoopCommit};
 #[cfg(feature = "full")]
@@ -76,8 +80,6 @@ pub use hash::{
 #[cfg(feature = "full")]
 pub use hash::{HASH_BLOCK_SIZE, HASH_BLOCK_SIZE_U32, HASH_LENGTH_U32, HASH_LENGTH_U32_X2};
 #[cfg(feature = "full")]
-use integer_encoding::VarInt;
-#[cfg(feature = "full")]
 use kv::KV;
 #[cfg(feature = "full")]
 pub use link::Link;
@@ -421,7 +423,7 @@ impl Tree {
                 // 36 = 32 Hash + 1 key length + 2 child heights + 1 feature type
                 link.key().len() as u32 + 36,
                 link.sum()
-                    .map(|s| s.encode_var_vec().len() as u32)
+                    .map(|s| Link::sum_encoding_length(s) as u32)
                     .unwrap_or_default(),
             )
         })
@@ -463,7 +465,7 @@ impl Tree {
     /// Returns the sum of the root node's child on the given side, if any. If
     /// there is no child, returns 0.
     #[inline]
-    pub fn child_sum(&self, left: bool) -> i64 {
+    pub fn child_sum(&self, left: bool) -> i128 {
         match self.link(left) {
             Some(link) => link.sum().unwrap_or_default(),
             _ => 0,
@@ -481,16 +483,62 @@ impl Tree {
         )
     }
 
-    /// Computes and returns the hash of the root node.
+    /// Computes and returns the sum of the tree. Fails for nodes of an
+    /// aggregated Merk, see `sum_with_aggregates`.
     #[inline]
-    pub fn sum(&self) -> Result<Option<i64>, Error> {
+    pub fn sum(&self) -> Result<Option<i128>, Error> {
+        self.sum_with_aggregates(None)
+    }
+
+    /// Computes and returns the sum of the tree, or its aggregate for nodes
+    /// of an aggregated Merk, folded with the aggregate registered in
+    /// `aggregates` under the id of the node.
+    #[inline]
+    pub fn sum_with_aggregates(
+        &self,
+        aggregates: Option<&TreeAggregates>,
+    ) -> Result<Option<i128>, Error> {
         match self.inner.kv.feature_type {
             TreeFeatureType::BasicMerk => Ok(None),
-            TreeFeatureType::SummedMerk(value) => value
+            TreeFeatureType::SummedMerk(value) => (value as i128)
                 .checked_add(self.child_sum(true))
                 .and_then(|a| a.checked_add(self.child_sum(false)))
+                .filter(|sum| i64::try_from(*sum).is_ok())
                 .ok_or(Overflow("sum is overflowing"))
                 .map(Some),
+            TreeFeatureType::BigSummedMerk(value) => value
+                .checked_add(self.child_sum(true))
+                .and_then(|a| a.checked_add(self.child_sum(false)))
+                .ok_or(Overflow("sum is overflowing"))
+                .map(Some),
+            TreeFeatureType::CountedMerk(count) => (count as i128)
+                .checked_add(self.child_sum(true))
+                .and_then(|a| a.checked_add(self.child_sum(false)))
+                .filter(|count| u64::try_from(*count).is_ok())
+                .ok_or(Overflow("count is overflowing"))
+                .map(Some),
+            TreeFeatureType::AggregatedMerk(aggregate_id, value) => {
+                let aggregate = aggregates
+                    .ok_or(Error::InvalidInputError(
+                        "aggregated nodes need the tree aggregates to be summed",
+                    ))?
+                    .get(aggregate_id)?;
+                // nodes without a value are left out, in key order
+                [
+                    self.link(true).and_then(Link::sum),
+                    value,
+                    self.link(false).and_then(Link::sum),
+                ]
+                .into_iter()
+                .flatten()
+                .try_fold(None, |aggregated, value| match aggregated {
+                    None => Ok(Some(value)),
+                    Some(aggregated) => aggregate
+                        .combine(aggregated, value)
+                        .map(Some)
+                        .ok_or(Overflow("aggregate is overflowing")),
+                })
+            }
         }
     }
 
@@ -724,6 +772,30 @@ impl Tree {
         self.wrap_with_cost(cost)
     }
 
+    /// Applies `f` to the root node's value in place, recomputes the value
+    /// hash and kv hash, and returns the modified `Tree`. Once the tree is
+    /// reattached to its parent the parent's link becomes `Link::Modified`, so
+    /// the hashes of all ancestors are recomputed on the next commit.
+    ///
+    /// The value hash is recomputed as the plain hash of the new value, so
+    /// nodes carrying a combined or reference value hash should use
+    /// `put_value_and_reference_value_hash` instead.
+    #[inline]
+    pub fn value_mut_with_invalidation<F>(mut self, f: F) -> CostContext<Self>
+    where
+        F: FnOnce(&mut Vec<u8>),
+    {
+        let mut cost = OperationCost::default();
+        let mut value = std::mem::take(&mut self.inner.kv.value);
+        f(&mut value);
+        self.inner.kv = self
+            .inner
+            .kv
+            .put_value_then_update(value)
+            .unwrap_add_cost(&mut cost);
+        self.wrap_with_cost(cost)
+    }
+
     // TODO: add compute_hashes method
 
     /// Called to finalize modifications to a tree, recompute its hashes, and
@@ -778,7 +850,8 @@ impl Tree {
                         section_removal_bytes
                     )
                 );
-                let sum = cost_return_on_error_default!(tree.sum());
+                let sum =
+                    cost_return_on_error_default!(tree.sum_with_aggregates(c.tree_aggregates()));
 
                 self.inner.left = Some(Link::Loaded {
                     hash: tree.hash().unwrap_add_cost(&mut cost),
@@ -809,7 +882,8 @@ impl Tree {
                         section_removal_bytes
                     )
                 );
-                let sum = cost_return_on_error_default!(tree.sum());
+                let sum =
+                    cost_return_on_error_default!(tree.sum_with_aggregates(c.tree_aggregates()));
                 self.inner.right = Some(Link::Loaded {
                     hash: tree.hash().unwrap_add_cost(&mut cost),
                     tree,
@@ -886,10 +960,14 @@ pub const fn side_to_str(left: bool) -> &'static str {
 #[cfg(feature = "full")]
 #[cfg(test)]
 mod test {
+    use ed::{Decode, Encode};
     use grovedb_costs::storage_cost::removal::StorageRemovedBytes::NoStorageRemoval;
 
-    use super::{commit::NoopCommit, hash::NULL_HASH, Tree};
-    use crate::tree::{tree_feature_type::TreeFeatureType::SummedMerk, TreeFeatureType::BasicMerk};
+    use super::{commit::NoopCommit, hash::NULL_HASH, Link, Tree};
+    use crate::tree::{
+        tree_feature_type::TreeFeatureType::{BigSummedMerk, CountedMerk, SummedMerk},
+        TreeFeatureType::{self, BasicMerk},
+    };
 
     #[test]
     fn build_tree() {
@@ -1117,4 +1195,77 @@ mod test {
 
         assert_eq!(Some(8), tree.sum().expect("expected to get sum from tree"));
     }
+
+    #[test]
+    fn big_sum_tree() {
+        let mut tree = Tree::new(vec![0], vec![1], None, BigSummedMerk(i64::MAX as i128))
+            .unwrap()
+            .attach(
+                false,
+                Some(Tree::new(vec![2], vec![3], None, BigSummedMerk(i64::MAX as i128)).unwrap()),
+            );
+        tree.commit(
+            &mut NoopCommit {},
+            &|_, _| Ok(0),
+            &mut |_, _, _| Ok((false, None)),
+            &mut |_, _, _| Ok((NoStorageRemoval, NoStorageRemoval)),
+        )
+        .unwrap()
+        .expect("commit failed");
+
+        assert_eq!(
+            Some(2 * i64::MAX as i128),
+            tree.sum().expect("expected to get sum from tree")
+        );
+
+        // The same sum overflows a regular sum tree
+        let mut tree = Tree::new(vec![0], vec![1], None, SummedMerk(i64::MAX))
+            .unwrap()
+            .attach(
+                false,
+                Some(Tree::new(vec![2], vec![3], None, SummedMerk(i64::MAX)).unwrap()),
+            );
+        tree.commit(
+            &mut NoopCommit {},
+            &|_, _| Ok(0),
+            &mut |_, _, _| Ok((false, None)),
+            &mut |_, _, _| Ok((NoStorageRemoval, NoStorageRemoval)),
+        )
+        .unwrap()
+        .expect("commit failed");
+
+        assert!(matches!(tree.sum(), Err(crate::Error::Overflow(_))));
+    }
+
+    #[test]
+    fn counted_tree() {
+        let mut tree = Tree::new(vec![0], vec![1], None, CountedMerk(1))
+            .unwrap()
+            .attach(
+                false,
+                Some(Tree::new(vec![2], vec![3], None, CountedMerk(1)).unwrap()),
+            );
+        tree.commit(
+            &mut NoopCommit {},
+            &|_, _| Ok(0),
+            &mut |_, _, _| Ok((false, None)),
+            &mut |_, _, _| Ok((NoStorageRemoval, NoStorageRemoval)),
+        )
+        .unwrap()
+        .expect("commit failed");
+
+        assert_eq!(
+            Some(2),
+            tree.sum().expect("expected to get count from tree")
+        );
+        assert_eq!(tree.link(false).and_then(Link::sum), Some(1));
+
+        // Counts are encoded with their own feature type
+        let encoded = CountedMerk(u64::MAX).encode().expect("expected to encode");
+        assert_eq!(encoded[0], 4);
+        assert_eq!(
+            TreeFeatureType::decode(encoded.as_slice()).expect("expected to decode"),
+            CountedMerk(u64::MAX)
+        );
+    }
 }
@@ -39,7 +39,9 @@ use ed::{Decode, Encode};
 use integer_encoding::{VarInt, VarIntReader, VarIntWriter};
 
 #[cfg(any(feature = "full", feature = "verify"))]
-use crate::tree::tree_feature_type::TreeFeatureType::{BasicMerk, SummedMerk};
+use crate::tree::tree_feature_type::TreeFeatureType::{
+    AggregatedMerk, BasicMerk, BigSummedMerk, CountedMerk, SummedMerk,
+};
 
 #[cfg(any(feature = "full", feature = "verify"))]
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -49,23 +51,45 @@ pub enum TreeFeatureType {
     BasicMerk,
     /// Summed Merk
     SummedMerk(i64),
+    /// Summed Merk with 128 bit sums, for totals that would overflow an i64.
+    /// Only nodes of big sum trees pay for the wider encoding
+    BigSummedMerk(i128),
+    /// Merk maintaining a custom aggregate, holding the id the aggregate was
+    /// registered with and the value of the node, nodes without a value being
+    /// left out of the aggregate. See `TreeAggregates`
+    AggregatedMerk(u8, Option<i128>),
+    /// Merk counting its elements, holding the count of the node itself,
+    /// which is one for every element. Links carry the count of the nodes
+    /// beneath them, like sums
+    CountedMerk(u64),
 }
 
 #[cfg(feature = "full")]
 impl TreeFeatureType {
     #[inline]
-    /// Get length of encoded SummedMerk
-    pub fn sum_length(&self) -> Option<u32> {
+    /// Get length of encoded SummedMerk, and the length paid for it
+    pub fn sum_length(&self) -> Option<(u32, u32)> {
         match self {
             BasicMerk => None,
-            SummedMerk(m) => Some(m.encode_var_vec().len() as u32),
+            // varint sums are always paid as 8 bytes
+            SummedMerk(m) => Some((m.encode_var_vec().len() as u32, 8)),
+            BigSummedMerk(_) => Some((16, 16)),
+            // the aggregate id and the value option are paid for with the
+            // value, even when the node has no value
+            AggregatedMerk(_, None) => Some((2, 18)),
+            AggregatedMerk(_, Some(_)) => Some((18, 18)),
+            // varint counts are paid as 8 bytes, like sums
+            CountedMerk(count) => Some((count.encode_var_vec().len() as u32, 8)),
         }
     }
 
     #[inline]
     /// Is sum feature?
     pub fn is_sum_feature(&self) -> bool {
-        matches!(self, SummedMerk(_))
+        matches!(
+            self,
+            SummedMerk(_) | BigSummedMerk(_) | AggregatedMerk(..) | CountedMerk(_)
+        )
     }
 
     #[inline]
@@ -74,6 +98,9 @@ impl TreeFeatureType {
         match self {
             BasicMerk => 1,
             SummedMerk(_sum) => 9,
+            BigSummedMerk(_sum) => 17,
+            AggregatedMerk(..) => 19,
+            CountedMerk(_count) => 9,
         }
     }
 }
@@ -94,6 +121,25 @@ impl Encode for TreeFeatureType {
                 dest.write_varint(sum.to_owned())?;
                 Ok(())
             }
+            BigSummedMerk(sum) => {
+                dest.write_all(&[2])?;
+                dest.write_all(&sum.to_be_bytes())?;
+                Ok(())
+            }
+            AggregatedMerk(aggregate_id, None) => {
+                dest.write_all(&[3, *aggregate_id, 0])?;
+                Ok(())
+            }
+            AggregatedMerk(aggregate_id, Some(value)) => {
+                dest.write_all(&[3, *aggregate_id, 1])?;
+                dest.write_all(&value.to_be_bytes())?;
+                Ok(())
+            }
+            CountedMerk(count) => {
+                dest.write_all(&[4])?;
+                dest.write_varint(count.to_owned())?;
+                Ok(())
+            }
         }
     }
 
@@ -107,6 +153,14 @@ impl Encode for TreeFeatureType {
                 // encoded_sum.len() for the length of the encoded vector
                 Ok(1 + encoded_sum.len())
             }
+            // 1 for the enum type and 16 for the fixed size sum
+            BigSummedMerk(_) => Ok(17),
+            // 1 for the enum type, 1 for the aggregate id, 1 for the value
+            // option and 16 for the value if any
+            AggregatedMerk(_, None) => Ok(3),
+            AggregatedMerk(_, Some(_)) => Ok(19),
+            // 1 for the enum type and the length of the varint count
+            CountedMerk(count) => Ok(1 + count.encode_var_vec().len()),
         }
     }
 }
@@ -123,6 +177,32 @@ impl Decode for TreeFeatureType {
                 let encoded_sum: i64 = input.read_varint()?;
                 Ok(SummedMerk(encoded_sum))
             }
+            [2] => {
+                let mut encoded_sum = [0; 16];
+                input.read_exact(&mut encoded_sum)?;
+                Ok(BigSummedMerk(i128::from_be_bytes(encoded_sum)))
+            }
+            [3] => {
+                let mut aggregate_id_and_has_value = [0; 2];
+                input.read_exact(&mut aggregate_id_and_has_value)?;
+                let [aggregate_id, has_value] = aggregate_id_and_has_value;
+                match has_value {
+                    0 => Ok(AggregatedMerk(aggregate_id, None)),
+                    1 => {
+                        let mut encoded_value = [0; 16];
+                        input.read_exact(&mut encoded_value)?;
+                        Ok(AggregatedMerk(
+                            aggregate_id,
+                            Some(i128::from_be_bytes(encoded_value)),
+                        ))
+                    }
+                    _ => Err(ed::Error::UnexpectedByte(has_value)),
+                }
+            }
+            [4] => {
+                let encoded_count: u64 = input.read_varint()?;
+                Ok(CountedMerk(encoded_count))
+            }
             _ => Err(ed::Error::UnexpectedByte(55)),
         }
     }
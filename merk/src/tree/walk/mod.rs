@@ -33,6 +33,9 @@ mod fetch;
 #[cfg(feature = "full")]
 mod ref_walker;
 
+#[cfg(feature = "full")]
+use std::cmp::Ordering;
+
 #[cfg(feature = "full")]
 pub use fetch::Fetch;
 #[cfg(feature = "full")]
@@ -240,6 +243,80 @@ where
         });
         self.wrap_with_cost(cost)
     }
+
+    /// Similar to `Tree#value_mut_with_invalidation`.
+    pub fn value_mut_with_invalidation<F>(mut self, f: F) -> CostContext<Self>
+    where
+        F: FnOnce(&mut Vec<u8>),
+    {
+        let mut cost = OperationCost::default();
+        self.tree
+            .own(|t| t.value_mut_with_invalidation(f).unwrap_add_cost(&mut cost));
+        self.wrap_with_cost(cost)
+    }
+
+    /// Walks down to the node with the given key and applies `f` to its value
+    /// with `Tree#value_mut_with_invalidation`. Every node on the way down is
+    /// reattached as `Link::Modified` if the key is found, and left as it was
+    /// otherwise. Returns whether the key was found.
+    pub fn value_mut_at_key_with_invalidation<F>(
+        self,
+        key: &[u8],
+        f: F,
+    ) -> CostResult<(Self, bool), Error>
+    where
+        F: FnOnce(&mut Vec<u8>),
+    {
+        let mut cost = OperationCost::default();
+        let left = match key.cmp(self.tree().key()) {
+            Ordering::Equal => {
+                let walker = self
+                    .value_mut_with_invalidation(f)
+                    .unwrap_add_cost(&mut cost);
+                return Ok((walker, true)).wrap_with_cost(cost);
+            }
+            ordering => ordering == Ordering::Less,
+        };
+
+        // Hash, sum and kind of the link to the child, to put it back as it
+        // was if the key isn't below it
+        let unchanged_link = match self.tree().link(left) {
+            None => return Ok((self, false)).wrap_with_cost(cost),
+            Some(Link::Modified { .. }) => None,
+            Some(link @ Link::Uncommitted { child_heights, .. }) => {
+                Some((*link.hash(), link.sum(), *child_heights, true))
+            }
+            Some(
+                link @ (Link::Reference { child_heights, .. } | Link::Loaded { child_heights, .. }),
+            ) => Some((*link.hash(), link.sum(), *child_heights, false)),
+        };
+
+        let (mut walker, child) = cost_return_on_error!(&mut cost, self.detach_expect(left));
+        let (child, found) =
+            cost_return_on_error!(&mut cost, child.value_mut_at_key_with_invalidation(key, f));
+        let child = child.into_inner();
+        match unchanged_link {
+            Some((hash, sum, child_heights, uncommitted)) if !found => {
+                *walker.tree.slot_mut(left) = Some(if uncommitted {
+                    Link::Uncommitted {
+                        hash,
+                        child_heights,
+                        tree: child,
+                        sum,
+                    }
+                } else {
+                    Link::Loaded {
+                        hash,
+                        child_heights,
+                        tree: child,
+                        sum,
+                    }
+                });
+            }
+            _ => walker.tree.own(|t| t.attach(left, Some(child))),
+        }
+        Ok((walker, found)).wrap_with_cost(cost)
+    }
 }
 
 #[cfg(feature = "full")]
@@ -116,7 +116,7 @@ impl GroveDbWrapper {
                         callback(&channel);
                     }
                     DbMessage::StartTransaction(callback) => {
-                        transaction = Some(grove_db.start_transaction());
+                        transaction = Some(grove_db.start_transaction().unwrap());
                         callback(&channel);
                     }
                     DbMessage::CommitTransaction(callback) => {
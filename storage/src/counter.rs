@@ -0,0 +1,88 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Counter merge operator.
+//!
+//! Counters are stored in auxiliary storage as little-endian `i64` values.
+//! Increments are written as merge operands holding the delta, which the
+//! storage folds into the stored value on read and compaction. Merges never
+//! read the current value, so increments are blind writes which can't lose
+//! updates. Optimistic transactions still track merged keys, so transactions
+//! incrementing the same counter concurrently conflict on commit.
+
+/// Name the counter merge operator is registered with
+pub const COUNTER_MERGE_OPERATOR_NAME: &str = "grovedb_counter_add";
+
+/// Encodes a counter value or increment operand
+pub fn encode_counter(value: i64) -> [u8; 8] {
+    value.to_le_bytes()
+}
+
+/// Decodes a counter value or increment operand, returns `None` if `bytes`
+/// are not a valid encoding
+pub fn decode_counter(bytes: &[u8]) -> Option<i64> {
+    Some(i64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// Folds increment `operands` into the `existing` counter value. Returns
+/// `None` if any of the values is not a valid encoding.
+pub(crate) fn fold_counter<'a>(
+    existing: Option<&[u8]>,
+    operands: impl IntoIterator<Item = &'a [u8]>,
+) -> Option<Vec<u8>> {
+    let mut value = match existing {
+        Some(existing) => decode_counter(existing)?,
+        None => 0,
+    };
+    for operand in operands {
+        value = value.wrapping_add(decode_counter(operand)?);
+    }
+    Some(encode_counter(value).to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fold_counter() {
+        let operands = [encode_counter(5), encode_counter(-2)];
+        assert_eq!(
+            fold_counter(None, operands.iter().map(|o| o.as_slice())),
+            Some(encode_counter(3).to_vec())
+        );
+        assert_eq!(
+            fold_counter(
+                Some(&encode_counter(10)),
+                operands.iter().map(|o| o.as_slice())
+            ),
+            Some(encode_counter(13).to_vec())
+        );
+        assert_eq!(fold_counter(Some(b"bad"), []), None);
+    }
+}
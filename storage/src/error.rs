@@ -36,9 +36,23 @@ pub enum Error {
     StorageError(String),
     /// Cost Error
     #[error("cost error: {0}")]
-    CostError(grovedb_costs::error::Error),
+    CostError(#[from] grovedb_costs::error::Error),
     /// Rocks DB error
     #[error("rocksDB error: {0}")]
     #[cfg(feature = "rocksdb_storage")]
     RocksDBError(#[from] rocksdb::Error),
+    /// The database has column families this version does not know of
+    #[error("unknown column families: {0:?}")]
+    UnknownColumnFamilies(Vec<String>),
+    /// The database files are corrupted
+    #[error("corruption: {0}")]
+    Corruption(String),
+    /// A write or a transaction was attempted on a read-only secondary
+    /// storage
+    #[error("read-only storage: {0}")]
+    ReadOnly(&'static str),
+    /// The prefix derivation the database is opened with doesn't match the
+    /// one it was created with, or doesn't support an operation
+    #[error("prefix derivation error: {0}")]
+    PrefixDerivation(&'static str),
 }
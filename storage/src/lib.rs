@@ -30,7 +30,10 @@
 
 #![deny(missing_docs)]
 
+pub mod counter;
 pub mod error;
+pub mod memory_storage;
+mod prefix;
 #[cfg(feature = "rocksdb_storage")]
 pub mod rocksdb_storage;
 mod storage;
@@ -38,5 +41,8 @@ pub mod worst_case_costs;
 
 pub use crate::{
     error::Error,
-    storage::{Batch, ChildrenSizes, RawIterator, Storage, StorageBatch, StorageContext},
+    storage::{
+        Batch, ChildrenSizes, RawIterator, RawIteratorOptions, Storage, StorageBatch,
+        StorageContext,
+    },
 };
@@ -0,0 +1,189 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Raw iterator over the records of an in-memory storage context.
+
+use grovedb_costs::{CostContext, CostsExt, OperationCost};
+
+use crate::{prefix::SubtreePrefix, RawIterator};
+
+/// 256 bytes for the key and 32 bytes for the prefix
+const MAX_PREFIXED_KEY_LENGTH: u32 = 256 + 32;
+
+/// Raw iterator over the records of a subtree of an in-memory storage, taken
+/// when the iterator is created. Costs are the same as those of RocksDB
+/// iterators.
+pub struct InMemoryRawIterator {
+    prefix: SubtreePrefix,
+    /// Prefixed records of the subtree, ordered by key
+    records: Vec<(Vec<u8>, Vec<u8>)>,
+    /// Prefixed exclusive upper bound of visited keys
+    upper_bound: Option<Vec<u8>>,
+    /// Whether a record is keyed exactly by the upper bound or, without one,
+    /// by the next prefix. RocksDB iterators step over such a record when
+    /// seeking backward from the bound, paying for one more seek.
+    bound_hit: bool,
+    position: Option<usize>,
+}
+
+impl InMemoryRawIterator {
+    /// Create an iterator over the prefixed `records`, ordered by key and
+    /// below `upper_bound` if any
+    pub(crate) fn new(
+        prefix: SubtreePrefix,
+        records: Vec<(Vec<u8>, Vec<u8>)>,
+        upper_bound: Option<Vec<u8>>,
+        bound_hit: bool,
+    ) -> Self {
+        InMemoryRawIterator {
+            prefix,
+            records,
+            upper_bound,
+            bound_hit,
+            position: None,
+        }
+    }
+
+    fn current(&self) -> Option<&(Vec<u8>, Vec<u8>)> {
+        self.position
+            .and_then(|position| self.records.get(position))
+    }
+
+    /// Positions the iterator on the last record keyed at most by `key`
+    fn seek_at_most(&mut self, key: &[u8]) {
+        let count = self
+            .records
+            .partition_point(|(record_key, _)| record_key.as_slice() <= key);
+        self.position = count.checked_sub(1);
+    }
+}
+
+impl RawIterator for InMemoryRawIterator {
+    fn seek_to_first(&mut self) -> CostContext<()> {
+        self.position = Some(0);
+        ().wrap_with_cost(OperationCost::with_seek_count(1))
+    }
+
+    fn seek_to_last(&mut self) -> CostContext<()> {
+        let mut cost = OperationCost::with_seek_count(1);
+        if self.bound_hit {
+            cost.seek_count += 1;
+        }
+        self.position = self.records.len().checked_sub(1);
+        ().wrap_with_cost(cost)
+    }
+
+    fn seek<K: AsRef<[u8]>>(&mut self, key: K) -> CostContext<()> {
+        let mut prefixed_key = self.prefix.clone();
+        prefixed_key.extend_from_slice(key.as_ref());
+        self.position = Some(
+            self.records
+                .partition_point(|(record_key, _)| *record_key < prefixed_key),
+        );
+        ().wrap_with_cost(OperationCost::with_seek_count(1))
+    }
+
+    fn seek_for_prev<K: AsRef<[u8]>>(&mut self, key: K) -> CostContext<()> {
+        let mut cost = OperationCost::with_seek_count(1);
+        let mut prefixed_key = self.prefix.clone();
+        prefixed_key.extend_from_slice(key.as_ref());
+        if self.bound_hit
+            && matches!(&self.upper_bound, Some(upper_bound) if prefixed_key >= *upper_bound)
+        {
+            cost.seek_count += 1;
+        }
+        self.seek_at_most(&prefixed_key);
+        ().wrap_with_cost(cost)
+    }
+
+    fn next(&mut self) -> CostContext<()> {
+        self.position = self
+            .position
+            .map(|position| position + 1)
+            .filter(|position| *position < self.records.len());
+        ().wrap_with_cost(OperationCost::with_seek_count(1))
+    }
+
+    fn prev(&mut self) -> CostContext<()> {
+        self.position = self.position.and_then(|position| position.checked_sub(1));
+        ().wrap_with_cost(OperationCost::with_seek_count(1))
+    }
+
+    fn value(&self) -> CostContext<Option<&[u8]>> {
+        let mut cost = OperationCost::default();
+
+        let value = if self.valid().unwrap_add_cost(&mut cost) {
+            self.current().map(|(_, v)| {
+                cost.storage_loaded_bytes += v.len() as u32;
+                v.as_slice()
+            })
+        } else {
+            None
+        };
+
+        value.wrap_with_cost(cost)
+    }
+
+    fn key(&self) -> CostContext<Option<&[u8]>> {
+        let mut cost = OperationCost::default();
+
+        let value = match self.current() {
+            Some((k, _)) => {
+                cost.storage_loaded_bytes += k.len() as u32;
+                Some(k.split_at(self.prefix.len()).1)
+            }
+            None => {
+                // out of the subtree, a fixed sized cost is paid so that costs don't depend
+                // on the ordering of subtrees, as with RocksDB
+                cost.storage_loaded_bytes += MAX_PREFIXED_KEY_LENGTH;
+                None
+            }
+        };
+
+        value.wrap_with_cost(cost)
+    }
+
+    fn valid(&self) -> CostContext<bool> {
+        let mut cost = OperationCost::default();
+
+        let valid = match self.current() {
+            Some((k, _)) => {
+                cost.storage_loaded_bytes += k.len() as u32;
+                true
+            }
+            None => {
+                // out of the subtree, a fixed sized cost is paid so that costs don't depend
+                // on the ordering of subtrees, as with RocksDB
+                cost.storage_loaded_bytes += MAX_PREFIXED_KEY_LENGTH;
+                false
+            }
+        };
+
+        valid.wrap_with_cost(cost)
+    }
+}
@@ -0,0 +1,588 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Implementation for a storage abstraction kept in memory.
+
+use std::{cell::RefCell, collections::BTreeMap, path::Path};
+
+use grovedb_costs::{
+    cost_return_on_error, cost_return_on_error_no_add,
+    storage_cost::{
+        key_value_cost::KeyValueStorageCost, removal::StorageRemovedBytes::BasicStorageRemoval,
+    },
+    CostContext, CostResult, CostsExt, OperationCost,
+};
+use grovedb_path::SubtreePath;
+use integer_encoding::VarInt;
+
+use super::{InMemoryImmediateStorageContext, InMemoryRawIterator, InMemoryStorageContext};
+use crate::{
+    counter::fold_counter,
+    error::Error::{self, CostError, StorageError},
+    prefix::{build_hashed_prefix, hashed_prefix_worst_case_cost, next_prefix, SubtreePrefix},
+    storage::AbstractBatchOperation,
+    worst_case_costs::WorstKeyLength,
+    Storage, StorageBatch,
+};
+
+/// Column a record is kept in, matching the column families of RocksDB
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Column {
+    Data,
+    Aux,
+    Roots,
+    Meta,
+}
+
+/// Records of every column, ordered by key
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Columns<V> {
+    data: BTreeMap<Vec<u8>, V>,
+    aux: BTreeMap<Vec<u8>, V>,
+    roots: BTreeMap<Vec<u8>, V>,
+    meta: BTreeMap<Vec<u8>, V>,
+}
+
+impl<V> Columns<V> {
+    fn column(&self, column: Column) -> &BTreeMap<Vec<u8>, V> {
+        match column {
+            Column::Data => &self.data,
+            Column::Aux => &self.aux,
+            Column::Roots => &self.roots,
+            Column::Meta => &self.meta,
+        }
+    }
+
+    fn column_mut(&mut self, column: Column) -> &mut BTreeMap<Vec<u8>, V> {
+        match column {
+            Column::Data => &mut self.data,
+            Column::Aux => &mut self.aux,
+            Column::Roots => &mut self.roots,
+            Column::Meta => &mut self.meta,
+        }
+    }
+
+    fn into_records(self) -> impl Iterator<Item = (Column, Vec<u8>, V)> {
+        let Columns {
+            data,
+            aux,
+            roots,
+            meta,
+        } = self;
+        data.into_iter()
+            .map(|(key, value)| (Column::Data, key, value))
+            .chain(
+                aux.into_iter()
+                    .map(|(key, value)| (Column::Aux, key, value)),
+            )
+            .chain(
+                roots
+                    .into_iter()
+                    .map(|(key, value)| (Column::Roots, key, value)),
+            )
+            .chain(
+                meta.into_iter()
+                    .map(|(key, value)| (Column::Meta, key, value)),
+            )
+    }
+}
+
+/// Writes not applied to the storage yet, `None` values being deletions
+type Writes = Columns<Option<Vec<u8>>>;
+
+/// Writes of a transaction replaced since a savepoint, `None` values being
+/// keys it hadn't written
+type Undo = Columns<Option<Option<Vec<u8>>>>;
+
+/// Storage which keeps its records in memory, losing them once dropped.
+/// Subtree prefixes and costs are the same as those of a RocksDB storage with
+/// the default hashed prefix scheme.
+#[derive(Debug, Default)]
+pub struct InMemoryStorage {
+    columns: RefCell<Columns<Vec<u8>>>,
+}
+
+/// Transaction over an in-memory storage. Its writes are kept aside until it
+/// is committed. Unlike RocksDB optimistic transactions, commits don't check
+/// for conflicting writes.
+#[derive(Debug, Default)]
+pub struct InMemoryTransaction {
+    writes: RefCell<Writes>,
+    savepoints: RefCell<Vec<Undo>>,
+    /// Committed records when a snapshot transaction was started
+    snapshot: Option<Columns<Vec<u8>>>,
+}
+
+impl InMemoryStorage {
+    /// Create an empty in-memory storage
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A helper method to build a prefix to storage keys of a subtree
+    pub fn build_prefix<B>(path: SubtreePath<B>) -> CostContext<SubtreePrefix>
+    where
+        B: AsRef<[u8]>,
+    {
+        build_hashed_prefix(path).map(|prefix| prefix.to_vec())
+    }
+
+    /// Get a record, as seen from `transaction` if any
+    pub(crate) fn get(
+        &self,
+        column: Column,
+        key: &[u8],
+        transaction: Option<&InMemoryTransaction>,
+    ) -> Option<Vec<u8>> {
+        if let Some(write) =
+            transaction.and_then(|tx| tx.writes.borrow().column(column).get(key).cloned())
+        {
+            return write;
+        }
+        self.with_committed(column, transaction, |records| records.get(key).cloned())
+    }
+
+    /// Calls `f` with the committed records of a column, as of the start of
+    /// `transaction` if it is a snapshot transaction
+    fn with_committed<R>(
+        &self,
+        column: Column,
+        transaction: Option<&InMemoryTransaction>,
+        f: impl FnOnce(&BTreeMap<Vec<u8>, Vec<u8>>) -> R,
+    ) -> R {
+        match transaction.and_then(|tx| tx.snapshot.as_ref()) {
+            Some(snapshot) => f(snapshot.column(column)),
+            None => f(self.columns.borrow().column(column)),
+        }
+    }
+
+    /// Put or delete a record, directly or into `transaction` if any
+    pub(crate) fn write(
+        &self,
+        column: Column,
+        key: Vec<u8>,
+        value: Option<Vec<u8>>,
+        transaction: Option<&InMemoryTransaction>,
+    ) {
+        match transaction {
+            Some(transaction) => {
+                let previous = transaction
+                    .writes
+                    .borrow_mut()
+                    .column_mut(column)
+                    .insert(key.clone(), value);
+                // Only the first write of a key since the savepoint needs
+                // undoing
+                if let Some(undo) = transaction.savepoints.borrow_mut().last_mut() {
+                    undo.column_mut(column).entry(key).or_insert(previous);
+                }
+            }
+            None => self.apply(column, key, value),
+        }
+    }
+
+    fn apply(&self, column: Column, key: Vec<u8>, value: Option<Vec<u8>>) {
+        let mut columns = self.columns.borrow_mut();
+        match value {
+            Some(value) => {
+                columns.column_mut(column).insert(key, value);
+            }
+            None => {
+                columns.column_mut(column).remove(&key);
+            }
+        }
+    }
+
+    /// Fold a counter increment `operand` into an auxiliary record, see
+    /// [`crate::counter`]
+    pub(crate) fn merge_aux(
+        &self,
+        key: Vec<u8>,
+        operand: &[u8],
+        transaction: Option<&InMemoryTransaction>,
+    ) -> Result<(), Error> {
+        let existing = self.get(Column::Aux, &key, transaction);
+        let value = fold_counter(existing.as_deref(), [operand]).ok_or_else(invalid_counter)?;
+        self.write(Column::Aux, key, Some(value), transaction);
+        Ok(())
+    }
+
+    /// Records of a column with keys starting with `prefix` and below
+    /// `upper_bound`, ordered by key and seen from `transaction` if any
+    fn records(
+        &self,
+        column: Column,
+        prefix: &[u8],
+        upper_bound: Option<&[u8]>,
+        transaction: Option<&InMemoryTransaction>,
+    ) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let in_range = |key: &[u8]| {
+            key.starts_with(prefix)
+                && !matches!(upper_bound, Some(upper_bound) if key >= upper_bound)
+        };
+        let mut records: BTreeMap<Vec<u8>, Option<Vec<u8>>> =
+            self.with_committed(column, transaction, |committed| {
+                committed
+                    .range(prefix.to_vec()..)
+                    .take_while(|(key, _)| in_range(key))
+                    .map(|(key, value)| (key.clone(), Some(value.clone())))
+                    .collect()
+            });
+        if let Some(transaction) = transaction {
+            records.extend(
+                transaction
+                    .writes
+                    .borrow()
+                    .column(column)
+                    .range(prefix.to_vec()..)
+                    .take_while(|(key, _)| in_range(key))
+                    .map(|(key, value)| (key.clone(), value.clone())),
+            );
+        }
+        records
+            .into_iter()
+            .filter_map(|(key, value)| Some((key, value?)))
+            .collect()
+    }
+
+    /// Raw iterator over the records of a column with keys starting with
+    /// `prefix` and below the prefixed `upper_bound`, seen from `transaction`
+    /// if any
+    pub(crate) fn raw_iter(
+        &self,
+        column: Column,
+        prefix: &SubtreePrefix,
+        upper_bound: Option<Vec<u8>>,
+        transaction: Option<&InMemoryTransaction>,
+    ) -> InMemoryRawIterator {
+        let records = self.records(column, prefix, upper_bound.as_deref(), transaction);
+        let bound_hit = if let Some(bound) = upper_bound.clone().or_else(|| next_prefix(prefix)) {
+            self.get(column, &bound, transaction).is_some()
+        } else {
+            false
+        };
+        InMemoryRawIterator::new(prefix.clone(), records, upper_bound, bound_hit)
+    }
+
+    /// Applies a multi-context batch, directly or into `transaction` if any.
+    /// Returns pending costs, which are only paid once the whole batch is
+    /// applied.
+    pub(crate) fn write_batch(
+        &self,
+        storage_batch: StorageBatch,
+        transaction: Option<&InMemoryTransaction>,
+    ) -> CostResult<OperationCost, Error> {
+        let mut cost = OperationCost::default();
+        let mut pending_costs = OperationCost::default();
+        // Writes are staged so that a failing batch leaves the storage untouched
+        let mut staged = Writes::default();
+        let staged_get =
+            |staged: &Writes, column: Column, key: &[u8]| match staged.column(column).get(key) {
+                Some(write) => write.clone(),
+                None => self.get(column, key, transaction),
+            };
+
+        for op in storage_batch.into_iter() {
+            let (column, key, value) = match op {
+                AbstractBatchOperation::Put {
+                    key,
+                    value,
+                    children_sizes,
+                    cost_info,
+                } => {
+                    cost.seek_count += 1;
+                    cost_return_on_error_no_add!(
+                        &cost,
+                        pending_costs
+                            .add_key_value_storage_costs(
+                                key.len() as u32,
+                                value.len() as u32,
+                                children_sizes,
+                                cost_info
+                            )
+                            .map_err(CostError)
+                    );
+                    (Column::Data, key, Some(value))
+                }
+                AbstractBatchOperation::PutAux {
+                    key,
+                    value,
+                    cost_info,
+                } => {
+                    cost.seek_count += 1;
+                    cost_return_on_error_no_add!(
+                        &cost,
+                        pending_costs
+                            .add_key_value_storage_costs(
+                                key.len() as u32,
+                                value.len() as u32,
+                                None,
+                                cost_info
+                            )
+                            .map_err(CostError)
+                    );
+                    (Column::Aux, key, Some(value))
+                }
+                AbstractBatchOperation::MergeAux { key, operand } => {
+                    // The folded counter replaces the stored one, so merges
+                    // don't add storage
+                    cost.seek_count += 1;
+                    let existing = staged_get(&staged, Column::Aux, &key);
+                    let value = cost_return_on_error_no_add!(
+                        &cost,
+                        fold_counter(existing.as_deref(), [operand.as_slice()])
+                            .ok_or_else(invalid_counter)
+                    );
+                    (Column::Aux, key, Some(value))
+                }
+                AbstractBatchOperation::PutRoot {
+                    key,
+                    value,
+                    cost_info,
+                } => {
+                    cost.seek_count += 1;
+                    // We only add costs for put root if they are set, otherwise it is free
+                    if cost_info.is_some() {
+                        cost_return_on_error_no_add!(
+                            &cost,
+                            pending_costs
+                                .add_key_value_storage_costs(
+                                    key.len() as u32,
+                                    value.len() as u32,
+                                    None,
+                                    cost_info
+                                )
+                                .map_err(CostError)
+                        );
+                    }
+                    (Column::Roots, key, Some(value))
+                }
+                AbstractBatchOperation::PutMeta {
+                    key,
+                    value,
+                    cost_info,
+                } => {
+                    cost.seek_count += 1;
+                    cost_return_on_error_no_add!(
+                        &cost,
+                        pending_costs
+                            .add_key_value_storage_costs(
+                                key.len() as u32,
+                                value.len() as u32,
+                                None,
+                                cost_info
+                            )
+                            .map_err(CostError)
+                    );
+                    (Column::Meta, key, Some(value))
+                }
+                AbstractBatchOperation::Delete { key, cost_info } => {
+                    add_removal_costs(&key, cost_info, &mut cost, &mut pending_costs, || {
+                        staged_get(&staged, Column::Data, &key)
+                    });
+                    (Column::Data, key, None)
+                }
+                AbstractBatchOperation::DeleteAux { key, cost_info } => {
+                    add_removal_costs(&key, cost_info, &mut cost, &mut pending_costs, || {
+                        staged_get(&staged, Column::Aux, &key)
+                    });
+                    (Column::Aux, key, None)
+                }
+                AbstractBatchOperation::DeleteRoot { key, cost_info } => {
+                    add_removal_costs(&key, cost_info, &mut cost, &mut pending_costs, || {
+                        staged_get(&staged, Column::Roots, &key)
+                    });
+                    (Column::Roots, key, None)
+                }
+                AbstractBatchOperation::DeleteMeta { key, cost_info } => {
+                    add_removal_costs(&key, cost_info, &mut cost, &mut pending_costs, || {
+                        staged_get(&staged, Column::Meta, &key)
+                    });
+                    (Column::Meta, key, None)
+                }
+            };
+            staged.column_mut(column).insert(key, value);
+        }
+
+        for (column, key, value) in staged.into_records() {
+            self.write(column, key, value, transaction);
+        }
+        Ok(pending_costs).wrap_with_cost(cost)
+    }
+}
+
+fn invalid_counter() -> Error {
+    StorageError("invalid counter value or merge operand".to_owned())
+}
+
+/// Adds the costs of removing a record, loading the record to measure it if
+/// the removed bytes are not known
+fn add_removal_costs(
+    key: &[u8],
+    cost_info: Option<KeyValueStorageCost>,
+    cost: &mut OperationCost,
+    pending_costs: &mut OperationCost,
+    stored_value: impl FnOnce() -> Option<Vec<u8>>,
+) {
+    if let Some(key_value_removed_bytes) = cost_info {
+        cost.seek_count += 1;
+        pending_costs.storage_cost.removed_bytes +=
+            key_value_removed_bytes.combined_removed_bytes();
+    } else {
+        cost.seek_count += 2;
+        let value_len = stored_value().map(|x| x.len() as u32).unwrap_or(0);
+        cost.storage_loaded_bytes += value_len;
+        let key_len = key.len() as u32;
+        pending_costs.storage_cost.removed_bytes += BasicStorageRemoval(
+            key_len
+                + value_len
+                + key_len.required_space() as u32
+                + value_len.required_space() as u32,
+        );
+    }
+}
+
+impl<'db> Storage<'db> for InMemoryStorage {
+    type BatchStorageContext = InMemoryStorageContext<'db>;
+    type BatchTransactionalStorageContext = InMemoryStorageContext<'db>;
+    type ImmediateStorageContext = InMemoryImmediateStorageContext<'db>;
+    type Transaction = InMemoryTransaction;
+
+    fn start_transaction(&'db self) -> Result<Self::Transaction, Error> {
+        Ok(InMemoryTransaction::default())
+    }
+
+    fn start_snapshot_transaction(&'db self) -> Result<Self::Transaction, Error> {
+        Ok(InMemoryTransaction {
+            snapshot: Some(self.columns.borrow().clone()),
+            ..Default::default()
+        })
+    }
+
+    fn commit_transaction(&self, transaction: Self::Transaction) -> CostResult<(), Error> {
+        // All transaction costs were provided on method calls
+        for (column, key, value) in transaction.writes.into_inner().into_records() {
+            self.apply(column, key, value);
+        }
+        Ok(()).wrap_with_cost(Default::default())
+    }
+
+    fn rollback_transaction(&self, transaction: &Self::Transaction) -> Result<(), Error> {
+        *transaction.writes.borrow_mut() = Writes::default();
+        transaction.savepoints.borrow_mut().clear();
+        Ok(())
+    }
+
+    fn set_transaction_savepoint(&self, transaction: &Self::Transaction) {
+        transaction.savepoints.borrow_mut().push(Undo::default());
+    }
+
+    fn rollback_transaction_to_savepoint(
+        &self,
+        transaction: &Self::Transaction,
+    ) -> Result<(), Error> {
+        let undo = transaction
+            .savepoints
+            .borrow_mut()
+            .pop()
+            .ok_or_else(|| StorageError("no savepoint to roll back to".to_owned()))?;
+        let mut writes = transaction.writes.borrow_mut();
+        for (column, key, previous) in undo.into_records() {
+            match previous {
+                Some(previous) => {
+                    writes.column_mut(column).insert(key, previous);
+                }
+                None => {
+                    writes.column_mut(column).remove(&key);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn commit_multi_context_batch(
+        &self,
+        batch: StorageBatch,
+        transaction: Option<&'db Self::Transaction>,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+        let pending_costs = cost_return_on_error!(&mut cost, self.write_batch(batch, transaction));
+        Ok(()).wrap_with_cost(pending_costs).add_cost(cost)
+    }
+
+    fn flush(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn get_storage_context<'b, B>(
+        &'db self,
+        path: SubtreePath<'b, B>,
+        batch: Option<&'db StorageBatch>,
+    ) -> CostContext<Self::BatchStorageContext>
+    where
+        B: AsRef<[u8]> + 'b,
+    {
+        Self::build_prefix(path)
+            .map(|prefix| InMemoryStorageContext::new(self, None, prefix, batch))
+    }
+
+    fn get_transactional_storage_context<'b, B>(
+        &'db self,
+        path: SubtreePath<'b, B>,
+        batch: Option<&'db StorageBatch>,
+        transaction: &'db Self::Transaction,
+    ) -> CostContext<Self::BatchTransactionalStorageContext>
+    where
+        B: AsRef<[u8]> + 'b,
+    {
+        Self::build_prefix(path)
+            .map(|prefix| InMemoryStorageContext::new(self, Some(transaction), prefix, batch))
+    }
+
+    fn get_immediate_storage_context<'b, B>(
+        &'db self,
+        path: SubtreePath<'b, B>,
+        transaction: &'db Self::Transaction,
+    ) -> CostContext<Self::ImmediateStorageContext>
+    where
+        B: AsRef<[u8]> + 'b,
+    {
+        Self::build_prefix(path)
+            .map(|prefix| InMemoryImmediateStorageContext::new(self, transaction, prefix))
+    }
+
+    fn create_checkpoint<P: AsRef<Path>>(&self, _path: P) -> Result<(), Error> {
+        Err(StorageError(
+            "checkpoints are not supported by in-memory storage".to_owned(),
+        ))
+    }
+
+    fn get_storage_context_cost<L: WorstKeyLength>(path: &[L]) -> OperationCost {
+        hashed_prefix_worst_case_cost(path)
+    }
+}
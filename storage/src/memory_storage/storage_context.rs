@@ -0,0 +1,585 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Storage contexts of an in-memory storage.
+
+use grovedb_costs::{
+    cost_return_on_error, storage_cost::key_value_cost::KeyValueStorageCost,
+    ChildrenSizesWithIsSumTree, CostResult, CostsExt, OperationCost,
+};
+use integer_encoding::VarInt;
+
+use super::{
+    storage::{Column, InMemoryStorage},
+    InMemoryRawIterator, InMemoryTransaction,
+};
+use crate::{
+    prefix::{make_prefixed_key, SubtreePrefix},
+    Batch, Error, RawIterator, RawIteratorOptions, StorageBatch, StorageContext,
+};
+
+/// Storage context of a subtree of an in-memory storage, deferring all writes
+/// to a multi-context batch. Reads see the data of a transaction if it has
+/// one.
+pub struct InMemoryStorageContext<'db> {
+    storage: &'db InMemoryStorage,
+    transaction: Option<&'db InMemoryTransaction>,
+    prefix: SubtreePrefix,
+    batch: Option<&'db StorageBatch>,
+}
+
+impl<'db> InMemoryStorageContext<'db> {
+    /// Create a new in-memory storage context instance
+    pub fn new(
+        storage: &'db InMemoryStorage,
+        transaction: Option<&'db InMemoryTransaction>,
+        prefix: SubtreePrefix,
+        batch: Option<&'db StorageBatch>,
+    ) -> Self {
+        InMemoryStorageContext {
+            storage,
+            transaction,
+            prefix,
+            batch,
+        }
+    }
+
+    /// Clears all the data in the tree at the storage level
+    pub fn clear(&mut self) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+
+        let mut iter = self.raw_iter();
+        iter.seek_to_first().unwrap_add_cost(&mut cost);
+
+        while iter.valid().unwrap_add_cost(&mut cost) {
+            if let Some(key) = iter.key().unwrap_add_cost(&mut cost) {
+                cost_return_on_error!(
+                    &mut cost,
+                    // todo: calculate cost
+                    self.delete(key, None)
+                );
+            }
+            iter.next().unwrap_add_cost(&mut cost);
+        }
+        Ok(()).wrap_with_cost(cost)
+    }
+
+    fn get_column(&self, column: Column, key: &[u8]) -> CostResult<Option<Vec<u8>>, Error> {
+        let value = self.storage.get(
+            column,
+            &make_prefixed_key(&self.prefix, key),
+            self.transaction,
+        );
+        let storage_loaded_bytes = value.as_ref().map(|x| x.len() as u32).unwrap_or(0);
+        Ok(value).wrap_with_cost(OperationCost {
+            seek_count: 1,
+            storage_loaded_bytes,
+            ..Default::default()
+        })
+    }
+
+    fn iter_column(&self, column: Column, upper_bound: Option<Vec<u8>>) -> InMemoryRawIterator {
+        self.storage
+            .raw_iter(column, &self.prefix, upper_bound, self.transaction)
+    }
+}
+
+impl<'db> StorageContext<'db> for InMemoryStorageContext<'db> {
+    type Batch = InMemoryBatch;
+    type RawIterator = InMemoryRawIterator;
+
+    fn put<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        value: &[u8],
+        children_sizes: ChildrenSizesWithIsSumTree,
+        cost_info: Option<KeyValueStorageCost>,
+    ) -> CostResult<(), Error> {
+        if let Some(existing_batch) = self.batch {
+            existing_batch.put(
+                make_prefixed_key(&self.prefix, key),
+                value.to_vec(),
+                children_sizes,
+                cost_info,
+            );
+        }
+        Ok(()).wrap_with_cost(OperationCost::default())
+    }
+
+    fn put_aux<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        value: &[u8],
+        cost_info: Option<KeyValueStorageCost>,
+    ) -> CostResult<(), Error> {
+        if let Some(existing_batch) = self.batch {
+            existing_batch.put_aux(
+                make_prefixed_key(&self.prefix, key),
+                value.to_vec(),
+                cost_info,
+            );
+        }
+        Ok(()).wrap_with_cost(OperationCost::default())
+    }
+
+    fn put_root<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        value: &[u8],
+        cost_info: Option<KeyValueStorageCost>,
+    ) -> CostResult<(), Error> {
+        if let Some(existing_batch) = self.batch {
+            existing_batch.put_root(
+                make_prefixed_key(&self.prefix, key),
+                value.to_vec(),
+                cost_info,
+            );
+        }
+        Ok(()).wrap_with_cost(OperationCost::default())
+    }
+
+    fn put_meta<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        value: &[u8],
+        cost_info: Option<KeyValueStorageCost>,
+    ) -> CostResult<(), Error> {
+        if let Some(existing_batch) = self.batch {
+            existing_batch.put_meta(
+                make_prefixed_key(&self.prefix, key),
+                value.to_vec(),
+                cost_info,
+            );
+        }
+        Ok(()).wrap_with_cost(OperationCost::default())
+    }
+
+    fn merge_aux<K: AsRef<[u8]>>(&self, key: K, operand: &[u8]) -> CostResult<(), Error> {
+        if let Some(existing_batch) = self.batch {
+            existing_batch.merge_aux(make_prefixed_key(&self.prefix, key), operand.to_vec());
+        }
+        Ok(()).wrap_with_cost(OperationCost::default())
+    }
+
+    fn delete<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        cost_info: Option<KeyValueStorageCost>,
+    ) -> CostResult<(), Error> {
+        if let Some(existing_batch) = self.batch {
+            existing_batch.delete(make_prefixed_key(&self.prefix, key), cost_info);
+        }
+        Ok(()).wrap_with_cost(OperationCost::default())
+    }
+
+    fn delete_aux<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        cost_info: Option<KeyValueStorageCost>,
+    ) -> CostResult<(), Error> {
+        if let Some(existing_batch) = self.batch {
+            existing_batch.delete_aux(make_prefixed_key(&self.prefix, key), cost_info);
+        }
+        Ok(()).wrap_with_cost(OperationCost::default())
+    }
+
+    fn delete_root<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        cost_info: Option<KeyValueStorageCost>,
+    ) -> CostResult<(), Error> {
+        if let Some(existing_batch) = self.batch {
+            existing_batch.delete_root(make_prefixed_key(&self.prefix, key), cost_info);
+        }
+        Ok(()).wrap_with_cost(OperationCost::default())
+    }
+
+    fn delete_meta<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        cost_info: Option<KeyValueStorageCost>,
+    ) -> CostResult<(), Error> {
+        if let Some(existing_batch) = self.batch {
+            existing_batch.delete_meta(make_prefixed_key(&self.prefix, key), cost_info);
+        }
+        Ok(()).wrap_with_cost(OperationCost::default())
+    }
+
+    fn get<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error> {
+        self.get_column(Column::Data, key.as_ref())
+    }
+
+    fn get_staged<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error> {
+        let pending = self
+            .batch
+            .and_then(|batch| batch.get(&make_prefixed_key(&self.prefix, &key)));
+        match pending {
+            Some(value) => {
+                let storage_loaded_bytes = value.as_ref().map(|x| x.len() as u32).unwrap_or(0);
+                Ok(value).wrap_with_cost(OperationCost {
+                    seek_count: 1,
+                    storage_loaded_bytes,
+                    ..Default::default()
+                })
+            }
+            None => self.get(key),
+        }
+    }
+
+    fn get_aux<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error> {
+        self.get_column(Column::Aux, key.as_ref())
+    }
+
+    fn get_root<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error> {
+        self.get_column(Column::Roots, key.as_ref())
+    }
+
+    fn get_meta<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error> {
+        self.get_column(Column::Meta, key.as_ref())
+    }
+
+    fn new_batch(&self) -> Self::Batch {
+        InMemoryBatch {
+            prefix: self.prefix.clone(),
+            batch: StorageBatch::new(),
+        }
+    }
+
+    fn commit_batch(&self, batch: Self::Batch) -> CostResult<(), Error> {
+        if let Some(existing_batch) = self.batch {
+            existing_batch.merge(batch.batch);
+        }
+        Ok(()).wrap_with_cost(OperationCost::default())
+    }
+
+    fn raw_iter(&self) -> Self::RawIterator {
+        self.iter_column(Column::Data, None)
+    }
+
+    fn raw_iter_with_options(&self, options: &RawIteratorOptions) -> Self::RawIterator {
+        // Read tuning has no effect on records kept in memory
+        self.iter_column(
+            Column::Data,
+            options
+                .iterate_upper_bound
+                .as_ref()
+                .map(|upper_bound| make_prefixed_key(&self.prefix, upper_bound)),
+        )
+    }
+
+    fn raw_iter_aux(&self) -> Self::RawIterator {
+        self.iter_column(Column::Aux, None)
+    }
+
+    fn raw_iter_roots(&self) -> Self::RawIterator {
+        self.iter_column(Column::Roots, None)
+    }
+
+    fn raw_iter_meta(&self) -> Self::RawIterator {
+        self.iter_column(Column::Meta, None)
+    }
+}
+
+/// Storage context of a subtree of an in-memory storage, applying all writes
+/// straight to a transaction.
+pub struct InMemoryImmediateStorageContext<'db> {
+    storage: &'db InMemoryStorage,
+    transaction: &'db InMemoryTransaction,
+    prefix: SubtreePrefix,
+}
+
+impl<'db> InMemoryImmediateStorageContext<'db> {
+    /// Create a new in-memory immediate storage context instance
+    pub fn new(
+        storage: &'db InMemoryStorage,
+        transaction: &'db InMemoryTransaction,
+        prefix: SubtreePrefix,
+    ) -> Self {
+        InMemoryImmediateStorageContext {
+            storage,
+            transaction,
+            prefix,
+        }
+    }
+
+    fn put_column<K: AsRef<[u8]>>(
+        &self,
+        column: Column,
+        key: K,
+        value: Option<&[u8]>,
+    ) -> CostResult<(), Error> {
+        self.storage.write(
+            column,
+            make_prefixed_key(&self.prefix, key),
+            value.map(|value| value.to_vec()),
+            Some(self.transaction),
+        );
+        Ok(()).wrap_with_cost(Default::default())
+    }
+
+    fn get_column(&self, column: Column, key: &[u8]) -> CostResult<Option<Vec<u8>>, Error> {
+        Ok(self.storage.get(
+            column,
+            &make_prefixed_key(&self.prefix, key),
+            Some(self.transaction),
+        ))
+        .wrap_with_cost(Default::default())
+    }
+
+    fn iter_column(&self, column: Column, upper_bound: Option<Vec<u8>>) -> InMemoryRawIterator {
+        self.storage
+            .raw_iter(column, &self.prefix, upper_bound, Some(self.transaction))
+    }
+}
+
+impl<'db> StorageContext<'db> for InMemoryImmediateStorageContext<'db> {
+    type Batch = InMemoryBatch;
+    type RawIterator = InMemoryRawIterator;
+
+    fn put<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        value: &[u8],
+        _children_sizes: ChildrenSizesWithIsSumTree,
+        _cost_info: Option<KeyValueStorageCost>,
+    ) -> CostResult<(), Error> {
+        self.put_column(Column::Data, key, Some(value))
+    }
+
+    fn put_aux<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        value: &[u8],
+        _cost_info: Option<KeyValueStorageCost>,
+    ) -> CostResult<(), Error> {
+        self.put_column(Column::Aux, key, Some(value))
+    }
+
+    fn put_root<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        value: &[u8],
+        _cost_info: Option<KeyValueStorageCost>,
+    ) -> CostResult<(), Error> {
+        self.put_column(Column::Roots, key, Some(value))
+    }
+
+    fn put_meta<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        value: &[u8],
+        _cost_info: Option<KeyValueStorageCost>,
+    ) -> CostResult<(), Error> {
+        self.put_column(Column::Meta, key, Some(value))
+    }
+
+    fn merge_aux<K: AsRef<[u8]>>(&self, key: K, operand: &[u8]) -> CostResult<(), Error> {
+        self.storage
+            .merge_aux(
+                make_prefixed_key(&self.prefix, key),
+                operand,
+                Some(self.transaction),
+            )
+            .wrap_with_cost(Default::default())
+    }
+
+    fn delete<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        _cost_info: Option<KeyValueStorageCost>,
+    ) -> CostResult<(), Error> {
+        self.put_column(Column::Data, key, None)
+    }
+
+    fn delete_aux<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        _cost_info: Option<KeyValueStorageCost>,
+    ) -> CostResult<(), Error> {
+        self.put_column(Column::Aux, key, None)
+    }
+
+    fn delete_root<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        _cost_info: Option<KeyValueStorageCost>,
+    ) -> CostResult<(), Error> {
+        self.put_column(Column::Roots, key, None)
+    }
+
+    fn delete_meta<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        _cost_info: Option<KeyValueStorageCost>,
+    ) -> CostResult<(), Error> {
+        self.put_column(Column::Meta, key, None)
+    }
+
+    fn get<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error> {
+        self.get_column(Column::Data, key.as_ref())
+    }
+
+    fn get_staged<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error> {
+        // immediate contexts have no deferred writes
+        self.get(key)
+    }
+
+    fn get_aux<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error> {
+        self.get_column(Column::Aux, key.as_ref())
+    }
+
+    fn get_root<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error> {
+        self.get_column(Column::Roots, key.as_ref())
+    }
+
+    fn get_meta<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error> {
+        self.get_column(Column::Meta, key.as_ref())
+    }
+
+    fn new_batch(&self) -> Self::Batch {
+        InMemoryBatch {
+            prefix: self.prefix.clone(),
+            batch: StorageBatch::new(),
+        }
+    }
+
+    fn commit_batch(&self, batch: Self::Batch) -> CostResult<(), Error> {
+        self.storage
+            .write_batch(batch.batch, Some(self.transaction))
+            .value
+            .map(|_| ())
+            .wrap_with_cost(Default::default())
+    }
+
+    fn raw_iter(&self) -> Self::RawIterator {
+        self.iter_column(Column::Data, None)
+    }
+
+    fn raw_iter_with_options(&self, options: &RawIteratorOptions) -> Self::RawIterator {
+        // Read tuning has no effect on records kept in memory
+        self.iter_column(
+            Column::Data,
+            options
+                .iterate_upper_bound
+                .as_ref()
+                .map(|upper_bound| make_prefixed_key(&self.prefix, upper_bound)),
+        )
+    }
+
+    fn raw_iter_aux(&self) -> Self::RawIterator {
+        self.iter_column(Column::Aux, None)
+    }
+
+    fn raw_iter_roots(&self) -> Self::RawIterator {
+        self.iter_column(Column::Roots, None)
+    }
+
+    fn raw_iter_meta(&self) -> Self::RawIterator {
+        self.iter_column(Column::Meta, None)
+    }
+}
+
+/// Batch of an in-memory storage context, holding prefixed operations until
+/// they are committed by the context.
+pub struct InMemoryBatch {
+    prefix: SubtreePrefix,
+    batch: StorageBatch,
+}
+
+impl Batch for InMemoryBatch {
+    fn put<K: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        value: &[u8],
+        children_sizes: ChildrenSizesWithIsSumTree,
+        cost_info: Option<KeyValueStorageCost>,
+    ) -> Result<(), grovedb_costs::error::Error> {
+        let prefixed_key = make_prefixed_key(&self.prefix, key);
+
+        // Update the key_storage_cost based on the prefixed key
+        let updated_cost_info = cost_info.map(|mut key_value_storage_cost| {
+            if key_value_storage_cost.new_node {
+                // key is new, storage_cost needs to be created for it
+                key_value_storage_cost.key_storage_cost.added_bytes +=
+                    (prefixed_key.len() + prefixed_key.len().required_space()) as u32;
+            }
+            key_value_storage_cost
+        });
+
+        self.batch.put(
+            prefixed_key,
+            value.to_vec(),
+            children_sizes,
+            updated_cost_info,
+        );
+        Ok(())
+    }
+
+    fn put_aux<K: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        value: &[u8],
+        cost_info: Option<KeyValueStorageCost>,
+    ) -> Result<(), grovedb_costs::error::Error> {
+        self.batch.put_aux(
+            make_prefixed_key(&self.prefix, key),
+            value.to_vec(),
+            cost_info,
+        );
+        Ok(())
+    }
+
+    fn put_root<K: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        value: &[u8],
+        cost_info: Option<KeyValueStorageCost>,
+    ) -> Result<(), grovedb_costs::error::Error> {
+        self.batch.put_root(
+            make_prefixed_key(&self.prefix, key),
+            value.to_vec(),
+            cost_info,
+        );
+        Ok(())
+    }
+
+    fn delete<K: AsRef<[u8]>>(&mut self, key: K, cost_info: Option<KeyValueStorageCost>) {
+        self.batch
+            .delete(make_prefixed_key(&self.prefix, key), cost_info);
+    }
+
+    fn delete_aux<K: AsRef<[u8]>>(&mut self, key: K, cost_info: Option<KeyValueStorageCost>) {
+        self.batch
+            .delete_aux(make_prefixed_key(&self.prefix, key), cost_info);
+    }
+
+    fn delete_root<K: AsRef<[u8]>>(&mut self, key: K, cost_info: Option<KeyValueStorageCost>) {
+        self.batch
+            .delete_root(make_prefixed_key(&self.prefix, key), cost_info);
+    }
+}
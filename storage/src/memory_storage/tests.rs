@@ -0,0 +1,475 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Tests
+
+use grovedb_costs::OperationCost;
+
+use super::InMemoryStorage;
+use crate::{
+    counter::{decode_counter, encode_counter},
+    RawIterator, RawIteratorOptions, Storage, StorageBatch, StorageContext,
+};
+
+fn collect<I: RawIterator>(mut iter: I) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let mut records = Vec::new();
+    iter.seek_to_first().unwrap();
+    while iter.valid().unwrap() {
+        records.push((
+            iter.key().unwrap().expect("key must exist").to_vec(),
+            iter.value().unwrap().expect("value must exist").to_vec(),
+        ));
+        iter.next().unwrap();
+    }
+    records
+}
+
+#[test]
+fn test_batch_without_transaction() {
+    let storage = InMemoryStorage::new();
+    let batch = StorageBatch::new();
+    let context = storage
+        .get_storage_context([b"ayya"].as_ref().into(), Some(&batch))
+        .unwrap();
+    context
+        .put(b"key1", b"value1", None, None)
+        .unwrap()
+        .expect("cannot put");
+    context
+        .put_aux(b"key1", b"aux1", None)
+        .unwrap()
+        .expect("cannot put aux");
+    context
+        .put_meta(b"key1", b"meta1", None)
+        .unwrap()
+        .expect("cannot put meta");
+
+    // Writes are deferred until the batch is committed
+    assert!(context.get(b"key1").unwrap().unwrap().is_none());
+    assert_eq!(
+        context.get_staged(b"key1").unwrap().unwrap(),
+        Some(b"value1".to_vec())
+    );
+
+    storage
+        .commit_multi_context_batch(batch, None)
+        .unwrap()
+        .expect("cannot commit batch");
+
+    let context = storage
+        .get_storage_context([b"ayya"].as_ref().into(), None)
+        .unwrap();
+    assert_eq!(
+        context.get(b"key1").unwrap().unwrap(),
+        Some(b"value1".to_vec())
+    );
+    assert_eq!(
+        context.get_aux(b"key1").unwrap().unwrap(),
+        Some(b"aux1".to_vec())
+    );
+    assert_eq!(
+        context.get_meta(b"key1").unwrap().unwrap(),
+        Some(b"meta1".to_vec())
+    );
+    assert!(context.get_root(b"key1").unwrap().unwrap().is_none());
+
+    // Subtrees don't see each other's records
+    let other_context = storage
+        .get_storage_context([b"ayyb"].as_ref().into(), None)
+        .unwrap();
+    assert!(other_context.get(b"key1").unwrap().unwrap().is_none());
+}
+
+#[test]
+fn test_transaction_properties() {
+    let storage = InMemoryStorage::new();
+    let tx = storage.start_transaction().unwrap();
+    let batch = StorageBatch::new();
+    let context = storage
+        .get_transactional_storage_context([b"ayya"].as_ref().into(), Some(&batch), &tx)
+        .unwrap();
+    context
+        .put(b"key1", b"value1", None, None)
+        .unwrap()
+        .expect("cannot put");
+    storage
+        .commit_multi_context_batch(batch, Some(&tx))
+        .unwrap()
+        .expect("cannot commit batch");
+
+    let tx_context = storage
+        .get_transactional_storage_context([b"ayya"].as_ref().into(), None, &tx)
+        .unwrap();
+    let no_tx_context = storage
+        .get_storage_context([b"ayya"].as_ref().into(), None)
+        .unwrap();
+    assert_eq!(
+        tx_context.get(b"key1").unwrap().unwrap(),
+        Some(b"value1".to_vec())
+    );
+    assert!(no_tx_context.get(b"key1").unwrap().unwrap().is_none());
+
+    // Writes after a savepoint are undone by rolling back to it
+    storage.set_transaction_savepoint(&tx);
+    let immediate_context = storage
+        .get_immediate_storage_context([b"ayya"].as_ref().into(), &tx)
+        .unwrap();
+    immediate_context
+        .put(b"key2", b"value2", None, None)
+        .unwrap()
+        .expect("cannot put");
+    immediate_context
+        .delete(b"key1", None)
+        .unwrap()
+        .expect("cannot delete");
+    assert!(tx_context.get(b"key1").unwrap().unwrap().is_none());
+    storage
+        .rollback_transaction_to_savepoint(&tx)
+        .expect("cannot roll back to savepoint");
+    assert!(tx_context.get(b"key2").unwrap().unwrap().is_none());
+    assert!(storage.rollback_transaction_to_savepoint(&tx).is_err());
+
+    storage
+        .commit_transaction(tx)
+        .unwrap()
+        .expect("cannot commit transaction");
+    assert_eq!(
+        no_tx_context.get(b"key1").unwrap().unwrap(),
+        Some(b"value1".to_vec())
+    );
+    assert!(no_tx_context.get(b"key2").unwrap().unwrap().is_none());
+
+    // Rolled back writes are discarded
+    let tx = storage.start_transaction().unwrap();
+    let immediate_context = storage
+        .get_immediate_storage_context([b"ayya"].as_ref().into(), &tx)
+        .unwrap();
+    immediate_context
+        .delete(b"key1", None)
+        .unwrap()
+        .expect("cannot delete");
+    storage
+        .rollback_transaction(&tx)
+        .expect("cannot roll back transaction");
+    storage
+        .commit_transaction(tx)
+        .unwrap()
+        .expect("cannot commit transaction");
+    assert_eq!(
+        no_tx_context.get(b"key1").unwrap().unwrap(),
+        Some(b"value1".to_vec())
+    );
+}
+
+#[test]
+fn test_nested_savepoints() {
+    let storage = InMemoryStorage::new();
+    let tx = storage.start_transaction().unwrap();
+    let context = storage
+        .get_immediate_storage_context([b"ayya"].as_ref().into(), &tx)
+        .unwrap();
+    let get = |key: &[u8]| context.get(key).unwrap().unwrap();
+    context
+        .put(b"key1", b"value1", None, None)
+        .unwrap()
+        .expect("cannot put");
+
+    storage.set_transaction_savepoint(&tx);
+    context
+        .put(b"key1", b"value2", None, None)
+        .unwrap()
+        .expect("cannot put");
+    storage.set_transaction_savepoint(&tx);
+    context
+        .put(b"key1", b"value3", None, None)
+        .unwrap()
+        .expect("cannot put");
+    context
+        .put(b"key2", b"value2", None, None)
+        .unwrap()
+        .expect("cannot put");
+
+    storage
+        .rollback_transaction_to_savepoint(&tx)
+        .expect("cannot roll back to savepoint");
+    assert_eq!(get(b"key1"), Some(b"value2".to_vec()));
+    assert_eq!(get(b"key2"), None);
+    storage
+        .rollback_transaction_to_savepoint(&tx)
+        .expect("cannot roll back to savepoint");
+    assert_eq!(get(b"key1"), Some(b"value1".to_vec()));
+    assert!(storage.rollback_transaction_to_savepoint(&tx).is_err());
+}
+
+#[test]
+fn test_snapshot_transaction() {
+    let storage = InMemoryStorage::new();
+    let put = |key: &[u8], value: &[u8]| {
+        let tx = storage.start_transaction().unwrap();
+        storage
+            .get_immediate_storage_context([b"ayya"].as_ref().into(), &tx)
+            .unwrap()
+            .put(key, value, None, None)
+            .unwrap()
+            .expect("cannot put");
+        storage
+            .commit_transaction(tx)
+            .unwrap()
+            .expect("cannot commit transaction");
+    };
+    put(b"key1", b"value1");
+
+    let snapshot = storage.start_snapshot_transaction().unwrap();
+    let snapshot_context = storage
+        .get_transactional_storage_context([b"ayya"].as_ref().into(), None, &snapshot)
+        .unwrap();
+
+    // Writes committed after the snapshot was started aren't seen from it
+    put(b"key1", b"value2");
+    put(b"key2", b"value2");
+    assert_eq!(
+        snapshot_context.get(b"key1").unwrap().unwrap(),
+        Some(b"value1".to_vec())
+    );
+    assert!(snapshot_context.get(b"key2").unwrap().unwrap().is_none());
+    assert_eq!(
+        collect(snapshot_context.raw_iter()),
+        vec![(b"key1".to_vec(), b"value1".to_vec())]
+    );
+}
+
+#[test]
+fn test_raw_iterator() {
+    let storage = InMemoryStorage::new();
+    let tx = storage.start_transaction().unwrap();
+    for path in [
+        b"someprefix".as_ref(),
+        b"anothersomeprefix",
+        b"zanothersomeprefix",
+    ] {
+        let context = storage
+            .get_immediate_storage_context([path].as_ref().into(), &tx)
+            .unwrap();
+        for key in [b"key1", b"key0", b"key3", b"key2"] {
+            context
+                .put(key, path, None, None)
+                .unwrap()
+                .expect("cannot put");
+        }
+    }
+    storage
+        .commit_transaction(tx)
+        .unwrap()
+        .expect("cannot commit transaction");
+
+    // Uncommitted writes are seen by iterators over the transaction
+    let tx = storage.start_transaction().unwrap();
+    let context = storage
+        .get_immediate_storage_context([b"someprefix"].as_ref().into(), &tx)
+        .unwrap();
+    context
+        .delete(b"key1", None)
+        .unwrap()
+        .expect("cannot delete");
+    context
+        .put(b"key4", b"someprefix", None, None)
+        .unwrap()
+        .expect("cannot put");
+    let keys: Vec<Vec<u8>> = collect(context.raw_iter())
+        .into_iter()
+        .map(|(key, value)| {
+            assert_eq!(value, b"someprefix");
+            key
+        })
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            b"key0".to_vec(),
+            b"key2".to_vec(),
+            b"key3".to_vec(),
+            b"key4".to_vec()
+        ]
+    );
+
+    let mut iter = context.raw_iter();
+    iter.seek(b"key1").unwrap();
+    assert_eq!(iter.key().unwrap(), Some(b"key2".as_ref()));
+    iter.prev().unwrap();
+    assert_eq!(iter.key().unwrap(), Some(b"key0".as_ref()));
+    iter.prev().unwrap();
+    assert!(!iter.valid().unwrap());
+    iter.seek_for_prev(b"key1").unwrap();
+    assert_eq!(iter.key().unwrap(), Some(b"key0".as_ref()));
+    iter.seek_to_last().unwrap();
+    assert_eq!(iter.key().unwrap(), Some(b"key4".as_ref()));
+    iter.next().unwrap();
+    assert!(!iter.valid().unwrap());
+
+    let mut iter = context.raw_iter_with_options(&RawIteratorOptions {
+        iterate_upper_bound: Some(b"key3".to_vec()),
+        ..Default::default()
+    });
+    iter.seek_to_last().unwrap();
+    assert_eq!(iter.key().unwrap(), Some(b"key2".as_ref()));
+    iter.seek_for_prev(b"key4").unwrap();
+    assert_eq!(iter.key().unwrap(), Some(b"key2".as_ref()));
+
+    // Out of the subtree, keys cost as much as the longest prefixed key
+    let mut iter = context.raw_iter();
+    iter.seek_to_last().unwrap();
+    iter.next().unwrap();
+    let mut cost = OperationCost::default();
+    assert!(iter.key().unwrap_add_cost(&mut cost).is_none());
+    assert_eq!(cost.storage_loaded_bytes, 256 + 32);
+}
+
+#[test]
+fn test_merge_aux_counter() {
+    let storage = InMemoryStorage::new();
+    let batch = StorageBatch::new();
+    let context = storage
+        .get_storage_context([b"ayya"].as_ref().into(), Some(&batch))
+        .unwrap();
+    context
+        .merge_aux(b"counter", &encode_counter(5))
+        .unwrap()
+        .expect("cannot merge");
+    context
+        .merge_aux(b"counter", &encode_counter(2))
+        .unwrap()
+        .expect("cannot merge");
+    storage
+        .commit_multi_context_batch(batch, None)
+        .unwrap()
+        .expect("cannot commit multi context batch");
+
+    let tx = storage.start_transaction().unwrap();
+    let context = storage
+        .get_immediate_storage_context([b"ayya"].as_ref().into(), &tx)
+        .unwrap();
+    context
+        .merge_aux(b"counter", &encode_counter(-3))
+        .unwrap()
+        .expect("cannot merge");
+    let value = context
+        .get_aux(b"counter")
+        .unwrap()
+        .expect("cannot get data")
+        .expect("expected a counter");
+    assert_eq!(decode_counter(&value), Some(4));
+
+    // A batch that fails to merge leaves the storage untouched
+    context
+        .put_aux(b"invalid", b"bad", None)
+        .unwrap()
+        .expect("cannot put aux");
+    let batch = StorageBatch::new();
+    let batch_context = storage
+        .get_transactional_storage_context([b"ayya"].as_ref().into(), Some(&batch), &tx)
+        .unwrap();
+    batch_context
+        .put_aux(b"key", b"value", None)
+        .unwrap()
+        .expect("cannot put aux");
+    batch_context
+        .merge_aux(b"invalid", &encode_counter(1))
+        .unwrap()
+        .expect("cannot merge");
+    assert!(storage
+        .commit_multi_context_batch(batch, Some(&tx))
+        .unwrap()
+        .is_err());
+    assert!(context.get_aux(b"key").unwrap().unwrap().is_none());
+}
+
+#[cfg(feature = "rocksdb_storage")]
+#[test]
+fn test_costs_match_rocksdb() {
+    use crate::rocksdb_storage::test_utils::TempStorage;
+
+    fn apply<S>(storage: &S) -> Vec<OperationCost>
+    where
+        for<'db> S: Storage<'db>,
+    {
+        let mut costs = Vec::new();
+        let batch = StorageBatch::new();
+        let context = storage
+            .get_storage_context([b"ayya", b"ayyb"].as_ref().into(), Some(&batch))
+            .unwrap_add_cost(&mut OperationCost::default());
+        for key in [b"key1", b"key2", b"key3"] {
+            context
+                .put(key, b"value", None, None)
+                .unwrap()
+                .expect("cannot put");
+        }
+        context
+            .put_aux(b"aux", b"value", None)
+            .unwrap()
+            .expect("cannot put");
+        drop(context);
+        costs.push(storage.commit_multi_context_batch(batch, None).cost);
+
+        let batch = StorageBatch::new();
+        let context = storage
+            .get_storage_context([b"ayya", b"ayyb"].as_ref().into(), Some(&batch))
+            .unwrap_add_cost(&mut OperationCost::default());
+        context
+            .delete(b"key2", None)
+            .unwrap()
+            .expect("cannot delete");
+        context
+            .delete_aux(b"aux", None)
+            .unwrap()
+            .expect("cannot delete");
+        drop(context);
+        costs.push(storage.commit_multi_context_batch(batch, None).cost);
+
+        let context = storage
+            .get_storage_context([b"ayya", b"ayyb"].as_ref().into(), None)
+            .unwrap_add_cost(&mut OperationCost::default());
+        costs.push(context.get(b"key1").cost);
+        let mut cost = OperationCost::default();
+        let mut iter = context.raw_iter();
+        iter.seek_to_first().unwrap_add_cost(&mut cost);
+        while iter.valid().unwrap_add_cost(&mut cost) {
+            iter.key().unwrap_add_cost(&mut cost);
+            iter.value().unwrap_add_cost(&mut cost);
+            iter.next().unwrap_add_cost(&mut cost);
+        }
+        iter.seek_to_last().unwrap_add_cost(&mut cost);
+        iter.prev().unwrap_add_cost(&mut cost);
+        iter.key().unwrap_add_cost(&mut cost);
+        costs.push(cost);
+        costs
+    }
+
+    let in_memory_costs = apply(&InMemoryStorage::new());
+    let rocksdb_costs = apply(&*TempStorage::new());
+    assert_eq!(in_memory_costs, rocksdb_costs);
+}
@@ -0,0 +1,128 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Subtree prefixes hashed from their paths, shared by the storage backends.
+
+use grovedb_costs::{CostContext, CostsExt, OperationCost};
+use grovedb_path::SubtreePath;
+
+use crate::worst_case_costs::WorstKeyLength;
+
+const BLAKE_BLOCK_LEN: usize = 64;
+
+/// Prefix the keys of a subtree are stored under
+pub(crate) type SubtreePrefix = Vec<u8>;
+
+/// Prefix of a subtree derived with the hashed prefix scheme
+pub(crate) type HashedSubtreePrefix = [u8; blake3::OUT_LEN];
+
+pub(crate) fn blake_block_count(len: usize) -> usize {
+    if len == 0 {
+        1
+    } else {
+        1 + (len - 1) / BLAKE_BLOCK_LEN
+    }
+}
+
+/// Serializes a path into the body hashed into its prefix, returning the
+/// number of segments along with it
+pub(crate) fn build_prefix_body<B>(path: SubtreePath<B>) -> (Vec<u8>, usize)
+where
+    B: AsRef<[u8]>,
+{
+    let segments_iter = path.into_reverse_iter();
+    let mut segments_count: usize = 0;
+    let mut res = Vec::new();
+    let mut lengthes = Vec::new();
+
+    for s in segments_iter {
+        segments_count += 1;
+        res.extend_from_slice(s);
+        lengthes.push(s.len() as u8); // if the key len is under 255 bytes
+    }
+
+    res.extend(segments_count.to_ne_bytes());
+    res.extend(lengthes);
+    (res, segments_count)
+}
+
+/// Hashes the path of a subtree into its prefix, the root having an all zero
+/// prefix
+pub(crate) fn build_hashed_prefix<B>(path: SubtreePath<B>) -> CostContext<HashedSubtreePrefix>
+where
+    B: AsRef<[u8]>,
+{
+    let (body, segments_count) = build_prefix_body(path);
+    if segments_count == 0 {
+        HashedSubtreePrefix::default().wrap_with_cost(OperationCost::default())
+    } else {
+        let blocks_count = blake_block_count(body.len());
+        HashedSubtreePrefix::from(blake3::hash(&body))
+            .wrap_with_cost(OperationCost::with_hash_node_calls(blocks_count as u32))
+    }
+}
+
+/// Worst case cost of hashing the prefix of a subtree
+pub(crate) fn hashed_prefix_worst_case_cost<L: WorstKeyLength>(path: &[L]) -> OperationCost {
+    if path.is_empty() {
+        OperationCost::default()
+    } else {
+        let body_size = path.len() + path.iter().map(|a| a.max_length() as usize).sum::<usize>();
+        // the block size of blake3 is 64
+        let blocks_num = blake_block_count(body_size) as u32;
+        OperationCost::with_hash_node_calls(blocks_num)
+    }
+}
+
+/// Returns the smallest key greater than every key starting with `prefix`, or
+/// `None` if `prefix` consists of `0xFF` bytes only.
+pub(crate) fn next_prefix(prefix: &SubtreePrefix) -> Option<Vec<u8>> {
+    let mut next_prefix = prefix.to_vec();
+    for i in (0..next_prefix.len()).rev() {
+        next_prefix[i] = next_prefix[i].wrapping_add(1);
+        if next_prefix[i] != 0 {
+            // if it is == 0 then we need to carry over to the previous byte
+            return Some(next_prefix);
+        }
+    }
+    None
+}
+
+/// Length of a key prefixed with `prefix` as paid for by costs. Every prefix
+/// is paid for as a hash, so costs don't depend on the prefix scheme or on the
+/// depth of the subtree.
+pub(crate) fn cost_key_len(prefix: &SubtreePrefix, prefixed_key: &[u8]) -> usize {
+    prefixed_key.len() - prefix.len() + blake3::OUT_LEN
+}
+
+/// Make prefixed key
+pub(crate) fn make_prefixed_key<K: AsRef<[u8]>>(prefix: &SubtreePrefix, key: K) -> Vec<u8> {
+    let mut prefix_vec = prefix.to_vec();
+    prefix_vec.extend_from_slice(key.as_ref());
+    prefix_vec
+}
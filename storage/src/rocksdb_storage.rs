@@ -27,16 +27,21 @@
 // DEALINGS IN THE SOFTWARE.
 
 //! GroveDB storage layer implemented over RocksDB backend.
+mod builder;
 mod storage;
 mod storage_context;
 pub mod test_utils;
 #[cfg(test)]
 mod tests;
 
+pub use builder::{
+    ColumnFamilyTuning, CompactionStyle, Compression, PrefixScheme, RocksDbStorageBuilder,
+    StorageColumnFamily,
+};
 pub use rocksdb::{Error, WriteBatchWithTransaction};
 pub use storage_context::{
     PrefixedRocksDbBatch, PrefixedRocksDbImmediateStorageContext, PrefixedRocksDbRawIterator,
     PrefixedRocksDbStorageContext, PrefixedRocksDbTransactionContext,
 };
 
-pub use self::storage::RocksDbStorage;
+pub use self::storage::{HierarchyUsage, RocksDbMemoryUsage, RocksDbStorage, Tx};
@@ -0,0 +1,366 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Builder of RocksDB storages with per column family tuning.
+
+use std::path::Path;
+
+use rocksdb::{
+    ColumnFamilyDescriptor, DBCompactionStyle, DBCompressionType, ErrorKind,
+    OptimisticTransactionDB, SingleThreaded, DB, DEFAULT_COLUMN_FAMILY_NAME,
+};
+
+use super::{
+    storage::{
+        cf_meta, Db, AUX_CF_NAME, AUX_OPTS, DEFAULT_OPTS, META_CF_NAME, PREFIX_SALT_META_KEY,
+        PREFIX_SCHEME_META_KEY, ROOTS_CF_NAME,
+    },
+    RocksDbStorage,
+};
+use crate::error::{Error, Error::RocksDBError};
+
+/// Column families of the storage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StorageColumnFamily {
+    /// Subtrees data, the default column family
+    Data,
+    /// Auxiliary data
+    Aux,
+    /// Subtrees roots data
+    Roots,
+    /// Metadata
+    Meta,
+}
+
+/// Compaction style of a column family
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompactionStyle {
+    /// Files are compacted level by level
+    Level,
+    /// Sorted runs of similar size are merged together, lowering write
+    /// amplification
+    Universal,
+}
+
+/// Compression of a column family
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// No compression
+    None,
+    /// Snappy compression
+    Snappy,
+    /// LZ4 compression
+    Lz4,
+    /// Zstandard compression
+    Zstd,
+}
+
+/// How the prefixes of the storage keys of subtrees are derived from their
+/// paths
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PrefixScheme {
+    /// A hash of the path, all prefixes having the same length
+    #[default]
+    Hashed,
+    /// The segments of the path one after the other, the prefix of a subtree
+    /// extending the prefix of its parent, so a subtree and its descendants
+    /// can be covered by a single range scan
+    Hierarchical,
+}
+
+/// Tuning of a column family, unset fields keep the RocksDB defaults
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColumnFamilyTuning {
+    /// Compaction style
+    pub compaction_style: Option<CompactionStyle>,
+    /// Target size of the files of the first level
+    pub target_file_size_base: Option<u64>,
+    /// Compression
+    pub compression: Option<Compression>,
+}
+
+impl ColumnFamilyTuning {
+    fn apply_to(&self, opts: &mut rocksdb::Options) {
+        if let Some(compaction_style) = self.compaction_style {
+            opts.set_compaction_style(match compaction_style {
+                CompactionStyle::Level => DBCompactionStyle::Level,
+                CompactionStyle::Universal => DBCompactionStyle::Universal,
+            });
+        }
+        if let Some(target_file_size_base) = self.target_file_size_base {
+            opts.set_target_file_size_base(target_file_size_base);
+        }
+        if let Some(compression) = self.compression {
+            opts.set_compression_type(match compression {
+                Compression::None => DBCompressionType::None,
+                Compression::Snappy => DBCompressionType::Snappy,
+                Compression::Lz4 => DBCompressionType::Lz4,
+                Compression::Zstd => DBCompressionType::Zstd,
+            });
+        }
+    }
+}
+
+/// Builder of a `RocksDbStorage`, tuning every column family separately
+#[derive(Debug, Clone, Default)]
+pub struct RocksDbStorageBuilder {
+    data: ColumnFamilyTuning,
+    aux: ColumnFamilyTuning,
+    roots: ColumnFamilyTuning,
+    meta: ColumnFamilyTuning,
+    paranoid_checks: bool,
+    prefix_salt: Option<[u8; blake3::KEY_LEN]>,
+    prefix_scheme: Option<PrefixScheme>,
+}
+
+impl RocksDbStorageBuilder {
+    /// New builder with the default tuning for every column family
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the tuning of a column family
+    pub fn column_family_tuning(
+        mut self,
+        column_family: StorageColumnFamily,
+        tuning: ColumnFamilyTuning,
+    ) -> Self {
+        match column_family {
+            StorageColumnFamily::Data => self.data = tuning,
+            StorageColumnFamily::Aux => self.aux = tuning,
+            StorageColumnFamily::Roots => self.roots = tuning,
+            StorageColumnFamily::Meta => self.meta = tuning,
+        }
+        self
+    }
+
+    /// Enables RocksDB paranoid checks, validating the manifest and the files
+    /// on open and reporting any inconsistency as `Error::Corruption`
+    pub fn paranoid_checks(mut self, enabled: bool) -> Self {
+        self.paranoid_checks = enabled;
+        self
+    }
+
+    /// Derives the subtree prefixes of a new database with a hash keyed by
+    /// `salt`, so databases with different salts use different keys for the
+    /// same paths. The salt is stored in the database and used whenever it is
+    /// opened; opening an existing database with a salt it wasn't created
+    /// with is an error.
+    pub fn prefix_salt(mut self, salt: [u8; blake3::KEY_LEN]) -> Self {
+        self.prefix_salt = Some(salt);
+        self
+    }
+
+    /// Derives the subtree prefixes of a new database with `scheme`. The
+    /// scheme is stored in the database and used whenever it is opened;
+    /// opening an existing database with a scheme it wasn't created with is an
+    /// error.
+    pub fn prefix_scheme(mut self, scheme: PrefixScheme) -> Self {
+        self.prefix_scheme = Some(scheme);
+        self
+    }
+
+    /// Checks that the database at `path` has no column family unknown to
+    /// this version, returning its column families or `None` if there is no
+    /// database yet
+    fn existing_column_families<P: AsRef<Path>>(path: P) -> Result<Option<Vec<String>>, Error> {
+        // listing fails when there is no database yet
+        let Ok(existing_column_families) =
+            OptimisticTransactionDB::<SingleThreaded>::list_cf(&DEFAULT_OPTS, &path)
+        else {
+            return Ok(None);
+        };
+        let unknown_column_families: Vec<String> = existing_column_families
+            .iter()
+            .filter(|name| {
+                ![
+                    DEFAULT_COLUMN_FAMILY_NAME,
+                    AUX_CF_NAME,
+                    ROOTS_CF_NAME,
+                    META_CF_NAME,
+                ]
+                .contains(&name.as_str())
+            })
+            .cloned()
+            .collect();
+        if !unknown_column_families.is_empty() {
+            return Err(Error::UnknownColumnFamilies(unknown_column_families));
+        }
+        Ok(Some(existing_column_families))
+    }
+
+    /// Opens the storage at `path`. Column families missing from an existing
+    /// database, like one written by an older version, are created, while
+    /// unknown column families, written by a newer version, are an error.
+    pub fn open<P: AsRef<Path>>(self, path: P) -> Result<RocksDbStorage, Error> {
+        let is_new = Self::existing_column_families(&path)?.is_none();
+
+        let tuned = |base: &rocksdb::Options, tuning: &ColumnFamilyTuning| {
+            let mut opts = base.clone();
+            tuning.apply_to(&mut opts);
+            opts
+        };
+        let mut db_opts = tuned(&DEFAULT_OPTS, &self.data);
+        db_opts.set_paranoid_checks(self.paranoid_checks);
+        // `DEFAULT_OPTS` create the missing column families
+        let db = OptimisticTransactionDB::open_cf_descriptors(
+            &db_opts,
+            &path,
+            [
+                ColumnFamilyDescriptor::new(AUX_CF_NAME, tuned(&AUX_OPTS, &self.aux)),
+                ColumnFamilyDescriptor::new(ROOTS_CF_NAME, tuned(&DEFAULT_OPTS, &self.roots)),
+                ColumnFamilyDescriptor::new(META_CF_NAME, tuned(&DEFAULT_OPTS, &self.meta)),
+            ],
+        )
+        .map_err(open_error)?;
+
+        self.with_prefix_derivation(Db::Primary(db), is_new)
+    }
+
+    /// Opens a read-only secondary storage following the primary database at
+    /// `primary_path`, keeping its own logs at `secondary_path`. The secondary
+    /// sees the writes of the primary up to the last call to
+    /// [`RocksDbStorage::try_catch_up_with_primary`]. Writes to a secondary
+    /// storage are an error and it can't start transactions. The primary
+    /// database must have all the column families of this version, and the
+    /// prefix salt and scheme set on the builder, if any, must be the ones it
+    /// was created with; column family tuning is not used.
+    pub fn open_as_secondary<P: AsRef<Path>>(
+        self,
+        primary_path: P,
+        secondary_path: P,
+    ) -> Result<RocksDbStorage, Error> {
+        let existing_column_families = Self::existing_column_families(&primary_path)?
+            .ok_or_else(|| Error::StorageError("there is no primary database".to_owned()))?;
+        if [AUX_CF_NAME, ROOTS_CF_NAME, META_CF_NAME]
+            .iter()
+            .any(|name| {
+                !existing_column_families
+                    .iter()
+                    .any(|existing| existing == name)
+            })
+        {
+            return Err(Error::StorageError(
+                "the primary database lacks column families, it must be opened as a primary by \
+                 this version first"
+                    .to_owned(),
+            ));
+        }
+
+        // secondaries keep every table file of the primary open
+        let secondary = |base: &rocksdb::Options| {
+            let mut opts = base.clone();
+            opts.set_max_open_files(-1);
+            opts
+        };
+        let mut db_opts = secondary(&DEFAULT_OPTS);
+        db_opts.set_paranoid_checks(self.paranoid_checks);
+        let db = DB::open_cf_descriptors_as_secondary(
+            &db_opts,
+            primary_path.as_ref(),
+            secondary_path.as_ref(),
+            [
+                ColumnFamilyDescriptor::new(AUX_CF_NAME, secondary(&AUX_OPTS)),
+                ColumnFamilyDescriptor::new(ROOTS_CF_NAME, secondary(&DEFAULT_OPTS)),
+                ColumnFamilyDescriptor::new(META_CF_NAME, secondary(&DEFAULT_OPTS)),
+            ],
+        )
+        .map_err(open_error)?;
+
+        self.with_prefix_derivation(Db::Secondary(db), false)
+    }
+
+    /// Builds the storage over `db`, checking the prefix salt and scheme of
+    /// the builder against the ones `db` was created with, or storing them if
+    /// the database `is_new`
+    fn with_prefix_derivation(self, db: Db, is_new: bool) -> Result<RocksDbStorage, Error> {
+        let stored_salt = db
+            .get_cf(cf_meta(&db), PREFIX_SALT_META_KEY)?
+            .map(|salt| {
+                <[u8; blake3::KEY_LEN]>::try_from(salt.as_slice())
+                    .map_err(|_| Error::Corruption("prefix salt is not 32 bytes".to_owned()))
+            })
+            .transpose()?;
+        let prefix_salt = match (stored_salt, self.prefix_salt) {
+            (stored_salt, None) => stored_salt,
+            (Some(stored_salt), Some(salt)) if stored_salt == salt => Some(salt),
+            (None, Some(salt)) if is_new => {
+                db.put_cf(cf_meta(&db), PREFIX_SALT_META_KEY, salt)?;
+                Some(salt)
+            }
+            (Some(_), Some(_)) => {
+                return Err(Error::PrefixDerivation(
+                    "the database was created with another prefix salt",
+                ))
+            }
+            (None, Some(_)) => {
+                return Err(Error::PrefixDerivation(
+                    "the database was created without a prefix salt",
+                ))
+            }
+        };
+
+        // databases without a stored scheme were created with hashed prefixes
+        let stored_scheme = match db.get_cf(cf_meta(&db), PREFIX_SCHEME_META_KEY)?.as_deref() {
+            None | Some([0]) => PrefixScheme::Hashed,
+            Some([1]) => PrefixScheme::Hierarchical,
+            Some(_) => return Err(Error::Corruption("unknown prefix scheme".to_owned())),
+        };
+        let prefix_scheme = match self.prefix_scheme {
+            None => stored_scheme,
+            Some(scheme) if scheme == stored_scheme => scheme,
+            Some(scheme) if is_new => {
+                let encoded = match scheme {
+                    PrefixScheme::Hashed => 0,
+                    PrefixScheme::Hierarchical => 1,
+                };
+                db.put_cf(cf_meta(&db), PREFIX_SCHEME_META_KEY, [encoded])?;
+                scheme
+            }
+            Some(_) => {
+                return Err(Error::PrefixDerivation(
+                    "the database was created with another prefix scheme",
+                ))
+            }
+        };
+
+        Ok(RocksDbStorage {
+            db,
+            prefix_salt,
+            prefix_scheme,
+        })
+    }
+}
+
+/// Reports the corruptions found when opening a database as such
+fn open_error(e: rocksdb::Error) -> Error {
+    match e.kind() {
+        ErrorKind::Corruption => Error::Corruption(e.into_string()),
+        _ => RocksDBError(e),
+    }
+}
@@ -28,7 +28,7 @@
 
 //! Implementation for a storage abstraction over RocksDB.
 
-use std::path::Path;
+use std::{ffi::CStr, path::Path};
 
 use error::Error;
 use grovedb_costs::{
@@ -40,33 +40,30 @@ use grovedb_path::SubtreePath;
 use integer_encoding::VarInt;
 use lazy_static::lazy_static;
 use rocksdb::{
-    checkpoint::Checkpoint, ColumnFamily, ColumnFamilyDescriptor, OptimisticTransactionDB,
-    Transaction, WriteBatchWithTransaction,
+    checkpoint::Checkpoint, properties, BottommostLevelCompaction, ColumnFamily, CompactOptions,
+    DBRawIteratorWithThreadMode, MergeOperands, OptimisticTransactionDB,
+    OptimisticTransactionOptions, ReadOptions, Transaction, WriteBatchWithTransaction,
+    WriteOptions, DB,
 };
 
 use super::{
-    PrefixedRocksDbImmediateStorageContext, PrefixedRocksDbStorageContext,
-    PrefixedRocksDbTransactionContext,
+    PrefixScheme, PrefixedRocksDbImmediateStorageContext, PrefixedRocksDbStorageContext,
+    PrefixedRocksDbTransactionContext, RocksDbStorageBuilder, StorageColumnFamily,
 };
 use crate::{
+    counter::{fold_counter, COUNTER_MERGE_OPERATOR_NAME},
     error,
     error::Error::{CostError, RocksDBError},
+    prefix::{
+        blake_block_count, build_hashed_prefix, build_prefix_body, hashed_prefix_worst_case_cost,
+        HashedSubtreePrefix,
+    },
     storage::AbstractBatchOperation,
     worst_case_costs::WorstKeyLength,
     Storage, StorageBatch,
 };
 
-const BLAKE_BLOCK_LEN: usize = 64;
-
-pub(crate) type SubtreePrefix = [u8; blake3::OUT_LEN];
-
-fn blake_block_count(len: usize) -> usize {
-    if len == 0 {
-        1
-    } else {
-        1 + (len - 1) / BLAKE_BLOCK_LEN
-    }
-}
+pub(crate) use crate::prefix::SubtreePrefix;
 
 /// Name of column family used to store auxiliary data
 pub(crate) const AUX_CF_NAME: &str = "aux";
@@ -74,9 +71,24 @@ pub(crate) const AUX_CF_NAME: &str = "aux";
 pub(crate) const ROOTS_CF_NAME: &str = "roots";
 /// Name of column family used to store metadata
 pub(crate) const META_CF_NAME: &str = "meta";
+/// Key of the prefix salt in the metadata column family. Hashed prefixes are
+/// longer and hierarchical ones start with `0x00` or `0x01` unless salted, so
+/// it can't collide with prefixed keys.
+pub(crate) const PREFIX_SALT_META_KEY: &[u8] = b"prefix_salt";
+/// Key of the prefix scheme in the metadata column family
+pub(crate) const PREFIX_SCHEME_META_KEY: &[u8] = b"prefix_scheme";
+
+/// Marks a path segment in a hierarchical prefix
+const HIERARCHICAL_SEGMENT_MARKER: u8 = 0x01;
+/// Ends a hierarchical prefix, sorting the keys of a subtree before the keys
+/// of its descendants
+const HIERARCHICAL_PREFIX_END: u8 = 0x00;
+/// Bounds the keys of a subtree and of its descendants in the hierarchical
+/// prefix scheme
+const HIERARCHICAL_RANGE_END: u8 = 0x02;
 
 lazy_static! {
-    static ref DEFAULT_OPTS: rocksdb::Options = {
+    pub(crate) static ref DEFAULT_OPTS: rocksdb::Options = {
         let mut opts = rocksdb::Options::default();
         opts.create_if_missing(true);
         opts.increase_parallelism(num_cpus::get() as i32);
@@ -86,74 +98,663 @@ lazy_static! {
         opts.set_atomic_flush(true);
         opts
     };
+    pub(crate) static ref AUX_OPTS: rocksdb::Options = {
+        let mut opts = DEFAULT_OPTS.clone();
+        opts.set_merge_operator_associative(COUNTER_MERGE_OPERATOR_NAME, counter_merge);
+        opts
+    };
 }
 
-/// Type alias for a database
-pub(crate) type Db = OptimisticTransactionDB;
+/// Counter merge operator of the auxiliary data column family
+fn counter_merge(
+    _key: &[u8],
+    existing: Option<&[u8]>,
+    operands: &MergeOperands,
+) -> Option<Vec<u8>> {
+    fold_counter(existing, operands.iter())
+}
+
+/// Size in bytes of the writes [`RocksDbStorage::import_records`] splits
+/// records into
+const IMPORT_CHUNK_BYTES: usize = 4 * 1024 * 1024;
+
+/// RocksDB database of a storage
+pub enum Db {
+    /// Database opened for reads and writes
+    Primary(OptimisticTransactionDB),
+    /// Read-only database following a primary one, see
+    /// [`RocksDbStorageBuilder::open_as_secondary`]
+    Secondary(DB),
+}
 
 /// Type alias for a transaction
-pub(crate) type Tx<'db> = Transaction<'db, Db>;
+pub type Tx<'db> = Transaction<'db, OptimisticTransactionDB>;
+
+impl Db {
+    /// Error returned by the writes to a secondary database
+    fn read_only_error() -> Error {
+        Error::ReadOnly("a secondary storage can't be written to")
+    }
+
+    pub(crate) fn cf_handle(&self, name: &str) -> Option<&ColumnFamily> {
+        match self {
+            Db::Primary(db) => db.cf_handle(name),
+            Db::Secondary(db) => db.cf_handle(name),
+        }
+    }
+
+    pub(crate) fn get<K: AsRef<[u8]>>(&self, key: K) -> Result<Option<Vec<u8>>, rocksdb::Error> {
+        match self {
+            Db::Primary(db) => db.get(key),
+            Db::Secondary(db) => db.get(key),
+        }
+    }
+
+    pub(crate) fn get_cf<K: AsRef<[u8]>>(
+        &self,
+        cf: &ColumnFamily,
+        key: K,
+    ) -> Result<Option<Vec<u8>>, rocksdb::Error> {
+        match self {
+            Db::Primary(db) => db.get_cf(cf, key),
+            Db::Secondary(db) => db.get_cf(cf, key),
+        }
+    }
+
+    pub(crate) fn multi_get<K, I>(&self, keys: I) -> Vec<Result<Option<Vec<u8>>, rocksdb::Error>>
+    where
+        K: AsRef<[u8]>,
+        I: IntoIterator<Item = K>,
+    {
+        match self {
+            Db::Primary(db) => db.multi_get(keys),
+            Db::Secondary(db) => db.multi_get(keys),
+        }
+    }
+
+    pub(crate) fn raw_iterator(&self) -> DbRawIterator<'_> {
+        match self {
+            Db::Primary(db) => DbRawIterator::Primary(db.raw_iterator()),
+            Db::Secondary(db) => DbRawIterator::Secondary(db.raw_iterator()),
+        }
+    }
+
+    pub(crate) fn raw_iterator_opt(&self, read_options: ReadOptions) -> DbRawIterator<'_> {
+        match self {
+            Db::Primary(db) => DbRawIterator::Primary(db.raw_iterator_opt(read_options)),
+            Db::Secondary(db) => DbRawIterator::Secondary(db.raw_iterator_opt(read_options)),
+        }
+    }
+
+    pub(crate) fn raw_iterator_cf<'db>(&'db self, cf: &ColumnFamily) -> DbRawIterator<'db> {
+        match self {
+            Db::Primary(db) => DbRawIterator::Primary(db.raw_iterator_cf(cf)),
+            Db::Secondary(db) => DbRawIterator::Secondary(db.raw_iterator_cf(cf)),
+        }
+    }
+
+    pub(crate) fn raw_iterator_cf_opt<'db>(
+        &'db self,
+        cf: &ColumnFamily,
+        read_options: ReadOptions,
+    ) -> DbRawIterator<'db> {
+        match self {
+            Db::Primary(db) => DbRawIterator::Primary(db.raw_iterator_cf_opt(cf, read_options)),
+            Db::Secondary(db) => DbRawIterator::Secondary(db.raw_iterator_cf_opt(cf, read_options)),
+        }
+    }
+
+    pub(crate) fn property_int_value(&self, name: &CStr) -> Result<Option<u64>, rocksdb::Error> {
+        match self {
+            Db::Primary(db) => db.property_int_value(name),
+            Db::Secondary(db) => db.property_int_value(name),
+        }
+    }
+
+    pub(crate) fn property_int_value_cf(
+        &self,
+        cf: &ColumnFamily,
+        name: &CStr,
+    ) -> Result<Option<u64>, rocksdb::Error> {
+        match self {
+            Db::Primary(db) => db.property_int_value_cf(cf, name),
+            Db::Secondary(db) => db.property_int_value_cf(cf, name),
+        }
+    }
+
+    pub(crate) fn put_cf<K, V>(&self, cf: &ColumnFamily, key: K, value: V) -> Result<(), Error>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        match self {
+            Db::Primary(db) => db.put_cf(cf, key, value).map_err(RocksDBError),
+            Db::Secondary(_) => Err(Self::read_only_error()),
+        }
+    }
+
+    pub(crate) fn write(&self, db_batch: WriteBatchWithTransaction<true>) -> Result<(), Error> {
+        match self {
+            Db::Primary(db) => db.write(db_batch).map_err(RocksDBError),
+            Db::Secondary(_) => Err(Self::read_only_error()),
+        }
+    }
+
+    pub(crate) fn flush(&self) -> Result<(), Error> {
+        match self {
+            Db::Primary(db) => db.flush().map_err(RocksDBError),
+            // a secondary has nothing of its own to flush
+            Db::Secondary(_) => Ok(()),
+        }
+    }
+
+    pub(crate) fn compact_range_opt(&self, opts: &CompactOptions) {
+        match self {
+            Db::Primary(db) => db.compact_range_opt(None::<&[u8]>, None::<&[u8]>, opts),
+            Db::Secondary(db) => db.compact_range_opt(None::<&[u8]>, None::<&[u8]>, opts),
+        }
+    }
+
+    pub(crate) fn compact_range_cf_opt(&self, cf: &ColumnFamily, opts: &CompactOptions) {
+        match self {
+            Db::Primary(db) => db.compact_range_cf_opt(cf, None::<&[u8]>, None::<&[u8]>, opts),
+            Db::Secondary(db) => db.compact_range_cf_opt(cf, None::<&[u8]>, None::<&[u8]>, opts),
+        }
+    }
+
+    /// Sequence number of the most recent write
+    pub(crate) fn latest_sequence_number(&self) -> u64 {
+        match self {
+            Db::Primary(db) => db.latest_sequence_number(),
+            Db::Secondary(db) => db.latest_sequence_number(),
+        }
+    }
+
+    /// Starts a transaction, which a read-only secondary database can't do
+    pub(crate) fn transaction_opt(
+        &self,
+        options: &OptimisticTransactionOptions,
+    ) -> Result<Tx<'_>, Error> {
+        match self {
+            Db::Primary(db) => Ok(db.transaction_opt(&WriteOptions::default(), options)),
+            Db::Secondary(_) => Err(Error::ReadOnly(
+                "transactions can't be started on a secondary storage",
+            )),
+        }
+    }
+}
+
+/// Raw iterator over a [`Db`]
+pub enum DbRawIterator<'db> {
+    /// Iterator over a primary database
+    Primary(DBRawIteratorWithThreadMode<'db, OptimisticTransactionDB>),
+    /// Iterator over a secondary database
+    Secondary(DBRawIteratorWithThreadMode<'db, DB>),
+}
+
+impl DbRawIterator<'_> {
+    pub(crate) fn seek<K: AsRef<[u8]>>(&mut self, key: K) {
+        match self {
+            DbRawIterator::Primary(iter) => iter.seek(key),
+            DbRawIterator::Secondary(iter) => iter.seek(key),
+        }
+    }
+
+    pub(crate) fn seek_for_prev<K: AsRef<[u8]>>(&mut self, key: K) {
+        match self {
+            DbRawIterator::Primary(iter) => iter.seek_for_prev(key),
+            DbRawIterator::Secondary(iter) => iter.seek_for_prev(key),
+        }
+    }
+
+    pub(crate) fn seek_to_first(&mut self) {
+        match self {
+            DbRawIterator::Primary(iter) => iter.seek_to_first(),
+            DbRawIterator::Secondary(iter) => iter.seek_to_first(),
+        }
+    }
+
+    pub(crate) fn seek_to_last(&mut self) {
+        match self {
+            DbRawIterator::Primary(iter) => iter.seek_to_last(),
+            DbRawIterator::Secondary(iter) => iter.seek_to_last(),
+        }
+    }
+
+    pub(crate) fn next(&mut self) {
+        match self {
+            DbRawIterator::Primary(iter) => iter.next(),
+            DbRawIterator::Secondary(iter) => iter.next(),
+        }
+    }
+
+    pub(crate) fn prev(&mut self) {
+        match self {
+            DbRawIterator::Primary(iter) => iter.prev(),
+            DbRawIterator::Secondary(iter) => iter.prev(),
+        }
+    }
+
+    pub(crate) fn key(&self) -> Option<&[u8]> {
+        match self {
+            DbRawIterator::Primary(iter) => iter.key(),
+            DbRawIterator::Secondary(iter) => iter.key(),
+        }
+    }
+
+    pub(crate) fn value(&self) -> Option<&[u8]> {
+        match self {
+            DbRawIterator::Primary(iter) => iter.value(),
+            DbRawIterator::Secondary(iter) => iter.value(),
+        }
+    }
+
+    pub(crate) fn status(&self) -> Result<(), rocksdb::Error> {
+        match self {
+            DbRawIterator::Primary(iter) => iter.status(),
+            DbRawIterator::Secondary(iter) => iter.status(),
+        }
+    }
+}
+
+/// Memory held by RocksDB, in bytes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RocksDbMemoryUsage {
+    /// Active and not yet flushed memtables
+    pub memtables: u64,
+    /// Index and filter blocks of open table files kept outside the block
+    /// cache
+    pub table_readers: u64,
+    /// Entries in the block cache
+    pub block_cache: u64,
+    /// Entries of the block cache pinned by iterators or table readers
+    pub pinned_blocks: u64,
+}
+
+/// Number and size of the storage keys of a hierarchy of subtrees
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HierarchyUsage {
+    /// Number of keys, over every column family
+    pub keys: u64,
+    /// Total size of the keys and of their values, in bytes
+    pub bytes: u64,
+}
 
 /// Storage which uses RocksDB as its backend.
 pub struct RocksDbStorage {
-    db: OptimisticTransactionDB,
+    pub(crate) db: Db,
+    /// Key of the keyed hash deriving subtree prefixes, if any
+    pub(crate) prefix_salt: Option<[u8; blake3::KEY_LEN]>,
+    /// Scheme subtree prefixes are derived with
+    pub(crate) prefix_scheme: PrefixScheme,
 }
 
 impl RocksDbStorage {
     /// Create RocksDb storage with default parameters using `path`.
     pub fn default_rocksdb_with_path<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
-        let db = Db::open_cf_descriptors(
-            &DEFAULT_OPTS,
-            &path,
-            [
-                ColumnFamilyDescriptor::new(AUX_CF_NAME, DEFAULT_OPTS.clone()),
-                ColumnFamilyDescriptor::new(ROOTS_CF_NAME, DEFAULT_OPTS.clone()),
-                ColumnFamilyDescriptor::new(META_CF_NAME, DEFAULT_OPTS.clone()),
-            ],
-        )
-        .map_err(RocksDBError)?;
+        RocksDbStorageBuilder::new().open(path)
+    }
+
+    /// Whether the storage is a read-only secondary following a primary
+    pub fn is_secondary(&self) -> bool {
+        matches!(self.db, Db::Secondary(_))
+    }
+
+    /// Catches up a secondary storage with the writes of its primary, which
+    /// become visible to the reads started afterwards
+    pub fn try_catch_up_with_primary(&self) -> Result<(), Error> {
+        match &self.db {
+            Db::Primary(_) => Err(Error::StorageError(
+                "only secondary storages follow a primary".to_owned(),
+            )),
+            Db::Secondary(db) => db.try_catch_up_with_primary().map_err(RocksDBError),
+        }
+    }
+
+    /// Fully compacts every column family down to the bottommost level
+    pub fn compact(&self) {
+        let mut opts = CompactOptions::default();
+        opts.set_bottommost_level_compaction(BottommostLevelCompaction::Force);
+        self.db.compact_range_opt(&opts);
+        for cf in [cf_aux(&self.db), cf_roots(&self.db), cf_meta(&self.db)] {
+            self.db.compact_range_cf_opt(cf, &opts);
+        }
+    }
+
+    /// Returns the memory held by RocksDB. Memtables and table readers are
+    /// summed over the column families, while the block cache shared by all
+    /// of them is only counted once.
+    pub fn memory_usage(&self) -> Result<RocksDbMemoryUsage, Error> {
+        let mut usage = RocksDbMemoryUsage::default();
+        let add = |property: &CStr| -> Result<u64, Error> {
+            let mut total = self.db.property_int_value(property)?.unwrap_or(0);
+            for cf in [cf_aux(&self.db), cf_roots(&self.db), cf_meta(&self.db)] {
+                total += self.db.property_int_value_cf(cf, property)?.unwrap_or(0);
+            }
+            Ok(total)
+        };
+        usage.memtables = add(properties::CUR_SIZE_ALL_MEM_TABLES)?;
+        usage.table_readers = add(properties::ESTIMATE_TABLE_READERS_MEM)?;
+        // every column family is opened with options cloned from
+        // `DEFAULT_OPTS` and so shares its block cache
+        usage.block_cache = self
+            .db
+            .property_int_value(properties::BLOCK_CACHE_USAGE)?
+            .unwrap_or(0);
+        usage.pinned_blocks = self
+            .db
+            .property_int_value(properties::BLOCK_CACHE_PINNED_USAGE)?
+            .unwrap_or(0);
+        Ok(usage)
+    }
 
-        Ok(RocksDbStorage { db })
+    /// A helper method to build a prefix to rocksdb keys or identify a subtree
+    /// in `subtrees` map by tree path;
+    pub fn build_prefix<B>(path: SubtreePath<B>) -> CostContext<HashedSubtreePrefix>
+    where
+        B: AsRef<[u8]>,
+    {
+        build_hashed_prefix(path)
     }
 
-    fn build_prefix_body<B>(path: SubtreePath<B>) -> (Vec<u8>, usize)
+    /// Builds the prefix of a subtree with a keyed hash of its path, so that
+    /// storages with different salts use different prefixes for the same
+    /// path. Unlike unkeyed prefixes, the prefix of the root is hashed too.
+    pub fn build_keyed_prefix<B>(
+        salt: &[u8; blake3::KEY_LEN],
+        path: SubtreePath<B>,
+    ) -> CostContext<HashedSubtreePrefix>
     where
         B: AsRef<[u8]>,
     {
-        let segments_iter = path.into_reverse_iter();
-        let mut segments_count: usize = 0;
-        let mut res = Vec::new();
-        let mut lengthes = Vec::new();
+        let (body, _) = build_prefix_body(path);
+        let blocks_count = blake_block_count(body.len());
+        HashedSubtreePrefix::from(blake3::keyed_hash(salt, &body))
+            .wrap_with_cost(OperationCost::with_hash_node_calls(blocks_count as u32))
+    }
 
-        for s in segments_iter {
-            segments_count += 1;
-            res.extend_from_slice(s);
-            lengthes.push(s.len() as u8); // if the key len is under 255 bytes
+    /// Builds the prefix of a subtree with the hierarchical scheme, without
+    /// its end marker. The segments of the path are appended one after the
+    /// other to a root, empty or hashed from `salt`, so the prefix of a subtree
+    /// extends the prefix of its parent.
+    fn build_hierarchical_prefix_body<B>(
+        salt: Option<&[u8; blake3::KEY_LEN]>,
+        path: SubtreePath<B>,
+    ) -> CostContext<Vec<u8>>
+    where
+        B: AsRef<[u8]>,
+    {
+        let mut cost = OperationCost::default();
+        let mut body = Vec::new();
+        if let Some(salt) = salt {
+            body.extend_from_slice(blake3::keyed_hash(salt, &[]).as_bytes());
+            cost.hash_node_calls += 1;
         }
+        for segment in path.to_vec() {
+            body.push(HIERARCHICAL_SEGMENT_MARKER);
+            body.extend_from_slice(&(segment.len() as u32).to_be_bytes());
+            body.extend_from_slice(&segment);
+        }
+        body.wrap_with_cost(cost)
+    }
 
-        res.extend(segments_count.to_ne_bytes());
-        res.extend(lengthes);
-        (res, segments_count)
+    /// Builds the prefix of a subtree with the hierarchical scheme, where the
+    /// prefix of a subtree extends the prefix of its parent, so the keys of a
+    /// subtree and of all its descendants form a single range
+    pub fn build_hierarchical_prefix<B>(
+        salt: Option<&[u8; blake3::KEY_LEN]>,
+        path: SubtreePath<B>,
+    ) -> CostContext<SubtreePrefix>
+    where
+        B: AsRef<[u8]>,
+    {
+        Self::build_hierarchical_prefix_body(salt, path).map(|mut prefix| {
+            prefix.push(HIERARCHICAL_PREFIX_END);
+            prefix
+        })
     }
 
-    /// A helper method to build a prefix to rocksdb keys or identify a subtree
-    /// in `subtrees` map by tree path;
-    pub fn build_prefix<B>(path: SubtreePath<B>) -> CostContext<SubtreePrefix>
+    /// Builds the prefix of a subtree of this storage with its prefix scheme,
+    /// keyed by its prefix salt if it has one
+    pub fn subtree_prefix<B>(&self, path: SubtreePath<B>) -> CostContext<SubtreePrefix>
     where
         B: AsRef<[u8]>,
     {
-        let (body, segments_count) = Self::build_prefix_body(path);
-        if segments_count == 0 {
-            SubtreePrefix::default().wrap_with_cost(OperationCost::default())
-        } else {
-            let blocks_count = blake_block_count(body.len());
-            SubtreePrefix::from(blake3::hash(&body))
-                .wrap_with_cost(OperationCost::with_hash_node_calls(blocks_count as u32))
+        match (self.prefix_scheme, &self.prefix_salt) {
+            (PrefixScheme::Hierarchical, salt) => {
+                Self::build_hierarchical_prefix(salt.as_ref(), path)
+            }
+            (PrefixScheme::Hashed, Some(salt)) => {
+                Self::build_keyed_prefix(salt, path).map(|prefix| prefix.to_vec())
+            }
+            (PrefixScheme::Hashed, None) => Self::build_prefix(path).map(|prefix| prefix.to_vec()),
+        }
+    }
+
+    /// Salt subtree prefixes are derived with, if any
+    pub fn prefix_salt(&self) -> Option<[u8; blake3::KEY_LEN]> {
+        self.prefix_salt
+    }
+
+    /// Length of the hierarchical prefix `prefixed_key` starts with, if it
+    /// starts with one
+    fn hierarchical_prefix_len(salted: bool, prefixed_key: &[u8]) -> Option<usize> {
+        let mut len = if salted { blake3::OUT_LEN } else { 0 };
+        loop {
+            match *prefixed_key.get(len)? {
+                HIERARCHICAL_PREFIX_END => return Some(len + 1),
+                HIERARCHICAL_SEGMENT_MARKER => {
+                    let segment_len = prefixed_key.get(len + 1..len + 5)?;
+                    let segment_len = u32::from_be_bytes(segment_len.try_into().ok()?) as usize;
+                    len += 5 + segment_len;
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// Length of a prefixed key as paid for by costs. Every prefix is paid for
+    /// as a hash, so costs don't depend on the prefix scheme or on the depth
+    /// of the subtree.
+    fn cost_key_len(&self, prefixed_key: &[u8]) -> u32 {
+        let len = match self.prefix_scheme {
+            PrefixScheme::Hashed => prefixed_key.len(),
+            PrefixScheme::Hierarchical => {
+                Self::hierarchical_prefix_len(self.prefix_salt.is_some(), prefixed_key)
+                    .map_or(prefixed_key.len(), |prefix_len| {
+                        prefixed_key.len() - prefix_len + blake3::OUT_LEN
+                    })
+            }
+        };
+        len as u32
+    }
+
+    /// Scheme subtree prefixes are derived with
+    pub fn prefix_scheme(&self) -> PrefixScheme {
+        self.prefix_scheme
+    }
+
+    /// Range of the storage keys of the subtree at `path` and of all its
+    /// descendants, as inclusive start and exclusive end, in every column
+    /// family. Only the hierarchical prefix scheme has such a range.
+    pub fn hierarchy_key_range<B>(&self, path: SubtreePath<B>) -> Option<(Vec<u8>, Vec<u8>)>
+    where
+        B: AsRef<[u8]>,
+    {
+        if self.prefix_scheme != PrefixScheme::Hierarchical {
+            return None;
+        }
+        let start = Self::build_hierarchical_prefix_body(self.prefix_salt.as_ref(), path).unwrap();
+        let mut end = start.clone();
+        end.push(HIERARCHICAL_RANGE_END);
+        Some((start, end))
+    }
+
+    /// Counts the storage keys of the subtree at `path` and of all its
+    /// descendants, with a single range scan per column family. Only
+    /// supported by the hierarchical prefix scheme.
+    pub fn hierarchy_usage<B>(&self, path: SubtreePath<B>) -> Result<HierarchyUsage, Error>
+    where
+        B: AsRef<[u8]>,
+    {
+        let (start, end) = self
+            .hierarchy_key_range(path)
+            .ok_or(Error::PrefixDerivation(
+                "hierarchy ranges require the hierarchical prefix scheme",
+            ))?;
+        let mut usage = HierarchyUsage::default();
+        for cf in [
+            None,
+            Some(cf_aux(&self.db)),
+            Some(cf_roots(&self.db)),
+            Some(cf_meta(&self.db)),
+        ] {
+            let mut read_options = rocksdb::ReadOptions::default();
+            read_options.set_iterate_upper_bound(end.clone());
+            let mut iter = match cf {
+                None => self.db.raw_iterator_opt(read_options),
+                Some(cf) => self.db.raw_iterator_cf_opt(cf, read_options),
+            };
+            iter.seek(&start);
+            while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+                usage.keys += 1;
+                usage.bytes += (key.len() + value.len()) as u64;
+                iter.next();
+            }
+            iter.status().map_err(RocksDBError)?;
+        }
+        Ok(usage)
+    }
+
+    /// Column families of the storage with their handles, `None` standing for
+    /// the default column family holding the data
+    fn column_families(&self) -> [(StorageColumnFamily, Option<&ColumnFamily>); 4] {
+        [
+            (StorageColumnFamily::Data, None),
+            (StorageColumnFamily::Aux, Some(cf_aux(&self.db))),
+            (StorageColumnFamily::Roots, Some(cf_roots(&self.db))),
+            (StorageColumnFamily::Meta, Some(cf_meta(&self.db))),
+        ]
+    }
+
+    /// Calls `f` with every record of every column family, as of the snapshot
+    /// pinned by `transaction`, which must have been started with
+    /// [`Storage::start_snapshot_transaction`]
+    pub fn for_each_record<'db, E, F>(&self, transaction: &Tx<'db>, mut f: F) -> Result<(), E>
+    where
+        E: From<Error>,
+        F: FnMut(StorageColumnFamily, &[u8], &[u8]) -> Result<(), E>,
+    {
+        let snapshot = transaction.snapshot();
+        for (column_family, cf) in self.column_families() {
+            let mut iter = match cf {
+                None => snapshot.raw_iterator_opt(rocksdb::ReadOptions::default()),
+                Some(cf) => snapshot.raw_iterator_cf_opt(cf, rocksdb::ReadOptions::default()),
+            };
+            iter.seek_to_first();
+            while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+                f(column_family, key, value)?;
+                iter.next();
+            }
+            iter.status().map_err(RocksDBError)?;
+        }
+        Ok(())
+    }
+
+    /// Whether the storage holds no records, besides the metadata of its
+    /// prefix derivation
+    pub fn is_empty(&self) -> Result<bool, Error> {
+        for (column_family, cf) in self.column_families() {
+            let mut iter = match cf {
+                None => self.db.raw_iterator(),
+                Some(cf) => self.db.raw_iterator_cf(cf),
+            };
+            iter.seek_to_first();
+            while let Some(key) = iter.key() {
+                let is_prefix_derivation_meta = column_family == StorageColumnFamily::Meta
+                    && (key == PREFIX_SALT_META_KEY || key == PREFIX_SCHEME_META_KEY);
+                if !is_prefix_derivation_meta {
+                    return Ok(false);
+                }
+                iter.next();
+            }
+            iter.status().map_err(RocksDBError)?;
+        }
+        Ok(true)
+    }
+
+    /// Writes `records` into their column families, in writes of a few
+    /// megabytes so records don't have to be held in memory at once. If a record fails, the records already written are
+    /// removed, so either all of them are imported or none is. The storage
+    /// must be empty, see [`Self::is_empty`].
+    pub fn import_records<E, I>(&self, records: I) -> Result<(), E>
+    where
+        E: From<Error>,
+        I: IntoIterator<Item = Result<(StorageColumnFamily, Vec<u8>, Vec<u8>), E>>,
+    {
+        if !self.is_empty()? {
+            return Err(Error::StorageError(
+                "records can only be imported into an empty storage".to_owned(),
+            )
+            .into());
+        }
+        let result = self.write_records_in_chunks(records);
+        if result.is_err() {
+            self.remove_records()?;
+        }
+        result
+    }
+
+    fn write_records_in_chunks<E, I>(&self, records: I) -> Result<(), E>
+    where
+        E: From<Error>,
+        I: IntoIterator<Item = Result<(StorageColumnFamily, Vec<u8>, Vec<u8>), E>>,
+    {
+        let mut db_batch = WriteBatchWithTransaction::<true>::default();
+        for record in records {
+            let (column_family, key, value) = record?;
+            match column_family {
+                StorageColumnFamily::Data => db_batch.put(key, value),
+                StorageColumnFamily::Aux => db_batch.put_cf(cf_aux(&self.db), key, value),
+                StorageColumnFamily::Roots => db_batch.put_cf(cf_roots(&self.db), key, value),
+                StorageColumnFamily::Meta => db_batch.put_cf(cf_meta(&self.db), key, value),
+            }
+            if db_batch.size_in_bytes() >= IMPORT_CHUNK_BYTES {
+                self.db.write(std::mem::take(&mut db_batch))?;
+            }
         }
+        self.db.write(db_batch)?;
+        Ok(())
     }
 
-    fn worst_case_body_size<L: WorstKeyLength>(path: &[L]) -> usize {
-        path.len() + path.iter().map(|a| a.max_length() as usize).sum::<usize>()
+    /// Removes every record of the storage, besides the metadata of its
+    /// prefix derivation
+    fn remove_records(&self) -> Result<(), Error> {
+        for (column_family, cf) in self.column_families() {
+            let mut iter = match cf {
+                None => self.db.raw_iterator(),
+                Some(cf) => self.db.raw_iterator_cf(cf),
+            };
+            iter.seek_to_first();
+            let mut db_batch = WriteBatchWithTransaction::<true>::default();
+            while let Some(key) = iter.key() {
+                let is_prefix_derivation_meta = column_family == StorageColumnFamily::Meta
+                    && (key == PREFIX_SALT_META_KEY || key == PREFIX_SCHEME_META_KEY);
+                if !is_prefix_derivation_meta {
+                    match cf {
+                        None => db_batch.delete(key),
+                        Some(cf) => db_batch.delete_cf(cf, key),
+                    }
+                }
+                if db_batch.size_in_bytes() >= IMPORT_CHUNK_BYTES {
+                    self.db.write(std::mem::take(&mut db_batch))?;
+                }
+                iter.next();
+            }
+            iter.status().map_err(RocksDBError)?;
+            self.db.write(db_batch)?;
+        }
+        Ok(())
     }
 
     /// Returns the write batch, with costs and pending costs
@@ -175,6 +776,17 @@ impl RocksDbStorage {
         &self,
         db_batch: &mut WriteBatchWithTransaction<true>,
         storage_batch: StorageBatch,
+    ) -> CostResult<OperationCost, Error> {
+        self.continue_write_batch_recording_key_costs(db_batch, storage_batch, None)
+    }
+
+    /// Continues the write batch like `continue_write_batch`, pushing the
+    /// pending costs of each operation, with its key, to `key_costs` if given
+    fn continue_write_batch_recording_key_costs(
+        &self,
+        db_batch: &mut WriteBatchWithTransaction<true>,
+        storage_batch: StorageBatch,
+        mut key_costs: Option<&mut Vec<(Vec<u8>, OperationCost)>>,
     ) -> CostResult<OperationCost, Error> {
         let mut cost = OperationCost::default();
         // Until batch is committed these costs are pending (should not be added in case
@@ -182,6 +794,8 @@ impl RocksDbStorage {
         let mut pending_costs = OperationCost::default();
 
         for op in storage_batch.into_iter() {
+            let key = key_costs.is_some().then(|| op.key().to_vec());
+            let mut op_pending_costs = OperationCost::default();
             match op {
                 AbstractBatchOperation::Put {
                     key,
@@ -193,9 +807,9 @@ impl RocksDbStorage {
                     cost.seek_count += 1;
                     cost_return_on_error_no_add!(
                         &cost,
-                        pending_costs
+                        op_pending_costs
                             .add_key_value_storage_costs(
-                                key.len() as u32,
+                                self.cost_key_len(&key),
                                 value.len() as u32,
                                 children_sizes,
                                 cost_info
@@ -212,9 +826,9 @@ impl RocksDbStorage {
                     cost.seek_count += 1;
                     cost_return_on_error_no_add!(
                         &cost,
-                        pending_costs
+                        op_pending_costs
                             .add_key_value_storage_costs(
-                                key.len() as u32,
+                                self.cost_key_len(&key),
                                 value.len() as u32,
                                 None,
                                 cost_info
@@ -222,6 +836,12 @@ impl RocksDbStorage {
                             .map_err(CostError)
                     );
                 }
+                AbstractBatchOperation::MergeAux { key, operand } => {
+                    // The folded counter replaces the stored one, so merges
+                    // don't add storage
+                    db_batch.merge_cf(cf_aux(&self.db), &key, &operand);
+                    cost.seek_count += 1;
+                }
                 AbstractBatchOperation::PutRoot {
                     key,
                     value,
@@ -233,9 +853,9 @@ impl RocksDbStorage {
                     if cost_info.is_some() {
                         cost_return_on_error_no_add!(
                             &cost,
-                            pending_costs
+                            op_pending_costs
                                 .add_key_value_storage_costs(
-                                    key.len() as u32,
+                                    self.cost_key_len(&key),
                                     value.len() as u32,
                                     None,
                                     cost_info
@@ -253,9 +873,9 @@ impl RocksDbStorage {
                     cost.seek_count += 1;
                     cost_return_on_error_no_add!(
                         &cost,
-                        pending_costs
+                        op_pending_costs
                             .add_key_value_storage_costs(
-                                key.len() as u32,
+                                self.cost_key_len(&key),
                                 value.len() as u32,
                                 None,
                                 cost_info
@@ -270,7 +890,7 @@ impl RocksDbStorage {
 
                     if let Some(key_value_removed_bytes) = cost_info {
                         cost.seek_count += 1;
-                        pending_costs.storage_cost.removed_bytes +=
+                        op_pending_costs.storage_cost.removed_bytes +=
                             key_value_removed_bytes.combined_removed_bytes();
                     } else {
                         cost.seek_count += 2;
@@ -282,9 +902,9 @@ impl RocksDbStorage {
                         .map(|x| x.len() as u32)
                         .unwrap_or(0);
                         cost.storage_loaded_bytes += value_len;
-                        let key_len = key.len() as u32;
+                        let key_len = self.cost_key_len(&key);
                         // todo: improve deletion
-                        pending_costs.storage_cost.removed_bytes += BasicStorageRemoval(
+                        op_pending_costs.storage_cost.removed_bytes += BasicStorageRemoval(
                             key_len
                                 + value_len
                                 + key_len.required_space() as u32
@@ -298,7 +918,7 @@ impl RocksDbStorage {
                     // TODO: fix not atomic freed size computation
                     if let Some(key_value_removed_bytes) = cost_info {
                         cost.seek_count += 1;
-                        pending_costs.storage_cost.removed_bytes +=
+                        op_pending_costs.storage_cost.removed_bytes +=
                             key_value_removed_bytes.combined_removed_bytes();
                     } else {
                         cost.seek_count += 2;
@@ -310,9 +930,9 @@ impl RocksDbStorage {
                         .unwrap_or(0);
                         cost.storage_loaded_bytes += value_len;
 
-                        let key_len = key.len() as u32;
+                        let key_len = self.cost_key_len(&key);
                         // todo: improve deletion
-                        pending_costs.storage_cost.removed_bytes += BasicStorageRemoval(
+                        op_pending_costs.storage_cost.removed_bytes += BasicStorageRemoval(
                             key_len
                                 + value_len
                                 + key_len.required_space() as u32
@@ -326,7 +946,7 @@ impl RocksDbStorage {
                     // TODO: fix not atomic freed size computation
                     if let Some(key_value_removed_bytes) = cost_info {
                         cost.seek_count += 1;
-                        pending_costs.storage_cost.removed_bytes +=
+                        op_pending_costs.storage_cost.removed_bytes +=
                             key_value_removed_bytes.combined_removed_bytes();
                     } else {
                         cost.seek_count += 2;
@@ -340,9 +960,9 @@ impl RocksDbStorage {
                         .unwrap_or(0);
                         cost.storage_loaded_bytes += value_len;
 
-                        let key_len = key.len() as u32;
+                        let key_len = self.cost_key_len(&key);
                         // todo: improve deletion
-                        pending_costs.storage_cost.removed_bytes += BasicStorageRemoval(
+                        op_pending_costs.storage_cost.removed_bytes += BasicStorageRemoval(
                             key_len
                                 + value_len
                                 + key_len.required_space() as u32
@@ -356,7 +976,7 @@ impl RocksDbStorage {
                     // TODO: fix not atomic freed size computation
                     if let Some(key_value_removed_bytes) = cost_info {
                         cost.seek_count += 1;
-                        pending_costs.storage_cost.removed_bytes +=
+                        op_pending_costs.storage_cost.removed_bytes +=
                             key_value_removed_bytes.combined_removed_bytes();
                     } else {
                         cost.seek_count += 2;
@@ -370,9 +990,9 @@ impl RocksDbStorage {
                         .unwrap_or(0);
                         cost.storage_loaded_bytes += value_len;
 
-                        let key_len = key.len() as u32;
+                        let key_len = self.cost_key_len(&key);
                         // todo: improve deletion
-                        pending_costs.storage_cost.removed_bytes += BasicStorageRemoval(
+                        op_pending_costs.storage_cost.removed_bytes += BasicStorageRemoval(
                             key_len
                                 + value_len
                                 + key_len.required_space() as u32
@@ -381,10 +1001,34 @@ impl RocksDbStorage {
                     }
                 }
             }
+            if let (Some(key_costs), Some(key)) = (key_costs.as_deref_mut(), key) {
+                key_costs.push((key, op_pending_costs.clone()));
+            }
+            pending_costs += op_pending_costs;
         }
         Ok(pending_costs).wrap_with_cost(cost)
     }
 
+    /// Starts `count` snapshot transactions pinned to the same state, so that
+    /// reads spread over them see one snapshot. Their start is retried while
+    /// writes land in between, `None` being returned if they keep doing so.
+    pub fn start_snapshot_transactions(&self, count: usize) -> Result<Option<Vec<Tx<'_>>>, Error> {
+        const ATTEMPTS: usize = 4;
+
+        let mut options = OptimisticTransactionOptions::default();
+        options.set_snapshot(true);
+        for _ in 0..ATTEMPTS {
+            let sequence_number = self.db.latest_sequence_number();
+            let transactions = (0..count)
+                .map(|_| self.db.transaction_opt(&options))
+                .collect::<Result<Vec<_>, Error>>()?;
+            if self.db.latest_sequence_number() == sequence_number {
+                return Ok(Some(transactions));
+            }
+        }
+        Ok(None)
+    }
+
     /// Commits a write batch
     pub fn commit_db_write_batch(
         &self,
@@ -394,17 +1038,37 @@ impl RocksDbStorage {
     ) -> CostResult<(), Error> {
         let result = match transaction {
             None => self.db.write(db_batch),
-            Some(transaction) => transaction.rebuild_from_writebatch(&db_batch),
+            Some(transaction) => transaction
+                .rebuild_from_writebatch(&db_batch)
+                .map_err(RocksDBError),
         };
 
         if result.is_ok() {
-            result.map_err(RocksDBError).wrap_with_cost(pending_costs)
+            result.wrap_with_cost(pending_costs)
         } else {
-            result
-                .map_err(RocksDBError)
-                .wrap_with_cost(OperationCost::default())
+            result.wrap_with_cost(OperationCost::default())
         }
     }
+
+    /// Commits a multi context batch like `commit_multi_context_batch`,
+    /// pushing the storage costs of each operation, with its prefixed key, to
+    /// `key_costs`
+    pub fn commit_multi_context_batch_recording_key_costs(
+        &self,
+        batch: StorageBatch,
+        transaction: Option<&<RocksDbStorage as Storage>::Transaction>,
+        key_costs: &mut Vec<(Vec<u8>, OperationCost)>,
+    ) -> CostResult<(), Error> {
+        let mut cost = OperationCost::default();
+        let mut db_batch = WriteBatchWithTransaction::<true>::default();
+        let pending_costs = cost_return_on_error!(
+            &mut cost,
+            self.continue_write_batch_recording_key_costs(&mut db_batch, batch, Some(key_costs))
+        );
+
+        self.commit_db_write_batch(db_batch, pending_costs, transaction)
+            .add_cost(cost)
+    }
 }
 
 impl<'db> Storage<'db> for RocksDbStorage {
@@ -413,8 +1077,15 @@ impl<'db> Storage<'db> for RocksDbStorage {
     type ImmediateStorageContext = PrefixedRocksDbImmediateStorageContext<'db>;
     type Transaction = Tx<'db>;
 
-    fn start_transaction(&'db self) -> Self::Transaction {
-        self.db.transaction()
+    fn start_transaction(&'db self) -> Result<Self::Transaction, Error> {
+        self.db
+            .transaction_opt(&OptimisticTransactionOptions::default())
+    }
+
+    fn start_snapshot_transaction(&'db self) -> Result<Self::Transaction, Error> {
+        let mut options = OptimisticTransactionOptions::default();
+        options.set_snapshot(true);
+        self.db.transaction_opt(&options)
     }
 
     fn commit_transaction(&self, transaction: Self::Transaction) -> CostResult<(), Error> {
@@ -429,8 +1100,19 @@ impl<'db> Storage<'db> for RocksDbStorage {
         transaction.rollback().map_err(RocksDBError)
     }
 
+    fn set_transaction_savepoint(&self, transaction: &Self::Transaction) {
+        transaction.set_savepoint()
+    }
+
+    fn rollback_transaction_to_savepoint(
+        &self,
+        transaction: &Self::Transaction,
+    ) -> Result<(), Error> {
+        transaction.rollback_to_savepoint().map_err(RocksDBError)
+    }
+
     fn flush(&self) -> Result<(), Error> {
-        self.db.flush().map_err(RocksDBError)
+        self.db.flush()
     }
 
     fn get_storage_context<'b, B>(
@@ -441,7 +1123,7 @@ impl<'db> Storage<'db> for RocksDbStorage {
     where
         B: AsRef<[u8]> + 'b,
     {
-        Self::build_prefix(path)
+        self.subtree_prefix(path)
             .map(|prefix| PrefixedRocksDbStorageContext::new(&self.db, prefix, batch))
     }
 
@@ -454,7 +1136,7 @@ impl<'db> Storage<'db> for RocksDbStorage {
     where
         B: AsRef<[u8]> + 'b,
     {
-        Self::build_prefix(path).map(|prefix| {
+        self.subtree_prefix(path).map(|prefix| {
             PrefixedRocksDbTransactionContext::new(&self.db, transaction, prefix, batch)
         })
     }
@@ -467,7 +1149,7 @@ impl<'db> Storage<'db> for RocksDbStorage {
     where
         B: AsRef<[u8]> + 'b,
     {
-        Self::build_prefix(path).map(|prefix| {
+        self.subtree_prefix(path).map(|prefix| {
             PrefixedRocksDbImmediateStorageContext::new(&self.db, transaction, prefix)
         })
     }
@@ -486,20 +1168,16 @@ impl<'db> Storage<'db> for RocksDbStorage {
     }
 
     fn get_storage_context_cost<L: WorstKeyLength>(path: &[L]) -> OperationCost {
-        if path.is_empty() {
-            OperationCost::default()
-        } else {
-            let body_size = Self::worst_case_body_size(path);
-            // the block size of blake3 is 64
-            let blocks_num = blake_block_count(body_size) as u32;
-            OperationCost::with_hash_node_calls(blocks_num)
-        }
+        hashed_prefix_worst_case_cost(path)
     }
 
     fn create_checkpoint<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
-        Checkpoint::new(&self.db)
-            .and_then(|x| x.create_checkpoint(path))
-            .map_err(RocksDBError)
+        match &self.db {
+            Db::Primary(db) => Checkpoint::new(db),
+            Db::Secondary(db) => Checkpoint::new(db),
+        }
+        .and_then(|x| x.create_checkpoint(path))
+        .map_err(RocksDBError)
     }
 }
 
@@ -518,7 +1196,7 @@ fn cf_roots(storage: &Db) -> &ColumnFamily {
 }
 
 /// Get metadata column family
-fn cf_meta(storage: &Db) -> &ColumnFamily {
+pub(crate) fn cf_meta(storage: &Db) -> &ColumnFamily {
     storage
         .cf_handle(META_CF_NAME)
         .expect("meta column family must exist")
@@ -40,11 +40,21 @@ pub use context_no_tx::PrefixedRocksDbStorageContext;
 pub use context_tx::PrefixedRocksDbTransactionContext;
 pub use raw_iterator::PrefixedRocksDbRawIterator;
 
+use rocksdb::ReadOptions;
+
 use super::storage::SubtreePrefix;
+pub(crate) use crate::prefix::{cost_key_len, make_prefixed_key};
+use crate::RawIteratorOptions;
 
-/// Make prefixed key
-pub fn make_prefixed_key<K: AsRef<[u8]>>(prefix: &SubtreePrefix, key: K) -> Vec<u8> {
-    let mut prefix_vec = prefix.to_vec();
-    prefix_vec.extend_from_slice(key.as_ref());
-    prefix_vec
+/// Make RocksDB read options for an iterator over a prefixed subtree
+fn make_read_options(prefix: &SubtreePrefix, options: &RawIteratorOptions) -> ReadOptions {
+    let mut read_options = ReadOptions::default();
+    if let Some(readahead_size) = options.readahead_size {
+        read_options.set_readahead_size(readahead_size);
+    }
+    read_options.fill_cache(options.fill_cache);
+    if let Some(upper_bound) = &options.iterate_upper_bound {
+        read_options.set_iterate_upper_bound(make_prefixed_key(prefix, upper_bound));
+    }
+    read_options
 }
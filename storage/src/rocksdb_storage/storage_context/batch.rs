@@ -34,7 +34,7 @@ use grovedb_costs::{
 use integer_encoding::VarInt;
 use rocksdb::{ColumnFamily, WriteBatchWithTransaction};
 
-use super::make_prefixed_key;
+use super::{cost_key_len, make_prefixed_key};
 use crate::{rocksdb_storage::storage::SubtreePrefix, Batch, StorageBatch};
 
 /// Wrapper to RocksDB batch.
@@ -75,15 +75,16 @@ impl<'db> Batch for PrefixedRocksDbBatch<'db> {
         let updated_cost_info = cost_info.map(|mut key_value_storage_cost| {
             if key_value_storage_cost.new_node {
                 // key is new, storage_cost needs to be created for it
+                let key_len = cost_key_len(&self.prefix, &prefixed_key);
                 key_value_storage_cost.key_storage_cost.added_bytes +=
-                    (prefixed_key.len() + prefixed_key.len().required_space()) as u32;
+                    (key_len + key_len.required_space()) as u32;
             }
             key_value_storage_cost
         });
 
         self.cost_acc.seek_count += 1;
         self.cost_acc.add_key_value_storage_costs(
-            prefixed_key.len() as u32,
+            cost_key_len(&self.prefix, &prefixed_key) as u32,
             value.len() as u32,
             children_sizes,
             updated_cost_info,
@@ -103,7 +104,7 @@ impl<'db> Batch for PrefixedRocksDbBatch<'db> {
 
         self.cost_acc.seek_count += 1;
         self.cost_acc.add_key_value_storage_costs(
-            prefixed_key.len() as u32,
+            cost_key_len(&self.prefix, &prefixed_key) as u32,
             value.len() as u32,
             None,
             cost_info,
@@ -125,7 +126,7 @@ impl<'db> Batch for PrefixedRocksDbBatch<'db> {
         // put root only pays if cost info is set
         if cost_info.is_some() {
             self.cost_acc.add_key_value_storage_costs(
-                prefixed_key.len() as u32,
+                cost_key_len(&self.prefix, &prefixed_key) as u32,
                 value.len() as u32,
                 None,
                 cost_info,
@@ -189,8 +190,9 @@ impl Batch for PrefixedMultiContextBatchPart {
         let updated_cost_info = cost_info.map(|mut key_value_storage_cost| {
             if key_value_storage_cost.new_node {
                 // key is new, storage_cost needs to be created for it
+                let key_len = cost_key_len(&self.prefix, &prefixed_key);
                 key_value_storage_cost.key_storage_cost.added_bytes +=
-                    (prefixed_key.len() + prefixed_key.len().required_space()) as u32;
+                    (key_len + key_len.required_space()) as u32;
             }
             key_value_storage_cost
         });
@@ -35,12 +35,14 @@ use grovedb_costs::{
 };
 use rocksdb::{ColumnFamily, DBRawIteratorWithThreadMode, WriteBatchWithTransaction};
 
-use super::{make_prefixed_key, PrefixedRocksDbBatch, PrefixedRocksDbRawIterator};
+use super::{
+    make_prefixed_key, make_read_options, PrefixedRocksDbBatch, PrefixedRocksDbRawIterator,
+};
 use crate::{
     error,
     error::Error::RocksDBError,
     rocksdb_storage::storage::{Db, SubtreePrefix, Tx, AUX_CF_NAME, META_CF_NAME, ROOTS_CF_NAME},
-    StorageContext,
+    RawIteratorOptions, StorageContext,
 };
 
 /// Storage context with a prefix applied to be used in a subtree to be used in
@@ -142,6 +144,13 @@ impl<'db> StorageContext<'db> for PrefixedRocksDbImmediateStorageContext<'db> {
             .wrap_with_cost(Default::default())
     }
 
+    fn merge_aux<K: AsRef<[u8]>>(&self, key: K, operand: &[u8]) -> CostResult<(), Error> {
+        self.transaction
+            .merge_cf(self.cf_aux(), make_prefixed_key(&self.prefix, key), operand)
+            .map_err(RocksDBError)
+            .wrap_with_cost(Default::default())
+    }
+
     fn delete<K: AsRef<[u8]>>(
         &self,
         key: K,
@@ -193,6 +202,11 @@ impl<'db> StorageContext<'db> for PrefixedRocksDbImmediateStorageContext<'db> {
             .wrap_with_cost(Default::default())
     }
 
+    fn get_staged<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error> {
+        // immediate contexts have no deferred writes
+        self.get(key)
+    }
+
     fn get_aux<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error> {
         self.transaction
             .get_cf(self.cf_aux(), make_prefixed_key(&self.prefix, key))
@@ -216,7 +230,7 @@ impl<'db> StorageContext<'db> for PrefixedRocksDbImmediateStorageContext<'db> {
 
     fn new_batch(&self) -> Self::Batch {
         PrefixedRocksDbBatch {
-            prefix: self.prefix,
+            prefix: self.prefix.clone(),
             batch: WriteBatchWithTransaction::<true>::default(),
             cf_aux: self.cf_aux(),
             cf_roots: self.cf_roots(),
@@ -233,8 +247,46 @@ impl<'db> StorageContext<'db> for PrefixedRocksDbImmediateStorageContext<'db> {
 
     fn raw_iter(&self) -> Self::RawIterator {
         PrefixedRocksDbRawIterator {
-            prefix: self.prefix,
+            prefix: self.prefix.clone(),
             raw_iterator: self.transaction.raw_iterator(),
+            upper_bound: None,
+        }
+    }
+
+    fn raw_iter_with_options(&self, options: &RawIteratorOptions) -> Self::RawIterator {
+        PrefixedRocksDbRawIterator {
+            prefix: self.prefix.clone(),
+            raw_iterator: self
+                .transaction
+                .raw_iterator_opt(make_read_options(&self.prefix, options)),
+            upper_bound: options
+                .iterate_upper_bound
+                .as_ref()
+                .map(|upper_bound| make_prefixed_key(&self.prefix, upper_bound)),
+        }
+    }
+
+    fn raw_iter_aux(&self) -> Self::RawIterator {
+        PrefixedRocksDbRawIterator {
+            prefix: self.prefix.clone(),
+            raw_iterator: self.transaction.raw_iterator_cf(self.cf_aux()),
+            upper_bound: None,
+        }
+    }
+
+    fn raw_iter_roots(&self) -> Self::RawIterator {
+        PrefixedRocksDbRawIterator {
+            prefix: self.prefix.clone(),
+            raw_iterator: self.transaction.raw_iterator_cf(self.cf_roots()),
+            upper_bound: None,
+        }
+    }
+
+    fn raw_iter_meta(&self) -> Self::RawIterator {
+        PrefixedRocksDbRawIterator {
+            prefix: self.prefix.clone(),
+            raw_iterator: self.transaction.raw_iterator_cf(self.cf_meta()),
+            upper_bound: None,
         }
     }
 }
@@ -33,14 +33,19 @@ use grovedb_costs::{
     storage_cost::key_value_cost::KeyValueStorageCost, ChildrenSizesWithIsSumTree, CostResult,
     CostsExt, OperationCost,
 };
-use rocksdb::{ColumnFamily, DBRawIteratorWithThreadMode};
+use rocksdb::ColumnFamily;
 
-use super::{batch::PrefixedMultiContextBatchPart, make_prefixed_key, PrefixedRocksDbRawIterator};
+use super::{
+    batch::PrefixedMultiContextBatchPart, make_prefixed_key, make_read_options,
+    PrefixedRocksDbRawIterator,
+};
 use crate::{
     error,
     error::Error::RocksDBError,
-    rocksdb_storage::storage::{Db, SubtreePrefix, AUX_CF_NAME, META_CF_NAME, ROOTS_CF_NAME},
-    StorageBatch, StorageContext,
+    rocksdb_storage::storage::{
+        Db, DbRawIterator, SubtreePrefix, AUX_CF_NAME, META_CF_NAME, ROOTS_CF_NAME,
+    },
+    RawIteratorOptions, StorageBatch, StorageContext,
 };
 
 /// Storage context with a prefix applied to be used in a subtree to be used
@@ -87,7 +92,7 @@ impl<'db> PrefixedRocksDbStorageContext<'db> {
 
 impl<'db> StorageContext<'db> for PrefixedRocksDbStorageContext<'db> {
     type Batch = PrefixedMultiContextBatchPart;
-    type RawIterator = PrefixedRocksDbRawIterator<DBRawIteratorWithThreadMode<'db, Db>>;
+    type RawIterator = PrefixedRocksDbRawIterator<DbRawIterator<'db>>;
 
     fn put<K: AsRef<[u8]>>(
         &self,
@@ -155,6 +160,13 @@ impl<'db> StorageContext<'db> for PrefixedRocksDbStorageContext<'db> {
         Ok(()).wrap_with_cost(OperationCost::default())
     }
 
+    fn merge_aux<K: AsRef<[u8]>>(&self, key: K, operand: &[u8]) -> CostResult<(), Error> {
+        if let Some(existing_batch) = self.batch {
+            existing_batch.merge_aux(make_prefixed_key(&self.prefix, key), operand.to_vec());
+        }
+        Ok(()).wrap_with_cost(OperationCost::default())
+    }
+
     fn delete<K: AsRef<[u8]>>(
         &self,
         key: K,
@@ -215,6 +227,39 @@ impl<'db> StorageContext<'db> for PrefixedRocksDbStorageContext<'db> {
             })
     }
 
+    fn multi_get<K: AsRef<[u8]>>(&self, keys: &[K]) -> CostResult<Vec<Option<Vec<u8>>>, Error> {
+        self.storage
+            .multi_get(keys.iter().map(|key| make_prefixed_key(&self.prefix, key)))
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(RocksDBError)
+            .wrap_fn_cost(|values| OperationCost {
+                seek_count: keys.len() as u16,
+                storage_loaded_bytes: values
+                    .as_ref()
+                    .map(|values| values.iter().flatten().map(|x| x.len() as u32).sum())
+                    .unwrap_or(0),
+                ..Default::default()
+            })
+    }
+
+    fn get_staged<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error> {
+        let pending = self
+            .batch
+            .and_then(|batch| batch.get(&make_prefixed_key(&self.prefix, &key)));
+        match pending {
+            Some(value) => {
+                let storage_loaded_bytes = value.as_ref().map(|x| x.len() as u32).unwrap_or(0);
+                Ok(value).wrap_with_cost(OperationCost {
+                    seek_count: 1,
+                    storage_loaded_bytes,
+                    ..Default::default()
+                })
+            }
+            None => self.get(key),
+        }
+    }
+
     fn get_aux<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error> {
         self.storage
             .get_cf(self.cf_aux(), make_prefixed_key(&self.prefix, key))
@@ -281,6 +326,44 @@ impl<'db> StorageContext<'db> for PrefixedRocksDbStorageContext<'db> {
         PrefixedRocksDbRawIterator {
             prefix: self.prefix.clone(),
             raw_iterator: self.storage.raw_iterator(),
+            upper_bound: None,
+        }
+    }
+
+    fn raw_iter_with_options(&self, options: &RawIteratorOptions) -> Self::RawIterator {
+        PrefixedRocksDbRawIterator {
+            prefix: self.prefix.clone(),
+            raw_iterator: self
+                .storage
+                .raw_iterator_opt(make_read_options(&self.prefix, options)),
+            upper_bound: options
+                .iterate_upper_bound
+                .as_ref()
+                .map(|upper_bound| make_prefixed_key(&self.prefix, upper_bound)),
+        }
+    }
+
+    fn raw_iter_aux(&self) -> Self::RawIterator {
+        PrefixedRocksDbRawIterator {
+            prefix: self.prefix.clone(),
+            raw_iterator: self.storage.raw_iterator_cf(self.cf_aux()),
+            upper_bound: None,
+        }
+    }
+
+    fn raw_iter_roots(&self) -> Self::RawIterator {
+        PrefixedRocksDbRawIterator {
+            prefix: self.prefix.clone(),
+            raw_iterator: self.storage.raw_iterator_cf(self.cf_roots()),
+            upper_bound: None,
+        }
+    }
+
+    fn raw_iter_meta(&self) -> Self::RawIterator {
+        PrefixedRocksDbRawIterator {
+            prefix: self.prefix.clone(),
+            raw_iterator: self.storage.raw_iterator_cf(self.cf_meta()),
+            upper_bound: None,
         }
     }
 }
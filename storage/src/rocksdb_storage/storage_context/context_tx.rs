@@ -33,14 +33,17 @@ use grovedb_costs::{
     cost_return_on_error, storage_cost::key_value_cost::KeyValueStorageCost,
     ChildrenSizesWithIsSumTree, CostResult, CostsExt, OperationCost,
 };
-use rocksdb::{ColumnFamily, DBRawIteratorWithThreadMode};
+use rocksdb::{ColumnFamily, DBRawIteratorWithThreadMode, ReadOptions};
 
-use super::{batch::PrefixedMultiContextBatchPart, make_prefixed_key, PrefixedRocksDbRawIterator};
+use super::{
+    batch::PrefixedMultiContextBatchPart, make_prefixed_key, make_read_options,
+    PrefixedRocksDbRawIterator,
+};
 use crate::{
     error,
     error::Error::RocksDBError,
     rocksdb_storage::storage::{Db, SubtreePrefix, Tx, AUX_CF_NAME, META_CF_NAME, ROOTS_CF_NAME},
-    RawIterator, StorageBatch, StorageContext,
+    RawIterator, RawIteratorOptions, StorageBatch, StorageContext,
 };
 
 /// Storage context with a prefix applied to be used in a subtree to be used in
@@ -110,6 +113,21 @@ impl<'db> PrefixedRocksDbTransactionContext<'db> {
             .cf_handle(META_CF_NAME)
             .expect("meta column family must exist")
     }
+
+    /// Read options for reads of the transaction, see
+    /// [`PrefixedRocksDbTransactionContext::with_snapshot`]
+    fn read_options(&self) -> ReadOptions {
+        self.with_snapshot(ReadOptions::default())
+    }
+
+    /// Pins reads to the snapshot of the transaction, if it was started with
+    /// one, so that they don't see writes committed afterwards. Read options
+    /// keep the snapshot itself, which is owned by the transaction, so the
+    /// handle to it can be dropped right away.
+    fn with_snapshot(&self, mut read_options: ReadOptions) -> ReadOptions {
+        read_options.set_snapshot(&self.transaction.snapshot());
+        read_options
+    }
 }
 
 impl<'db> StorageContext<'db> for PrefixedRocksDbTransactionContext<'db> {
@@ -182,6 +200,13 @@ impl<'db> StorageContext<'db> for PrefixedRocksDbTransactionContext<'db> {
         Ok(()).wrap_with_cost(OperationCost::default())
     }
 
+    fn merge_aux<K: AsRef<[u8]>>(&self, key: K, operand: &[u8]) -> CostResult<(), Error> {
+        if let Some(existing_batch) = self.batch {
+            existing_batch.merge_aux(make_prefixed_key(&self.prefix, key), operand.to_vec());
+        }
+        Ok(()).wrap_with_cost(OperationCost::default())
+    }
+
     fn delete<K: AsRef<[u8]>>(
         &self,
         key: K,
@@ -232,7 +257,7 @@ impl<'db> StorageContext<'db> for PrefixedRocksDbTransactionContext<'db> {
 
     fn get<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error> {
         self.transaction
-            .get(make_prefixed_key(&self.prefix, key))
+            .get_opt(make_prefixed_key(&self.prefix, key), &self.read_options())
             .map_err(RocksDBError)
             .wrap_fn_cost(|value| OperationCost {
                 seek_count: 1,
@@ -246,9 +271,49 @@ impl<'db> StorageContext<'db> for PrefixedRocksDbTransactionContext<'db> {
             })
     }
 
+    fn multi_get<K: AsRef<[u8]>>(&self, keys: &[K]) -> CostResult<Vec<Option<Vec<u8>>>, Error> {
+        self.transaction
+            .multi_get_opt(
+                keys.iter().map(|key| make_prefixed_key(&self.prefix, key)),
+                &self.read_options(),
+            )
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(RocksDBError)
+            .wrap_fn_cost(|values| OperationCost {
+                seek_count: keys.len() as u16,
+                storage_loaded_bytes: values
+                    .as_ref()
+                    .map(|values| values.iter().flatten().map(|x| x.len() as u32).sum())
+                    .unwrap_or(0),
+                ..Default::default()
+            })
+    }
+
+    fn get_staged<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error> {
+        let pending = self
+            .batch
+            .and_then(|batch| batch.get(&make_prefixed_key(&self.prefix, &key)));
+        match pending {
+            Some(value) => {
+                let storage_loaded_bytes = value.as_ref().map(|x| x.len() as u32).unwrap_or(0);
+                Ok(value).wrap_with_cost(OperationCost {
+                    seek_count: 1,
+                    storage_loaded_bytes,
+                    ..Default::default()
+                })
+            }
+            None => self.get(key),
+        }
+    }
+
     fn get_aux<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error> {
         self.transaction
-            .get_cf(self.cf_aux(), make_prefixed_key(&self.prefix, key))
+            .get_cf_opt(
+                self.cf_aux(),
+                make_prefixed_key(&self.prefix, key),
+                &self.read_options(),
+            )
             .map_err(RocksDBError)
             .wrap_fn_cost(|value| OperationCost {
                 seek_count: 1,
@@ -264,7 +329,11 @@ impl<'db> StorageContext<'db> for PrefixedRocksDbTransactionContext<'db> {
 
     fn get_root<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error> {
         self.transaction
-            .get_cf(self.cf_roots(), make_prefixed_key(&self.prefix, key))
+            .get_cf_opt(
+                self.cf_roots(),
+                make_prefixed_key(&self.prefix, key),
+                &self.read_options(),
+            )
             .map_err(RocksDBError)
             .wrap_fn_cost(|value| OperationCost {
                 seek_count: 1,
@@ -280,7 +349,11 @@ impl<'db> StorageContext<'db> for PrefixedRocksDbTransactionContext<'db> {
 
     fn get_meta<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error> {
         self.transaction
-            .get_cf(self.cf_meta(), make_prefixed_key(&self.prefix, key))
+            .get_cf_opt(
+                self.cf_meta(),
+                make_prefixed_key(&self.prefix, key),
+                &self.read_options(),
+            )
             .map_err(RocksDBError)
             .wrap_fn_cost(|value| OperationCost {
                 seek_count: 1,
@@ -312,7 +385,51 @@ impl<'db> StorageContext<'db> for PrefixedRocksDbTransactionContext<'db> {
     fn raw_iter(&self) -> Self::RawIterator {
         PrefixedRocksDbRawIterator {
             prefix: self.prefix.clone(),
-            raw_iterator: self.transaction.raw_iterator(),
+            raw_iterator: self.transaction.raw_iterator_opt(self.read_options()),
+            upper_bound: None,
+        }
+    }
+
+    fn raw_iter_with_options(&self, options: &RawIteratorOptions) -> Self::RawIterator {
+        PrefixedRocksDbRawIterator {
+            prefix: self.prefix.clone(),
+            raw_iterator: self
+                .transaction
+                .raw_iterator_opt(self.with_snapshot(make_read_options(&self.prefix, options))),
+            upper_bound: options
+                .iterate_upper_bound
+                .as_ref()
+                .map(|upper_bound| make_prefixed_key(&self.prefix, upper_bound)),
+        }
+    }
+
+    fn raw_iter_aux(&self) -> Self::RawIterator {
+        PrefixedRocksDbRawIterator {
+            prefix: self.prefix.clone(),
+            raw_iterator: self
+                .transaction
+                .raw_iterator_cf_opt(self.cf_aux(), self.read_options()),
+            upper_bound: None,
+        }
+    }
+
+    fn raw_iter_roots(&self) -> Self::RawIterator {
+        PrefixedRocksDbRawIterator {
+            prefix: self.prefix.clone(),
+            raw_iterator: self
+                .transaction
+                .raw_iterator_cf_opt(self.cf_roots(), self.read_options()),
+            upper_bound: None,
+        }
+    }
+
+    fn raw_iter_meta(&self) -> Self::RawIterator {
+        PrefixedRocksDbRawIterator {
+            prefix: self.prefix.clone(),
+            raw_iterator: self
+                .transaction
+                .raw_iterator_cf_opt(self.cf_meta(), self.read_options()),
+            upper_bound: None,
         }
     }
 }
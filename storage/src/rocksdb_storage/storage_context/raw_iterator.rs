@@ -29,11 +29,12 @@
 //! Prefixed storage_cost raw iterator implementation for RocksDB backend.
 
 use grovedb_costs::{CostContext, CostsExt, OperationCost};
-use rocksdb::DBRawIteratorWithThreadMode;
+use rocksdb::{DBAccess, DBRawIteratorWithThreadMode};
 
 use super::make_prefixed_key;
 use crate::{
-    rocksdb_storage::storage::{Db, SubtreePrefix, Tx},
+    prefix::next_prefix,
+    rocksdb_storage::storage::{DbRawIterator, SubtreePrefix, Tx},
     RawIterator,
 };
 
@@ -44,25 +45,66 @@ const MAX_PREFIXED_KEY_LENGTH: u32 = 256 + 32;
 pub struct PrefixedRocksDbRawIterator<I> {
     pub(super) prefix: SubtreePrefix,
     pub(super) raw_iterator: I,
+    /// Prefixed exclusive upper bound of visited keys; enforced here as well
+    /// since transaction iterators don't apply it to uncommitted writes
+    pub(super) upper_bound: Option<Vec<u8>>,
 }
 
-impl<'a> RawIterator for PrefixedRocksDbRawIterator<DBRawIteratorWithThreadMode<'a, Db>> {
+impl<I> PrefixedRocksDbRawIterator<I> {
+    /// Checks that a prefixed key belongs to the subtree and is below the upper
+    /// bound
+    fn is_in_bounds(&self, key: &[u8]) -> bool {
+        key.starts_with(&self.prefix)
+            && !matches!(&self.upper_bound, Some(upper_bound) if key >= upper_bound.as_slice())
+    }
+}
+
+impl<'a, D: DBAccess> PrefixedRocksDbRawIterator<DBRawIteratorWithThreadMode<'a, D>> {
+    /// Positions the iterator on the last record strictly before `bound`
+    fn seek_before(&mut self, bound: &[u8], cost: &mut OperationCost) {
+        self.raw_iterator.seek_for_prev(bound);
+        // `seek_for_prev` is inclusive, so a record keyed exactly by the bound has to
+        // be stepped over
+        if self.raw_iterator.key() == Some(bound) {
+            self.raw_iterator.prev();
+            cost.seek_count += 1;
+        }
+    }
+}
+
+impl<'a> PrefixedRocksDbRawIterator<DbRawIterator<'a>> {
+    /// Positions the iterator on the last record strictly before `bound`
+    fn seek_before(&mut self, bound: &[u8], cost: &mut OperationCost) {
+        self.raw_iterator.seek_for_prev(bound);
+        // `seek_for_prev` is inclusive, so a record keyed exactly by the bound has to
+        // be stepped over
+        if self.raw_iterator.key() == Some(bound) {
+            self.raw_iterator.prev();
+            cost.seek_count += 1;
+        }
+    }
+}
+
+impl<'a> RawIterator for PrefixedRocksDbRawIterator<DbRawIterator<'a>> {
     fn seek_to_first(&mut self) -> CostContext<()> {
         self.raw_iterator.seek(&self.prefix);
         ().wrap_with_cost(OperationCost::with_seek_count(1))
     }
 
     fn seek_to_last(&mut self) -> CostContext<()> {
-        let mut prefix_vec = self.prefix.to_vec();
-        for i in (0..prefix_vec.len()).rev() {
-            prefix_vec[i] = prefix_vec[i].wrapping_add(1);
-            if prefix_vec[i] != 0 {
-                // if it is == 0 then we need to go to next bit
-                break;
-            }
+        let mut cost = OperationCost::with_seek_count(1);
+        // the next prefix may be an empty key of the following subtree
+        match self
+            .upper_bound
+            .clone()
+            .or_else(|| next_prefix(&self.prefix))
+        {
+            Some(bound) => self.seek_before(&bound, &mut cost),
+            // no prefix follows this one, so its last record is the last one of the column
+            // family
+            None => self.raw_iterator.seek_to_last(),
         }
-        self.raw_iterator.seek_for_prev(prefix_vec);
-        ().wrap_with_cost(OperationCost::with_seek_count(1))
+        ().wrap_with_cost(cost)
     }
 
     fn seek<K: AsRef<[u8]>>(&mut self, key: K) -> CostContext<()> {
@@ -71,9 +113,13 @@ impl<'a> RawIterator for PrefixedRocksDbRawIterator<DBRawIteratorWithThreadMode<
     }
 
     fn seek_for_prev<K: AsRef<[u8]>>(&mut self, key: K) -> CostContext<()> {
-        self.raw_iterator
-            .seek_for_prev(make_prefixed_key(&self.prefix, key));
-        ().wrap_with_cost(OperationCost::with_seek_count(1))
+        let mut cost = OperationCost::with_seek_count(1);
+        let key = make_prefixed_key(&self.prefix, key);
+        match self.upper_bound.clone() {
+            Some(upper_bound) if key >= upper_bound => self.seek_before(&upper_bound, &mut cost),
+            _ => self.raw_iterator.seek_for_prev(key),
+        }
+        ().wrap_with_cost(cost)
     }
 
     fn next(&mut self) -> CostContext<()> {
@@ -108,7 +154,7 @@ impl<'a> RawIterator for PrefixedRocksDbRawIterator<DBRawIteratorWithThreadMode<
             Some(k) => {
                 // Even if we truncate prefix, loaded cost should be maximum for the whole
                 // function
-                if k.starts_with(&self.prefix) {
+                if self.is_in_bounds(k) {
                     cost.storage_loaded_bytes += k.len() as u32;
                     Some(k.split_at(self.prefix.len()).1)
                 } else {
@@ -141,7 +187,7 @@ impl<'a> RawIterator for PrefixedRocksDbRawIterator<DBRawIteratorWithThreadMode<
         self.raw_iterator
             .key()
             .map(|k| {
-                if k.starts_with(&self.prefix) {
+                if self.is_in_bounds(k) {
                     cost.storage_loaded_bytes += k.len() as u32;
                     true
                 } else {
@@ -174,16 +220,19 @@ impl<'a> RawIterator for PrefixedRocksDbRawIterator<DBRawIteratorWithThreadMode<
     }
 
     fn seek_to_last(&mut self) -> CostContext<()> {
-        let mut prefix_vec = self.prefix.to_vec();
-        for i in (0..prefix_vec.len()).rev() {
-            prefix_vec[i] = prefix_vec[i].wrapping_add(1);
-            if prefix_vec[i] != 0 {
-                // if it is == 0 then we need to go to next bit
-                break;
-            }
+        let mut cost = OperationCost::with_seek_count(1);
+        // the next prefix may be an empty key of the following subtree
+        match self
+            .upper_bound
+            .clone()
+            .or_else(|| next_prefix(&self.prefix))
+        {
+            Some(bound) => self.seek_before(&bound, &mut cost),
+            // no prefix follows this one, so its last record is the last one of the column
+            // family
+            None => self.raw_iterator.seek_to_last(),
         }
-        self.raw_iterator.seek_for_prev(prefix_vec);
-        ().wrap_with_cost(OperationCost::with_seek_count(1))
+        ().wrap_with_cost(cost)
     }
 
     fn seek<K: AsRef<[u8]>>(&mut self, key: K) -> CostContext<()> {
@@ -192,9 +241,13 @@ impl<'a> RawIterator for PrefixedRocksDbRawIterator<DBRawIteratorWithThreadMode<
     }
 
     fn seek_for_prev<K: AsRef<[u8]>>(&mut self, key: K) -> CostContext<()> {
-        self.raw_iterator
-            .seek_for_prev(make_prefixed_key(&self.prefix, key));
-        ().wrap_with_cost(OperationCost::with_seek_count(1))
+        let mut cost = OperationCost::with_seek_count(1);
+        let key = make_prefixed_key(&self.prefix, key);
+        match self.upper_bound.clone() {
+            Some(upper_bound) if key >= upper_bound => self.seek_before(&upper_bound, &mut cost),
+            _ => self.raw_iterator.seek_for_prev(key),
+        }
+        ().wrap_with_cost(cost)
     }
 
     fn next(&mut self) -> CostContext<()> {
@@ -229,7 +282,7 @@ impl<'a> RawIterator for PrefixedRocksDbRawIterator<DBRawIteratorWithThreadMode<
             Some(k) => {
                 // Even if we truncate prefix, loaded cost should be maximum for the whole
                 // function
-                if k.starts_with(&self.prefix) {
+                if self.is_in_bounds(k) {
                     cost.storage_loaded_bytes += k.len() as u32;
                     Some(k.split_at(self.prefix.len()).1)
                 } else {
@@ -262,7 +315,7 @@ impl<'a> RawIterator for PrefixedRocksDbRawIterator<DBRawIteratorWithThreadMode<
         self.raw_iterator
             .key()
             .map(|k| {
-                if k.starts_with(&self.prefix) {
+                if self.is_in_bounds(k) {
                     cost.storage_loaded_bytes += k.len() as u32;
                     true
                 } else {
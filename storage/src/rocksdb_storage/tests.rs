@@ -32,13 +32,23 @@ use super::test_utils::TempStorage;
 use crate::Batch;
 
 mod immediate_storage {
+    use grovedb_path::SubtreePath;
+    use tempfile::TempDir;
+
     use super::*;
-    use crate::{RawIterator, Storage, StorageContext};
+    use crate::{
+        error::Error,
+        rocksdb_storage::{
+            ColumnFamilyTuning, CompactionStyle, Compression, PrefixScheme, RocksDbStorage,
+            RocksDbStorageBuilder, StorageColumnFamily,
+        },
+        RawIterator, RawIteratorOptions, Storage, StorageBatch, StorageContext,
+    };
 
     #[test]
     fn test_aux_cf_methods() {
         let storage = TempStorage::new();
-        let tx = storage.start_transaction();
+        let tx = storage.start_transaction().unwrap();
         let context_ayya = storage
             .get_immediate_storage_context([b"ayya"].as_ref().into(), &tx)
             .unwrap();
@@ -78,11 +88,11 @@ mod immediate_storage {
             .unwrap()
             .expect("cannot commit transaction");
 
-        let tx2 = storage.start_transaction();
+        let tx2 = storage.start_transaction().unwrap();
         let context_ayya_after_tx = storage
             .get_immediate_storage_context([b"ayya"].as_ref().into(), &tx2)
             .unwrap();
-        let tx3 = storage.start_transaction();
+        let tx3 = storage.start_transaction().unwrap();
         let context_ayya_after_no_tx = storage
             .get_immediate_storage_context([b"ayya"].as_ref().into(), &tx3)
             .unwrap();
@@ -127,7 +137,7 @@ mod immediate_storage {
     #[test]
     fn test_roots_cf_methods() {
         let storage = TempStorage::new();
-        let tx = storage.start_transaction();
+        let tx = storage.start_transaction().unwrap();
         let context_ayya = storage
             .get_immediate_storage_context([b"ayya"].as_ref().into(), &tx)
             .unwrap();
@@ -167,11 +177,11 @@ mod immediate_storage {
             .unwrap()
             .expect("cannot commit transaction");
 
-        let tx2 = storage.start_transaction();
+        let tx2 = storage.start_transaction().unwrap();
         let context_ayya_after_tx = storage
             .get_immediate_storage_context([b"ayya"].as_ref().into(), &tx2)
             .unwrap();
-        let tx3 = storage.start_transaction();
+        let tx3 = storage.start_transaction().unwrap();
         let context_ayya_after_no_tx = storage
             .get_immediate_storage_context([b"ayya"].as_ref().into(), &tx3)
             .unwrap();
@@ -213,10 +223,79 @@ mod immediate_storage {
             .is_none());
     }
 
+    #[test]
+    fn test_aux_and_roots_cf_iterators() {
+        let storage = TempStorage::new();
+        let tx = storage.start_transaction().unwrap();
+        let context_ayya = storage
+            .get_immediate_storage_context([b"ayya"].as_ref().into(), &tx)
+            .unwrap();
+        let context_ayyb = storage
+            .get_immediate_storage_context([b"ayyb"].as_ref().into(), &tx)
+            .unwrap();
+
+        for context in [&context_ayya, &context_ayyb] {
+            context
+                .put_aux(b"aux2", b"auxvalue2", None)
+                .unwrap()
+                .expect("cannot insert into aux cf");
+            context
+                .put_aux(b"aux1", b"auxvalue1", None)
+                .unwrap()
+                .expect("cannot insert into aux cf");
+            context
+                .put_root(b"root1", b"rootvalue1", None)
+                .unwrap()
+                .expect("cannot insert into roots cf");
+        }
+        context_ayya
+            .put(b"key1", b"value1", None, None)
+            .unwrap()
+            .expect("cannot insert into storage");
+
+        fn collect<I: RawIterator>(mut iter: I) -> Vec<(Vec<u8>, Vec<u8>)> {
+            let mut entries = Vec::new();
+            iter.seek_to_first().unwrap();
+            while iter.valid().unwrap() {
+                entries.push((
+                    iter.key().unwrap().unwrap().to_vec(),
+                    iter.value().unwrap().unwrap().to_vec(),
+                ));
+                iter.next().unwrap();
+            }
+            entries
+        }
+
+        // Each column family is iterated separately and within the subtree prefix only
+        assert_eq!(
+            collect(context_ayya.raw_iter_aux()),
+            vec![
+                (b"aux1".to_vec(), b"auxvalue1".to_vec()),
+                (b"aux2".to_vec(), b"auxvalue2".to_vec()),
+            ]
+        );
+        assert_eq!(
+            collect(context_ayya.raw_iter_roots()),
+            vec![(b"root1".to_vec(), b"rootvalue1".to_vec())]
+        );
+        assert_eq!(
+            collect(context_ayya.raw_iter()),
+            vec![(b"key1".to_vec(), b"value1".to_vec())]
+        );
+
+        let mut iter = context_ayyb.raw_iter_aux();
+        iter.seek_to_last().unwrap();
+        assert_eq!(iter.key().unwrap(), Some(b"aux2".as_ref()));
+        iter.prev().unwrap();
+        assert_eq!(iter.key().unwrap(), Some(b"aux1".as_ref()));
+        iter.prev().unwrap();
+        assert!(!iter.valid().unwrap());
+    }
+
     #[test]
     fn test_meta_cf_methods() {
         let storage = TempStorage::new();
-        let tx = storage.start_transaction();
+        let tx = storage.start_transaction().unwrap();
         let context_ayya = storage
             .get_immediate_storage_context([b"ayya"].as_ref().into(), &tx)
             .unwrap();
@@ -284,7 +363,7 @@ mod immediate_storage {
     #[test]
     fn test_default_cf_methods() {
         let storage = TempStorage::new();
-        let tx = storage.start_transaction();
+        let tx = storage.start_transaction().unwrap();
         let context_ayya = storage
             .get_immediate_storage_context([b"ayya"].as_ref().into(), &tx)
             .unwrap();
@@ -349,10 +428,45 @@ mod immediate_storage {
         );
     }
 
+    #[test]
+    fn test_snapshot_transactions() {
+        let storage = TempStorage::new();
+        let snapshots = storage
+            .start_snapshot_transactions(3)
+            .expect("cannot start snapshot transactions")
+            .expect("expected snapshot transactions without concurrent writes");
+        assert_eq!(snapshots.len(), 3);
+
+        let tx = storage.start_transaction().unwrap();
+        storage
+            .get_immediate_storage_context([b"ayya"].as_ref().into(), &tx)
+            .unwrap()
+            .put(b"key1", b"ayyavalue1", None, None)
+            .unwrap()
+            .expect("cannot insert into storage");
+        storage
+            .commit_transaction(tx)
+            .unwrap()
+            .expect("cannot commit transaction");
+
+        // Writes committed after the snapshots were started aren't seen from
+        // any of them
+        for snapshot in snapshots.iter() {
+            let context = storage
+                .get_transactional_storage_context([b"ayya"].as_ref().into(), None, snapshot)
+                .unwrap();
+            assert!(context
+                .get(b"key1")
+                .unwrap()
+                .expect("cannot get from storage")
+                .is_none());
+        }
+    }
+
     #[test]
     fn test_batch() {
         let storage = TempStorage::new();
-        let tx = storage.start_transaction();
+        let tx = storage.start_transaction().unwrap();
         let context_ayya = storage
             .get_immediate_storage_context([b"ayya"].as_ref().into(), &tx)
             .unwrap();
@@ -398,7 +512,7 @@ mod immediate_storage {
             .unwrap()
             .expect("cannot commit transaction");
 
-        let tx = storage.start_transaction();
+        let tx = storage.start_transaction().unwrap();
         let context_ayya = storage
             .get_immediate_storage_context([b"ayya"].as_ref().into(), &tx)
             .unwrap();
@@ -421,7 +535,7 @@ mod immediate_storage {
     #[test]
     fn test_raw_iterator() {
         let storage = TempStorage::new();
-        let tx = storage.start_transaction();
+        let tx = storage.start_transaction().unwrap();
         let context = storage
             .get_immediate_storage_context([b"someprefix"].as_ref().into(), &tx)
             .unwrap();
@@ -472,7 +586,7 @@ mod immediate_storage {
 
         // Test uncommited changes
         {
-            let tx = storage.start_transaction();
+            let tx = storage.start_transaction().unwrap();
             let context_tx = storage
                 .get_immediate_storage_context([b"someprefix"].as_ref().into(), &tx)
                 .unwrap();
@@ -528,7 +642,7 @@ mod immediate_storage {
                 (b"key3", b"value3"),
             ];
             let mut expected_iter = expected.into_iter();
-            let tx = storage.start_transaction();
+            let tx = storage.start_transaction().unwrap();
             let context = storage
                 .get_immediate_storage_context([b"someprefix"].as_ref().into(), &tx)
                 .unwrap();
@@ -545,11 +659,541 @@ mod immediate_storage {
             assert!(expected_iter.next().is_none());
         }
     }
+    #[test]
+    fn test_raw_iterator_backwards() {
+        let storage = TempStorage::new();
+        let tx = storage.start_transaction().unwrap();
+        let context = storage
+            .get_immediate_storage_context([b"someprefix"].as_ref().into(), &tx)
+            .unwrap();
+
+        for key in [b"key1", b"key3", b"key5"] {
+            context
+                .put(key, b"value", None, None)
+                .unwrap()
+                .expect("expected successful insertion");
+        }
+
+        // Neighbouring subtrees must not leak into backward iteration
+        for prefix in [
+            b"anothersomeprefix".as_ref(),
+            b"zanothersomeprefix".as_ref(),
+        ] {
+            let neighbour_context = storage
+                .get_immediate_storage_context([prefix].as_ref().into(), &tx)
+                .unwrap();
+            for key in [b"key0", b"key4", b"key9"] {
+                neighbour_context
+                    .put(key, b"other", None, None)
+                    .unwrap()
+                    .expect("expected successful insertion");
+            }
+        }
+
+        let mut iter = context.raw_iter();
+
+        // Exact match
+        iter.seek_for_prev(b"key3").unwrap();
+        assert_eq!(iter.key().unwrap(), Some(b"key3".as_ref()));
+
+        // Between two keys, the lower one is picked
+        iter.seek_for_prev(b"key4").unwrap();
+        assert_eq!(iter.key().unwrap(), Some(b"key3".as_ref()));
+
+        // Past the last key
+        iter.seek_for_prev(b"key9").unwrap();
+        assert_eq!(iter.key().unwrap(), Some(b"key5".as_ref()));
+
+        // Before the first key
+        iter.seek_for_prev(b"key0").unwrap();
+        assert!(!iter.valid().unwrap());
+        assert_eq!(iter.key().unwrap(), None);
+
+        // Stepping backwards stops at the prefix boundary
+        let mut collected = Vec::new();
+        iter.seek_to_last().unwrap();
+        while iter.valid().unwrap() {
+            collected.push(iter.key().unwrap().unwrap().to_vec());
+            iter.prev().unwrap();
+        }
+        assert_eq!(
+            collected,
+            vec![b"key5".to_vec(), b"key3".to_vec(), b"key1".to_vec()]
+        );
+
+        // Backward and forward steps can be mixed
+        iter.seek_for_prev(b"key4").unwrap();
+        iter.prev().unwrap();
+        assert_eq!(iter.key().unwrap(), Some(b"key1".as_ref()));
+        iter.next().unwrap();
+        assert_eq!(iter.key().unwrap(), Some(b"key3".as_ref()));
+    }
+
+    #[test]
+    fn test_raw_iterator_backwards_on_empty_subtree() {
+        let storage = TempStorage::new();
+        let tx = storage.start_transaction().unwrap();
+        let context = storage
+            .get_immediate_storage_context([b"someprefix"].as_ref().into(), &tx)
+            .unwrap();
+        let neighbour_context = storage
+            .get_immediate_storage_context([b"anothersomeprefix"].as_ref().into(), &tx)
+            .unwrap();
+        neighbour_context
+            .put(b"key", b"value", None, None)
+            .unwrap()
+            .expect("expected successful insertion");
+
+        let mut iter = context.raw_iter();
+        iter.seek_to_last().unwrap();
+        assert!(!iter.valid().unwrap());
+        iter.seek_for_prev(b"key").unwrap();
+        assert!(!iter.valid().unwrap());
+    }
+
+    #[test]
+    fn test_raw_iterator_backwards_edge_cases() {
+        let storage = TempStorage::new();
+        let tx = storage.start_transaction().unwrap();
+
+        // Empty path: the root subtree must not see keys of its children
+        let root_context = storage
+            .get_immediate_storage_context(SubtreePath::empty(), &tx)
+            .unwrap();
+        for key in [b"rootkey1", b"rootkey2"] {
+            root_context
+                .put(key, b"value", None, None)
+                .unwrap()
+                .expect("expected successful insertion");
+        }
+        let child_context = storage
+            .get_immediate_storage_context([b"a"].as_ref().into(), &tx)
+            .unwrap();
+        child_context
+            .put(b"rootkey3", b"value", None, None)
+            .unwrap()
+            .expect("expected successful insertion");
+
+        let mut iter = root_context.raw_iter();
+        let mut collected = Vec::new();
+        iter.seek_to_last().unwrap();
+        while iter.valid().unwrap() {
+            collected.push(iter.key().unwrap().unwrap().to_vec());
+            iter.prev().unwrap();
+        }
+        assert_eq!(collected, vec![b"rootkey2".to_vec(), b"rootkey1".to_vec()]);
+
+        // Boundary keys, with the same keys present in neighbouring subtrees
+        let max_key = [0xFF; 32];
+        let boundary_keys: [&[u8]; 3] = [b"", &[0x00], &max_key];
+        for prefix in [b"someprefix".as_ref(), b"anothersomeprefix".as_ref()] {
+            let context = storage
+                .get_immediate_storage_context([prefix].as_ref().into(), &tx)
+                .unwrap();
+            for key in boundary_keys {
+                context
+                    .put(key, b"value", None, None)
+                    .unwrap()
+                    .expect("expected successful insertion");
+            }
+        }
+        let context = storage
+            .get_immediate_storage_context([b"someprefix"].as_ref().into(), &tx)
+            .unwrap();
+        let mut iter = context.raw_iter();
+
+        iter.seek_to_last().unwrap();
+        assert_eq!(iter.key().unwrap(), Some(max_key.as_ref()));
+
+        iter.seek_for_prev([0x00u8, 0x00]).unwrap();
+        assert_eq!(iter.key().unwrap(), Some([0x00u8].as_ref()));
+
+        iter.seek_for_prev(b"").unwrap();
+        assert_eq!(iter.key().unwrap(), Some(b"".as_ref()));
+        iter.prev().unwrap();
+        assert!(!iter.valid().unwrap());
+
+        // Missing subtree: nothing was ever written under this path
+        let missing_context = storage
+            .get_immediate_storage_context([b"missingprefix"].as_ref().into(), &tx)
+            .unwrap();
+        let mut iter = missing_context.raw_iter();
+        iter.seek_to_last().unwrap();
+        assert!(!iter.valid().unwrap());
+        iter.seek_for_prev(max_key).unwrap();
+        assert!(!iter.valid().unwrap());
+        assert_eq!(iter.key().unwrap(), None);
+    }
+
+    #[test]
+    fn test_raw_iterator_with_options() {
+        let storage = TempStorage::new();
+        let tx = storage.start_transaction().unwrap();
+        let context = storage
+            .get_immediate_storage_context([b"someprefix"].as_ref().into(), &tx)
+            .unwrap();
+        for key in [b"key1", b"key3", b"key5"] {
+            context
+                .put(key, b"value", None, None)
+                .unwrap()
+                .expect("expected successful insertion");
+        }
+
+        let options = RawIteratorOptions {
+            readahead_size: Some(2 * 1024 * 1024),
+            fill_cache: false,
+            iterate_upper_bound: Some(b"key5".to_vec()),
+        };
+
+        let mut collected = Vec::new();
+        let mut iter = context.raw_iter_with_options(&options);
+        iter.seek_to_first().unwrap();
+        while iter.valid().unwrap() {
+            collected.push(iter.key().unwrap().unwrap().to_vec());
+            iter.next().unwrap();
+        }
+        assert_eq!(collected, vec![b"key1".to_vec(), b"key3".to_vec()]);
+
+        // The upper bound also limits backward iteration
+        iter.seek_to_last().unwrap();
+        assert_eq!(iter.key().unwrap(), Some(b"key3".as_ref()));
+        iter.seek_for_prev(b"key9").unwrap();
+        assert_eq!(iter.key().unwrap(), Some(b"key3".as_ref()));
+
+        // Default options behave like a plain raw iterator
+        let mut iter = context.raw_iter_with_options(&RawIteratorOptions::default());
+        iter.seek_to_last().unwrap();
+        assert_eq!(iter.key().unwrap(), Some(b"key5".as_ref()));
+    }
+
+    #[test]
+    fn test_storage_builder_column_family_tuning() {
+        let tmp_dir = TempDir::new().expect("cannot create tempdir");
+        let builder = RocksDbStorageBuilder::new()
+            .column_family_tuning(
+                StorageColumnFamily::Data,
+                ColumnFamilyTuning {
+                    compaction_style: Some(CompactionStyle::Level),
+                    target_file_size_base: Some(32 << 20),
+                    compression: Some(Compression::Lz4),
+                },
+            )
+            .column_family_tuning(
+                StorageColumnFamily::Aux,
+                ColumnFamilyTuning {
+                    compaction_style: Some(CompactionStyle::Universal),
+                    compression: Some(Compression::None),
+                    ..Default::default()
+                },
+            )
+            .column_family_tuning(
+                StorageColumnFamily::Roots,
+                ColumnFamilyTuning {
+                    target_file_size_base: Some(4 << 20),
+                    ..Default::default()
+                },
+            );
+
+        {
+            let storage = builder
+                .clone()
+                .open(tmp_dir.path())
+                .expect("cannot open storage");
+            let tx = storage.start_transaction().unwrap();
+            let context = storage
+                .get_immediate_storage_context([b"ayya"].as_ref().into(), &tx)
+                .unwrap();
+            context
+                .put(b"key1", b"ayyavalue1", None, None)
+                .unwrap()
+                .expect("cannot insert into storage");
+            context
+                .put_aux(b"key1", b"ayyaauxvalue1", None)
+                .unwrap()
+                .expect("cannot insert into aux cf");
+            storage
+                .commit_transaction(tx)
+                .unwrap()
+                .expect("cannot commit transaction");
+        }
+
+        let storage = builder.open(tmp_dir.path()).expect("cannot reopen storage");
+        let tx = storage.start_transaction().unwrap();
+        let context = storage
+            .get_immediate_storage_context([b"ayya"].as_ref().into(), &tx)
+            .unwrap();
+        assert_eq!(
+            context
+                .get(b"key1")
+                .unwrap()
+                .ok()
+                .flatten()
+                .expect("cannot get from storage"),
+            b"ayyavalue1"
+        );
+        assert_eq!(
+            context
+                .get_aux(b"key1")
+                .unwrap()
+                .ok()
+                .flatten()
+                .expect("cannot get from aux cf"),
+            b"ayyaauxvalue1"
+        );
+    }
+
+    #[test]
+    fn test_storage_builder_handles_column_family_changes() {
+        let tmp_dir = TempDir::new().expect("cannot create tempdir");
+        let mut opts = rocksdb::Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+
+        // A database written before the meta column family existed
+        drop(
+            rocksdb::DB::open_cf(&opts, tmp_dir.path(), ["aux", "roots"])
+                .expect("cannot create old database"),
+        );
+        drop(
+            RocksDbStorageBuilder::new()
+                .open(tmp_dir.path())
+                .expect("cannot open old database"),
+        );
+        let column_families =
+            rocksdb::DB::list_cf(&opts, tmp_dir.path()).expect("cannot list column families");
+        assert!(column_families.iter().any(|name| name == "meta"));
+
+        // A database written by a version with an additional column family
+        {
+            let mut db = rocksdb::DB::open_cf(&opts, tmp_dir.path(), &column_families)
+                .expect("cannot open database");
+            db.create_cf("future", &opts)
+                .expect("cannot create column family");
+        }
+        assert!(matches!(
+            RocksDbStorageBuilder::new().open(tmp_dir.path()),
+            Err(Error::UnknownColumnFamilies(column_families))
+                if column_families == vec!["future".to_owned()]
+        ));
+    }
+
+    #[test]
+    fn test_storage_builder_prefix_salt() {
+        let tmp_dir = TempDir::new().expect("cannot create tempdir");
+        let path = [b"ayya"];
+
+        {
+            let storage = RocksDbStorageBuilder::new()
+                .prefix_salt([1; 32])
+                .open(tmp_dir.path())
+                .expect("cannot open storage");
+            assert_eq!(storage.prefix_salt(), Some([1; 32]));
+            assert_ne!(
+                storage.subtree_prefix(path.as_ref().into()).unwrap(),
+                RocksDbStorage::build_prefix(path.as_ref().into()).unwrap()
+            );
+            assert_ne!(
+                storage.subtree_prefix(path.as_ref().into()).unwrap(),
+                RocksDbStorage::build_keyed_prefix(&[2; 32], path.as_ref().into()).unwrap()
+            );
+        }
+
+        // The stored salt is used when opening without one
+        let storage = RocksDbStorageBuilder::new()
+            .open(tmp_dir.path())
+            .expect("cannot reopen storage");
+        assert_eq!(storage.prefix_salt(), Some([1; 32]));
+        drop(storage);
+
+        assert!(matches!(
+            RocksDbStorageBuilder::new()
+                .prefix_salt([2; 32])
+                .open(tmp_dir.path()),
+            Err(Error::PrefixDerivation(_))
+        ));
+
+        // An existing database can't be salted
+        let unsalted_dir = TempDir::new().expect("cannot create tempdir");
+        drop(
+            RocksDbStorageBuilder::new()
+                .open(unsalted_dir.path())
+                .expect("cannot open storage"),
+        );
+        assert!(matches!(
+            RocksDbStorageBuilder::new()
+                .prefix_salt([1; 32])
+                .open(unsalted_dir.path()),
+            Err(Error::PrefixDerivation(_))
+        ));
+    }
+
+    #[test]
+    fn test_storage_builder_hierarchical_prefixes() {
+        let tmp_dir = TempDir::new().expect("cannot create tempdir");
+        let builder = RocksDbStorageBuilder::new().prefix_scheme(PrefixScheme::Hierarchical);
+
+        {
+            let storage = builder
+                .clone()
+                .open(tmp_dir.path())
+                .expect("cannot open storage");
+            let tx = storage.start_transaction().unwrap();
+            for path in [
+                [b"a"].as_ref(),
+                [b"a", b"b"].as_ref(),
+                [b"a", b"b", b"c"].as_ref(),
+                [b"b"].as_ref(),
+            ] {
+                let context = storage
+                    .get_immediate_storage_context(path.into(), &tx)
+                    .unwrap();
+                context
+                    .put(b"key", b"value", None, None)
+                    .unwrap()
+                    .expect("cannot insert into storage");
+                context
+                    .put_aux(b"key", b"value", None)
+                    .unwrap()
+                    .expect("cannot insert into aux cf");
+            }
+            storage
+                .commit_transaction(tx)
+                .unwrap()
+                .expect("cannot commit transaction");
+
+            // The prefix of a subtree extends the prefix of its parent, while
+            // iterating over a subtree doesn't reach its descendants
+            let parent_range = storage
+                .hierarchy_key_range([b"a"].as_ref().into())
+                .expect("hierarchical scheme has ranges");
+            let child_prefix = storage
+                .subtree_prefix([b"a", b"b"].as_ref().into())
+                .unwrap();
+            assert!(child_prefix.starts_with(&parent_range.0));
+            let tx = storage.start_transaction().unwrap();
+            let context = storage
+                .get_immediate_storage_context([b"a"].as_ref().into(), &tx)
+                .unwrap();
+            let mut iter = context.raw_iter();
+            iter.seek_to_first().unwrap();
+            assert_eq!(iter.key().unwrap(), Some(b"key".as_ref()));
+            iter.next().unwrap();
+            assert!(!iter.valid().unwrap());
+            drop(iter);
+            drop(context);
+            drop(tx);
+
+            assert_eq!(
+                storage
+                    .hierarchy_usage([b"a"].as_ref().into())
+                    .expect("cannot read usage")
+                    .keys,
+                6
+            );
+            assert_eq!(
+                storage
+                    .hierarchy_usage([b"a", b"b", b"c"].as_ref().into())
+                    .expect("cannot read usage")
+                    .keys,
+                2
+            );
+        }
+
+        // The stored scheme is used when opening without one
+        let storage = RocksDbStorageBuilder::new()
+            .open(tmp_dir.path())
+            .expect("cannot reopen storage");
+        assert_eq!(storage.prefix_scheme(), PrefixScheme::Hierarchical);
+        drop(storage);
+
+        assert!(matches!(
+            RocksDbStorageBuilder::new()
+                .prefix_scheme(PrefixScheme::Hashed)
+                .open(tmp_dir.path()),
+            Err(Error::PrefixDerivation(_))
+        ));
+
+        // Hashed prefixes have no hierarchy ranges
+        let hashed_dir = TempDir::new().expect("cannot create tempdir");
+        let storage = RocksDbStorageBuilder::new()
+            .open(hashed_dir.path())
+            .expect("cannot open storage");
+        assert!(storage
+            .hierarchy_key_range([b"a"].as_ref().into())
+            .is_none());
+        assert!(matches!(
+            storage.hierarchy_usage([b"a"].as_ref().into()),
+            Err(Error::PrefixDerivation(_))
+        ));
+    }
+
+    #[test]
+    fn test_storage_builder_secondary() {
+        let primary_dir = TempDir::new().expect("cannot create tempdir");
+        let secondary_dir = TempDir::new().expect("cannot create tempdir");
+        let put = |storage: &RocksDbStorage, key: &[u8]| {
+            let batch = StorageBatch::new();
+            storage
+                .get_storage_context([b"ayya"].as_ref().into(), Some(&batch))
+                .unwrap()
+                .put(key, b"value", None, None)
+                .unwrap()
+                .expect("cannot insert into storage");
+            storage.commit_multi_context_batch(batch, None).unwrap()
+        };
+        let get = |storage: &RocksDbStorage, key: &[u8]| {
+            storage
+                .get_storage_context([b"ayya"].as_ref().into(), None)
+                .unwrap()
+                .get(key)
+                .unwrap()
+                .expect("cannot get from storage")
+        };
+
+        assert!(RocksDbStorageBuilder::new()
+            .open_as_secondary(primary_dir.path(), secondary_dir.path())
+            .is_err());
+
+        let primary = RocksDbStorageBuilder::new()
+            .prefix_salt([1; 32])
+            .open(primary_dir.path())
+            .expect("cannot open storage");
+        put(&primary, b"key1").expect("cannot commit batch");
+        primary.flush().expect("cannot flush storage");
+
+        let secondary = RocksDbStorageBuilder::new()
+            .open_as_secondary(primary_dir.path(), secondary_dir.path())
+            .expect("cannot open secondary storage");
+        assert!(secondary.is_secondary());
+        assert!(!primary.is_secondary());
+        assert_eq!(secondary.prefix_salt(), Some([1; 32]));
+        assert_eq!(get(&secondary, b"key1"), Some(b"value".to_vec()));
+
+        // Writes of the primary are seen once the secondary caught up
+        put(&primary, b"key2").expect("cannot commit batch");
+        assert_eq!(get(&secondary, b"key2"), None);
+        secondary
+            .try_catch_up_with_primary()
+            .expect("cannot catch up with primary");
+        assert_eq!(get(&secondary, b"key2"), Some(b"value".to_vec()));
+        assert!(primary.try_catch_up_with_primary().is_err());
+
+        // The secondary is read only
+        assert!(matches!(put(&secondary, b"key3"), Err(Error::ReadOnly(_))));
+        assert!(matches!(
+            secondary.start_transaction(),
+            Err(Error::ReadOnly(_))
+        ));
+        assert_eq!(get(&primary, b"key3"), None);
+    }
 }
 
 mod batch_no_transaction {
     use super::*;
-    use crate::{Batch, Storage, StorageBatch, StorageContext};
+    use crate::{
+        counter::{decode_counter, encode_counter},
+        Batch, Storage, StorageBatch, StorageContext,
+    };
 
     #[test]
     fn test_various_cf_methods() {
@@ -773,16 +1417,101 @@ mod batch_no_transaction {
             b"ayyavalue3"
         );
     }
+
+    #[test]
+    fn test_multi_get() {
+        let storage = TempStorage::new();
+        let batch = StorageBatch::new();
+        let context = storage
+            .get_storage_context([b"ayya"].as_ref().into(), Some(&batch))
+            .unwrap();
+        context
+            .put(b"key1", b"ayyavalue1", None, None)
+            .unwrap()
+            .expect("cannot insert into storage");
+        context
+            .put(b"key2", b"ayyavalue2", None, None)
+            .unwrap()
+            .expect("cannot insert into storage");
+        storage
+            .commit_multi_context_batch(batch, None)
+            .unwrap()
+            .expect("cannot commit multi context batch");
+
+        let context = storage
+            .get_storage_context([b"ayya"].as_ref().into(), None)
+            .unwrap();
+        let keys: [&[u8]; 3] = [b"key2", b"key3", b"key1"];
+        let values = context.multi_get(&keys);
+        assert_eq!(values.cost.seek_count, 3);
+        assert_eq!(
+            values.value.expect("cannot get data"),
+            vec![
+                Some(b"ayyavalue2".to_vec()),
+                None,
+                Some(b"ayyavalue1".to_vec())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_aux_counter() {
+        let storage = TempStorage::new();
+        let batch = StorageBatch::new();
+        let context = storage
+            .get_storage_context([b"ayya"].as_ref().into(), Some(&batch))
+            .unwrap();
+        context
+            .merge_aux(b"counter", &encode_counter(5))
+            .unwrap()
+            .expect("cannot merge");
+        context
+            .merge_aux(b"counter", &encode_counter(2))
+            .unwrap()
+            .expect("cannot merge");
+        // Operands on the same key are folded in the batch
+        assert_eq!(batch.len(), 1);
+        storage
+            .commit_multi_context_batch(batch, None)
+            .unwrap()
+            .expect("cannot commit multi context batch");
+
+        let batch = StorageBatch::new();
+        let context = storage
+            .get_storage_context([b"ayya"].as_ref().into(), Some(&batch))
+            .unwrap();
+        context
+            .merge_aux(b"counter", &encode_counter(-3))
+            .unwrap()
+            .expect("cannot merge");
+        storage
+            .commit_multi_context_batch(batch, None)
+            .unwrap()
+            .expect("cannot commit multi context batch");
+
+        let context = storage
+            .get_storage_context([b"ayya"].as_ref().into(), None)
+            .unwrap();
+        let value = context
+            .get_aux(b"counter")
+            .unwrap()
+            .expect("cannot get data")
+            .expect("expected a counter");
+        assert_eq!(decode_counter(&value), Some(4));
+    }
 }
 
 mod batch_transaction {
     use super::*;
-    use crate::{Batch, RawIterator, Storage, StorageBatch, StorageContext};
+    use crate::{
+        counter::{decode_counter, encode_counter},
+        Batch, RawIterator, Storage, StorageBatch, StorageContext,
+    };
 
     #[test]
     fn test_transaction_properties() {
         let storage = TempStorage::new();
-        let transaction = storage.start_transaction();
+        let transaction = storage.start_transaction().unwrap();
 
         let batch = StorageBatch::new();
         let batch_tx = StorageBatch::new();
@@ -959,7 +1688,7 @@ mod batch_transaction {
     #[test]
     fn test_db_batch_in_transaction_merged_into_context_batch() {
         let storage = TempStorage::new();
-        let transaction = storage.start_transaction();
+        let transaction = storage.start_transaction().unwrap();
         let batch = StorageBatch::new();
 
         let context_ayya = storage
@@ -1069,4 +1798,59 @@ mod batch_transaction {
             Some(b"value2".to_vec())
         );
     }
+
+    #[test]
+    fn test_counter_merges_in_transaction() {
+        let storage = TempStorage::new();
+        let transaction = storage.start_transaction().unwrap();
+        for delta in [1, 2] {
+            let batch = StorageBatch::new();
+            let context = storage
+                .get_transactional_storage_context(
+                    [b"ayya"].as_ref().into(),
+                    Some(&batch),
+                    &transaction,
+                )
+                .unwrap();
+            context
+                .merge_aux(b"counter", &encode_counter(delta))
+                .unwrap()
+                .expect("cannot merge");
+            storage
+                .commit_multi_context_batch(batch, Some(&transaction))
+                .unwrap()
+                .expect("cannot commit multi context batch");
+        }
+
+        {
+            let context = storage
+                .get_transactional_storage_context([b"ayya"].as_ref().into(), None, &transaction)
+                .unwrap();
+            let value = context
+                .get_aux(b"counter")
+                .unwrap()
+                .expect("cannot get data")
+                .expect("expected a counter");
+            assert_eq!(decode_counter(&value), Some(3));
+        }
+        let context = storage
+            .get_storage_context([b"ayya"].as_ref().into(), None)
+            .unwrap();
+        assert!(context
+            .get_aux(b"counter")
+            .unwrap()
+            .expect("cannot get data")
+            .is_none());
+
+        storage
+            .commit_transaction(transaction)
+            .unwrap()
+            .expect("cannot commit transaction");
+        let value = context
+            .get_aux(b"counter")
+            .unwrap()
+            .expect("cannot get data")
+            .expect("expected a counter");
+        assert_eq!(decode_counter(&value), Some(3));
+    }
 }
@@ -35,13 +35,13 @@ use std::{
 };
 
 use grovedb_costs::{
-    storage_cost::key_value_cost::KeyValueStorageCost, ChildrenSizesWithIsSumTree, CostContext,
-    CostResult, OperationCost,
+    cost_return_on_error, storage_cost::key_value_cost::KeyValueStorageCost,
+    ChildrenSizesWithIsSumTree, CostContext, CostResult, CostsExt, OperationCost,
 };
 use grovedb_path::SubtreePath;
 use grovedb_visualize::visualize_to_vec;
 
-use crate::{worst_case_costs::WorstKeyLength, Error};
+use crate::{counter::fold_counter, worst_case_costs::WorstKeyLength, Error};
 
 /// Top-level storage_cost abstraction.
 /// Should be able to hold storage_cost connection and to start transaction when
@@ -60,8 +60,12 @@ pub trait Storage<'db> {
     /// is replication process.
     type ImmediateStorageContext: StorageContext<'db>;
 
-    /// Starts a new transaction
-    fn start_transaction(&'db self) -> Self::Transaction;
+    /// Starts a new transaction, failing on a read-only storage
+    fn start_transaction(&'db self) -> Result<Self::Transaction, Error>;
+
+    /// Starts a transaction whose reads are pinned to the state of the
+    /// storage when it is started, not seeing writes committed afterwards
+    fn start_snapshot_transaction(&'db self) -> Result<Self::Transaction, Error>;
 
     /// Consumes and commits a transaction
     fn commit_transaction(&self, transaction: Self::Transaction) -> CostResult<(), Error>;
@@ -69,6 +73,15 @@ pub trait Storage<'db> {
     /// Rollback a transaction
     fn rollback_transaction(&self, transaction: &Self::Transaction) -> Result<(), Error>;
 
+    /// Records the state of a transaction to roll back to, savepoints stack
+    fn set_transaction_savepoint(&self, transaction: &Self::Transaction);
+
+    /// Rollback a transaction to its most recent savepoint and removes it
+    fn rollback_transaction_to_savepoint(
+        &self,
+        transaction: &Self::Transaction,
+    ) -> Result<(), Error>;
+
     /// Consumes and applies multi-context batch.
     fn commit_multi_context_batch(
         &self,
@@ -163,6 +176,10 @@ pub trait StorageContext<'db> {
         cost_info: Option<KeyValueStorageCost>,
     ) -> CostResult<(), Error>;
 
+    /// Merge a counter increment `operand` into auxiliary data storage_cost
+    /// with `key`, see [`crate::counter`]
+    fn merge_aux<K: AsRef<[u8]>>(&self, key: K, operand: &[u8]) -> CostResult<(), Error>;
+
     /// Delete entry with `key` from data storage_cost
     fn delete<K: AsRef<[u8]>>(
         &self,
@@ -194,6 +211,22 @@ pub trait StorageContext<'db> {
     /// Get entry by `key` from data storage_cost
     fn get<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error>;
 
+    /// Get entries by `keys` from data storage_cost, in the order of the keys
+    /// with `None` for the missing ones. Storages able to look up many keys at
+    /// once override the default of one `get` per key.
+    fn multi_get<K: AsRef<[u8]>>(&self, keys: &[K]) -> CostResult<Vec<Option<Vec<u8>>>, Error> {
+        let mut cost = OperationCost::default();
+        let mut values = Vec::with_capacity(keys.len());
+        for key in keys {
+            values.push(cost_return_on_error!(&mut cost, self.get(key)));
+        }
+        Ok(values).wrap_with_cost(cost)
+    }
+
+    /// Get entry by `key` from data storage_cost, seeing the writes deferred in
+    /// the batch of a batched context
+    fn get_staged<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error>;
+
     /// Get entry by `key` from auxiliary data storage_cost
     fn get_aux<K: AsRef<[u8]>>(&self, key: K) -> CostResult<Option<Vec<u8>>, Error>;
 
@@ -211,6 +244,42 @@ pub trait StorageContext<'db> {
 
     /// Get raw iterator over storage_cost
     fn raw_iter(&self) -> Self::RawIterator;
+
+    /// Get raw iterator over storage_cost tuned with the given options
+    fn raw_iter_with_options(&self, options: &RawIteratorOptions) -> Self::RawIterator;
+
+    /// Get raw iterator over auxiliary data storage_cost
+    fn raw_iter_aux(&self) -> Self::RawIterator;
+
+    /// Get raw iterator over trees roots storage_cost
+    fn raw_iter_roots(&self) -> Self::RawIterator;
+
+    /// Get raw iterator over metadata storage_cost
+    fn raw_iter_meta(&self) -> Self::RawIterator;
+}
+
+/// Read tuning options of a raw iterator, allowing large scans to stream
+/// efficiently without affecting point lookups.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawIteratorOptions {
+    /// Amount of bytes to read ahead while iterating, storage default if
+    /// `None`
+    pub readahead_size: Option<usize>,
+    /// Whether blocks read by the iterator are put into the block cache
+    pub fill_cache: bool,
+    /// Exclusive upper bound of keys visited by the iterator, relative to the
+    /// storage context
+    pub iterate_upper_bound: Option<Vec<u8>>,
+}
+
+impl Default for RawIteratorOptions {
+    fn default() -> Self {
+        RawIteratorOptions {
+            readahead_size: None,
+            fill_cache: true,
+            iterate_upper_bound: None,
+        }
+    }
 }
 
 /// Database batch (not to be confused with multi-tree operations batch).
@@ -327,6 +396,17 @@ impl StorageBatch {
             + operations.meta.len()
     }
 
+    /// Get the deferred state of a data record: `Some(Some(value))` for a
+    /// pending put, `Some(None)` for a pending delete and `None` if the batch
+    /// doesn't touch the key
+    pub(crate) fn get(&self, key: &[u8]) -> Option<Option<Vec<u8>>> {
+        match self.operations.borrow().data.get(key)? {
+            AbstractBatchOperation::Put { value, .. } => Some(Some(value.clone())),
+            AbstractBatchOperation::Delete { .. } => Some(None),
+            _ => None,
+        }
+    }
+
     /// Add deferred `put` operation
     pub(crate) fn put(
         &self,
@@ -397,6 +477,48 @@ impl StorageBatch {
         );
     }
 
+    /// Add deferred counter `merge` operation for aux storage_cost, folding it
+    /// into an operation already deferred for the key
+    pub(crate) fn merge_aux(&self, key: Vec<u8>, operand: Vec<u8>) {
+        let operations = &mut self.operations.borrow_mut().aux;
+        let folded = match operations.get(&key) {
+            Some(AbstractBatchOperation::MergeAux {
+                operand: existing, ..
+            }) => fold_counter(Some(existing), [operand.as_slice()]).map(|operand| {
+                AbstractBatchOperation::MergeAux {
+                    key: key.clone(),
+                    operand,
+                }
+            }),
+            Some(AbstractBatchOperation::PutAux {
+                value, cost_info, ..
+            }) => fold_counter(Some(value), [operand.as_slice()]).map(|value| {
+                AbstractBatchOperation::PutAux {
+                    key: key.clone(),
+                    value,
+                    cost_info: cost_info.clone(),
+                }
+            }),
+            Some(AbstractBatchOperation::DeleteAux { cost_info, .. }) => {
+                fold_counter(None, [operand.as_slice()]).map(|value| {
+                    AbstractBatchOperation::PutAux {
+                        key: key.clone(),
+                        value,
+                        cost_info: cost_info.clone(),
+                    }
+                })
+            }
+            _ => None,
+        };
+        // An operand that can't be folded is deferred as is for the storage to
+        // report
+        let op = folded.unwrap_or_else(|| AbstractBatchOperation::MergeAux {
+            key: key.clone(),
+            operand,
+        });
+        operations.insert(key, op);
+    }
+
     /// Add deferred `delete` operation
     pub(crate) fn delete(&self, key: Vec<u8>, cost_info: Option<KeyValueStorageCost>) {
         let operations = &mut self.operations.borrow_mut().data;
@@ -466,6 +588,7 @@ impl StorageBatch {
                     value,
                     cost_info,
                 } => self.put_meta(key, value, cost_info),
+                AbstractBatchOperation::MergeAux { key, operand } => self.merge_aux(key, operand),
                 AbstractBatchOperation::Delete { key, cost_info } => self.delete(key, cost_info),
                 AbstractBatchOperation::DeleteAux { key, cost_info } => {
                     self.delete_aux(key, cost_info)
@@ -552,6 +675,8 @@ pub(crate) enum AbstractBatchOperation {
         value: Vec<u8>,
         cost_info: Option<KeyValueStorageCost>,
     },
+    /// Deferred counter merge operation for aux storage_cost
+    MergeAux { key: Vec<u8>, operand: Vec<u8> },
     /// Deferred delete operation
     Delete {
         key: Vec<u8>,
@@ -574,6 +699,23 @@ pub(crate) enum AbstractBatchOperation {
     },
 }
 
+impl AbstractBatchOperation {
+    /// Key the operation is on
+    pub(crate) fn key(&self) -> &[u8] {
+        match self {
+            AbstractBatchOperation::Put { key, .. }
+            | AbstractBatchOperation::PutAux { key, .. }
+            | AbstractBatchOperation::PutRoot { key, .. }
+            | AbstractBatchOperation::PutMeta { key, .. }
+            | AbstractBatchOperation::MergeAux { key, .. }
+            | AbstractBatchOperation::Delete { key, .. }
+            | AbstractBatchOperation::DeleteAux { key, .. }
+            | AbstractBatchOperation::DeleteRoot { key, .. }
+            | AbstractBatchOperation::DeleteMeta { key, .. } => key,
+        }
+    }
+}
+
 impl std::fmt::Debug for AbstractBatchOperation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut fmt = f.debug_struct(self.as_ref());
@@ -585,7 +727,11 @@ impl std::fmt::Debug for AbstractBatchOperation {
             AbstractBatchOperation::Put { key, value, .. }
             | AbstractBatchOperation::PutAux { key, value, .. }
             | AbstractBatchOperation::PutMeta { key, value, .. }
-            | AbstractBatchOperation::PutRoot { key, value, .. } => {
+            | AbstractBatchOperation::PutRoot { key, value, .. }
+            | AbstractBatchOperation::MergeAux {
+                key,
+                operand: value,
+            } => {
                 key_buf.clear();
                 value_buf.clear();
                 visualize_to_vec(&mut key_buf, key.as_slice());
@@ -0,0 +1,66 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Aggregates
+//!
+//! Aggregate queries return a single value computed over the elements a path
+//! query matches instead of the elements themselves.
+
+use grovedb_merk::proofs::query::QueryItem;
+
+use crate::{Error, PathQuery};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Aggregate computed over the elements matched by a path query
+pub enum Aggregate {
+    /// Number of matched elements. The path query can't have subqueries.
+    Count,
+    /// Total of the matched sum items, references to sum items included
+    Sum,
+}
+
+/// Adds up sum item values, failing on overflow
+pub fn total_of_sum_items<I: IntoIterator<Item = i64>>(values: I) -> Result<i64, Error> {
+    values
+        .into_iter()
+        .try_fold(0i64, |total, value| total.checked_add(value))
+        .ok_or(Error::SumOverflow("sum of sum items overflows"))
+}
+
+/// Whether a path query matches every element of the subtree at its path,
+/// so that the count of a count tree is given by the count tree element
+pub fn matches_whole_subtree(query: &PathQuery) -> bool {
+    !query.path.is_empty()
+        && query.query.limit.is_none()
+        && query.query.offset.is_none()
+        && !query.query.query.has_subquery()
+        && matches!(
+            query.query.query.items.as_slice(),
+            [QueryItem::RangeFull(..)]
+        )
+}
@@ -0,0 +1,66 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Backlinks proofs
+//!
+//! A backlinks proof proves an element and, for each of its referrers, the
+//! bidirectional reference pointing to it. As backlinks aren't covered by the
+//! root hash, it can't prove that no referrer was left out.
+
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+
+/// Proof of the referrers of an element, generated by
+/// `GroveDb::prove_backlinks`
+#[derive(Serialize, Deserialize)]
+pub struct BacklinksProof {
+    /// Proof of the element
+    pub element_proof: Vec<u8>,
+    /// Proofs of the bidirectional references pointing to the element
+    pub referrers: Vec<ReferrerProof>,
+}
+
+/// Proof of a bidirectional reference pointing to an element
+#[derive(Serialize, Deserialize)]
+pub struct ReferrerProof {
+    /// Qualified path of the reference
+    pub referrer: Vec<Vec<u8>>,
+    /// The serialized reference, which proofs replace with the element it
+    /// points to
+    pub reference: Vec<u8>,
+    /// Proof of the reference
+    pub proof: Vec<u8>,
+}
+
+/// Serialization options of the qualified paths held by backlinks and of
+/// backlinks proofs
+pub fn backlinks_serialization_options() -> impl Options {
+    bincode::DefaultOptions::default()
+        .with_varint_encoding()
+        .reject_trailing_bytes()
+}
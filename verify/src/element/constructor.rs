@@ -31,7 +31,7 @@
 
 #[cfg(feature = "full")]
 use crate::{
-    element::{MaxReferenceHop, SumValue},
+    element::{AggregateId, AggregateValue, BigSumValue, CountValue, MaxReferenceHop, SumValue},
     reference_path::ReferencePathType,
     Element, ElementFlags,
 };
@@ -62,6 +62,45 @@ impl Element {
         Element::new_sum_tree_with_flags(Default::default(), flags)
     }
 
+    #[cfg(feature = "full")]
+    /// Set element to default empty count tree without flags
+    pub fn empty_count_tree() -> Self {
+        Element::new_count_tree(Default::default())
+    }
+
+    #[cfg(feature = "full")]
+    /// Set element to default empty count tree with flags
+    pub fn empty_count_tree_with_flags(flags: Option<ElementFlags>) -> Self {
+        Element::new_count_tree_with_flags(Default::default(), flags)
+    }
+
+    #[cfg(feature = "full")]
+    /// Set element to default empty big sum tree without flags
+    pub fn empty_big_sum_tree() -> Self {
+        Element::new_big_sum_tree(Default::default())
+    }
+
+    #[cfg(feature = "full")]
+    /// Set element to default empty big sum tree with flags
+    pub fn empty_big_sum_tree_with_flags(flags: Option<ElementFlags>) -> Self {
+        Element::new_big_sum_tree_with_flags(Default::default(), flags)
+    }
+
+    #[cfg(feature = "full")]
+    /// Set element to default empty aggregate tree without flags
+    pub fn empty_aggregate_tree(aggregate_id: AggregateId) -> Self {
+        Element::new_aggregate_tree(Default::default(), aggregate_id)
+    }
+
+    #[cfg(feature = "full")]
+    /// Set element to default empty aggregate tree with flags
+    pub fn empty_aggregate_tree_with_flags(
+        aggregate_id: AggregateId,
+        flags: Option<ElementFlags>,
+    ) -> Self {
+        Element::new_aggregate_tree_with_flags(Default::default(), aggregate_id, flags)
+    }
+
     #[cfg(feature = "full")]
     /// Set element to an item without flags
     pub fn new_item(item_value: Vec<u8>) -> Self {
@@ -120,6 +159,31 @@ impl Element {
         Element::Reference(reference_path, max_reference_hop, flags)
     }
 
+    #[cfg(feature = "full")]
+    /// Set element to a bidirectional reference without flags
+    pub fn new_bidirectional_reference(reference_path: ReferencePathType) -> Self {
+        Element::BidirectionalReference(reference_path, None, None)
+    }
+
+    #[cfg(feature = "full")]
+    /// Set element to a bidirectional reference with flags
+    pub fn new_bidirectional_reference_with_flags(
+        reference_path: ReferencePathType,
+        flags: Option<ElementFlags>,
+    ) -> Self {
+        Element::BidirectionalReference(reference_path, None, flags)
+    }
+
+    #[cfg(feature = "full")]
+    /// Set element to a bidirectional reference with max hops and flags
+    pub fn new_bidirectional_reference_with_max_hops_and_flags(
+        reference_path: ReferencePathType,
+        max_reference_hop: MaxReferenceHop,
+        flags: Option<ElementFlags>,
+    ) -> Self {
+        Element::BidirectionalReference(reference_path, max_reference_hop, flags)
+    }
+
     #[cfg(feature = "full")]
     /// Set element to a tree without flags
     pub fn new_tree(maybe_root_key: Option<Vec<u8>>) -> Self {
@@ -159,4 +223,81 @@ impl Element {
     ) -> Self {
         Element::SumTree(maybe_root_key, sum_value, flags)
     }
+
+    #[cfg(feature = "full")]
+    /// Set element to a count tree without flags
+    pub fn new_count_tree(maybe_root_key: Option<Vec<u8>>) -> Self {
+        Element::CountTree(maybe_root_key, 0, None)
+    }
+
+    #[cfg(feature = "full")]
+    /// Set element to a count tree with flags
+    pub fn new_count_tree_with_flags(
+        maybe_root_key: Option<Vec<u8>>,
+        flags: Option<ElementFlags>,
+    ) -> Self {
+        Element::CountTree(maybe_root_key, 0, flags)
+    }
+
+    #[cfg(feature = "full")]
+    /// Set element to a count tree with flags and count value
+    pub fn new_count_tree_with_flags_and_count_value(
+        maybe_root_key: Option<Vec<u8>>,
+        count_value: CountValue,
+        flags: Option<ElementFlags>,
+    ) -> Self {
+        Element::CountTree(maybe_root_key, count_value, flags)
+    }
+
+    #[cfg(feature = "full")]
+    /// Set element to a big sum tree without flags
+    pub fn new_big_sum_tree(maybe_root_key: Option<Vec<u8>>) -> Self {
+        Element::BigSumTree(maybe_root_key, 0, None)
+    }
+
+    #[cfg(feature = "full")]
+    /// Set element to a big sum tree with flags
+    pub fn new_big_sum_tree_with_flags(
+        maybe_root_key: Option<Vec<u8>>,
+        flags: Option<ElementFlags>,
+    ) -> Self {
+        Element::BigSumTree(maybe_root_key, 0, flags)
+    }
+
+    #[cfg(feature = "full")]
+    /// Set element to a big sum tree with flags and sum value
+    pub fn new_big_sum_tree_with_flags_and_sum_value(
+        maybe_root_key: Option<Vec<u8>>,
+        sum_value: BigSumValue,
+        flags: Option<ElementFlags>,
+    ) -> Self {
+        Element::BigSumTree(maybe_root_key, sum_value, flags)
+    }
+
+    #[cfg(feature = "full")]
+    /// Set element to an aggregate tree without flags
+    pub fn new_aggregate_tree(maybe_root_key: Option<Vec<u8>>, aggregate_id: AggregateId) -> Self {
+        Element::AggregateTree(maybe_root_key, aggregate_id, None, None)
+    }
+
+    #[cfg(feature = "full")]
+    /// Set element to an aggregate tree with flags
+    pub fn new_aggregate_tree_with_flags(
+        maybe_root_key: Option<Vec<u8>>,
+        aggregate_id: AggregateId,
+        flags: Option<ElementFlags>,
+    ) -> Self {
+        Element::AggregateTree(maybe_root_key, aggregate_id, None, flags)
+    }
+
+    #[cfg(feature = "full")]
+    /// Set element to an aggregate tree with flags and aggregate value
+    pub fn new_aggregate_tree_with_flags_and_aggregate_value(
+        maybe_root_key: Option<Vec<u8>>,
+        aggregate_id: AggregateId,
+        aggregate_value: Option<AggregateValue>,
+        flags: Option<ElementFlags>,
+    ) -> Self {
+        Element::AggregateTree(maybe_root_key, aggregate_id, aggregate_value, flags)
+    }
 }
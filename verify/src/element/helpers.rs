@@ -29,26 +29,28 @@
 //! Helpers
 //! Implements helper functions in Element
 
+use grovedb_merk::proofs::query::ElementKind;
 #[cfg(feature = "full")]
 use grovedb_merk::{
     tree::{kv::KV, Tree},
-    TreeFeatureType,
-    TreeFeatureType::{BasicMerk, SummedMerk},
+    CryptoHash, Merk, TreeFeatureType,
+    TreeFeatureType::{AggregatedMerk, BasicMerk, BigSummedMerk, CountedMerk, SummedMerk},
 };
 #[cfg(feature = "full")]
 use integer_encoding::VarInt;
 
-#[cfg(any(feature = "full", feature = "verify"))]
-use crate::{element::SUM_ITEM_COST_SIZE, Element, Error};
 #[cfg(feature = "full")]
 use crate::{
-    element::{SUM_TREE_COST_SIZE, TREE_COST_SIZE},
+    element::{
+        AGGREGATE_TREE_COST_SIZE, BIG_SUM_TREE_COST_SIZE, COUNT_TREE_COST_SIZE, SUM_ITEM_COST_SIZE,
+        SUM_TREE_COST_SIZE, TREE_COST_SIZE,
+    },
     reference_path::{path_from_reference_path_type, ReferencePathType},
     ElementFlags,
 };
+use crate::{Element, Error};
 
 impl Element {
-    #[cfg(any(feature = "full", feature = "verify"))]
     /// Decoded the integer value in the SumItem element type, returns 0 for
     /// everything else
     pub fn sum_value_or_default(&self) -> i64 {
@@ -58,7 +60,46 @@ impl Element {
         }
     }
 
-    #[cfg(any(feature = "full", feature = "verify"))]
+    /// Decoded the integer value in the SumItem, SumTree and BigSumTree element
+    /// types, returns 0 for everything else
+    pub fn big_sum_value_or_default(&self) -> i128 {
+        match self {
+            Element::SumItem(sum_value, _) | Element::SumTree(_, sum_value, _) => {
+                *sum_value as i128
+            }
+            Element::BigSumTree(_, sum_value, _) => *sum_value,
+            _ => 0,
+        }
+    }
+
+    /// Decoded the value the element contributes to the aggregate of an
+    /// aggregate tree, the value of a SumItem or the aggregate of an
+    /// AggregateTree, returns None for everything else
+    pub fn aggregate_value(&self) -> Option<i128> {
+        match self {
+            Element::SumItem(value, _) => Some(*value as i128),
+            Element::AggregateTree(_, _, aggregate_value, _) => *aggregate_value,
+            _ => None,
+        }
+    }
+
+    /// Gives the aggregate id of the AggregateTree element type
+    pub fn aggregate_id(&self) -> Option<u8> {
+        match self {
+            Element::AggregateTree(_, aggregate_id, ..) => Some(*aggregate_id),
+            _ => None,
+        }
+    }
+
+    /// Decoded the count value in the CountTree element type, returns 0 for
+    /// everything else
+    pub fn count_value_or_default(&self) -> u64 {
+        match self {
+            Element::CountTree(_, count_value, _) => *count_value,
+            _ => 0,
+        }
+    }
+
     /// Decoded the integer value in the SumItem element type, returns 0 for
     /// everything else
     pub fn as_sum_item_value(&self) -> Result<i64, Error> {
@@ -68,7 +109,6 @@ impl Element {
         }
     }
 
-    #[cfg(any(feature = "full", feature = "verify"))]
     /// Gives the item value in the Item element type
     pub fn as_item_bytes(&self) -> Result<&[u8], Error> {
         match self {
@@ -77,7 +117,6 @@ impl Element {
         }
     }
 
-    #[cfg(any(feature = "full", feature = "verify"))]
     /// Gives the item value in the Item element type
     pub fn into_item_bytes(self) -> Result<Vec<u8>, Error> {
         match self {
@@ -86,25 +125,75 @@ impl Element {
         }
     }
 
-    #[cfg(any(feature = "full", feature = "verify"))]
+    /// Get the kind of the element
+    pub fn kind(&self) -> ElementKind {
+        match self {
+            Element::Item(..) => ElementKind::Item,
+            Element::Reference(..) | Element::BidirectionalReference(..) => ElementKind::Reference,
+            Element::Tree(..) => ElementKind::Tree,
+            Element::SumItem(..) => ElementKind::SumItem,
+            Element::SumTree(..) => ElementKind::SumTree,
+            Element::CountTree(..) => ElementKind::CountTree,
+            Element::BigSumTree(..) => ElementKind::BigSumTree,
+            Element::AggregateTree(..) => ElementKind::AggregateTree,
+        }
+    }
+
     /// Check if the element is a sum tree
     pub fn is_sum_tree(&self) -> bool {
         matches!(self, Element::SumTree(..))
     }
 
-    #[cfg(any(feature = "full", feature = "verify"))]
+    /// Check if the element is a count tree
+    pub fn is_count_tree(&self) -> bool {
+        matches!(self, Element::CountTree(..))
+    }
+
+    /// Check if the element is a big sum tree
+    pub fn is_big_sum_tree(&self) -> bool {
+        matches!(self, Element::BigSumTree(..))
+    }
+
+    /// Check if the element is an aggregate tree
+    pub fn is_aggregate_tree(&self) -> bool {
+        matches!(self, Element::AggregateTree(..))
+    }
+
     /// Check if the element is a tree
     pub fn is_tree(&self) -> bool {
-        matches!(self, Element::SumTree(..) | Element::Tree(..))
+        matches!(
+            self,
+            Element::SumTree(..)
+                | Element::Tree(..)
+                | Element::CountTree(..)
+                | Element::BigSumTree(..)
+                | Element::AggregateTree(..)
+        )
+    }
+
+    /// Check if the Merk of the subtree this element points to uses sum
+    /// nodes, which is the case for sum trees, count trees, big sum trees and
+    /// aggregate trees, whose aggregates are kept in the sums of the links
+    pub fn uses_sum_nodes(&self) -> bool {
+        matches!(
+            self,
+            Element::SumTree(..)
+                | Element::CountTree(..)
+                | Element::BigSumTree(..)
+                | Element::AggregateTree(..)
+        )
+    }
+
+    /// Check if the element is a bidirectional reference
+    pub fn is_bidirectional_reference(&self) -> bool {
+        matches!(self, Element::BidirectionalReference(..))
     }
 
-    #[cfg(any(feature = "full", feature = "verify"))]
     /// Check if the element is an item
     pub fn is_item(&self) -> bool {
         matches!(self, Element::Item(..) | Element::SumItem(..))
     }
 
-    #[cfg(any(feature = "full", feature = "verify"))]
     /// Check if the element is a sum item
     pub fn is_sum_item(&self) -> bool {
         matches!(self, Element::SumItem(..))
@@ -113,9 +202,29 @@ impl Element {
     #[cfg(feature = "full")]
     /// Get the tree feature type
     pub fn get_feature_type(&self, parent_is_sum_tree: bool) -> Result<TreeFeatureType, Error> {
-        match parent_is_sum_tree {
-            true => Ok(SummedMerk(self.sum_value_or_default())),
-            false => Ok(BasicMerk),
+        match (parent_is_sum_tree, self) {
+            (true, Element::BigSumTree(_, sum_value, _)) => i64::try_from(*sum_value)
+                .map(SummedMerk)
+                .map_err(|_| Error::SumOverflow("big sum tree sum doesn't fit in a sum tree")),
+            (true, _) => Ok(SummedMerk(self.sum_value_or_default())),
+            (false, _) => Ok(BasicMerk),
+        }
+    }
+
+    #[cfg(feature = "full")]
+    /// Get the tree feature type of the element in the given Merk, elements of
+    /// a count tree each count as one, elements of a big sum tree are summed
+    /// with 128 bits and elements of an aggregate tree carry their aggregate
+    /// value
+    pub fn get_feature_type_in_merk<S>(&self, merk: &Merk<S>) -> Result<TreeFeatureType, Error> {
+        if let Some(aggregate_id) = merk.aggregate_id {
+            Ok(AggregatedMerk(aggregate_id, self.aggregate_value()))
+        } else if merk.is_count_tree {
+            Ok(CountedMerk(1))
+        } else if merk.is_big_sum_tree {
+            Ok(BigSummedMerk(self.big_sum_value_or_default()))
+        } else {
+            self.get_feature_type(merk.is_sum_tree)
         }
     }
 
@@ -126,8 +235,12 @@ impl Element {
             Element::Tree(_, flags)
             | Element::Item(_, flags)
             | Element::Reference(_, _, flags)
+            | Element::BidirectionalReference(_, _, flags)
             | Element::SumTree(.., flags)
-            | Element::SumItem(_, flags) => flags,
+            | Element::SumItem(_, flags)
+            | Element::CountTree(.., flags)
+            | Element::BigSumTree(.., flags)
+            | Element::AggregateTree(.., flags) => flags,
         }
     }
 
@@ -138,8 +251,12 @@ impl Element {
             Element::Tree(_, flags)
             | Element::Item(_, flags)
             | Element::Reference(_, _, flags)
+            | Element::BidirectionalReference(_, _, flags)
             | Element::SumTree(.., flags)
-            | Element::SumItem(_, flags) => flags,
+            | Element::SumItem(_, flags)
+            | Element::CountTree(.., flags)
+            | Element::BigSumTree(.., flags)
+            | Element::AggregateTree(.., flags) => flags,
         }
     }
 
@@ -150,8 +267,12 @@ impl Element {
             Element::Tree(_, flags)
             | Element::Item(_, flags)
             | Element::Reference(_, _, flags)
+            | Element::BidirectionalReference(_, _, flags)
             | Element::SumTree(.., flags)
-            | Element::SumItem(_, flags) => flags,
+            | Element::SumItem(_, flags)
+            | Element::CountTree(.., flags)
+            | Element::BigSumTree(.., flags)
+            | Element::AggregateTree(.., flags) => flags,
         }
     }
 
@@ -174,7 +295,8 @@ impl Element {
                     item.required_space() as u32
                 }
             }
-            Element::Reference(path_reference, _, element_flag) => {
+            Element::Reference(path_reference, _, element_flag)
+            | Element::BidirectionalReference(path_reference, _, element_flag) => {
                 let path_length = path_reference.serialized_size() as u32;
 
                 if let Some(flag) = element_flag {
@@ -190,13 +312,27 @@ impl Element {
                     32
                 }
             }
-            Element::SumTree(_, _, element_flag) => {
+            Element::SumTree(_, _, element_flag) | Element::CountTree(_, _, element_flag) => {
                 if let Some(flag) = element_flag {
                     flag.len() as u32 + 32 + 8
                 } else {
                     32 + 8
                 }
             }
+            Element::BigSumTree(_, _, element_flag) => {
+                if let Some(flag) = element_flag {
+                    flag.len() as u32 + 32 + 16
+                } else {
+                    32 + 16
+                }
+            }
+            Element::AggregateTree(.., element_flag) => {
+                if let Some(flag) = element_flag {
+                    flag.len() as u32 + 32 + 18
+                } else {
+                    32 + 18
+                }
+            }
         }
     }
 
@@ -208,7 +344,7 @@ impl Element {
 
     #[cfg(feature = "full")]
     /// Convert the reference to an absolute reference
-    pub(crate) fn convert_if_reference_to_absolute_reference(
+    pub fn convert_if_reference_to_absolute_reference(
         self,
         path: &[&[u8]],
         key: Option<&[u8]>,
@@ -218,7 +354,9 @@ impl Element {
         // to follow non absolute references, we need the path they are stored at
         // this information is lost during the aggregation phase.
         Ok(match &self {
-            Element::Reference(reference_path_type, ..) => match reference_path_type {
+            Element::Reference(reference_path_type, ..)
+            | Element::BidirectionalReference(reference_path_type, ..) => match reference_path_type
+            {
                 ReferencePathType::AbsolutePathReference(..) => self,
                 _ => {
                     // Element is a reference and is not absolute.
@@ -273,6 +411,45 @@ impl Element {
                     is_sum_node,
                 )
             }
+            Element::CountTree(_, _count_value, flags) => {
+                let flags_len = flags.map_or(0, |flags| {
+                    let flags_len = flags.len() as u32;
+                    flags_len + flags_len.required_space() as u32
+                });
+                let value_len = COUNT_TREE_COST_SIZE + flags_len;
+                let key_len = key.len() as u32;
+                KV::layered_value_byte_cost_size_for_key_and_value_lengths(
+                    key_len,
+                    value_len,
+                    is_sum_node,
+                )
+            }
+            Element::BigSumTree(_, _sum_value, flags) => {
+                let flags_len = flags.map_or(0, |flags| {
+                    let flags_len = flags.len() as u32;
+                    flags_len + flags_len.required_space() as u32
+                });
+                let value_len = BIG_SUM_TREE_COST_SIZE + flags_len;
+                let key_len = key.len() as u32;
+                KV::layered_value_byte_cost_size_for_key_and_value_lengths(
+                    key_len,
+                    value_len,
+                    is_sum_node,
+                )
+            }
+            Element::AggregateTree(.., flags) => {
+                let flags_len = flags.map_or(0, |flags| {
+                    let flags_len = flags.len() as u32;
+                    flags_len + flags_len.required_space() as u32
+                });
+                let value_len = AGGREGATE_TREE_COST_SIZE + flags_len;
+                let key_len = key.len() as u32;
+                KV::layered_value_byte_cost_size_for_key_and_value_lengths(
+                    key_len,
+                    value_len,
+                    is_sum_node,
+                )
+            }
             Element::SumItem(.., flags) => {
                 let flags_len = flags.map_or(0, |flags| {
                     let flags_len = flags.len() as u32;
@@ -301,6 +478,9 @@ impl Element {
         match self {
             Element::Tree(..) => Ok(TREE_COST_SIZE),
             Element::SumTree(..) => Ok(SUM_TREE_COST_SIZE),
+            Element::CountTree(..) => Ok(COUNT_TREE_COST_SIZE),
+            Element::BigSumTree(..) => Ok(BIG_SUM_TREE_COST_SIZE),
+            Element::AggregateTree(..) => Ok(AGGREGATE_TREE_COST_SIZE),
             Element::SumItem(..) => Ok(SUM_ITEM_COST_SIZE),
             _ => Err(Error::CorruptedCodeExecution(
                 "trying to get tree cost from non tree element",
@@ -316,3 +496,11 @@ pub fn raw_decode(bytes: &[u8]) -> Result<Element, Error> {
     let element: Element = Element::deserialize(tree.value_as_slice())?;
     Ok(element)
 }
+
+#[cfg(feature = "full")]
+/// Decode from bytes, along with the value hash of the merk node
+pub fn raw_decode_with_value_hash(bytes: &[u8]) -> Result<(Element, CryptoHash), Error> {
+    let tree = Tree::decode_raw(bytes, vec![]).map_err(|e| Error::CorruptedData(e.to_string()))?;
+    let element: Element = Element::deserialize(tree.value_as_slice())?;
+    Ok((element, *tree.value_hash()))
+}
@@ -0,0 +1,158 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Module for subtrees handling.
+//! Subtrees handling is isolated so basically this module is about adapting
+//! Merk API to GroveDB needs.
+//!
+//! Elements are decoded and hashed without the `full` feature, which adds
+//! their constructors and the costs of storing them. The operations reading
+//! and writing them in a Merk are kept in grovedb.
+
+#[cfg(feature = "full")]
+mod constructor;
+pub mod helpers;
+mod query;
+mod serialize;
+#[cfg(feature = "full")]
+use core::fmt;
+
+use grovedb_merk::estimated_costs::SUM_VALUE_EXTRA_COST;
+#[cfg(feature = "full")]
+use grovedb_merk::estimated_costs::{LAYER_COST_SIZE, SUM_LAYER_COST_SIZE};
+#[cfg(feature = "full")]
+use grovedb_visualize::visualize_to_vec;
+use serde::{Deserialize, Serialize};
+
+use crate::reference_path::ReferencePathType;
+
+/// Optional meta-data to be stored per element
+pub type ElementFlags = Vec<u8>;
+
+/// Optional single byte to represent the maximum number of reference hop to
+/// base element
+pub type MaxReferenceHop = Option<u8>;
+
+#[cfg(feature = "full")]
+/// The cost of a tree
+pub const TREE_COST_SIZE: u32 = LAYER_COST_SIZE; // 3
+/// The cost of a sum item
+///
+/// It is 11 because we have 9 bytes for the sum value
+/// 1 byte for the item type
+/// 1 byte for the flags option
+pub const SUM_ITEM_COST_SIZE: u32 = SUM_VALUE_EXTRA_COST + 2; // 11
+#[cfg(feature = "full")]
+/// The cost of a sum tree
+pub const SUM_TREE_COST_SIZE: u32 = SUM_LAYER_COST_SIZE; // 12
+#[cfg(feature = "full")]
+/// The cost of a count tree
+///
+/// The count is stored just like the sum of a sum tree
+pub const COUNT_TREE_COST_SIZE: u32 = SUM_LAYER_COST_SIZE; // 12
+#[cfg(feature = "full")]
+/// The cost of a big sum tree
+///
+/// It is 20 because we have 17 bytes for the 128 bit sum value on top of the
+/// layer cost
+pub const BIG_SUM_TREE_COST_SIZE: u32 = LAYER_COST_SIZE + 17; // 20
+#[cfg(feature = "full")]
+/// The cost of an aggregate tree
+///
+/// It is 22 because we have 1 byte for the aggregate id and 18 bytes for the
+/// optional 128 bit aggregate value on top of the layer cost
+pub const AGGREGATE_TREE_COST_SIZE: u32 = LAYER_COST_SIZE + 19; // 22
+
+#[cfg(feature = "full")]
+/// The largest flags an element may carry once they are updated during a
+/// batch
+pub const MAX_ELEMENT_FLAGS_SIZE: usize = u16::MAX as usize;
+
+/// int 64 sum value
+pub type SumValue = i64;
+
+/// uint 64 count value
+pub type CountValue = u64;
+
+/// int 128 sum value of big sum trees
+pub type BigSumValue = i128;
+
+/// Id of the tree aggregate maintained by an aggregate tree
+pub type AggregateId = u8;
+
+/// int 128 aggregate value of aggregate trees
+pub type AggregateValue = i128;
+
+/// Variants of GroveDB stored entities
+///
+/// ONLY APPEND TO THIS LIST!!! Because
+/// of how serialization works.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Element {
+    /// An ordinary value
+    Item(Vec<u8>, Option<ElementFlags>),
+    /// A reference to an object by its path
+    Reference(ReferencePathType, MaxReferenceHop, Option<ElementFlags>),
+    /// A subtree, contains the a prefixed key representing the root of the
+    /// subtree.
+    Tree(Option<Vec<u8>>, Option<ElementFlags>),
+    /// Signed integer value that can be totaled in a sum tree
+    SumItem(SumValue, Option<ElementFlags>),
+    /// Same as Element::Tree but underlying Merk sums value of it's summable
+    /// nodes
+    SumTree(Option<Vec<u8>>, SumValue, Option<ElementFlags>),
+    /// Same as Element::Tree but the number of elements directly under it is
+    /// maintained, the underlying Merk sums one for each of its nodes
+    CountTree(Option<Vec<u8>>, CountValue, Option<ElementFlags>),
+    /// Same as Element::SumTree but the sum is 128 bits wide, so totals that
+    /// would overflow an i64 can be maintained
+    BigSumTree(Option<Vec<u8>>, BigSumValue, Option<ElementFlags>),
+    /// Same as Element::Reference but a backlink to it is maintained next to
+    /// the element it points to, so its referrers can be looked up
+    BidirectionalReference(ReferencePathType, MaxReferenceHop, Option<ElementFlags>),
+    /// Same as Element::Tree but the underlying Merk maintains the tree
+    /// aggregate registered under the aggregate id on the GroveDB, over the
+    /// values of its sum items and aggregate trees. The aggregate is `None`
+    /// while no element has a value
+    AggregateTree(
+        Option<Vec<u8>>,
+        AggregateId,
+        Option<AggregateValue>,
+        Option<ElementFlags>,
+    ),
+}
+
+#[cfg(feature = "full")]
+impl fmt::Debug for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut v = Vec::new();
+        visualize_to_vec(&mut v, self);
+
+        f.write_str(&String::from_utf8_lossy(&v))
+    }
+}
@@ -0,0 +1,171 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Query
+//! Implements functions in Element for querying
+
+#[cfg(feature = "full")]
+use grovedb_costs::{
+    cost_return_on_error_no_add, CostContext, CostResult, CostsExt, OperationCost,
+};
+use grovedb_merk::proofs::{query::ElementKindAction, Query};
+#[cfg(feature = "full")]
+use grovedb_storage::RawIterator;
+
+#[cfg(feature = "full")]
+use crate::{element::helpers::raw_decode, query_result_type::KeyElementPair, Error};
+use crate::{query_result_type::Path, Element, SizedQuery};
+
+impl Element {
+    /// Takes a sized query and a key and returns subquery key and subquery as
+    /// tuple
+    pub fn subquery_paths_and_value_for_sized_query(
+        sized_query: &SizedQuery,
+        key: &[u8],
+    ) -> (Option<Path>, Option<Query>) {
+        if let Some(conditional_subquery_branches) =
+            &sized_query.query.conditional_subquery_branches
+        {
+            for (query_item, subquery_branch) in conditional_subquery_branches {
+                if query_item.contains(key) {
+                    let subquery_path = subquery_branch.subquery_path.clone();
+                    let subquery = subquery_branch
+                        .subquery
+                        .as_ref()
+                        .map(|query| *query.clone());
+                    return (subquery_path, subquery);
+                }
+            }
+        }
+        let subquery_path = sized_query
+            .query
+            .default_subquery_branch
+            .subquery_path
+            .clone();
+        let subquery = sized_query
+            .query
+            .default_subquery_branch
+            .subquery
+            .as_ref()
+            .map(|query| *query.clone());
+        (subquery_path, subquery)
+    }
+
+    /// Takes a sized query and the key and element it matched and returns
+    /// the subquery path and subquery to apply to the element, or `None` if
+    /// the query skips the element
+    pub fn subquery_paths_and_value_for_element(
+        sized_query: &SizedQuery,
+        key: &[u8],
+        element: &Element,
+    ) -> Option<(Option<Path>, Option<Query>)> {
+        match sized_query.query.element_kind_action(key, element.kind()) {
+            Some(ElementKindAction::Skip) => None,
+            Some(ElementKindAction::Subquery(subquery_branch)) => Some((
+                subquery_branch.subquery_path.clone(),
+                subquery_branch
+                    .subquery
+                    .as_ref()
+                    .map(|query| *query.clone()),
+            )),
+            None => Some(Self::subquery_paths_and_value_for_sized_query(
+                sized_query,
+                key,
+            )),
+        }
+    }
+
+    #[cfg(feature = "full")]
+    /// Iterator
+    pub fn iterator<I: RawIterator>(mut raw_iter: I) -> CostContext<ElementsIterator<I>> {
+        let mut cost = OperationCost::default();
+        raw_iter.seek_to_first().unwrap_add_cost(&mut cost);
+        ElementsIterator::new(raw_iter).wrap_with_cost(cost)
+    }
+
+    #[cfg(feature = "full")]
+    /// Iterator starting from the first key not lower than `key`
+    pub fn iterator_from<I: RawIterator>(
+        mut raw_iter: I,
+        key: &[u8],
+    ) -> CostContext<ElementsIterator<I>> {
+        let mut cost = OperationCost::default();
+        raw_iter.seek(key).unwrap_add_cost(&mut cost);
+        ElementsIterator::new(raw_iter).wrap_with_cost(cost)
+    }
+}
+
+#[cfg(feature = "full")]
+/// Iterator over the elements of a subtree, in key order
+pub struct ElementsIterator<I: RawIterator> {
+    raw_iter: I,
+}
+
+#[cfg(feature = "full")]
+impl<I: RawIterator> ElementsIterator<I> {
+    /// New iterator over the elements of `raw_iter`
+    pub fn new(raw_iter: I) -> Self {
+        ElementsIterator { raw_iter }
+    }
+
+    /// Decodes the element under the current key and moves to the next one
+    pub fn next_element(&mut self) -> CostResult<Option<KeyElementPair>, Error> {
+        let mut cost = OperationCost::default();
+
+        Ok(if self.raw_iter.valid().unwrap_add_cost(&mut cost) {
+            if let Some((key, value)) = self
+                .raw_iter
+                .key()
+                .unwrap_add_cost(&mut cost)
+                .zip(self.raw_iter.value().unwrap_add_cost(&mut cost))
+            {
+                let element = cost_return_on_error_no_add!(&cost, raw_decode(value));
+                let key_vec = key.to_vec();
+                self.raw_iter.next().unwrap_add_cost(&mut cost);
+                Some((key_vec, element))
+            } else {
+                None
+            }
+        } else {
+            None
+        })
+        .wrap_with_cost(cost)
+    }
+
+    /// Moves to `key`, or past the last key if it isn't found
+    pub fn fast_forward(&mut self, key: &[u8]) -> Result<(), Error> {
+        while self.raw_iter.valid().unwrap() {
+            if self.raw_iter.key().unwrap().unwrap() == key {
+                break;
+            } else {
+                self.raw_iter.next().unwrap();
+            }
+        }
+        Ok(())
+    }
+}
@@ -29,10 +29,8 @@
 //! Serialize
 //! Implements serialization functions in Element
 
-#[cfg(any(feature = "full", feature = "verify"))]
 use bincode::Options;
 
-#[cfg(any(feature = "full", feature = "verify"))]
 use crate::{Element, Error};
 
 impl Element {
@@ -56,7 +54,6 @@ impl Element {
             .unwrap() as usize // this should not be able to error
     }
 
-    #[cfg(any(feature = "full", feature = "verify"))]
     /// Deserializes given bytes and sets as self
     pub fn deserialize(bytes: &[u8]) -> Result<Self, Error> {
         bincode::DefaultOptions::default()
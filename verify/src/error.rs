@@ -29,7 +29,6 @@
 //! GroveDB Errors
 
 /// GroveDB Errors
-#[cfg(any(feature = "full", feature = "verify"))]
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     // Input data errors
@@ -42,12 +41,18 @@ pub enum Error {
     #[error("missing reference {0}")]
     /// Missing reference
     MissingReference(String),
+    #[error("deletion would leave {} dangling references", .0.len())]
+    /// Path and key of the references a deletion would leave dangling
+    DanglingReferences(Vec<(Vec<Vec<u8>>, Vec<u8>)>),
     #[error("internal error: {0}")]
     /// Internal error
     InternalError(&'static str),
     #[error("invalid proof: {0}")]
     /// Invalid proof
     InvalidProof(&'static str),
+    #[error("proof limit offset mismatch: {0}")]
+    /// Proof doesn't match the limit or offset of the verified query
+    ProofLimitOffsetMismatch(&'static str),
     #[error("invalid input: {0}")]
     /// Invalid input
     InvalidInput(&'static str),
@@ -109,6 +114,24 @@ pub enum Error {
     /// Storage error
     StorageError(#[from] grovedb_storage::error::Error),
 
+    #[cfg(feature = "full")]
+    #[error("unsupported schema version error: {0}")]
+    /// The database was written by a newer version of GroveDB
+    UnsupportedSchemaVersion(String),
+
+    #[cfg(feature = "full")]
+    #[error("storage corruption error: {details}")]
+    /// The database is corrupted and should be restored from a backup
+    StorageCorruption {
+        /// What was found to be corrupted
+        details: String,
+    },
+
+    #[cfg(feature = "full")]
+    #[error("snapshot io error: {0}")]
+    /// Reading or writing a snapshot failed
+    SnapshotIo(std::io::Error),
+
     #[error("data corruption error: {0}")]
     /// Corrupted data
     CorruptedData(String),
@@ -124,6 +147,10 @@ pub enum Error {
     /// Invalid batch operation
     InvalidBatchOperation(&'static str),
 
+    #[error("batch operation condition failed error: {0}")]
+    /// The condition of a conditional batch operation doesn't hold
+    BatchOperationConditionFailed(String),
+
     #[error("delete up tree stop height more than initial path size error: {0}")]
     /// Delete up tree stop height more than initial path size
     DeleteUpTreeStopHeightMoreThanInitialPathSize(String),
@@ -132,6 +159,14 @@ pub enum Error {
     /// Deleting non empty tree
     DeletingNonEmptyTree(&'static str),
 
+    #[error("clearing tree with subtrees not allowed error: {0}")]
+    /// Clearing a tree holding subtrees while options don't allow it
+    ClearingTreeWithSubtreesNotAllowed(&'static str),
+
+    #[error("invalid element flags error: {0}")]
+    /// Element flags are invalid after being updated
+    InvalidElementFlags(String),
+
     // Client allowed errors
     #[error("just in time element flags client error: {0}")]
     /// Just in time element flags client error
@@ -153,13 +188,44 @@ pub enum Error {
     /// Path not found in cache for estimated costs
     PathNotFoundInCacheForEstimatedCosts(String),
 
+    #[error("sum overflow: {0}")]
+    /// A sum tree total or a sum of sum items doesn't fit in an i64
+    SumOverflow(&'static str),
+
     // Support errors
     #[error("not supported: {0}")]
     /// Not supported
     NotSupported(&'static str),
 
+    // Cost errors
+    #[error("cost overflow of {0}")]
+    /// A cost counter overflowed while accumulating the cost of an operation
+    CostOverflow(&'static str),
+
     // Merk errors
     #[error("merk error: {0}")]
     /// Merk error
     MerkError(grovedb_merk::error::Error),
 }
+
+#[cfg(feature = "full")]
+impl From<grovedb_costs::error::Error> for Error {
+    fn from(error: grovedb_costs::error::Error) -> Self {
+        match error {
+            grovedb_costs::error::Error::CostOverflow(counter) => Error::CostOverflow(counter),
+            error => Error::CorruptedData(error.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "full")]
+impl Error {
+    /// Converts an error from applying changes to a merk or computing its
+    /// root, an overflowing sum of a sum tree becomes a `SumOverflow`
+    pub fn from_merk_apply_error(error: grovedb_merk::error::Error) -> Self {
+        match error {
+            grovedb_merk::error::Error::Overflow(message) => Error::SumOverflow(message),
+            error => Error::CorruptedData(error.to_string()),
+        }
+    }
+}
@@ -0,0 +1,67 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! GroveDB proof verification.
+//!
+//! Verifies GroveDB proofs, decodes elements and computes Merk hashes without
+//! depending on RocksDB or any storage code, for light clients which only
+//! check proofs against a known root hash. GroveDB depends on this crate and
+//! re-exports it.
+//!
+//! The crate compiles to `wasm32-unknown-unknown`, so proofs can be verified
+//! in browsers. The `full` feature adds what GroveDB needs on top, such as
+//! the element constructors and storage costs; the operations reading and
+//! writing elements in a Merk live in GroveDB itself.
+//!
+//! Verification requires the standard library: elements are decoded with
+//! `bincode` and Merk proofs with `ed`, both built on `std::io`.
+
+pub mod aggregate;
+pub mod backlinks;
+pub mod element;
+pub mod error;
+pub mod proof;
+mod query;
+pub mod query_result_type;
+pub mod reference_path;
+pub mod versioning;
+#[cfg(feature = "full")]
+mod visualize;
+
+pub use aggregate::Aggregate;
+pub use element::{Element, ElementFlags};
+pub use error::Error;
+pub use grovedb_merk::{
+    proofs::{query::query_item::QueryItem, Query},
+    tree::{combine_hash, kv_digest_to_kv_hash, kv_hash, node_hash, value_hash},
+    CryptoHash,
+};
+pub use proof::verify::*;
+pub use query::{PathQuery, QueryCursor, SizedQuery};
+pub use query_result_type::PathKeyOptionalElementTrio;
+pub use versioning::ProofVersion;
@@ -0,0 +1,355 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Compact encoding of proofs
+//!
+//! Version 2 proofs carry the same tokens as version 1 proofs, but their merk
+//! proofs are re-encoded op by op: a hash or key already written anywhere in
+//! the proof is replaced with its index among the hashes or keys written so
+//! far, and lengths are varint encoded. Only non verbose proofs can be
+//! compacted.
+//!
+//! A version 2 proof is not always smaller: the length of a value of 16384
+//! bytes or more takes three bytes instead of two, and referencing the 128th
+//! or a later key takes two bytes, more than writing an empty key again.
+
+#[cfg(feature = "full")]
+use std::collections::HashMap;
+use std::io::Read;
+
+#[cfg(feature = "full")]
+use grovedb_merk::proofs::Decoder;
+use grovedb_merk::{
+    ed::{Decode, Encode},
+    proofs::{Node, Op},
+    CryptoHash, TreeFeatureType,
+};
+use integer_encoding::{VarInt, VarIntReader};
+
+use crate::{proof::util::ProofTokenType, Error};
+
+// Tags of compactly encoded ops. The tag of a push holds the type of its node
+// in the lowest bits, and flags telling whether the push is inverted and
+// whether the key and hash of the node are references to earlier ones.
+const NODE_TYPE_MASK: u8 = 0x07;
+const PUSH_INVERTED: u8 = 0x08;
+const KEY_REFERENCE: u8 = 0x10;
+const HASH_REFERENCE: u8 = 0x20;
+const PARENT: u8 = 0x40;
+const CHILD: u8 = 0x41;
+const PARENT_INVERTED: u8 = 0x42;
+const CHILD_INVERTED: u8 = 0x43;
+
+/// Type of a node and the key, value, hash and feature type it holds
+#[cfg(feature = "full")]
+type NodeParts<'a> = (
+    u8,
+    Option<&'a [u8]>,
+    Option<&'a [u8]>,
+    Option<&'a CryptoHash>,
+    Option<&'a TreeFeatureType>,
+);
+
+#[cfg(feature = "full")]
+fn node_parts(node: &Node) -> NodeParts<'_> {
+    match node {
+        Node::Hash(hash) => (0, None, None, Some(hash), None),
+        Node::KVHash(kv_hash) => (1, None, None, Some(kv_hash), None),
+        Node::KVDigest(key, value_hash) => (2, Some(key.as_slice()), None, Some(value_hash), None),
+        Node::KV(key, value) => (3, Some(key.as_slice()), Some(value.as_slice()), None, None),
+        Node::KVValueHash(key, value, value_hash) => (
+            4,
+            Some(key.as_slice()),
+            Some(value.as_slice()),
+            Some(value_hash),
+            None,
+        ),
+        Node::KVValueHashFeatureType(key, value, value_hash, feature_type) => (
+            5,
+            Some(key.as_slice()),
+            Some(value.as_slice()),
+            Some(value_hash),
+            Some(feature_type),
+        ),
+        Node::KVRefValueHash(key, value, value_hash) => (
+            6,
+            Some(key.as_slice()),
+            Some(value.as_slice()),
+            Some(value_hash),
+            None,
+        ),
+    }
+}
+
+fn read_byte(input: &mut &[u8]) -> Result<u8, Error> {
+    let mut byte = [0; 1];
+    input
+        .read_exact(&mut byte)
+        .map_err(|_| Error::InvalidProof("unexpected end of proof"))?;
+    Ok(byte[0])
+}
+
+fn read_length(input: &mut &[u8]) -> Result<usize, Error> {
+    input
+        .read_varint()
+        .map_err(|_| Error::InvalidProof("expected length data"))
+}
+
+fn read_slice<'a>(input: &mut &'a [u8]) -> Result<&'a [u8], Error> {
+    let length = read_length(input)?;
+    if input.len() < length {
+        return Err(Error::InvalidProof("proof is shorter than its length data"));
+    }
+    let (slice, rest) = input.split_at(length);
+    *input = rest;
+    Ok(slice)
+}
+
+#[cfg(feature = "full")]
+/// Writes compactly encoded merk proofs, remembering the hashes and keys
+/// written so far
+#[derive(Default)]
+struct CompactEncoder {
+    hashes: HashMap<CryptoHash, usize>,
+    keys: HashMap<Vec<u8>, usize>,
+    output: Vec<u8>,
+}
+
+#[cfg(feature = "full")]
+impl CompactEncoder {
+    fn write_length(&mut self, length: usize) {
+        self.output.extend(length.encode_var_vec());
+    }
+
+    fn write_slice(&mut self, slice: &[u8]) {
+        self.write_length(slice.len());
+        self.output.extend_from_slice(slice);
+    }
+
+    fn write_push(&mut self, mut tag: u8, node: &Node) -> Result<(), Error> {
+        let (node_type, key, value, hash, feature_type) = node_parts(node);
+        let key_index = key.and_then(|key| self.keys.get(key).copied());
+        let hash_index = hash.and_then(|hash| self.hashes.get(hash).copied());
+        tag |= node_type;
+        if key_index.is_some() {
+            tag |= KEY_REFERENCE;
+        }
+        if hash_index.is_some() {
+            tag |= HASH_REFERENCE;
+        }
+        self.output.push(tag);
+
+        if let Some(key) = key {
+            if let Some(index) = key_index {
+                self.write_length(index);
+            } else {
+                self.keys.insert(key.to_vec(), self.keys.len());
+                self.write_slice(key);
+            }
+        }
+        if let Some(value) = value {
+            self.write_slice(value);
+        }
+        if let Some(hash) = hash {
+            if let Some(index) = hash_index {
+                self.write_length(index);
+            } else {
+                self.hashes.insert(*hash, self.hashes.len());
+                self.output.extend_from_slice(hash);
+            }
+        }
+        if let Some(feature_type) = feature_type {
+            feature_type
+                .encode_into(&mut self.output)
+                .map_err(|_| Error::InternalError("failed to encode feature type"))?;
+        }
+        Ok(())
+    }
+
+    fn write_merk_proof(&mut self, merk_proof: &[u8]) -> Result<(), Error> {
+        let ops = Decoder::new(merk_proof)
+            .collect::<Result<Vec<Op>, _>>()
+            .map_err(|_| Error::InvalidProof("proof contains an invalid op"))?;
+        self.write_length(ops.len());
+        for op in ops.iter() {
+            match op {
+                Op::Push(node) => self.write_push(0, node)?,
+                Op::PushInverted(node) => self.write_push(PUSH_INVERTED, node)?,
+                Op::Parent => self.output.push(PARENT),
+                Op::Child => self.output.push(CHILD),
+                Op::ParentInverted => self.output.push(PARENT_INVERTED),
+                Op::ChildInverted => self.output.push(CHILD_INVERTED),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads compactly encoded merk proofs back, remembering the hashes and keys
+/// read so far
+#[derive(Default)]
+struct CompactDecoder {
+    hashes: Vec<CryptoHash>,
+    keys: Vec<Vec<u8>>,
+}
+
+impl CompactDecoder {
+    fn read_key(&mut self, tag: u8, input: &mut &[u8]) -> Result<Vec<u8>, Error> {
+        if tag & KEY_REFERENCE != 0 {
+            self.keys
+                .get(read_length(input)?)
+                .cloned()
+                .ok_or(Error::InvalidProof("reference to an unknown key"))
+        } else {
+            let key = read_slice(input)?.to_vec();
+            self.keys.push(key.clone());
+            Ok(key)
+        }
+    }
+
+    fn read_hash(&mut self, tag: u8, input: &mut &[u8]) -> Result<CryptoHash, Error> {
+        if tag & HASH_REFERENCE != 0 {
+            self.hashes
+                .get(read_length(input)?)
+                .copied()
+                .ok_or(Error::InvalidProof("reference to an unknown hash"))
+        } else {
+            let mut hash = CryptoHash::default();
+            input
+                .read_exact(&mut hash)
+                .map_err(|_| Error::InvalidProof("unexpected end of proof"))?;
+            self.hashes.push(hash);
+            Ok(hash)
+        }
+    }
+
+    fn read_node(&mut self, tag: u8, input: &mut &[u8]) -> Result<Node, Error> {
+        // fields are read in the order `write_push` writes them
+        Ok(match tag & NODE_TYPE_MASK {
+            0 => Node::Hash(self.read_hash(tag, input)?),
+            1 => Node::KVHash(self.read_hash(tag, input)?),
+            2 => Node::KVDigest(self.read_key(tag, input)?, self.read_hash(tag, input)?),
+            3 => Node::KV(self.read_key(tag, input)?, read_slice(input)?.to_vec()),
+            4 => Node::KVValueHash(
+                self.read_key(tag, input)?,
+                read_slice(input)?.to_vec(),
+                self.read_hash(tag, input)?,
+            ),
+            5 => Node::KVValueHashFeatureType(
+                self.read_key(tag, input)?,
+                read_slice(input)?.to_vec(),
+                self.read_hash(tag, input)?,
+                TreeFeatureType::decode(&mut *input)
+                    .map_err(|_| Error::InvalidProof("invalid feature type"))?,
+            ),
+            6 => Node::KVRefValueHash(
+                self.read_key(tag, input)?,
+                read_slice(input)?.to_vec(),
+                self.read_hash(tag, input)?,
+            ),
+            _ => return Err(Error::InvalidProof("unknown node type in compact proof")),
+        })
+    }
+
+    /// Reads a compactly encoded merk proof and encodes it back as merk does
+    fn read_merk_proof(&mut self, input: &mut &[u8]) -> Result<Vec<u8>, Error> {
+        let op_count = read_length(input)?;
+        let mut merk_proof = vec![];
+        for _ in 0..op_count {
+            let op = match read_byte(input)? {
+                PARENT => Op::Parent,
+                CHILD => Op::Child,
+                PARENT_INVERTED => Op::ParentInverted,
+                CHILD_INVERTED => Op::ChildInverted,
+                tag if tag >= PARENT => {
+                    return Err(Error::InvalidProof("unknown op in compact proof"))
+                }
+                tag if tag & PUSH_INVERTED != 0 => Op::PushInverted(self.read_node(tag, input)?),
+                tag => Op::Push(self.read_node(tag, input)?),
+            };
+            op.encode_into(&mut merk_proof)
+                .map_err(|_| Error::InvalidProof("compact proof op can't be encoded"))?;
+        }
+        Ok(merk_proof)
+    }
+}
+
+#[cfg(feature = "full")]
+/// Compactly encodes the merk proofs of a non verbose proof.
+/// `proof` holds the tokens of the proof, without the version.
+pub fn compact_merk_proofs(mut proof: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut encoder = CompactEncoder::default();
+    while !proof.is_empty() {
+        let token = read_byte(&mut proof)?;
+        encoder.output.push(token);
+        match ProofTokenType::from(token) {
+            ProofTokenType::Merk | ProofTokenType::SizedMerk => {
+                let merk_proof = read_slice(&mut proof)?;
+                encoder.write_merk_proof(merk_proof)?;
+            }
+            ProofTokenType::MerkRef => {
+                let index = read_length(&mut proof)?;
+                encoder.write_length(index);
+            }
+            ProofTokenType::EmptyTree | ProofTokenType::AbsentPath => {}
+            ProofTokenType::PathInfo | ProofTokenType::Invalid => {
+                return Err(Error::InvalidProof(
+                    "only non verbose proofs can be compactly encoded",
+                ))
+            }
+        }
+    }
+    Ok(encoder.output)
+}
+
+/// Decodes the tokens of a compactly encoded proof, without the version, into
+/// the tokens of the same proof in version 1
+pub fn expand_merk_proofs(mut proof: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut decoder = CompactDecoder::default();
+    let mut expanded = Vec::with_capacity(proof.len() * 2);
+    while !proof.is_empty() {
+        let token = read_byte(&mut proof)?;
+        expanded.push(token);
+        match ProofTokenType::from(token) {
+            ProofTokenType::Merk | ProofTokenType::SizedMerk => {
+                let merk_proof = decoder.read_merk_proof(&mut proof)?;
+                expanded.extend(merk_proof.len().encode_var_vec());
+                expanded.extend(merk_proof);
+            }
+            ProofTokenType::MerkRef => {
+                let index = read_length(&mut proof)?;
+                expanded.extend(index.encode_var_vec());
+            }
+            ProofTokenType::EmptyTree | ProofTokenType::AbsentPath => {}
+            ProofTokenType::PathInfo | ProofTokenType::Invalid => {
+                return Err(Error::InvalidProof("unexpected token in compact proof"))
+            }
+        }
+    }
+    Ok(expanded)
+}
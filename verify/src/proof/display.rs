@@ -0,0 +1,293 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Text encodings of root hashes and proofs, and proof summaries
+
+use std::fmt;
+
+use grovedb_merk::proofs::{Decoder, Node, Op};
+
+use crate::{
+    proof::{
+        compact::expand_merk_proofs,
+        util::{ProofReader, ProofTokenType},
+    },
+    versioning::{read_and_consume_known_proof_version, COMPACT_PROOF_VERSION},
+    Error,
+};
+
+/// Bitcoin base58 alphabet
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Text encoding of root hashes and proofs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    /// Lowercase hexadecimal
+    Hex,
+    /// Base58 with the bitcoin alphabet
+    Base58,
+}
+
+impl TextEncoding {
+    /// Encodes bytes as text
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            TextEncoding::Hex => hex::encode(bytes),
+            TextEncoding::Base58 => encode_base58(bytes),
+        }
+    }
+
+    /// Decodes text into bytes
+    pub fn decode(self, text: &str) -> Result<Vec<u8>, Error> {
+        match self {
+            TextEncoding::Hex => {
+                hex::decode(text.trim()).map_err(|_| Error::InvalidInput("invalid hex string"))
+            }
+            TextEncoding::Base58 => decode_base58(text.trim()),
+        }
+    }
+}
+
+/// Displays a root hash in the given encoding
+pub fn display_root_hash(root_hash: &[u8; 32], encoding: TextEncoding) -> String {
+    encoding.encode(root_hash)
+}
+
+/// Parses a root hash displayed with `display_root_hash`
+pub fn parse_root_hash(text: &str, encoding: TextEncoding) -> Result<[u8; 32], Error> {
+    encoding
+        .decode(text)?
+        .try_into()
+        .map_err(|_| Error::InvalidInput("root hash must be 32 bytes"))
+}
+
+/// Displays an encoded proof in the given encoding
+pub fn display_proof(proof: &[u8], encoding: TextEncoding) -> String {
+    encoding.encode(proof)
+}
+
+/// Parses a proof displayed with `display_proof`, checking it starts with a
+/// known proof version
+pub fn parse_proof(text: &str, encoding: TextEncoding) -> Result<Vec<u8>, Error> {
+    let proof = encoding.decode(text)?;
+    read_and_consume_known_proof_version(&proof)?;
+    Ok(proof)
+}
+
+fn encode_base58(bytes: &[u8]) -> String {
+    let leading_zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+    // base 58 digits, least significant first
+    let mut digits: Vec<u8> = Vec::with_capacity(bytes.len() * 138 / 100 + 1);
+    for byte in &bytes[leading_zeros..] {
+        let mut carry = *byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let mut text = String::with_capacity(leading_zeros + digits.len());
+    text.push_str(&"1".repeat(leading_zeros));
+    text.extend(
+        digits
+            .iter()
+            .rev()
+            .map(|digit| BASE58_ALPHABET[*digit as usize] as char),
+    );
+    text
+}
+
+fn decode_base58(text: &str) -> Result<Vec<u8>, Error> {
+    let leading_zeros = text.bytes().take_while(|c| *c == b'1').count();
+    // bytes, least significant first
+    let mut bytes: Vec<u8> = Vec::with_capacity(text.len() * 733 / 1000 + 1);
+    for c in text.bytes().skip(leading_zeros) {
+        let mut carry = BASE58_ALPHABET
+            .iter()
+            .position(|a| *a == c)
+            .ok_or(Error::InvalidInput("invalid base58 character"))? as u32;
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    bytes.resize(bytes.len() + leading_zeros, 0);
+    bytes.reverse();
+    Ok(bytes)
+}
+
+/// Summary of a single merk proof of a proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofLayerSummary {
+    /// Kind of the merk proof, the kind of the proof it repeats for a
+    /// `MerkRef`
+    pub proof_token_type: ProofTokenType,
+    /// Whether the layer is encoded as a `MerkRef` to an earlier merk proof,
+    /// its lengths and counts being those of that proof
+    pub is_merk_ref: bool,
+    /// Length of the merk proof
+    pub byte_length: usize,
+    /// Number of proof operations
+    pub op_count: usize,
+    /// Number of nodes carrying a value
+    pub value_count: usize,
+}
+
+/// Compact summary of a proof's layers and operations, for logs and debugging
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofSummary {
+    /// Proof version
+    pub version: u32,
+    /// Length of the whole proof
+    pub byte_length: usize,
+    /// Whether the proof is verbose
+    pub is_verbose: bool,
+    /// Layers in the order they appear in the proof
+    pub layers: Vec<ProofLayerSummary>,
+}
+
+impl ProofSummary {
+    /// Summarizes an encoded proof
+    pub fn from_proof(proof: &[u8]) -> Result<Self, Error> {
+        let (version, mut tokens) = read_and_consume_known_proof_version(proof)?;
+        // layers of compactly encoded proofs are summarized as version 1 layers
+        let expanded_tokens;
+        if version == COMPACT_PROOF_VERSION {
+            expanded_tokens = expand_merk_proofs(tokens)?;
+            tokens = expanded_tokens.as_slice();
+        }
+        let is_verbose = tokens.first() == Some(&ProofTokenType::PathInfo.into());
+        let mut reader = ProofReader::new_with_verbose_status(tokens, is_verbose);
+        if is_verbose {
+            reader.read_path_info()?;
+        }
+
+        let mut layers = vec![];
+        while !reader.is_empty() {
+            let is_merk_ref = reader.peek_proof_token_type() == Some(ProofTokenType::MerkRef);
+            // empty tree and absent path tokens carry no key even in verbose proofs
+            let (proof_token_type, merk_proof, _) =
+                reader.read_proof_internal_with_optional_type(None, is_verbose)?;
+            let mut op_count = 0;
+            let mut value_count = 0;
+            for op in Decoder::new(&merk_proof) {
+                let op = op.map_err(|_| Error::InvalidProof("proof contains an invalid op"))?;
+                op_count += 1;
+                if let Op::Push(node) | Op::PushInverted(node) = op {
+                    if matches!(
+                        node,
+                        Node::KV(..)
+                            | Node::KVValueHash(..)
+                            | Node::KVValueHashFeatureType(..)
+                            | Node::KVRefValueHash(..)
+                    ) {
+                        value_count += 1;
+                    }
+                }
+            }
+            layers.push(ProofLayerSummary {
+                proof_token_type,
+                is_merk_ref,
+                byte_length: merk_proof.len(),
+                op_count,
+                value_count,
+            });
+        }
+
+        Ok(ProofSummary {
+            version,
+            byte_length: proof.len(),
+            is_verbose,
+            layers,
+        })
+    }
+}
+
+impl fmt::Display for ProofSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "proof v{}{}, {} bytes, {} layers:",
+            self.version,
+            if self.is_verbose { " verbose" } else { "" },
+            self.byte_length,
+            self.layers.len()
+        )?;
+        for layer in &self.layers {
+            match layer.proof_token_type {
+                ProofTokenType::EmptyTree => write!(f, " empty_tree")?,
+                ProofTokenType::AbsentPath => write!(f, " absent_path")?,
+                proof_token_type => write!(
+                    f,
+                    " {}{}({} bytes, {} ops, {} values)",
+                    if layer.is_merk_ref { "ref:" } else { "" },
+                    if proof_token_type == ProofTokenType::SizedMerk {
+                        "sized_merk"
+                    } else {
+                        "merk"
+                    },
+                    layer.byte_length,
+                    layer.op_count,
+                    layer.value_count
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base58_round_trip() {
+        // known vectors
+        assert_eq!(encode_base58(b""), "");
+        assert_eq!(encode_base58(&[0, 0, 1]), "112");
+        assert_eq!(encode_base58(b"hello world"), "StV1DL6CwTryKyV");
+        assert_eq!(decode_base58("StV1DL6CwTryKyV").unwrap(), b"hello world");
+        assert!(decode_base58("0OIl").is_err());
+
+        let hash = [7; 32];
+        for encoding in [TextEncoding::Hex, TextEncoding::Base58] {
+            let text = display_root_hash(&hash, encoding);
+            assert_eq!(parse_root_hash(&text, encoding).unwrap(), hash);
+        }
+        assert!(parse_root_hash("0011", TextEncoding::Hex).is_err());
+    }
+}
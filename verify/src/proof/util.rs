@@ -26,7 +26,8 @@
 // IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-#[cfg(any(feature = "full", feature = "verify"))]
+#[cfg(feature = "full")]
+use std::collections::{hash_map::Entry, HashMap};
 use std::io::Read;
 #[cfg(feature = "full")]
 use std::io::Write;
@@ -35,20 +36,18 @@ use grovedb_merk::{
     proofs::query::{Key, Path, ProvedKeyValue},
     CryptoHash,
 };
-#[cfg(any(feature = "full", feature = "verify"))]
-use integer_encoding::{VarInt, VarIntReader};
+#[cfg(feature = "full")]
+use integer_encoding::VarInt;
+use integer_encoding::VarIntReader;
 
-use crate::operations::proof::verify::ProvedKeyValues;
-#[cfg(any(feature = "full", feature = "verify"))]
+use crate::proof::verify::ProvedKeyValues;
 use crate::Error;
 
-#[cfg(any(feature = "full", feature = "verify"))]
 pub const EMPTY_TREE_HASH: [u8; 32] = [0; 32];
 
 pub type ProofTokenInfo = (ProofTokenType, Vec<u8>, Option<Vec<u8>>);
 
-#[cfg(any(feature = "full", feature = "verify"))]
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Proof type
 // TODO: there might be a better name for this
 pub enum ProofTokenType {
@@ -57,10 +56,12 @@ pub enum ProofTokenType {
     EmptyTree,
     AbsentPath,
     PathInfo,
+    /// Stands for the merk proof at the given index among the merk proofs
+    /// read so far, repeated layer proofs are encoded this way
+    MerkRef,
     Invalid,
 }
 
-#[cfg(any(feature = "full", feature = "verify"))]
 impl From<ProofTokenType> for u8 {
     fn from(proof_token_type: ProofTokenType) -> Self {
         match proof_token_type {
@@ -69,12 +70,12 @@ impl From<ProofTokenType> for u8 {
             ProofTokenType::EmptyTree => 0x04,
             ProofTokenType::AbsentPath => 0x05,
             ProofTokenType::PathInfo => 0x06,
+            ProofTokenType::MerkRef => 0x07,
             ProofTokenType::Invalid => 0x10,
         }
     }
 }
 
-#[cfg(any(feature = "full", feature = "verify"))]
 impl From<u8> for ProofTokenType {
     fn from(val: u8) -> Self {
         match val {
@@ -83,27 +84,29 @@ impl From<u8> for ProofTokenType {
             0x04 => ProofTokenType::EmptyTree,
             0x05 => ProofTokenType::AbsentPath,
             0x06 => ProofTokenType::PathInfo,
+            0x07 => ProofTokenType::MerkRef,
             _ => ProofTokenType::Invalid,
         }
     }
 }
 
-#[cfg(any(feature = "full", feature = "verify"))]
 #[derive(Debug)]
 // TODO: possibility for a proof writer??
 /// Proof reader
 pub struct ProofReader<'a> {
     proof_data: &'a [u8],
     is_verbose: bool,
+    /// Merk proofs read so far, `MerkRef` tokens point into them
+    merk_proofs: Vec<(ProofTokenType, &'a [u8])>,
 }
 
-#[cfg(any(feature = "full", feature = "verify"))]
 impl<'a> ProofReader<'a> {
     /// New proof reader
     pub fn new(proof_data: &'a [u8]) -> Self {
         Self {
             proof_data,
             is_verbose: false,
+            merk_proofs: vec![],
         }
     }
 
@@ -112,9 +115,20 @@ impl<'a> ProofReader<'a> {
         Self {
             proof_data,
             is_verbose,
+            merk_proofs: vec![],
         }
     }
 
+    /// Whether all the proof data was read
+    pub fn is_empty(&self) -> bool {
+        self.proof_data.is_empty()
+    }
+
+    /// Type of the next proof token as encoded, without reading it
+    pub fn peek_proof_token_type(&self) -> Option<ProofTokenType> {
+        self.proof_data.first().map(|data_type| (*data_type).into())
+    }
+
     /// For non verbose proof read the immediate next proof, for verbose proof
     /// read the first proof that matches a given key
     pub fn read_next_proof(&mut self, key: &[u8]) -> Result<(ProofTokenType, Vec<u8>), Error> {
@@ -206,13 +220,34 @@ impl<'a> ProofReader<'a> {
         let mut data_type = [0; 1];
         self.read_into_slice(&mut data_type)?;
 
+        let mut proof_token_type: ProofTokenType = data_type[0].into();
+
+        let referenced_proof = if proof_token_type == ProofTokenType::MerkRef {
+            if is_verbose {
+                return Err(Error::InvalidProof(
+                    "merk proof references are not supported in verbose proofs",
+                ));
+            }
+            let index = self.read_length_data()?;
+            let (referenced_token_type, proof) = self
+                .merk_proofs
+                .get(index)
+                .ok_or(Error::InvalidProof("reference to an unknown merk proof"))?;
+            proof_token_type = *referenced_token_type;
+            Some(proof.to_vec())
+        } else {
+            None
+        };
+
         if let Some(expected_data_type) = expected_data_type_option {
-            if data_type != [expected_data_type] {
+            if u8::from(proof_token_type) != expected_data_type {
                 return Err(Error::InvalidProof("wrong data_type"));
             }
         }
 
-        let proof_token_type: ProofTokenType = data_type[0].into();
+        if let Some(proof) = referenced_proof {
+            return Ok((proof_token_type, proof, None));
+        }
 
         if proof_token_type == ProofTokenType::EmptyTree
             || proof_token_type == ProofTokenType::AbsentPath
@@ -237,10 +272,15 @@ impl<'a> ProofReader<'a> {
 
             let proof_length = self.read_length_data()?;
 
-            let mut proof = vec![0; proof_length];
-            self.read_into_slice(&mut proof)?;
+            let proof_data: &'a [u8] = self.proof_data;
+            if proof_data.len() < proof_length {
+                return Err(Error::InvalidProof("proof is shorter than its length data"));
+            }
+            let (proof, rest) = proof_data.split_at(proof_length);
+            self.proof_data = rest;
+            self.merk_proofs.push((proof_token_type, proof));
 
-            (proof, key)
+            (proof.to_vec(), key)
         } else {
             return Err(Error::InvalidProof("expected merk or sized merk proof"));
         };
@@ -287,6 +327,39 @@ pub fn write_slice_to_vec<W: Write>(dest: &mut W, value: &[u8]) -> Result<(), Er
     Ok(())
 }
 
+#[cfg(feature = "full")]
+/// Replace every merk proof repeating an earlier one with a `MerkRef` to it.
+/// `proof` holds the tokens of a non verbose proof, without the version.
+pub fn deduplicate_merk_proofs(proof: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut reader = ProofReader::new(proof);
+    let mut deduplicated = Vec::with_capacity(proof.len());
+    let mut indexes: HashMap<(u8, Vec<u8>), usize> = HashMap::new();
+
+    while !reader.is_empty() {
+        let (proof_token_type, merk_proof, _) = reader.read_proof()?;
+        let token: u8 = proof_token_type.into();
+        match proof_token_type {
+            ProofTokenType::Merk | ProofTokenType::SizedMerk => {
+                let next_index = indexes.len();
+                match indexes.entry((token, merk_proof)) {
+                    Entry::Occupied(entry) => {
+                        write_to_vec(&mut deduplicated, &[ProofTokenType::MerkRef.into()])?;
+                        write_to_vec(&mut deduplicated, &entry.get().encode_var_vec())?;
+                    }
+                    Entry::Vacant(entry) => {
+                        write_to_vec(&mut deduplicated, &[token])?;
+                        write_slice_to_vec(&mut deduplicated, &entry.key().1)?;
+                        entry.insert(next_index);
+                    }
+                }
+            }
+            _ => write_to_vec(&mut deduplicated, &[token])?,
+        }
+    }
+
+    Ok(deduplicated)
+}
+
 #[cfg(feature = "full")]
 /// Write a slice of a slice to a flat vector:w
 pub fn write_slice_of_slice_to_slice<W: Write>(dest: &mut W, value: &[&[u8]]) -> Result<(), Error> {
@@ -298,7 +371,6 @@ pub fn write_slice_of_slice_to_slice<W: Write>(dest: &mut W, value: &[&[u8]]) ->
     Ok(())
 }
 
-#[cfg(any(feature = "full", feature = "verify"))]
 pub fn reduce_limit_and_offset_by(
     limit: &mut Option<u16>,
     offset: &mut Option<u16>,
@@ -337,7 +409,6 @@ pub fn reduce_limit_and_offset_by(
 pub type ProvedPathKeyValues = Vec<ProvedPathKeyValue>;
 
 /// Proved path-key-value
-#[cfg(any(feature = "full", feature = "verify"))]
 #[derive(Debug, PartialEq, Eq)]
 pub struct ProvedPathKeyValue {
     /// Path
@@ -377,7 +448,7 @@ impl ProvedPathKeyValue {
 mod tests {
     use grovedb_merk::proofs::query::ProvedKeyValue;
 
-    use crate::operations::proof::util::{ProofTokenType, ProvedPathKeyValue};
+    use crate::proof::util::{ProofTokenType, ProvedPathKeyValue};
 
     #[test]
     fn test_proof_token_type_encoding() {
@@ -0,0 +1,1454 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Verify proof operations
+
+use std::{borrow::Cow, collections::BTreeMap};
+
+use bincode::Options;
+use grovedb_merk::proofs::query::Key;
+use grovedb_merk::proofs::query::PathKey;
+pub use grovedb_merk::proofs::query::{Path, ProvedKeyValue};
+use grovedb_merk::{
+    proofs::{
+        query::{execute_keys_only_proof, ElementKind, ElementKindAction, QueryItem},
+        Query,
+    },
+    tree::{combine_hash, value_hash as value_hash_fn},
+    CryptoHash,
+};
+
+use crate::{
+    aggregate::{matches_whole_subtree, total_of_sum_items, Aggregate},
+    backlinks::{backlinks_serialization_options, BacklinksProof},
+    element::SumValue,
+    proof::{
+        compact::expand_merk_proofs,
+        util::{
+            reduce_limit_and_offset_by, ProofReader, ProofTokenType, ProofTokenType::AbsentPath,
+            ProvedPathKeyValue, ProvedPathKeyValues, EMPTY_TREE_HASH,
+        },
+    },
+    query_result_type::{
+        PathKeyOptionalElementTrio, QueryResultElement, QueryResultElements, QueryResultType,
+    },
+    reference_path::path_from_reference_path_type,
+    versioning::{read_and_consume_known_proof_version, COMPACT_PROOF_VERSION},
+    Element, Error, PathQuery, SizedQuery,
+};
+
+/// Key values proven by a merk proof
+pub type ProvedKeyValues = Vec<ProvedKeyValue>;
+
+type EncounteredAbsence = bool;
+
+/// Sums of the sum trees a proof goes through, by path
+pub type SubtreeSums = BTreeMap<Path, SumValue>;
+
+/// Limit and offset of a path query left after verifying its proof, each is
+/// `None` if the path query had none. Subtracting them from the limit and
+/// offset of the path query gives the number of results returned and skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RemainingLimitOffset {
+    /// Remaining limit
+    pub limit: Option<u16>,
+    /// Remaining offset
+    pub offset: Option<u16>,
+}
+
+/// Verify proof given a path query
+/// Returns the root hash + deserialized elements
+pub fn verify_query(
+    proof: &[u8],
+    query: &PathQuery,
+) -> Result<([u8; 32], Vec<PathKeyOptionalElementTrio>), Error> {
+    let (root_hash, proved_path_key_values) = verify_query_raw(proof, query)?;
+    let path_key_optional_elements = proved_path_key_values
+        .into_iter()
+        .map(|pkv| pkv.try_into())
+        .collect::<Result<Vec<PathKeyOptionalElementTrio>, Error>>()?;
+    Ok((root_hash, path_key_optional_elements))
+}
+
+/// Verify a proof generated by `prove_sum_of_subtree`
+/// Returns the root hash + the aggregated sum of the sum tree at the path
+pub fn verify_sum_of_subtree(proof: &[u8], path: Vec<Vec<u8>>) -> Result<([u8; 32], i64), Error> {
+    let query = PathQuery::for_subtree_element(path)?;
+    let (root_hash, result_set) = verify_query(proof, &query)?;
+    match result_set.into_iter().next() {
+        Some((_, _, Some(Element::SumTree(_, sum, _)))) => Ok((root_hash, sum)),
+        Some((_, _, Some(_))) => Err(Error::WrongElementType(
+            "the subtree at the given path is not a sum tree",
+        )),
+        _ => Err(Error::InvalidProof("proof doesn't contain the sum tree")),
+    }
+}
+
+/// Verify a proof generated by `prove_count_in_subtree`
+/// Returns the root hash + the number of elements in the count tree at
+/// the path
+pub fn verify_count_in_subtree(proof: &[u8], path: Vec<Vec<u8>>) -> Result<([u8; 32], u64), Error> {
+    let query = PathQuery::for_subtree_element(path)?;
+    let (root_hash, result_set) = verify_query(proof, &query)?;
+    match result_set.into_iter().next() {
+        Some((_, _, Some(Element::CountTree(_, count, _)))) => Ok((root_hash, count)),
+        Some((_, _, Some(_))) => Err(Error::WrongElementType(
+            "the subtree at the given path is not a count tree",
+        )),
+        _ => Err(Error::InvalidProof("proof doesn't contain the count tree")),
+    }
+}
+
+/// Verify a proof generated by `prove_aggregate_of_subtree`
+/// Returns the root hash + the aggregate id and the aggregate of the aggregate
+/// tree at the path
+pub fn verify_aggregate_of_subtree(
+    proof: &[u8],
+    path: Vec<Vec<u8>>,
+) -> Result<([u8; 32], u8, Option<i128>), Error> {
+    let query = PathQuery::for_subtree_element(path)?;
+    let (root_hash, result_set) = verify_query(proof, &query)?;
+    match result_set.into_iter().next() {
+        Some((_, _, Some(Element::AggregateTree(_, aggregate_id, aggregate_value, _)))) => {
+            Ok((root_hash, aggregate_id, aggregate_value))
+        }
+        Some((_, _, Some(_))) => Err(Error::WrongElementType(
+            "the subtree at the given path is not an aggregate tree",
+        )),
+        _ => Err(Error::InvalidProof(
+            "proof doesn't contain the aggregate tree",
+        )),
+    }
+}
+
+/// Verify a proof generated by `prove_backlinks`
+/// Returns the root hash + the qualified paths of the bidirectional
+/// references pointing to the element under `key` in the subtree at
+/// `path`. Each of them is proven to point to the element, but backlinks
+/// aren't covered by the root hash, so referrers may have been left out.
+pub fn verify_backlinks(
+    proof: &[u8],
+    path: Vec<Vec<u8>>,
+    key: &[u8],
+) -> Result<(CryptoHash, Vec<Path>), Error> {
+    let backlinks_proof: BacklinksProof = backlinks_serialization_options()
+        .deserialize(proof)
+        .map_err(|_| Error::CorruptedData("unable to deserialize backlinks proof".to_owned()))?;
+    let (root_hash, _) = verify_query_raw(
+        &backlinks_proof.element_proof,
+        &PathQuery::new_single_key(path.clone(), key.to_vec()),
+    )?;
+    let mut target = path;
+    target.push(key.to_vec());
+
+    let mut referrers = Vec::with_capacity(backlinks_proof.referrers.len());
+    for referrer_proof in backlinks_proof.referrers {
+        let (referrer_key, referrer_path) = referrer_proof
+            .referrer
+            .split_last()
+            .ok_or(Error::InvalidProof("proof holds an empty referrer path"))?;
+        let (referrer_root_hash, proved) = verify_query_raw(
+            &referrer_proof.proof,
+            &PathQuery::new_single_key(referrer_path.to_vec(), referrer_key.clone()),
+        )?;
+        if referrer_root_hash != root_hash {
+            return Err(Error::InvalidProof(
+                "proofs of the referrers have a different root hash",
+            ));
+        }
+        // References are proven with the element they point to, the
+        // reference itself being committed to by its value hash
+        let proved_reference_hash = proved
+            .first()
+            .ok_or(Error::InvalidProof("proof doesn't contain a referrer"))?
+            .proof;
+        if value_hash_fn(&referrer_proof.reference).value() != &proved_reference_hash {
+            return Err(Error::InvalidProof(
+                "proof holds a reference not matching its referrer",
+            ));
+        }
+        let Element::BidirectionalReference(reference_path, ..) =
+            Element::deserialize(&referrer_proof.reference)?
+        else {
+            return Err(Error::InvalidProof(
+                "proof holds a referrer which is not a bidirectional reference",
+            ));
+        };
+        if path_from_reference_path_type(reference_path, referrer_path, Some(referrer_key))?
+            != target
+        {
+            return Err(Error::InvalidProof(
+                "proof holds a referrer not pointing to the element",
+            ));
+        }
+        referrers.push(referrer_proof.referrer);
+    }
+    Ok((root_hash, referrers))
+}
+
+/// Verify a proof generated by `prove_aggregate`
+/// Returns the root hash + the aggregate over the elements matched by the
+/// path query. A count of all the elements of a subtree is taken from the
+/// count tree element when the proof holds it.
+pub fn verify_aggregate(
+    proof: &[u8],
+    query: &PathQuery,
+    aggregate: Aggregate,
+) -> Result<([u8; 32], i64), Error> {
+    match aggregate {
+        Aggregate::Count => {
+            if matches_whole_subtree(query) {
+                if let Ok((root_hash, count)) = verify_count_in_subtree(proof, query.path.clone()) {
+                    return Ok((root_hash, count as i64));
+                }
+            }
+            let (root_hash, keys) = verify_keys_only_query(proof, &query.clone().with_keys_only())?;
+            Ok((root_hash, keys.len() as i64))
+        }
+        Aggregate::Sum => {
+            let (root_hash, result_set) = verify_query(proof, query)?;
+            let values = result_set
+                .into_iter()
+                .filter_map(|(_, _, element)| element)
+                .map(|element| element.as_sum_item_value())
+                .collect::<Result<Vec<i64>, Error>>()?;
+            let total = total_of_sum_items(values)?;
+            Ok((root_hash, total))
+        }
+    }
+}
+
+/// Verify a proof generated by `prove_is_empty_tree`
+/// Returns the root hash + whether the subtree at the path has no elements
+pub fn verify_is_empty_tree(proof: &[u8], path: Vec<Vec<u8>>) -> Result<([u8; 32], bool), Error> {
+    let query = PathQuery::for_subtree_element(path)?;
+    let (root_hash, result_set) = verify_query_raw(proof, &query)?;
+    let proved = result_set
+        .into_iter()
+        .next()
+        .ok_or(Error::InvalidProof("proof doesn't contain the tree"))?;
+    let root_key = match Element::deserialize(proved.value.as_slice())? {
+        Element::Tree(root_key, _)
+        | Element::SumTree(root_key, ..)
+        | Element::CountTree(root_key, ..)
+        | Element::BigSumTree(root_key, ..)
+        | Element::AggregateTree(root_key, ..) => root_key,
+        _ => {
+            return Err(Error::WrongElementType(
+                "the element at the given path is not a tree",
+            ))
+        }
+    };
+    // the value hash of a tree element combines the hash of the element
+    // with the root hash of its subtree, which is the empty tree hash only
+    // when the subtree has no elements
+    let empty_tree_value_hash =
+        combine_hash(value_hash_fn(&proved.value).value(), &EMPTY_TREE_HASH)
+            .value()
+            .to_owned();
+    let is_empty = proved.proof == empty_tree_value_hash;
+    if is_empty != root_key.is_none() {
+        return Err(Error::InvalidProof(
+            "tree root key doesn't match the root hash of its subtree",
+        ));
+    }
+    Ok((root_hash, is_empty))
+}
+
+/// Verify a proof generated by `prove_path_absence`
+/// Returns the root hash + the number of leading segments of the path that
+/// exist, the deepest existing ancestor being the path truncated to them
+pub fn verify_path_absence(proof: &[u8], path: Vec<Vec<u8>>) -> Result<([u8; 32], usize), Error> {
+    let query = PathQuery::for_subtree_element(path)?;
+    let mut verifier = ProofVerifier::new(&query);
+    let root_hash = verifier.execute_proof(proof, &query, false)?;
+    let existing_depth = match verifier.absent_path_depth {
+        Some(depth) => depth,
+        // the parent of the last segment exists but doesn't hold it
+        None if verifier.result_set.is_empty() => query.path.len(),
+        None => return Err(Error::InvalidProof("proof invalid: path not absent")),
+    };
+    Ok((root_hash, existing_depth))
+}
+
+/// Verify a proof generated by `prove_path_key_absence`
+/// Returns the root hash, failing if an element exists under the key
+pub fn verify_path_key_absence(
+    proof: &[u8],
+    path: Vec<Vec<u8>>,
+    key: &[u8],
+) -> Result<[u8; 32], Error> {
+    let query = PathQuery::new_single_key(path, key.to_vec());
+    let mut verifier = ProofVerifier::new(&query);
+    let root_hash = verifier.execute_proof(proof, &query, false)?;
+    if !verifier.result_set.is_empty() {
+        return Err(Error::InvalidProof("proof invalid: key not absent"));
+    }
+    Ok(root_hash)
+}
+
+/// Verify a proof generated by `prove_root_leaf`
+/// Returns the root hash + the element under the key of the root leaf, if
+/// there is one
+pub fn verify_root_leaf(proof: &[u8], key: &[u8]) -> Result<([u8; 32], Option<Element>), Error> {
+    let query = Query::new_single_key(key.to_vec());
+    let (root_hash, result) = grovedb_merk::execute_proof(proof, &query, None, None, true)
+        .unwrap()
+        .map_err(|_| Error::InvalidProof("invalid root leaf proof"))?;
+    let element = result
+        .result_set
+        .into_iter()
+        .find(|proved| proved.key == key)
+        .map(|proved| Element::deserialize(&proved.value))
+        .transpose()?;
+    Ok((root_hash, element))
+}
+
+/// Verify proof for a given path query returns serialized elements
+pub fn verify_query_raw(
+    proof: &[u8],
+    query: &PathQuery,
+) -> Result<([u8; 32], ProvedPathKeyValues), Error> {
+    let mut verifier = ProofVerifier::new(query);
+    let hash = verifier.execute_proof(proof, query, false)?;
+
+    Ok((hash, verifier.result_set))
+}
+
+/// Verify proof for a given keys only path query
+/// Returns the root hash + the proven keys with the value hashes of their
+/// elements
+pub fn verify_keys_only_query(
+    proof: &[u8],
+    query: &PathQuery,
+) -> Result<(CryptoHash, Vec<(Key, CryptoHash)>), Error> {
+    if !query.is_keys_only() {
+        return Err(Error::InvalidInput("the path query is not keys only"));
+    }
+    let mut verifier = ProofVerifier::new(query);
+    let hash = verifier.execute_proof(proof, query, false)?;
+
+    let keys = verifier
+        .result_set
+        .into_iter()
+        .map(|proved| (proved.key, proved.proof))
+        .collect();
+    Ok((hash, keys))
+}
+
+/// Verify proof for a given keys only path query
+/// Returns the root hash + the proven keys as results of `result_type`,
+/// which must be `QueryPathKeyResultType` or
+/// `QueryPathKeyValueHashResultType` as the proof holds no values
+pub fn verify_keys_only_query_with_result_type(
+    proof: &[u8],
+    query: &PathQuery,
+    result_type: QueryResultType,
+) -> Result<([u8; 32], QueryResultElements), Error> {
+    let (hash, keys) = verify_keys_only_query(proof, query)?;
+    let elements =
+        keys.into_iter()
+            .map(|(key, value_hash)| match result_type {
+                QueryResultType::QueryPathKeyResultType => Ok(
+                    QueryResultElement::PathKeyResultItem((query.path.clone(), key)),
+                ),
+                QueryResultType::QueryPathKeyValueHashResultType => {
+                    Ok(QueryResultElement::PathKeyValueHashResultItem((
+                        query.path.clone(),
+                        key,
+                        value_hash,
+                    )))
+                }
+                _ => Err(Error::InvalidInput(
+                    "keys only proofs only prove keys and value hashes",
+                )),
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+    Ok((hash, QueryResultElements::from_elements(elements)))
+}
+
+/// Verify proof given a path query
+/// Returns the root hash + deserialized elements + the limit and offset
+/// left, to compute the parameters of the next page
+pub fn verify_query_with_remaining_limit_offset(
+    proof: &[u8],
+    query: &PathQuery,
+) -> Result<
+    (
+        [u8; 32],
+        Vec<PathKeyOptionalElementTrio>,
+        RemainingLimitOffset,
+    ),
+    Error,
+> {
+    let (root_hash, proved_path_key_values, remaining) =
+        verify_query_raw_with_remaining_limit_offset(proof, query)?;
+    let path_key_optional_elements = proved_path_key_values
+        .into_iter()
+        .map(|pkv| pkv.try_into())
+        .collect::<Result<Vec<PathKeyOptionalElementTrio>, Error>>()?;
+    Ok((root_hash, path_key_optional_elements, remaining))
+}
+
+/// Verify proof for a given path query returns serialized elements + the
+/// limit and offset left
+pub fn verify_query_raw_with_remaining_limit_offset(
+    proof: &[u8],
+    query: &PathQuery,
+) -> Result<([u8; 32], ProvedPathKeyValues, RemainingLimitOffset), Error> {
+    let mut verifier = ProofVerifier::new(query);
+    let hash = verifier.execute_proof(proof, query, false)?;
+    let remaining = RemainingLimitOffset {
+        limit: verifier.limit,
+        offset: verifier.offset,
+    };
+
+    Ok((hash, verifier.result_set, remaining))
+}
+
+/// Verify proof given a path query
+/// Returns the root hash + deserialized elements + the sum of every sum
+/// tree the proof goes through, by path. The sum of a sum tree whose
+/// elements are all returned is also checked against those elements
+pub fn verify_query_with_subtree_sums(
+    proof: &[u8],
+    query: &PathQuery,
+) -> Result<(CryptoHash, Vec<PathKeyOptionalElementTrio>, SubtreeSums), Error> {
+    let mut verifier = ProofVerifier::new(query);
+    let hash = verifier.execute_proof(proof, query, false)?;
+    let path_key_optional_elements = verifier
+        .result_set
+        .into_iter()
+        .map(|pkv| pkv.try_into())
+        .collect::<Result<Vec<PathKeyOptionalElementTrio>, Error>>()?;
+    Ok((hash, path_key_optional_elements, verifier.subtree_sums))
+}
+
+/// Verify proof given multiple path queries.
+/// If we have more than one path query we merge before performing
+/// verification.
+pub fn verify_query_many(
+    proof: &[u8],
+    query: Vec<&PathQuery>,
+) -> Result<([u8; 32], ProvedPathKeyValues), Error> {
+    if query.len() > 1 {
+        let query = PathQuery::merge(query)?;
+        verify_query_raw(proof, &query)
+    } else {
+        verify_query_raw(proof, query[0])
+    }
+}
+
+/// Given a verbose proof, we can verify it with a subset path query.
+/// Returning the root hash and the deserialized result set.
+pub fn verify_subset_query(
+    proof: &[u8],
+    query: &PathQuery,
+) -> Result<([u8; 32], Vec<PathKeyOptionalElementTrio>), Error> {
+    let (root_hash, proved_path_key_values) = verify_subset_query_raw(proof, query)?;
+    let path_key_optional_elements = proved_path_key_values
+        .into_iter()
+        .map(|pkv| pkv.try_into())
+        .collect::<Result<Vec<PathKeyOptionalElementTrio>, Error>>()?;
+    Ok((root_hash, path_key_optional_elements))
+}
+
+/// Given a verbose proof, we can verify it with a subset path query.
+/// Returning the root hash and the serialized result set.
+pub fn verify_subset_query_raw(
+    proof: &[u8],
+    query: &PathQuery,
+) -> Result<([u8; 32], ProvedPathKeyValues), Error> {
+    let mut verifier = ProofVerifier::new(query);
+    let hash = verifier.execute_proof(proof, query, true)?;
+    Ok((hash, verifier.result_set))
+}
+
+/// Verify non subset query return the absence proof
+/// Returns all possible keys within the Path Query with an optional Element
+/// Value Element is set to None if absent
+pub fn verify_query_with_absence_proof(
+    proof: &[u8],
+    query: &PathQuery,
+) -> Result<([u8; 32], Vec<PathKeyOptionalElementTrio>), Error> {
+    verify_with_absence_proof(proof, query, verify_query)
+}
+
+/// Verify subset query return the absence proof
+/// Returns all possible keys within the Path Query with an optional Element
+/// Value Element is set to None if absent
+pub fn verify_subset_query_with_absence_proof(
+    proof: &[u8],
+    query: &PathQuery,
+) -> Result<([u8; 32], Vec<PathKeyOptionalElementTrio>), Error> {
+    verify_with_absence_proof(proof, query, verify_subset_query)
+}
+
+/// Verifies the proof and returns both elements in the result set and the
+/// elements in query but not in state.
+/// Note: This only works for certain path queries.
+// TODO: We should not care about terminal keys, as theoretically they can be
+//  infinite  we should perform the absence check solely on the proof and the
+//  given key, this is a temporary solution
+fn verify_with_absence_proof<T>(
+    proof: &[u8],
+    query: &PathQuery,
+    verification_fn: T,
+) -> Result<([u8; 32], Vec<PathKeyOptionalElementTrio>), Error>
+where
+    T: Fn(&[u8], &PathQuery) -> Result<([u8; 32], Vec<PathKeyOptionalElementTrio>), Error>,
+{
+    // must have a limit
+    let max_results = query.query.limit.ok_or(Error::NotSupported(
+        "limits must be set in verify_query_with_absence_proof",
+    ))? as usize;
+
+    // must have no offset
+    if query.query.offset.is_some() {
+        return Err(Error::NotSupported(
+            "offsets are not supported for verify_query_with_absence_proof",
+        ));
+    }
+
+    let terminal_keys = query.terminal_keys(max_results)?;
+
+    // need to actually verify the query
+    let (root_hash, result_set) = verification_fn(proof, query)?;
+
+    // convert the result set to a btree map
+    let mut result_set_as_map: BTreeMap<PathKey, Option<Element>> = result_set
+        .into_iter()
+        .map(|(path, key, element)| ((path, key), element))
+        .collect();
+
+    let result_set_with_absence: Vec<PathKeyOptionalElementTrio> = terminal_keys
+        .into_iter()
+        .map(|terminal_key| {
+            let element = result_set_as_map.remove(&terminal_key).flatten();
+            (terminal_key.0, terminal_key.1, element)
+        })
+        .collect();
+
+    Ok((root_hash, result_set_with_absence))
+}
+
+/// Verify subset proof with a chain of path query functions.
+/// After subset verification with the first path query, the result if
+/// passed to the next path query generation function which generates a
+/// new path query Apply the new path query, and pass the result to the
+/// next ... This is useful for verifying proofs with multiple path
+/// queries that depend on one another.
+pub fn verify_query_with_chained_path_queries<C>(
+    proof: &[u8],
+    first_query: &PathQuery,
+    chained_path_queries: Vec<C>,
+) -> Result<(CryptoHash, Vec<Vec<PathKeyOptionalElementTrio>>), Error>
+where
+    C: Fn(Vec<PathKeyOptionalElementTrio>) -> Option<PathQuery>,
+{
+    let mut results = vec![];
+
+    let (last_root_hash, elements) = verify_subset_query(proof, first_query)?;
+    results.push(elements);
+
+    // we should iterate over each chained path queries
+    for path_query_generator in chained_path_queries {
+        let new_path_query = path_query_generator(results[results.len() - 1].clone()).ok_or(
+            Error::InvalidInput("one of the path query generators returns no path query"),
+        )?;
+        let (new_root_hash, new_elements) = verify_subset_query(proof, &new_path_query)?;
+        if new_root_hash != last_root_hash {
+            return Err(Error::InvalidProof(
+                "root hash for different path queries do no match",
+            ));
+        }
+        results.push(new_elements);
+    }
+
+    Ok((last_root_hash, results))
+}
+
+/// Proof verifier
+struct ProofVerifier {
+    limit: Option<u16>,
+    offset: Option<u16>,
+    result_set: ProvedPathKeyValues,
+    subtree_sums: BTreeMap<Path, SumValue>,
+    /// Sum trees whose elements were all queried, their sums are recomputed
+    /// from the result set
+    whole_sum_trees: Vec<Path>,
+    /// Index of the first missing segment of an absent query path
+    absent_path_depth: Option<usize>,
+    /// The query only proves keys and value hashes
+    keys_only: bool,
+}
+
+impl ProofVerifier {
+    /// New query
+    pub fn new(query: &PathQuery) -> Self {
+        ProofVerifier {
+            limit: query.query.limit,
+            offset: query.query.offset,
+            result_set: vec![],
+            subtree_sums: BTreeMap::new(),
+            whole_sum_trees: vec![],
+            absent_path_depth: None,
+            keys_only: query.is_keys_only(),
+        }
+    }
+
+    /// Execute proof
+    pub fn execute_proof(
+        &mut self,
+        proof: &[u8],
+        query: &PathQuery,
+        is_verbose: bool,
+    ) -> Result<[u8; 32], Error> {
+        if query.query.query.has_element_kind_actions()
+            && (query.is_keys_only() || query.query.limit.is_some() || query.query.offset.is_some())
+        {
+            return Err(Error::NotSupported(
+                "element kind actions can't be proved with a limit, an offset or keys only",
+            ));
+        }
+        let (proof_version, mut proof) = read_and_consume_known_proof_version(proof)?;
+        let expanded_proof;
+        if proof_version == COMPACT_PROOF_VERSION {
+            if is_verbose {
+                return Err(Error::InvalidProof(
+                    "compactly encoded proofs can't be verbose",
+                ));
+            }
+            expanded_proof = expand_merk_proofs(proof)?;
+            proof = expanded_proof.as_slice();
+        }
+        let mut proof_reader = ProofReader::new_with_verbose_status(proof, is_verbose);
+
+        let path_slices = query.path.iter().map(|x| x.as_slice()).collect::<Vec<_>>();
+        let mut query = Cow::Borrowed(query);
+
+        // TODO: refactor and add better comments
+        // if verbose, the first thing we want to do is read the path info
+        if is_verbose {
+            let original_path = proof_reader.read_path_info()?;
+
+            if original_path == path_slices {
+                // do nothing
+            } else if original_path.len() > path_slices.len() {
+                // TODO: can we relax this constraint
+                return Err(Error::InvalidProof(
+                    "original path query path must not be greater than the subset path len",
+                ));
+            } else {
+                let original_path_in_new_path = original_path
+                    .iter()
+                    .all(|key| path_slices.contains(&key.as_slice()));
+
+                if !original_path_in_new_path {
+                    return Err(Error::InvalidProof(
+                        "the original path should be a subset of the subset path",
+                    ));
+                } else {
+                    // We construct a new path query
+                    let path_not_common = path_slices[original_path.len()..].to_vec();
+                    let mut path_iter = path_not_common.iter();
+
+                    let mut new_query = Query::new();
+                    if path_iter.len() >= 1 {
+                        new_query
+                            .insert_key(path_iter.next().expect("confirmed has value").to_vec());
+                    }
+
+                    // need to add the first key to the query
+                    new_query.set_subquery_path(path_iter.map(|a| a.to_vec()).collect());
+                    new_query.set_subquery(query.query.query.clone());
+
+                    query = Cow::Owned(PathQuery::new(
+                        original_path,
+                        SizedQuery::new(new_query, query.query.limit, query.query.offset),
+                    ));
+                }
+            }
+        }
+
+        let (proof_token_type, proof, _) = proof_reader.read_proof()?;
+
+        let root_hash = if proof_token_type == AbsentPath {
+            self.verify_absent_path(&mut proof_reader, path_slices)?
+        } else {
+            let path_owned = query.path.iter().map(|a| a.to_vec()).collect();
+            let mut last_subtree_root_hash = self.execute_subquery_proof(
+                proof_token_type,
+                proof,
+                &mut proof_reader,
+                query.as_ref(),
+                path_owned,
+            )?;
+
+            // validate the path elements are connected
+            self.verify_path_to_root(
+                query.as_ref(),
+                query.path.iter().map(|a| a.as_ref()).collect(),
+                &mut proof_reader,
+                &mut last_subtree_root_hash,
+            )?
+        };
+
+        self.verify_limit_and_offset(query.as_ref())?;
+        self.verify_subtree_sums(query.as_ref())?;
+
+        Ok(root_hash)
+    }
+
+    /// Check that the results taken from the proof agree with the limit and
+    /// offset of the query
+    fn verify_limit_and_offset(&self, query: &PathQuery) -> Result<(), Error> {
+        let result_count = self.result_set.len();
+        if let Some(limit) = query.query.limit {
+            if result_count > limit as usize {
+                return Err(Error::ProofLimitOffsetMismatch(
+                    "proof returns more results than the limit allows",
+                ));
+            }
+            let consumed = limit.saturating_sub(self.limit.unwrap_or(0));
+            if result_count != consumed as usize {
+                return Err(Error::ProofLimitOffsetMismatch(
+                    "proof results don't match the consumed limit",
+                ));
+            }
+        }
+        if matches!(self.offset, Some(offset) if offset > 0) && result_count > 0 {
+            return Err(Error::ProofLimitOffsetMismatch(
+                "proof returns results before the offset is exhausted",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Keep the sum of a sum tree the proof goes through, `whole` tells if
+    /// all elements of the sum tree are queried
+    fn record_subtree_sum(&mut self, path: Path, element: &Element, whole: bool) {
+        if let Element::SumTree(_, sum, _) = element {
+            if whole {
+                self.whole_sum_trees.push(path.clone());
+            }
+            self.subtree_sums.insert(path, *sum);
+        }
+    }
+
+    /// Recompute the sum of every sum tree whose elements were all returned
+    /// and compare it with the sum its parent commits to
+    fn verify_subtree_sums(&self, query: &PathQuery) -> Result<(), Error> {
+        // with an offset or an exhausted limit some elements may be missing,
+        // and keys only proofs have no elements to sum
+        if query.query.offset.unwrap_or(0) != 0 || self.limit == Some(0) || self.keys_only {
+            return Ok(());
+        }
+        for path in &self.whole_sum_trees {
+            let mut sum: SumValue = 0;
+            for proved in self.result_set.iter().filter(|proved| &proved.path == path) {
+                // a reference is proven with the value it points to, which
+                // doesn't add to the sum of the tree holding the reference
+                let value = match Element::deserialize(proved.value.as_slice())? {
+                    Element::SumItem(value, _)
+                        if value_hash_fn(&proved.value).value() == &proved.proof =>
+                    {
+                        value
+                    }
+                    Element::SumTree(_, value, _) => value,
+                    _ => 0,
+                };
+                sum = sum
+                    .checked_add(value)
+                    .ok_or(Error::InvalidProof("sum of proven elements overflows"))?;
+            }
+            if self.subtree_sums.get(path) != Some(&sum) {
+                return Err(Error::InvalidProof(
+                    "sum tree total doesn't match its proven elements",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn execute_subquery_proof(
+        &mut self,
+        proof_token_type: ProofTokenType,
+        proof: Vec<u8>,
+        proof_reader: &mut ProofReader,
+        query: &PathQuery,
+        path: Path,
+    ) -> Result<[u8; 32], Error> {
+        let last_root_hash: [u8; 32];
+
+        match proof_token_type {
+            ProofTokenType::SizedMerk => {
+                // verify proof with limit and offset values
+                let verification_result = self.execute_merk_proof(
+                    ProofTokenType::SizedMerk,
+                    &proof,
+                    &query.query.query,
+                    query.query.query.left_to_right,
+                    path,
+                )?;
+
+                last_root_hash = verification_result.0;
+            }
+            ProofTokenType::Merk => {
+                // for non leaf subtrees, we want to prove that all the queried keys
+                // have an accompanying proof as long as the limit is non zero
+                // and their child subtree is not empty
+                let (proof_root_hash, children) = self.execute_merk_proof(
+                    ProofTokenType::Merk,
+                    &proof,
+                    &query.query.query,
+                    query.query.query.left_to_right,
+                    path,
+                )?;
+
+                last_root_hash = proof_root_hash;
+                let children = children.ok_or(Error::InvalidProof(
+                    "MERK_PROOF always returns a result set",
+                ))?;
+
+                for proved_path_key_value in children {
+                    let ProvedPathKeyValue {
+                        path,
+                        key,
+                        value: value_bytes,
+                        proof: value_hash,
+                    } = proved_path_key_value;
+                    let child_element = Element::deserialize(value_bytes.as_slice())?;
+                    match &child_element {
+                        Element::Tree(expected_root_key, _)
+                        | Element::SumTree(expected_root_key, ..)
+                        | Element::CountTree(expected_root_key, ..)
+                        | Element::BigSumTree(expected_root_key, ..)
+                        | Element::AggregateTree(expected_root_key, ..) => {
+                            let mut expected_combined_child_hash = value_hash;
+                            let mut current_value_bytes = value_bytes;
+
+                            if self.limit == Some(0) {
+                                // we are done verifying the subqueries
+                                break;
+                            }
+
+                            let Some((subquery_path, subquery_value)) =
+                                Element::subquery_paths_and_value_for_element(
+                                    &query.query,
+                                    key.as_slice(),
+                                    &child_element,
+                                )
+                            else {
+                                // the query skips this tree
+                                continue;
+                            };
+
+                            if subquery_value.is_none() && subquery_path.is_none() {
+                                // add this element to the result set
+                                let skip_limit = reduce_limit_and_offset_by(
+                                    &mut self.limit,
+                                    &mut self.offset,
+                                    1,
+                                );
+
+                                if !skip_limit {
+                                    // only insert to the result set if the offset value is not
+                                    // greater than 0
+                                    self.result_set.push(
+                                        ProvedPathKeyValue::from_proved_key_value(
+                                            path,
+                                            ProvedKeyValue {
+                                                key,
+                                                value: current_value_bytes,
+                                                proof: value_hash,
+                                            },
+                                        ),
+                                    );
+                                }
+
+                                continue;
+                            }
+
+                            // What is the equivalent for an empty tree
+                            if expected_root_key.is_none() {
+                                // child node is empty, move on to next
+                                continue;
+                            }
+
+                            // update the path, we are about to perform a subquery call
+                            let mut new_path = path.to_owned();
+                            new_path.push(key);
+
+                            if let Some(mut subquery_path) =
+                                subquery_path.filter(|subquery_path| !subquery_path.is_empty())
+                            {
+                                if subquery_value.is_none() {
+                                    self.verify_subquery_path(
+                                        proof_reader,
+                                        ProofTokenType::SizedMerk,
+                                        &mut subquery_path,
+                                        &mut expected_combined_child_hash,
+                                        &mut current_value_bytes,
+                                        &mut new_path,
+                                    )?;
+                                    continue;
+                                } else {
+                                    let (_, result_set_opt, encountered_absence) = self
+                                        .verify_subquery_path(
+                                            proof_reader,
+                                            ProofTokenType::Merk,
+                                            &mut subquery_path,
+                                            &mut expected_combined_child_hash,
+                                            &mut current_value_bytes,
+                                            &mut new_path,
+                                        )?;
+
+                                    if encountered_absence {
+                                        // we hit an absence proof while verifying the subquery path
+                                        continue;
+                                    }
+
+                                    let subquery_path_result_set = result_set_opt;
+                                    if subquery_path_result_set.is_none() {
+                                        // this means a sized proof was generated for the subquery
+                                        // key
+                                        // which is invalid as there exists a subquery value
+                                        return Err(Error::InvalidProof(
+                                            "expected unsized proof for subquery path as subquery \
+                                             value exists",
+                                        ));
+                                    }
+                                    let subquery_path_result_set =
+                                        subquery_path_result_set.expect("confirmed exists above");
+
+                                    if subquery_path_result_set.is_empty() {
+                                        // we have a valid proof that shows the absence of the
+                                        // subquery path in the tree, hence the subquery value
+                                        // cannot be applied, move on to the next.
+                                        continue;
+                                    }
+
+                                    Self::update_root_key_from_subquery_path_element(
+                                        &mut expected_combined_child_hash,
+                                        &mut current_value_bytes,
+                                        &subquery_path_result_set,
+                                    )?;
+                                }
+                            }
+
+                            let subquery_value = subquery_value.unwrap();
+                            self.record_subtree_sum(
+                                new_path.clone(),
+                                &Element::deserialize(current_value_bytes.as_slice())?,
+                                queries_whole_layer(&subquery_value),
+                            );
+
+                            let new_path_query = PathQuery::new_unsized(vec![], subquery_value);
+
+                            let (child_proof_token_type, child_proof) = proof_reader
+                                .read_next_proof(new_path.last().unwrap_or(&Default::default()))?;
+
+                            let child_hash = self.execute_subquery_proof(
+                                child_proof_token_type,
+                                child_proof,
+                                proof_reader,
+                                &new_path_query,
+                                new_path,
+                            )?;
+
+                            let combined_child_hash = combine_hash(
+                                value_hash_fn(&current_value_bytes).value(),
+                                &child_hash,
+                            )
+                            .value()
+                            .to_owned();
+
+                            if combined_child_hash != expected_combined_child_hash {
+                                return Err(Error::InvalidProof(
+                                    "child hash doesn't match the expected hash",
+                                ));
+                            }
+                        }
+                        _ => {
+                            // encountered a non tree element, we can't apply a subquery to it
+                            // add it to the result set.
+                            if self.limit == Some(0) {
+                                break;
+                            }
+
+                            if is_skipped(
+                                &query.query.query,
+                                &key,
+                                &child_element,
+                                &value_bytes,
+                                &value_hash,
+                            ) {
+                                continue;
+                            }
+
+                            let skip_limit =
+                                reduce_limit_and_offset_by(&mut self.limit, &mut self.offset, 1);
+
+                            if !skip_limit {
+                                // only insert to the result set if the offset value is not greater
+                                // than 0
+                                self.result_set
+                                    .push(ProvedPathKeyValue::from_proved_key_value(
+                                        path,
+                                        ProvedKeyValue {
+                                            key,
+                                            value: value_bytes,
+                                            proof: value_hash,
+                                        },
+                                    ));
+                            }
+                        }
+                    }
+                }
+            }
+            ProofTokenType::EmptyTree => {
+                last_root_hash = EMPTY_TREE_HASH;
+            }
+            _ => {
+                // execute_subquery_proof only expects proofs for merk trees
+                // root proof is handled separately
+                return Err(Error::InvalidProof("wrong proof type"));
+            }
+        }
+        Ok(last_root_hash)
+    }
+
+    /// Deserialize subkey_element and update expected root hash and element
+    /// value
+    fn update_root_key_from_subquery_path_element(
+        expected_child_hash: &mut CryptoHash,
+        current_value_bytes: &mut Vec<u8>,
+        subquery_path_result_set: &[ProvedPathKeyValue],
+    ) -> Result<(), Error> {
+        let elem_value = &subquery_path_result_set[0].value;
+        let subquery_path_element = Element::deserialize(elem_value)
+            .map_err(|_| Error::CorruptedData("failed to deserialize element".to_string()))?;
+        match subquery_path_element {
+            Element::Tree(..)
+            | Element::SumTree(..)
+            | Element::CountTree(..)
+            | Element::BigSumTree(..)
+            | Element::AggregateTree(..) => {
+                *expected_child_hash = subquery_path_result_set[0].proof;
+                *current_value_bytes = subquery_path_result_set[0].value.to_owned();
+            }
+            _ => {
+                // the means that the subquery path pointed to a non tree
+                // element, this is not valid as you cannot apply the
+                // the subquery value to non tree items
+                return Err(Error::InvalidProof(
+                    "subquery path cannot point to non tree element",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that a valid proof showing the existence or absence of the
+    /// subquery path is present
+    fn verify_subquery_path(
+        &mut self,
+        proof_reader: &mut ProofReader,
+        expected_proof_token_type: ProofTokenType,
+        subquery_path: &mut Path,
+        expected_root_hash: &mut CryptoHash,
+        current_value_bytes: &mut Vec<u8>,
+        current_path: &mut Path,
+    ) -> Result<(CryptoHash, Option<ProvedPathKeyValues>, EncounteredAbsence), Error> {
+        // the subquery path contains at least one item.
+        let last_key = subquery_path.remove(subquery_path.len() - 1);
+
+        for subquery_key in subquery_path.iter() {
+            let (proof_token_type, subkey_proof) =
+                proof_reader.read_next_proof(current_path.last().unwrap_or(&Default::default()))?;
+            // intermediate proofs are all going to be unsized merk proofs
+            if proof_token_type != ProofTokenType::Merk {
+                return Err(Error::InvalidProof(
+                    "expected MERK proof type for intermediate subquery path keys",
+                ));
+            }
+            match proof_token_type {
+                ProofTokenType::Merk => {
+                    let mut key_as_query = Query::new();
+                    key_as_query.insert_key(subquery_key.to_owned());
+
+                    let (proof_root_hash, result_set) = self.execute_merk_proof(
+                        proof_token_type,
+                        &subkey_proof,
+                        &key_as_query,
+                        key_as_query.left_to_right,
+                        current_path.to_owned(),
+                    )?;
+                    current_path.push(subquery_key.to_owned());
+
+                    // should always be some as we force the proof type to be MERK
+                    debug_assert!(result_set.is_some(), "{}", true);
+
+                    // result_set being empty means we could not find the given key in the subtree
+                    // which essentially means an absence proof
+                    if result_set
+                        .as_ref()
+                        .expect("result set should always be some for merk proof type")
+                        .is_empty()
+                    {
+                        return Ok((proof_root_hash, None, true));
+                    }
+
+                    // verify that the elements in the subquery path are linked by root hashes.
+                    let combined_child_hash =
+                        combine_hash(value_hash_fn(current_value_bytes).value(), &proof_root_hash)
+                            .value()
+                            .to_owned();
+
+                    if combined_child_hash != *expected_root_hash {
+                        return Err(Error::InvalidProof(
+                            "child hash doesn't match the expected hash",
+                        ));
+                    }
+
+                    // after confirming they are linked use the latest hash values for subsequent
+                    // checks
+                    Self::update_root_key_from_subquery_path_element(
+                        expected_root_hash,
+                        current_value_bytes,
+                        &result_set.expect("confirmed is some"),
+                    )?;
+                }
+                _ => {
+                    return Err(Error::InvalidProof(
+                        "expected merk of sized merk proof type for subquery path",
+                    ));
+                }
+            }
+        }
+
+        let (proof_token_type, subkey_proof) =
+            proof_reader.read_next_proof(current_path.last().unwrap_or(&Default::default()))?;
+        if proof_token_type != expected_proof_token_type {
+            return Err(Error::InvalidProof(
+                "unexpected proof type for subquery path",
+            ));
+        }
+
+        match proof_token_type {
+            ProofTokenType::Merk | ProofTokenType::SizedMerk => {
+                let mut key_as_query = Query::new();
+                key_as_query.insert_key(last_key.to_owned());
+
+                // the proved key lives in the subtree at the current path, the
+                // path only reaches into it once the key is proved
+                let verification_result = self.execute_merk_proof(
+                    proof_token_type,
+                    &subkey_proof,
+                    &key_as_query,
+                    key_as_query.left_to_right,
+                    current_path.to_owned(),
+                )?;
+                current_path.push(last_key);
+
+                Ok((verification_result.0, verification_result.1, false))
+            }
+            _ => Err(Error::InvalidProof(
+                "expected merk or sized merk proof type for subquery path",
+            )),
+        }
+    }
+
+    fn verify_absent_path(
+        &mut self,
+        proof_reader: &mut ProofReader,
+        path_slices: Vec<&[u8]>,
+    ) -> Result<[u8; 32], Error> {
+        let mut root_key_hash = None;
+        let mut expected_child_hash = None;
+        let mut last_result_set: ProvedPathKeyValues = vec![];
+
+        for (depth, key) in path_slices.into_iter().enumerate() {
+            let (proof_token_type, merk_proof, _) = proof_reader.read_proof()?;
+            if proof_token_type == ProofTokenType::EmptyTree {
+                // the tree reached is empty, so it doesn't hold the key
+                match expected_child_hash {
+                    None => root_key_hash = Some(EMPTY_TREE_HASH),
+                    Some(expected_child_hash) => {
+                        let combined_hash = combine_hash(
+                            value_hash_fn(last_result_set[0].value.as_slice()).value(),
+                            &EMPTY_TREE_HASH,
+                        )
+                        .value()
+                        .to_owned();
+                        if combined_hash != expected_child_hash {
+                            return Err(Error::InvalidProof("proof invalid: invalid parent"));
+                        }
+                    }
+                }
+                last_result_set.clear();
+                self.absent_path_depth = Some(depth);
+                break;
+            }
+            if proof_token_type != ProofTokenType::Merk {
+                return Err(Error::InvalidProof("expected a merk proof for absent path"));
+            }
+
+            let mut child_query = Query::new();
+            child_query.insert_key(key.to_vec());
+
+            // TODO: don't pass empty vec
+            let proof_result = self.execute_merk_proof(
+                ProofTokenType::Merk,
+                &merk_proof,
+                &child_query,
+                true,
+                // cannot return a result set
+                Vec::new(),
+            )?;
+
+            if expected_child_hash.is_none() {
+                root_key_hash = Some(proof_result.0);
+            } else {
+                let combined_hash = combine_hash(
+                    value_hash_fn(last_result_set[0].value.as_slice()).value(),
+                    &proof_result.0,
+                )
+                .value()
+                .to_owned();
+                if Some(combined_hash) != expected_child_hash {
+                    return Err(Error::InvalidProof("proof invalid: invalid parent"));
+                }
+            }
+
+            last_result_set = proof_result
+                .1
+                .expect("MERK_PROOF always returns a result set");
+            if last_result_set.is_empty() {
+                // if result set is empty then we have reached the absence point, break
+                self.absent_path_depth = Some(depth);
+                break;
+            }
+
+            let elem = Element::deserialize(last_result_set[0].value.as_slice())?;
+            let child_hash = match elem {
+                Element::Tree(..)
+                | Element::SumTree(..)
+                | Element::CountTree(..)
+                | Element::BigSumTree(..)
+                | Element::AggregateTree(..) => Ok(Some(last_result_set[0].proof)),
+                _ => Err(Error::InvalidProof(
+                    "intermediate proofs should be for trees",
+                )),
+            }?;
+            expected_child_hash = child_hash;
+        }
+
+        if last_result_set.is_empty() {
+            if let Some(hash) = root_key_hash {
+                Ok(hash)
+            } else {
+                Err(Error::InvalidProof("proof invalid: no non root tree found"))
+            }
+        } else {
+            Err(Error::InvalidProof("proof invalid: path not absent"))
+        }
+    }
+
+    /// Verifies that the correct proof was provided to confirm the path in
+    /// query
+    fn verify_path_to_root(
+        &mut self,
+        query: &PathQuery,
+        path_slices: Vec<&[u8]>,
+        proof_reader: &mut ProofReader,
+        expected_root_hash: &mut [u8; 32],
+    ) -> Result<[u8; 32], Error> {
+        let mut split_path = path_slices.split_last();
+        while let Some((key, path_slice)) = split_path {
+            // for every subtree, there should be a corresponding proof for the parent
+            // which should prove that this subtree is a child of the parent tree
+            let (proof_token_type, parent_merk_proof) =
+                proof_reader.read_next_proof(path_slice.last().unwrap_or(&Default::default()))?;
+            if proof_token_type != ProofTokenType::Merk {
+                return Err(Error::InvalidProof("wrong data_type expected merk proof"));
+            }
+
+            let mut parent_query = Query::new();
+            parent_query.insert_key(key.to_vec());
+
+            let proof_result = self.execute_merk_proof(
+                ProofTokenType::Merk,
+                &parent_merk_proof,
+                &parent_query,
+                query.query.query.left_to_right,
+                // TODO: don't pass empty vec
+                Vec::new(),
+            )?;
+
+            let result_set = proof_result
+                .1
+                .expect("MERK_PROOF always returns a result set");
+            if result_set.is_empty() || &result_set[0].key != key {
+                return Err(Error::InvalidProof("proof invalid: invalid parent"));
+            }
+
+            let elem = Element::deserialize(result_set[0].value.as_slice())?;
+            // only the last path segment is the queried subtree itself
+            let is_queried_subtree = path_slice.len() + 1 == path_slices.len();
+            let mut subtree_path: Path =
+                path_slice.iter().map(|segment| segment.to_vec()).collect();
+            subtree_path.push(key.to_vec());
+            self.record_subtree_sum(
+                subtree_path,
+                &elem,
+                is_queried_subtree && queries_whole_layer(&query.query.query),
+            );
+            let child_hash = match elem {
+                Element::Tree(..)
+                | Element::SumTree(..)
+                | Element::CountTree(..)
+                | Element::BigSumTree(..)
+                | Element::AggregateTree(..) => Ok(result_set[0].proof),
+                _ => Err(Error::InvalidProof(
+                    "intermediate proofs should be for trees",
+                )),
+            }?;
+
+            let combined_root_hash = combine_hash(
+                value_hash_fn(&result_set[0].value).value(),
+                expected_root_hash,
+            )
+            .value()
+            .to_owned();
+            if child_hash != combined_root_hash {
+                return Err(Error::InvalidProof(
+                    "Bad path: tree hash does not have expected hash",
+                ));
+            }
+
+            *expected_root_hash = proof_result.0;
+
+            split_path = path_slice.split_last();
+        }
+
+        Ok(*expected_root_hash)
+    }
+
+    /// Execute a merk proof, update the state when a sized proof is
+    /// encountered i.e. update the limit, offset and result set values
+    fn execute_merk_proof(
+        &mut self,
+        proof_token_type: ProofTokenType,
+        proof: &[u8],
+        query: &Query,
+        left_to_right: bool,
+        path: Path,
+    ) -> Result<(CryptoHash, Option<ProvedPathKeyValues>), Error> {
+        let is_sized_proof = proof_token_type == ProofTokenType::SizedMerk;
+        let mut limit = None;
+        let mut offset = None;
+
+        if is_sized_proof {
+            limit = self.limit;
+            offset = self.offset;
+        }
+
+        let execute_proof = if is_sized_proof && self.keys_only {
+            execute_keys_only_proof
+        } else {
+            grovedb_merk::execute_proof
+        };
+        let (hash, result) = execute_proof(proof, query, limit, offset, left_to_right)
+            .unwrap()
+            .map_err(|e| match e {
+                grovedb_merk::Error::ProofLimitOffsetError(message) => {
+                    Error::ProofLimitOffsetMismatch(message)
+                }
+                e => {
+                    eprintln!("{e}");
+                    Error::InvalidProof("invalid proof verification parameters")
+                }
+            })?;
+
+        // convert the result set to proved_path_key_values, without the elements
+        // the query skips for their kind
+        let mut result_set = result.result_set;
+        if query.element_kind_actions.is_some() {
+            let mut kept = Vec::with_capacity(result_set.len());
+            for proved_key_value in result_set {
+                let element = Element::deserialize(proved_key_value.value.as_slice())?;
+                if !is_skipped(
+                    query,
+                    &proved_key_value.key,
+                    &element,
+                    &proved_key_value.value,
+                    &proved_key_value.proof,
+                ) {
+                    kept.push(proved_key_value);
+                }
+            }
+            result_set = kept;
+        }
+        let proved_path_key_values = ProvedPathKeyValue::from_proved_key_values(path, result_set);
+
+        if is_sized_proof {
+            self.limit = result.limit;
+            self.offset = result.offset;
+            self.result_set.extend(proved_path_key_values);
+            Ok((hash, None))
+        } else {
+            Ok((hash, Some(proved_path_key_values)))
+        }
+    }
+}
+
+/// Whether the query skips `element` at `key` for its kind, `value` and
+/// `value_hash` being the proved bytes of the element and their hash
+fn is_skipped(
+    query: &Query,
+    key: &[u8],
+    element: &Element,
+    value: &[u8],
+    value_hash: &CryptoHash,
+) -> bool {
+    // a reference is proven with the element it points to, its value hash being
+    // the hash of the reference rather than of the proved value
+    let kind = if !element.is_tree() && value_hash_fn(value).value() != value_hash {
+        ElementKind::Reference
+    } else {
+        element.kind()
+    };
+    query.element_kind_action(key, kind) == Some(&ElementKindAction::Skip)
+}
+
+/// Whether the query returns every element of the subtree it is applied to
+fn queries_whole_layer(query: &Query) -> bool {
+    !query.has_subquery() && matches!(query.items.as_slice(), [QueryItem::RangeFull(..)])
+}
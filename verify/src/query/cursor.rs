@@ -0,0 +1,191 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Query cursors
+//!
+//! A cursor points at the last element returned by a path query. Resuming
+//! the query from it yields a path query matching only the elements ordered
+//! after it, subtree by subtree, so paging through results neither scans the
+//! skipped elements again nor shifts when elements are inserted or deleted
+//! before the cursor. The resumed query is a plain path query: it is proven
+//! and verified like any other.
+
+use grovedb_merk::proofs::query::{Query, QueryItem, SubqueryBranch};
+use integer_encoding::{VarInt, VarIntReader};
+
+use crate::{Error, PathQuery};
+
+/// Position of the last element returned by a path query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryCursor {
+    path: Vec<Vec<u8>>,
+    key: Vec<u8>,
+}
+
+impl QueryCursor {
+    /// Cursor pointing at the element at `path` and `key`, as returned by a
+    /// query or by the verification of its proof
+    pub fn new(path: Vec<Vec<u8>>, key: Vec<u8>) -> Self {
+        Self { path, key }
+    }
+
+    /// Encodes the cursor, to be handed over to whoever requests the next
+    /// page
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.path.len().encode_var_vec();
+        for segment in self.path.iter().chain(std::iter::once(&self.key)) {
+            bytes.extend(segment.len().encode_var_vec());
+            bytes.extend_from_slice(segment);
+        }
+        bytes
+    }
+
+    /// Decodes a cursor encoded with `to_bytes`
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self, Error> {
+        let path_length = read_length(&mut bytes)?;
+        let path = (0..path_length)
+            .map(|_| read_segment(&mut bytes))
+            .collect::<Result<Vec<_>, _>>()?;
+        let key = read_segment(&mut bytes)?;
+        if !bytes.is_empty() {
+            return Err(Error::CorruptedData(
+                "query cursor has trailing bytes".to_string(),
+            ));
+        }
+        Ok(Self { path, key })
+    }
+}
+
+fn read_length(bytes: &mut &[u8]) -> Result<usize, Error> {
+    bytes
+        .read_varint()
+        .map_err(|_| Error::CorruptedData("invalid query cursor".to_string()))
+}
+
+fn read_segment(bytes: &mut &[u8]) -> Result<Vec<u8>, Error> {
+    let length = read_length(bytes)?;
+    if bytes.len() < length {
+        return Err(Error::CorruptedData("invalid query cursor".to_string()));
+    }
+    let (segment, rest) = bytes.split_at(length);
+    *bytes = rest;
+    Ok(segment.to_vec())
+}
+
+impl PathQuery {
+    /// Returns the path query matching the elements this query matches that
+    /// come after the element at `cursor`, in the query's order. The limit
+    /// is kept and the offset dropped, as the cursor replaces it.
+    pub fn resume_after(&self, cursor: &QueryCursor) -> Result<PathQuery, Error> {
+        let Some(relative_path) = cursor.path.strip_prefix(self.path.as_slice()) else {
+            return Err(Error::InvalidInput("cursor isn't under the query path"));
+        };
+        let mut position = relative_path.to_vec();
+        position.push(cursor.key.clone());
+
+        let mut resumed = self.clone();
+        resumed.query.query = resume_query_after(&self.query.query, &position)?;
+        resumed.query.offset = None;
+        Ok(resumed)
+    }
+}
+
+/// Returns the subquery branch `query` applies to the subtree at `key`
+fn subquery_branch_for_key<'a>(query: &'a Query, key: &[u8]) -> &'a SubqueryBranch {
+    query
+        .conditional_subquery_branches
+        .iter()
+        .flatten()
+        .find(|(query_item, _)| query_item.contains(key))
+        .map(|(_, subquery_branch)| subquery_branch)
+        .unwrap_or(&query.default_subquery_branch)
+}
+
+/// Restricts `query` to the elements after `position`, the path relative to
+/// the queried subtree and the key of the last returned element
+fn resume_query_after(query: &Query, position: &[Vec<u8>]) -> Result<Query, Error> {
+    let Some((key, rest)) = position.split_first() else {
+        return Err(Error::InvalidInput("cursor isn't under the query path"));
+    };
+    let branch = subquery_branch_for_key(query, key);
+    let subquery_path = branch.subquery_path.as_deref().unwrap_or_default();
+
+    // Position inside the subquery of the subtree at `key`, if the last
+    // element was returned by it
+    let subquery_position = match &branch.subquery {
+        Some(subquery) if rest.len() > subquery_path.len() => {
+            let (path, subquery_position) = rest.split_at(subquery_path.len());
+            if path != subquery_path {
+                return Err(Error::InvalidInput("cursor isn't a position of the query"));
+            }
+            Some((subquery, subquery_position))
+        }
+        _ if rest == subquery_path => None,
+        _ => return Err(Error::InvalidInput("cursor isn't a position of the query")),
+    };
+
+    // The subtree at `key` stays queried as long as its subquery has elements
+    // left after the cursor
+    let bound = match (query.left_to_right, subquery_position.is_some()) {
+        (true, true) => QueryItem::RangeFrom(key.clone()..),
+        (true, false) => QueryItem::RangeAfter(key.clone()..),
+        (false, true) => QueryItem::RangeToInclusive(..=key.clone()),
+        (false, false) => QueryItem::RangeTo(..key.clone()),
+    };
+
+    let mut resumed = query.clone();
+    resumed.items = query
+        .items
+        .iter()
+        .filter_map(|item| item.intersect(&bound).in_both)
+        .collect();
+
+    if let Some((subquery, subquery_position)) = subquery_position {
+        let resumed_branch = SubqueryBranch {
+            subquery_path: branch.subquery_path.clone(),
+            subquery: Some(Box::new(resume_query_after(subquery, subquery_position)?)),
+        };
+        // conditional subquery branches are looked up in order, the resumed
+        // one must come first
+        let resumed_item = QueryItem::Key(key.clone());
+        let earlier_branches = query
+            .conditional_subquery_branches
+            .iter()
+            .flatten()
+            .filter(|(query_item, _)| **query_item != resumed_item);
+        resumed.conditional_subquery_branches = Some(
+            std::iter::once((resumed_item.clone(), resumed_branch))
+                .chain(earlier_branches.map(|(query_item, subquery_branch)| {
+                    (query_item.clone(), subquery_branch.clone())
+                }))
+                .collect(),
+        );
+    }
+
+    Ok(resumed)
+}
@@ -0,0 +1,314 @@
+// MIT LICENSE
+//
+// Copyright (c) 2021 Dash Core Group
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Queries
+
+mod cursor;
+
+use std::cmp::Ordering;
+
+pub use cursor::QueryCursor;
+
+use grovedb_merk::proofs::query::query_item::QueryItem;
+use grovedb_merk::proofs::query::SubqueryBranch;
+use grovedb_merk::proofs::Query;
+
+use crate::query_result_type::PathKey;
+use crate::Error;
+
+#[derive(Debug, Clone)]
+/// Path query
+///
+/// Represents a path to a specific GroveDB tree and a corresponding query to
+/// apply to the given tree.
+pub struct PathQuery {
+    /// Path
+    // TODO: Make generic over path type
+    pub path: Vec<Vec<u8>>,
+    /// Query
+    pub query: SizedQuery,
+    /// Only the keys matched by the query are requested, proofs carrying the
+    /// value hashes of the matched elements instead of the elements
+    keys_only: bool,
+}
+
+#[derive(Debug, Clone)]
+/// Holds a query to apply to a tree and an optional limit/offset value.
+/// Limit and offset values affect the size of the result set.
+pub struct SizedQuery {
+    /// Query
+    pub query: Query,
+    /// Limit
+    pub limit: Option<u16>,
+    /// Offset
+    pub offset: Option<u16>,
+}
+
+impl SizedQuery {
+    /// New sized query
+    pub const fn new(query: Query, limit: Option<u16>, offset: Option<u16>) -> Self {
+        Self {
+            query,
+            limit,
+            offset,
+        }
+    }
+
+    /// New sized query with one key
+    pub fn new_single_key(key: Vec<u8>) -> Self {
+        Self {
+            query: Query::new_single_key(key),
+            limit: None,
+            offset: None,
+        }
+    }
+
+    /// New sized query with one key
+    pub fn new_single_query_item(query_item: QueryItem) -> Self {
+        Self {
+            query: Query::new_single_query_item(query_item),
+            limit: None,
+            offset: None,
+        }
+    }
+}
+
+impl PathQuery {
+    /// New path query
+    pub const fn new(path: Vec<Vec<u8>>, query: SizedQuery) -> Self {
+        Self {
+            path,
+            query,
+            keys_only: false,
+        }
+    }
+
+    /// New path query with a single key
+    pub fn new_single_key(path: Vec<Vec<u8>>, key: Vec<u8>) -> Self {
+        Self {
+            path,
+            query: SizedQuery::new_single_key(key),
+            keys_only: false,
+        }
+    }
+
+    /// New path query with a single query item
+    pub fn new_single_query_item(path: Vec<Vec<u8>>, query_item: QueryItem) -> Self {
+        Self {
+            path,
+            query: SizedQuery::new_single_query_item(query_item),
+            keys_only: false,
+        }
+    }
+
+    /// New path query for the element of the subtree at the given path, i.e.
+    /// the last path segment as a key in the parent subtree
+    pub fn for_subtree_element(mut path: Vec<Vec<u8>>) -> Result<Self, Error> {
+        let key = path.pop().ok_or(Error::InvalidPath(
+            "the root tree has no element".to_owned(),
+        ))?;
+        Ok(Self::new_single_key(path, key))
+    }
+
+    /// New unsized path query
+    pub const fn new_unsized(path: Vec<Vec<u8>>, query: Query) -> Self {
+        let query = SizedQuery::new(query, None, None);
+        Self {
+            path,
+            query,
+            keys_only: false,
+        }
+    }
+
+    /// Requests only the keys matched by the path query, which must have no
+    /// subqueries. Querying it only returns the path keys or value hashes of
+    /// the results, and proofs carry value hashes instead of elements.
+    pub fn with_keys_only(mut self) -> Self {
+        self.keys_only = true;
+        self
+    }
+
+    /// Whether only the keys matched by the path query are requested
+    pub const fn is_keys_only(&self) -> bool {
+        self.keys_only
+    }
+
+    /// Gets the path of all terminal keys
+    pub fn terminal_keys(&self, max_results: usize) -> Result<Vec<PathKey>, Error> {
+        let mut result: Vec<(Vec<Vec<u8>>, Vec<u8>)> = vec![];
+        self.query
+            .query
+            .terminal_keys(self.path.clone(), max_results, &mut result)
+            .map_err(Error::MerkError)?;
+        Ok(result)
+    }
+
+    /// Combines multiple path queries into one equivalent path query
+    pub fn merge(mut path_queries: Vec<&PathQuery>) -> Result<Self, Error> {
+        if path_queries.is_empty() {
+            return Err(Error::InvalidInput(
+                "merge function requires at least 1 path query",
+            ));
+        }
+        if path_queries.len() == 1 {
+            return Ok(path_queries.remove(0).clone());
+        }
+
+        let keys_only = path_queries[0].keys_only;
+        if path_queries
+            .iter()
+            .any(|path_query| path_query.keys_only != keys_only)
+        {
+            return Err(Error::NotSupported(
+                "can not merge keys only pathqueries with other pathqueries",
+            ));
+        }
+        let (common_path, next_index) = PathQuery::get_common_path(&path_queries);
+
+        let mut queries_for_common_path_this_level: Vec<Query> = vec![];
+
+        let mut queries_for_common_path_sub_level: Vec<SubqueryBranch> = vec![];
+
+        // convert all the paths after the common path to queries
+        path_queries.into_iter().try_for_each(|path_query| {
+            if path_query.query.offset.is_some() {
+                return Err(Error::NotSupported(
+                    "can not merge pathqueries with offsets",
+                ));
+            }
+            if path_query.query.limit.is_some() {
+                return Err(Error::NotSupported(
+                    "can not merge pathqueries with limits, consider setting the limit after the \
+                     merge",
+                ));
+            }
+            path_query
+                .to_subquery_branch_with_offset_start_index(next_index)
+                .map(|unsized_path_query| {
+                    if unsized_path_query.subquery_path.is_none() {
+                        queries_for_common_path_this_level
+                            .push(*unsized_path_query.subquery.unwrap());
+                    } else {
+                        queries_for_common_path_sub_level.push(unsized_path_query);
+                    }
+                })
+        })?;
+
+        let mut merged_query = Query::merge_multiple(queries_for_common_path_this_level);
+        // add conditional subqueries
+        for sub_path_query in queries_for_common_path_sub_level {
+            let SubqueryBranch {
+                subquery_path,
+                subquery,
+            } = sub_path_query;
+            let mut subquery_path =
+                subquery_path.ok_or(Error::CorruptedCodeExecution("subquery path must exist"))?;
+            let key = subquery_path.remove(0); // must exist
+            merged_query.insert_item(QueryItem::Key(key.clone()));
+            let rest_of_path = if subquery_path.is_empty() {
+                None
+            } else {
+                Some(subquery_path)
+            };
+            let subquery_branch = SubqueryBranch {
+                subquery_path: rest_of_path,
+                subquery,
+            };
+            merged_query.merge_conditional_boxed_subquery(QueryItem::Key(key), subquery_branch);
+        }
+
+        Ok(PathQuery {
+            keys_only,
+            ..PathQuery::new_unsized(common_path, merged_query)
+        })
+    }
+
+    /// Given a set of path queries, this returns an array of path keys that are
+    /// common across all the path queries.
+    /// Also returns the point at which they stopped being equal.
+    fn get_common_path(path_queries: &[&PathQuery]) -> (Vec<Vec<u8>>, usize) {
+        let min_path_length = path_queries
+            .iter()
+            .map(|path_query| path_query.path.len())
+            .min()
+            .expect("expect path_queries length to be 2 or more");
+
+        let mut common_path = vec![];
+        let mut level = 0;
+
+        while level < min_path_length {
+            let keys_at_level = path_queries
+                .iter()
+                .map(|path_query| &path_query.path[level])
+                .collect::<Vec<_>>();
+            let first_key = keys_at_level[0];
+
+            let keys_are_uniform = keys_at_level.iter().all(|&curr_key| curr_key == first_key);
+
+            if keys_are_uniform {
+                common_path.push(first_key.to_vec());
+                level += 1;
+            } else {
+                break;
+            }
+        }
+        (common_path, level)
+    }
+
+    /// Given a path and a starting point, a query that is equivalent to the
+    /// path is generated example: [a, b, c] =>
+    ///     query a
+    ///         cond a
+    ///             query b
+    ///                 cond b
+    ///                    query c
+    fn to_subquery_branch_with_offset_start_index(
+        &self,
+        start_index: usize,
+    ) -> Result<SubqueryBranch, Error> {
+        let path = &self.path;
+
+        match path.len().cmp(&start_index) {
+            Ordering::Equal => Ok(SubqueryBranch {
+                subquery_path: None,
+                subquery: Some(Box::new(self.query.query.clone())),
+            }),
+            Ordering::Less => Err(Error::CorruptedCodeExecution(
+                "invalid start index for path query merge",
+            )),
+            _ => {
+                let (_, remainder) = path.split_at(start_index);
+
+                Ok(SubqueryBranch {
+                    subquery_path: Some(remainder.to_vec()),
+                    subquery: Some(Box::new(self.query.query.clone())),
+                })
+            }
+        }
+    }
+}
@@ -34,8 +34,9 @@ use std::{
 };
 
 pub use grovedb_merk::proofs::query::{Key, Path, PathKey};
+use grovedb_merk::CryptoHash;
 
-use crate::{operations::proof::util::ProvedPathKeyValue, Element, Error};
+use crate::{proof::util::ProvedPathKeyValue, Element, Error};
 
 #[derive(Copy, Clone)]
 /// Query result type
@@ -46,6 +47,11 @@ pub enum QueryResultType {
     QueryKeyElementPairResultType,
     /// Query path key element trio result type
     QueryPathKeyElementTrioResultType,
+    /// Query path key result type, for existence and ordering checks
+    QueryPathKeyResultType,
+    /// Query path key value hash result type, the value hash being the one
+    /// of the element's merk node
+    QueryPathKeyValueHashResultType,
 }
 
 /// Query result elements
@@ -61,7 +67,7 @@ impl QueryResultElements {
     }
 
     /// From elements
-    pub(crate) fn from_elements(elements: Vec<QueryResultElement>) -> Self {
+    pub fn from_elements(elements: Vec<QueryResultElement>) -> Self {
         QueryResultElements { elements }
     }
 
@@ -84,14 +90,16 @@ impl QueryResultElements {
     pub fn to_elements(self) -> Vec<Element> {
         self.elements
             .into_iter()
-            .map(|result_item| match result_item {
-                QueryResultElement::ElementResultItem(element) => element,
+            .filter_map(|result_item| match result_item {
+                QueryResultElement::ElementResultItem(element) => Some(element),
                 QueryResultElement::KeyElementPairResultItem(element_key_pair) => {
-                    element_key_pair.1
+                    Some(element_key_pair.1)
                 }
                 QueryResultElement::PathKeyElementTrioResultItem(path_key_element_trio) => {
-                    path_key_element_trio.2
+                    Some(path_key_element_trio.2)
                 }
+                QueryResultElement::PathKeyResultItem(_)
+                | QueryResultElement::PathKeyValueHashResultItem(_) => None,
             })
             .collect()
     }
@@ -108,6 +116,8 @@ impl QueryResultElements {
                 QueryResultElement::PathKeyElementTrioResultItem(path_key_element_trio) => {
                     Some((path_key_element_trio.1, path_key_element_trio.2))
                 }
+                QueryResultElement::PathKeyResultItem(_)
+                | QueryResultElement::PathKeyValueHashResultItem(_) => None,
             })
             .collect()
     }
@@ -124,6 +134,8 @@ impl QueryResultElements {
                 QueryResultElement::PathKeyElementTrioResultItem(path_key_element_trio) => {
                     Some(path_key_element_trio.1)
                 }
+                QueryResultElement::PathKeyResultItem((_, key))
+                | QueryResultElement::PathKeyValueHashResultItem((_, key, _)) => Some(key),
             })
             .collect()
     }
@@ -140,6 +152,8 @@ impl QueryResultElements {
                 QueryResultElement::PathKeyElementTrioResultItem(path_key_element_trio) => {
                     Some((path_key_element_trio.1, path_key_element_trio.2))
                 }
+                QueryResultElement::PathKeyResultItem(_)
+                | QueryResultElement::PathKeyValueHashResultItem(_) => None,
             })
             .collect()
     }
@@ -156,6 +170,8 @@ impl QueryResultElements {
                 QueryResultElement::PathKeyElementTrioResultItem(path_key_element_trio) => {
                     Some((path_key_element_trio.1, path_key_element_trio.2))
                 }
+                QueryResultElement::PathKeyResultItem(_)
+                | QueryResultElement::PathKeyValueHashResultItem(_) => None,
             })
             .collect()
     }
@@ -170,6 +186,8 @@ impl QueryResultElements {
                 QueryResultElement::PathKeyElementTrioResultItem(path_key_element_pair) => {
                     Some(path_key_element_pair)
                 }
+                QueryResultElement::PathKeyResultItem(_)
+                | QueryResultElement::PathKeyValueHashResultItem(_) => None,
             })
             .collect()
     }
@@ -184,6 +202,37 @@ impl QueryResultElements {
                 QueryResultElement::PathKeyElementTrioResultItem((path, key, element)) => {
                     Some(((path, key), element))
                 }
+                QueryResultElement::PathKeyResultItem(_)
+                | QueryResultElement::PathKeyValueHashResultItem(_) => None,
+            })
+            .collect()
+    }
+
+    /// To path keys
+    pub fn to_path_keys(self) -> Vec<PathKey> {
+        self.elements
+            .into_iter()
+            .filter_map(|result_item| match result_item {
+                QueryResultElement::ElementResultItem(_) => None,
+                QueryResultElement::KeyElementPairResultItem(_) => None,
+                QueryResultElement::PathKeyElementTrioResultItem((path, key, _))
+                | QueryResultElement::PathKeyResultItem((path, key))
+                | QueryResultElement::PathKeyValueHashResultItem((path, key, _)) => {
+                    Some((path, key))
+                }
+            })
+            .collect()
+    }
+
+    /// To path key value hashes
+    pub fn to_path_key_value_hashes(self) -> Vec<PathKeyValueHashTrio> {
+        self.elements
+            .into_iter()
+            .filter_map(|result_item| match result_item {
+                QueryResultElement::PathKeyValueHashResultItem(path_key_value_hash_trio) => {
+                    Some(path_key_value_hash_trio)
+                }
+                _ => None,
             })
             .collect()
     }
@@ -203,6 +252,10 @@ pub enum QueryResultElement {
     KeyElementPairResultItem(KeyElementPair),
     /// Path key element trio result item
     PathKeyElementTrioResultItem(PathKeyElementTrio),
+    /// Path key result item
+    PathKeyResultItem(PathKey),
+    /// Path key value hash trio result item
+    PathKeyValueHashResultItem(PathKeyValueHashTrio),
 }
 
 #[cfg(feature = "full")]
@@ -226,27 +279,27 @@ impl QueryResultElement {
                     map_function(element)?,
                 ))
             }
+            QueryResultElement::PathKeyResultItem(_)
+            | QueryResultElement::PathKeyValueHashResultItem(_) => self,
         })
     }
 }
 
-#[cfg(any(feature = "full", feature = "verify"))]
 /// Type alias for key-element common pattern.
 pub type KeyElementPair = (Key, Element);
 
-#[cfg(any(feature = "full", feature = "verify"))]
 /// Type alias for key optional_element common pattern.
 pub type KeyOptionalElementPair = (Key, Option<Element>);
 
-#[cfg(any(feature = "full", feature = "verify"))]
 /// Type alias for path-key-element common pattern.
 pub type PathKeyElementTrio = (Path, Key, Element);
 
-#[cfg(any(feature = "full", feature = "verify"))]
+/// Type alias for path-key-value hash common pattern.
+pub type PathKeyValueHashTrio = (Path, Key, CryptoHash);
+
 /// Type alias for path - key - optional_element common pattern.
 pub type PathKeyOptionalElementTrio = (Path, Key, Option<Element>);
 
-#[cfg(any(feature = "full", feature = "verify"))]
 impl TryFrom<ProvedPathKeyValue> for PathKeyOptionalElementTrio {
     type Error = Error;
 
@@ -264,8 +317,7 @@ impl TryFrom<ProvedPathKeyValue> for PathKeyOptionalElementTrio {
 #[cfg(test)]
 mod tests {
     use crate::{
-        operations::proof::util::ProvedPathKeyValue, query_result_type::PathKeyOptionalElementTrio,
-        Element,
+        proof::util::ProvedPathKeyValue, query_result_type::PathKeyOptionalElementTrio, Element,
     };
 
     #[test]
@@ -35,13 +35,10 @@ use std::fmt;
 use grovedb_visualize::visualize_to_vec;
 #[cfg(feature = "full")]
 use integer_encoding::VarInt;
-#[cfg(any(feature = "full", feature = "verify"))]
 use serde::{Deserialize, Serialize};
 
-#[cfg(feature = "full")]
 use crate::Error;
 
-#[cfg(any(feature = "full", feature = "verify"))]
 /// Reference path variants
 #[derive(Hash, Eq, PartialEq, Serialize, Deserialize, Clone)]
 pub enum ReferencePathType {
@@ -74,6 +71,12 @@ pub enum ReferencePathType {
     /// This swaps the key with a new value, you use this to point to an element
     /// in the same tree.
     SiblingReference(Vec<u8>),
+
+    /// This discards the last n elements from the current path, descends into
+    /// the subtree under a given key and points to a given key in it. If
+    /// current path is [a, b, c, d] and we discard the last 2 elements, we
+    /// can then descend into s and point to k to get [a, b, s, k]
+    CousinWithKeyReference(u8, Vec<u8>, Vec<u8>),
 }
 
 #[cfg(feature = "full")]
@@ -103,7 +106,6 @@ pub fn path_from_reference_qualified_path_type<B: AsRef<[u8]>>(
     }
 }
 
-#[cfg(feature = "full")]
 /// Given the reference path type, the current path and the terminal key, this
 /// computes the absolute path of the item the reference is pointing to.
 pub fn path_from_reference_path_type<B: AsRef<[u8]>>(
@@ -205,6 +207,29 @@ pub fn path_from_reference_path_type<B: AsRef<[u8]>>(
             current_path_as_vec.push(sibling_key);
             Ok(current_path_as_vec)
         }
+
+        // Discard the last n elements from current path, attach subtree and key
+        ReferencePathType::CousinWithKeyReference(
+            no_of_elements_to_discard_from_end,
+            subtree_key,
+            key,
+        ) => {
+            let current_path_len = current_path.len();
+            if usize::from(no_of_elements_to_discard_from_end) > current_path_len {
+                return Err(Error::InvalidInput(
+                    "reference stored path cannot satisfy reference constraints",
+                ));
+            }
+
+            let mut subpath_as_vec = current_path
+                .iter()
+                .take(current_path_len - no_of_elements_to_discard_from_end as usize)
+                .map(|x| x.as_ref().to_vec())
+                .collect::<Vec<_>>();
+            subpath_as_vec.push(subtree_key);
+            subpath_as_vec.push(key);
+            Ok(subpath_as_vec)
+        }
     }
 }
 
@@ -238,177 +263,13 @@ impl ReferencePathType {
             | ReferencePathType::SiblingReference(path) => {
                 1 + path.len() + path.len().required_space()
             }
+            ReferencePathType::CousinWithKeyReference(_, subtree_key, key) => {
+                1 + 1
+                    + subtree_key.len()
+                    + subtree_key.len().required_space()
+                    + key.len()
+                    + key.len().required_space()
+            }
         }
     }
 }
-
-#[cfg(feature = "full")]
-#[cfg(test)]
-mod tests {
-    use grovedb_merk::proofs::Query;
-
-    use crate::{
-        reference_path::{path_from_reference_path_type, ReferencePathType},
-        tests::{make_deep_tree, TEST_LEAF},
-        Element, GroveDb, PathQuery,
-    };
-
-    #[test]
-    fn test_upstream_root_height_reference() {
-        let stored_path = vec![b"a".as_ref(), b"b".as_ref(), b"m".as_ref()];
-        // selects the first 2 elements from the stored path and appends the new path.
-        let ref1 =
-            ReferencePathType::UpstreamRootHeightReference(2, vec![b"c".to_vec(), b"d".to_vec()]);
-        let final_path = path_from_reference_path_type(ref1, &stored_path, None).unwrap();
-        assert_eq!(
-            final_path,
-            vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec(), b"d".to_vec()]
-        );
-    }
-
-    #[test]
-    fn test_upstream_from_element_height_reference() {
-        let stored_path = vec![b"a".as_ref(), b"b".as_ref(), b"m".as_ref()];
-        // discards the last element from the stored_path
-        let ref1 = ReferencePathType::UpstreamFromElementHeightReference(
-            1,
-            vec![b"c".to_vec(), b"d".to_vec()],
-        );
-        let final_path = path_from_reference_path_type(ref1, &stored_path, None).unwrap();
-        assert_eq!(
-            final_path,
-            vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec(), b"d".to_vec()]
-        );
-    }
-
-    #[test]
-    fn test_cousin_reference_no_key() {
-        let stored_path = vec![b"a".as_ref(), b"b".as_ref(), b"m".as_ref()];
-        // Replaces the immediate parent (in this case b) with the given key (c)
-        let ref1 = ReferencePathType::CousinReference(b"c".to_vec());
-        let final_path = path_from_reference_path_type(ref1, &stored_path, None);
-        assert!(final_path.is_err());
-    }
-
-    #[test]
-    fn test_cousin_reference() {
-        let stored_path = vec![b"a".as_ref(), b"b".as_ref()];
-        let key = b"m".as_ref();
-        // Replaces the immediate parent (in this case b) with the given key (c)
-        let ref1 = ReferencePathType::CousinReference(b"c".to_vec());
-        let final_path = path_from_reference_path_type(ref1, &stored_path, Some(key)).unwrap();
-        assert_eq!(
-            final_path,
-            vec![b"a".to_vec(), b"c".to_vec(), b"m".to_vec()]
-        );
-    }
-
-    #[test]
-    fn test_removed_cousin_reference_no_key() {
-        let stored_path = vec![b"a".as_ref(), b"b".as_ref(), b"m".as_ref()];
-        // Replaces the immediate parent (in this case b) with the given key (c)
-        let ref1 = ReferencePathType::RemovedCousinReference(vec![b"c".to_vec(), b"d".to_vec()]);
-        let final_path = path_from_reference_path_type(ref1, &stored_path, None);
-        assert!(final_path.is_err());
-    }
-
-    #[test]
-    fn test_removed_cousin_reference() {
-        let stored_path = vec![b"a".as_ref(), b"b".as_ref()];
-        let key = b"m".as_ref();
-        // Replaces the immediate parent (in this case b) with the given key (c)
-        let ref1 = ReferencePathType::RemovedCousinReference(vec![b"c".to_vec(), b"d".to_vec()]);
-        let final_path = path_from_reference_path_type(ref1, &stored_path, Some(key)).unwrap();
-        assert_eq!(
-            final_path,
-            vec![b"a".to_vec(), b"c".to_vec(), b"d".to_vec(), b"m".to_vec()]
-        );
-    }
-
-    #[test]
-    fn test_sibling_reference() {
-        let stored_path = vec![b"a".as_ref(), b"b".as_ref()];
-        let key = b"m".as_ref();
-        let ref1 = ReferencePathType::SiblingReference(b"c".to_vec());
-        let final_path = path_from_reference_path_type(ref1, &stored_path, Some(key)).unwrap();
-        assert_eq!(
-            final_path,
-            vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]
-        );
-    }
-
-    #[test]
-    fn test_query_many_with_different_reference_types() {
-        let db = make_deep_tree();
-
-        db.insert(
-            [TEST_LEAF, b"innertree4"].as_ref(),
-            b"ref1",
-            Element::new_reference(ReferencePathType::AbsolutePathReference(vec![
-                TEST_LEAF.to_vec(),
-                b"innertree".to_vec(),
-                b"key1".to_vec(),
-            ])),
-            None,
-            None,
-        )
-        .unwrap()
-        .expect("should insert successfully");
-
-        db.insert(
-            [TEST_LEAF, b"innertree4"].as_ref(),
-            b"ref2",
-            Element::new_reference(ReferencePathType::UpstreamRootHeightReference(
-                1,
-                vec![b"innertree".to_vec(), b"key1".to_vec()],
-            )),
-            None,
-            None,
-        )
-        .unwrap()
-        .expect("should insert successfully");
-
-        db.insert(
-            [TEST_LEAF, b"innertree4"].as_ref(),
-            b"ref3",
-            Element::new_reference(ReferencePathType::UpstreamFromElementHeightReference(
-                1,
-                vec![b"innertree".to_vec(), b"key1".to_vec()],
-            )),
-            None,
-            None,
-        )
-        .unwrap()
-        .expect("should insert successfully");
-
-        // Query all the elements in Test Leaf
-        let mut query = Query::new();
-        query.insert_all();
-        let path_query =
-            PathQuery::new_unsized(vec![TEST_LEAF.to_vec(), b"innertree4".to_vec()], query);
-        let result = db
-            .query_item_value(&path_query, true, None)
-            .unwrap()
-            .expect("should query items");
-        assert_eq!(result.0.len(), 5);
-        assert_eq!(
-            result.0,
-            vec![
-                b"value4".to_vec(),
-                b"value5".to_vec(),
-                b"value1".to_vec(),
-                b"value1".to_vec(),
-                b"value1".to_vec()
-            ]
-        );
-
-        let proof = db
-            .prove_query(&path_query)
-            .unwrap()
-            .expect("should generate proof");
-        let (hash, result) =
-            GroveDb::verify_query_raw(&proof, &path_query).expect("should verify proof");
-        assert_eq!(hash, db.root_hash(None).unwrap().unwrap());
-        assert_eq!(result.len(), 5);
-    }
-}
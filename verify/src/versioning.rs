@@ -0,0 +1,126 @@
+use std::io::Cursor;
+
+use integer_encoding::{VarInt, VarIntReader};
+
+use crate::Error;
+
+/// Version of proofs whose merk proofs are embedded as merk encodes them, a
+/// merk proof repeated in a non verbose proof being replaced by a `MerkRef`
+/// to its first occurrence. Proofs of this version start with a `03` byte
+/// instead of the `01` of legacy proofs and are rejected by verifiers that
+/// predate it, even when they hold no `MerkRef`
+pub const PROOF_VERSION: u32 = 3;
+
+/// Version of proofs whose merk proofs are embedded as merk encodes them,
+/// generated before repeated merk proofs were deduplicated
+pub const LEGACY_PROOF_VERSION: u32 = 1;
+
+/// Version of proofs whose merk proofs are compactly encoded
+pub const COMPACT_PROOF_VERSION: u32 = 2;
+
+/// Encoding of generated proofs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProofVersion {
+    /// Merk proofs are embedded as merk encodes them
+    #[default]
+    V1,
+    /// Hashes and keys repeated across the merk proofs of a proof are only
+    /// written once and lengths are varint encoded
+    V2,
+}
+
+impl From<ProofVersion> for u32 {
+    fn from(version: ProofVersion) -> Self {
+        match version {
+            ProofVersion::V1 => PROOF_VERSION,
+            ProofVersion::V2 => COMPACT_PROOF_VERSION,
+        }
+    }
+}
+
+/// Reads a version number from the given byte slice using variable-length
+/// encoding. Returns a Result containing the parsed u32 version number, or an
+/// Error if the data is corrupted and could not be read.
+pub fn read_proof_version(mut bytes: &[u8]) -> Result<u32, Error> {
+    bytes
+        .read_varint()
+        .map_err(|_| Error::CorruptedData("could not read version info".to_string()))
+}
+
+/// Reads a version number from the given byte slice using variable-length
+/// encoding, and returns the version number as well as a slice of the remaining
+/// bytes.
+pub fn read_and_consume_proof_version(bytes: &[u8]) -> Result<(u32, &[u8]), Error> {
+    let mut cursor = Cursor::new(bytes);
+    let version_number = cursor
+        .read_varint()
+        .map_err(|_| Error::CorruptedData("sdfs".to_string()))?;
+    let version_length: usize = cursor.position() as usize;
+    Ok((version_number, &bytes[version_length..]))
+}
+
+/// Reads the version of a proof like `read_and_consume_proof_version`,
+/// rejecting the versions that can't be verified
+pub fn read_and_consume_known_proof_version(bytes: &[u8]) -> Result<(u32, &[u8]), Error> {
+    let (version_number, rest) = read_and_consume_proof_version(bytes)?;
+    match version_number {
+        LEGACY_PROOF_VERSION | COMPACT_PROOF_VERSION | PROOF_VERSION => Ok((version_number, rest)),
+        _ => Err(Error::InvalidProof("unknown proof version")),
+    }
+}
+
+/// Encodes the given version number as variable-length bytes and adds it to the
+/// beginning of the given Vec<u8>, returning the modified vector.
+pub fn prepend_version_to_bytes(mut bytes: Vec<u8>, version: u32) -> Result<Vec<u8>, Error> {
+    let version_bytes = version.encode_var_vec();
+    bytes.splice(..0, version_bytes);
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        versioning::{
+            prepend_version_to_bytes, read_and_consume_known_proof_version,
+            read_and_consume_proof_version, read_proof_version, COMPACT_PROOF_VERSION,
+            LEGACY_PROOF_VERSION, PROOF_VERSION,
+        },
+        Error,
+    };
+
+    #[test]
+    fn read_correct_version() {
+        let data = vec![1, 2, 3];
+        let version = 500_u32;
+
+        // prepend the version information to the data vector
+        let new_data = prepend_version_to_bytes(data, version).unwrap();
+        assert_eq!(new_data, [244, 3, 1, 2, 3]);
+
+        // show that read_version doesn't consume
+        assert_eq!(read_proof_version(new_data.as_slice()).unwrap(), 500);
+        assert_eq!(new_data, [244, 3, 1, 2, 3]);
+
+        // show that we consume the version number and return the remaining vector
+        let (version_number, data_vec) = read_and_consume_proof_version(&new_data).unwrap();
+        assert_eq!(version_number, 500_u32);
+        assert_eq!(data_vec, [1, 2, 3]);
+    }
+
+    #[test]
+    fn reject_unknown_version() {
+        for version in [LEGACY_PROOF_VERSION, COMPACT_PROOF_VERSION, PROOF_VERSION] {
+            let data = prepend_version_to_bytes(vec![1, 2, 3], version).unwrap();
+            assert_eq!(
+                read_and_consume_known_proof_version(&data).unwrap(),
+                (version, [1, 2, 3].as_slice())
+            );
+        }
+
+        let data = prepend_version_to_bytes(vec![1, 2, 3], PROOF_VERSION + 1).unwrap();
+        assert!(matches!(
+            read_and_consume_known_proof_version(&data),
+            Err(Error::InvalidProof(_))
+        ));
+    }
+}